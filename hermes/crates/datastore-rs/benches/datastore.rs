@@ -0,0 +1,141 @@
+//! Compare reads through the datastore task with the reads of the handle
+//!
+//! Run with `cargo bench -p onlyati_datastore`. Every iteration is a batch of concurrent clients,
+//! like a dashboard that reads many keys at once, optionally beside a writer.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc::channel;
+
+use onlyati_datastore::datastore::{
+    enums::{DatabaseAction, ListType},
+    handle::DatastoreHandle,
+    storage::MemoryStorage,
+    utilities::start_datastore_handle,
+};
+
+/// Number of keys in the database
+const KEYS: usize = 1000;
+
+/// Number of concurrent clients in an iteration
+const CLIENTS: [usize; 3] = [1, 16, 64];
+
+/// Start a database and fill it with records
+async fn prepare() -> DatastoreHandle {
+    let storage = Box::new(MemoryStorage::new());
+    let (handle, _) = start_datastore_handle("root".to_string(), storage, None, None).await;
+
+    for i in 0..KEYS {
+        let (tx, mut rx) = channel(1);
+        let action = DatabaseAction::Set(tx, format!("/root/status/vps{}", i), "ok".to_string());
+        handle.send(action).await.unwrap();
+        rx.recv().await.unwrap().unwrap();
+    }
+
+    handle
+}
+
+/// Read via the datastore task, like interfaces did before the handle
+async fn get_via_task(handle: &DatastoreHandle, i: usize) {
+    let (tx, mut rx) = channel(1);
+    let action = DatabaseAction::Get(tx, format!("/root/status/vps{}", i % KEYS));
+    handle.send(action).await.unwrap();
+    rx.recv().await.unwrap().unwrap();
+}
+
+/// Read via the read lock of handle
+async fn get_via_handle(handle: &DatastoreHandle, i: usize) {
+    let key = format!("/root/status/vps{}", i % KEYS);
+    handle.get(key, None).await.unwrap();
+}
+
+/// Run concurrent clients that do the same read
+macro_rules! concurrent {
+    ($handle:expr, $clients:expr, $read:ident) => {{
+        let mut tasks = Vec::with_capacity($clients);
+        for i in 0..$clients {
+            let handle = $handle.clone();
+            tasks.push(tokio::spawn(async move {
+                $read(&handle, i).await;
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+    }};
+}
+
+fn concurrent_get(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let handle = rt.block_on(prepare());
+
+    let mut group = c.benchmark_group("concurrent_get");
+    for clients in CLIENTS {
+        group.bench_with_input(BenchmarkId::new("task", clients), &clients, |b, &n| {
+            b.to_async(&rt)
+                .iter(|| async { concurrent!(handle, n, get_via_task) });
+        });
+        group.bench_with_input(BenchmarkId::new("handle", clients), &clients, |b, &n| {
+            b.to_async(&rt)
+                .iter(|| async { concurrent!(handle, n, get_via_handle) });
+        });
+    }
+    group.finish();
+}
+
+fn get_with_writer(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let handle = rt.block_on(prepare());
+
+    // Writer changes a key in every millisecond in the background
+    let writer = handle.clone();
+    rt.spawn(async move {
+        let mut timer = tokio::time::interval(std::time::Duration::from_millis(1));
+        loop {
+            timer.tick().await;
+            let (tx, mut rx) = channel(1);
+            let action = DatabaseAction::Set(tx, "/root/counter".to_string(), "1".to_string());
+            if writer.send(action).await.is_err() {
+                break;
+            }
+            let _ = rx.recv().await;
+        }
+    });
+
+    let mut group = c.benchmark_group("get_with_writer");
+    for clients in CLIENTS {
+        group.bench_with_input(BenchmarkId::new("task", clients), &clients, |b, &n| {
+            b.to_async(&rt)
+                .iter(|| async { concurrent!(handle, n, get_via_task) });
+        });
+        group.bench_with_input(BenchmarkId::new("handle", clients), &clients, |b, &n| {
+            b.to_async(&rt)
+                .iter(|| async { concurrent!(handle, n, get_via_handle) });
+        });
+    }
+    group.finish();
+}
+
+fn list_keys(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let handle = rt.block_on(prepare());
+
+    let mut group = c.benchmark_group("list_keys");
+    group.bench_function("task", |b| {
+        b.to_async(&rt).iter(|| async {
+            let (tx, mut rx) = channel(1);
+            let action = DatabaseAction::ListKeys(tx, "/root/status".to_string(), ListType::All);
+            handle.send(action).await.unwrap();
+            rx.recv().await.unwrap().unwrap();
+        });
+    });
+    group.bench_function("handle", |b| {
+        b.to_async(&rt).iter(|| async {
+            let key = "/root/status".to_string();
+            handle.list_keys(key, ListType::All, None).await.unwrap();
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, concurrent_get, get_with_writer, list_keys);
+criterion_main!(benches);
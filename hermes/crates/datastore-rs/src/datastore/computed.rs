@@ -0,0 +1,135 @@
+//! Keys whose value is computed from the records under a source prefix
+//!
+//! Computed keys are records that are maintained by the database: when anything changes under their
+//! source, they are computed again and written like a normal record, so hooks are called and watchers
+//! get events. Clients cannot write them. They are not written into the append file, because they are
+//! computed again when they are set after the replay.
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::enums::{error::ErrorKind, pair::RecordValue};
+
+/// Function that is computed by the caller, e.g. a Lua expression
+pub trait ComputeFunction: Send + Sync {
+    /// Return with the value of computed key, `None` removes the key
+    ///
+    /// # Arguments
+    /// 1. `key` - Computed key, e.g. `/root/summary/nok_count`
+    /// 1. `records` - Records under the source prefix with their full key
+    fn compute(
+        &self,
+        key: &str,
+        records: &BTreeMap<String, RecordValue>,
+    ) -> Result<Option<String>, ErrorKind>;
+}
+
+/// How the value of computed key is made from the source records
+#[derive(Clone)]
+pub enum Function {
+    /// Number of records, or number of records with this value if it is specified
+    Count(Option<String>),
+
+    /// Sum of numeric records, it is integer if every number is integer
+    Sum,
+
+    /// Smallest numeric record, key is removed if there is not any
+    Min,
+
+    /// Largest numeric record, key is removed if there is not any
+    Max,
+
+    /// Time of the last change under the source in nanoseconds since epoch
+    LastUpdated,
+
+    /// Function of the caller
+    Custom(Arc<dyn ComputeFunction>),
+}
+
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Count(value) => write!(f, "Count({:?})", value),
+            Self::Sum => write!(f, "Sum"),
+            Self::Min => write!(f, "Min"),
+            Self::Max => write!(f, "Max"),
+            Self::LastUpdated => write!(f, "LastUpdated"),
+            Self::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+/// Key that is computed from the records under a prefix
+#[derive(Clone, Debug)]
+pub struct ComputedKey {
+    /// Record that is maintained, e.g. `/root/summary/nok_count`
+    pub key: String,
+
+    /// Records under this prefix are the input, e.g. `/root/status`
+    pub source: String,
+
+    /// How the value is computed
+    pub function: Function,
+}
+
+impl Function {
+    /// Compute the value from the records, `None` means that the key has to be removed.
+    /// `modified` is the newest modification time of the records.
+    pub(crate) fn compute(
+        &self,
+        key: &str,
+        records: &BTreeMap<String, RecordValue>,
+        modified: Option<Duration>,
+    ) -> Result<Option<RecordValue>, ErrorKind> {
+        let numbers = || records.values().filter_map(number);
+        let value = match self {
+            Self::Count(None) => Some(RecordValue::Integer(records.len() as i64)),
+            Self::Count(Some(value)) => {
+                let count = records.values().filter(|x| x.to_string() == *value).count();
+                Some(RecordValue::Integer(count as i64))
+            }
+            Self::Sum => {
+                let integers: Option<Vec<i64>> = numbers()
+                    .map(|x| match x {
+                        RecordValue::Integer(number) => Some(number),
+                        _ => None,
+                    })
+                    .collect();
+                let sum = integers.and_then(|x| x.into_iter().try_fold(0i64, i64::checked_add));
+                match sum {
+                    Some(sum) => Some(RecordValue::Integer(sum)),
+                    None => Some(RecordValue::Float(numbers().map(|x| as_float(&x)).sum())),
+                }
+            }
+            Self::Min => numbers().min_by(|a, b| as_float(a).total_cmp(&as_float(b))),
+            Self::Max => numbers().max_by(|a, b| as_float(a).total_cmp(&as_float(b))),
+            Self::LastUpdated => modified.map(|x| RecordValue::Integer(x.as_nanos() as i64)),
+            Self::Custom(function) => function.compute(key, records)?.map(RecordValue::String),
+        };
+
+        Ok(value)
+    }
+}
+
+/// Return with the numeric value of record, texts are parsed
+fn number(value: &RecordValue) -> Option<RecordValue> {
+    match value {
+        RecordValue::Integer(_) | RecordValue::Float(_) => Some(value.clone()),
+        RecordValue::String(text) => match text.trim().parse::<i64>() {
+            Ok(number) => Some(RecordValue::Integer(number)),
+            Err(_) => match text.trim().parse::<f64>() {
+                Ok(number) if number.is_finite() => Some(RecordValue::Float(number)),
+                _ => None,
+            },
+        },
+        _ => None,
+    }
+}
+
+fn as_float(value: &RecordValue) -> f64 {
+    match value {
+        RecordValue::Integer(number) => *number as f64,
+        RecordValue::Float(number) => *number,
+        _ => 0.0,
+    }
+}
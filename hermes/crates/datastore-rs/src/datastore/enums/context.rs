@@ -0,0 +1,34 @@
+//! Identity of the client that has sent a request
+
+use serde::{Deserialize, Serialize};
+
+///
+/// Who has sent the request and where it has come from, it is written into the log beside the items
+///
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct RequestContext {
+    /// Interface where the request has come, e.g. `rest` or `websocket`
+    pub interface: String,
+
+    /// Address of the client, e.g. `127.0.0.1:52044`
+    pub peer: Option<String>,
+
+    /// User that the client claims to be, Hermes does not verify it, so it must not be trusted
+    pub claimed_user: Option<String>,
+
+    /// Identifier of the request, sent by the client or generated by the interface
+    pub request_id: Option<String>,
+}
+
+impl std::fmt::Display for RequestContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "interface={} peer={} claimed_user={} request={}",
+            self.interface,
+            self.peer.as_deref().unwrap_or("-"),
+            self.claimed_user.as_deref().unwrap_or("-"),
+            self.request_id.as_deref().unwrap_or("-")
+        )
+    }
+}
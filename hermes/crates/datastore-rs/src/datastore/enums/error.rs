@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 ///
 /// Possible error types that database can return
 ///
@@ -6,9 +8,24 @@ pub enum ErrorKind {
     /// The root name in the key does not match with the root table name
     InvalidRoot(String),
 
-    /// Wrong key is specified, reason in the message
+    /// Wrong key is specified, e.g. syntax error, reason in the message
     InvalidKey(String),
 
+    /// Key, path or hook does not exist
+    NotFound(String),
+
+    /// Key exists but with other type than it is requested, e.g. record instead of table
+    TypeMismatch(String),
+
+    /// Request is in conflict with the current state, e.g. hook is already defined
+    Conflict(String),
+
+    /// Request is not allowed
+    Forbidden(String),
+
+    /// Lua script has failed
+    ScriptError(String),
+
     /// Oops, it should not happen
     InternalError(String),
 
@@ -20,6 +37,36 @@ pub enum ErrorKind {
 
     /// Replication errors
     ReplicationError(String),
+
+    /// Storage backend has failed, e.g. disk error
+    StorageError(String),
+
+    /// Write would exceed a limit of database, e.g. memory or key count
+    LimitExceeded(String),
+
+    /// Key or value is rejected by the schema of its prefix
+    ValidationError(String),
+}
+
+impl ErrorKind {
+    /// Return with the machine-readable code of error
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::InvalidRoot(_) | Self::InvalidKey(_) => ErrorCode::InvalidKey,
+            Self::NotFound(_) => ErrorCode::NotFound,
+            Self::TypeMismatch(_) => ErrorCode::TypeMismatch,
+            Self::Conflict(_) => ErrorCode::Conflict,
+            Self::Forbidden(_) => ErrorCode::Forbidden,
+            Self::ScriptError(_) => ErrorCode::ScriptError,
+            Self::LimitExceeded(_) => ErrorCode::LimitExceeded,
+            Self::ValidationError(_) => ErrorCode::ValidationFailed,
+            Self::InternalError(_)
+            | Self::InactiveHookManager
+            | Self::LogError(_)
+            | Self::ReplicationError(_)
+            | Self::StorageError(_) => ErrorCode::Internal,
+        }
+    }
 }
 
 impl std::fmt::Display for ErrorKind {
@@ -27,11 +74,72 @@ impl std::fmt::Display for ErrorKind {
         let response = match self {
             Self::InvalidKey(message) => format!("Invalid key: {message}"),
             Self::InvalidRoot(message) => format!("Invalid root: {message}"),
+            Self::NotFound(message) => format!("Not found: {message}"),
+            Self::TypeMismatch(message) => format!("Type mismatch: {message}"),
+            Self::Conflict(message) => format!("Conflict: {message}"),
+            Self::Forbidden(message) => format!("Forbidden: {message}"),
+            Self::ScriptError(message) => format!("Script error: {message}"),
             Self::InternalError(message) => format!("Internal error: {message}"),
-            Self::InactiveHookManager => "Inacvite hook manager: database is not subscried".to_string(),
+            Self::InactiveHookManager => {
+                "Inacvite hook manager: database is not subscried".to_string()
+            }
             Self::LogError(message) => format!("LogError: {}", message),
             Self::ReplicationError(message) => format!("ReplicationError: {}", message),
+            Self::StorageError(message) => format!("StorageError: {}", message),
+            Self::LimitExceeded(message) => format!("Limit exceeded: {message}"),
+            Self::ValidationError(message) => format!("Validation failed: {message}"),
         };
         write!(f, "{}", response)
     }
 }
+
+///
+/// Machine-readable error codes, interfaces send them to the clients
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// Key, path or hook does not exist
+    NotFound,
+
+    /// Key exists but with other type
+    TypeMismatch,
+
+    /// Key or any other parameter of request is invalid
+    InvalidKey,
+
+    /// Request is in conflict with the current state
+    Conflict,
+
+    /// Request is not allowed
+    Forbidden,
+
+    /// Lua script has failed
+    ScriptError,
+
+    /// Write would exceed a limit of database
+    LimitExceeded,
+
+    /// Key or value is rejected by a schema
+    ValidationFailed,
+
+    /// Something issue happened on server
+    Internal,
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::NotFound => "NOT_FOUND",
+            Self::TypeMismatch => "TYPE_MISMATCH",
+            Self::InvalidKey => "INVALID_KEY",
+            Self::Conflict => "CONFLICT",
+            Self::Forbidden => "FORBIDDEN",
+            Self::ScriptError => "SCRIPT_ERROR",
+            Self::LimitExceeded => "LIMIT_EXCEEDED",
+            Self::ValidationFailed => "VALIDATION_FAILED",
+            Self::Internal => "INTERNAL",
+        };
+        write!(f, "{}", text)
+    }
+}
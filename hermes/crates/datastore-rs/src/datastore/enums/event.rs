@@ -0,0 +1,84 @@
+//! Change events of the database
+
+///
+/// Kind of change that has happened in the database
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    /// Record has been created or updated
+    Set,
+
+    /// Record or a whole table has been removed
+    Delete,
+
+    /// Value has been pushed into a queue
+    Push,
+
+    /// Value has been popped from a queue
+    Pop,
+
+    /// Trigger has been sent without storing the value
+    Trigger,
+
+    /// Field of a hash has been created or updated, value is a JSON object with the field
+    HashSet,
+
+    /// Field has been removed from a hash, value is the field name
+    HashDelete,
+
+    /// Member has been added to a set
+    SetAdd,
+
+    /// Member has been removed from a set
+    SetRemove,
+
+    /// Key has been moved here, value is its old key
+    Move,
+
+    /// Key has been copied here, value is the key of original
+    Copy,
+}
+
+impl std::fmt::Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::Set => "set",
+            Self::Delete => "delete",
+            Self::Push => "push",
+            Self::Pop => "pop",
+            Self::Trigger => "trigger",
+            Self::HashSet => "hset",
+            Self::HashDelete => "hdel",
+            Self::SetAdd => "sadd",
+            Self::SetRemove => "srem",
+            Self::Move => "move",
+            Self::Copy => "copy",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+///
+/// Change event that is broadcasted to the watchers of database
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// Sequence number of event, it is increased by every event
+    pub id: u64,
+
+    /// Kind of the change
+    pub kind: EventKind,
+
+    /// Key that has been changed
+    pub key: String,
+
+    /// New value or the popped value, `None` in case of delete
+    pub value: Option<String>,
+}
+
+impl Event {
+    /// Tells that event belongs to the specified prefix
+    pub fn match_prefix(&self, prefix: &str) -> bool {
+        self.key.starts_with(prefix)
+    }
+}
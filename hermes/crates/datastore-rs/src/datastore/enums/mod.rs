@@ -2,13 +2,27 @@
 
 use crate::hook::types::{Link, Prefix};
 
+use super::computed::ComputedKey;
+use super::filter::FilterBinding;
+use super::limits::Limits;
+use super::schema::Schema;
 use super::types::{
-    ResultWithHook, ResultWithHooks, ResultWithList, ResultWithResult, ResultWithoutResult, Table,
+    ResultWithDelivery, ResultWithEvents, ResultWithFlag, ResultWithHash, ResultWithHistory,
+    ResultWithHook, ResultWithHooks, ResultWithImport, ResultWithItems, ResultWithLength,
+    ResultWithList, ResultWithMembers, ResultWithResult, ResultWithSnapshot, ResultWithStat,
+    ResultWithUsage, ResultWithoutResult, Table,
 };
+use context::RequestContext;
+use error::ErrorKind;
+use pair::{KeyType, RecordValue};
+use snapshot::{ImportPolicy, Snapshot};
 use tokio::sync::mpsc::Sender;
 
+pub mod context;
 pub mod error;
+pub mod event;
 pub mod pair;
+pub mod snapshot;
 
 ///
 /// Specifiy the level for listing key function
@@ -32,6 +46,92 @@ impl std::fmt::Display for ListType {
     }
 }
 
+///
+/// Specify which end of a queue is used
+///
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum QueueEnd {
+    /// First item of the queue, pop and peek use it by default
+    Front,
+
+    /// Last item of the queue, push uses it by default
+    Back,
+}
+
+impl QueueEnd {
+    /// Return with the other end of queue
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Front => Self::Back,
+            Self::Back => Self::Front,
+        }
+    }
+}
+
+impl std::fmt::Display for QueueEnd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::Front => "Front",
+            Self::Back => "Back",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl std::str::FromStr for QueueEnd {
+    type Err = ErrorKind;
+
+    /// Parse `front` or `back`, case insensitive
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "front" => Ok(Self::Front),
+            "back" => Ok(Self::Back),
+            _ => Err(ErrorKind::InvalidKey(
+                "Queue end must be 'front' or 'back'".to_string(),
+            )),
+        }
+    }
+}
+
+/// Longest time that a blocking pop waits and a reliable pop keeps its item in flight,
+/// longer timeouts are rejected with `ErrorKind::InvalidKey`
+pub const MAX_QUEUE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(u32::MAX as u64);
+
+/// Convert the seconds of a queue timeout that a client has sent, it fails above `MAX_QUEUE_TIMEOUT`
+///
+/// # Arguments
+/// 1. `seconds` - Wait of blocking pop or visibility of reliable pop
+///
+/// # Example
+/// ```
+/// use onlyati_datastore::datastore::enums::queue_timeout;
+///
+/// assert_eq!(10, queue_timeout(10).unwrap().as_secs());
+/// assert!(queue_timeout(u64::MAX).is_err());
+/// ```
+pub fn queue_timeout(seconds: u64) -> Result<std::time::Duration, ErrorKind> {
+    match seconds <= MAX_QUEUE_TIMEOUT.as_secs() {
+        true => Ok(std::time::Duration::from_secs(seconds)),
+        false => Err(ErrorKind::InvalidKey(format!(
+            "Timeout cannot be longer than {} seconds",
+            MAX_QUEUE_TIMEOUT.as_secs()
+        ))),
+    }
+}
+
+/// What happens with the expiration of a record when it is set
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Expiration {
+    /// Record is kept until it is deleted, this is what a simple set does
+    Remove,
+
+    /// Earlier expiration of the record remains
+    Keep,
+
+    /// Record is deleted after the time
+    After(std::time::Duration),
+}
+
 ///
 /// Actions for built-in server
 ///
@@ -49,6 +149,9 @@ pub enum DatabaseAction {
     /// Delete a whole table
     DeleteTable(Sender<ResultWithoutResult>, String),
 
+    /// Delete a record, queue, hash or set, only the specified kind is deleted
+    DeleteTyped(Sender<ResultWithoutResult>, KeyType),
+
     /// List keys from a route
     ListKeys(Sender<ResultWithList>, String, ListType),
 
@@ -76,8 +179,193 @@ pub enum DatabaseAction {
     /// Push to a queue
     Push(Sender<ResultWithoutResult>, String, String),
 
+    /// Push to the specified end of queue and return with the length of queue
+    PushTo(Sender<ResultWithLength>, String, String, QueueEnd),
+
     /// Pop from queue
     Pop(Sender<ResultWithResult>, String),
+
+    /// Subscribe to the change events of database
+    Watch(Sender<ResultWithEvents>),
+
+    /// Set or update a key-value pair with typed value
+    SetTyped(Sender<ResultWithoutResult>, String, RecordValue),
+
+    /// Set or update a key-value pair and change its expiration
+    SetExpiring(Sender<ResultWithoutResult>, String, String, Expiration),
+
+    /// Delete the record after the time or keep it if time is `None`, result tells that record exists
+    Expire(Sender<ResultWithFlag>, String, Option<std::time::Duration>),
+
+    /// Replace a sub-path of a JSON record
+    Patch(
+        Sender<ResultWithoutResult>,
+        String,
+        String,
+        serde_json::Value,
+    ),
+
+    /// Add a number to a counter and return with the new value
+    Increment(Sender<ResultWithResult>, String, i64),
+
+    /// Get an item of queue without removing it
+    Peek(Sender<ResultWithResult>, String, QueueEnd),
+
+    /// Get the number of items in a queue
+    QueueLength(Sender<ResultWithLength>, String),
+
+    /// Pop from the specified end of queue
+    PopFrom(Sender<ResultWithResult>, String, QueueEnd),
+
+    /// Pop at most the specified number of items from the specified end of queue
+    PopMany(Sender<ResultWithItems>, String, QueueEnd, usize),
+
+    /// Pop from queue, if it is empty then wait for an item until the timeout
+    PopWait(
+        Sender<ResultWithResult>,
+        String,
+        QueueEnd,
+        std::time::Duration,
+    ),
+
+    /// Pop from queue and keep the item in flight until it is acknowledged
+    /// or the visibility timeout expires, then it is returned to the queue
+    PopReliable(Sender<ResultWithDelivery>, String, std::time::Duration),
+
+    /// Acknowledge an item that was popped by `PopReliable`
+    Ack(Sender<ResultWithoutResult>, u64),
+
+    /// Set a field of hash: key, field and value, result tells that field was new
+    HashSet(Sender<ResultWithFlag>, String, String, String),
+
+    /// Get a field of hash: key and field
+    HashGet(Sender<ResultWithResult>, String, String),
+
+    /// Remove a field of hash: key and field
+    HashDelete(Sender<ResultWithoutResult>, String, String),
+
+    /// Get all fields of hash
+    HashGetAll(Sender<ResultWithHash>, String),
+
+    /// Add member to a set, result tells that it was new
+    SetAdd(Sender<ResultWithFlag>, String, String),
+
+    /// Remove member from a set, result tells that it was a member
+    SetRemove(Sender<ResultWithFlag>, String, String),
+
+    /// Get members of a set
+    SetMembers(Sender<ResultWithMembers>, String),
+
+    /// Check that value is member of a set
+    SetIsMember(Sender<ResultWithFlag>, String, String),
+
+    /// Export everything under a table with the hooks whose prefix begins with it
+    Export(Sender<ResultWithSnapshot>, String),
+
+    /// Import a snapshot, policy tells what happens with the existing keys and hooks
+    Import(Sender<ResultWithImport>, Snapshot, ImportPolicy),
+
+    /// Read the changes of a key from the append file
+    History(Sender<ResultWithHistory>, String),
+
+    /// Write the pending changes of storage and append file onto the disk, e.g. before shutdown
+    Flush(Sender<ResultWithoutResult>),
+
+    /// Replace the limits of database, key is the root of database, e.g. `/root`
+    SetLimits(Sender<ResultWithoutResult>, String, Limits),
+
+    /// Replace the schemas of database, key is the root of database, e.g. `/root`
+    SetSchemas(Sender<ResultWithoutResult>, String, Vec<Schema>),
+
+    /// Replace the write filters of database, key is the root of database, e.g. `/root`
+    SetFilters(Sender<ResultWithoutResult>, String, Vec<FilterBinding>),
+
+    /// Replace the computed keys of database, key is the root of database, e.g. `/root`
+    SetComputed(Sender<ResultWithoutResult>, String, Vec<ComputedKey>),
+
+    /// Replace the value indexes of database, key is the root of database, then the indexed prefixes
+    SetIndexes(Sender<ResultWithoutResult>, String, Vec<String>),
+
+    /// Find the records under a prefix with the value by the value index: prefix then value
+    Find(Sender<ResultWithList>, String, String),
+
+    /// Get the limits and current usage of database, key is the root of database
+    Usage(Sender<ResultWithUsage>, String),
+
+    /// Get the metadata of a key or a table
+    Stat(Sender<ResultWithStat>, String),
+
+    /// Move a key or a table with everything under it: source and destination key
+    Move(Sender<ResultWithoutResult>, KeyType, String),
+
+    /// Copy a key or a table with everything under it: source and destination key
+    Copy(Sender<ResultWithoutResult>, KeyType, String),
+
+    /// Do the action on behalf of a client, the context is written into the log beside its items
+    WithContext(RequestContext, Box<DatabaseAction>),
+
+    /// Do the actions one after the other without other requests between them, every action
+    /// responds on its own sender. Result tells that every action was done, actions are not rolled back.
+    Batch(Sender<ResultWithoutResult>, Vec<DatabaseAction>),
+}
+
+impl DatabaseAction {
+    /// Return with the key that the action works on, it is `None` for actions without key,
+    /// e.g. `Watch` or `Ack`. Import returns the first key of the snapshot.
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            Self::Set(_, key, _)
+            | Self::Get(_, key)
+            | Self::DeleteKey(_, key)
+            | Self::DeleteTable(_, key)
+            | Self::ListKeys(_, key, _)
+            | Self::Trigger(_, key, _)
+            | Self::HookSet(_, key, _)
+            | Self::HookGet(_, key)
+            | Self::HookRemove(_, key, _)
+            | Self::HookList(_, key)
+            | Self::Push(_, key, _)
+            | Self::PushTo(_, key, _, _)
+            | Self::Pop(_, key)
+            | Self::SetTyped(_, key, _)
+            | Self::SetExpiring(_, key, _, _)
+            | Self::Expire(_, key, _)
+            | Self::Patch(_, key, _, _)
+            | Self::Increment(_, key, _)
+            | Self::Peek(_, key, _)
+            | Self::QueueLength(_, key)
+            | Self::PopFrom(_, key, _)
+            | Self::PopMany(_, key, _, _)
+            | Self::PopWait(_, key, _, _)
+            | Self::PopReliable(_, key, _)
+            | Self::HashSet(_, key, _, _)
+            | Self::HashGet(_, key, _)
+            | Self::HashDelete(_, key, _)
+            | Self::HashGetAll(_, key)
+            | Self::SetAdd(_, key, _)
+            | Self::SetRemove(_, key, _)
+            | Self::SetMembers(_, key)
+            | Self::SetIsMember(_, key, _)
+            | Self::Export(_, key)
+            | Self::History(_, key)
+            | Self::SetLimits(_, key, _)
+            | Self::SetSchemas(_, key, _)
+            | Self::SetFilters(_, key, _)
+            | Self::SetComputed(_, key, _)
+            | Self::SetIndexes(_, key, _)
+            | Self::Find(_, key, _)
+            | Self::Usage(_, key)
+            | Self::Stat(_, key) => Some(key),
+            Self::Move(_, key, _) | Self::Copy(_, key, _) | Self::DeleteTyped(_, key) => {
+                Some(key.get_key())
+            }
+            Self::Import(_, snapshot, _) => snapshot.first_key(),
+            Self::WithContext(_, action) => action.key(),
+            Self::Batch(_, actions) => actions.iter().find_map(|x| x.key()),
+            Self::SuspendLog(_) | Self::ResumeLog(_) | Self::Watch(_) | Self::Ack(_, _) => None,
+            Self::Flush(_) => None,
+        }
+    }
 }
 
 impl std::fmt::Display for DatabaseAction {
@@ -87,6 +375,7 @@ impl std::fmt::Display for DatabaseAction {
             Self::Get(_, key) => format!("Get[{}]", key),
             Self::DeleteKey(_, key) => format!("RemKey[{}]", key),
             Self::DeleteTable(_, key) => format!("RemPath[{}]", key),
+            Self::DeleteTyped(_, key) => format!("RemTypedKey[{}]", key),
             Self::ListKeys(_, key, r#type) => format!("ListKeys[{}, {}]", key, r#type),
             Self::Trigger(_, key, value) => format!("Trigger[{}, {}]", key, value),
             Self::HookSet(_, prefix, link) => format!("HookSet[{}, {}]", prefix, link),
@@ -96,7 +385,68 @@ impl std::fmt::Display for DatabaseAction {
             Self::SuspendLog(_) => "SuspendLog".to_string(),
             Self::ResumeLog(_) => "ResumeLog".to_string(),
             Self::Push(_, key, _) => format!("Push[{}]", key),
+            Self::PushTo(_, key, _, end) => format!("PushTo[{}, {}]", key, end),
             Self::Pop(_, key) => format!("Pop[{}]", key),
+            Self::Watch(_) => "Watch".to_string(),
+            Self::SetTyped(_, key, value) => format!("SetTyped[{}, {}]", key, value.get_type()),
+            Self::SetExpiring(_, key, _, expiration) => {
+                format!("SetExpiring[{}, {:?}]", key, expiration)
+            }
+            Self::Expire(_, key, after) => format!("Expire[{}, {:?}]", key, after),
+            Self::Patch(_, key, path, _) => format!("Patch[{}, {}]", key, path),
+            Self::Increment(_, key, by) => format!("Increment[{}, {}]", key, by),
+            Self::Peek(_, key, end) => format!("Peek[{}, {}]", key, end),
+            Self::QueueLength(_, key) => format!("QueueLength[{}]", key),
+            Self::PopFrom(_, key, end) => format!("PopFrom[{}, {}]", key, end),
+            Self::PopMany(_, key, end, count) => format!("PopMany[{}, {}, {}]", key, end, count),
+            Self::PopWait(_, key, end, timeout) => {
+                format!("PopWait[{}, {}, {:?}]", key, end, timeout)
+            }
+            Self::PopReliable(_, key, visibility) => {
+                format!("PopReliable[{}, {:?}]", key, visibility)
+            }
+            Self::Ack(_, id) => format!("Ack[{}]", id),
+            Self::HashSet(_, key, field, value) => {
+                format!("HashSet[{}, {}, {}]", key, field, value)
+            }
+            Self::HashGet(_, key, field) => format!("HashGet[{}, {}]", key, field),
+            Self::HashDelete(_, key, field) => format!("HashDelete[{}, {}]", key, field),
+            Self::HashGetAll(_, key) => format!("HashGetAll[{}]", key),
+            Self::SetAdd(_, key, member) => format!("SetAdd[{}, {}]", key, member),
+            Self::SetRemove(_, key, member) => format!("SetRemove[{}, {}]", key, member),
+            Self::SetMembers(_, key) => format!("SetMembers[{}]", key),
+            Self::SetIsMember(_, key, member) => format!("SetIsMember[{}, {}]", key, member),
+            Self::Export(_, key) => format!("Export[{}]", key),
+            Self::Import(_, _, policy) => format!("Import[{}]", policy),
+            Self::History(_, key) => format!("History[{}]", key),
+            Self::Flush(_) => "Flush".to_string(),
+            Self::SetLimits(_, key, limits) => format!("SetLimits[{}, {}]", key, limits.policy),
+            Self::SetSchemas(_, key, schemas) => {
+                format!("SetSchemas[{}, {} schemas]", key, schemas.len())
+            }
+            Self::SetFilters(_, key, filters) => {
+                format!("SetFilters[{}, {} filters]", key, filters.len())
+            }
+            Self::SetComputed(_, key, computed) => {
+                format!("SetComputed[{}, {} keys]", key, computed.len())
+            }
+            Self::SetIndexes(_, key, prefixes) => {
+                format!("SetIndexes[{}, {} prefixes]", key, prefixes.len())
+            }
+            Self::Find(_, key, value) => format!("Find[{}, {}]", key, value),
+            Self::Usage(_, key) => format!("Usage[{}]", key),
+            Self::Stat(_, key) => format!("Stat[{}]", key),
+            Self::Move(_, key, destination) => format!("Move[{}, {}]", key, destination),
+            Self::Copy(_, key, destination) => format!("Copy[{}, {}]", key, destination),
+            Self::WithContext(context, action) => format!("{} ({})", action, context),
+            Self::Batch(_, actions) => format!(
+                "Batch[{}]",
+                actions
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
         };
         write!(f, "{}", text)
     }
@@ -1,10 +1,14 @@
-use std::collections::VecDeque;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::fmt::Display;
 
+use super::error::ErrorKind;
+
 ///
 /// Key type that database accept, it can be record or another table
 ///
-#[derive(Eq, PartialOrd, Ord, Debug, Clone)]
+#[derive(Eq, PartialOrd, Ord, Debug, Clone, Serialize, Deserialize)]
 pub enum KeyType {
     /// Value will be a pointer to another table
     Table(String),
@@ -14,6 +18,12 @@ pub enum KeyType {
 
     /// Value will be a queue
     Queue(String),
+
+    /// Value will be a hash of fields
+    Hash(String),
+
+    /// Value will be a set of unique members
+    Set(String),
 }
 
 impl KeyType {
@@ -32,12 +42,54 @@ impl KeyType {
         matches!(self, KeyType::Queue(_))
     }
 
+    /// Tells that key type is `KeyType::Hash`
+    pub fn is_hash(&self) -> bool {
+        matches!(self, KeyType::Hash(_))
+    }
+
+    /// Tells that key type is `KeyType::Set`
+    pub fn is_set(&self) -> bool {
+        matches!(self, KeyType::Set(_))
+    }
+
     /// Return with the record name or the table name
     pub fn get_key(&self) -> &str {
         match self {
             KeyType::Record(key) => key,
             KeyType::Table(key) => key,
             KeyType::Queue(key) => key,
+            KeyType::Hash(key) => key,
+            KeyType::Set(key) => key,
+        }
+    }
+
+    /// Return with a key of the same type but with another name
+    pub fn with_key(&self, key: String) -> Self {
+        match self {
+            KeyType::Record(_) => KeyType::Record(key),
+            KeyType::Table(_) => KeyType::Table(key),
+            KeyType::Queue(_) => KeyType::Queue(key),
+            KeyType::Hash(_) => KeyType::Hash(key),
+            KeyType::Set(_) => KeyType::Set(key),
+        }
+    }
+
+    /// Create key from the name of its kind: `record`, `queue`, `hash`, `set` or `table`
+    ///
+    /// # Arguments
+    /// 1. `kind` - Name of kind, `path` is accepted for tables too
+    /// 1. `key` - Full key, e.g. `/root/status`
+    pub fn from_kind(kind: &str, key: String) -> Result<Self, ErrorKind> {
+        match kind {
+            "record" => Ok(KeyType::Record(key)),
+            "queue" => Ok(KeyType::Queue(key)),
+            "hash" => Ok(KeyType::Hash(key)),
+            "set" => Ok(KeyType::Set(key)),
+            "table" | "path" => Ok(KeyType::Table(key)),
+            _ => Err(ErrorKind::InvalidKey(format!(
+                "Kind must be record, queue, hash, set or table but not {}",
+                kind
+            ))),
         }
     }
 
@@ -47,6 +99,8 @@ impl KeyType {
             KeyType::Record(_) => "r",
             KeyType::Table(_) => "t",
             KeyType::Queue(_) => "q",
+            KeyType::Hash(_) => "h",
+            KeyType::Set(_) => "s",
         }
     }
 }
@@ -57,6 +111,8 @@ impl Display for KeyType {
             Self::Table(key) => ("t", key),
             Self::Record(key) => ("r", key),
             Self::Queue(key) => ("q", key),
+            Self::Hash(key) => ("h", key),
+            Self::Set(key) => ("s", key),
         };
         write!(f, "{} {}", message.0, message.1)
     }
@@ -67,6 +123,8 @@ impl PartialEq for KeyType {
         if (self.is_record() && other.is_record())
             || (self.is_table() && other.is_table())
             || (self.is_queue() && other.is_queue()) && self.get_key() == other.get_key()
+            || (self.is_hash() && other.is_hash()) && self.get_key() == other.get_key()
+            || (self.is_set() && other.is_set()) && self.get_key() == other.get_key()
         {
             return true;
         }
@@ -83,10 +141,16 @@ pub enum ValueType {
     TablePointer(super::Table),
 
     /// This is a record pointer, belongs to `KeyType::Record`
-    RecordPointer(String),
+    RecordPointer(RecordValue),
 
     /// This is a queue pointer, belongs to `KeyType::Queue`
     QueuePointer(VecDeque<String>),
+
+    /// This is a hash pointer, belongs to `KeyType::Hash`
+    HashPointer(BTreeMap<String, String>),
+
+    /// This is a set pointer, belongs to `KeyType::Set`
+    SetPointer(BTreeSet<String>),
 }
 
 impl ValueType {
@@ -105,11 +169,287 @@ impl ValueType {
         matches!(self, ValueType::QueuePointer(_))
     }
 
-    pub fn get_value(&self) -> &str {
+    /// Tells that it is a `ValueType::HashPointer`
+    pub fn is_hash(&self) -> bool {
+        matches!(self, ValueType::HashPointer(_))
+    }
+
+    /// Tells that it is a `ValueType::SetPointer`
+    pub fn is_set(&self) -> bool {
+        matches!(self, ValueType::SetPointer(_))
+    }
+
+    pub fn get_value(&self) -> String {
+        match self {
+            Self::TablePointer(_) => "TablePointer".to_string(),
+            Self::RecordPointer(value) => value.to_string(),
+            Self::QueuePointer(_) => "QueuePointer".to_string(),
+            Self::HashPointer(_) => "HashPointer".to_string(),
+            Self::SetPointer(_) => "SetPointer".to_string(),
+        }
+    }
+}
+
+///
+/// Value of a record, its type is kept as it was stored
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RecordValue {
+    /// Plain text
+    String(String),
+
+    /// Signed 64-bit integer
+    Integer(i64),
+
+    /// 64-bit floating point number
+    Float(f64),
+
+    /// True or false
+    Bool(bool),
+
+    /// JSON document, it is encoded as text because bincode cannot handle `serde_json::Value`
+    Json(#[serde(with = "json_as_text")] serde_json::Value),
+
+    /// Binary data, it is displayed as base64
+    Bytes(Vec<u8>),
+}
+
+impl RecordValue {
+    /// Name of the type: `string`, `integer`, `float`, `bool`, `json` or `bytes`
+    pub fn get_type(&self) -> &str {
+        match self {
+            Self::String(_) => "string",
+            Self::Integer(_) => "integer",
+            Self::Float(_) => "float",
+            Self::Bool(_) => "bool",
+            Self::Json(_) => "json",
+            Self::Bytes(_) => "bytes",
+        }
+    }
+
+    /// Parse value from text according to the type name, see `get_type` for the names.
+    /// Bytes must be base64 encoded.
+    pub fn parse(r#type: &str, text: &str) -> Result<Self, ErrorKind> {
+        let invalid = || ErrorKind::InvalidKey(format!("Value is not a valid {}", r#type));
+
+        match r#type {
+            "string" => Ok(Self::String(text.to_string())),
+            "integer" => text
+                .trim()
+                .parse()
+                .map(Self::Integer)
+                .map_err(|_| invalid()),
+            "float" => text.trim().parse().map(Self::Float).map_err(|_| invalid()),
+            "bool" => text.trim().parse().map(Self::Bool).map_err(|_| invalid()),
+            "json" => serde_json::from_str(text)
+                .map(Self::Json)
+                .map_err(|_| invalid()),
+            "bytes" => base64::engine::general_purpose::STANDARD
+                .decode(text.trim())
+                .map(Self::Bytes)
+                .map_err(|_| invalid()),
+            other => Err(ErrorKind::InvalidKey(format!(
+                "Unknown value type: {}",
+                other
+            ))),
+        }
+    }
+
+    /// Convert a JSON value into record: strings, numbers and booleans keep their type,
+    /// everything else is stored as JSON document
+    pub fn from_json(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::String(text) => Self::String(text),
+            serde_json::Value::Bool(flag) => Self::Bool(flag),
+            serde_json::Value::Number(number) => match number.as_i64() {
+                Some(integer) => Self::Integer(integer),
+                None => Self::Float(number.as_f64().unwrap_or_default()),
+            },
+            other => Self::Json(other),
+        }
+    }
+
+    /// Convert record into native JSON value, bytes are base64 encoded string
+    pub fn to_json(&self) -> serde_json::Value {
         match self {
-            Self::TablePointer(_) => "TablePointer",
-            Self::RecordPointer(key) => &key[..],
-            Self::QueuePointer(_) => "QueuePointer",
+            Self::String(text) => serde_json::Value::String(text.clone()),
+            Self::Integer(integer) => serde_json::Value::from(*integer),
+            Self::Float(float) => serde_json::Value::from(*float),
+            Self::Bool(flag) => serde_json::Value::Bool(*flag),
+            Self::Json(value) => value.clone(),
+            Self::Bytes(_) => serde_json::Value::String(self.to_string()),
         }
     }
+
+    /// Read a sub-path of JSON document, e.g. `$.status.cpu` or `$.disks[0].name`
+    pub fn get_path(&self, path: &str) -> Result<serde_json::Value, ErrorKind> {
+        let mut current = match self {
+            Self::Json(value) => value,
+            _ => {
+                return Err(ErrorKind::TypeMismatch(
+                    "Value is not a JSON document".to_string(),
+                ))
+            }
+        };
+
+        for step in parse_json_path(path)? {
+            let next = match &step {
+                JsonStep::Field(name) => current.get(name),
+                JsonStep::Index(index) => current.get(index),
+            };
+            current = match next {
+                Some(value) => value,
+                None => {
+                    return Err(ErrorKind::NotFound(format!(
+                        "Path '{}' does not exist",
+                        path
+                    )))
+                }
+            };
+        }
+
+        Ok(current.clone())
+    }
+
+    /// Replace a sub-path of JSON document with a new value. Missing fields of objects
+    /// are created, but array indexes must exist.
+    pub fn patch_path(
+        &mut self,
+        path: &str,
+        new_value: serde_json::Value,
+    ) -> Result<(), ErrorKind> {
+        let mut current = match self {
+            Self::Json(value) => value,
+            _ => {
+                return Err(ErrorKind::TypeMismatch(
+                    "Value is not a JSON document".to_string(),
+                ))
+            }
+        };
+
+        for step in parse_json_path(path)? {
+            current = match (&step, current) {
+                (JsonStep::Field(name), serde_json::Value::Object(map)) => {
+                    map.entry(name.clone()).or_insert(serde_json::Value::Null)
+                }
+                (JsonStep::Index(index), serde_json::Value::Array(array)) => {
+                    match array.get_mut(*index) {
+                        Some(value) => value,
+                        None => {
+                            return Err(ErrorKind::NotFound(format!(
+                                "Path '{}' does not exist",
+                                path
+                            )))
+                        }
+                    }
+                }
+                (JsonStep::Field(_), value @ serde_json::Value::Null) => {
+                    *value = serde_json::Value::Object(serde_json::Map::new());
+                    match (&step, value) {
+                        (JsonStep::Field(name), serde_json::Value::Object(map)) => {
+                            map.entry(name.clone()).or_insert(serde_json::Value::Null)
+                        }
+                        _ => unreachable!(),
+                    }
+                }
+                _ => {
+                    return Err(ErrorKind::TypeMismatch(format!(
+                        "Path '{}' goes through a scalar value",
+                        path
+                    )))
+                }
+            };
+        }
+
+        *current = new_value;
+        Ok(())
+    }
+}
+
+impl Display for RecordValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::String(text) => write!(f, "{}", text),
+            Self::Integer(integer) => write!(f, "{}", integer),
+            Self::Float(float) => write!(f, "{}", float),
+            Self::Bool(flag) => write!(f, "{}", flag),
+            Self::Json(value) => write!(f, "{}", value),
+            Self::Bytes(bytes) => write!(
+                f,
+                "{}",
+                base64::engine::general_purpose::STANDARD.encode(bytes)
+            ),
+        }
+    }
+}
+
+impl From<String> for RecordValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for RecordValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+/// One step of a JSON path
+enum JsonStep {
+    /// Field of an object
+    Field(String),
+
+    /// Item of an array
+    Index(usize),
+}
+
+/// Parse JSON path like `$.status.cpu` or `$.disks[0].name`, leading `$` is optional
+fn parse_json_path(path: &str) -> Result<Vec<JsonStep>, ErrorKind> {
+    let invalid = || ErrorKind::InvalidKey(format!("Invalid JSON path: {}", path));
+    let path = path.strip_prefix('$').unwrap_or(path);
+    let mut steps = Vec::new();
+
+    for part in path.split('.').filter(|x| !x.is_empty()) {
+        let (name, mut rest) = match part.find('[') {
+            Some(index) => (&part[..index], &part[index..]),
+            None => (part, ""),
+        };
+
+        if !name.is_empty() {
+            steps.push(JsonStep::Field(name.to_string()));
+        }
+
+        while let Some(stripped) = rest.strip_prefix('[') {
+            let end = stripped.find(']').ok_or_else(invalid)?;
+            let index = stripped[..end].parse().map_err(|_| invalid())?;
+            steps.push(JsonStep::Index(index));
+            rest = &stripped[end + 1..];
+        }
+
+        if !rest.is_empty() {
+            return Err(invalid());
+        }
+    }
+
+    Ok(steps)
+}
+
+/// Serialize JSON document as text, so it can be used by non self-describing formats like bincode
+mod json_as_text {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &serde_json::Value,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<serde_json::Value, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        serde_json::from_str(&text).map_err(serde::de::Error::custom)
+    }
 }
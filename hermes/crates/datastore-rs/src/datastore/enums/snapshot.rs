@@ -0,0 +1,235 @@
+//! Export and import of the database content
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::error::ErrorKind;
+
+///
+/// Content of the database or a subtree of it. Its TOML form is compatible with the initial file.
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Records with their type
+    #[serde(default)]
+    pub record: Vec<SnapshotRecord>,
+
+    /// Queues with their items from front to back
+    #[serde(default)]
+    pub queue: Vec<SnapshotQueue>,
+
+    /// Hashes with their fields
+    #[serde(default)]
+    pub hash: Vec<SnapshotHash>,
+
+    /// Sets with their members
+    #[serde(default)]
+    pub set: Vec<SnapshotSet>,
+
+    /// Hooks with their links
+    #[serde(default)]
+    pub hook: Vec<SnapshotHook>,
+}
+
+/// Record in a snapshot, value is in text form like at `RecordValue::parse`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotRecord {
+    pub key: String,
+    pub value: String,
+    #[serde(rename = "type", default = "default_record_type")]
+    pub r#type: String,
+}
+
+/// Queue in a snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotQueue {
+    pub key: String,
+    pub values: Vec<String>,
+}
+
+/// Hash in a snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotHash {
+    pub key: String,
+    pub fields: BTreeMap<String, String>,
+}
+
+/// Set in a snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotSet {
+    pub key: String,
+    pub members: Vec<String>,
+}
+
+/// Hook in a snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotHook {
+    pub prefix: String,
+    pub links: Vec<String>,
+}
+
+/// Records of initial files do not have type
+fn default_record_type() -> String {
+    "string".to_string()
+}
+
+impl Snapshot {
+    /// Tells that snapshot does not contain anything
+    pub fn is_empty(&self) -> bool {
+        self.record.is_empty()
+            && self.queue.is_empty()
+            && self.hash.is_empty()
+            && self.set.is_empty()
+            && self.hook.is_empty()
+    }
+
+    /// Return with the first key of snapshot, or `None` if it is empty
+    pub fn first_key(&self) -> Option<&str> {
+        self.record
+            .first()
+            .map(|x| x.key.as_str())
+            .or_else(|| self.queue.first().map(|x| x.key.as_str()))
+            .or_else(|| self.hash.first().map(|x| x.key.as_str()))
+            .or_else(|| self.set.first().map(|x| x.key.as_str()))
+            .or_else(|| self.hook.first().map(|x| x.prefix.as_str()))
+    }
+
+    /// Encode snapshot in the specified format
+    pub fn encode(&self, format: SnapshotFormat) -> Result<Vec<u8>, ErrorKind> {
+        let failed = |e: String| ErrorKind::InternalError(format!("Failed to encode: {}", e));
+
+        match format {
+            SnapshotFormat::Json => {
+                serde_json::to_vec_pretty(self).map_err(|e| failed(e.to_string()))
+            }
+            SnapshotFormat::Toml => toml::to_string(self)
+                .map(String::into_bytes)
+                .map_err(|e| failed(e.to_string())),
+            SnapshotFormat::Binary => bincode::serialize(self).map_err(|e| failed(e.to_string())),
+        }
+    }
+
+    /// Decode snapshot from the specified format
+    pub fn decode(data: &[u8], format: SnapshotFormat) -> Result<Self, ErrorKind> {
+        let invalid =
+            |e: String| ErrorKind::InvalidKey(format!("Invalid {} snapshot: {}", format, e));
+
+        match format {
+            SnapshotFormat::Json => {
+                serde_json::from_slice(data).map_err(|e| invalid(e.to_string()))
+            }
+            SnapshotFormat::Toml => {
+                let text = std::str::from_utf8(data).map_err(|e| invalid(e.to_string()))?;
+                toml::from_str(text).map_err(|e| invalid(e.to_string()))
+            }
+            SnapshotFormat::Binary => {
+                bincode::deserialize(data).map_err(|e| invalid(e.to_string()))
+            }
+        }
+    }
+}
+
+///
+/// Encoding of an exported snapshot
+///
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SnapshotFormat {
+    /// Human readable JSON document
+    Json,
+
+    /// TOML document, it can be used as initial file
+    Toml,
+
+    /// Compact bincode encoding
+    Binary,
+}
+
+impl SnapshotFormat {
+    /// MIME type of the format
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Toml => "application/toml",
+            Self::Binary => "application/octet-stream",
+        }
+    }
+}
+
+impl std::fmt::Display for SnapshotFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::Json => "json",
+            Self::Toml => "toml",
+            Self::Binary => "binary",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl std::str::FromStr for SnapshotFormat {
+    type Err = ErrorKind;
+
+    /// Parse `json`, `toml` or `binary`, case insensitive
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "toml" => Ok(Self::Toml),
+            "binary" => Ok(Self::Binary),
+            _ => Err(ErrorKind::InvalidKey(
+                "Format must be 'json', 'toml' or 'binary'".to_string(),
+            )),
+        }
+    }
+}
+
+///
+/// What happens with keys and hooks that already exist during import
+///
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum ImportPolicy {
+    /// Records are updated, items are appended to queues, fields and members and links are added
+    Merge,
+
+    /// Existing records, queues, hashes, sets and links of hooks are replaced
+    Overwrite,
+
+    /// Existing records, queues, hashes, sets and hooks remain as they are
+    Skip,
+}
+
+impl std::fmt::Display for ImportPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::Merge => "merge",
+            Self::Overwrite => "overwrite",
+            Self::Skip => "skip",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl std::str::FromStr for ImportPolicy {
+    type Err = ErrorKind;
+
+    /// Parse `merge`, `overwrite` or `skip`, case insensitive
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "merge" => Ok(Self::Merge),
+            "overwrite" => Ok(Self::Overwrite),
+            "skip" => Ok(Self::Skip),
+            _ => Err(ErrorKind::InvalidKey(
+                "Policy must be 'merge', 'overwrite' or 'skip'".to_string(),
+            )),
+        }
+    }
+}
+
+/// Result of an import
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportSummary {
+    /// Number of records, queues, hashes, sets and hooks that have been written
+    pub imported: usize,
+
+    /// Number of records, queues, hashes, sets and hooks that have been left as they were
+    pub skipped: usize,
+}
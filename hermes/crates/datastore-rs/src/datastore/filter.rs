@@ -0,0 +1,75 @@
+//! Filters that can change, redirect or reject the writes before they are committed
+//!
+//! Filters are bound to prefixes, every filter whose prefix contains the key is called in the order
+//! of binding for each write of a request: set, patch and increment of records, push, hash fields, set
+//! members and the content of imported snapshots. Each filter gets the key and value that the previous
+//! one returned. Moved and copied values are checked at their new key too, but there filters can only
+//! accept or reject, because the append file logs the move and not the values.
+//!
+//! Filters run only for requests, so values that are replayed from the append file are not filtered
+//! again: the log has the key and value that were written.
+use std::sync::Arc;
+
+use super::enums::error::ErrorKind;
+
+/// Kind of write that is filtered
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WriteKind {
+    /// Record is set
+    Set,
+
+    /// Item is pushed into a queue
+    Push,
+
+    /// Field of a hash is set, value is the value of field
+    HashSet,
+
+    /// Member is added to a set
+    SetAdd,
+}
+
+impl std::fmt::Display for WriteKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::Set => "set",
+            Self::Push => "push",
+            Self::HashSet => "hash_set",
+            Self::SetAdd => "set_add",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Filter that is called before a write under its prefix
+pub trait WriteFilter: Send + Sync {
+    /// Return with the key and value that have to be written, or with error if the write is rejected
+    ///
+    /// # Arguments
+    /// 1. `kind` - What is written, patch and increment are set of the new value
+    /// 1. `key` - Full key of write, e.g. `/root/status/vps01`
+    /// 1. `value` - Value in text format
+    /// 1. `old` - Current value of record, it is `None` for new records and for the other kinds
+    fn filter(
+        &self,
+        kind: WriteKind,
+        key: &str,
+        value: &str,
+        old: Option<&str>,
+    ) -> Result<(String, String), ErrorKind>;
+}
+
+/// Filter that is bound to a prefix
+#[derive(Clone)]
+pub struct FilterBinding {
+    /// Writes under this prefix are filtered, e.g. `/root/status`
+    pub prefix: String,
+
+    /// Filter that is called
+    pub filter: Arc<dyn WriteFilter>,
+}
+
+impl std::fmt::Debug for FilterBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FilterBinding({})", self.prefix)
+    }
+}
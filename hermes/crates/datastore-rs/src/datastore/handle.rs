@@ -0,0 +1,254 @@
+//! Shared handle of a running database
+//!
+//! Database is protected by a read/write lock. Actions that change the database are sent to the task
+//! of `start_datastore`, so they are executed in the order of their arrival, holding the write lock.
+//! Reads are served by the handle right away with the read lock, so they can run concurrently.
+//! `get` and `list_keys` do not need a reply channel, while `Peek`, `QueueLength`, `HashGet`,
+//! `HashGetAll`, `SetMembers`, `SetIsMember`, `Find` and `Stat` actions that are sent by `send`
+//! are answered by the handle too. Lock is released before the read is written into the log.
+use std::sync::Arc;
+use tokio::sync::{
+    broadcast,
+    mpsc::{error::SendError, Sender},
+    RwLock,
+};
+
+use super::{
+    enums::{
+        context::RequestContext, event::Event, pair::KeyType, pair::ValueType, DatabaseAction,
+        ListType,
+    },
+    types::{ResultWithList, ResultWithResult},
+    Database,
+};
+use crate::logger::enums::{LogItem, LoggerAction};
+
+/// Handle of a running database, it is cheap to clone
+#[derive(Clone)]
+pub struct DatastoreHandle {
+    /// Database that is shared with the datastore task
+    database: Arc<RwLock<Database>>,
+
+    /// Sender of the datastore task
+    sender: Sender<DatabaseAction>,
+
+    /// Sender of the change events of database
+    events: broadcast::Sender<Event>,
+}
+
+impl DatastoreHandle {
+    /// Create new handle
+    ///
+    /// # Arguments
+    /// 1. `database` - Database that is shared with the datastore task
+    /// 1. `sender` - Sender of the datastore task
+    /// 1. `events` - Sender of the change events of database
+    pub(crate) fn new(
+        database: Arc<RwLock<Database>>,
+        sender: Sender<DatabaseAction>,
+        events: broadcast::Sender<Event>,
+    ) -> Self {
+        Self {
+            database,
+            sender,
+            events,
+        }
+    }
+
+    /// Send an action to the datastore task, actions are executed in the order of their arrival.
+    /// Reads that do not change the database are answered right away with the read lock.
+    ///
+    /// # Arguments
+    /// 1. `action` - Action that is executed
+    pub async fn send(&self, action: DatabaseAction) -> Result<(), SendError<DatabaseAction>> {
+        match self.serve_read(action).await {
+            Some(action) => self.sender.send(action).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Return with a clone of the sender of the datastore task
+    pub fn sender(&self) -> Sender<DatabaseAction> {
+        self.sender.clone()
+    }
+
+    /// Subscribe to the change events of database, it is the same as `DatabaseAction::Watch`
+    /// but it does not wait for the datastore task
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    /// Read a record, it is the same as `DatabaseAction::Get` without the datastore task
+    ///
+    /// # Arguments
+    /// 1. `key` - Key of the record
+    /// 1. `context` - Client of the request, it is written into the log
+    ///
+    /// # Example
+    /// ```
+    /// use onlyati_datastore::datastore::{
+    ///     enums::{pair::RecordValue, pair::ValueType, DatabaseAction},
+    ///     storage::MemoryStorage,
+    ///     utilities::start_datastore_handle,
+    /// };
+    /// use tokio::sync::mpsc::channel;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let storage = Box::new(MemoryStorage::new());
+    /// let (handle, _) = start_datastore_handle("root".to_string(), storage, None, None).await;
+    ///
+    /// let (tx, mut rx) = channel(10);
+    /// let action = DatabaseAction::Set(tx, "/root/network".to_string(), "ok".to_string());
+    /// handle.send(action).await.expect("Failed to send the request");
+    /// rx.recv().await.unwrap().expect("Failed to set");
+    ///
+    /// let value = handle.get("/root/network".to_string(), None).await.expect("Failed to get");
+    /// assert_eq!(ValueType::RecordPointer(RecordValue::from("ok")), value);
+    /// # })
+    /// ```
+    pub async fn get(&self, key: String, context: Option<RequestContext>) -> ResultWithResult {
+        let (result, logger) = self.read(|db| db.get(KeyType::Record(key.clone()))).await;
+        log(logger, context, LogItem::GetKey(now(), key)).await;
+
+        result
+    }
+
+    /// List keys under a table, it is the same as `DatabaseAction::ListKeys` without the datastore task
+    ///
+    /// # Arguments
+    /// 1. `key` - Table where listing starts
+    /// 1. `level` - List only the direct keys or everything under it
+    /// 1. `context` - Client of the request, it is written into the log
+    pub async fn list_keys(
+        &self,
+        key: String,
+        level: ListType,
+        context: Option<RequestContext>,
+    ) -> ResultWithList {
+        let (result, logger) = self
+            .read(|db| db.list_keys(KeyType::Record(key.clone()), level))
+            .await;
+        log(logger, context, LogItem::ListKeys(now(), key)).await;
+
+        result
+    }
+
+    /// Read the database with the read lock, lock is released before the return, so the read can be
+    /// logged without blocking the datastore task. Logger sender is returned with the result.
+    async fn read<T>(
+        &self,
+        read: impl FnOnce(&Database) -> T,
+    ) -> (T, Option<Sender<LoggerAction>>) {
+        let db = self.database.read().await;
+        (read(&db), db.logger_sender.clone())
+    }
+
+    /// Answer the action if it only reads the database, else return with it, so it can be sent to
+    /// the datastore task. Reads are logged like they are logged by the datastore task.
+    async fn serve_read(&self, action: DatabaseAction) -> Option<DatabaseAction> {
+        let (context, action) = match action {
+            DatabaseAction::WithContext(context, action) => (Some(context), *action),
+            action => (None, action),
+        };
+
+        match action {
+            DatabaseAction::Peek(sender, key, end) => {
+                let (result, logger) = self
+                    .read(|db| {
+                        db.peek(KeyType::Record(key.clone()), end)
+                            .map(|value| ValueType::RecordPointer(value.into()))
+                    })
+                    .await;
+                reply(&sender, result).await;
+                log(logger, context, LogItem::Peek(now(), key)).await;
+            }
+            DatabaseAction::QueueLength(sender, key) => {
+                let (result, logger) = self
+                    .read(|db| db.queue_length(KeyType::Record(key.clone())))
+                    .await;
+                reply(&sender, result).await;
+                log(logger, context, LogItem::QueueLength(now(), key)).await;
+            }
+            DatabaseAction::HashGet(sender, key, field) => {
+                let (result, logger) = self
+                    .read(|db| {
+                        db.hash_get(KeyType::Record(key.clone()), &field)
+                            .map(|value| ValueType::RecordPointer(value.into()))
+                    })
+                    .await;
+                reply(&sender, result).await;
+                log(logger, context, LogItem::HashGet(now(), key, field)).await;
+            }
+            DatabaseAction::HashGetAll(sender, key) => {
+                let (result, logger) = self
+                    .read(|db| db.hash_get_all(KeyType::Record(key.clone())))
+                    .await;
+                reply(&sender, result).await;
+                log(logger, context, LogItem::HashGetAll(now(), key)).await;
+            }
+            DatabaseAction::SetMembers(sender, key) => {
+                let (result, logger) = self
+                    .read(|db| db.set_members(KeyType::Record(key.clone())))
+                    .await;
+                reply(&sender, result).await;
+                log(logger, context, LogItem::SetMembers(now(), key)).await;
+            }
+            DatabaseAction::SetIsMember(sender, key, member) => {
+                let (result, logger) = self
+                    .read(|db| db.set_is_member(KeyType::Record(key.clone()), &member))
+                    .await;
+                reply(&sender, result).await;
+                log(logger, context, LogItem::SetIsMember(now(), key, member)).await;
+            }
+            DatabaseAction::Find(sender, key, value) => {
+                let (result, _) = self
+                    .read(|db| {
+                        db.find(&key, &value)
+                            .map(|keys| keys.into_iter().map(KeyType::Record).collect())
+                    })
+                    .await;
+                reply(&sender, result).await;
+            }
+            DatabaseAction::Stat(sender, key) => {
+                let (result, _) = self.read(|db| db.stat(&key)).await;
+                reply(&sender, result).await;
+            }
+            action => {
+                return Some(match context {
+                    Some(context) => DatabaseAction::WithContext(context, Box::new(action)),
+                    None => action,
+                })
+            }
+        }
+
+        None
+    }
+}
+
+/// Time since UNIX epoch, it is zero if the system clock is before it
+fn now() -> std::time::Duration {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Send the result of a read to the requester
+async fn reply<T>(sender: &Sender<T>, result: T) {
+    sender
+        .send(result)
+        .await
+        .unwrap_or_else(|e| tracing::error!("Error during send: {}", e));
+}
+
+/// Send a read into the log if logger is active, logger writes only the human log from it
+async fn log(logger: Option<Sender<LoggerAction>>, context: Option<RequestContext>, item: LogItem) {
+    if let Some(sender) = logger {
+        sender
+            .send(LoggerAction::WriteAsync(LogItem::with_context(
+                &context,
+                vec![item],
+            )))
+            .await
+            .unwrap_or_else(|e| tracing::error!("{}", e));
+    }
+}
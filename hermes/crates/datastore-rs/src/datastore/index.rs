@@ -0,0 +1,110 @@
+//! Indexes of record values, they find the records with a value under a prefix without walking the tree
+//!
+//! Indexes are declared for prefixes, every record under the prefix is indexed by its value in text
+//! format. Queues, hashes and sets are not indexed.
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::stat::is_under;
+
+/// Index of records under a prefix
+struct PrefixIndex {
+    /// Records under this prefix are indexed, e.g. `/root/status`
+    prefix: String,
+
+    /// Keys of records by their value
+    keys: BTreeMap<String, BTreeSet<String>>,
+
+    /// Value of records by their key, it is needed to remove the key from `keys`
+    values: BTreeMap<String, String>,
+}
+
+/// Value indexes of a database
+#[derive(Default)]
+pub(crate) struct ValueIndex {
+    indexes: Vec<PrefixIndex>,
+}
+
+impl ValueIndex {
+    /// Create empty indexes for the prefixes, the existing records have to be inserted
+    pub fn new(prefixes: Vec<String>) -> Self {
+        Self {
+            indexes: prefixes
+                .into_iter()
+                .map(|prefix| PrefixIndex {
+                    prefix,
+                    keys: BTreeMap::new(),
+                    values: BTreeMap::new(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Return with the indexed prefixes
+    pub fn prefixes(&self) -> Vec<String> {
+        self.indexes.iter().map(|x| x.prefix.clone()).collect()
+    }
+
+    /// Index the new value of a record, its old value is removed
+    pub fn insert(&mut self, key: &str, value: &str) {
+        for index in self.indexes.iter_mut().filter(|x| is_under(key, &x.prefix)) {
+            index.remove(key);
+            index
+                .keys
+                .entry(value.to_string())
+                .or_default()
+                .insert(key.to_string());
+            index.values.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    /// Remove a record from the indexes
+    pub fn remove(&mut self, key: &str) {
+        for index in self.indexes.iter_mut().filter(|x| is_under(key, &x.prefix)) {
+            index.remove(key);
+        }
+    }
+
+    /// Remove every record under a removed table from the indexes
+    pub fn remove_table(&mut self, prefix: &str) {
+        for index in self.indexes.iter_mut() {
+            let removed: Vec<String> = index
+                .values
+                .keys()
+                .filter(|x| is_under(x, prefix))
+                .cloned()
+                .collect();
+            for key in removed {
+                index.remove(&key);
+            }
+        }
+    }
+
+    /// Return with the keys under the prefix whose value is the specified one,
+    /// or with `None` if no index contains the prefix
+    pub fn find(&self, prefix: &str, value: &str) -> Option<Vec<String>> {
+        let index = self.indexes.iter().find(|x| is_under(prefix, &x.prefix))?;
+        let keys = match index.keys.get(value) {
+            Some(keys) => keys
+                .iter()
+                .filter(|x| is_under(x, prefix))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Some(keys)
+    }
+}
+
+impl PrefixIndex {
+    fn remove(&mut self, key: &str) {
+        if let Some(value) = self.values.remove(key) {
+            if let Some(keys) = self.keys.get_mut(&value) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.keys.remove(&value);
+                }
+            }
+        }
+    }
+}
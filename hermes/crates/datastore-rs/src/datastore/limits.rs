@@ -0,0 +1,121 @@
+//! Limits of database and the usage that they are checked against
+//!
+//! Usage is an estimate: every key counts with the length of its name and its content, e.g. the text
+//! of a record, the items of a queue or the fields and values of a hash. Tables are not counted.
+//! When a write would exceed a limit, records under the designated prefixes are evicted according to
+//! the policy, or the write is rejected with `ErrorKind::LimitExceeded`.
+use serde::{Deserialize, Serialize};
+
+use super::stat::is_under;
+
+/// What happens when a write would exceed a limit
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EvictionPolicy {
+    /// Write is rejected
+    #[default]
+    Reject,
+
+    /// Least recently read or written records are evicted
+    Lru,
+
+    /// Least frequently read or written records are evicted
+    Lfu,
+
+    /// Records that were created first are evicted
+    Oldest,
+}
+
+impl std::fmt::Display for EvictionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::Reject => "reject",
+            Self::Lru => "lru",
+            Self::Lfu => "lfu",
+            Self::Oldest => "oldest",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Maximum length of queues under a prefix
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QueueLimit {
+    /// Queues under this prefix are limited, e.g. `/root/jobs`
+    pub prefix: String,
+
+    /// Maximum number of items in a queue
+    pub max_length: usize,
+}
+
+/// Limits of a database, nothing is limited by default
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Limits {
+    /// Maximum estimated size of keys and values in bytes
+    pub max_memory: Option<usize>,
+
+    /// Maximum number of records, queues, hashes and sets
+    pub max_keys: Option<usize>,
+
+    /// Maximum size of a single value in bytes, e.g. a record, a queue item or a hash value
+    pub max_value_size: Option<usize>,
+
+    /// Length limit of queues, the most specific prefix applies
+    #[serde(default)]
+    pub queues: Vec<QueueLimit>,
+
+    /// What happens when a limit is reached
+    #[serde(default)]
+    pub policy: EvictionPolicy,
+
+    /// Records under these prefixes can be evicted and full queues under them drop their oldest item.
+    /// Other keys are never evicted, so the write is rejected if it cannot fit.
+    #[serde(default)]
+    pub evict_prefixes: Vec<String>,
+}
+
+impl Limits {
+    /// Return with the length limit of queue, or `None` if it is not limited
+    ///
+    /// # Arguments
+    /// 1. `key` - Key of queue
+    pub fn queue_limit(&self, key: &str) -> Option<usize> {
+        self.queues
+            .iter()
+            .filter(|x| is_under(key, &x.prefix))
+            .max_by_key(|x| x.prefix.len())
+            .map(|x| x.max_length)
+    }
+
+    /// Tells that the key can be evicted
+    ///
+    /// # Arguments
+    /// 1. `key` - Key of record or queue
+    pub fn is_evictable(&self, key: &str) -> bool {
+        self.policy != EvictionPolicy::Reject
+            && self.evict_prefixes.iter().any(|x| is_under(key, x))
+    }
+}
+
+/// Current usage of a database
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    /// Estimated size of keys and values in bytes
+    pub memory: usize,
+
+    /// Number of records, queues, hashes and sets
+    pub keys: usize,
+
+    /// Number of records and queue items that have been evicted since start
+    pub evicted: u64,
+}
+
+/// Limits and the usage of a database together
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageReport {
+    /// Configured limits
+    pub limits: Limits,
+
+    /// Current usage
+    pub usage: Usage,
+}
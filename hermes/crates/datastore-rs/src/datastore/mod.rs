@@ -1,31 +1,175 @@
 //! Main component
-use std::collections::VecDeque;
-use tokio::sync::mpsc::Sender;
-
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc::Sender};
+
+pub mod computed;
 pub mod enums;
+pub mod filter;
+pub mod handle;
+pub mod index;
+pub mod limits;
+pub mod router;
+pub mod schema;
+pub mod stat;
+pub mod storage;
 pub mod types;
 pub mod utilities;
 
-use crate::{hook::enums::HookManagerAction, logger::enums::LoggerAction};
+use crate::{
+    hook::enums::HookManagerAction,
+    logger::enums::{LogItem, LoggerAction},
+};
 
 use self::{
-    enums::{error::ErrorKind, pair::KeyType, pair::ValueType, ListType},
-    types::Table,
+    computed::ComputedKey,
+    enums::{
+        context::RequestContext,
+        error::ErrorKind,
+        event::{Event, EventKind},
+        pair::KeyType,
+        pair::RecordValue,
+        pair::ValueType,
+        snapshot::{
+            ImportPolicy, ImportSummary, Snapshot, SnapshotHash, SnapshotQueue, SnapshotRecord,
+            SnapshotSet,
+        },
+        ListType, QueueEnd,
+    },
+    filter::{FilterBinding, WriteKind},
+    index::ValueIndex,
+    limits::{EvictionPolicy, Limits, UsageReport},
+    schema::{Schema, Validator},
+    stat::{content_size, Stat, StatKind, Tracker},
+    storage::{MemoryStorage, Storage},
 };
 
+/// Identifier of the next reliable delivery, it is unique in the process,
+/// so an acknowledge can find its database when more databases run in the same process
+static NEXT_DELIVERY: AtomicU64 = AtomicU64::new(1);
+
+/// Content of a table with the path of every item, see `Database::collect_ordered`
+type OrderedContent<T> = Vec<(Vec<KeyType>, T)>;
+
+/// Prefix of the in-flight deliveries in the state of storage, see `Storage::put_meta`
+const DELIVERY_META: &str = "delivery/";
+
+/// Prefix of the hooks in the state of storage, see `Storage::put_meta`
+const HOOK_META: &str = "hook/";
+
+/// Prefix of the record expirations in the state of storage, see `Storage::put_meta`
+const EXPIRATION_META: &str = "expire/";
+
+/// Item that has been popped by reliable pop and waits for acknowledge
+#[derive(Serialize, Deserialize)]
+struct InFlight {
+    /// Queue where the item came from
+    key: String,
+
+    /// Popped value
+    value: String,
+
+    /// Time since UNIX epoch after the item is returned to the queue
+    deadline: Duration,
+}
+
+/// Hook and logger messages of the actions, they are sent after the database lock is released,
+/// so slow hook manager or logger does not block the readers
+#[derive(Default)]
+pub(crate) struct Outbox {
+    hooks: Vec<HookManagerAction>,
+    logs: Vec<LoggerAction>,
+    hook_sender: Option<Sender<HookManagerAction>>,
+    logger_sender: Option<Sender<LoggerAction>>,
+}
+
+impl Outbox {
+    /// Send the held back messages in the order they were made
+    pub(crate) async fn send(self) {
+        if let Some(sender) = &self.hook_sender {
+            for action in self.hooks {
+                sender
+                    .send(action)
+                    .await
+                    .unwrap_or_else(|e| tracing::error!("Error during send: {}", e));
+            }
+        }
+
+        if let Some(sender) = &self.logger_sender {
+            for action in self.logs {
+                sender
+                    .send(action)
+                    .await
+                    .unwrap_or_else(|e| tracing::error!("{}", e));
+            }
+        }
+    }
+}
+
 /// Database struct
 pub struct Database {
     /// Name of database
     name: String,
 
-    /// Pointer to the root table
-    root: Table,
+    /// Storage where the tables are kept
+    storage: Box<dyn Storage>,
 
     /// Sender to HookManager
     hook_sender: Option<Sender<HookManagerAction>>,
 
     /// Logger function
     logger_sender: Option<Sender<LoggerAction>>,
+
+    /// Hook and logger messages that are held back until the database lock is released
+    outbox: Option<Outbox>,
+
+    /// Broadcast channel of change events
+    event_sender: broadcast::Sender<Event>,
+
+    /// Sequence number of the last event
+    event_counter: AtomicU64,
+
+    /// Items popped by reliable pop that are not acknowledged yet
+    in_flight: BTreeMap<u64, InFlight>,
+
+    /// Records that are deleted at a time since UNIX epoch, e.g. by `EXPIRE` of RESP
+    expirations: BTreeMap<String, Duration>,
+
+    /// Client of the request that is processed, its changes are logged with it
+    context: Option<RequestContext>,
+
+    /// Limits that are checked before writes
+    limits: Limits,
+
+    /// Schemas that are checked before records are set and items are pushed
+    validator: Validator,
+
+    /// Filters that are called before records are set and items are pushed by requests
+    filters: Vec<FilterBinding>,
+
+    /// Keys that are computed from the records under their source
+    computed: Vec<ComputedKey>,
+
+    /// Index of computed keys whose source has changed since they were computed
+    dirty_computed: BTreeSet<usize>,
+
+    /// Computed keys are being written, so they are not read-only meanwhile
+    computing: bool,
+
+    /// Indexes of record values under the declared prefixes
+    index: ValueIndex,
+
+    /// Usage, access statistics and metadata of keys and tables
+    tracker: Tracker,
+
+    /// Time of the log item that is replayed, changes are dated with it instead of the current time
+    replay_time: Option<Duration>,
+
+    /// Removals of evicted records and dropped queue items, they are written into the log
+    evictions: Vec<LogItem>,
 }
 
 impl Database {
@@ -39,6 +183,26 @@ impl Database {
     /// let db = onlyati_datastore::datastore::Database::new("root".to_string()).unwrap();
     /// ```
     pub fn new(root_name: String) -> Result<Self, ErrorKind> {
+        Self::with_storage(root_name, Box::new(MemoryStorage::new()))
+    }
+
+    /// Create new database on the specified storage and return with the struct.
+    ///
+    /// # Arguments
+    /// 1. `root_name` - Name of database
+    /// 1. `storage` - Storage where the tables are kept, e.g. `MemoryStorage` or `SledStorage`
+    ///
+    /// # Examples
+    /// ```
+    /// use onlyati_datastore::datastore::{storage::SledStorage, Database};
+    ///
+    /// let storage = SledStorage::temporary().unwrap();
+    /// let db = Database::with_storage("root".to_string(), Box::new(storage)).unwrap();
+    /// ```
+    pub fn with_storage(
+        root_name: String,
+        mut storage: Box<dyn Storage>,
+    ) -> Result<Self, ErrorKind> {
         tracing::trace!(
             "try to allocate new database with '{}' root table",
             root_name
@@ -49,369 +213,836 @@ impl Database {
             ));
         }
 
+        // Persistent storage may have content already, it is counted into the usage one by one,
+        // so the tree is not loaded into memory
+        let mut tracker = Tracker::new(&format!("/{}", root_name), storage.as_ref())?;
+        let mut in_flight = BTreeMap::new();
+        let mut expirations = BTreeMap::new();
+        if storage.is_persistent() {
+            // Keys that were written by an older version do not have stats yet
+            let mut missing = Vec::new();
+            let mut result = Ok(());
+            let mut count = |table: &[&str], key: &KeyType, value: &ValueType| match tracker
+                .existing(storage.as_ref(), table, key, value)
+            {
+                Ok(Some(stats)) => missing.push((table.join("/"), key.clone(), stats)),
+                Ok(None) => (),
+                Err(e) => result = Err(e),
+            };
+            storage.scan(&[&root_name], &mut count)?;
+            result?;
+            for (table, key, stats) in missing {
+                let table: Vec<&str> = table.split('/').collect();
+                storage.put_stats(&table, key, stats)?;
+            }
+
+            // Deliveries are not replayed from append file, they are read from the storage
+            for (name, value) in storage.scan_meta(DELIVERY_META)? {
+                let id = name[DELIVERY_META.len()..]
+                    .parse::<u64>()
+                    .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+                let item: InFlight = bincode::deserialize(&value)
+                    .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+
+                NEXT_DELIVERY.fetch_max(id + 1, Ordering::Relaxed);
+                in_flight.insert(id, item);
+            }
+
+            for (name, value) in storage.scan_meta(EXPIRATION_META)? {
+                let deadline = bincode::deserialize(&value)
+                    .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+                expirations.insert(name[EXPIRATION_META.len()..].to_string(), deadline);
+            }
+        }
+
         tracing::trace!("root table is allocated");
         Ok(Self {
             name: root_name,
-            root: Table::new(),
+            storage,
             hook_sender: None,
             logger_sender: None,
+            outbox: None,
+            event_sender: broadcast::channel(1024).0,
+            event_counter: AtomicU64::new(0),
+            in_flight,
+            expirations,
+            context: None,
+            limits: Limits::default(),
+            validator: Validator::default(),
+            filters: Vec::new(),
+            computed: Vec::new(),
+            dirty_computed: BTreeSet::new(),
+            computing: false,
+            index: ValueIndex::default(),
+            tracker,
+            replay_time: None,
+            evictions: Vec::new(),
         })
     }
 
-    /// Subscribe to HookManager
+    /// Tells that the storage persists the changes, so the append file does not need to be replayed
+    pub fn is_persistent(&self) -> bool {
+        self.storage.is_persistent()
+    }
+
+    /// Write the pending changes of storage onto the disk
+    pub fn flush(&self) -> Result<(), ErrorKind> {
+        self.storage.flush()
+    }
+
+    /// Set the limits of database, they are checked at the next writes.
+    /// If the usage is above the new limits, nothing is evicted until the next write.
     ///
     /// # Arguments
-    /// 1. `sender` - Sender to HookManager thread
+    /// 1. `limits` - New limits
     ///
-    /// # Examples
+    /// # Example
     /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::pair::{KeyType, ValueType};
+    /// use onlyati_datastore::datastore::limits::{EvictionPolicy, Limits};
+    ///
     /// # tokio_test::block_on(async {
-    /// let (sender, _) = onlyati_datastore::hook::utilities::start_hook_manager().await;
-    /// let mut db = onlyati_datastore::datastore::Database::new("root".to_string()).unwrap();
-    /// db.subscribe_to_hook_manager(sender);
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    /// db.set_limits(Limits {
+    ///     max_keys: Some(2),
+    ///     policy: EvictionPolicy::Oldest,
+    ///     evict_prefixes: vec!["/root/cache".to_string()],
+    ///     ..Default::default()
+    /// });
+    ///
+    /// for i in 0..3 {
+    ///     let key = KeyType::Record(format!("/root/cache/{}", i));
+    ///     db.insert(key, ValueType::RecordPointer("ok".into())).await.expect("Failed to insert");
+    /// }
+    ///
+    /// // The oldest record has been evicted
+    /// assert_eq!(true, db.get(KeyType::Record("/root/cache/0".to_string())).is_err());
+    /// assert_eq!(2, db.usage().usage.keys);
     /// # })
     /// ```
-    pub fn subscribe_to_hook_manager(&mut self, sender: Sender<HookManagerAction>) {
-        tracing::trace!("subscribe to hook manager");
-        self.hook_sender = Some(sender);
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
     }
 
-    /// Subscribe to Logger
+    /// Replace the schemas of database, they are checked when a record is set or an item is pushed.
+    /// Schemas are not changed if any of them is invalid, e.g. it has invalid regular expression.
     ///
     /// # Arguments
-    /// 1. `sender` - Sender to Logger thread
+    /// 1. `schemas` - New schemas, empty list removes every schema
     ///
-    /// # Examples
+    /// # Example
     /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::{error::ErrorCode, pair::{KeyType, ValueType}};
+    /// use onlyati_datastore::datastore::schema::Schema;
+    ///
     /// # tokio_test::block_on(async {
-    /// let (sender, _) = onlyati_datastore::logger::utilities::start_logger(&"/tmp/datastore-tmp.txt".to_string()).await;
-    /// let mut db = onlyati_datastore::datastore::Database::new("root".to_string()).unwrap();
-    /// db.subscribe_to_logger(sender);
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    /// db.set_schemas(vec![Schema {
+    ///     prefix: "/root/status".to_string(),
+    ///     r#enum: Some(vec!["OK".to_string(), "NOK".to_string()]),
+    ///     ..Default::default()
+    /// }]).expect("Invalid schema");
+    ///
+    /// let key = KeyType::Record("/root/status/vps01".to_string());
+    /// db.insert(key.clone(), ValueType::RecordPointer("OK".into())).await.expect("Failed to insert");
+    ///
+    /// let result = db.insert(key, ValueType::RecordPointer("probably fine".into())).await;
+    /// assert_eq!(ErrorCode::ValidationFailed, result.unwrap_err().code());
     /// # })
     /// ```
-    pub fn subscribe_to_logger(&mut self, sender: Sender<LoggerAction>) {
-        tracing::trace!("subscribe to logger");
-        self.logger_sender = Some(sender);
+    pub fn set_schemas(&mut self, schemas: Vec<Schema>) -> Result<(), ErrorKind> {
+        self.validator = Validator::new(schemas)?;
+        Ok(())
     }
 
-    /// Insert or update key into database. Return with nothing if the insert was successful. Else with an error code.
+    /// Return with the schemas of database
+    pub fn schemas(&self) -> Vec<Schema> {
+        self.validator.schemas()
+    }
+
+    /// Replace the filters of database, they are called in this order
     ///
     /// # Arguments
-    /// 1. `key` - Unique key for data
-    /// 1. `value` - Value that is assigned for the key
+    /// 1. `filters` - Filters and their prefixes, empty list removes every filter
+    pub fn set_filters(&mut self, filters: Vec<FilterBinding>) {
+        self.filters = filters;
+    }
+
+    /// Call the filters whose prefix contains the key, then return with the key and value that
+    /// have to be written. Value keeps its type, so the text that the filters return must be valid
+    /// for it, e.g. an integer record remains integer. The new key must be in the same database.
     ///
-    /// # Example
+    /// # Arguments
+    /// 1. `kind` - What is written
+    /// 1. `key` - Key of request
+    /// 1. `value` - Value of request, items of queues, hash fields and set members are strings
     ///
+    /// # Example
     /// ```
+    /// use std::sync::Arc;
     /// use onlyati_datastore::datastore::Database;
-    /// use onlyati_datastore::datastore::enums::pair::{KeyType, ValueType};
+    /// use onlyati_datastore::datastore::enums::{error::ErrorKind, pair::RecordValue};
+    /// use onlyati_datastore::datastore::filter::{FilterBinding, WriteFilter, WriteKind};
+    ///
+    /// struct Uppercase;
+    ///
+    /// impl WriteFilter for Uppercase {
+    ///     fn filter(&self, _: WriteKind, key: &str, value: &str, _: Option<&str>) -> Result<(String, String), ErrorKind> {
+    ///         Ok((key.to_string(), value.to_uppercase()))
+    ///     }
+    /// }
     ///
-    /// # tokio_test::block_on(async {
     /// let mut db = Database::new("root".to_string()).unwrap();
+    /// db.set_filters(vec![FilterBinding { prefix: "/root/status".to_string(), filter: Arc::new(Uppercase) }]);
     ///
-    /// let result = db.insert(KeyType::Record("/root/network/dns-stats".to_string()), ValueType::RecordPointer("ok".to_string())).await;
-    /// # })
+    /// let (key, value) = db.filter_write(WriteKind::Set, "/root/status/vps01".to_string(), "ok".into()).unwrap();
+    /// assert_eq!(RecordValue::String("OK".to_string()), value);
     /// ```
-    pub async fn insert(
-        &mut self,
-        key: KeyType,
-        value: ValueType,
-    ) -> Result<(), ErrorKind> {
-        tracing::trace!("set request is performed for '{}'", key.get_key());
+    pub fn filter_write(
+        &self,
+        kind: WriteKind,
+        key: String,
+        value: RecordValue,
+    ) -> Result<(String, RecordValue), ErrorKind> {
+        let key_routes = utilities::internal::validate_key(&key, &self.name)?;
+        let full_key = format!("/{}", key_routes.join("/"));
+        let bindings: Vec<&FilterBinding> = self
+            .filters
+            .iter()
+            .filter(|x| stat::is_under(&full_key, &x.prefix))
+            .collect();
+        if bindings.is_empty() {
+            return Ok((key, value));
+        }
 
-        // Validate the specified key
-        let key_routes = utilities::internal::validate_key(key.get_key(), &self.name)?;
+        let old = match kind {
+            WriteKind::Set => match self.get(KeyType::Record(full_key.clone())) {
+                Ok(ValueType::RecordPointer(old)) => Some(old.to_string()),
+                _ => None,
+            },
+            _ => None,
+        };
 
-        // Find the last table or create them according to request
-        // For example if key is /root/status/vps01/github then create or find root->status->vps01 table
-        let mut table = Box::new(&mut self.root);
-        let last_route = key_routes[key_routes.len() - 1];
-        let mut route_index: usize = 0;
-        let mut current_route = key_routes[route_index].to_string();
-
-        while last_route != current_route {
-            let temp_key = KeyType::Table(current_route);
-            table
-                .entry(temp_key.clone())
-                .or_insert(ValueType::TablePointer(Table::new()));
-
-            *table = match table.get_mut(&temp_key) {
-                Some(item) => match item {
-                    ValueType::TablePointer(sub_table) => sub_table,
-                    _ => {
-                        tracing::error!("wow, this should not happen a table pointer should be here not a record pointer");
-                        return Err(ErrorKind::InternalError(
-                            "This should not have happen".to_string(),
-                        ));
-                    }
-                },
-                _ => {
-                    tracing::error!("wow, this should not happen table must exist");
-                    return Err(ErrorKind::InternalError(
-                        "This should not have happen".to_string(),
-                    ));
-                }
-            };
+        let original = value.to_string();
+        let (mut new_key, mut new_value) = (full_key, original.clone());
+        for binding in bindings {
+            tracing::trace!("filter of '{}' is called for '{}'", binding.prefix, new_key);
+            (new_key, new_value) =
+                binding
+                    .filter
+                    .filter(kind, &new_key, &new_value, old.as_deref())?;
+        }
 
-            route_index += 1;
-            current_route = key_routes[route_index].to_string();
+        utilities::internal::validate_key(&new_key, &self.name)?;
+        if new_value == original {
+            return Ok((new_key, value));
         }
 
-        // In the found table, save the key
-        let record_key = KeyType::Record(last_route.to_string());
-        table.insert(record_key, value.clone());
-        tracing::trace!("set request is done for '{}'", key.get_key());
+        match RecordValue::parse(value.get_type(), &new_value) {
+            Ok(parsed) => Ok((new_key, parsed)),
+            Err(_) => Err(ErrorKind::ScriptError(format!(
+                "Filter has returned invalid {} value for '{}'",
+                value.get_type(),
+                new_key
+            ))),
+        }
+    }
 
-        // If hook manager exists, then send the pair to hook manager
-        if let Some(sender) = &self.hook_sender {
-            tracing::trace!("send alert to hook manager about '{}' key", key.get_key());
-            if let ValueType::RecordPointer(value) = &value {
-                let action = HookManagerAction::Send(key.get_key().to_string(), value.to_string());
+    /// Call the filters for the content of a snapshot before it is imported, like it was written one
+    /// by one: records are set, items are pushed, fields and members are added. Items, fields and
+    /// members that are redirected are moved into the queue, hash or set of their new key.
+    /// Hooks of the snapshot are not filtered.
+    ///
+    /// # Arguments
+    /// 1. `snapshot` - Snapshot that is imported, its keys and values are replaced with the filtered ones
+    pub fn filter_snapshot(&self, snapshot: &mut Snapshot) -> Result<(), ErrorKind> {
+        if self.filters.is_empty() {
+            return Ok(());
+        }
 
-                sender
-                    .send(action)
-                    .await
-                    .unwrap_or_else(|e| tracing::error!("Error during send: {}", e));
+        for record in &mut snapshot.record {
+            let value = RecordValue::parse(&record.r#type, &record.value)?;
+            let (key, value) = self.filter_write(WriteKind::Set, record.key.clone(), value)?;
+            record.key = key;
+            record.value = value.to_string();
+        }
+
+        let mut queues: Vec<SnapshotQueue> = Vec::new();
+        for queue in std::mem::take(&mut snapshot.queue) {
+            if queue.values.is_empty() {
+                queues.push(queue);
+                continue;
+            }
+
+            for value in queue.values {
+                let (key, value) =
+                    self.filter_write(WriteKind::Push, queue.key.clone(), value.into())?;
+                let value = value.to_string();
+                match queues.iter_mut().find(|x| x.key == key) {
+                    Some(queue) => queue.values.push(value),
+                    None => queues.push(SnapshotQueue {
+                        key,
+                        values: vec![value],
+                    }),
+                }
+            }
+        }
+        snapshot.queue = queues;
+
+        let mut hashes: Vec<SnapshotHash> = Vec::new();
+        for hash in std::mem::take(&mut snapshot.hash) {
+            if hash.fields.is_empty() {
+                hashes.push(hash);
+                continue;
+            }
+
+            for (field, value) in hash.fields {
+                let (key, value) =
+                    self.filter_write(WriteKind::HashSet, hash.key.clone(), value.into())?;
+                let value = value.to_string();
+                match hashes.iter_mut().find(|x| x.key == key) {
+                    Some(hash) => {
+                        hash.fields.insert(field, value);
+                    }
+                    None => hashes.push(SnapshotHash {
+                        key,
+                        fields: BTreeMap::from([(field, value)]),
+                    }),
+                }
+            }
+        }
+        snapshot.hash = hashes;
+
+        let mut sets: Vec<SnapshotSet> = Vec::new();
+        for set in std::mem::take(&mut snapshot.set) {
+            if set.members.is_empty() {
+                sets.push(set);
+                continue;
+            }
+
+            for member in set.members {
+                let (key, member) =
+                    self.filter_write(WriteKind::SetAdd, set.key.clone(), member.into())?;
+                let member = member.to_string();
+                match sets.iter_mut().find(|x| x.key == key) {
+                    Some(set) => set.members.push(member),
+                    None => sets.push(SnapshotSet {
+                        key,
+                        members: vec![member],
+                    }),
+                }
             }
         }
+        snapshot.set = sets;
 
         Ok(())
     }
 
-    /// Push a value into a queue. Return with nothing if the insert was successful. Else with an error code.
+    /// Replace the computed keys of database, they are computed at the next refresh
     ///
     /// # Arguments
-    /// 1. `key` - Unique key for data
-    /// 1. `value` - Value that will be pushed to queue
+    /// 1. `computed` - Computed keys and their sources, empty list removes every one, the values remain
     ///
     /// # Example
     /// ```
     /// use onlyati_datastore::datastore::Database;
-    /// use onlyati_datastore::datastore::enums::pair::{KeyType, ValueType};
+    /// use onlyati_datastore::datastore::computed::{ComputedKey, Function};
+    /// use onlyati_datastore::datastore::enums::pair::{KeyType, RecordValue, ValueType};
     ///
     /// # tokio_test::block_on(async {
     /// let mut db = Database::new("root".to_string()).unwrap();
+    /// db.set_computed(vec![ComputedKey {
+    ///     key: "/root/summary/nok_count".to_string(),
+    ///     source: "/root/status".to_string(),
+    ///     function: Function::Count(Some("NOK".to_string())),
+    /// }]).unwrap();
     ///
-    /// let result = db.push(KeyType::Record("/root/ticket/open".to_string()), "SINC100".to_string()).await.expect("Failed to push");
-    /// let result = db.push(KeyType::Record("/root/ticket/open".to_string()), "SINC101".to_string()).await.expect("Failed to push");
+    /// db.insert(KeyType::Record("/root/status/vps01".to_string()), ValueType::RecordPointer("NOK".into())).await.unwrap();
+    /// db.refresh_computed().await;
+    ///
+    /// let value = db.get(KeyType::Record("/root/summary/nok_count".to_string())).unwrap();
+    /// assert_eq!(ValueType::RecordPointer(RecordValue::Integer(1)), value);
     /// # })
     /// ```
-    pub async fn push(
-        &mut self,
-        key: KeyType,
-        value: String,
-    ) -> Result<(), ErrorKind> {
-        tracing::trace!("push request is performed for '{}'", key.get_key());
-
-        // Verify and validate key value
-        let key = match key {
-            KeyType::Record(key) => key,
-            _ => {
+    pub fn set_computed(&mut self, computed: Vec<ComputedKey>) -> Result<(), ErrorKind> {
+        let mut keys = BTreeSet::new();
+        for item in &computed {
+            let key_routes = utilities::internal::validate_key(&item.key, &self.name)?;
+            if key_routes.len() == 1 {
                 return Err(ErrorKind::InvalidKey(
-                    "Parameter must be a Record type".to_string(),
+                    "Root table cannot be computed".to_string(),
                 ));
             }
-        };
-
-        let key_routes = utilities::internal::validate_key(&key[..], &self.name)?;
+            utilities::internal::validate_key(&item.source, &self.name)?;
+            if !keys.insert(format!("/{}", key_routes.join("/"))) {
+                return Err(ErrorKind::Conflict(format!(
+                    "'{}' is computed more times",
+                    item.key
+                )));
+            }
+        }
 
-        // Find the last table or create them according to request
-        // For example if key is /root/status/vps01/github then create or find root->status->vps01 table
-        let mut table = Box::new(&mut self.root);
-        let last_route = key_routes[key_routes.len() - 1];
-        let mut route_index: usize = 0;
-        let mut current_route = key_routes[route_index].to_string();
-
-        while last_route != current_route {
-            let temp_key = KeyType::Table(current_route);
-            table
-                .entry(temp_key.clone())
-                .or_insert(ValueType::TablePointer(Table::new()));
-
-            *table = match table.get_mut(&temp_key) {
-                Some(item) => match item {
-                    ValueType::TablePointer(sub_table) => sub_table,
-                    _ => {
-                        tracing::error!("wow, this should not happen a table pointer should be here not a record pointer");
-                        return Err(ErrorKind::InternalError(
-                            "This should not have happen".to_string(),
-                        ));
-                    }
-                },
-                _ => {
-                    tracing::error!("wow, this should not happen table must exist");
-                    return Err(ErrorKind::InternalError(
-                        "This should not have happen".to_string(),
-                    ));
-                }
-            };
+        self.dirty_computed = (0..computed.len()).collect();
+        self.computed = computed;
+        Ok(())
+    }
 
-            route_index += 1;
-            current_route = key_routes[route_index].to_string();
-        }
-
-        // If table has been found, then we have to check that record exists.
-        // If exists then get it as mutable and add new element into the queue.
-        // Else create a new record with the specified key and value in a queue.
-        match table.get_mut(&KeyType::Queue(last_route.to_string())) {
-            Some(elem) => {
-                match elem {
-                    ValueType::QueuePointer(queue) => {
-                        // Queue key was already exist, add value to existing queue
-                        queue.push_back(value.clone());
-                        tracing::trace!("push request is done for '{}'", key);
-
-                        // Send data to hook manager if active
-                        if let Some(sender) = &self.hook_sender {
-                            tracing::trace!("send alert to hook manager about '{}' key", key);
-                            let action = HookManagerAction::Send(key, value);
-
-                            sender
-                                .send(action)
-                                .await
-                                .unwrap_or_else(|e| tracing::error!("Error during send: {}", e));
-                        }
-                    }
-                    _ => {
-                        // Since we are within a get_mut this branch should never run
-                        tracing::trace!("queue '{}' does not exist", key);
-                        return Err(ErrorKind::InvalidKey(
-                            "Specified key does not exist".to_string(),
-                        ));
-                    }
-                }
+    /// Compute again the keys whose source has changed since the last refresh, then write the changed
+    /// values like a normal set, so hooks and watchers are notified. Computed keys are not the input
+    /// of each other, even if they are under the source.
+    pub async fn refresh_computed(&mut self) {
+        for index in std::mem::take(&mut self.dirty_computed) {
+            let computed = self.computed[index].clone();
+            if let Err(e) = self.refresh_one(&computed).await {
+                tracing::error!("failed to compute '{}': {}", computed.key, e);
             }
-            None => {
-                // Queue key does not exist yet, create a new one
-                let new_qeue = KeyType::Queue(last_route.to_string());
-                let mut queue = VecDeque::new();
-                queue.push_back(value.clone());
-                table.insert(new_qeue, ValueType::QueuePointer(queue));
-
-                // Send data to hook manager if active
-                if let Some(sender) = &self.hook_sender {
-                    tracing::trace!("send alert to hook manager about '{}' key", key);
-                    let action = HookManagerAction::Send(key, value);
-
-                    sender
-                        .send(action)
-                        .await
-                        .unwrap_or_else(|e| tracing::error!("Error during send: {}", e));
-                }
+        }
+    }
+
+    /// Compute a key and write it if its value has changed
+    async fn refresh_one(&mut self, computed: &ComputedKey) -> Result<(), ErrorKind> {
+        let mut records = BTreeMap::new();
+        let source_routes = utilities::internal::validate_key(&computed.source, &self.name)?;
+        self.scan_records(&source_routes, &mut |key, value| {
+            if !self.computed.iter().any(|x| x.key == key) {
+                records.insert(key, value.clone());
             }
+        })?;
+
+        let mut modified = None;
+        for key in records.keys() {
+            let key = KeyType::Record(key.clone());
+            let stats = self.tracker.key_stats(self.storage.as_ref(), &key)?;
+            modified = modified.max(stats.and_then(|x| x.modified_at));
         }
 
-        Ok(())
+        let value = computed
+            .function
+            .compute(&computed.key, &records, modified)?;
+        let key = KeyType::Record(computed.key.clone());
+        let current = match self.get(key.clone()) {
+            Ok(ValueType::RecordPointer(current)) => Some(current),
+            _ => None,
+        };
+
+        self.computing = true;
+        let result = match (value, current) {
+            (Some(value), Some(current)) if value == current => Ok(()),
+            (Some(value), _) => self.insert(key, ValueType::RecordPointer(value)).await,
+            (None, Some(_)) => self.delete_key(key).await,
+            (None, None) => Ok(()),
+        };
+        self.computing = false;
+        result
     }
 
-    /// Send a trigger to HookManager, record is not created like at `insert` but it can trigger and send some hooks out
+    /// Computed keys are read-only for the clients
+    fn check_computed(&self, key: &str) -> Result<(), ErrorKind> {
+        match !self.computing && self.computed.iter().any(|x| x.key == key) {
+            true => Err(ErrorKind::Forbidden(format!(
+                "'{}' is computed by the server, it is read-only",
+                key
+            ))),
+            false => Ok(()),
+        }
+    }
+
+    /// Mark the computed keys that depend on the changed key or table
+    fn mark_computed(&mut self, changed: &str, table: bool) {
+        if self.computing {
+            return;
+        }
+
+        // Computed key itself is written again if it is removed or evicted
+        for (index, computed) in self.computed.iter().enumerate() {
+            let dirty = stat::is_under(changed, &computed.source)
+                || changed == computed.key
+                || (table && stat::is_under(&computed.source, changed))
+                || (table && stat::is_under(&computed.key, changed));
+            if dirty {
+                self.dirty_computed.insert(index);
+            }
+        }
+    }
+
+    /// Replace the value indexes of database, they are built from the existing records
     ///
     /// # Arguments
-    ///
-    /// 1. `key` - Unique key for data
-    /// 1. `value` - Value that is assigned for the key
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use onlyati_datastore::datastore::Database;
-    /// use onlyati_datastore::datastore::enums::pair::{KeyType, ValueType};
-    ///
-    /// # tokio_test::block_on(async {
-    /// let mut db = Database::new("root".to_string()).unwrap();
-    ///
-    /// let result = db.trigger(KeyType::Record("/root/network/dns-stats".to_string()), ValueType::RecordPointer("ok".to_string())).await;
-    /// # })
-    /// ```
-    pub async fn trigger(&self, key: KeyType, value: ValueType) -> Result<(), ErrorKind> {
-        match &self.hook_sender {
-            Some(sender) => {
-                tracing::trace!("send trigger to hook manager about '{}' key", key.get_key());
-                if let ValueType::RecordPointer(value) = &value {
-                    let action =
-                        HookManagerAction::Send(key.get_key().to_string(), value.to_string());
-
-                    sender
-                        .send(action)
-                        .await
-                        .unwrap_or_else(|e| tracing::error!("Error during send: {}", e));
-                }
-                Ok(())
-            }
-            None => Err(ErrorKind::InactiveHookManager),
+    /// 1. `prefixes` - Records under these prefixes are indexed, empty list removes every index
+    pub fn set_indexes(&mut self, prefixes: Vec<String>) -> Result<(), ErrorKind> {
+        let mut normalized = Vec::with_capacity(prefixes.len());
+        for prefix in prefixes {
+            let key_routes = utilities::internal::validate_key(&prefix, &self.name)?;
+            normalized.push(format!("/{}", key_routes.join("/")));
+        }
+
+        let mut index = ValueIndex::new(normalized.clone());
+        for prefix in normalized {
+            let key_routes: Vec<&str> = prefix.split('/').filter(|x| !x.is_empty()).collect();
+            self.scan_records(&key_routes, &mut |key, value| {
+                index.insert(&key, &value.to_string())
+            })?;
         }
+
+        self.index = index;
+        Ok(())
     }
 
-    /// Get the value of a key and return with a copy of it. If not found return with error.
+    /// Return with the prefixes that have value index
+    pub fn indexes(&self) -> Vec<String> {
+        self.index.prefixes()
+    }
+
+    /// Find the records under a prefix whose value is the specified one, values are compared in text format.
+    /// Prefix must be under an indexed prefix, the tree is not walked.
     ///
     /// # Arguments
-    /// 1. `key` - Unique key that has to be found
+    /// 1. `prefix` - Records are searched under this prefix, e.g. `/root/status`
+    /// 1. `value` - Value of records, e.g. `NOK`
     ///
     /// # Example
-    ///
     /// ```
     /// use onlyati_datastore::datastore::Database;
     /// use onlyati_datastore::datastore::enums::pair::{KeyType, ValueType};
     ///
     /// # tokio_test::block_on(async {
     /// let mut db = Database::new("root".to_string()).unwrap();
+    /// db.set_indexes(vec!["/root/status".to_string()]).unwrap();
     ///
-    /// db.insert(KeyType::Record("/root/status".to_string()), ValueType::RecordPointer("Having a great time".to_string())).await.expect("Failed to insert");
-    /// let value = db.get(KeyType::Record("/root/status".to_string())).expect("Key not found");
+    /// db.insert(KeyType::Record("/root/status/vps01".to_string()), ValueType::RecordPointer("NOK".into())).await.unwrap();
+    /// db.insert(KeyType::Record("/root/status/vps02".to_string()), ValueType::RecordPointer("OK".into())).await.unwrap();
+    ///
+    /// let keys = db.find("/root/status", "NOK").unwrap();
+    /// assert_eq!(vec!["/root/status/vps01".to_string()], keys);
     /// # })
     /// ```
-    pub fn get(&self, key: KeyType) -> Result<ValueType, ErrorKind> {
-        tracing::trace!("get request is performed for '{}'", key.get_key());
+    pub fn find(&self, prefix: &str, value: &str) -> Result<Vec<String>, ErrorKind> {
+        tracing::trace!(
+            "find request is performed for '{}' under '{}'",
+            value,
+            prefix
+        );
+        let key_routes = utilities::internal::validate_key(prefix, &self.name)?;
+        let prefix = format!("/{}", key_routes.join("/"));
+
+        match self.index.find(&prefix, value) {
+            Some(keys) => Ok(keys),
+            None => Err(ErrorKind::NotFound(format!(
+                "No value index contains '{}'",
+                prefix
+            ))),
+        }
+    }
 
-        // Verify and validate the key
-        let key = match key {
-            KeyType::Record(key) => key,
-            _ => {
-                return Err(ErrorKind::InvalidKey(
-                    "Parameter must be a Record type".to_string(),
-                ));
+    /// Return with the limits and the current usage of database
+    pub fn usage(&self) -> UsageReport {
+        UsageReport {
+            limits: self.limits.clone(),
+            usage: self.tracker.usage(),
+        }
+    }
+
+    /// Time of the change that is processed, it is the time of log item during replay
+    fn now(&self) -> Duration {
+        match self.replay_time {
+            Some(time) => time,
+            None => std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Report the new content size of key to the tracker
+    fn track(&mut self, key: KeyType, content: usize) -> Result<(), ErrorKind> {
+        self.mark_computed(key.get_key(), false);
+        let now = self.now();
+        self.tracker.set(self.storage.as_mut(), key, content, now)
+    }
+
+    /// Write the reads that are registered by the tracker into the stats of keys
+    pub(crate) fn save_reads(&mut self) {
+        if let Err(e) = self.tracker.save_reads(self.storage.as_mut()) {
+            tracing::error!("failed to save the access statistics: {}", e);
+        }
+    }
+
+    /// Return with the size of content that is reported to the tracker, 0 if key does not exist
+    fn content_of(&self, key: &KeyType) -> Result<usize, ErrorKind> {
+        Ok(self
+            .tracker
+            .content(self.storage.as_ref(), key)?
+            .unwrap_or(0))
+    }
+
+    /// Report a removed key to the tracker
+    fn untrack(&mut self, key: &KeyType) -> Result<(), ErrorKind> {
+        self.mark_computed(key.get_key(), false);
+        if let KeyType::Record(key) = key {
+            self.index.remove(key);
+            if self.expirations.remove(key).is_some() {
+                self.forget_expiration(key);
             }
+        }
+        let now = self.now();
+        self.tracker.remove(self.storage.as_mut(), key, now)
+    }
+
+    /// Report a removed table to the tracker
+    fn untrack_table(&mut self, prefix: &str) -> Result<(), ErrorKind> {
+        self.mark_computed(prefix, true);
+        self.index.remove_table(prefix);
+        let expired: Vec<String> = self
+            .expirations
+            .keys()
+            .filter(|key| stat::is_under(key, prefix))
+            .cloned()
+            .collect();
+        for key in expired {
+            self.expirations.remove(&key);
+            self.forget_expiration(&key);
+        }
+        let now = self.now();
+        self.tracker
+            .remove_table(self.storage.as_mut(), prefix, now)
+    }
+
+    /// Write the evictions into the append file, so replay removes the same records and queue items.
+    /// They are logged before the write that caused them.
+    async fn log_evictions(&mut self) {
+        let evictions = std::mem::take(&mut self.evictions);
+        if evictions.is_empty() {
+            return;
+        }
+
+        self.write_log(evictions).await;
+    }
+
+    /// Hold back hook and logger messages until `take_outbox` is called, so they can be sent
+    /// after the database lock is released
+    pub(crate) fn defer_messages(&mut self) {
+        self.outbox = Some(Outbox::default());
+    }
+
+    /// Take the held back messages, they are sent by `Outbox::send`
+    pub(crate) fn take_outbox(&mut self) -> Outbox {
+        let outbox = match &mut self.outbox {
+            Some(outbox) => std::mem::take(outbox),
+            None => Outbox::default(),
         };
 
-        let key_routes = utilities::internal::validate_key(&key[..], &self.name)?;
+        Outbox {
+            hook_sender: self.hook_sender.clone(),
+            logger_sender: self.logger_sender.clone(),
+            ..outbox
+        }
+    }
 
-        // Try to find the last table where the key can be found
-        // For example if key is /root/test/status then root->test table must be found, else return with a not found error
-        let table = match utilities::internal::find_table(
-            &self.root,
-            key_routes[..key_routes.len() - 1].to_vec(),
-        ) {
-            Some(table) => table,
-            None => {
-                tracing::trace!("key '{}' does not exist", key);
-                return Err(ErrorKind::InvalidKey(
-                    "Specified key does not exist".to_string(),
-                ));
-            }
+    /// Write items into the append file if logger is active
+    pub(crate) async fn write_log(&mut self, items: Vec<LogItem>) {
+        let action = LoggerAction::WriteAsync(LogItem::with_context(&self.context, items));
+        if let Some(outbox) = &mut self.outbox {
+            outbox.logs.push(action);
+        } else if let Some(sender) = &self.logger_sender {
+            sender
+                .send(action)
+                .await
+                .unwrap_or_else(|e| tracing::error!("{}", e));
+        }
+    }
+
+    /// Check that a single value, e.g. a record or a queue item, is not too large
+    fn check_value_size(&self, size: usize) -> Result<(), ErrorKind> {
+        match self.limits.max_value_size {
+            Some(max) if size > max => Err(ErrorKind::LimitExceeded(format!(
+                "value is larger than {} bytes",
+                max
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Make room for the new content of a key. Records are evicted if memory or key limit
+    /// would be exceeded, else the write is rejected.
+    ///
+    /// # Arguments
+    /// 1. `key` - Full key with its type, e.g. `KeyType::Queue("/root/jobs")`
+    /// 1. `content` - Size of the content after the write
+    fn reserve(&mut self, key: &KeyType, content: usize) -> Result<(), ErrorKind> {
+        let current = self.tracker.content(self.storage.as_ref(), key)?;
+        let usage = self.tracker.usage();
+
+        let growth = match current {
+            Some(current) => content.saturating_sub(current),
+            None => key.get_key().len() + content,
         };
+        let new_keys = usize::from(current.is_none());
 
-        let find_key = KeyType::Record(key_routes[key_routes.len() - 1].to_string());
+        let memory = match self.limits.max_memory {
+            Some(max) => (usage.memory + growth).saturating_sub(max),
+            None => 0,
+        };
+        let keys = match self.limits.max_keys {
+            Some(max) => (usage.keys + new_keys).saturating_sub(max),
+            None => 0,
+        };
+        if memory == 0 && keys == 0 {
+            return Ok(());
+        }
 
-        // Last table has been found, check that key exist within it
-        // If exists return with this, else with a not found error
-        match table.get(&find_key) {
-            Some(value) => {
-                tracing::trace!("get request is done for '{}'", key);
-                Ok(value.clone())
+        // Access statistics of the candidates must be up to date
+        self.tracker.save_reads(self.storage.as_mut())?;
+        let victims =
+            self.tracker
+                .victims(self.storage.as_ref(), &self.limits, memory, keys, key)?;
+        let victims = match victims {
+            Some(victims) => victims,
+            None if keys > 0 => {
+                return Err(ErrorKind::LimitExceeded(format!(
+                    "key limit of {} keys is reached",
+                    self.limits.max_keys.unwrap_or_default()
+                )))
             }
             None => {
-                tracing::trace!("key '{}' does not exist", key);
-                Err(ErrorKind::InvalidKey(
-                    "Specified key does not exist".to_string(),
-                ))
+                return Err(ErrorKind::LimitExceeded(format!(
+                    "memory limit of {} bytes is reached",
+                    self.limits.max_memory.unwrap_or_default()
+                )))
             }
+        };
+
+        for victim in victims {
+            self.evict(victim)?;
         }
+
+        Ok(())
     }
 
-    /// Pop value from queue. If not found return with error.
+    /// Remove a record due to a limit
+    fn evict(&mut self, key: KeyType) -> Result<(), ErrorKind> {
+        let key_routes = utilities::internal::validate_key(key.get_key(), &self.name)?;
+        self.storage.remove(
+            &key_routes[..key_routes.len() - 1],
+            &KeyType::Record(key_routes[key_routes.len() - 1].to_string()),
+        )?;
+
+        tracing::debug!("'{}' is evicted", key.get_key());
+        self.untrack(&key)?;
+        self.tracker.evicted();
+        self.send_event(EventKind::Delete, key.get_key(), None);
+        self.evictions
+            .push(LogItem::RemKey(self.now(), key.get_key().to_string()));
+
+        Ok(())
+    }
+
+    /// Subscribe to HookManager
     ///
     /// # Arguments
-    /// 1. `key` - Unique key that has to be found
+    /// 1. `sender` - Sender to HookManager thread
+    ///
+    /// # Examples
+    /// ```
+    /// # tokio_test::block_on(async {
+    /// let (sender, _) = onlyati_datastore::hook::utilities::start_hook_manager().await;
+    /// let mut db = onlyati_datastore::datastore::Database::new("root".to_string()).unwrap();
+    /// db.subscribe_to_hook_manager(sender);
+    /// # })
+    /// ```
+    pub fn subscribe_to_hook_manager(&mut self, sender: Sender<HookManagerAction>) {
+        tracing::trace!("subscribe to hook manager");
+        self.hook_sender = Some(sender);
+    }
+
+    /// Save a hook into the storage, so persistent storage can restore it without the append file
+    ///
+    /// # Arguments
+    /// 1. `prefix` - Prefix of the hook
+    /// 1. `link` - Link where the changes are sent
+    pub(crate) fn save_hook(&mut self, prefix: &str, link: &str) -> Result<(), ErrorKind> {
+        let value = bincode::serialize(&(prefix, link))
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+        self.storage.put_meta(&hook_meta(prefix, link), value)
+    }
+
+    /// Remove a hook that was saved by `save_hook`
+    ///
+    /// # Arguments
+    /// 1. `prefix` - Prefix of the hook
+    /// 1. `link` - Link where the changes are sent
+    pub(crate) fn forget_hook(&mut self, prefix: &str, link: &str) -> Result<(), ErrorKind> {
+        self.storage.remove_meta(&hook_meta(prefix, link))
+    }
+
+    /// Return with the prefix and link of hooks that are saved in the storage
+    pub(crate) fn saved_hooks(&self) -> Result<Vec<(String, String)>, ErrorKind> {
+        self.storage
+            .scan_meta(HOOK_META)?
+            .into_iter()
+            .map(|(_, value)| {
+                bincode::deserialize(&value).map_err(|e| ErrorKind::StorageError(e.to_string()))
+            })
+            .collect()
+    }
+
+    /// Subscribe to Logger
+    ///
+    /// # Arguments
+    /// 1. `sender` - Sender to Logger thread
+    ///
+    /// # Examples
+    /// ```
+    /// # tokio_test::block_on(async {
+    /// let (sender, _) = onlyati_datastore::logger::utilities::start_logger(&"/tmp/datastore-tmp.txt".to_string()).await;
+    /// let mut db = onlyati_datastore::datastore::Database::new("root".to_string()).unwrap();
+    /// db.subscribe_to_logger(sender);
+    /// # })
+    /// ```
+    pub fn subscribe_to_logger(&mut self, sender: Sender<LoggerAction>) {
+        tracing::trace!("subscribe to logger");
+        self.logger_sender = Some(sender);
+    }
+
+    /// Subscribe to change events of the database. Every successful set, delete, push, pop and trigger
+    /// are sent to the returned receiver.
+    ///
+    /// # Examples
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::{event::EventKind, pair::KeyType, pair::ValueType};
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    /// let mut events = db.subscribe_to_events();
+    ///
+    /// db.insert(KeyType::Record("/root/status".to_string()), ValueType::RecordPointer("ok".into())).await.expect("Failed to insert");
+    ///
+    /// let event = events.recv().await.expect("Failed to receive event");
+    /// assert_eq!(EventKind::Set, event.kind);
+    /// # })
+    /// ```
+    pub fn subscribe_to_events(&self) -> broadcast::Receiver<Event> {
+        tracing::trace!("new subscription to events");
+        self.event_sender.subscribe()
+    }
+
+    /// Send an event to the subscribers, if nobody listen then it is dropped
+    fn send_event(&self, kind: EventKind, key: &str, value: Option<String>) {
+        let event = Event {
+            id: self.event_counter.fetch_add(1, Ordering::Relaxed) + 1,
+            kind,
+            key: key.to_string(),
+            value,
+        };
+        let _ = self.event_sender.send(event);
+    }
+
+    /// Insert or update key into database. Return with nothing if the insert was successful. Else with an error code.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key for data
+    /// 1. `value` - Value that is assigned for the key
     ///
     /// # Example
     ///
@@ -422,88 +1053,1335 @@ impl Database {
     /// # tokio_test::block_on(async {
     /// let mut db = Database::new("root".to_string()).unwrap();
     ///
+    /// let result = db.insert(KeyType::Record("/root/network/dns-stats".to_string()), ValueType::RecordPointer("ok".into())).await;
+    /// # })
+    /// ```
+    pub async fn insert(&mut self, key: KeyType, value: ValueType) -> Result<(), ErrorKind> {
+        tracing::trace!("set request is performed for '{}'", key.get_key());
+
+        // Validate the specified key
+        let key_routes = utilities::internal::validate_key(key.get_key(), &self.name)?;
+
+        // Check the schemas and limits before the write, other records may be evicted to make room
+        let tracked_key = KeyType::Record(format!("/{}", key_routes.join("/")));
+        self.check_computed(tracked_key.get_key())?;
+        if let ValueType::RecordPointer(record) = &value {
+            self.validator.check_record(tracked_key.get_key(), record)?;
+        }
+        let size = content_size(&value);
+        if value.is_record() {
+            self.check_value_size(size)?;
+            self.reserve(&tracked_key, size)?;
+            self.log_evictions().await;
+        }
+
+        // Save the key in the last table, missing tables are created according to request
+        // For example if key is /root/status/vps01/github then it is saved in root->status->vps01 table
+        let record_key = KeyType::Record(key_routes[key_routes.len() - 1].to_string());
+        self.storage.put(
+            &key_routes[..key_routes.len() - 1],
+            record_key,
+            value.clone(),
+        )?;
+        tracing::trace!("set request is done for '{}'", key.get_key());
+
+        if let ValueType::RecordPointer(record) = &value {
+            self.index
+                .insert(tracked_key.get_key(), &record.to_string());
+            self.track(tracked_key, size)?;
+        }
+
+        if let ValueType::RecordPointer(value) = &value {
+            self.send_event(EventKind::Set, key.get_key(), Some(value.to_string()));
+        }
+
+        // If hook manager exists, then send the pair to hook manager
+        if let ValueType::RecordPointer(value) = &value {
+            self.send_hook(key.get_key(), value.to_string()).await;
+        }
+
+        Ok(())
+    }
+
+    /// Push a value into a queue. Return with nothing if the insert was successful. Else with an error code.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key for data
+    /// 1. `value` - Value that will be pushed to queue
+    ///
+    /// # Example
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::pair::{KeyType, ValueType};
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
     /// let result = db.push(KeyType::Record("/root/ticket/open".to_string()), "SINC100".to_string()).await.expect("Failed to push");
     /// let result = db.push(KeyType::Record("/root/ticket/open".to_string()), "SINC101".to_string()).await.expect("Failed to push");
+    /// # })
+    /// ```
+    pub async fn push(&mut self, key: KeyType, value: String) -> Result<(), ErrorKind> {
+        self.push_to(key, value, QueueEnd::Back).await
+    }
+
+    /// Push a value into the front or the back of a queue. If the queue is full and it can be evicted,
+    /// the item at the other end is dropped.
     ///
-    /// let ticket = db.pop(KeyType::Record("/root/ticket/open".to_string())).await.expect("Failed to pop");
-    /// assert_eq!("SINC100".to_string(), ticket);
+    /// # Arguments
+    /// 1. `key` - Unique key for data
+    /// 1. `value` - Value that will be pushed to queue
+    /// 1. `end` - Value is pushed to the front or the back
     ///
-    /// let ticket = db.pop(KeyType::Record("/root/ticket/open".to_string())).await.expect("Failed to pop");
-    /// assert_eq!("SINC101".to_string(), ticket);
+    /// # Example
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::{pair::KeyType, QueueEnd};
     ///
-    /// let ticket = db.pop(KeyType::Record("/root/ticket/open".to_string())).await;
-    /// assert_eq!(true, ticket.is_err());
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    /// let key = KeyType::Record("/root/ticket/open".to_string());
+    ///
+    /// db.push_to(key.clone(), "SINC100".to_string(), QueueEnd::Front).await.expect("Failed to push");
+    /// db.push_to(key.clone(), "SINC101".to_string(), QueueEnd::Front).await.expect("Failed to push");
+    ///
+    /// let result = db.pop_from(key, QueueEnd::Back).await.expect("Failed to pop");
+    /// assert_eq!("SINC100", result);
     /// # })
     /// ```
-    pub async fn pop(&mut self, key: KeyType) -> Result<String, ErrorKind> {
-        tracing::trace!("get request is performed for '{}'", key.get_key());
+    pub async fn push_to(
+        &mut self,
+        key: KeyType,
+        value: String,
+        end: QueueEnd,
+    ) -> Result<(), ErrorKind> {
+        tracing::trace!("push request is performed for '{}'", key.get_key());
+
+        // Verify and validate key value
+        let key = match key {
+            KeyType::Record(key) => key,
+            _ => {
+                return Err(ErrorKind::TypeMismatch(
+                    "Parameter must be a Record type".to_string(),
+                ));
+            }
+        };
+
+        let key_routes = utilities::internal::validate_key(&key[..], &self.name)?;
+
+        // Full queue drops the item at the other end if it can be evicted, else push is rejected
+        self.validator
+            .check_queue(&format!("/{}", key_routes.join("/")), &value)?;
+        self.check_value_size(value.len())?;
+        let drop_oldest = match self.limits.queue_limit(&key) {
+            Some(max) if self.queue_length(KeyType::Record(key.clone()))? >= max => {
+                if !self.limits.is_evictable(&key) {
+                    return Err(ErrorKind::LimitExceeded(format!(
+                        "queue has reached its limit of {} items",
+                        max
+                    )));
+                }
+                true
+            }
+            _ => false,
+        };
+
+        let tracked_key = KeyType::Queue(format!("/{}", key_routes.join("/")));
+        let current = self.content_of(&tracked_key)?;
+        self.reserve(&tracked_key, current + value.len())?;
+        self.log_evictions().await;
+
+        // If queue exists then add new element into it, else create a new queue with the value
+        // For example if key is /root/ticket/open then the queue is in root->ticket table
+        let table = &key_routes[..key_routes.len() - 1];
+        let name = key_routes[key_routes.len() - 1];
+        let dropped = match drop_oldest {
+            true => self
+                .storage
+                .pop_item(table, name, end.opposite())?
+                .map(|(item, _)| item),
+            false => None,
+        };
+        self.storage
+            .push_item(table, name, value.clone(), end, true)?;
+        tracing::trace!("push request is done for '{}'", key);
+
+        let dropped_size = dropped.as_ref().map(|x| x.len()).unwrap_or(0);
+        self.track(tracked_key.clone(), current + value.len() - dropped_size)?;
+        if let Some(dropped) = dropped {
+            tracing::debug!("item at the {} of '{}' is evicted", end.opposite(), key);
+            self.tracker.evicted();
+            self.send_event(EventKind::Pop, &key, Some(dropped));
+            let queue = tracked_key.get_key().to_string();
+            let item = match end {
+                QueueEnd::Front => LogItem::PopBack(self.now(), queue),
+                QueueEnd::Back => LogItem::Pop(self.now(), queue),
+            };
+            self.evictions.push(item);
+        }
+        self.log_evictions().await;
+
+        // Send data to hook manager if active
+        self.send_hook(&key, value.clone()).await;
+
+        self.send_event(EventKind::Push, &key, Some(value));
+
+        Ok(())
+    }
+
+    /// Send a trigger to HookManager, record is not created like at `insert` but it can trigger and send some hooks out
+    ///
+    /// # Arguments
+    ///
+    /// 1. `key` - Unique key for data
+    /// 1. `value` - Value that is assigned for the key
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::pair::{KeyType, ValueType};
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// let result = db.trigger(KeyType::Record("/root/network/dns-stats".to_string()), ValueType::RecordPointer("ok".into())).await;
+    /// # })
+    /// ```
+    pub async fn trigger(&mut self, key: KeyType, value: ValueType) -> Result<(), ErrorKind> {
+        if self.hook_sender.is_none() {
+            return Err(ErrorKind::InactiveHookManager);
+        }
+
+        tracing::trace!("send trigger to hook manager about '{}' key", key.get_key());
+        if let ValueType::RecordPointer(value) = &value {
+            self.send_hook(key.get_key(), value.to_string()).await;
+            self.send_event(EventKind::Trigger, key.get_key(), Some(value.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Replace a sub-path of a JSON record. Return with the whole updated value, else with an error code.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key of JSON record
+    /// 1. `path` - Path within the document, e.g. `$.status.cpu`
+    /// 1. `value` - New value of the path
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::pair::{KeyType, RecordValue, ValueType};
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// let doc = RecordValue::parse("json", r#"{"status": {"cpu": 10}}"#).unwrap();
+    /// db.insert(KeyType::Record("/root/server1".to_string()), ValueType::RecordPointer(doc)).await.expect("Failed to insert");
+    ///
+    /// let doc = db.patch(KeyType::Record("/root/server1".to_string()), "$.status.cpu", serde_json::json!(25)).await.expect("Failed to patch");
+    /// assert_eq!(serde_json::json!(25), doc.get_path("$.status.cpu").unwrap());
+    /// # })
+    /// ```
+    pub async fn patch(
+        &mut self,
+        key: KeyType,
+        path: &str,
+        value: serde_json::Value,
+    ) -> Result<RecordValue, ErrorKind> {
+        tracing::trace!("patch request is performed for '{}'", key.get_key());
+
+        let record = self.patched(key.clone(), path, value)?;
+        self.insert(key, ValueType::RecordPointer(record.clone()))
+            .await?;
+
+        Ok(record)
+    }
+
+    /// Return with the patched value of a JSON record without writing it
+    fn patched(
+        &self,
+        key: KeyType,
+        path: &str,
+        value: serde_json::Value,
+    ) -> Result<RecordValue, ErrorKind> {
+        let mut record = match self.get(key)? {
+            ValueType::RecordPointer(record) => record,
+            _ => {
+                return Err(ErrorKind::TypeMismatch(
+                    "Specified key is not a record".to_string(),
+                ))
+            }
+        };
+
+        record.patch_path(path, value)?;
+        Ok(record)
+    }
+
+    /// Add a number to a numeric record atomically. Missing key is created with zero before the addition.
+    /// Return with the new value, else with an error code.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key of the counter
+    /// 1. `by` - Number that is added, negative number decrements the counter
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::pair::{KeyType, RecordValue};
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// db.increment(KeyType::Record("/root/builds".to_string()), 1).await.expect("Failed to increment");
+    /// let value = db.increment(KeyType::Record("/root/builds".to_string()), 5).await.expect("Failed to increment");
+    /// assert_eq!(RecordValue::Integer(6), value);
+    /// # })
+    /// ```
+    pub async fn increment(&mut self, key: KeyType, by: i64) -> Result<RecordValue, ErrorKind> {
+        tracing::trace!("increment request is performed for '{}'", key.get_key());
+
+        let new_value = self.incremented(key.clone(), by)?;
+        self.insert(key, ValueType::RecordPointer(new_value.clone()))
+            .await?;
+
+        Ok(new_value)
+    }
+
+    /// Return with the incremented value of a counter without writing it
+    fn incremented(&self, key: KeyType, by: i64) -> Result<RecordValue, ErrorKind> {
+        let current = match self.get(key) {
+            Ok(ValueType::RecordPointer(record)) => record,
+            Ok(_) => {
+                return Err(ErrorKind::TypeMismatch(
+                    "Specified key is not a record".to_string(),
+                ))
+            }
+            Err(ErrorKind::NotFound(_)) => RecordValue::Integer(0),
+            Err(e) => return Err(e),
+        };
+
+        let not_numeric = || ErrorKind::TypeMismatch("Value is not numeric".to_string());
+
+        let overflow = || ErrorKind::Conflict("Counter would overflow".to_string());
+
+        match current {
+            RecordValue::Integer(number) => number
+                .checked_add(by)
+                .map(RecordValue::Integer)
+                .ok_or_else(overflow),
+            RecordValue::Float(number) => Ok(RecordValue::Float(number + by as f64)),
+            RecordValue::String(text) => {
+                let number = text.trim().parse::<i64>().map_err(|_| not_numeric())?;
+                number
+                    .checked_add(by)
+                    .map(RecordValue::Integer)
+                    .ok_or_else(overflow)
+            }
+            _ => Err(not_numeric()),
+        }
+    }
+
+    /// Get the value of a key and return with a copy of it. If not found return with error.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key that has to be found
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::pair::{KeyType, ValueType};
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// db.insert(KeyType::Record("/root/status".to_string()), ValueType::RecordPointer("Having a great time".into())).await.expect("Failed to insert");
+    /// let value = db.get(KeyType::Record("/root/status".to_string())).expect("Key not found");
+    /// # })
+    /// ```
+    pub fn get(&self, key: KeyType) -> Result<ValueType, ErrorKind> {
+        tracing::trace!("get request is performed for '{}'", key.get_key());
+
+        // Verify and validate the key
+        let key = match key {
+            KeyType::Record(key) => key,
+            _ => {
+                return Err(ErrorKind::TypeMismatch(
+                    "Parameter must be a Record type".to_string(),
+                ));
+            }
+        };
+
+        let key_routes = utilities::internal::validate_key(&key[..], &self.name)?;
+        let find_key = KeyType::Record(key_routes[key_routes.len() - 1].to_string());
+
+        // Check that key exist in the last table
+        // For example if key is /root/test/status then it is searched in root->test table
+        // If exists return with this, else with a not found error
+        match self
+            .storage
+            .get(&key_routes[..key_routes.len() - 1], &find_key)?
+        {
+            Some(value) => {
+                tracing::trace!("get request is done for '{}'", key);
+
+                // Access statistics are needed only by LRU and LFU eviction
+                if matches!(
+                    self.limits.policy,
+                    EvictionPolicy::Lru | EvictionPolicy::Lfu
+                ) {
+                    self.tracker
+                        .touch(&KeyType::Record(format!("/{}", key_routes.join("/"))));
+                }
+
+                Ok(value.into_owned())
+            }
+            None => {
+                tracing::trace!("key '{}' does not exist", key);
+                Err(ErrorKind::NotFound(
+                    "Specified key does not exist".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Pop value from queue. If not found return with error.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key that has to be found
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::pair::{KeyType, ValueType};
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// let result = db.push(KeyType::Record("/root/ticket/open".to_string()), "SINC100".to_string()).await.expect("Failed to push");
+    /// let result = db.push(KeyType::Record("/root/ticket/open".to_string()), "SINC101".to_string()).await.expect("Failed to push");
+    ///
+    /// let ticket = db.pop(KeyType::Record("/root/ticket/open".to_string())).await.expect("Failed to pop");
+    /// assert_eq!("SINC100".to_string(), ticket);
+    ///
+    /// let ticket = db.pop(KeyType::Record("/root/ticket/open".to_string())).await.expect("Failed to pop");
+    /// assert_eq!("SINC101".to_string(), ticket);
+    ///
+    /// let ticket = db.pop(KeyType::Record("/root/ticket/open".to_string())).await;
+    /// assert_eq!(true, ticket.is_err());
+    /// # })
+    /// ```
+    pub async fn pop(&mut self, key: KeyType) -> Result<String, ErrorKind> {
+        self.pop_from(key, QueueEnd::Front).await
+    }
+
+    /// Pop value from the specified end of queue. If not found return with error.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key that has to be found
+    /// 1. `end` - Take the oldest (`QueueEnd::Front`) or the newest (`QueueEnd::Back`) item
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::{pair::KeyType, QueueEnd};
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// db.push(KeyType::Record("/root/ticket/open".to_string()), "SINC100".to_string()).await.expect("Failed to push");
+    /// db.push(KeyType::Record("/root/ticket/open".to_string()), "SINC101".to_string()).await.expect("Failed to push");
+    ///
+    /// let ticket = db.pop_from(KeyType::Record("/root/ticket/open".to_string()), QueueEnd::Back).await.expect("Failed to pop");
+    /// assert_eq!("SINC101".to_string(), ticket);
+    /// # })
+    /// ```
+    pub async fn pop_from(&mut self, key: KeyType, end: QueueEnd) -> Result<String, ErrorKind> {
+        tracing::trace!(
+            "pop request is performed for '{}' from {}",
+            key.get_key(),
+            end
+        );
+
+        // Validate and verify the key
+        let key = match key {
+            KeyType::Record(key) => key,
+            _ => {
+                return Err(ErrorKind::TypeMismatch(
+                    "Parameter must be a Record type".to_string(),
+                ));
+            }
+        };
+
+        let key_routes = utilities::internal::validate_key(&key[..], &self.name)?;
+
+        // Try to find the queue in the last table. If it exists, then remove an element from
+        // the queue, queue is removed when it becomes empty.
+        let item = self.storage.pop_item(
+            &key_routes[..key_routes.len() - 1],
+            key_routes[key_routes.len() - 1],
+            end,
+        )?;
+
+        match item {
+            Some((ret_value, emptied)) => {
+                tracing::trace!("pop request is done for '{}'", key);
+                let tracked_key = KeyType::Queue(format!("/{}", key_routes.join("/")));
+                match emptied {
+                    true => self.untrack(&tracked_key)?,
+                    false => {
+                        let current = self.content_of(&tracked_key)?;
+                        self.track(tracked_key, current.saturating_sub(ret_value.len()))?;
+                    }
+                }
+                self.send_event(EventKind::Pop, &key, Some(ret_value.clone()));
+
+                Ok(ret_value)
+            }
+            None => {
+                tracing::trace!("key '{}' does not exist", key);
+                Err(ErrorKind::NotFound(
+                    "Specified key does not exist".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Return with an item of the queue without removing it. If not found return with error.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key that has to be found
+    /// 1. `end` - Read the oldest (`QueueEnd::Front`) or the newest (`QueueEnd::Back`) item
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::{pair::KeyType, QueueEnd};
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// db.push(KeyType::Record("/root/ticket/open".to_string()), "SINC100".to_string()).await.expect("Failed to push");
+    ///
+    /// let ticket = db.peek(KeyType::Record("/root/ticket/open".to_string()), QueueEnd::Front).expect("Failed to peek");
+    /// assert_eq!("SINC100".to_string(), ticket);
+    /// assert_eq!(1, db.queue_length(KeyType::Record("/root/ticket/open".to_string())).unwrap());
+    /// # })
+    /// ```
+    pub fn peek(&self, key: KeyType, end: QueueEnd) -> Result<String, ErrorKind> {
+        tracing::trace!("peek request is performed for '{}'", key.get_key());
+
+        let key_routes = self.queue_routes(&key)?;
+        let item = self.storage.peek_item(
+            &key_routes[..key_routes.len() - 1],
+            key_routes[key_routes.len() - 1],
+            end,
+        )?;
+
+        match item {
+            Some(value) => Ok(value),
+            None => Err(ErrorKind::NotFound(
+                "Specified key does not exist".to_string(),
+            )),
+        }
+    }
+
+    /// Return with the number of items in the queue, it is zero if queue does not exist.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key of the queue
+    pub fn queue_length(&self, key: KeyType) -> Result<usize, ErrorKind> {
+        tracing::trace!("queue length request is performed for '{}'", key.get_key());
+
+        let key_routes = self.queue_routes(&key)?;
+        self.storage.queue_length(
+            &key_routes[..key_routes.len() - 1],
+            key_routes[key_routes.len() - 1],
+        )
+    }
+
+    /// Return with the identifier that the next reliable pop should use
+    pub fn next_delivery_id(&self) -> u64 {
+        NEXT_DELIVERY.load(Ordering::Relaxed)
+    }
+
+    /// Pop the first item of the queue and keep it in flight until it is acknowledged.
+    /// If it is not acknowledged before the deadline, `requeue` puts it back.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key of the queue
+    /// 1. `id` - Identifier of delivery, see `next_delivery_id`
+    /// 1. `deadline` - Time since UNIX epoch when the item is returned to the queue
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::pair::KeyType;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// db.push(KeyType::Record("/root/jobs".to_string()), "job1".to_string()).await.expect("Failed to push");
+    ///
+    /// let id = db.next_delivery_id();
+    /// let job = db.pop_reliable(KeyType::Record("/root/jobs".to_string()), id, Duration::from_secs(10)).await.expect("Failed to pop");
+    /// assert_eq!("job1".to_string(), job);
+    ///
+    /// let expired = db.expired_deliveries(Duration::from_secs(11));
+    /// assert_eq!(vec![id], expired);
+    ///
+    /// db.ack(id).expect("Failed to ack");
+    /// assert_eq!(true, db.ack(id).is_err());
+    /// # })
+    /// ```
+    pub async fn pop_reliable(
+        &mut self,
+        key: KeyType,
+        id: u64,
+        deadline: Duration,
+    ) -> Result<String, ErrorKind> {
+        let queue_key = key.get_key().to_string();
+        let value = self.pop_from(key, QueueEnd::Front).await?;
+
+        NEXT_DELIVERY.fetch_max(id + 1, Ordering::Relaxed);
+        let item = InFlight {
+            key: queue_key,
+            value: value.clone(),
+            deadline,
+        };
+        self.save_delivery(id, &item)?;
+        self.in_flight.insert(id, item);
+
+        Ok(value)
+    }
+
+    /// Save an in-flight delivery into the storage, so it survives restart without the append file
+    fn save_delivery(&mut self, id: u64, item: &InFlight) -> Result<(), ErrorKind> {
+        let value = bincode::serialize(item).map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+        self.storage.put_meta(&delivery_meta(id), value)
+    }
+
+    /// Acknowledge an item that was popped by `pop_reliable`, so it is not returned to the queue
+    ///
+    /// # Arguments
+    /// 1. `id` - Identifier of delivery
+    pub fn ack(&mut self, id: u64) -> Result<(), ErrorKind> {
+        match self.in_flight.remove(&id) {
+            Some(_) => self.storage.remove_meta(&delivery_meta(id)),
+            None => Err(ErrorKind::NotFound(
+                "Delivery does not exist or already acknowledged".to_string(),
+            )),
+        }
+    }
+
+    /// Return with the identifier of deliveries whose visibility timeout has expired
+    ///
+    /// # Arguments
+    /// 1. `now` - Current time since UNIX epoch
+    pub fn expired_deliveries(&self, now: Duration) -> Vec<u64> {
+        self.in_flight
+            .iter()
+            .filter(|(_, item)| item.deadline <= now)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// Put an unacknowledged item back to the front of its queue and return with the queue name
+    ///
+    /// # Arguments
+    /// 1. `id` - Identifier of delivery
+    pub async fn requeue(&mut self, id: u64) -> Result<String, ErrorKind> {
+        let item = match self.in_flight.remove(&id) {
+            Some(item) => item,
+            None => {
+                return Err(ErrorKind::NotFound(
+                    "Delivery does not exist or already acknowledged".to_string(),
+                ))
+            }
+        };
+        self.storage.remove_meta(&delivery_meta(id))?;
+
+        // Put it back to the front if queue still exists, else a new queue is created
+        let key_routes = utilities::internal::validate_key(&item.key, &self.name)?;
+        let requeued = self.storage.push_item(
+            &key_routes[..key_routes.len() - 1],
+            key_routes[key_routes.len() - 1],
+            item.value.clone(),
+            QueueEnd::Front,
+            false,
+        )?;
+
+        match requeued {
+            true => {
+                let tracked_key = KeyType::Queue(format!("/{}", key_routes.join("/")));
+                let current = self.content_of(&tracked_key)?;
+                self.track(tracked_key, current + item.value.len())?;
+                self.send_event(EventKind::Push, &item.key, Some(item.value.clone()))
+            }
+            false => {
+                self.push(KeyType::Record(item.key.clone()), item.value.clone())
+                    .await?
+            }
+        }
+
+        Ok(item.key)
+    }
+
+    /// Set or remove the time when a record is deleted, it is also removed when the record is deleted.
+    /// Return with `false` if the record does not exist.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key of the record
+    /// 1. `deadline` - Time since UNIX epoch when the record is deleted, `None` keeps the record
+    ///
+    /// # Example
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::pair::{KeyType, ValueType};
+    /// use std::time::Duration;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// let key = KeyType::Record("/root/session".to_string());
+    /// db.insert(key.clone(), ValueType::RecordPointer("abc".into())).await.unwrap();
+    ///
+    /// assert_eq!(true, db.expire(key.clone(), Some(Duration::from_secs(60))).unwrap());
+    /// assert_eq!(Some(Duration::from_secs(60)), db.expiration(&key).unwrap());
+    /// assert_eq!(vec!["/root/session".to_string()], db.expired_records(Duration::from_secs(61)));
+    /// # })
+    /// ```
+    pub fn expire(&mut self, key: KeyType, deadline: Option<Duration>) -> Result<bool, ErrorKind> {
+        let key_routes = utilities::internal::validate_key(key.get_key(), &self.name)?;
+        let name = KeyType::Record(key_routes[key_routes.len() - 1].to_string());
+        if self
+            .storage
+            .get(&key_routes[..key_routes.len() - 1], &name)?
+            .is_none()
+        {
+            return Ok(false);
+        }
+
+        let key = format!("/{}", key_routes.join("/"));
+        let name = format!("{}{}", EXPIRATION_META, key);
+        match deadline {
+            Some(deadline) => {
+                let value = bincode::serialize(&deadline)
+                    .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+                self.storage.put_meta(&name, value)?;
+                self.expirations.insert(key, deadline);
+            }
+            None => {
+                self.storage.remove_meta(&name)?;
+                self.expirations.remove(&key);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Remove the expiration of a deleted record from the storage, failure is only logged,
+    /// because the record is already deleted
+    fn forget_expiration(&mut self, key: &str) {
+        let name = format!("{}{}", EXPIRATION_META, key);
+        if let Err(e) = self.storage.remove_meta(&name) {
+            tracing::error!("failed to remove expiration of '{}': {}", key, e);
+        }
+    }
+
+    /// Return with the time since UNIX epoch when the record is deleted, `None` if it is kept
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key of the record
+    pub fn expiration(&self, key: &KeyType) -> Result<Option<Duration>, ErrorKind> {
+        let key_routes = utilities::internal::validate_key(key.get_key(), &self.name)?;
+        Ok(self
+            .expirations
+            .get(&format!("/{}", key_routes.join("/")))
+            .copied())
+    }
+
+    /// Return with the records whose expiration time has passed
+    ///
+    /// # Arguments
+    /// 1. `now` - Current time since UNIX epoch
+    pub fn expired_records(&self, now: Duration) -> Vec<String> {
+        self.expirations
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Validate the key of a queue and return with its route, queue is the last item of the route
+    fn queue_routes<'a>(&self, key: &'a KeyType) -> Result<Vec<&'a str>, ErrorKind> {
+        match key {
+            KeyType::Record(key) => utilities::internal::validate_key(key, &self.name),
+            _ => Err(ErrorKind::TypeMismatch(
+                "Parameter must be a Record type".to_string(),
+            )),
+        }
+    }
+
+    /// Set a field of hash, hash is created if it does not exist.
+    /// Return with `true` if field was not in the hash before.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key of the hash
+    /// 1. `field` - Name of the field
+    /// 1. `value` - Value of the field
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::pair::KeyType;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// let key = KeyType::Record("/root/servers/vps01".to_string());
+    /// db.hash_set(key.clone(), "os".to_string(), "debian".to_string()).await.expect("Failed to set field");
+    /// db.hash_set(key.clone(), "cpu".to_string(), "4".to_string()).await.expect("Failed to set field");
+    ///
+    /// assert_eq!("debian".to_string(), db.hash_get(key.clone(), "os").expect("Failed to get field"));
+    /// assert_eq!(2, db.hash_get_all(key.clone()).expect("Failed to get hash").len());
+    ///
+    /// db.hash_delete(key.clone(), "os").await.expect("Failed to delete field");
+    /// assert_eq!(true, db.hash_get(key, "os").is_err());
+    /// # })
+    /// ```
+    pub async fn hash_set(
+        &mut self,
+        key: KeyType,
+        field: String,
+        value: String,
+    ) -> Result<bool, ErrorKind> {
+        tracing::trace!("hash set request is performed for '{}'", key.get_key());
+
+        let key = Self::record_key(key)?;
+        let key_routes = utilities::internal::validate_key(&key[..], &self.name)?;
+
+        let tracked_key = KeyType::Hash(format!("/{}", key_routes.join("/")));
+        self.validator.check_item(tracked_key.get_key(), &value)?;
+        self.check_value_size(value.len())?;
+        let current = self.content_of(&tracked_key)?;
+        self.reserve(&tracked_key, current + field.len() + value.len())?;
+        self.log_evictions().await;
+
+        let mut old = None;
+        self.storage.update(
+            &key_routes[..key_routes.len() - 1],
+            KeyType::Hash(key_routes[key_routes.len() - 1].to_string()),
+            &mut |item| {
+                let hash = item.get_or_insert_with(|| ValueType::HashPointer(BTreeMap::new()));
+                match hash {
+                    ValueType::HashPointer(hash) => {
+                        old = hash.insert(field.clone(), value.clone());
+                        Ok(())
+                    }
+                    _ => Err(ErrorKind::InternalError(
+                        "Hash pointer was expected".to_string(),
+                    )),
+                }
+            },
+        )?;
+
+        let added = old.is_none();
+        let replaced = old.map(|x| field.len() + x.len()).unwrap_or(0);
+        self.track(tracked_key, current + field.len() + value.len() - replaced)?;
+
+        let change = serde_json::json!({ field: value }).to_string();
+        self.send_hook(&key, change.clone()).await;
+        self.send_event(EventKind::HashSet, &key, Some(change));
+
+        Ok(added)
+    }
+
+    /// Return with a field of hash. If hash or field is not found return with error.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key of the hash
+    /// 1. `field` - Name of the field
+    pub fn hash_get(&self, key: KeyType, field: &str) -> Result<String, ErrorKind> {
+        tracing::trace!("hash get request is performed for '{}'", key.get_key());
+
+        match self.hash_get_all(key)?.remove(field) {
+            Some(value) => Ok(value),
+            None => Err(ErrorKind::NotFound("Field does not exist".to_string())),
+        }
+    }
+
+    /// Return with all fields of hash. If not found return with error.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key of the hash
+    pub fn hash_get_all(&self, key: KeyType) -> Result<BTreeMap<String, String>, ErrorKind> {
+        tracing::trace!("hash get all request is performed for '{}'", key.get_key());
+
+        let key = Self::record_key(key)?;
+        match self.find_value(&key, KeyType::Hash)?.map(Cow::into_owned) {
+            Some(ValueType::HashPointer(hash)) => Ok(hash),
+            _ => Err(ErrorKind::NotFound(
+                "Specified key does not exist".to_string(),
+            )),
+        }
+    }
+
+    /// Remove a field from hash, hash is removed when its last field is removed.
+    /// If hash or field is not found return with error.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key of the hash
+    /// 1. `field` - Name of the field
+    pub async fn hash_delete(&mut self, key: KeyType, field: &str) -> Result<(), ErrorKind> {
+        tracing::trace!("hash delete request is performed for '{}'", key.get_key());
+
+        let key = Self::record_key(key)?;
+        let key_routes = utilities::internal::validate_key(&key[..], &self.name)?;
+
+        let mut result = Err(ErrorKind::NotFound(
+            "Specified key does not exist".to_string(),
+        ));
+        let mut emptied = false;
+        self.storage.update(
+            &key_routes[..key_routes.len() - 1],
+            KeyType::Hash(key_routes[key_routes.len() - 1].to_string()),
+            &mut |item| {
+                if let Some(ValueType::HashPointer(hash)) = item {
+                    result = match hash.remove(field) {
+                        Some(value) => Ok(field.len() + value.len()),
+                        None => Err(ErrorKind::NotFound("Field does not exist".to_string())),
+                    };
+
+                    if hash.is_empty() {
+                        *item = None;
+                        emptied = true;
+                    }
+                }
+                Ok(())
+            },
+        )?;
+        let removed = result?;
+
+        let tracked_key = KeyType::Hash(format!("/{}", key_routes.join("/")));
+        match emptied {
+            true => self.untrack(&tracked_key)?,
+            false => {
+                let current = self.content_of(&tracked_key)?;
+                self.track(tracked_key, current.saturating_sub(removed))?;
+            }
+        }
+
+        self.send_event(EventKind::HashDelete, &key, Some(field.to_string()));
+
+        Ok(())
+    }
+
+    /// Add a member to set, set is created if it does not exist.
+    /// Return with `true` if member was not in the set before.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key of the set
+    /// 1. `member` - Member to be added
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::pair::KeyType;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// let key = KeyType::Record("/root/online".to_string());
+    /// assert_eq!(true, db.set_add(key.clone(), "vps01".to_string()).await.unwrap());
+    /// assert_eq!(false, db.set_add(key.clone(), "vps01".to_string()).await.unwrap());
+    ///
+    /// assert_eq!(true, db.set_is_member(key.clone(), "vps01").unwrap());
+    /// assert_eq!(true, db.set_remove(key.clone(), "vps01").await.unwrap());
+    /// assert_eq!(false, db.set_is_member(key, "vps01").unwrap());
+    /// # })
+    /// ```
+    pub async fn set_add(&mut self, key: KeyType, member: String) -> Result<bool, ErrorKind> {
+        tracing::trace!("set add request is performed for '{}'", key.get_key());
+
+        let key = Self::record_key(key)?;
+        let key_routes = utilities::internal::validate_key(&key[..], &self.name)?;
+
+        let tracked_key = KeyType::Set(format!("/{}", key_routes.join("/")));
+        self.validator.check_item(tracked_key.get_key(), &member)?;
+        self.check_value_size(member.len())?;
+        let current = self.content_of(&tracked_key)?;
+        self.reserve(&tracked_key, current + member.len())?;
+        self.log_evictions().await;
+
+        let mut added = false;
+        self.storage.update(
+            &key_routes[..key_routes.len() - 1],
+            KeyType::Set(key_routes[key_routes.len() - 1].to_string()),
+            &mut |item| {
+                let set = item.get_or_insert_with(|| ValueType::SetPointer(BTreeSet::new()));
+                match set {
+                    ValueType::SetPointer(set) => {
+                        added = set.insert(member.clone());
+                        Ok(())
+                    }
+                    _ => Err(ErrorKind::InternalError(
+                        "Set pointer was expected".to_string(),
+                    )),
+                }
+            },
+        )?;
+
+        if added {
+            self.track(tracked_key, current + member.len())?;
+            self.send_hook(&key, member.clone()).await;
+            self.send_event(EventKind::SetAdd, &key, Some(member));
+        }
+
+        Ok(added)
+    }
+
+    /// Remove a member from set, set is removed when its last member is removed.
+    /// Return with `true` if member was in the set.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key of the set
+    /// 1. `member` - Member to be removed
+    pub async fn set_remove(&mut self, key: KeyType, member: &str) -> Result<bool, ErrorKind> {
+        tracing::trace!("set remove request is performed for '{}'", key.get_key());
+
+        let key = Self::record_key(key)?;
+        let key_routes = utilities::internal::validate_key(&key[..], &self.name)?;
+
+        let mut removed = false;
+        let mut emptied = false;
+        self.storage.update(
+            &key_routes[..key_routes.len() - 1],
+            KeyType::Set(key_routes[key_routes.len() - 1].to_string()),
+            &mut |item| {
+                if let Some(ValueType::SetPointer(set)) = item {
+                    removed = set.remove(member);
+                    if set.is_empty() {
+                        *item = None;
+                        emptied = true;
+                    }
+                }
+                Ok(())
+            },
+        )?;
+
+        if removed {
+            let tracked_key = KeyType::Set(format!("/{}", key_routes.join("/")));
+            match emptied {
+                true => self.untrack(&tracked_key)?,
+                false => {
+                    let current = self.content_of(&tracked_key)?;
+                    self.track(tracked_key, current.saturating_sub(member.len()))?;
+                }
+            }
+            self.send_event(EventKind::SetRemove, &key, Some(member.to_string()));
+        }
+
+        Ok(removed)
+    }
+
+    /// Return with the members of set. If not found return with error.
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key of the set
+    pub fn set_members(&self, key: KeyType) -> Result<BTreeSet<String>, ErrorKind> {
+        tracing::trace!("set members request is performed for '{}'", key.get_key());
+
+        let key = Self::record_key(key)?;
+        match self.find_value(&key, KeyType::Set)?.map(Cow::into_owned) {
+            Some(ValueType::SetPointer(set)) => Ok(set),
+            _ => Err(ErrorKind::NotFound(
+                "Specified key does not exist".to_string(),
+            )),
+        }
+    }
+
+    /// Tells that member is in the set, it is `false` if set does not exist
+    ///
+    /// # Arguments
+    /// 1. `key` - Unique key of the set
+    /// 1. `member` - Member to be checked
+    pub fn set_is_member(&self, key: KeyType, member: &str) -> Result<bool, ErrorKind> {
+        tracing::trace!("set is member request is performed for '{}'", key.get_key());
+
+        let key = Self::record_key(key)?;
+        match self.find_value(&key, KeyType::Set)?.as_deref() {
+            Some(ValueType::SetPointer(set)) => Ok(set.contains(member)),
+            _ => Ok(false),
+        }
+    }
+
+    /// Export records, queues, hashes and sets under a table. Hooks are not part of the database,
+    /// so the snapshot is returned without them.
+    ///
+    /// # Arguments
+    /// 1. `key_prefix` - Path of the table, e.g. `/root` for the whole database
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::pair::{KeyType, ValueType};
+    /// use onlyati_datastore::datastore::enums::snapshot::ImportPolicy;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// db.insert(KeyType::Record("/root/status/vps01".to_string()), ValueType::RecordPointer("ok".into())).await.expect("Failed to insert");
+    /// db.push(KeyType::Record("/root/jobs".to_string()), "job1".to_string()).await.expect("Failed to push");
+    ///
+    /// let snapshot = db.export(KeyType::Record("/root".to_string())).expect("Failed to export");
+    /// assert_eq!(1, snapshot.record.len());
+    /// assert_eq!(1, snapshot.queue.len());
+    ///
+    /// let mut other = Database::new("root".to_string()).unwrap();
+    /// let summary = other.import(&snapshot, ImportPolicy::Merge).await.expect("Failed to import");
+    /// assert_eq!(2, summary.imported);
+    /// # })
+    /// ```
+    pub fn export(&self, key_prefix: KeyType) -> Result<Snapshot, ErrorKind> {
+        tracing::trace!("export request is performed for '{}'", key_prefix.get_key());
+
+        let key_prefix = Self::record_key(key_prefix)?;
+        let key_routes = utilities::internal::validate_key(&key_prefix[..], &self.name)?;
+        let prefix = format!("/{}", key_routes.join("/"));
+        let entries = match self.collect_ordered(&key_routes, |value| value.clone())? {
+            Some(entries) => entries,
+            None => {
+                return Err(ErrorKind::NotFound(
+                    "Specified route does not exist".to_string(),
+                ))
+            }
+        };
+
+        let mut snapshot = Snapshot::default();
+        for (path, value) in entries {
+            let key = format!("{}/{}", prefix, Self::join_path(&path));
+            match value {
+                ValueType::RecordPointer(record) => snapshot.record.push(SnapshotRecord {
+                    key,
+                    value: record.to_string(),
+                    r#type: record.get_type().to_string(),
+                }),
+                ValueType::QueuePointer(queue) => snapshot.queue.push(SnapshotQueue {
+                    key,
+                    values: queue.into_iter().collect(),
+                }),
+                ValueType::HashPointer(hash) => {
+                    snapshot.hash.push(SnapshotHash { key, fields: hash })
+                }
+                ValueType::SetPointer(set) => snapshot.set.push(SnapshotSet {
+                    key,
+                    members: set.into_iter().collect(),
+                }),
+                ValueType::TablePointer(_) => (),
+            }
+        }
+
+        tracing::trace!("export request is done for '{}'", prefix);
+        Ok(snapshot)
+    }
+
+    /// Import records, queues, hashes and sets of a snapshot, hooks of the snapshot are ignored.
+    /// Every key and value is verified before the first change, so an invalid snapshot does not change anything.
+    ///
+    /// # Arguments
+    /// 1. `snapshot` - Content to be imported
+    /// 1. `policy` - What happens with the keys that already exist
+    pub async fn import(
+        &mut self,
+        snapshot: &Snapshot,
+        policy: ImportPolicy,
+    ) -> Result<ImportSummary, ErrorKind> {
+        tracing::trace!("import request is performed with '{}' policy", policy);
+
+        let mut records = Vec::with_capacity(snapshot.record.len());
+        for record in &snapshot.record {
+            let key_routes = utilities::internal::validate_key(&record.key, &self.name)?;
+            let value = RecordValue::parse(&record.r#type, &record.value)?;
+            self.validator
+                .check_record(&format!("/{}", key_routes.join("/")), &value)?;
+            records.push((record.key.clone(), value));
+        }
+
+        for queue in &snapshot.queue {
+            let key_routes = utilities::internal::validate_key(&queue.key, &self.name)?;
+            for value in &queue.values {
+                self.validator
+                    .check_queue(&format!("/{}", key_routes.join("/")), value)?;
+            }
+        }
+
+        for hash in &snapshot.hash {
+            let key_routes = utilities::internal::validate_key(&hash.key, &self.name)?;
+            for value in hash.fields.values() {
+                self.validator
+                    .check_item(&format!("/{}", key_routes.join("/")), value)?;
+            }
+        }
+
+        for set in &snapshot.set {
+            let key_routes = utilities::internal::validate_key(&set.key, &self.name)?;
+            for member in &set.members {
+                self.validator
+                    .check_item(&format!("/{}", key_routes.join("/")), member)?;
+            }
+        }
+
+        let keys = snapshot
+            .queue
+            .iter()
+            .map(|x| &x.key)
+            .chain(snapshot.hash.iter().map(|x| &x.key))
+            .chain(snapshot.set.iter().map(|x| &x.key));
+        for key in keys {
+            utilities::internal::validate_key(key, &self.name)?;
+        }
+
+        let mut summary = ImportSummary::default();
+
+        for (key, value) in records {
+            let computed = self.computed.iter().any(|x| x.key == key);
+            if computed || !self.prepare_import(&key, KeyType::Record, policy)? {
+                summary.skipped += 1;
+                continue;
+            }
 
-        // Validate and verify the key
-        let key = match key {
-            KeyType::Record(key) => key,
-            _ => {
-                return Err(ErrorKind::InvalidKey(
-                    "Parameter must be a Record type".to_string(),
-                ));
+            self.insert(KeyType::Record(key), ValueType::RecordPointer(value))
+                .await?;
+            summary.imported += 1;
+        }
+
+        for queue in &snapshot.queue {
+            if !self.prepare_import(&queue.key, KeyType::Queue, policy)? {
+                summary.skipped += 1;
+                continue;
             }
-        };
 
-        let key_routes = utilities::internal::validate_key(&key[..], &self.name)?;
+            for value in &queue.values {
+                self.push(KeyType::Record(queue.key.clone()), value.clone())
+                    .await?;
+            }
+            summary.imported += 1;
+        }
 
-        // Try to find the last table where the key can be found
-        // For example if key is /root/test/status then root->test table must be found, else return with a not found error
-        let table = match utilities::internal::find_table_mut(
-            &mut self.root,
-            key_routes[..key_routes.len() - 1].to_vec(),
-        ) {
-            Some(table) => table,
-            None => {
-                tracing::trace!("key '{}' does not exist", key);
-                return Err(ErrorKind::InvalidKey(
-                    "Specified key does not exist".to_string(),
-                ));
+        for hash in &snapshot.hash {
+            if !self.prepare_import(&hash.key, KeyType::Hash, policy)? {
+                summary.skipped += 1;
+                continue;
             }
-        };
 
-        let find_key = KeyType::Queue(key_routes[key_routes.len() - 1].to_string());
+            for (field, value) in &hash.fields {
+                self.hash_set(
+                    KeyType::Record(hash.key.clone()),
+                    field.clone(),
+                    value.clone(),
+                )
+                .await?;
+            }
+            summary.imported += 1;
+        }
 
-        // Try to find the key in the last table. If key exists, then remove the first element from
-        // the queue, then return with this. Else return with a not found error.
-        match table.get_mut(&find_key) {
-            Some(value) => {
-                tracing::trace!("get request is done for '{}'", key);
-                match value {
-                    ValueType::QueuePointer(queue) => {
-                        let ret_value = match queue.pop_front() {
-                            Some(v) => v,
-                            None => {
-                                tracing::error!("queue was not cleanup before, try now");
-                                table.remove(&find_key);
-                                return Err(ErrorKind::InvalidKey(
-                                    "Specified key does not exist".to_string(),
-                                ));
-                            }
-                        };
-
-                        if queue.is_empty() {
-                            table.remove(&find_key);
-                        }
-
-                        Ok(ret_value)
-                    }
-                    _ => {
-                        tracing::error!("this should not be happen, search was to a Queue but something else was found");
-                        Err(ErrorKind::InvalidKey(
-                            "Specified key does not exist".to_string(),
-                        ))
-                    }
-                }
+        for set in &snapshot.set {
+            if !self.prepare_import(&set.key, KeyType::Set, policy)? {
+                summary.skipped += 1;
+                continue;
             }
-            None => {
-                tracing::trace!("key '{}' does not exist", key);
-                Err(ErrorKind::InvalidKey(
-                    "Specified key does not exist".to_string(),
-                ))
+
+            for member in &set.members {
+                self.set_add(KeyType::Record(set.key.clone()), member.clone())
+                    .await?;
+            }
+            summary.imported += 1;
+        }
+
+        tracing::trace!(
+            "import request is done, {} imported and {} skipped",
+            summary.imported,
+            summary.skipped
+        );
+        Ok(summary)
+    }
+
+    /// Handle the existing value before import according to the policy.
+    /// Return with `false` if the value must be skipped.
+    fn prepare_import(
+        &mut self,
+        key: &str,
+        kind: fn(String) -> KeyType,
+        policy: ImportPolicy,
+    ) -> Result<bool, ErrorKind> {
+        if self.find_value(key, kind)?.is_none() {
+            return Ok(true);
+        }
+
+        match policy {
+            ImportPolicy::Skip => Ok(false),
+            ImportPolicy::Merge => Ok(true),
+            ImportPolicy::Overwrite => {
+                let key_routes = utilities::internal::validate_key(key, &self.name)?;
+                self.storage.remove(
+                    &key_routes[..key_routes.len() - 1],
+                    &kind(key_routes[key_routes.len() - 1].to_string()),
+                )?;
+                self.untrack(&kind(format!("/{}", key_routes.join("/"))))?;
+
+                self.send_event(EventKind::Delete, key, None);
+                Ok(true)
+            }
+        }
+    }
+
+    /// Unwrap the key string, actions on hashes and sets are requested by `KeyType::Record`
+    fn record_key(key: KeyType) -> Result<String, ErrorKind> {
+        match key {
+            KeyType::Record(key) => Ok(key),
+            _ => Err(ErrorKind::TypeMismatch(
+                "Parameter must be a Record type".to_string(),
+            )),
+        }
+    }
+
+    /// Find value by its key, `kind` tells which key type is searched
+    fn find_value(
+        &self,
+        key: &str,
+        kind: fn(String) -> KeyType,
+    ) -> Result<Option<Cow<'_, ValueType>>, ErrorKind> {
+        let key_routes = utilities::internal::validate_key(key, &self.name)?;
+        self.storage.get(
+            &key_routes[..key_routes.len() - 1],
+            &kind(key_routes[key_routes.len() - 1].to_string()),
+        )
+    }
+
+    /// Send change to hook manager if it is active
+    async fn send_hook(&mut self, key: &str, value: String) {
+        if let Some(sender) = &self.hook_sender {
+            tracing::trace!("send alert to hook manager about '{}' key", key);
+            let action = HookManagerAction::Send(key.to_string(), value);
+
+            match &mut self.outbox {
+                Some(outbox) => outbox.hooks.push(action),
+                None => sender
+                    .send(action)
+                    .await
+                    .unwrap_or_else(|e| tracing::error!("Error during send: {}", e)),
             }
         }
     }
@@ -512,7 +2390,8 @@ impl Database {
     ///
     /// # Arguments
     /// 1. `key_prefix` - Path where the keys has to be collected
-    /// 1. `level` - Need all inner level (`ListType::All`) or just current level (`ListType::OneLevel`)
+    /// 1. `level` - Need all inner level (`ListType::All`) or just current level (`ListType::OneLevel`),
+    ///    the current level contains its tables too
     ///
     /// # Example
     ///
@@ -523,16 +2402,16 @@ impl Database {
     /// # tokio_test::block_on(async {
     /// let mut db = Database::new("root".to_string()).unwrap();
     ///
-    /// db.insert(KeyType::Record("/root/status/sub1".to_string()), ValueType::RecordPointer("PING OK".to_string())).await.expect("Failed to insert");
-    /// db.insert(KeyType::Record("/root/status/sub2".to_string()), ValueType::RecordPointer("PING NOK".to_string())).await.expect("Failed to insert");
-    /// db.insert(KeyType::Record("/root/status/sub3".to_string()), ValueType::RecordPointer("PING OK".to_string())).await.expect("Failed to insert");
+    /// db.insert(KeyType::Record("/root/status/sub1".to_string()), ValueType::RecordPointer("PING OK".into())).await.expect("Failed to insert");
+    /// db.insert(KeyType::Record("/root/status/sub2".to_string()), ValueType::RecordPointer("PING NOK".into())).await.expect("Failed to insert");
+    /// db.insert(KeyType::Record("/root/status/sub3".to_string()), ValueType::RecordPointer("PING OK".into())).await.expect("Failed to insert");
     /// let list = db.list_keys(KeyType::Record("/root/status".to_string()), ListType::All).expect("Key not found");
     ///
     /// println!("{:?}", list);
     /// # })
     /// ```
     pub fn list_keys(
-        &mut self,
+        &self,
         key_prefix: KeyType,
         level: ListType,
     ) -> Result<Vec<KeyType>, ErrorKind> {
@@ -543,7 +2422,7 @@ impl Database {
         let key_prefix = match key_prefix {
             KeyType::Record(key) => key,
             _ => {
-                return Err(ErrorKind::InvalidKey(
+                return Err(ErrorKind::TypeMismatch(
                     "Parameter must be a Record type".to_string(),
                 ));
             }
@@ -551,24 +2430,129 @@ impl Database {
 
         // Find the base table
         let key_routes = utilities::internal::validate_key(&key_prefix[..], &self.name)?;
-        let table = match utilities::internal::find_table(&self.root, key_routes) {
-            Some(table) => table,
+        let entries = match self.collect_ordered(&key_routes, |_| ())? {
+            Some(entries) => entries,
             None => {
                 tracing::trace!("get request is failed due to no '{}' key exist", key_prefix);
-                return Err(ErrorKind::InvalidKey(
+                return Err(ErrorKind::NotFound(
                     "Specified route does not exist".to_string(),
                 ));
             }
         };
 
-        // Fetch the keys from the spcified table and return with them
-        let result = utilities::internal::display_tables(table, &key_prefix, &level)?;
+        // Fetch the keys from the spcified table and return with them, tables of the current level
+        // are listed with `ListType::OneLevel` but their content is not
+        let result = entries
+            .into_iter()
+            .filter(|(path, _)| match level {
+                ListType::OneLevel => path.len() == 1,
+                ListType::All => !path[path.len() - 1].is_table(),
+            })
+            .map(|(path, _)| {
+                let key = format!("{}/{}", key_prefix, Self::join_path(&path));
+                path[path.len() - 1].with_key(key)
+            })
+            .collect();
 
         tracing::trace!("list keys request is done for '{}'", key_prefix);
         Ok(result)
     }
 
+    /// Return with the metadata of key or table. A record, a queue, a hash, a set and a table can have
+    /// the same key, every existing one is returned. Metadata is maintained during the writes,
+    /// so the tree is not walked.
+    ///
+    /// # Arguments
+    /// 1. `key` - Full key of a key or a table, e.g. `/root/status`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use onlyati_datastore::datastore::{stat::StatKind, Database};
+    /// use onlyati_datastore::datastore::enums::pair::{KeyType, ValueType};
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// db.insert(KeyType::Record("/root/status/sub1".to_string()), ValueType::RecordPointer("PING OK".into())).await.expect("Failed to insert");
+    /// db.push(KeyType::Record("/root/status/jobs".to_string()), "job1".to_string()).await.expect("Failed to push");
+    ///
+    /// let stats = db.stat("/root/status").expect("Key not found");
+    /// assert_eq!(StatKind::Table, stats[0].kind);
+    /// assert_eq!(2, stats[0].table.as_ref().unwrap().children);
+    ///
+    /// let stats = db.stat("/root/status/jobs").expect("Key not found");
+    /// assert_eq!(StatKind::Queue, stats[0].kind);
+    /// assert_eq!(Some(1), stats[0].length);
+    /// # })
+    /// ```
+    pub fn stat(&self, key: &str) -> Result<Vec<Stat>, ErrorKind> {
+        tracing::trace!("stat request is performed for '{}'", key);
+        let key_routes = utilities::internal::validate_key(key, &self.name)?;
+        let full_key = format!("/{}", key_routes.join("/"));
+        let mut stats = Vec::new();
+
+        let kinds = [
+            (KeyType::Record as fn(String) -> KeyType, StatKind::Record),
+            (KeyType::Queue, StatKind::Queue),
+            (KeyType::Hash, StatKind::Hash),
+            (KeyType::Set, StatKind::Set),
+        ];
+        for (kind, stat_kind) in kinds {
+            let key_stats = self
+                .tracker
+                .key_stats(self.storage.as_ref(), &kind(full_key.clone()))?;
+            let key_stats = match key_stats {
+                Some(key_stats) => key_stats,
+                None => continue,
+            };
+
+            let length = match self.find_value(&full_key, kind)?.as_deref() {
+                Some(ValueType::QueuePointer(queue)) => Some(queue.len()),
+                Some(ValueType::HashPointer(hash)) => Some(hash.len()),
+                Some(ValueType::SetPointer(set)) => Some(set.len()),
+                _ => None,
+            };
+
+            stats.push(Stat {
+                key: full_key.clone(),
+                kind: stat_kind,
+                size: key_stats.content,
+                length,
+                created: key_stats.created_at,
+                modified: key_stats.modified_at,
+                table: None,
+            });
+        }
+
+        // Root table exists even if everything has been deleted from it
+        match self.tracker.table_stat(&full_key) {
+            Some(table) => stats.push(table),
+            None if key_routes.len() == 1 => stats.push(Stat {
+                key: full_key.clone(),
+                kind: StatKind::Table,
+                size: 0,
+                length: None,
+                created: None,
+                modified: None,
+                table: Some(Default::default()),
+            }),
+            None => (),
+        }
+
+        if stats.is_empty() {
+            tracing::trace!("key '{}' does not exist", full_key);
+            return Err(ErrorKind::NotFound(
+                "Specified key does not exist".to_string(),
+            ));
+        }
+
+        tracing::trace!("stat request is done for '{}'", full_key);
+        Ok(stats)
+    }
+
     /// Delete specific key, return with nothig if successful, else with error message.
+    /// Only the kind of the key is deleted, e.g. a queue is deleted by `KeyType::Queue`.
     ///
     /// # Arguments
     /// 1. `key` - Unique key that has to be deleted
@@ -583,7 +2567,7 @@ impl Database {
     /// let mut db = Database::new("root".to_string()).unwrap();
     ///
     /// let key = KeyType::Record("/root/status".to_string());
-    /// db.insert(key.clone(), ValueType::RecordPointer("Having a great time".to_string())).await.expect("Failed to insert");
+    /// db.insert(key.clone(), ValueType::RecordPointer("Having a great time".into())).await.expect("Failed to insert");
     /// db.delete_key(key).await.expect("Could not delete the key");
     /// # })
     /// ```
@@ -593,37 +2577,28 @@ impl Database {
         // Validate and verify the key
         if let KeyType::Table(_) = key {
             tracing::trace!("delete request is failed due to wrong key type");
-            return Err(ErrorKind::InvalidKey(
+            return Err(ErrorKind::TypeMismatch(
                 "Parameter must be a Record type".to_string(),
             ));
         }
 
         let key_routes = utilities::internal::validate_key(key.get_key(), &self.name)?;
+        if let KeyType::Record(_) = key {
+            self.check_computed(&format!("/{}", key_routes.join("/")))?;
+        }
 
-        // Try to find the last table where the key can be found
-        // For example if key is /root/test/status then root->test table must be found, else return with a not found error
-        let table = match utilities::internal::find_table_mut(
-            &mut self.root,
-            key_routes[..key_routes.len() - 1].to_vec(),
-        ) {
-            Some(table) => table,
-            None => {
-                tracing::trace!(
-                    "delete request is failed because no '{}' key exist",
-                    key.get_key()
-                );
-                return Err(ErrorKind::InvalidKey(
-                    "Specified key does not exist".to_string(),
-                ));
-            }
-        };
-
-        let delete_key = KeyType::Record(key_routes[key_routes.len() - 1].to_string());
+        let delete_key = key.with_key(key_routes[key_routes.len() - 1].to_string());
 
-        // Try to delete the key, if it would fail, e.g.: does not exist, then return with error
-        match table.remove(&delete_key) {
+        // Try to delete the key from the last table, if it would fail, e.g.: does not exist, then return with error
+        // For example if key is /root/test/status then it is deleted from root->test table
+        match self
+            .storage
+            .remove(&key_routes[..key_routes.len() - 1], &delete_key)?
+        {
             Some(_) => {
                 tracing::trace!("delete request is done for '{}'", key.get_key());
+                self.untrack(&key.with_key(format!("/{}", key_routes.join("/"))))?;
+                self.send_event(EventKind::Delete, key.get_key(), None);
 
                 Ok(())
             }
@@ -632,7 +2607,7 @@ impl Database {
                     "delete request is failed because no '{}' key exist",
                     key.get_key()
                 );
-                Err(ErrorKind::InvalidKey(
+                Err(ErrorKind::NotFound(
                     "Specified key does not exist".to_string(),
                 ))
             }
@@ -653,10 +2628,10 @@ impl Database {
     /// # tokio_test::block_on(async {
     /// let mut db = Database::new("root".to_string()).unwrap();
     ///
-    /// db.insert(KeyType::Record("/root/status/sub1".to_string()), ValueType::RecordPointer("PING OK".to_string())).await.expect("Failed to insert");
-    /// db.insert(KeyType::Record("/root/status/sub2".to_string()), ValueType::RecordPointer("PING NOK".to_string())).await.expect("Failed to insert");
-    /// db.insert(KeyType::Record("/root/status/sub3".to_string()), ValueType::RecordPointer("PING OK".to_string())).await.expect("Failed to insert");
-    /// db.insert(KeyType::Record("/root/node_name".to_string()), ValueType::RecordPointer("vps01".to_string())).await.expect("Failed to insert");
+    /// db.insert(KeyType::Record("/root/status/sub1".to_string()), ValueType::RecordPointer("PING OK".into())).await.expect("Failed to insert");
+    /// db.insert(KeyType::Record("/root/status/sub2".to_string()), ValueType::RecordPointer("PING NOK".into())).await.expect("Failed to insert");
+    /// db.insert(KeyType::Record("/root/status/sub3".to_string()), ValueType::RecordPointer("PING OK".into())).await.expect("Failed to insert");
+    /// db.insert(KeyType::Record("/root/node_name".to_string()), ValueType::RecordPointer("vps01".into())).await.expect("Failed to insert");
     ///
     /// db.delete_table(KeyType::Table("/root/status".to_string())).await.expect("Failed to drop from status table");
     ///
@@ -671,49 +2646,447 @@ impl Database {
         // Validate and verify the key
         if let KeyType::Record(_) = key {
             tracing::trace!("delete table request is failed due to wrong key type is specified");
-            return Err(ErrorKind::InvalidKey(
+            return Err(ErrorKind::TypeMismatch(
                 "Parameter must be a Table type".to_string(),
             ));
         }
 
         let key_routes = utilities::internal::validate_key(key.get_key(), &self.name)?;
 
-        // Try to find the last table where the key can be found
-        // For example if key is /root/test/status then root->test table must be found, else return with a not found error
-        let table = match utilities::internal::find_table_mut(
-            &mut self.root,
-            key_routes[..key_routes.len() - 1].to_vec(),
-        ) {
-            Some(table) => table,
-            None => {
+        // Try to delete the table with everything in it, if it would fail, e.g.: does not exist, then return with error
+        match self.storage.remove_table(&key_routes)? {
+            true => {
+                tracing::trace!("delete table request is performed for '{}'", key.get_key());
+                self.untrack_table(&format!("/{}", key_routes.join("/")))?;
+                self.send_event(EventKind::Delete, key.get_key(), None);
+
+                Ok(())
+            }
+            false => {
                 tracing::trace!(
                     "delete table request is failed because no '{}' key exist",
                     key.get_key()
                 );
-                return Err(ErrorKind::InvalidKey(
+                Err(ErrorKind::NotFound(
                     "Specified key does not exist".to_string(),
-                ));
+                ))
+            }
+        }
+    }
+
+    /// Move a record, a queue, a hash, a set or a whole table to another key in the same database.
+    /// Table is moved with everything under it. Everything is verified before the first change,
+    /// so if the request fails, e.g. destination exists, nothing is changed.
+    ///
+    /// # Arguments
+    /// 1. `source` - Key that has to be moved, its type tells what is moved, e.g. `KeyType::Table("/root/status")`
+    /// 1. `destination` - New key, it must not exist
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::pair::{KeyType, ValueType};
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// db.insert(KeyType::Record("/root/status/vps01".to_string()), ValueType::RecordPointer("ok".into())).await.expect("Failed to insert");
+    /// db.move_key(KeyType::Table("/root/status".to_string()), "/root/infra/status").await.expect("Failed to move");
+    ///
+    /// assert!(db.get(KeyType::Record("/root/infra/status/vps01".to_string())).is_ok());
+    /// assert!(db.get(KeyType::Record("/root/status/vps01".to_string())).is_err());
+    /// # })
+    /// ```
+    pub async fn move_key(&mut self, source: KeyType, destination: &str) -> Result<(), ErrorKind> {
+        self.relocate(source, destination, true, false).await
+    }
+
+    /// Copy a record, a queue, a hash, a set or a whole table to another key in the same database.
+    /// Table is copied with everything under it. Everything is verified before the first change,
+    /// so if the request fails, e.g. destination exists, nothing is changed.
+    ///
+    /// # Arguments
+    /// 1. `source` - Key that has to be copied, its type tells what is copied, e.g. `KeyType::Queue("/root/jobs")`
+    /// 1. `destination` - Key of the copy, it must not exist
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use onlyati_datastore::datastore::Database;
+    /// use onlyati_datastore::datastore::enums::pair::KeyType;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let mut db = Database::new("root".to_string()).unwrap();
+    ///
+    /// db.push(KeyType::Record("/root/jobs".to_string()), "job1".to_string()).await.expect("Failed to push");
+    /// db.copy_key(KeyType::Queue("/root/jobs".to_string()), "/root/jobs_backup").await.expect("Failed to copy");
+    ///
+    /// assert_eq!(1, db.queue_length(KeyType::Record("/root/jobs".to_string())).unwrap());
+    /// assert_eq!(1, db.queue_length(KeyType::Record("/root/jobs_backup".to_string())).unwrap());
+    /// # })
+    /// ```
+    pub async fn copy_key(&mut self, source: KeyType, destination: &str) -> Result<(), ErrorKind> {
+        self.relocate(source, destination, false, false).await
+    }
+
+    /// Write the source onto the destination, source is removed if `remove_source` is set.
+    /// If `filtered` is set, the filters of destination must accept the values as they are.
+    async fn relocate(
+        &mut self,
+        source: KeyType,
+        destination: &str,
+        remove_source: bool,
+        filtered: bool,
+    ) -> Result<(), ErrorKind> {
+        tracing::trace!(
+            "{} request is performed from '{}' to '{}'",
+            if remove_source { "move" } else { "copy" },
+            source.get_key(),
+            destination
+        );
+
+        let source_routes = utilities::internal::validate_key(source.get_key(), &self.name)?;
+        let destination_routes = utilities::internal::validate_key(destination, &self.name)?;
+        if source_routes.len() == 1 || destination_routes.len() == 1 {
+            return Err(ErrorKind::InvalidKey(
+                "Root table cannot be moved, copied or overwritten".to_string(),
+            ));
+        }
+
+        let source_key = format!("/{}", source_routes.join("/"));
+        let destination_key = format!("/{}", destination_routes.join("/"));
+        if source.is_table() && stat::is_under(&destination_key, &source_key) {
+            return Err(ErrorKind::InvalidKey(
+                "Table cannot be moved or copied under itself".to_string(),
+            ));
+        }
+
+        // Computed keys are read-only, they cannot be overwritten or moved away
+        for computed in &self.computed {
+            if stat::is_under(&computed.key, &destination_key)
+                || (remove_source && stat::is_under(&computed.key, &source_key))
+            {
+                self.check_computed(&computed.key)?;
+            }
+        }
+
+        // Collect everything before the first change, keys are relative to the source, e.g. `Record("/sub/key")`
+        let mut tables = Vec::new();
+        let mut values = Vec::new();
+        let (destination_parent, destination_name) =
+            destination_routes.split_at(destination_routes.len() - 1);
+        let exists = match &source {
+            KeyType::Table(_) => {
+                if !self.collect_table(&source_routes, &mut tables, &mut values)? {
+                    return Err(ErrorKind::NotFound(
+                        "Specified key does not exist".to_string(),
+                    ));
+                }
+                self.storage.contains_table(&destination_routes)?
+            }
+            _ => {
+                let (parent, name) = source_routes.split_at(source_routes.len() - 1);
+                match self
+                    .storage
+                    .get(parent, &source.with_key(name[0].to_string()))?
+                {
+                    Some(value) => {
+                        values.push((source.with_key(String::new()), value.into_owned()))
+                    }
+                    None => {
+                        return Err(ErrorKind::NotFound(
+                            "Specified key does not exist".to_string(),
+                        ))
+                    }
+                }
+                self.storage
+                    .get(
+                        destination_parent,
+                        &source.with_key(destination_name[0].to_string()),
+                    )?
+                    .is_some()
             }
         };
+        if exists {
+            return Err(ErrorKind::Conflict(format!(
+                "'{}' already exists",
+                destination_key
+            )));
+        }
+        self.check_relocation(&source_key, &destination_key, &values, remove_source)?;
+        if filtered {
+            self.check_relocation_filters(&destination_key, &values)?;
+        }
+
+        // Changes are done one after the other, nobody can see the state between them
+        if remove_source {
+            match &source {
+                KeyType::Table(_) => {
+                    self.storage.remove_table(&source_routes)?;
+                    self.untrack_table(&source_key)?;
+                }
+                _ => {
+                    let (parent, name) = source_routes.split_at(source_routes.len() - 1);
+                    self.storage
+                        .remove(parent, &source.with_key(name[0].to_string()))?;
+                    self.untrack(&source.with_key(source_key.clone()))?;
+                }
+            }
+        }
 
-        let delete_key = KeyType::Table(key_routes[key_routes.len() - 1].to_string());
+        for table in tables {
+            let name = format!("{}{}", destination_key, table);
+            let routes: Vec<&str> = name.split('/').filter(|x| !x.is_empty()).collect();
+            self.storage.create_table(&routes)?;
 
-        // Try to delete the key, if it would fail, e.g.: does not exist, then return with error
-        match table.remove(&delete_key) {
-            Some(_) => {
-                tracing::trace!("delete table request is performed for '{}'", key.get_key());
+            let now = self.now();
+            self.tracker
+                .create_table(self.storage.as_mut(), &name, now)?;
+        }
 
-                Ok(())
+        let (action, kind) = match remove_source {
+            true => ("move", EventKind::Move),
+            false => ("copy", EventKind::Copy),
+        };
+        for (relative, value) in values {
+            let from = format!("{}{}", source_key, relative.get_key());
+            let to = format!("{}{}", destination_key, relative.get_key());
+            let routes: Vec<&str> = to.split('/').filter(|x| !x.is_empty()).collect();
+
+            let size = content_size(&value);
+            if let ValueType::RecordPointer(record) = &value {
+                self.index.insert(&to, &record.to_string());
             }
-            None => {
-                tracing::trace!(
-                    "delete table request is failed because no '{}' key exist",
-                    key.get_key()
-                );
-                Err(ErrorKind::InvalidKey(
-                    "Specified key does not exist".to_string(),
-                ))
+            self.storage.put(
+                &routes[..routes.len() - 1],
+                relative.with_key(routes[routes.len() - 1].to_string()),
+                value,
+            )?;
+            self.track(relative.with_key(to.clone()), size)?;
+
+            // Items in flight return to the new place of their queue
+            if remove_source && relative.is_queue() {
+                let mut moved = Vec::new();
+                for (id, item) in self.in_flight.iter_mut().filter(|(_, x)| x.key == from) {
+                    item.key = to.clone();
+                    moved.push(*id);
+                }
+                for id in moved {
+                    let value = bincode::serialize(&self.in_flight[&id])
+                        .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+                    self.storage.put_meta(&delivery_meta(id), value)?;
+                }
+            }
+
+            if remove_source {
+                self.send_event(EventKind::Delete, &from, None);
+            }
+            self.send_event(kind.clone(), &to, Some(from.clone()));
+
+            let change = serde_json::json!({ "action": action, "source": from, "destination": to })
+                .to_string();
+            self.send_hook(&from, change.clone()).await;
+            self.send_hook(&to, change).await;
+        }
+
+        tracing::trace!(
+            "{} request is done from '{}' to '{}'",
+            action,
+            source_key,
+            destination_key
+        );
+        Ok(())
+    }
+
+    /// Collect the tables and the values under a table without building it in memory, tables come before
+    /// their content. Keys are relative to the table, e.g. `Record("/sub/key")`, the table itself is `""`.
+    /// Return with `false` if the table does not exist.
+    fn collect_table(
+        &self,
+        table: &[&str],
+        tables: &mut Vec<String>,
+        values: &mut Vec<(KeyType, ValueType)>,
+    ) -> Result<bool, ErrorKind> {
+        tables.push(String::new());
+        self.storage.scan(table, &mut |route, key, value| {
+            let relative: String = route[table.len()..]
+                .iter()
+                .chain(std::iter::once(&key.get_key()))
+                .map(|x| format!("/{}", x))
+                .collect();
+            match value {
+                ValueType::TablePointer(_) => tables.push(relative),
+                value => values.push((key.with_key(relative), value.clone())),
+            }
+        })
+    }
+
+    /// Visit the records under a table with their full key, the table is not built in memory
+    fn scan_records(
+        &self,
+        table: &[&str],
+        visit: &mut dyn FnMut(String, &RecordValue),
+    ) -> Result<(), ErrorKind> {
+        self.storage.scan(table, &mut |route, key, value| {
+            if let (KeyType::Record(name), ValueType::RecordPointer(value)) = (key, value) {
+                visit(format!("/{}/{}", route.join("/"), name), value);
+            }
+        })?;
+        Ok(())
+    }
+
+    /// Collect everything under a table in the order of the tree: keys of a table are ordered by their
+    /// type and name, and tables are followed by their content. Every item has its path relative to the
+    /// table, e.g. `[Table("sub"), Record("key")]`. Return with `None` if the table does not exist.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table
+    /// 1. `map` - What is kept from the values
+    fn collect_ordered<T>(
+        &self,
+        table: &[&str],
+        map: impl Fn(&ValueType) -> T,
+    ) -> Result<Option<OrderedContent<T>>, ErrorKind> {
+        let mut entries = Vec::new();
+        let exists = self.storage.scan(table, &mut |route, key, value| {
+            let path = route[table.len()..]
+                .iter()
+                .map(|x| KeyType::Table(x.to_string()))
+                .chain(std::iter::once(key.clone()))
+                .collect::<Vec<_>>();
+            entries.push((path, map(value)));
+        })?;
+
+        if !exists {
+            return Ok(None);
+        }
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(Some(entries))
+    }
+
+    /// Join the names of a path that is returned by `collect_ordered`
+    fn join_path(path: &[KeyType]) -> String {
+        path.iter()
+            .map(|x| x.get_key())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Check that the filters of the new keys accept the copies as they are. Filters cannot change
+    /// them, because the move or copy is logged, not the values.
+    fn check_relocation_filters(
+        &self,
+        destination_key: &str,
+        values: &[(KeyType, ValueType)],
+    ) -> Result<(), ErrorKind> {
+        if self.filters.is_empty() {
+            return Ok(());
+        }
+
+        for (relative, value) in values {
+            let key = format!("{}{}", destination_key, relative.get_key());
+            let writes: Vec<(WriteKind, RecordValue)> = match value {
+                ValueType::RecordPointer(record) => vec![(WriteKind::Set, record.clone())],
+                ValueType::QueuePointer(queue) => queue
+                    .iter()
+                    .map(|x| (WriteKind::Push, x.as_str().into()))
+                    .collect(),
+                ValueType::HashPointer(hash) => hash
+                    .values()
+                    .map(|x| (WriteKind::HashSet, x.as_str().into()))
+                    .collect(),
+                ValueType::SetPointer(set) => set
+                    .iter()
+                    .map(|x| (WriteKind::SetAdd, x.as_str().into()))
+                    .collect(),
+                ValueType::TablePointer(_) => Vec::new(),
+            };
+
+            for (kind, value) in writes {
+                let (new_key, new_value) = self.filter_write(kind, key.clone(), value.clone())?;
+                if new_key != key || new_value != value {
+                    return Err(ErrorKind::ValidationError(format!(
+                        "Filter would change the {} of '{}', so it cannot be moved or copied",
+                        kind, key
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that the copies are allowed by the schemas of their new keys and fit into the limits.
+    /// Records are not evicted for them, because the evicted records could be the ones that are copied.
+    fn check_relocation(
+        &self,
+        source_key: &str,
+        destination_key: &str,
+        values: &[(KeyType, ValueType)],
+        remove_source: bool,
+    ) -> Result<(), ErrorKind> {
+        for (relative, value) in values {
+            let key = format!("{}{}", destination_key, relative.get_key());
+            match value {
+                ValueType::RecordPointer(record) => self.validator.check_record(&key, record)?,
+                ValueType::QueuePointer(queue) => {
+                    for item in queue {
+                        self.validator.check_queue(&key, item)?;
+                    }
+                }
+                ValueType::HashPointer(hash) => {
+                    for value in hash.values() {
+                        self.validator.check_item(&key, value)?;
+                    }
+                }
+                ValueType::SetPointer(set) => {
+                    for member in set {
+                        self.validator.check_item(&key, member)?;
+                    }
+                }
+                ValueType::TablePointer(_) => (),
+            }
+        }
+
+        let usage = self.tracker.usage();
+        let mut memory = usage.memory;
+        for (relative, value) in values {
+            let content = content_size(value);
+            memory += destination_key.len() + relative.get_key().len() + content;
+            if remove_source {
+                memory -= source_key.len() + relative.get_key().len() + content;
+            }
+        }
+
+        if let Some(max) = self.limits.max_memory {
+            if memory > max && memory > usage.memory {
+                return Err(ErrorKind::LimitExceeded(format!(
+                    "memory limit of {} bytes is reached",
+                    max
+                )));
+            }
+        }
+
+        if let Some(max) = self.limits.max_keys {
+            if !remove_source && usage.keys + values.len() > max {
+                return Err(ErrorKind::LimitExceeded(format!(
+                    "key limit of {} keys is reached",
+                    max
+                )));
             }
         }
+
+        Ok(())
     }
 }
+
+/// Name of a hook in the state of storage
+fn hook_meta(prefix: &str, link: &str) -> String {
+    format!("{}{}\0{}", HOOK_META, prefix, link)
+}
+
+/// Name of an in-flight delivery in the state of storage, it is padded so they are ordered by identifier
+fn delivery_meta(id: u64) -> String {
+    format!("{}{:020}", DELIVERY_META, id)
+}
@@ -0,0 +1,384 @@
+//! Route requests between databases that run in the same process
+//!
+//! Every database has its own root name, e.g. `/team1` and `/team2`, so requests are routed by the
+//! first segment of their key. Keys with unknown root are sent to the default database, which
+//! rejects them like before. Actions without key are handled as follows:
+//! - `SuspendLog`, `ResumeLog` and `Flush` are sent to every database,
+//! - `Ack` is tried on every database, because delivery identifiers are unique in the process,
+//! - `Batch` is sent to the database of its actions, it is rejected if their keys belong to more databases,
+//! - `Watch` subscribes to the events of every database, databases that are added later are included too.
+//!   Identifiers of events are given by the router, so they are increasing across databases.
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc::{channel, error::SendError, Sender};
+
+use super::{
+    enums::{context::RequestContext, error::ErrorKind, event::Event, DatabaseAction, ListType},
+    handle::DatastoreHandle,
+    types::{ResultWithList, ResultWithResult, ResultWithoutResult},
+};
+
+/// Router of running databases, it is cheap to clone
+#[derive(Clone)]
+pub struct DatastoreRouter {
+    /// Name of database that receives requests without known root
+    default: String,
+
+    /// Running databases by their name
+    databases: Arc<RwLock<HashMap<String, DatastoreHandle>>>,
+
+    /// Events of every database
+    events: broadcast::Sender<Event>,
+
+    /// Identifier of the last event that has been forwarded
+    event_counter: Arc<Mutex<u64>>,
+}
+
+impl DatastoreRouter {
+    /// Create new router with a default database
+    ///
+    /// # Arguments
+    /// 1. `default` - Name of the default database, it cannot be removed
+    /// 1. `handle` - Handle of the default database
+    pub fn new(default: String, handle: DatastoreHandle) -> Self {
+        let router = Self {
+            default: default.clone(),
+            databases: Arc::new(RwLock::new(HashMap::new())),
+            events: broadcast::channel(1024).0,
+            event_counter: Arc::new(Mutex::new(0)),
+        };
+
+        router.forward_events(default.clone(), &handle);
+        router.databases.write().unwrap().insert(default, handle);
+        router
+    }
+
+    /// Add a running database to the router
+    ///
+    /// # Arguments
+    /// 1. `name` - Name of database, it must be the same as its root name
+    /// 1. `handle` - Handle of the database
+    pub fn insert(&self, name: String, handle: DatastoreHandle) -> ResultWithoutResult {
+        let mut databases = self.databases.write().unwrap();
+        if databases.contains_key(&name) {
+            return Err(ErrorKind::Conflict(format!(
+                "database '{}' already exists",
+                name
+            )));
+        }
+
+        self.forward_events(name.clone(), &handle);
+        databases.insert(name, handle);
+        Ok(())
+    }
+
+    /// Remove a database from the router and return with its handle, the default database cannot be removed.
+    /// Database task stops when the last handle of it is dropped.
+    ///
+    /// # Arguments
+    /// 1. `name` - Name of database
+    pub fn remove(&self, name: &str) -> Result<DatastoreHandle, ErrorKind> {
+        if name == self.default {
+            return Err(ErrorKind::Forbidden(format!(
+                "default database '{}' cannot be dropped",
+                name
+            )));
+        }
+
+        match self.databases.write().unwrap().remove(name) {
+            Some(handle) => Ok(handle),
+            None => Err(ErrorKind::NotFound(format!(
+                "database '{}' does not exist",
+                name
+            ))),
+        }
+    }
+
+    /// Return with the name of default database
+    pub fn default_name(&self) -> &str {
+        &self.default
+    }
+
+    /// Return with the names of databases in alphabetical order
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.databases.read().unwrap().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Return with the handles of every database
+    pub fn handles(&self) -> Vec<DatastoreHandle> {
+        self.databases.read().unwrap().values().cloned().collect()
+    }
+
+    /// Return with the handle of a database
+    ///
+    /// # Arguments
+    /// 1. `name` - Name of database
+    pub fn handle(&self, name: &str) -> Option<DatastoreHandle> {
+        self.databases.read().unwrap().get(name).cloned()
+    }
+
+    /// Return with the handle that belongs to the key, it is the default database if root is unknown
+    ///
+    /// # Arguments
+    /// 1. `key` - Key of the request, e.g. `/team1/status`
+    pub fn handle_for(&self, key: &str) -> DatastoreHandle {
+        let databases = self.databases.read().unwrap();
+        databases[&self.name_for(&databases, key)].clone()
+    }
+
+    /// Return with the name of database that belongs to the key, it is the default if root is unknown
+    fn name_for(&self, databases: &HashMap<String, DatastoreHandle>, key: &str) -> String {
+        let root = key.trim_start_matches('/').split('/').next().unwrap_or("");
+
+        match databases.contains_key(root) {
+            true => root.to_string(),
+            false => self.default.clone(),
+        }
+    }
+
+    /// Return with a sender of the default database
+    pub fn sender(&self) -> Sender<DatabaseAction> {
+        self.handle_for("").sender()
+    }
+
+    /// Send an action to the database that belongs to its key
+    ///
+    /// # Arguments
+    /// 1. `action` - Action that is executed
+    ///
+    /// # Example
+    /// ```
+    /// use onlyati_datastore::datastore::{
+    ///     enums::{pair::RecordValue, pair::ValueType, DatabaseAction},
+    ///     router::DatastoreRouter,
+    ///     storage::MemoryStorage,
+    ///     utilities::start_datastore_handle,
+    /// };
+    /// use tokio::sync::mpsc::channel;
+    ///
+    /// # tokio_test::block_on(async {
+    /// let (root, _) = start_datastore_handle("root".to_string(), Box::new(MemoryStorage::new()), None, None).await;
+    /// let (team, _) = start_datastore_handle("team".to_string(), Box::new(MemoryStorage::new()), None, None).await;
+    ///
+    /// let router = DatastoreRouter::new("root".to_string(), root);
+    /// router.insert("team".to_string(), team).expect("Failed to add database");
+    ///
+    /// let (tx, mut rx) = channel(10);
+    /// let action = DatabaseAction::Set(tx, "/team/status".to_string(), "ok".to_string());
+    /// router.send(action).await.expect("Failed to send the request");
+    /// rx.recv().await.unwrap().expect("Failed to set");
+    ///
+    /// let value = router.get("/team/status".to_string(), None).await.expect("Failed to get");
+    /// assert_eq!(ValueType::RecordPointer(RecordValue::from("ok")), value);
+    /// # })
+    /// ```
+    pub async fn send(&self, action: DatabaseAction) -> Result<(), SendError<DatabaseAction>> {
+        let (context, action) = match action {
+            DatabaseAction::WithContext(context, action) => (Some(context), *action),
+            action => (None, action),
+        };
+
+        match action {
+            DatabaseAction::SuspendLog(sender) => {
+                self.broadcast(context, sender, DatabaseAction::SuspendLog)
+                    .await
+            }
+            DatabaseAction::ResumeLog(sender) => {
+                self.broadcast(context, sender, DatabaseAction::ResumeLog)
+                    .await
+            }
+            DatabaseAction::Flush(sender) => {
+                self.broadcast(context, sender, DatabaseAction::Flush).await
+            }
+            DatabaseAction::Ack(sender, id) => self.ack(context, sender, id).await,
+            DatabaseAction::Batch(sender, actions) => self.batch(context, sender, actions).await,
+            DatabaseAction::Watch(sender) => {
+                sender
+                    .send(Ok(self.events.subscribe()))
+                    .await
+                    .unwrap_or_else(|e| tracing::error!("Error during send: {}", e));
+                Ok(())
+            }
+            action => {
+                let handle = self.handle_for(action.key().unwrap_or(""));
+                handle.send(wrap(context, action)).await
+            }
+        }
+    }
+
+    /// Read a record from the database that belongs to the key, see `DatastoreHandle::get`
+    ///
+    /// # Arguments
+    /// 1. `key` - Key of the record
+    /// 1. `context` - Client of the request, it is written into the log
+    pub async fn get(&self, key: String, context: Option<RequestContext>) -> ResultWithResult {
+        self.handle_for(&key).get(key, context).await
+    }
+
+    /// List keys from the database that belongs to the key, see `DatastoreHandle::list_keys`
+    ///
+    /// # Arguments
+    /// 1. `key` - Table where listing starts
+    /// 1. `level` - List only the direct keys or everything under it
+    /// 1. `context` - Client of the request, it is written into the log
+    pub async fn list_keys(
+        &self,
+        key: String,
+        level: ListType,
+        context: Option<RequestContext>,
+    ) -> ResultWithList {
+        self.handle_for(&key).list_keys(key, level, context).await
+    }
+
+    /// Forward the events of database to the watchers of router, until the database is stopped
+    ///
+    /// # Arguments
+    /// 1. `name` - Name of database
+    /// 1. `handle` - Handle of the database
+    fn forward_events(&self, name: String, handle: &DatastoreHandle) {
+        let mut receiver = handle.subscribe();
+        let events = self.events.clone();
+        let event_counter = self.event_counter.clone();
+
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(mut event) => {
+                        let mut counter = event_counter.lock().unwrap();
+                        *counter += 1;
+                        event.id = *counter;
+                        // Nobody may listen, then the event is dropped
+                        let _ = events.send(event);
+                    }
+                    Err(RecvError::Lagged(count)) => {
+                        tracing::warn!("{} events of database '{}' are lost", count, name)
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        });
+    }
+
+    /// Send the action to every database and reply with the first error.
+    /// If none of them answers, e.g. they have no logger, the sender is dropped without reply.
+    async fn broadcast(
+        &self,
+        context: Option<RequestContext>,
+        sender: Sender<ResultWithoutResult>,
+        action: fn(Sender<ResultWithoutResult>) -> DatabaseAction,
+    ) -> Result<(), SendError<DatabaseAction>> {
+        let mut result: Option<ResultWithoutResult> = None;
+
+        for handle in self.handles() {
+            let (tx, mut rx) = channel(1);
+            let reply = match handle.send(wrap(context.clone(), action(tx))).await {
+                Ok(_) => rx.recv().await,
+                Err(_) => Some(Err(ErrorKind::InternalError(
+                    "database has stopped".to_string(),
+                ))),
+            };
+
+            if let Some(reply) = reply {
+                if !matches!(result, Some(Err(_))) {
+                    result = Some(reply);
+                }
+            }
+        }
+
+        if let Some(result) = result {
+            sender
+                .send(result)
+                .await
+                .unwrap_or_else(|e| tracing::error!("Error during send: {}", e));
+        }
+        Ok(())
+    }
+
+    /// Send the actions to their database as one batch, so they are done without other requests between them.
+    /// Every action must have a key and all keys must belong to the same database.
+    async fn batch(
+        &self,
+        context: Option<RequestContext>,
+        sender: Sender<ResultWithoutResult>,
+        actions: Vec<DatabaseAction>,
+    ) -> Result<(), SendError<DatabaseAction>> {
+        let handle = {
+            let databases = self.databases.read().unwrap();
+            let names = actions
+                .iter()
+                .map(|action| action.key().map(|key| self.name_for(&databases, key)))
+                .collect::<Option<BTreeSet<String>>>();
+
+            match names {
+                Some(names) if names.len() <= 1 => {
+                    let name = names.into_iter().next().unwrap_or(self.default.clone());
+                    Ok(databases[&name].clone())
+                }
+                Some(_) => Err("keys of batch belong to different databases"),
+                None => Err("every action of batch must have a key"),
+            }
+        };
+
+        let error = match handle {
+            Ok(handle) => {
+                let action = DatabaseAction::Batch(sender, actions);
+                return handle.send(wrap(context, action)).await;
+            }
+            Err(error) => error,
+        };
+
+        sender
+            .send(Err(ErrorKind::InvalidKey(error.to_string())))
+            .await
+            .unwrap_or_else(|e| tracing::error!("Error during send: {}", e));
+        Ok(())
+    }
+
+    /// Try the acknowledge on every database, reply with the last error if none of them knows it
+    async fn ack(
+        &self,
+        context: Option<RequestContext>,
+        sender: Sender<ResultWithoutResult>,
+        id: u64,
+    ) -> Result<(), SendError<DatabaseAction>> {
+        let mut result = Err(ErrorKind::NotFound(format!(
+            "delivery {} does not exist",
+            id
+        )));
+
+        for handle in self.handles() {
+            let (tx, mut rx) = channel(1);
+            if handle
+                .send(wrap(context.clone(), DatabaseAction::Ack(tx, id)))
+                .await
+                .is_err()
+            {
+                continue;
+            }
+
+            if let Some(reply) = rx.recv().await {
+                result = reply;
+                if result.is_ok() {
+                    break;
+                }
+            }
+        }
+
+        sender
+            .send(result)
+            .await
+            .unwrap_or_else(|e| tracing::error!("Error during send: {}", e));
+        Ok(())
+    }
+}
+
+/// Put back the context of request, if it had one
+fn wrap(context: Option<RequestContext>, action: DatabaseAction) -> DatabaseAction {
+    match context {
+        Some(context) => DatabaseAction::WithContext(context, Box::new(action)),
+        None => action,
+    }
+}
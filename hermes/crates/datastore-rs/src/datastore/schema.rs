@@ -0,0 +1,406 @@
+//! Schemas that restrict the keys and values under a prefix
+//!
+//! Every schema whose prefix contains the key is checked when a record is set, an item is pushed
+//! into a queue, a field of hash is set or a member is added to a set, also when they are moved, copied
+//! or imported. The write is rejected with `ErrorKind::ValidationError` if any of them fails.
+//! Regular expressions must match the whole text. JSON Schema is supported partially, the following
+//! keywords are checked and the others are ignored: `type`, `enum`, `const`, `properties`, `required`,
+//! `additionalProperties`, `items`, `minItems`, `maxItems`, `minimum`, `maximum`, `exclusiveMinimum`,
+//! `exclusiveMaximum`, `minLength`, `maxLength`, `pattern`, `allOf`, `anyOf`, `oneOf` and `not`.
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::enums::{error::ErrorKind, pair::RecordValue};
+use super::stat::is_under;
+
+/// Rules of keys and values under a prefix, nothing is restricted by default
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Schema {
+    /// Keys under this prefix are checked, e.g. `/root/status`
+    pub prefix: String,
+
+    /// Regular expression that the whole value must match, e.g. `OK|NOK`
+    pub pattern: Option<String>,
+
+    /// Allowed values, e.g. `["OK", "NOK"]`
+    pub r#enum: Option<Vec<String>>,
+
+    /// JSON Schema of the value, value is checked in its native JSON format
+    pub json_schema: Option<Value>,
+
+    /// Maximum number of characters of the value
+    pub max_length: Option<usize>,
+
+    /// Regular expression that the whole key must match, e.g. `/root/status/[a-z0-9-]+`
+    pub key_pattern: Option<String>,
+
+    /// Queues can be created under the prefix, default is true
+    pub queues: Option<bool>,
+}
+
+/// Schema with its compiled regular expressions
+struct Compiled {
+    /// Original definition
+    schema: Schema,
+
+    /// Compiled `pattern`
+    pattern: Option<Regex>,
+
+    /// Compiled `key_pattern`
+    key_pattern: Option<Regex>,
+
+    /// Compiled `pattern` keywords of JSON Schema
+    json_patterns: HashMap<String, Regex>,
+}
+
+/// Set of schemas of a database
+#[derive(Default)]
+pub(crate) struct Validator {
+    schemas: Vec<Compiled>,
+}
+
+impl Validator {
+    /// Compile the schemas, invalid regular expression is returned as error
+    ///
+    /// # Arguments
+    /// 1. `schemas` - Definition of schemas
+    pub fn new(schemas: Vec<Schema>) -> Result<Self, ErrorKind> {
+        let mut compiled = Vec::with_capacity(schemas.len());
+
+        for schema in schemas {
+            let invalid = |e: regex::Error| {
+                ErrorKind::InvalidKey(format!(
+                    "Schema of '{}' has invalid pattern: {}",
+                    schema.prefix, e
+                ))
+            };
+
+            let pattern = schema.pattern.as_deref().map(anchored).transpose();
+            let key_pattern = schema.key_pattern.as_deref().map(anchored).transpose();
+            let mut json_patterns = HashMap::new();
+            if let Some(json_schema) = &schema.json_schema {
+                collect_patterns(json_schema, &mut json_patterns).map_err(invalid)?;
+            }
+
+            compiled.push(Compiled {
+                pattern: pattern.map_err(invalid)?,
+                key_pattern: key_pattern.map_err(invalid)?,
+                json_patterns,
+                schema,
+            });
+        }
+
+        Ok(Self { schemas: compiled })
+    }
+
+    /// Return with the definition of schemas
+    pub fn schemas(&self) -> Vec<Schema> {
+        self.schemas.iter().map(|x| x.schema.clone()).collect()
+    }
+
+    /// Check a record before it is written
+    ///
+    /// # Arguments
+    /// 1. `key` - Full key of record, e.g. `/root/status/vps01`
+    /// 1. `value` - New value of record
+    pub fn check_record(&self, key: &str, value: &RecordValue) -> Result<(), ErrorKind> {
+        for compiled in self.matching(key) {
+            compiled.check_key(key)?;
+            compiled.check_value(key, &value.to_string(), &value.to_json())?;
+        }
+
+        Ok(())
+    }
+
+    /// Check an item before it is pushed into a queue, item is checked against JSON Schema
+    /// as JSON if it can be parsed, else as string
+    ///
+    /// # Arguments
+    /// 1. `key` - Full key of queue, e.g. `/root/jobs`
+    /// 1. `value` - Pushed item
+    pub fn check_queue(&self, key: &str, value: &str) -> Result<(), ErrorKind> {
+        for compiled in self.matching(key) {
+            if compiled.schema.queues == Some(false) {
+                return Err(ErrorKind::ValidationError(format!(
+                    "Queues are not allowed under '{}'",
+                    compiled.schema.prefix
+                )));
+            }
+        }
+
+        self.check_item(key, value)
+    }
+
+    /// Check a value of hash field or a member of set before it is written, key of hash or set must match
+    /// the key pattern and the value is checked like a queue item
+    ///
+    /// # Arguments
+    /// 1. `key` - Full key of hash or set, e.g. `/root/servers/vps01`
+    /// 1. `value` - Value of field or the member
+    pub fn check_item(&self, key: &str, value: &str) -> Result<(), ErrorKind> {
+        for compiled in self.matching(key) {
+            compiled.check_key(key)?;
+
+            let json = serde_json::from_str(value).unwrap_or(Value::String(value.to_string()));
+            compiled.check_value(key, value, &json)?;
+        }
+
+        Ok(())
+    }
+
+    /// Schemas whose prefix contains the key
+    fn matching<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a Compiled> {
+        self.schemas
+            .iter()
+            .filter(move |x| is_under(key, &x.schema.prefix))
+    }
+}
+
+impl Compiled {
+    /// Check the name of key
+    fn check_key(&self, key: &str) -> Result<(), ErrorKind> {
+        match &self.key_pattern {
+            Some(pattern) if !pattern.is_match(key) => Err(ErrorKind::ValidationError(format!(
+                "Key '{}' does not match '{}'",
+                key,
+                pattern.as_str()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the value in text and in JSON format
+    fn check_value(&self, key: &str, text: &str, json: &Value) -> Result<(), ErrorKind> {
+        let failed = |reason: String| {
+            Err(ErrorKind::ValidationError(format!(
+                "Value of '{}' is rejected by schema of '{}': {}",
+                key, self.schema.prefix, reason
+            )))
+        };
+
+        if let Some(max) = self.schema.max_length {
+            let length = text.chars().count();
+            if length > max {
+                return failed(format!("length is {} but maximum is {}", length, max));
+            }
+        }
+
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_match(text) {
+                return failed(format!("it does not match '{}'", pattern.as_str()));
+            }
+        }
+
+        if let Some(values) = &self.schema.r#enum {
+            if !values.iter().any(|x| x == text) {
+                return failed(format!("it must be one of {}", values.join(", ")));
+            }
+        }
+
+        if let Some(json_schema) = &self.schema.json_schema {
+            if let Err(reason) = check_json(json_schema, json, "$", &self.json_patterns) {
+                return failed(reason);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Compile regular expression that must match the whole text
+fn anchored(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{})$", pattern))
+}
+
+/// Compile the `pattern` keywords of JSON Schema, they are not anchored like in JSON Schema
+fn collect_patterns(
+    schema: &Value,
+    patterns: &mut HashMap<String, Regex>,
+) -> Result<(), regex::Error> {
+    match schema {
+        Value::Object(map) => {
+            if let Some(Value::String(pattern)) = map.get("pattern") {
+                patterns.insert(pattern.clone(), Regex::new(pattern)?);
+            }
+            for value in map.values() {
+                collect_patterns(value, patterns)?;
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_patterns(item, patterns)?;
+            }
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// Check a JSON value against a JSON Schema, reason is returned if it fails
+///
+/// # Arguments
+/// 1. `schema` - JSON Schema
+/// 1. `value` - Checked value
+/// 1. `path` - Location of value in the document, it is used in the reason, e.g. `$.disks[0]`
+/// 1. `patterns` - Compiled `pattern` keywords
+fn check_json(
+    schema: &Value,
+    value: &Value,
+    path: &str,
+    patterns: &HashMap<String, Regex>,
+) -> Result<(), String> {
+    let schema = match schema {
+        Value::Bool(true) => return Ok(()),
+        Value::Bool(false) => return Err(format!("{} is not allowed", path)),
+        Value::Object(schema) => schema,
+        _ => return Ok(()),
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(name) => vec![name],
+            Value::Array(names) => names.iter().filter_map(|x| x.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !types.iter().any(|x| is_type(value, x)) {
+            return Err(format!("{} must be {}", path, types.join(" or ")));
+        }
+    }
+
+    if let Some(Value::Array(values)) = schema.get("enum") {
+        if !values.contains(value) {
+            return Err(format!("{} is not an allowed value", path));
+        }
+    }
+
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            return Err(format!("{} must be {}", path, constant));
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for name in required.iter().filter_map(|x| x.as_str()) {
+                    if !map.contains_key(name) {
+                        return Err(format!("{}.{} is required", path, name));
+                    }
+                }
+            }
+
+            let properties = schema.get("properties").and_then(|x| x.as_object());
+            for (name, item) in map {
+                let item_path = format!("{}.{}", path, name);
+                match properties.and_then(|x| x.get(name)) {
+                    Some(property) => check_json(property, item, &item_path, patterns)?,
+                    None => {
+                        if let Some(additional) = schema.get("additionalProperties") {
+                            check_json(additional, item, &item_path, patterns)?;
+                        }
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(|x| x.as_u64()) {
+                if (items.len() as u64) < min {
+                    return Err(format!("{} must have at least {} items", path, min));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(|x| x.as_u64()) {
+                if items.len() as u64 > max {
+                    return Err(format!("{} must have at most {} items", path, max));
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check_json(item_schema, item, &format!("{}[{}]", path, i), patterns)?;
+                }
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            let limit = |name: &str| schema.get(name).and_then(|x| x.as_f64());
+            if matches!(limit("minimum"), Some(min) if number < min)
+                || matches!(limit("exclusiveMinimum"), Some(min) if number <= min)
+                || matches!(limit("maximum"), Some(max) if number > max)
+                || matches!(limit("exclusiveMaximum"), Some(max) if number >= max)
+            {
+                return Err(format!("{} is out of range", path));
+            }
+        }
+        Value::String(text) => {
+            let length = text.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(|x| x.as_u64()) {
+                if length < min {
+                    return Err(format!("{} must be at least {} characters", path, min));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(|x| x.as_u64()) {
+                if length > max {
+                    return Err(format!("{} must be at most {} characters", path, max));
+                }
+            }
+            if let Some(Value::String(pattern)) = schema.get("pattern") {
+                if let Some(regex) = patterns.get(pattern) {
+                    if !regex.is_match(text) {
+                        return Err(format!("{} does not match '{}'", path, pattern));
+                    }
+                }
+            }
+        }
+        _ => (),
+    }
+
+    if let Some(Value::Array(schemas)) = schema.get("allOf") {
+        for item in schemas {
+            check_json(item, value, path, patterns)?;
+        }
+    }
+
+    if let Some(Value::Array(schemas)) = schema.get("anyOf") {
+        if !schemas
+            .iter()
+            .any(|x| check_json(x, value, path, patterns).is_ok())
+        {
+            return Err(format!("{} does not match any of the schemas", path));
+        }
+    }
+
+    if let Some(Value::Array(schemas)) = schema.get("oneOf") {
+        let matched = schemas
+            .iter()
+            .filter(|x| check_json(x, value, path, patterns).is_ok())
+            .count();
+        if matched != 1 {
+            return Err(format!("{} must match exactly one of the schemas", path));
+        }
+    }
+
+    if let Some(not) = schema.get("not") {
+        if check_json(not, value, path, patterns).is_ok() {
+            return Err(format!("{} must not match the schema", path));
+        }
+    }
+
+    Ok(())
+}
+
+/// Tells that the value has the JSON Schema type
+fn is_type(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "number" => value.is_number(),
+        // Numbers like `1.0` are integers as well, only their fractional part matters
+        "integer" => matches!(value.as_f64(), Some(number) if number.fract() == 0.0),
+        "string" => value.is_string(),
+        _ => false,
+    }
+}
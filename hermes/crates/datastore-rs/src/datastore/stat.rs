@@ -0,0 +1,637 @@
+//! Metadata of keys and tables
+//!
+//! Database reports every change to the tracker, so size, times and counts are kept up to date
+//! incrementally: a stat does not need to walk the tree. Every key counts with the length of its
+//! name and its content, e.g. the text of a record, the items of a queue or the fields and values of
+//! a hash. Tables sum up everything under them.
+//!
+//! Stats of keys are kept by the storage next to their values, so they are not duplicated in memory and
+//! persistent storages keep them over restarts. Only the sums of tables are kept in memory, they are
+//! counted again from the storage at start.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::{
+    enums::{error::ErrorKind, pair::KeyType, pair::ValueType},
+    limits::{EvictionPolicy, Limits, Usage},
+    storage::Storage,
+};
+
+/// Type of the entry that is described by a stat
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatKind {
+    Record,
+    Queue,
+    Hash,
+    Set,
+    Table,
+}
+
+impl std::fmt::Display for StatKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Self::Record => "record",
+            Self::Queue => "queue",
+            Self::Hash => "hash",
+            Self::Set => "set",
+            Self::Table => "table",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Counts of a table
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TableStat {
+    /// Number of keys and tables directly in the table
+    pub children: usize,
+
+    /// Number of records under the table, recursively
+    pub records: usize,
+
+    /// Number of queues under the table, recursively
+    pub queues: usize,
+
+    /// Number of hashes under the table, recursively
+    pub hashes: usize,
+
+    /// Number of sets under the table, recursively
+    pub sets: usize,
+}
+
+/// Metadata of a key or a table
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stat {
+    /// Full key, e.g. `/root/status`
+    pub key: String,
+
+    /// What the key is, a record, a queue, a hash and a set can exist with the same key
+    pub kind: StatKind,
+
+    /// Size of value in bytes, for tables it is the size of keys and values under it
+    pub size: usize,
+
+    /// Number of items in a queue, fields in a hash or members in a set
+    pub length: Option<usize>,
+
+    /// When the key or the table was created, time since UNIX epoch.
+    /// It is unknown for keys and tables that were written by an older version into a persistent storage.
+    pub created: Option<Duration>,
+
+    /// When the key or anything under the table was changed the last time, time since UNIX epoch
+    pub modified: Option<Duration>,
+
+    /// Counts of a table, it is `None` for keys
+    pub table: Option<TableStat>,
+}
+
+/// Size, times and access statistics of a key, storages keep them next to the value
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeyStats {
+    /// Size of content without the key name
+    pub content: usize,
+
+    /// Logical time of creation
+    pub created: u64,
+
+    /// Time of creation since UNIX epoch
+    pub created_at: Option<Duration>,
+
+    /// Time of the last change since UNIX epoch
+    pub modified_at: Option<Duration>,
+
+    /// Logical time of the last access
+    pub last_access: u64,
+
+    /// Number of accesses
+    pub hits: u64,
+}
+
+/// Times of a table, persistent storages keep them next to the table
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TableTimes {
+    /// Time of creation since UNIX epoch
+    pub created_at: Option<Duration>,
+
+    /// Time of the last change under the table since UNIX epoch
+    pub modified_at: Option<Duration>,
+}
+
+/// Sums of a table that are changed with every key under it
+#[derive(Default)]
+struct TableStats {
+    /// Counts of the table
+    counts: TableStat,
+
+    /// Size of keys and values under the table
+    size: usize,
+
+    /// Creation and last change of the table
+    times: TableTimes,
+}
+
+/// Keep the usage and metadata of keys and tables up to date, database reports every change to it.
+/// Stats of keys are kept by the storage, only the sums of tables are in memory.
+#[derive(Default)]
+pub(crate) struct Tracker {
+    /// Statistics of every table that exists, e.g. `/root/status`
+    tables: BTreeMap<String, TableStats>,
+
+    /// Total usage
+    usage: Usage,
+
+    /// Logical clock, it is increased at every access
+    clock: AtomicU64,
+
+    /// Reads since the last save, logical time of the last read and the number of reads by key.
+    /// Reads do not change the storage, they are saved by `save_reads`.
+    reads: Mutex<BTreeMap<KeyType, (u64, u64)>>,
+}
+
+impl Tracker {
+    /// Create a tracker with the root table, its times are read from the storage
+    ///
+    /// # Arguments
+    /// 1. `root` - Key of the root table, e.g. `/root`
+    /// 1. `storage` - Storage of the database
+    pub fn new(root: &str, storage: &dyn Storage) -> Result<Self, ErrorKind> {
+        let mut tracker = Self::default();
+        let times = storage.table_times(&route_of(root))?.unwrap_or_default();
+        tracker.add_table(root, times);
+        Ok(tracker)
+    }
+
+    /// Count a key or table that already exists, e.g. after a persistent storage is opened.
+    /// Tables must be counted before their content. Return with the stats of key if the storage did not
+    /// have them, e.g. it was written by an older version, its times are unknown.
+    ///
+    /// # Arguments
+    /// 1. `storage` - Storage of the database
+    /// 1. `table` - Route of the table where the key is, e.g. `["root", "status"]`
+    /// 1. `key` - Key within the table
+    /// 1. `value` - Value of the key, content of tables is not used
+    pub fn existing(
+        &mut self,
+        storage: &dyn Storage,
+        table: &[&str],
+        key: &KeyType,
+        value: &ValueType,
+    ) -> Result<Option<KeyStats>, ErrorKind> {
+        let full_key = format!("/{}/{}", table.join("/"), key.get_key());
+
+        if key.is_table() {
+            let times = storage.table_times(&route_of(&full_key))?;
+            self.add_table(&full_key, times.unwrap_or_default());
+            return Ok(None);
+        }
+
+        let (stats, missing) = match storage.stats(table, key)? {
+            Some(stats) => {
+                self.clock
+                    .fetch_max(stats.created.max(stats.last_access), Ordering::Relaxed);
+                (stats, false)
+            }
+            None => {
+                let tick = self.tick();
+                let stats = KeyStats {
+                    content: content_size(value),
+                    created: tick,
+                    last_access: tick,
+                    ..Default::default()
+                };
+                (stats, true)
+            }
+        };
+
+        self.add_key(&key.with_key(full_key), stats.content, None);
+        Ok(missing.then_some(stats))
+    }
+
+    /// Return with the current usage
+    pub fn usage(&self) -> Usage {
+        self.usage.clone()
+    }
+
+    /// Return with the size of content, or `None` if key is not tracked
+    ///
+    /// # Arguments
+    /// 1. `storage` - Storage of the database
+    /// 1. `key` - Full key with its type, e.g. `KeyType::Queue("/root/jobs")`
+    pub fn content(
+        &self,
+        storage: &dyn Storage,
+        key: &KeyType,
+    ) -> Result<Option<usize>, ErrorKind> {
+        Ok(self.key_stats(storage, key)?.map(|x| x.content))
+    }
+
+    /// Set the size of content, key and its tables are created if they are new. Every write counts as an access.
+    ///
+    /// # Arguments
+    /// 1. `storage` - Storage of the database
+    /// 1. `key` - Full key with its type, e.g. `KeyType::Queue("/root/jobs")`
+    /// 1. `content` - Size of the content after the write
+    /// 1. `now` - Time of the change since UNIX epoch
+    pub fn set(
+        &mut self,
+        storage: &mut dyn Storage,
+        key: KeyType,
+        content: usize,
+        now: Duration,
+    ) -> Result<(), ErrorKind> {
+        let tick = self.tick();
+        let (table, name) = locate(&key);
+        let reads = self.take_reads(&key);
+
+        let stats = match storage.stats(&table, &name)? {
+            Some(mut stats) => {
+                let old = stats.content;
+                self.usage.memory = self.usage.memory - old + content;
+                for parent in parents(key.get_key()) {
+                    if let Some(table) = self.tables.get_mut(parent) {
+                        table.size = table.size - old + content;
+                        table.times.modified_at = Some(now);
+                    }
+                }
+
+                stats.content = content;
+                stats.modified_at = Some(now);
+                stats.last_access = tick;
+                stats.hits += reads + 1;
+                stats
+            }
+            None => {
+                self.add_key(&key, content, Some(now));
+                KeyStats {
+                    content,
+                    created: tick,
+                    created_at: Some(now),
+                    modified_at: Some(now),
+                    last_access: tick,
+                    hits: 1,
+                }
+            }
+        };
+
+        storage.put_stats(&table, name, stats)?;
+        self.save_tables(storage, &parents(key.get_key()))
+    }
+
+    /// Forget a key, its tables remain
+    ///
+    /// # Arguments
+    /// 1. `storage` - Storage of the database
+    /// 1. `key` - Full key with its type
+    /// 1. `now` - Time of the change since UNIX epoch
+    pub fn remove(
+        &mut self,
+        storage: &mut dyn Storage,
+        key: &KeyType,
+        now: Duration,
+    ) -> Result<(), ErrorKind> {
+        let (table, name) = locate(key);
+        self.take_reads(key);
+        let stats = match storage.remove_stats(&table, &name)? {
+            Some(stats) => stats,
+            None => return Ok(()),
+        };
+
+        let size = key.get_key().len() + stats.content;
+        self.usage.memory -= size;
+        self.usage.keys -= 1;
+
+        let parents = parents(key.get_key());
+        for table in parents.iter() {
+            if let Some(table) = self.tables.get_mut(*table) {
+                *count_of(&mut table.counts, key) -= 1;
+                table.size -= size;
+                table.times.modified_at = Some(now);
+            }
+        }
+        if let Some(parent) = parents.last().and_then(|x| self.tables.get_mut(*x)) {
+            parent.counts.children -= 1;
+        }
+
+        self.save_tables(storage, &parents)
+    }
+
+    /// Forget a table with every key under it, keys with the same name as the table remain.
+    /// Storage removes the stats of keys with the table.
+    ///
+    /// # Arguments
+    /// 1. `storage` - Storage of the database
+    /// 1. `prefix` - Key of the table, e.g. `/root/status`
+    /// 1. `now` - Time of the change since UNIX epoch
+    pub fn remove_table(
+        &mut self,
+        storage: &mut dyn Storage,
+        prefix: &str,
+        now: Duration,
+    ) -> Result<(), ErrorKind> {
+        self.reads
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|key, _| key.get_key() == prefix || !is_under(key.get_key(), prefix));
+
+        let table = match self.tables.remove(prefix) {
+            Some(table) => table,
+            None => return Ok(()),
+        };
+        self.tables.retain(|name, _| !is_under(name, prefix));
+
+        let counts = &table.counts;
+        self.usage.memory -= table.size;
+        self.usage.keys -= counts.records + counts.queues + counts.hashes + counts.sets;
+
+        let parents = parents(prefix);
+        for name in parents.iter() {
+            if let Some(parent) = self.tables.get_mut(*name) {
+                parent.counts.records -= counts.records;
+                parent.counts.queues -= counts.queues;
+                parent.counts.hashes -= counts.hashes;
+                parent.counts.sets -= counts.sets;
+                parent.size -= table.size;
+                parent.times.modified_at = Some(now);
+            }
+        }
+        if let Some(parent) = parents.last().and_then(|x| self.tables.get_mut(*x)) {
+            parent.counts.children -= 1;
+        }
+
+        self.save_tables(storage, &parents)
+    }
+
+    /// Register a table with its missing parents, e.g. an empty table that is copied
+    ///
+    /// # Arguments
+    /// 1. `storage` - Storage of the database
+    /// 1. `name` - Key of the table, e.g. `/root/status`
+    /// 1. `now` - Time of the change since UNIX epoch
+    pub fn create_table(
+        &mut self,
+        storage: &mut dyn Storage,
+        name: &str,
+        now: Duration,
+    ) -> Result<(), ErrorKind> {
+        let mut tables = parents(name);
+        tables.push(name);
+
+        let times = TableTimes {
+            created_at: Some(now),
+            modified_at: Some(now),
+        };
+        for table in tables.iter() {
+            self.add_table(table, times.clone());
+        }
+        self.save_tables(storage, &tables)
+    }
+
+    /// Count an eviction
+    pub fn evicted(&mut self) {
+        self.usage.evicted += 1;
+    }
+
+    /// Register a read of key, it can be called concurrently
+    pub fn touch(&self, key: &KeyType) {
+        let tick = self.tick();
+        let mut reads = self.reads.lock().unwrap_or_else(|e| e.into_inner());
+        let read = reads.entry(key.clone()).or_default();
+        read.0 = tick;
+        read.1 += 1;
+    }
+
+    /// Write the registered reads into the stats of keys
+    ///
+    /// # Arguments
+    /// 1. `storage` - Storage of the database
+    pub fn save_reads(&mut self, storage: &mut dyn Storage) -> Result<(), ErrorKind> {
+        let reads = std::mem::take(self.reads.get_mut().unwrap_or_else(|e| e.into_inner()));
+        for (key, (tick, hits)) in reads {
+            let (table, name) = locate(&key);
+            if let Some(mut stats) = storage.stats(&table, &name)? {
+                stats.last_access = tick;
+                stats.hits += hits;
+                storage.put_stats(&table, name, stats)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return with the stats of key, or `None` if key does not exist
+    ///
+    /// # Arguments
+    /// 1. `storage` - Storage of the database
+    /// 1. `key` - Full key with its type, e.g. `KeyType::Queue("/root/jobs")`
+    pub fn key_stats(
+        &self,
+        storage: &dyn Storage,
+        key: &KeyType,
+    ) -> Result<Option<KeyStats>, ErrorKind> {
+        let (table, name) = locate(key);
+        storage.stats(&table, &name)
+    }
+
+    /// Return with the stat of table without its key, or `None` if table does not exist
+    ///
+    /// # Arguments
+    /// 1. `name` - Key of the table, e.g. `/root/status`
+    pub fn table_stat(&self, name: &str) -> Option<Stat> {
+        self.tables.get(name).map(|x| Stat {
+            key: name.to_string(),
+            kind: StatKind::Table,
+            size: x.size,
+            length: None,
+            created: x.times.created_at,
+            modified: x.times.modified_at,
+            table: Some(x.counts.clone()),
+        })
+    }
+
+    /// Select the records that must be evicted to free the memory and the keys.
+    /// Return with `None` if the evictable records are not enough. Reads must be saved before.
+    ///
+    /// # Arguments
+    /// 1. `storage` - Storage of the database
+    /// 1. `limits` - Limits with the policy and the evictable prefixes
+    /// 1. `memory` - Bytes that must be freed
+    /// 1. `keys` - Number of keys that must be freed
+    /// 1. `protected` - Key that is written, it cannot be evicted
+    pub fn victims(
+        &self,
+        storage: &dyn Storage,
+        limits: &Limits,
+        memory: usize,
+        keys: usize,
+        protected: &KeyType,
+    ) -> Result<Option<Vec<KeyType>>, ErrorKind> {
+        if limits.policy == EvictionPolicy::Reject {
+            return Ok(None);
+        }
+
+        // Records under the prefixes and the record with the same key as the prefix
+        let mut candidates = BTreeMap::new();
+        for prefix in limits.evict_prefixes.iter() {
+            let route = route_of(prefix);
+            storage.scan_stats(&route, &mut |table, key, stats| {
+                if key.is_record() {
+                    let full_key = format!("/{}/{}", table.join("/"), key.get_key());
+                    candidates.insert(full_key, stats.clone());
+                }
+            })?;
+
+            if let Some((name, table)) = route.split_last() {
+                if let Some(stats) = storage.stats(table, &KeyType::Record(name.to_string()))? {
+                    candidates.insert(format!("/{}", route.join("/")), stats);
+                }
+            }
+        }
+
+        let mut candidates: Vec<(String, KeyStats)> = candidates
+            .into_iter()
+            .filter(|(key, _)| key != protected.get_key())
+            .collect();
+
+        match limits.policy {
+            EvictionPolicy::Reject => return Ok(None),
+            EvictionPolicy::Lru => candidates.sort_by_key(|(_, x)| x.last_access),
+            EvictionPolicy::Lfu => candidates.sort_by_key(|(_, x)| (x.hits, x.last_access)),
+            EvictionPolicy::Oldest => candidates.sort_by_key(|(_, x)| x.created),
+        }
+
+        let mut victims = Vec::new();
+        let (mut freed_memory, mut freed_keys) = (0, 0);
+        for (key, stats) in candidates {
+            if freed_memory >= memory && freed_keys >= keys {
+                break;
+            }
+            freed_memory += key.len() + stats.content;
+            freed_keys += 1;
+            victims.push(KeyType::Record(key));
+        }
+
+        match freed_memory >= memory && freed_keys >= keys {
+            true => Ok(Some(victims)),
+            false => Ok(None),
+        }
+    }
+
+    /// Count a new key into the usage and the sums of its tables, missing tables are created
+    fn add_key(&mut self, key: &KeyType, content: usize, now: Option<Duration>) {
+        let size = key.get_key().len() + content;
+        let parents = parents(key.get_key());
+
+        let times = TableTimes {
+            created_at: now,
+            modified_at: now,
+        };
+        for name in parents.iter() {
+            self.add_table(name, times.clone());
+            let table = self.tables.get_mut(*name).expect("table is just added");
+            *count_of(&mut table.counts, key) += 1;
+            table.size += size;
+            table.times.modified_at = now.or(table.times.modified_at);
+        }
+        if let Some(parent) = parents.last().and_then(|x| self.tables.get_mut(*x)) {
+            parent.counts.children += 1;
+        }
+
+        self.usage.memory += size;
+        self.usage.keys += 1;
+    }
+
+    /// Register a table if it is new, it is counted as child of its parent
+    fn add_table(&mut self, name: &str, times: TableTimes) {
+        if self.tables.contains_key(name) {
+            return;
+        }
+
+        self.tables.insert(
+            name.to_string(),
+            TableStats {
+                times,
+                ..Default::default()
+            },
+        );
+        if let Some(parent) = parents(name).last().and_then(|x| self.tables.get_mut(*x)) {
+            parent.counts.children += 1;
+        }
+    }
+
+    /// Write the times of tables into the storage
+    fn save_tables(&self, storage: &mut dyn Storage, names: &[&str]) -> Result<(), ErrorKind> {
+        for name in names {
+            if let Some(table) = self.tables.get(*name) {
+                storage.put_table_times(&route_of(name), &table.times)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove the registered reads of key and return with their number
+    fn take_reads(&mut self, key: &KeyType) -> u64 {
+        let reads = self.reads.get_mut().unwrap_or_else(|e| e.into_inner());
+        reads.remove(key).map(|(_, hits)| hits).unwrap_or(0)
+    }
+
+    /// Return with the next logical time
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+/// Size of content of a value without its key name, tables are not counted
+pub(crate) fn content_size(value: &ValueType) -> usize {
+    match value {
+        ValueType::TablePointer(_) => 0,
+        ValueType::RecordPointer(record) => record.to_string().len(),
+        ValueType::QueuePointer(queue) => queue.iter().map(|x| x.len()).sum(),
+        ValueType::HashPointer(hash) => hash.iter().map(|(k, v)| k.len() + v.len()).sum(),
+        ValueType::SetPointer(set) => set.iter().map(|x| x.len()).sum(),
+    }
+}
+
+/// Tells that key is the prefix itself or it is under the prefix
+pub(crate) fn is_under(key: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    match key.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Route of a key or table, e.g. `["root", "status"]` for `/root/status`
+fn route_of(key: &str) -> Vec<&str> {
+    key.split('/').filter(|x| !x.is_empty()).collect()
+}
+
+/// Split a full key to the route of its table and the key within the table,
+/// e.g. `["root", "status"]` and `Record("vps01")` for `Record("/root/status/vps01")`
+fn locate(key: &KeyType) -> (Vec<&str>, KeyType) {
+    let mut route = route_of(key.get_key());
+    let name = route.pop().unwrap_or_default();
+    (route, key.with_key(name.to_string()))
+}
+
+/// Tables that contain the key from the root, e.g. `/root` and `/root/status` for `/root/status/vps01`
+fn parents(key: &str) -> Vec<&str> {
+    key.match_indices('/')
+        .map(|(i, _)| &key[..i])
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+/// Counter of table that belongs to the type of key
+fn count_of<'a>(counts: &'a mut TableStat, key: &KeyType) -> &'a mut usize {
+    match key {
+        KeyType::Record(_) | KeyType::Table(_) => &mut counts.records,
+        KeyType::Queue(_) => &mut counts.queues,
+        KeyType::Hash(_) => &mut counts.hashes,
+        KeyType::Set(_) => &mut counts.sets,
+    }
+}
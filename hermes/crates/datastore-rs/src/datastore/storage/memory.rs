@@ -0,0 +1,139 @@
+//! Storage that keeps every table in memory
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use super::{Change, Storage, VisitStats};
+use crate::datastore::{
+    enums::{error::ErrorKind, pair::KeyType, pair::ValueType},
+    stat::KeyStats,
+    types::Table,
+    utilities::internal::{find_or_create_table_mut, find_table, find_table_mut},
+};
+
+/// Tables are stored in a tree of `BTreeMap`, this is the default storage
+#[derive(Default)]
+pub struct MemoryStorage {
+    /// Top level table, it contains the root table
+    root: Table,
+
+    /// Stats of keys by the route of their table and their key
+    stats: BTreeMap<(Vec<String>, KeyType), KeyStats>,
+}
+
+impl MemoryStorage {
+    /// Create new, empty storage
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stats of the keys under a table
+    fn stats_under(
+        &self,
+        table: &[&str],
+    ) -> impl Iterator<Item = (&(Vec<String>, KeyType), &KeyStats)> {
+        let prefix = route(table);
+        let start = (prefix.clone(), KeyType::Table(String::new()));
+        self.stats
+            .range((Bound::Included(start), Bound::Unbounded))
+            .take_while(move |((route, _), _)| route.starts_with(&prefix))
+    }
+}
+
+/// Owned route of a table
+fn route(table: &[&str]) -> Vec<String> {
+    table.iter().map(|x| x.to_string()).collect()
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, table: &[&str], key: &KeyType) -> Result<Option<Cow<'_, ValueType>>, ErrorKind> {
+        Ok(find_table(&self.root, table)
+            .and_then(|table| table.get(key))
+            .map(Cow::Borrowed))
+    }
+
+    fn put(&mut self, table: &[&str], key: KeyType, value: ValueType) -> Result<(), ErrorKind> {
+        find_or_create_table_mut(&mut self.root, table)?.insert(key, value);
+        Ok(())
+    }
+
+    fn remove(&mut self, table: &[&str], key: &KeyType) -> Result<Option<ValueType>, ErrorKind> {
+        Ok(find_table_mut(&mut self.root, table).and_then(|table| table.remove(key)))
+    }
+
+    fn update(&mut self, table: &[&str], key: KeyType, change: Change) -> Result<(), ErrorKind> {
+        // Value is taken out, so it is changed without copy
+        let mut value = self.remove(table, &key)?;
+        let result = change(&mut value);
+
+        if let Some(value) = value {
+            self.put(table, key, value)?;
+        }
+
+        result
+    }
+
+    fn table(&self, table: &[&str]) -> Result<Option<Cow<'_, Table>>, ErrorKind> {
+        Ok(find_table(&self.root, table).map(Cow::Borrowed))
+    }
+
+    fn create_table(&mut self, table: &[&str]) -> Result<(), ErrorKind> {
+        find_or_create_table_mut(&mut self.root, table)?;
+        Ok(())
+    }
+
+    fn remove_table(&mut self, table: &[&str]) -> Result<bool, ErrorKind> {
+        let (name, parent) = match table.split_last() {
+            Some(split) => split,
+            None => return Ok(false),
+        };
+
+        let removed = find_table_mut(&mut self.root, parent)
+            .and_then(|parent| parent.remove(&KeyType::Table(name.to_string())));
+
+        let stats: Vec<(Vec<String>, KeyType)> = self
+            .stats_under(table)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in stats {
+            self.stats.remove(&key);
+        }
+
+        Ok(removed.is_some())
+    }
+
+    fn stats(&self, table: &[&str], key: &KeyType) -> Result<Option<KeyStats>, ErrorKind> {
+        Ok(self.stats.get(&(route(table), key.clone())).cloned())
+    }
+
+    fn put_stats(
+        &mut self,
+        table: &[&str],
+        key: KeyType,
+        stats: KeyStats,
+    ) -> Result<(), ErrorKind> {
+        self.stats.insert((route(table), key), stats);
+        Ok(())
+    }
+
+    fn remove_stats(
+        &mut self,
+        table: &[&str],
+        key: &KeyType,
+    ) -> Result<Option<KeyStats>, ErrorKind> {
+        Ok(self.stats.remove(&(route(table), key.clone())))
+    }
+
+    fn scan_stats(&self, table: &[&str], visit: VisitStats) -> Result<(), ErrorKind> {
+        for ((route, key), stats) in self.stats_under(table) {
+            let route: Vec<&str> = route.iter().map(|x| x.as_str()).collect();
+            visit(&route, key, stats);
+        }
+
+        Ok(())
+    }
+
+    fn is_persistent(&self) -> bool {
+        false
+    }
+}
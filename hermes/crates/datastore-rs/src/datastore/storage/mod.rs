@@ -0,0 +1,325 @@
+//! Storage backends of database
+//!
+//! Database does not keep the tables itself, it works on a `Storage` implementation.
+//! Tables are addressed by their route, e.g. `/root/status/vps01` record is `KeyType::Record("vps01")`
+//! in the `["root", "status"]` table.
+//!
+//! - `MemoryStorage`: Every table is kept in memory, this is the default. Persistency is provided by the append file.
+//! - `SledStorage`: Tables are kept in a sled database on the disk, changes are persisted immediately.
+//!   State that is not in the tables, e.g. in-flight deliveries, is kept next to them.
+use std::borrow::Cow;
+
+use std::collections::VecDeque;
+
+use super::{
+    enums::{error::ErrorKind, pair::KeyType, pair::ValueType, QueueEnd},
+    stat::{KeyStats, TableTimes},
+    types::Table,
+};
+
+pub mod memory;
+pub mod sled;
+
+pub use self::memory::MemoryStorage;
+pub use self::sled::SledStorage;
+
+/// Closure that changes a value in place, see `Storage::update`
+pub type Change<'a> = &'a mut dyn FnMut(&mut Option<ValueType>) -> Result<(), ErrorKind>;
+
+/// Closure that gets the content of a table one by one, see `Storage::scan`
+pub type Visit<'a> = &'a mut dyn FnMut(&[&str], &KeyType, &ValueType);
+
+/// Closure that gets the stats of keys one by one, see `Storage::scan_stats`
+pub type VisitStats<'a> = &'a mut dyn FnMut(&[&str], &KeyType, &KeyStats);
+
+/// Place where the tables of database are stored
+pub trait Storage: Send + Sync {
+    /// Return with a value, or with `None` if it does not exist.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table where the key is, e.g. `["root", "status"]`
+    /// 1. `key` - Type and name of the key within the table
+    fn get(&self, table: &[&str], key: &KeyType) -> Result<Option<Cow<'_, ValueType>>, ErrorKind>;
+
+    /// Save a value, missing tables are created on the way.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table where the key is
+    /// 1. `key` - Type and name of the key within the table
+    /// 1. `value` - Value to be saved
+    fn put(&mut self, table: &[&str], key: KeyType, value: ValueType) -> Result<(), ErrorKind>;
+
+    /// Remove a value and return with it, or with `None` if it did not exist.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table where the key is
+    /// 1. `key` - Type and name of the key within the table
+    fn remove(&mut self, table: &[&str], key: &KeyType) -> Result<Option<ValueType>, ErrorKind>;
+
+    /// Change a value in place. Closure gets the current value, or `None` if it does not exist.
+    /// If the closure leaves a value then it is saved and missing tables are created,
+    /// if it leaves `None` then the key is removed. If the closure fails, nothing is changed.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table where the key is
+    /// 1. `key` - Type and name of the key within the table
+    /// 1. `change` - Closure that changes the value
+    fn update(&mut self, table: &[&str], key: KeyType, change: Change) -> Result<(), ErrorKind>;
+
+    /// Add an item to an end of a queue. Return with `false` if the queue does not exist and it is
+    /// not created. Storages that keep the items one by one override it, so the queue is not rewritten.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table where the queue is
+    /// 1. `name` - Name of the queue within the table
+    /// 1. `value` - Item that is added
+    /// 1. `end` - Item is added to the front or the back
+    /// 1. `create` - Create the queue and its missing tables if it does not exist
+    fn push_item(
+        &mut self,
+        table: &[&str],
+        name: &str,
+        value: String,
+        end: QueueEnd,
+        create: bool,
+    ) -> Result<bool, ErrorKind> {
+        let mut pushed = false;
+        self.update(table, KeyType::Queue(name.to_string()), &mut |item| {
+            match item {
+                Some(ValueType::QueuePointer(queue)) => match end {
+                    QueueEnd::Front => queue.push_front(value.clone()),
+                    QueueEnd::Back => queue.push_back(value.clone()),
+                },
+                Some(_) => {
+                    return Err(ErrorKind::InternalError(
+                        "Queue pointer was expected".to_string(),
+                    ))
+                }
+                None if create => {
+                    *item = Some(ValueType::QueuePointer(VecDeque::from([value.clone()])))
+                }
+                None => return Ok(()),
+            }
+            pushed = true;
+            Ok(())
+        })?;
+
+        Ok(pushed)
+    }
+
+    /// Remove an item from an end of a queue, queue is removed when it becomes empty.
+    /// Return with the item and with `true` if the queue has been removed, or `None` if it does not exist.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table where the queue is
+    /// 1. `name` - Name of the queue within the table
+    /// 1. `end` - Item is removed from the front or the back
+    fn pop_item(
+        &mut self,
+        table: &[&str],
+        name: &str,
+        end: QueueEnd,
+    ) -> Result<Option<(String, bool)>, ErrorKind> {
+        let mut popped = None;
+        self.update(table, KeyType::Queue(name.to_string()), &mut |value| {
+            if let Some(ValueType::QueuePointer(queue)) = value {
+                let item = match end {
+                    QueueEnd::Front => queue.pop_front(),
+                    QueueEnd::Back => queue.pop_back(),
+                };
+                let emptied = queue.is_empty();
+                if emptied {
+                    *value = None;
+                }
+                popped = item.map(|item| (item, emptied));
+            }
+            Ok(())
+        })?;
+
+        Ok(popped)
+    }
+
+    /// Return with an item from an end of a queue without removing it, or `None` if the queue does not exist.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table where the queue is
+    /// 1. `name` - Name of the queue within the table
+    /// 1. `end` - Item is read from the front or the back
+    fn peek_item(
+        &self,
+        table: &[&str],
+        name: &str,
+        end: QueueEnd,
+    ) -> Result<Option<String>, ErrorKind> {
+        match self
+            .get(table, &KeyType::Queue(name.to_string()))?
+            .as_deref()
+        {
+            Some(ValueType::QueuePointer(queue)) => Ok(match end {
+                QueueEnd::Front => queue.front().cloned(),
+                QueueEnd::Back => queue.back().cloned(),
+            }),
+            _ => Ok(None),
+        }
+    }
+
+    /// Return with the number of items in a queue, it is zero if the queue does not exist.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table where the queue is
+    /// 1. `name` - Name of the queue within the table
+    fn queue_length(&self, table: &[&str], name: &str) -> Result<usize, ErrorKind> {
+        match self
+            .get(table, &KeyType::Queue(name.to_string()))?
+            .as_deref()
+        {
+            Some(ValueType::QueuePointer(queue)) => Ok(queue.len()),
+            _ => Ok(0),
+        }
+    }
+
+    /// Return with a table with everything in it, or with `None` if it does not exist.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table, empty route is the top level that contains the root table
+    fn table(&self, table: &[&str]) -> Result<Option<Cow<'_, Table>>, ErrorKind>;
+
+    /// Visit everything under a table without building it in memory, tables come before their content.
+    /// Return with `false` if the table does not exist.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table
+    /// 1. `visit` - Closure that gets the route of the table where the key is, the key and its value.
+    ///    Value of a table may not have its content.
+    fn scan(&self, table: &[&str], visit: Visit) -> Result<bool, ErrorKind> {
+        fn walk(route: &mut Vec<String>, table: &Table, visit: Visit) {
+            for (key, value) in table.iter() {
+                let parent: Vec<&str> = route.iter().map(|x| x.as_str()).collect();
+                visit(&parent, key, value);
+
+                if let ValueType::TablePointer(table) = value {
+                    route.push(key.get_key().to_string());
+                    walk(route, table, visit);
+                    route.pop();
+                }
+            }
+        }
+
+        match self.table(table)? {
+            Some(content) => {
+                let mut route = table.iter().map(|x| x.to_string()).collect();
+                walk(&mut route, &content, visit);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Tells that a table exists, storages that keep the tables on the disk override it,
+    /// so the table is not read.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table
+    fn contains_table(&self, table: &[&str]) -> Result<bool, ErrorKind> {
+        Ok(self.table(table)?.is_some())
+    }
+
+    /// Create a table and its missing parents, nothing happens if it exists.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table
+    fn create_table(&mut self, table: &[&str]) -> Result<(), ErrorKind>;
+
+    /// Remove a table with everything in it, including the stats of its keys. Return with `false` if it did not exist.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table
+    fn remove_table(&mut self, table: &[&str]) -> Result<bool, ErrorKind>;
+
+    /// Return with the stats of a key, or with `None` if they do not exist.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table where the key is
+    /// 1. `key` - Type and name of the key within the table
+    fn stats(&self, table: &[&str], key: &KeyType) -> Result<Option<KeyStats>, ErrorKind>;
+
+    /// Save the stats of a key next to its value. They are not removed with the value,
+    /// only by `remove_stats` and `remove_table`.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table where the key is
+    /// 1. `key` - Type and name of the key within the table
+    /// 1. `stats` - Stats of the key
+    fn put_stats(&mut self, table: &[&str], key: KeyType, stats: KeyStats)
+        -> Result<(), ErrorKind>;
+
+    /// Remove the stats of a key and return with them, or with `None` if they did not exist.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table where the key is
+    /// 1. `key` - Type and name of the key within the table
+    fn remove_stats(
+        &mut self,
+        table: &[&str],
+        key: &KeyType,
+    ) -> Result<Option<KeyStats>, ErrorKind>;
+
+    /// Visit the stats of every key under a table without reading the values.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table
+    /// 1. `visit` - Closure that gets the route of the table where the key is, the key and its stats
+    fn scan_stats(&self, table: &[&str], visit: VisitStats) -> Result<(), ErrorKind>;
+
+    /// Return with the times of a table, or with `None` if they are unknown.
+    /// Storages that are not persistent do not need to keep them, because the append file restores them.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table
+    fn table_times(&self, _table: &[&str]) -> Result<Option<TableTimes>, ErrorKind> {
+        Ok(None)
+    }
+
+    /// Save the times of a table, nothing happens if the table does not exist.
+    ///
+    /// # Arguments
+    /// 1. `table` - Route of the table
+    /// 1. `times` - Creation and last change of the table
+    fn put_table_times(&mut self, _table: &[&str], _times: &TableTimes) -> Result<(), ErrorKind> {
+        Ok(())
+    }
+
+    /// Tells that changes are saved without the append file, so it does not need to be replayed at start
+    fn is_persistent(&self) -> bool;
+
+    /// Save state of database that is not in the tables, e.g. in-flight deliveries.
+    /// Storages that are not persistent do not need to keep it, because the append file restores it.
+    ///
+    /// # Arguments
+    /// 1. `name` - Unique name of the state, e.g. `delivery/00000000000000000001`
+    /// 1. `value` - Serialized state
+    fn put_meta(&mut self, _name: &str, _value: Vec<u8>) -> Result<(), ErrorKind> {
+        Ok(())
+    }
+
+    /// Remove a state that was saved by `put_meta`, nothing happens if it does not exist.
+    ///
+    /// # Arguments
+    /// 1. `name` - Unique name of the state
+    fn remove_meta(&mut self, _name: &str) -> Result<(), ErrorKind> {
+        Ok(())
+    }
+
+    /// Return with the states whose name starts with the prefix, ordered by their name.
+    ///
+    /// # Arguments
+    /// 1. `prefix` - Beginning of the names, e.g. `delivery/`
+    fn scan_meta(&self, _prefix: &str) -> Result<Vec<(String, Vec<u8>)>, ErrorKind> {
+        Ok(Vec::new())
+    }
+
+    /// Write the pending changes onto the disk, storages that buffer the writes must implement it
+    fn flush(&self) -> Result<(), ErrorKind> {
+        Ok(())
+    }
+}
@@ -0,0 +1,749 @@
+//! Storage that keeps the tables in a sled database on the disk
+//!
+//! Every value is stored under its full route with a type tag, e.g. `/root/status/vps01` record is
+//! `root/status/vps01\0R`. Tables have their own marker, e.g. `root/status\0T`, so empty tables are kept too.
+//! Queues are stored item by item, so push and pop do not rewrite the whole queue: `root/jobs\0Q` holds the
+//! bounds of the queue and its items are `root/jobs\0q` with their index, e.g. `root/jobs\0q7fffffffffffffff`.
+//! Stats of the keys are in a separate `stats` tree under the same key as their value, times of tables are
+//! stored in their marker. State of database that is not in the tables, see `Storage::put_meta`, is in a
+//! separate `meta` tree.
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use super::{Change, Storage, Visit, VisitStats};
+use crate::datastore::{
+    enums::{error::ErrorKind, pair::KeyType, pair::RecordValue, pair::ValueType, QueueEnd},
+    stat::{KeyStats, TableTimes},
+    types::Table,
+    utilities::internal::find_or_create_table_mut,
+};
+
+/// Separator between the route and the type tag in the keys
+const TAG_SEPARATOR: u8 = 0;
+
+/// Index of the first item of a new queue, it is in the middle, so items can be added to both ends
+const FIRST_ITEM: u64 = u64::MAX / 2;
+
+/// Value as it is written onto the disk
+#[derive(Serialize, Deserialize)]
+enum StoredValue {
+    Record(RecordValue),
+    Queue(VecDeque<String>),
+    Hash(BTreeMap<String, String>),
+    Set(BTreeSet<String>),
+
+    /// Index of the first item of a queue and the index after its last item, items are stored one by one.
+    /// Older databases have the whole queue in `Queue`, they are converted at their first use.
+    QueueBounds(u64, u64),
+}
+
+/// Tables are stored in a sled database, every change is persisted without the append file
+pub struct SledStorage {
+    /// Opened sled database
+    db: ::sled::Db,
+
+    /// Tree of the stats of keys
+    stats: ::sled::Tree,
+
+    /// Tree of the state that is not in the tables
+    meta: ::sled::Tree,
+}
+
+impl SledStorage {
+    /// Open the database in a directory, it is created if it does not exist
+    ///
+    /// # Arguments
+    /// 1. `path` - Directory of the database
+    pub fn open(path: &str) -> Result<Self, ErrorKind> {
+        tracing::debug!("open sled storage in '{}'", path);
+        match ::sled::open(path) {
+            Ok(db) => Self::with_db(db),
+            Err(e) => Err(ErrorKind::StorageError(e.to_string())),
+        }
+    }
+
+    /// Open a temporary database that is deleted when it is dropped, e.g. for tests
+    pub fn temporary() -> Result<Self, ErrorKind> {
+        match ::sled::Config::new().temporary(true).open() {
+            Ok(db) => Self::with_db(db),
+            Err(e) => Err(ErrorKind::StorageError(e.to_string())),
+        }
+    }
+
+    /// Create the storage from an opened database
+    fn with_db(db: ::sled::Db) -> Result<Self, ErrorKind> {
+        let open = |name: &str| {
+            db.open_tree(name)
+                .map_err(|e| ErrorKind::StorageError(e.to_string()))
+        };
+        let stats = open("stats")?;
+        let meta = open("meta")?;
+
+        Ok(Self { db, stats, meta })
+    }
+
+    /// Create the key of a value or a table marker
+    fn encode_key(table: &[&str], name: Option<&str>, tag: u8) -> Vec<u8> {
+        let mut key = table.join("/").into_bytes();
+        if let Some(name) = name {
+            if !table.is_empty() {
+                key.push(b'/');
+            }
+            key.extend_from_slice(name.as_bytes());
+        }
+        key.push(TAG_SEPARATOR);
+        key.push(tag);
+        key
+    }
+
+    /// Create the key of a value in a table
+    fn value_key(table: &[&str], key: &KeyType) -> Result<Vec<u8>, ErrorKind> {
+        let tag = match key {
+            KeyType::Record(_) => b'R',
+            KeyType::Queue(_) => b'Q',
+            KeyType::Hash(_) => b'H',
+            KeyType::Set(_) => b'S',
+            KeyType::Table(_) => {
+                return Err(ErrorKind::InternalError(
+                    "Table cannot be stored as value".to_string(),
+                ))
+            }
+        };
+
+        Ok(Self::encode_key(table, Some(key.get_key()), tag))
+    }
+
+    /// Split a stored key to its route and key type
+    fn decode_key(key: &[u8]) -> Result<(Vec<String>, u8), ErrorKind> {
+        let separator = match key.iter().rposition(|x| *x == TAG_SEPARATOR) {
+            Some(index) if index + 1 < key.len() => index,
+            _ => {
+                return Err(ErrorKind::StorageError(
+                    "Key without type tag is found".to_string(),
+                ))
+            }
+        };
+
+        let route = String::from_utf8_lossy(&key[..separator]);
+        let route = route
+            .split('/')
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_string())
+            .collect();
+        Ok((route, key[separator + 1]))
+    }
+
+    /// Serialize a value to bytes, queues are stored by `write_queue`
+    fn encode_value(value: ValueType) -> Result<Vec<u8>, ErrorKind> {
+        let value = match value {
+            ValueType::RecordPointer(record) => StoredValue::Record(record),
+            ValueType::HashPointer(hash) => StoredValue::Hash(hash),
+            ValueType::SetPointer(set) => StoredValue::Set(set),
+            ValueType::QueuePointer(_) | ValueType::TablePointer(_) => {
+                return Err(ErrorKind::InternalError(
+                    "Queue and table cannot be stored as value".to_string(),
+                ))
+            }
+        };
+
+        Self::encode_stored(&value)
+    }
+
+    /// Serialize a stored value to bytes
+    fn encode_stored(value: &StoredValue) -> Result<Vec<u8>, ErrorKind> {
+        bincode::serialize(value).map_err(|e| ErrorKind::StorageError(e.to_string()))
+    }
+
+    /// Deserialize a value from bytes, queues are read by `queue_items`
+    fn decode_value(value: &[u8]) -> Result<ValueType, ErrorKind> {
+        match bincode::deserialize::<StoredValue>(value) {
+            Ok(StoredValue::Record(record)) => Ok(ValueType::RecordPointer(record)),
+            Ok(StoredValue::Hash(hash)) => Ok(ValueType::HashPointer(hash)),
+            Ok(StoredValue::Set(set)) => Ok(ValueType::SetPointer(set)),
+            Ok(StoredValue::Queue(_)) | Ok(StoredValue::QueueBounds(..)) => Err(
+                ErrorKind::StorageError("Queue is not stored as value".to_string()),
+            ),
+            Err(e) => Err(ErrorKind::StorageError(e.to_string())),
+        }
+    }
+
+    /// Create the key of a queue item from the key of the queue
+    fn item_key(queue: &[u8], index: u64) -> Vec<u8> {
+        let mut key = Self::items_prefix(queue);
+        key.extend_from_slice(format!("{:016x}", index).as_bytes());
+        key
+    }
+
+    /// Prefix of the items of a queue, the type tag of the queue is changed to `q`
+    fn items_prefix(queue: &[u8]) -> Vec<u8> {
+        let mut prefix = queue[..queue.len() - 1].to_vec();
+        prefix.push(b'q');
+        prefix
+    }
+
+    /// Return with the index of the first item and the index after the last item of a queue, or `None`
+    /// if the queue does not exist. Queue that was stored as one value is converted to items.
+    fn queue_bounds(&self, queue: &[u8]) -> Result<Option<(u64, u64)>, ErrorKind> {
+        let stored = match self
+            .db
+            .get(queue)
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?
+        {
+            Some(stored) => stored,
+            None => return Ok(None),
+        };
+
+        match bincode::deserialize::<StoredValue>(&stored) {
+            Ok(StoredValue::QueueBounds(first, next)) => Ok(Some((first, next))),
+            Ok(StoredValue::Queue(items)) => self.write_queue(queue, items).map(Some),
+            Ok(_) => Err(ErrorKind::StorageError(
+                "Queue bounds were expected".to_string(),
+            )),
+            Err(e) => Err(ErrorKind::StorageError(e.to_string())),
+        }
+    }
+
+    /// Read every item of a queue, it is empty if the queue does not exist
+    fn queue_items(&self, queue: &[u8]) -> Result<VecDeque<String>, ErrorKind> {
+        if self.queue_bounds(queue)?.is_none() {
+            return Ok(VecDeque::new());
+        }
+
+        let mut items = VecDeque::new();
+        for item in self.db.scan_prefix(Self::items_prefix(queue)).values() {
+            let item = item.map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+            items.push_back(String::from_utf8_lossy(&item).to_string());
+        }
+
+        Ok(items)
+    }
+
+    /// Replace the items of a queue and return with its new bounds
+    fn write_queue(&self, queue: &[u8], items: VecDeque<String>) -> Result<(u64, u64), ErrorKind> {
+        let mut batch = ::sled::Batch::default();
+        for key in self.db.scan_prefix(Self::items_prefix(queue)).keys() {
+            let key = key.map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+            batch.remove(key);
+        }
+
+        let first = FIRST_ITEM;
+        let next = first + items.len() as u64;
+        for (index, item) in (first..next).zip(items) {
+            batch.insert(Self::item_key(queue, index), item.into_bytes());
+        }
+        batch.insert(
+            queue,
+            Self::encode_stored(&StoredValue::QueueBounds(first, next))?,
+        );
+
+        self.db
+            .apply_batch(batch)
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+
+        Ok((first, next))
+    }
+
+    /// Remove a queue with its items and return with them, or with `None` if it did not exist
+    fn remove_queue(&self, queue: &[u8]) -> Result<Option<VecDeque<String>>, ErrorKind> {
+        if self.queue_bounds(queue)?.is_none() {
+            return Ok(None);
+        }
+
+        let items = self.queue_items(queue)?;
+        let mut batch = ::sled::Batch::default();
+        for key in self.db.scan_prefix(Self::items_prefix(queue)).keys() {
+            let key = key.map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+            batch.remove(key);
+        }
+        batch.remove(queue);
+
+        self.db
+            .apply_batch(batch)
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+
+        Ok(Some(items))
+    }
+
+    /// Create the markers of the table and its parents if they do not exist
+    fn create_tables(&self, table: &[&str]) -> Result<(), ErrorKind> {
+        for index in 1..=table.len() {
+            let marker = Self::encode_key(&table[..index], None, b'T');
+            let exists = self
+                .db
+                .contains_key(&marker)
+                .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+
+            if !exists {
+                self.db
+                    .insert(marker, Vec::new())
+                    .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Deserialize stats of a key or a table
+    fn decode_stats<T: serde::de::DeserializeOwned>(value: &[u8]) -> Result<T, ErrorKind> {
+        bincode::deserialize(value).map_err(|e| ErrorKind::StorageError(e.to_string()))
+    }
+
+    /// Tells that the table exists, the top level always exists
+    fn table_exists(&self, table: &[&str]) -> Result<bool, ErrorKind> {
+        if table.is_empty() {
+            return Ok(true);
+        }
+
+        self.db
+            .contains_key(Self::encode_key(table, None, b'T'))
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))
+    }
+
+    /// Prefix of everything that is under the table
+    fn table_prefix(table: &[&str]) -> Vec<u8> {
+        match table.is_empty() {
+            true => Vec::new(),
+            false => format!("{}/", table.join("/")).into_bytes(),
+        }
+    }
+}
+
+impl Storage for SledStorage {
+    fn get(&self, table: &[&str], key: &KeyType) -> Result<Option<Cow<'_, ValueType>>, ErrorKind> {
+        if key.is_queue() {
+            let queue = Self::value_key(table, key)?;
+            if self.queue_bounds(&queue)?.is_none() {
+                return Ok(None);
+            }
+            let items = self.queue_items(&queue)?;
+            return Ok(Some(Cow::Owned(ValueType::QueuePointer(items))));
+        }
+
+        let stored = self
+            .db
+            .get(Self::value_key(table, key)?)
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+
+        match stored {
+            Some(value) => Ok(Some(Cow::Owned(Self::decode_value(&value)?))),
+            None => Ok(None),
+        }
+    }
+
+    fn put(&mut self, table: &[&str], key: KeyType, value: ValueType) -> Result<(), ErrorKind> {
+        let stored_key = Self::value_key(table, &key)?;
+        if let ValueType::QueuePointer(items) = value {
+            self.create_tables(table)?;
+            return self.write_queue(&stored_key, items).map(|_| ());
+        }
+        let value = Self::encode_value(value)?;
+
+        self.create_tables(table)?;
+        self.db
+            .insert(stored_key, value)
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    fn remove(&mut self, table: &[&str], key: &KeyType) -> Result<Option<ValueType>, ErrorKind> {
+        if key.is_queue() {
+            let queue = Self::value_key(table, key)?;
+            return Ok(self.remove_queue(&queue)?.map(ValueType::QueuePointer));
+        }
+
+        let removed = self
+            .db
+            .remove(Self::value_key(table, key)?)
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+
+        match removed {
+            Some(value) => Ok(Some(Self::decode_value(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn update(&mut self, table: &[&str], key: KeyType, change: Change) -> Result<(), ErrorKind> {
+        let mut value = self.get(table, &key)?.map(|x| x.into_owned());
+        let existed = value.is_some();
+
+        change(&mut value)?;
+
+        match value {
+            Some(value) => self.put(table, key, value),
+            None if existed => self.remove(table, &key).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    fn push_item(
+        &mut self,
+        table: &[&str],
+        name: &str,
+        value: String,
+        end: QueueEnd,
+        create: bool,
+    ) -> Result<bool, ErrorKind> {
+        let queue = Self::encode_key(table, Some(name), b'Q');
+        let (first, next) = match self.queue_bounds(&queue)? {
+            Some(bounds) => bounds,
+            None if create => {
+                self.create_tables(table)?;
+                (FIRST_ITEM, FIRST_ITEM)
+            }
+            None => return Ok(false),
+        };
+
+        let overflow = || ErrorKind::StorageError("Queue has no more index".to_string());
+        let (index, bounds) = match end {
+            QueueEnd::Front => {
+                let index = first.checked_sub(1).ok_or_else(overflow)?;
+                (index, (index, next))
+            }
+            QueueEnd::Back => (next, (first, next.checked_add(1).ok_or_else(overflow)?)),
+        };
+
+        let mut batch = ::sled::Batch::default();
+        batch.insert(Self::item_key(&queue, index), value.into_bytes());
+        batch.insert(
+            queue,
+            Self::encode_stored(&StoredValue::QueueBounds(bounds.0, bounds.1))?,
+        );
+        self.db
+            .apply_batch(batch)
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+
+        Ok(true)
+    }
+
+    fn pop_item(
+        &mut self,
+        table: &[&str],
+        name: &str,
+        end: QueueEnd,
+    ) -> Result<Option<(String, bool)>, ErrorKind> {
+        let queue = Self::encode_key(table, Some(name), b'Q');
+        let (first, next) = match self.queue_bounds(&queue)? {
+            Some((first, next)) if first < next => (first, next),
+            _ => return Ok(None),
+        };
+
+        let (index, bounds) = match end {
+            QueueEnd::Front => (first, (first + 1, next)),
+            QueueEnd::Back => (next - 1, (first, next - 1)),
+        };
+        let item_key = Self::item_key(&queue, index);
+        let item = match self
+            .db
+            .get(&item_key)
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?
+        {
+            Some(item) => String::from_utf8_lossy(&item).to_string(),
+            None => return Err(ErrorKind::StorageError("Queue item is missing".to_string())),
+        };
+
+        // Queue is removed when it becomes empty
+        let emptied = bounds.0 == bounds.1;
+        let mut batch = ::sled::Batch::default();
+        batch.remove(item_key);
+        match emptied {
+            true => batch.remove(queue),
+            false => batch.insert(
+                queue,
+                Self::encode_stored(&StoredValue::QueueBounds(bounds.0, bounds.1))?,
+            ),
+        }
+        self.db
+            .apply_batch(batch)
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+
+        Ok(Some((item, emptied)))
+    }
+
+    fn peek_item(
+        &self,
+        table: &[&str],
+        name: &str,
+        end: QueueEnd,
+    ) -> Result<Option<String>, ErrorKind> {
+        let queue = Self::encode_key(table, Some(name), b'Q');
+        let index = match self.queue_bounds(&queue)? {
+            Some((first, next)) if first < next => match end {
+                QueueEnd::Front => first,
+                QueueEnd::Back => next - 1,
+            },
+            _ => return Ok(None),
+        };
+
+        self.db
+            .get(Self::item_key(&queue, index))
+            .map(|item| item.map(|item| String::from_utf8_lossy(&item).to_string()))
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))
+    }
+
+    fn queue_length(&self, table: &[&str], name: &str) -> Result<usize, ErrorKind> {
+        let queue = Self::encode_key(table, Some(name), b'Q');
+        match self.queue_bounds(&queue)? {
+            Some((first, next)) => Ok((next - first) as usize),
+            None => Ok(0),
+        }
+    }
+
+    fn table(&self, table: &[&str]) -> Result<Option<Cow<'_, Table>>, ErrorKind> {
+        if !self.table_exists(table)? {
+            return Ok(None);
+        }
+
+        // Tree is built from the stored keys, so it is ordered the same way like in memory
+        let mut result = Table::new();
+        for item in self.db.scan_prefix(Self::table_prefix(table)) {
+            let (key, value) = item.map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+            let (route, tag) = Self::decode_key(&key)?;
+            let route: Vec<&str> = route[table.len()..].iter().map(|x| x.as_str()).collect();
+
+            let (name, parent) = match route.split_last() {
+                Some(split) => split,
+                None => continue,
+            };
+            let parent = find_or_create_table_mut(&mut result, parent)?;
+
+            let name = name.to_string();
+            match tag {
+                b'T' => {
+                    parent
+                        .entry(KeyType::Table(name))
+                        .or_insert(ValueType::TablePointer(Table::new()));
+                }
+                b'R' => {
+                    parent.insert(KeyType::Record(name), Self::decode_value(&value)?);
+                }
+                b'Q' => {
+                    let items = self.queue_items(&key)?;
+                    parent.insert(KeyType::Queue(name), ValueType::QueuePointer(items));
+                }
+                b'q' => (),
+                b'H' => {
+                    parent.insert(KeyType::Hash(name), Self::decode_value(&value)?);
+                }
+                b'S' => {
+                    parent.insert(KeyType::Set(name), Self::decode_value(&value)?);
+                }
+                other => {
+                    return Err(ErrorKind::StorageError(format!(
+                        "Unknown type tag: {}",
+                        other
+                    )))
+                }
+            }
+        }
+
+        Ok(Some(Cow::Owned(result)))
+    }
+
+    fn scan(&self, table: &[&str], visit: Visit) -> Result<bool, ErrorKind> {
+        if !self.table_exists(table)? {
+            return Ok(false);
+        }
+
+        // Only one value is decoded at once, markers are ordered before the content of their table
+        for item in self.db.scan_prefix(Self::table_prefix(table)) {
+            let (key, value) = item.map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+            let (route, tag) = Self::decode_key(&key)?;
+            let route: Vec<&str> = route.iter().map(|x| x.as_str()).collect();
+
+            let (name, parent) = match route.split_last() {
+                Some(split) => split,
+                None => continue,
+            };
+
+            let name = name.to_string();
+            let (key, value) = match tag {
+                b'T' => (KeyType::Table(name), ValueType::TablePointer(Table::new())),
+                b'R' => (KeyType::Record(name), Self::decode_value(&value)?),
+                b'Q' => (
+                    KeyType::Queue(name),
+                    ValueType::QueuePointer(self.queue_items(&key)?),
+                ),
+                b'q' => continue,
+                b'H' => (KeyType::Hash(name), Self::decode_value(&value)?),
+                b'S' => (KeyType::Set(name), Self::decode_value(&value)?),
+                other => {
+                    return Err(ErrorKind::StorageError(format!(
+                        "Unknown type tag: {}",
+                        other
+                    )))
+                }
+            };
+            visit(parent, &key, &value);
+        }
+
+        Ok(true)
+    }
+
+    fn contains_table(&self, table: &[&str]) -> Result<bool, ErrorKind> {
+        self.table_exists(table)
+    }
+
+    fn create_table(&mut self, table: &[&str]) -> Result<(), ErrorKind> {
+        self.create_tables(table)
+    }
+
+    fn remove_table(&mut self, table: &[&str]) -> Result<bool, ErrorKind> {
+        if table.is_empty() || !self.table_exists(table)? {
+            return Ok(false);
+        }
+
+        let mut batch = ::sled::Batch::default();
+        batch.remove(Self::encode_key(table, None, b'T'));
+        for item in self.db.scan_prefix(Self::table_prefix(table)).keys() {
+            let key = item.map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+            batch.remove(key);
+        }
+
+        self.db
+            .apply_batch(batch)
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+
+        let mut batch = ::sled::Batch::default();
+        for item in self.stats.scan_prefix(Self::table_prefix(table)).keys() {
+            let key = item.map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+            batch.remove(key);
+        }
+        self.stats
+            .apply_batch(batch)
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+
+        Ok(true)
+    }
+
+    fn stats(&self, table: &[&str], key: &KeyType) -> Result<Option<KeyStats>, ErrorKind> {
+        match self
+            .stats
+            .get(Self::value_key(table, key)?)
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?
+        {
+            Some(stats) => Ok(Some(Self::decode_stats(&stats)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn put_stats(
+        &mut self,
+        table: &[&str],
+        key: KeyType,
+        stats: KeyStats,
+    ) -> Result<(), ErrorKind> {
+        let stats =
+            bincode::serialize(&stats).map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+        self.stats
+            .insert(Self::value_key(table, &key)?, stats)
+            .map(|_| ())
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))
+    }
+
+    fn remove_stats(
+        &mut self,
+        table: &[&str],
+        key: &KeyType,
+    ) -> Result<Option<KeyStats>, ErrorKind> {
+        match self
+            .stats
+            .remove(Self::value_key(table, key)?)
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?
+        {
+            Some(stats) => Ok(Some(Self::decode_stats(&stats)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn scan_stats(&self, table: &[&str], visit: VisitStats) -> Result<(), ErrorKind> {
+        for item in self.stats.scan_prefix(Self::table_prefix(table)) {
+            let (key, stats) = item.map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+            let (route, tag) = Self::decode_key(&key)?;
+            let route: Vec<&str> = route.iter().map(|x| x.as_str()).collect();
+
+            let (name, parent) = match route.split_last() {
+                Some(split) => split,
+                None => continue,
+            };
+
+            let name = name.to_string();
+            let key = match tag {
+                b'R' => KeyType::Record(name),
+                b'Q' => KeyType::Queue(name),
+                b'H' => KeyType::Hash(name),
+                b'S' => KeyType::Set(name),
+                other => {
+                    return Err(ErrorKind::StorageError(format!(
+                        "Unknown type tag: {}",
+                        other
+                    )))
+                }
+            };
+            visit(parent, &key, &Self::decode_stats(&stats)?);
+        }
+
+        Ok(())
+    }
+
+    fn table_times(&self, table: &[&str]) -> Result<Option<TableTimes>, ErrorKind> {
+        // Markers of older versions are empty
+        match self
+            .db
+            .get(Self::encode_key(table, None, b'T'))
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))?
+        {
+            Some(times) if !times.is_empty() => Ok(Some(Self::decode_stats(&times)?)),
+            _ => Ok(None),
+        }
+    }
+
+    fn put_table_times(&mut self, table: &[&str], times: &TableTimes) -> Result<(), ErrorKind> {
+        if table.is_empty() || !self.table_exists(table)? {
+            return Ok(());
+        }
+
+        let times =
+            bincode::serialize(times).map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+        self.db
+            .insert(Self::encode_key(table, None, b'T'), times)
+            .map(|_| ())
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))
+    }
+
+    fn is_persistent(&self) -> bool {
+        true
+    }
+
+    fn put_meta(&mut self, name: &str, value: Vec<u8>) -> Result<(), ErrorKind> {
+        self.meta
+            .insert(name.as_bytes(), value)
+            .map(|_| ())
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))
+    }
+
+    fn remove_meta(&mut self, name: &str) -> Result<(), ErrorKind> {
+        self.meta
+            .remove(name.as_bytes())
+            .map(|_| ())
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))
+    }
+
+    fn scan_meta(&self, prefix: &str) -> Result<Vec<(String, Vec<u8>)>, ErrorKind> {
+        let mut result = Vec::new();
+        for item in self.meta.scan_prefix(prefix.as_bytes()) {
+            let (name, value) = item.map_err(|e| ErrorKind::StorageError(e.to_string()))?;
+            result.push((String::from_utf8_lossy(&name).to_string(), value.to_vec()));
+        }
+
+        Ok(result)
+    }
+
+    fn flush(&self) -> Result<(), ErrorKind> {
+        self.db
+            .flush()
+            .map(|_| ())
+            .map_err(|e| ErrorKind::StorageError(e.to_string()))
+    }
+}
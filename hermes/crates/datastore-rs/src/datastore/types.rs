@@ -1,7 +1,12 @@
 //! Custom types
 
-use std::collections::BTreeMap;
 use super::enums::error::ErrorKind;
+use super::enums::event::Event;
+use super::enums::snapshot::{ImportSummary, Snapshot};
+use super::limits::UsageReport;
+use super::stat::Stat;
+use crate::logger::enums::HistoryEntry;
+use std::collections::BTreeMap;
 
 use super::enums::pair::{KeyType, ValueType};
 
@@ -12,3 +17,15 @@ pub type ResultWithoutResult = Result<(), ErrorKind>;
 pub type ResultWithList = Result<Vec<KeyType>, ErrorKind>;
 pub type ResultWithHook = Result<(String, Vec<String>), ErrorKind>;
 pub type ResultWithHooks = Result<BTreeMap<String, Vec<String>>, ErrorKind>;
+pub type ResultWithEvents = Result<tokio::sync::broadcast::Receiver<Event>, ErrorKind>;
+pub type ResultWithLength = Result<usize, ErrorKind>;
+pub type ResultWithItems = Result<Vec<String>, ErrorKind>;
+pub type ResultWithDelivery = Result<(u64, String), ErrorKind>;
+pub type ResultWithHash = Result<std::collections::BTreeMap<String, String>, ErrorKind>;
+pub type ResultWithMembers = Result<std::collections::BTreeSet<String>, ErrorKind>;
+pub type ResultWithFlag = Result<bool, ErrorKind>;
+pub type ResultWithSnapshot = Result<Snapshot, ErrorKind>;
+pub type ResultWithImport = Result<ImportSummary, ErrorKind>;
+pub type ResultWithHistory = Result<Vec<HistoryEntry>, ErrorKind>;
+pub type ResultWithUsage = Result<UsageReport, ErrorKind>;
+pub type ResultWithStat = Result<Vec<Stat>, ErrorKind>;
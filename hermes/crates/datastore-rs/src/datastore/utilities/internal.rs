@@ -1,5 +1,5 @@
 use super::{
-    Table, {ErrorKind, KeyType, ListType, ValueType},
+    Table, {ErrorKind, KeyType, ValueType},
 };
 
 /// Validate and parse the key string.
@@ -8,7 +8,7 @@ pub(crate) fn validate_key<'a>(
     key_string: &'a str,
     db_name: &String,
 ) -> Result<Vec<&'a str>, ErrorKind> {
-    if &key_string[0..1] != "/" {
+    if !key_string.starts_with('/') {
         return Err(ErrorKind::InvalidKey(
             "Key must begin with '/' sign".to_string(),
         ));
@@ -35,74 +35,49 @@ pub(crate) fn validate_key<'a>(
 }
 
 /// Recursive algoritm to find a table
-pub(crate) fn find_table<'a>(db: &'a Table, routes: Vec<&'a str>) -> Option<&'a Table> {
+pub(crate) fn find_table<'a>(db: &'a Table, routes: &[&str]) -> Option<&'a Table> {
     if routes.is_empty() {
         return Some(db);
     }
 
     let current_table = KeyType::Table(routes[0].to_string());
     match db.get(&current_table) {
-        Some(ValueType::TablePointer(table_pointer)) => {
-            return find_table(table_pointer, routes[1..].to_vec());
-        }
+        Some(ValueType::TablePointer(table_pointer)) => find_table(table_pointer, &routes[1..]),
         _ => None,
     }
 }
 
 /// Recursive algoritm the find a table and return as mutable reference
-pub(crate) fn find_table_mut<'a>(db: &'a mut Table, routes: Vec<&'a str>) -> Option<&'a mut Table> {
+pub(crate) fn find_table_mut<'a>(db: &'a mut Table, routes: &[&str]) -> Option<&'a mut Table> {
     if routes.is_empty() {
         return Some(db);
     }
 
     let current_table = KeyType::Table(routes[0].to_string());
     match db.get_mut(&current_table) {
-        Some(ValueType::TablePointer(table_pointer)) => {
-            return find_table_mut(table_pointer, routes[1..].to_vec());
-        }
+        Some(ValueType::TablePointer(table_pointer)) => find_table_mut(table_pointer, &routes[1..]),
         _ => None,
     }
 }
 
-/// Display all items from a table
-pub(crate) fn display_tables(
-    db: &Table,
-    key_prefix: &String,
-    level: &ListType,
-) -> Result<Vec<KeyType>, ErrorKind> {
-    let mut result: Vec<KeyType> = Vec::with_capacity(std::mem::size_of::<KeyType>() * db.len());
-
-    for (key, value) in db.iter() {
-        match key {
-            KeyType::Record(key) => {
-                let new_key = format!("{}/{}", key_prefix.clone(), key);
-                let new_key = KeyType::Record(new_key);
-                result.push(new_key);
-            }
-            KeyType::Table(key) => {
-                if *level == ListType::OneLevel {
-                    continue;
-                }
-
-                let table_name = match value {
-                    ValueType::TablePointer(table) => table,
-                    _ => continue,
-                };
-                let mut temp = display_tables(
-                    table_name,
-                    &format!("{}/{}", key_prefix, key),
-                    level,
-                )?;
+/// Find a table and return as mutable reference, missing tables are created on the way
+pub(crate) fn find_or_create_table_mut<'a>(
+    db: &'a mut Table,
+    routes: &[&str],
+) -> Result<&'a mut Table, ErrorKind> {
+    if routes.is_empty() {
+        return Ok(db);
+    }
 
-                result.append(&mut temp);
-            }
-            KeyType::Queue(key) => {
-                let new_key = format!("{}/{}", key_prefix.clone(), key);
-                let new_key = KeyType::Queue(new_key);
-                result.push(new_key);
-            }
+    let current_table = db
+        .entry(KeyType::Table(routes[0].to_string()))
+        .or_insert(ValueType::TablePointer(Table::new()));
+    match current_table {
+        ValueType::TablePointer(table_pointer) => {
+            find_or_create_table_mut(table_pointer, &routes[1..])
         }
+        _ => Err(ErrorKind::InternalError(
+            "Table pointer was expected".to_string(),
+        )),
     }
-
-    Ok(result)
 }
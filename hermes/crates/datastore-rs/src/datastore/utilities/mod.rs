@@ -1,6 +1,12 @@
 //! Built-in utilities
 
-use tokio::sync::mpsc::{channel, Sender};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{
+    mpsc::{channel, Sender},
+    RwLock,
+};
 use tokio::task::JoinHandle;
 
 pub(crate) mod internal;
@@ -11,11 +17,40 @@ use crate::{
 };
 
 use super::{
-    enums::{error::ErrorKind, pair::KeyType, pair::ValueType, DatabaseAction, ListType},
-    types::Table,
+    enums::{
+        context::RequestContext,
+        error::ErrorKind,
+        pair::KeyType,
+        pair::RecordValue,
+        pair::ValueType,
+        snapshot::{ImportPolicy, ImportSummary, SnapshotHook},
+        DatabaseAction, Expiration, QueueEnd, MAX_QUEUE_TIMEOUT,
+    },
+    filter::WriteKind,
+    handle::DatastoreHandle,
+    storage::{MemoryStorage, Storage},
+    types::{ResultWithResult, ResultWithoutResult, Table},
     Database,
 };
 
+/// How often expired blocking pops and unacknowledged deliveries are checked
+const QUEUE_TIMER_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Client that waits for an item in an empty queue
+struct QueueWaiter {
+    /// Where the popped item has to be sent
+    sender: Sender<ResultWithResult>,
+
+    /// Which end of the queue is popped
+    end: QueueEnd,
+
+    /// After this point the waiter gets a not found error
+    deadline: tokio::time::Instant,
+}
+
+/// Parked waiters by queue name in the order of their arrival
+type QueueWaiters = HashMap<String, VecDeque<QueueWaiter>>;
+
 /// Initialize database on another thread, create a channel and return with it
 /// For all possible action check `onlyati::datastore::enums::mod::DatabaseAction` enum.
 ///
@@ -44,7 +79,7 @@ use super::{
 ///
 /// sender.send(get_action).await.expect("Failed to send the get request");
 /// let data = rx.recv().await.expect("Failed to receive message").expect("Failed to get data");
-/// assert_eq!(ValueType::RecordPointer("ok".to_string()), data);
+/// assert_eq!(ValueType::RecordPointer("ok".into()), data);
 /// # })
 /// ```
 pub async fn start_datastore(
@@ -52,11 +87,94 @@ pub async fn start_datastore(
     hook_sender: Option<Sender<HookManagerAction>>,
     logger_sender: Option<Sender<LoggerAction>>,
 ) -> (Sender<DatabaseAction>, JoinHandle<()>) {
+    start_datastore_with_storage(
+        name,
+        Box::new(MemoryStorage::new()),
+        hook_sender,
+        logger_sender,
+    )
+    .await
+}
+
+/// Initialize database on the specified storage on another thread, create a channel and return with it.
+/// If the storage is persistent, the append file is not replayed, logger just gets the changes.
+///
+/// # Example for call
+///
+/// ```
+/// use onlyati_datastore::datastore::{
+///     enums::{DatabaseAction, pair::ValueType},
+///     storage::SledStorage,
+///     utilities::start_datastore_with_storage,
+/// };
+/// use tokio::sync::mpsc::channel;
+///
+/// # tokio_test::block_on(async {
+/// let storage = SledStorage::temporary().expect("Failed to open storage");
+/// let (sender, _) = start_datastore_with_storage("root".to_string(), Box::new(storage), None, None).await;
+///
+/// let (tx, mut rx) = channel(10);
+/// let set_action = DatabaseAction::Set(tx, "/root/network".to_string(), "ok".to_string());
+/// sender.send(set_action).await.expect("Failed to send the request");
+/// rx.recv().await.unwrap().expect("Failed to set");
+/// # })
+/// ```
+pub async fn start_datastore_with_storage(
+    name: String,
+    storage: Box<dyn Storage>,
+    hook_sender: Option<Sender<HookManagerAction>>,
+    logger_sender: Option<Sender<LoggerAction>>,
+) -> (Sender<DatabaseAction>, JoinHandle<()>) {
+    let (handle, thread) = start_datastore_handle(name, storage, hook_sender, logger_sender).await;
+    (handle.sender(), thread)
+}
+
+/// Initialize database on the specified storage and return with a handle of it.
+/// Handle serves the reads concurrently, while the other actions are sent to the datastore task,
+/// so they are done in order. For more details see `DatastoreHandle`.
+///
+/// # Example for call
+///
+/// ```
+/// use onlyati_datastore::datastore::{
+///     enums::{DatabaseAction, ListType},
+///     storage::MemoryStorage,
+///     utilities::start_datastore_handle,
+/// };
+/// use tokio::sync::mpsc::channel;
+///
+/// # tokio_test::block_on(async {
+/// let storage = Box::new(MemoryStorage::new());
+/// let (handle, _) = start_datastore_handle("root".to_string(), storage, None, None).await;
+///
+/// let (tx, mut rx) = channel(10);
+/// let set_action = DatabaseAction::Set(tx, "/root/status/vps01".to_string(), "ok".to_string());
+/// handle.send(set_action).await.expect("Failed to send the request");
+/// rx.recv().await.unwrap().expect("Failed to set");
+///
+/// let list = handle.list_keys("/root/status".to_string(), ListType::All, None).await;
+/// assert_eq!(1, list.expect("Failed to list").len());
+/// # })
+/// ```
+pub async fn start_datastore_handle(
+    name: String,
+    storage: Box<dyn Storage>,
+    hook_sender: Option<Sender<HookManagerAction>>,
+    logger_sender: Option<Sender<LoggerAction>>,
+) -> (DatastoreHandle, JoinHandle<()>) {
     tracing::debug!("root element of database is '{}'", name);
     let (tx, mut rx) = tokio::sync::mpsc::channel::<DatabaseAction>(100);
 
+    let db = Database::with_storage(name, storage).expect("Failed to allocate database");
+    let events = db.event_sender.clone();
+    let database = Arc::new(RwLock::new(db));
+
+    // Readers wait until the database is restored
+    let mut guard = database.clone().write_owned().await;
+    let handle = DatastoreHandle::new(database.clone(), tx, events);
+
     let thread = tokio::spawn(async move {
-        let mut db = Database::new(name).expect("Failed to allocate database");
+        let db = &mut *guard;
 
         //
         // Set hook manager
@@ -67,9 +185,15 @@ pub async fn start_datastore(
         }
 
         //
-        // Set logger
+        // Restore data from append file, persistent storage already has them
         //
-        if let Some(sender) = logger_sender {
+        if db.is_persistent() {
+            tracing::info!("storage is persistent, append file is not replayed");
+            if let Err(e) = restore_hooks(db).await {
+                tracing::error!("failed to restore hooks from storage: {}", e);
+                return;
+            }
+        } else if let Some(sender) = &logger_sender {
             // Turn off hook execution because it is just a recovery
             tracing::debug!("turn off hook execution during data restore");
             if let Some(hook_sender) = &db.hook_sender {
@@ -98,62 +222,9 @@ pub async fn start_datastore(
                             "read {} item from append file, started to process them",
                             list.len()
                         );
-                        for action in list {
-                            match action {
-                                LogItem::SetKey(_, key, value) => {
-                                    db.insert(
-                                        KeyType::Record(key),
-                                        ValueType::RecordPointer(value),
-                                    )
-                                    .await
-                                    .unwrap();
-                                }
-                                LogItem::RemKey(_, key) => {
-                                    db.delete_key(KeyType::Record(key)).await.unwrap();
-                                }
-                                LogItem::RemPath(_, key) => {
-                                    db.delete_table(KeyType::Record(key)).await.unwrap();
-                                }
-                                LogItem::SetHook(_, prefix, link) => {
-                                    if let Some(hook_sender) = &db.hook_sender {
-                                        let (tx, mut rx) = channel(10);
-                                        let action = HookManagerAction::Set(tx, prefix, link);
-
-                                        hook_sender.send(action).await.unwrap();
-
-                                        let a = rx.recv().await.unwrap();
-                                        if a != HookManagerResponse::Ok
-                                            && a != HookManagerResponse::Error(
-                                                "Already defined".to_string(),
-                                            )
-                                        {
-                                            tracing::error!("failed to set hook: {:?}", a);
-                                            return;
-                                        }
-                                    }
-                                }
-                                LogItem::RemHook(_, prefix, link) => {
-                                    if let Some(hook_sender) = &db.hook_sender {
-                                        let (tx, mut rx) = channel(10);
-                                        let action = HookManagerAction::Remove(tx, prefix, link);
-
-                                        hook_sender.send(action).await.unwrap();
-
-                                        let a = rx.recv().await.unwrap();
-                                        if a != HookManagerResponse::Ok {
-                                            tracing::error!("failed to remove hook: {:?}", a);
-                                            return;
-                                        }
-                                    }
-                                }
-                                LogItem::Push(_, key, value) => {
-                                    db.push(KeyType::Record(key), value).await.unwrap();
-                                }
-                                LogItem::Pop(_, key) => {
-                                    db.pop(KeyType::Record(key)).await.unwrap();
-                                }
-                                _ => (),
-                            }
+                        if let Err((index, e)) = replay_log(db, list).await {
+                            tracing::error!("item {} of append file: {}", index, e);
+                            return;
                         }
                     }
                     other => {
@@ -183,15 +254,47 @@ pub async fn start_datastore(
                     return;
                 }
             }
+        }
 
+        //
+        // Set logger
+        //
+        if let Some(sender) = logger_sender {
             tracing::debug!("subscribe to logger");
             db.subscribe_to_logger(sender);
         }
 
+        // From now hook and logger messages are sent after the lock is released
+        db.defer_messages();
+        drop(guard);
+
         //
         // Start database process to host on mpsc
         //
-        while let Some(data) = rx.recv().await {
+        let mut waiters = QueueWaiters::new();
+        let mut timer = tokio::time::interval(QUEUE_TIMER_INTERVAL);
+        timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            let data = tokio::select! {
+                data = rx.recv() => match data {
+                    Some(data) => data,
+                    None => break,
+                },
+                _ = timer.tick() => {
+                    let mut db = database.write().await;
+                    db.context = None;
+                    check_queue_timers(&mut db, &mut waiters).await;
+                    delete_expired(&mut db).await;
+                    db.refresh_computed().await;
+                    db.save_reads();
+                    let outbox = db.take_outbox();
+                    drop(db);
+                    outbox.send().await;
+                    continue;
+                }
+            };
+
             let received_at =
                 match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
                     Ok(v) => v,
@@ -202,289 +305,1404 @@ pub async fn start_datastore(
                 };
 
             tracing::trace!("received request: {}", data);
+
+            // Actions are done one by one in the order of their arrival, reads of the handle wait meanwhile
+            let mut guard = database.write().await;
+            let db = &mut *guard;
+
+            // Context is kept until the next request, so changes that are made due to this request are logged with it
+            let (context, data) = unwrap_context(data);
+            db.context = context;
+
+            // Actions of a batch are done together, other requests and reads of the handle wait until the last one
             match data {
-                // Handle Get actions
-                DatabaseAction::Get(sender, key) => {
-                    match db.get(KeyType::Record(key.clone())) {
-                        Ok(value) => send_response!(sender, Ok(value)),
-                        Err(e) => send_response!(sender, Err(e)),
+                DatabaseAction::Batch(sender, actions) => {
+                    for action in actions {
+                        execute_action(db, &mut waiters, action, received_at).await;
                     }
+                    send_response!(sender, Ok(()));
+                }
+                data => execute_action(db, &mut waiters, data, received_at).await,
+            }
 
-                    if let Some(sender) = &db.logger_sender {
-                        write_log!(sender, vec![LogItem::GetKey(received_at, key)]);
-                    }
+            // Computed keys are written after the change of their source, before the next request
+            db.refresh_computed().await;
+
+            // Hook manager and logger can be slow, readers of the handle do not wait for them
+            let outbox = db.take_outbox();
+            drop(guard);
+            outbox.send().await;
+        }
+    });
+
+    (handle, thread)
+}
+
+/// Remove the context from the action, the innermost context is returned
+fn unwrap_context(data: DatabaseAction) -> (Option<RequestContext>, DatabaseAction) {
+    let mut data = data;
+    let mut context = None;
+    while let DatabaseAction::WithContext(inner, action) = data {
+        context = Some(inner);
+        data = *action;
+    }
+    (context, data)
+}
+
+/// Execute an action on the database, respond to its sender and log it
+async fn execute_action(
+    db: &mut Database,
+    waiters: &mut QueueWaiters,
+    data: DatabaseAction,
+    received_at: Duration,
+) {
+    // Actions of a batch may have their own context
+    let (context, data) = unwrap_context(data);
+    if context.is_some() {
+        db.context = context;
+    }
+
+    match data {
+        // Handle Get actions
+        DatabaseAction::Get(sender, key) => {
+            match db.get(KeyType::Record(key.clone())) {
+                Ok(value) => send_response!(sender, Ok(value)),
+                Err(e) => send_response!(sender, Err(e)),
+            }
+
+            write_log!(db, vec![LogItem::GetKey(received_at, key)]);
+        }
+        // Handle Set actions, rejected writes are not logged, so replay does not apply them
+        // Filters run before, the filtered key and value are written and logged
+        DatabaseAction::Set(sender, key, value) => {
+            let expiration = Expiration::Remove;
+            set_record(db, sender, key, value.into(), expiration, received_at).await;
+        }
+        // Handle typed Set actions
+        DatabaseAction::SetTyped(sender, key, value) => {
+            set_record(db, sender, key, value, Expiration::Remove, received_at).await;
+        }
+        // Handle Set actions that keep the expiration of record or set a new one
+        DatabaseAction::SetExpiring(sender, key, value, expiration) => {
+            set_record(db, sender, key, value.into(), expiration, received_at).await;
+        }
+        // Set or remove the expiration of a record
+        DatabaseAction::Expire(sender, key, after) => {
+            let deadline = match after.map(|x| expiration_deadline(received_at, x)) {
+                Some(Ok(deadline)) => Some(deadline),
+                Some(Err(e)) => {
+                    send_response!(sender, Err(e));
+                    return;
                 }
-                // Handle Set actions
-                DatabaseAction::Set(sender, key, value) => {
-                    match db
-                        .insert(
-                            KeyType::Record(key.clone()),
-                            ValueType::RecordPointer(value.clone()),
-                        )
-                        .await
-                    {
-                        Ok(_) => send_response!(sender, Ok(())),
-                        Err(e) => send_response!(sender, Err(e)),
-                    }
+                None => None,
+            };
 
-                    if let Some(sender) = &db.logger_sender {
-                        write_log!(sender, vec![LogItem::SetKey(received_at, key, value)]);
-                    }
+            let result = db.expire(KeyType::Record(key.clone()), deadline);
+            let changed = matches!(result, Ok(true));
+            send_response!(sender, result);
+
+            if changed {
+                write_log!(db, vec![LogItem::Expire(received_at, key, deadline)]);
+            }
+        }
+        // Handle Patch actions, the whole new value is filtered like a set, then it is logged
+        DatabaseAction::Patch(sender, key, path, value) => {
+            let (key, value) = match db
+                .patched(KeyType::Record(key.clone()), &path, value)
+                .and_then(|value| db.filter_write(WriteKind::Set, key, value))
+            {
+                Ok(filtered) => filtered,
+                Err(e) => {
+                    send_response!(sender, Err(e));
+                    return;
                 }
-                // Handle DeleteKey actions
-                DatabaseAction::DeleteKey(sender, key) => {
-                    match db.delete_key(KeyType::Record(key.clone())).await {
-                        Ok(_) => send_response!(sender, Ok(())),
-                        Err(e) => send_response!(sender, Err(e)),
-                    }
+            };
 
-                    if let Some(sender) = &db.logger_sender {
-                        write_log!(sender, vec![LogItem::RemKey(received_at, key)]);
-                    }
+            match db
+                .insert(
+                    KeyType::Record(key.clone()),
+                    ValueType::RecordPointer(value.clone()),
+                )
+                .await
+            {
+                Ok(_) => {
+                    send_response!(sender, Ok(()));
+
+                    write_log!(db, vec![LogItem::SetTypedKey(received_at, key, value)]);
                 }
-                // Handle DeleteTable actions
-                DatabaseAction::DeleteTable(sender, key) => {
-                    match db.delete_table(KeyType::Table(key.clone())).await {
-                        Ok(_) => send_response!(sender, Ok(())),
-                        Err(e) => send_response!(sender, Err(e)),
-                    }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        // Handle DeleteKey actions
+        // Only the successful deletes are logged, so replay does not fail on them
+        DatabaseAction::DeleteKey(sender, key) => {
+            let result = db.delete_key(KeyType::Record(key.clone())).await;
+            let deleted = result.is_ok();
+            send_response!(sender, result);
+
+            if deleted {
+                write_log!(db, vec![LogItem::RemKey(received_at, key)]);
+            }
+        }
+        // Delete a key of the specified kind, only the successful deletes are logged
+        DatabaseAction::DeleteTyped(sender, key) => {
+            let result = db.delete_key(key.clone()).await;
+            let deleted = result.is_ok();
+            send_response!(sender, result);
 
-                    if let Some(sender) = &db.logger_sender {
-                        write_log!(sender, vec![LogItem::RemPath(received_at, key)]);
+            if deleted {
+                write_log!(db, vec![LogItem::RemTypedKey(received_at, key)]);
+            }
+        }
+        // Handle DeleteTable actions
+        DatabaseAction::DeleteTable(sender, key) => {
+            let result = db.delete_table(KeyType::Table(key.clone())).await;
+            let deleted = result.is_ok();
+            send_response!(sender, result);
+
+            if deleted {
+                write_log!(db, vec![LogItem::RemPath(received_at, key)]);
+            }
+        }
+        // Handle ListKeys action
+        DatabaseAction::ListKeys(sender, key, level) => {
+            match db.list_keys(KeyType::Record(key.clone()), level) {
+                Ok(list) => send_response!(sender, Ok(list)),
+                Err(e) => send_response!(sender, Err(e)),
+            }
+
+            write_log!(db, vec![LogItem::ListKeys(received_at, key)]);
+        }
+        // Trigger HookManager
+        DatabaseAction::Trigger(sender, key, value) => {
+            match db
+                .trigger(
+                    KeyType::Record(key.clone()),
+                    ValueType::RecordPointer(value.clone().into()),
+                )
+                .await
+            {
+                Ok(_) => send_response!(sender, Ok(())),
+                Err(e) => send_response!(sender, Err(e)),
+            }
+
+            write_log!(db, vec![LogItem::Trigger(received_at, key, value)]);
+        }
+        // Set hook
+        DatabaseAction::HookSet(sender, prefix, link) => {
+            match &db.hook_sender {
+                Some(hook_sender) => {
+                    let (tx, mut rx) = channel(100);
+                    let action = HookManagerAction::Set(tx, prefix.clone(), link.clone());
+                    hook_send!(sender, hook_sender, action);
+
+                    match rx.recv().await {
+                        Some(response) => match response {
+                            HookManagerResponse::Ok => {
+                                send_response!(sender, db.save_hook(&prefix, &link));
+                            }
+                            HookManagerResponse::Error(message) => {
+                                send_response!(sender, Err(ErrorKind::Conflict(message)))
+                            }
+                            _ => send_response!(
+                                sender,
+                                Err(ErrorKind::InternalError("Failed to add hook".to_string()))
+                            ),
+                        },
+                        None => hook_receive_failed!(sender, "failed to get answer"),
                     }
                 }
-                // Handle ListKeys action
-                DatabaseAction::ListKeys(sender, key, level) => {
-                    match db.list_keys(KeyType::Record(key.clone()), level) {
-                        Ok(list) => send_response!(sender, Ok(list)),
-                        Err(e) => send_response!(sender, Err(e)),
-                    }
+                None => hook_inactive!(sender),
+            }
+
+            write_log!(db, vec![LogItem::SetHook(received_at, prefix, link)]);
+        }
+        // Get links for specific hook
+        DatabaseAction::HookGet(sender, prefix) => {
+            match &db.hook_sender {
+                Some(hook_sender) => {
+                    let (tx, mut rx) = channel(100);
+                    let action = HookManagerAction::Get(tx, prefix.clone());
+                    hook_send!(sender, hook_sender, action);
 
-                    if let Some(sender) = &db.logger_sender {
-                        write_log!(sender, vec![LogItem::ListKeys(received_at, key)]);
+                    match rx.recv().await {
+                        Some(response) => match response {
+                            HookManagerResponse::Hook(prefix, hooks) => {
+                                send_response!(sender, Ok((prefix, hooks)))
+                            }
+                            _ => send_response!(
+                                sender,
+                                Err(ErrorKind::NotFound("Hook is not found".to_string()))
+                            ),
+                        },
+                        None => hook_receive_failed!(sender, "failed to get answer"),
                     }
                 }
-                // Trigger HookManager
-                DatabaseAction::Trigger(sender, key, value) => {
-                    match db
-                        .trigger(
-                            KeyType::Record(key.clone()),
-                            ValueType::RecordPointer(value.clone()),
-                        )
-                        .await
-                    {
-                        Ok(_) => send_response!(sender, Ok(())),
-                        Err(e) => send_response!(sender, Err(e)),
-                    }
+                None => hook_inactive!(sender),
+            }
 
-                    if let Some(sender) = &db.logger_sender {
-                        write_log!(sender, vec![LogItem::Trigger(received_at, key, value)]);
+            write_log!(db, vec![LogItem::GetHook(received_at, prefix)]);
+        }
+        // List hooks
+        DatabaseAction::HookList(sender, prefix) => {
+            match &db.hook_sender {
+                Some(hook_sender) => {
+                    let (tx, mut rx) = channel(100);
+                    let action = HookManagerAction::List(tx, prefix.clone());
+
+                    hook_send!(sender, hook_sender, action);
+
+                    match rx.recv().await {
+                        Some(response) => match response {
+                            HookManagerResponse::HookList(list) => {
+                                send_response!(sender, Ok(list))
+                            }
+                            _ => send_response!(
+                                sender,
+                                Err(ErrorKind::NotFound("Hook is not found".to_string()))
+                            ),
+                        },
+                        None => hook_receive_failed!(sender, "failed to get answer"),
                     }
                 }
-                // Set hook
-                DatabaseAction::HookSet(sender, prefix, link) => {
-                    match &db.hook_sender {
-                        Some(hook_sender) => {
-                            let (tx, mut rx) = channel(100);
-                            let action = HookManagerAction::Set(tx, prefix.clone(), link.clone());
-                            hook_send!(sender, hook_sender, action);
-
-                            match rx.recv().await {
-                                Some(response) => match response {
-                                    HookManagerResponse::Ok => {
-                                        send_response!(sender, Ok(()));
-                                    }
-                                    _ => send_response!(
-                                        sender,
-                                        Err(ErrorKind::InternalError(
-                                            "Failed to add hook".to_string()
-                                        ))
-                                    ),
-                                },
-                                None => hook_receive_failed!(sender, "failed to get answer"),
+                None => hook_inactive!(sender),
+            }
+
+            write_log!(db, vec![LogItem::ListHooks(received_at, prefix)]);
+        }
+        // Remove existing hooks
+        DatabaseAction::HookRemove(sender, prefix, link) => {
+            match &db.hook_sender {
+                Some(hook_sender) => {
+                    let (tx, mut rx) = channel(100);
+                    let action = HookManagerAction::Remove(tx, prefix.clone(), link.clone());
+
+                    hook_send!(sender, hook_sender, action);
+
+                    match rx.recv().await {
+                        Some(response) => match response {
+                            HookManagerResponse::Ok => {
+                                send_response!(sender, db.forget_hook(&prefix, &link));
                             }
-                        }
-                        None => hook_inactive!(sender),
+                            _ => send_response!(
+                                sender,
+                                Err(ErrorKind::NotFound("Hook is not found".to_string()))
+                            ),
+                        },
+                        None => hook_receive_failed!(sender, "failed to get answer"),
                     }
+                }
+                None => hook_inactive!(sender),
+            }
+
+            write_log!(db, vec![LogItem::RemHook(received_at, prefix, link)]);
+        }
+        // Resume logging
+        DatabaseAction::ResumeLog(sender) => {
+            if let Some(logger_sender) = &db.logger_sender {
+                let (tx, mut rx) = channel(100);
+                send_response_with_mutex_sender!(logger_sender, LoggerAction::Resume(tx));
 
-                    if let Some(sender) = &db.logger_sender {
-                        write_log!(sender, vec![LogItem::SetHook(received_at, prefix, link)]);
+                match rx.recv().await {
+                    Some(response) => match response {
+                        LoggerResponse::Ok => send_response!(sender, Ok(())),
+                        LoggerResponse::Err(e) => {
+                            send_response!(sender, Err(ErrorKind::LogError(e)))
+                        }
+                        other => {
+                            tracing::error!(
+                                "logger should have return Ok or Err but returned with: {:?}",
+                                other
+                            );
+                            send_response!(
+                                sender,
+                                Err(ErrorKind::LogError("internal error".to_string()))
+                            );
+                        }
+                    },
+                    None => {
+                        tracing::error!("failed to get answer");
+                        send_response!(
+                            sender,
+                            Err(ErrorKind::LogError("failed to get answer".to_string()))
+                        );
                     }
                 }
-                // Get links for specific hook
-                DatabaseAction::HookGet(sender, prefix) => {
-                    match &db.hook_sender {
-                        Some(hook_sender) => {
-                            let (tx, mut rx) = channel(100);
-                            let action = HookManagerAction::Get(tx, prefix.clone());
-                            hook_send!(sender, hook_sender, action);
-
-                            match rx.recv().await {
-                                Some(response) => match response {
-                                    HookManagerResponse::Hook(prefix, hooks) => {
-                                        send_response!(sender, Ok((prefix, hooks)))
-                                    }
-                                    _ => send_response!(
-                                        sender,
-                                        Err(ErrorKind::InvalidKey("Hook is not found".to_string()))
-                                    ),
-                                },
-                                None => hook_receive_failed!(sender, "failed to get answer"),
-                            }
+            }
+        }
+        // Suspend logging
+        DatabaseAction::SuspendLog(sender) => {
+            if let Some(logger_sender) = &db.logger_sender {
+                let (tx, mut rx) = channel(100);
+                send_response_with_mutex_sender!(logger_sender, LoggerAction::Suspend(tx));
+
+                match rx.recv().await {
+                    Some(response) => match response {
+                        LoggerResponse::Ok => send_response!(sender, Ok(())),
+                        LoggerResponse::Err(e) => {
+                            send_response!(sender, Err(ErrorKind::LogError(e)))
                         }
-                        None => hook_inactive!(sender),
+                        other => {
+                            tracing::error!(
+                                "logger should have return Ok or Err but returned with: {:?}",
+                                other
+                            );
+                            send_response!(
+                                sender,
+                                Err(ErrorKind::LogError("internal error".to_string()))
+                            );
+                        }
+                    },
+                    None => {
+                        tracing::error!("failed to get answer");
+                        send_response!(
+                            sender,
+                            Err(ErrorKind::LogError("failed to get answer".to_string()))
+                        );
                     }
+                }
+            }
+        }
+        // Increment a counter, the new value is filtered like a set, then it is logged so
+        // replay does not depend on the old one
+        DatabaseAction::Increment(sender, key, by) => {
+            let (key, value) = match db
+                .incremented(KeyType::Record(key.clone()), by)
+                .and_then(|value| db.filter_write(WriteKind::Set, key, value))
+            {
+                Ok(filtered) => filtered,
+                Err(e) => {
+                    send_response!(sender, Err(e));
+                    return;
+                }
+            };
 
-                    if let Some(sender) = &db.logger_sender {
-                        write_log!(sender, vec![LogItem::GetHook(received_at, prefix)]);
-                    }
+            match db
+                .insert(
+                    KeyType::Record(key.clone()),
+                    ValueType::RecordPointer(value.clone()),
+                )
+                .await
+            {
+                Ok(_) => {
+                    send_response!(sender, Ok(ValueType::RecordPointer(value.clone())));
+
+                    write_log!(db, vec![LogItem::SetTypedKey(received_at, key, value)]);
                 }
-                // List hooks
-                DatabaseAction::HookList(sender, prefix) => {
-                    match &db.hook_sender {
-                        Some(hook_sender) => {
-                            let (tx, mut rx) = channel(100);
-                            let action = HookManagerAction::List(tx, prefix.clone());
-
-                            hook_send!(sender, hook_sender, action);
-
-                            match rx.recv().await {
-                                Some(response) => match response {
-                                    HookManagerResponse::HookList(list) => {
-                                        send_response!(sender, Ok(list))
-                                    }
-                                    _ => send_response!(
-                                        sender,
-                                        Err(ErrorKind::InvalidKey("Hook is not found".to_string()))
-                                    ),
-                                },
-                                None => hook_receive_failed!(sender, "failed to get answer"),
-                            }
-                        }
-                        None => hook_inactive!(sender),
-                    }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        // Push to a queue
+        DatabaseAction::Push(sender, key, value) => {
+            let (key, value) = match db.filter_write(WriteKind::Push, key, value.into()) {
+                Ok((key, value)) => (key, value.to_string()),
+                Err(e) => {
+                    send_response!(sender, Err(e));
+                    return;
+                }
+            };
 
-                    if let Some(sender) = &db.logger_sender {
-                        write_log!(sender, vec![LogItem::ListHooks(received_at, prefix)]);
-                    }
+            let result = db.push(KeyType::Record(key.clone()), value.clone()).await;
+            let pushed = result.is_ok();
+            send_response!(sender, result);
+
+            if pushed {
+                write_log!(db, vec![LogItem::Push(received_at, key.clone(), value)]);
+            }
+
+            if pushed {
+                serve_waiters(db, waiters, &key, received_at).await;
+            }
+        }
+        // Push to the specified end of queue, the length is returned before waiters are served
+        DatabaseAction::PushTo(sender, key, value, end) => {
+            let (key, value) = match db.filter_write(WriteKind::Push, key, value.into()) {
+                Ok((key, value)) => (key, value.to_string()),
+                Err(e) => {
+                    send_response!(sender, Err(e));
+                    return;
                 }
-                // Remove existing hooks
-                DatabaseAction::HookRemove(sender, prefix, link) => {
-                    match &db.hook_sender {
-                        Some(hook_sender) => {
-                            let (tx, mut rx) = channel(100);
-                            let action =
-                                HookManagerAction::Remove(tx, prefix.clone(), link.clone());
-
-                            hook_send!(sender, hook_sender, action);
-
-                            match rx.recv().await {
-                                Some(response) => match response {
-                                    HookManagerResponse::Ok => {
-                                        send_response!(sender, Ok(()));
-                                    }
-                                    _ => send_response!(
-                                        sender,
-                                        Err(ErrorKind::InvalidKey("Hook is not found".to_string()))
-                                    ),
-                                },
-                                None => hook_receive_failed!(sender, "failed to get answer"),
-                            }
-                        }
-                        None => hook_inactive!(sender),
-                    }
+            };
 
-                    if let Some(sender) = &db.logger_sender {
-                        write_log!(sender, vec![LogItem::RemHook(received_at, prefix, link)]);
-                    }
+            let result = match db
+                .push_to(KeyType::Record(key.clone()), value.clone(), end)
+                .await
+            {
+                Ok(_) => db.queue_length(KeyType::Record(key.clone())),
+                Err(e) => Err(e),
+            };
+            let pushed = result.is_ok();
+            send_response!(sender, result);
+
+            if pushed {
+                let item = match end {
+                    QueueEnd::Front => LogItem::PushFront(received_at, key.clone(), value),
+                    QueueEnd::Back => LogItem::Push(received_at, key.clone(), value),
+                };
+                write_log!(db, vec![item]);
+
+                serve_waiters(db, waiters, &key, received_at).await;
+            }
+        }
+        // Pop from queue
+        DatabaseAction::Pop(sender, key) => match db.pop(KeyType::Record(key.clone())).await {
+            Ok(value) => {
+                send_response!(sender, Ok(ValueType::RecordPointer(value.into())));
+
+                write_log!(db, vec![LogItem::Pop(received_at, key)]);
+            }
+            Err(e) => send_response!(sender, Err(e)),
+        },
+        // Pop from the specified end of queue
+        DatabaseAction::PopFrom(sender, key, end) => {
+            match db.pop_from(KeyType::Record(key.clone()), end).await {
+                Ok(value) => {
+                    send_response!(sender, Ok(ValueType::RecordPointer(value.into())));
+
+                    write_log!(db, vec![pop_log_item(received_at, key, end)]);
                 }
-                // Resume logging
-                DatabaseAction::ResumeLog(sender) => {
-                    if let Some(logger_sender) = &db.logger_sender {
-                        let (tx, mut rx) = channel(100);
-                        send_response_with_mutex_sender!(logger_sender, LoggerAction::Resume(tx));
-
-                        match rx.recv().await {
-                            Some(response) => match response {
-                                LoggerResponse::Ok => send_response!(sender, Ok(())),
-                                LoggerResponse::Err(e) => {
-                                    send_response!(sender, Err(ErrorKind::LogError(e)))
-                                }
-                                other => {
-                                    tracing::error!("logger should have return Ok or Err but returned with: {:?}", other);
-                                    send_response!(
-                                        sender,
-                                        Err(ErrorKind::LogError("internal error".to_string()))
-                                    );
-                                }
-                            },
-                            None => {
-                                tracing::error!("failed to get answer");
-                                send_response!(
-                                    sender,
-                                    Err(ErrorKind::LogError("failed to get answer".to_string()))
-                                );
-                            }
-                        }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        // Pop items until the count is reached or the queue is empty, only the first pop can fail
+        DatabaseAction::PopMany(sender, key, end, count) => {
+            let mut items = Vec::new();
+            let mut log_items = Vec::new();
+            while items.len() < count {
+                match db.pop_from(KeyType::Record(key.clone()), end).await {
+                    Ok(value) => {
+                        items.push(value);
+                        log_items.push(pop_log_item(received_at, key.clone(), end));
+                    }
+                    Err(e) if items.is_empty() => {
+                        send_response!(sender, Err(e));
+                        return;
                     }
+                    Err(_) => break,
                 }
-                // Suspend logging
-                DatabaseAction::SuspendLog(sender) => {
-                    if let Some(logger_sender) = &db.logger_sender {
-                        let (tx, mut rx) = channel(100);
-                        send_response_with_mutex_sender!(logger_sender, LoggerAction::Suspend(tx));
-
-                        match rx.recv().await {
-                            Some(response) => match response {
-                                LoggerResponse::Ok => send_response!(sender, Ok(())),
-                                LoggerResponse::Err(e) => {
-                                    send_response!(sender, Err(ErrorKind::LogError(e)))
-                                }
-                                other => {
-                                    tracing::error!("logger should have return Ok or Err but returned with: {:?}", other);
-                                    send_response!(
-                                        sender,
-                                        Err(ErrorKind::LogError("internal error".to_string()))
-                                    );
-                                }
-                            },
-                            None => {
-                                tracing::error!("failed to get answer");
-                                send_response!(
-                                    sender,
-                                    Err(ErrorKind::LogError("failed to get answer".to_string()))
-                                );
-                            }
-                        }
+            }
+            send_response!(sender, Ok(items));
+
+            if !log_items.is_empty() {
+                write_log!(db, log_items);
+            }
+        }
+        // Pop from queue or park the request until an item arrives or the timeout expires
+        DatabaseAction::PopWait(sender, key, end, timeout) => {
+            let deadline =
+                match queue_deadline(timeout, |x| tokio::time::Instant::now().checked_add(x)) {
+                    Ok(deadline) => deadline,
+                    Err(e) => {
+                        send_response!(sender, Err(e));
+                        return;
                     }
+                };
+
+            match db.pop_from(KeyType::Record(key.clone()), end).await {
+                Ok(value) => {
+                    send_response!(sender, Ok(ValueType::RecordPointer(value.into())));
+
+                    write_log!(db, vec![pop_log_item(received_at, key, end)]);
+                }
+                Err(ErrorKind::NotFound(_)) if !timeout.is_zero() => {
+                    tracing::trace!("park waiter of '{}' for {:?}", key, timeout);
+                    waiters
+                        .entry(queue_name(&key))
+                        .or_default()
+                        .push_back(QueueWaiter {
+                            sender,
+                            end,
+                            deadline,
+                        });
+                }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        // Pop from queue and keep the item in flight until it is acknowledged
+        DatabaseAction::PopReliable(sender, key, visibility) => {
+            let deadline = match queue_deadline(visibility, |x| received_at.checked_add(x)) {
+                Ok(deadline) => deadline,
+                Err(e) => {
+                    send_response!(sender, Err(e));
+                    return;
+                }
+            };
+            let id = db.next_delivery_id();
+
+            match db
+                .pop_reliable(KeyType::Record(key.clone()), id, deadline)
+                .await
+            {
+                Ok(value) => {
+                    send_response!(sender, Ok((id, value)));
+
+                    write_log!(
+                        db,
+                        vec![LogItem::PopReliable(received_at, key, id, deadline)]
+                    );
+                }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        // Acknowledge an in-flight item
+        DatabaseAction::Ack(sender, id) => match db.ack(id) {
+            Ok(_) => {
+                send_response!(sender, Ok(()));
+
+                write_log!(db, vec![LogItem::Ack(received_at, id)]);
+            }
+            Err(e) => send_response!(sender, Err(e)),
+        },
+        // Set field of a hash, filters get the value of field
+        DatabaseAction::HashSet(sender, key, field, value) => {
+            let (key, value) = match db.filter_write(WriteKind::HashSet, key, value.into()) {
+                Ok((key, value)) => (key, value.to_string()),
+                Err(e) => {
+                    send_response!(sender, Err(e));
+                    return;
+                }
+            };
+
+            match db
+                .hash_set(KeyType::Record(key.clone()), field.clone(), value.clone())
+                .await
+            {
+                Ok(added) => {
+                    send_response!(sender, Ok(added));
+
+                    write_log!(db, vec![LogItem::HashSet(received_at, key, field, value)]);
+                }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        // Get field of a hash
+        DatabaseAction::HashGet(sender, key, field) => {
+            send_response!(
+                sender,
+                db.hash_get(KeyType::Record(key.clone()), &field)
+                    .map(|value| ValueType::RecordPointer(value.into()))
+            );
+
+            write_log!(db, vec![LogItem::HashGet(received_at, key, field)]);
+        }
+        // Remove field of a hash
+        DatabaseAction::HashDelete(sender, key, field) => {
+            match db.hash_delete(KeyType::Record(key.clone()), &field).await {
+                Ok(_) => {
+                    send_response!(sender, Ok(()));
+
+                    write_log!(db, vec![LogItem::HashDelete(received_at, key, field)]);
+                }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        // Get every field of a hash
+        DatabaseAction::HashGetAll(sender, key) => {
+            send_response!(sender, db.hash_get_all(KeyType::Record(key.clone())));
+
+            write_log!(db, vec![LogItem::HashGetAll(received_at, key)]);
+        }
+        // Add member to a set, it is logged only if set has been changed
+        DatabaseAction::SetAdd(sender, key, member) => {
+            let (key, member) = match db.filter_write(WriteKind::SetAdd, key, member.into()) {
+                Ok((key, member)) => (key, member.to_string()),
+                Err(e) => {
+                    send_response!(sender, Err(e));
+                    return;
                 }
-                // Push to a queue
-                DatabaseAction::Push(sender, key, value) => {
-                    match db.push(KeyType::Record(key.clone()), value.clone()).await {
-                        Ok(_) => send_response!(sender, Ok(())),
-                        Err(e) => send_response!(sender, Err(e)),
+            };
+
+            match db
+                .set_add(KeyType::Record(key.clone()), member.clone())
+                .await
+            {
+                Ok(added) => {
+                    send_response!(sender, Ok(added));
+
+                    if added {
+                        write_log!(db, vec![LogItem::SetAdd(received_at, key, member)]);
                     }
+                }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        // Remove member from a set, it is logged only if set has been changed
+        DatabaseAction::SetRemove(sender, key, member) => {
+            match db.set_remove(KeyType::Record(key.clone()), &member).await {
+                Ok(removed) => {
+                    send_response!(sender, Ok(removed));
 
-                    if let Some(sender) = &db.logger_sender {
-                        write_log!(sender, vec![LogItem::Push(received_at, key, value)]);
+                    if removed {
+                        write_log!(db, vec![LogItem::SetRemove(received_at, key, member)]);
                     }
                 }
-                // Pop from queue
-                DatabaseAction::Pop(sender, key) => {
-                    match db.pop(KeyType::Record(key.clone())).await {
-                        Ok(value) => send_response!(sender, Ok(ValueType::RecordPointer(value))),
-                        Err(e) => send_response!(sender, Err(e)),
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        // Get members of a set
+        DatabaseAction::SetMembers(sender, key) => {
+            send_response!(sender, db.set_members(KeyType::Record(key.clone())));
+
+            write_log!(db, vec![LogItem::SetMembers(received_at, key)]);
+        }
+        // Check membership in a set
+        DatabaseAction::SetIsMember(sender, key, member) => {
+            send_response!(
+                sender,
+                db.set_is_member(KeyType::Record(key.clone()), &member)
+            );
+
+            write_log!(db, vec![LogItem::SetIsMember(received_at, key, member)]);
+        }
+        // Read an item of queue without removing it
+        DatabaseAction::Peek(sender, key, end) => {
+            send_response!(
+                sender,
+                db.peek(KeyType::Record(key.clone()), end)
+                    .map(|value| ValueType::RecordPointer(value.into()))
+            );
+
+            write_log!(db, vec![LogItem::Peek(received_at, key)]);
+        }
+        // Number of items in queue
+        DatabaseAction::QueueLength(sender, key) => {
+            send_response!(sender, db.queue_length(KeyType::Record(key.clone())));
+
+            write_log!(db, vec![LogItem::QueueLength(received_at, key)]);
+        }
+        // Export a subtree with its hooks
+        DatabaseAction::Export(sender, key) => {
+            let mut snapshot = match db.export(KeyType::Record(key.clone())) {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    send_response!(sender, Err(e));
+                    return;
+                }
+            };
+
+            if let Some(hook_sender) = &db.hook_sender {
+                let (tx, mut rx) = channel(100);
+                hook_send!(sender, hook_sender, HookManagerAction::List(tx, key));
+
+                match rx.recv().await {
+                    Some(HookManagerResponse::HookList(list)) => {
+                        snapshot.hook = list
+                            .into_iter()
+                            .map(|(prefix, links)| SnapshotHook { prefix, links })
+                            .collect();
+                    }
+                    _ => {
+                        hook_receive_failed!(sender, "failed to get hook list");
+                        return;
                     }
+                }
+            }
 
-                    if let Some(sender) = &db.logger_sender {
-                        write_log!(sender, vec![LogItem::Pop(received_at, key)]);
+            send_response!(sender, Ok(snapshot));
+        }
+        // Import a snapshot, hooks are passed to the hook manager
+        // Content is filtered before, the filtered snapshot is imported and logged
+        DatabaseAction::Import(sender, mut snapshot, policy) => {
+            let hooks = std::mem::take(&mut snapshot.hook);
+            if !hooks.is_empty() && db.hook_sender.is_none() {
+                hook_inactive!(sender);
+                return;
+            }
+
+            if let Err(e) = db.filter_snapshot(&mut snapshot) {
+                send_response!(sender, Err(e));
+                return;
+            }
+
+            let mut summary = match db.import(&snapshot, policy).await {
+                Ok(summary) => summary,
+                Err(e) => {
+                    send_response!(sender, Err(e));
+                    return;
+                }
+            };
+
+            let queues: Vec<String> = snapshot.queue.iter().map(|x| x.key.clone()).collect();
+            write_log!(db, vec![LogItem::Import(received_at, snapshot, policy)]);
+
+            match import_hooks(db, hooks, policy, received_at).await {
+                Ok(hook_summary) => {
+                    summary.imported += hook_summary.imported;
+                    summary.skipped += hook_summary.skipped;
+                    send_response!(sender, Ok(summary));
+                }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+
+            for key in queues {
+                serve_waiters(db, waiters, &key, received_at).await;
+            }
+        }
+        // Subscribe to change events
+        DatabaseAction::Watch(sender) => {
+            send_response!(sender, Ok(db.subscribe_to_events()));
+        }
+        DatabaseAction::WithContext(_, _) => unreachable!("context is unwrapped before"),
+        DatabaseAction::Batch(sender, _) => {
+            let e = ErrorKind::InvalidKey("Batch cannot be part of a batch".to_string());
+            send_response!(sender, Err(e));
+        }
+        // Read the changes of a key from the append file. Logger scans the file in a separate task,
+        // so the database is not locked meanwhile. Logs of the earlier requests are already sent.
+        DatabaseAction::History(sender, key) => {
+            if let Err(e) = internal::validate_key(&key, &db.name) {
+                send_response!(sender, Err(e));
+                return;
+            }
+
+            let logger_sender = match &db.logger_sender {
+                Some(logger_sender) => logger_sender.clone(),
+                None => {
+                    send_response!(
+                        sender,
+                        Err(ErrorKind::LogError("logging is disabled".to_string()))
+                    );
+                    return;
+                }
+            };
+
+            tokio::spawn(async move {
+                let (tx, mut rx) = channel(10);
+                send_response_with_mutex_sender!(logger_sender, LoggerAction::History(tx, key));
+
+                match rx.recv().await {
+                    Some(LoggerResponse::History(history)) => {
+                        send_response!(sender, Ok(history))
+                    }
+                    Some(LoggerResponse::Err(e)) => {
+                        send_response!(sender, Err(ErrorKind::LogError(e)))
                     }
+                    other => {
+                        tracing::error!(
+                            "logger should have return History or Err but returned with: {:?}",
+                            other
+                        );
+                        send_response!(
+                            sender,
+                            Err(ErrorKind::LogError("internal error".to_string()))
+                        );
+                    }
+                }
+            });
+        }
+        // Persist the pending changes of storage and append file
+        DatabaseAction::Flush(sender) => {
+            if let Some(logger_sender) = &db.logger_sender {
+                let (tx, mut rx) = channel(1);
+                send_response_with_mutex_sender!(logger_sender, LoggerAction::Write(tx, vec![]));
+                if let Some(LoggerResponse::Err(e)) = rx.recv().await {
+                    send_response!(sender, Err(ErrorKind::LogError(e)));
+                    return;
                 }
             }
+
+            send_response!(sender, db.flush());
         }
-    });
+        // Replace the limits, they apply from the next write
+        DatabaseAction::SetLimits(sender, key, limits) => {
+            match internal::validate_key(&key, &db.name) {
+                Ok(_) => {
+                    tracing::debug!("limits are set, policy is {}", limits.policy);
+                    db.set_limits(limits);
+                    send_response!(sender, Ok(()));
+                }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        // Replace the schemas, they apply from the next write
+        DatabaseAction::SetSchemas(sender, key, schemas) => {
+            match internal::validate_key(&key, &db.name) {
+                Ok(_) => {
+                    tracing::debug!("{} schemas are set", schemas.len());
+                    send_response!(sender, db.set_schemas(schemas));
+                }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        // Replace the filters, they apply from the next write
+        DatabaseAction::SetFilters(sender, key, filters) => {
+            match internal::validate_key(&key, &db.name) {
+                Ok(_) => {
+                    tracing::debug!("{} filters are set", filters.len());
+                    db.set_filters(filters);
+                    send_response!(sender, Ok(()));
+                }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        // Replace the computed keys, they are computed after this action
+        DatabaseAction::SetComputed(sender, key, computed) => {
+            match internal::validate_key(&key, &db.name) {
+                Ok(_) => {
+                    tracing::debug!("{} computed keys are set", computed.len());
+                    send_response!(sender, db.set_computed(computed));
+                }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        // Replace the value indexes, they are built from the existing records
+        DatabaseAction::SetIndexes(sender, key, prefixes) => {
+            match internal::validate_key(&key, &db.name) {
+                Ok(_) => {
+                    tracing::debug!("{} value indexes are set", prefixes.len());
+                    send_response!(sender, db.set_indexes(prefixes));
+                }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        // Find records by value index
+        DatabaseAction::Find(sender, key, value) => {
+            let result = db
+                .find(&key, &value)
+                .map(|keys| keys.into_iter().map(KeyType::Record).collect());
+            send_response!(sender, result);
+        }
+        // Return with the limits and current usage
+        DatabaseAction::Usage(sender, key) => match internal::validate_key(&key, &db.name) {
+            Ok(_) => send_response!(sender, Ok(db.usage())),
+            Err(e) => send_response!(sender, Err(e)),
+        },
+        // Return with the metadata of key or table
+        DatabaseAction::Stat(sender, key) => {
+            send_response!(sender, db.stat(&key));
+        }
+        // Move or copy a key or a table, it is logged as one item whatever its size is
+        // Filters of destination are called, but they can only reject the values
+        DatabaseAction::Move(sender, key, destination) => {
+            match db.relocate(key.clone(), &destination, true, true).await {
+                Ok(_) => {
+                    send_response!(sender, Ok(()));
+
+                    write_log!(
+                        db,
+                        vec![LogItem::Move(received_at, key, destination.clone())]
+                    );
+
+                    serve_relocated_waiters(db, waiters, &destination, received_at).await;
+                }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+        DatabaseAction::Copy(sender, key, destination) => {
+            match db.relocate(key.clone(), &destination, false, true).await {
+                Ok(_) => {
+                    send_response!(sender, Ok(()));
+
+                    write_log!(
+                        db,
+                        vec![LogItem::Copy(received_at, key, destination.clone())]
+                    );
+
+                    serve_relocated_waiters(db, waiters, &destination, received_at).await;
+                }
+                Err(e) => send_response!(sender, Err(e)),
+            }
+        }
+    }
+}
+
+/// Apply the items of an append file on the database, e.g. to restore it during startup.
+/// Hooks are restored only if the database is subscribed to a hook manager.
+/// Error contains the index of the item that could not be applied and the reason.
+///
+/// # Arguments
+/// 1. `db` - Database where the items are applied
+/// 1. `list` - Items of append file in the order of their writing
+pub async fn replay_log(db: &mut Database, list: Vec<LogItem>) -> Result<(), (usize, String)> {
+    let mut result = Ok(());
+    for (index, item) in list.into_iter().enumerate() {
+        let display = item.to_string();
+        if let Err(e) = replay_item(db, item).await {
+            result = Err((index, format!("failed to replay '{}': {}", display, e)));
+            break;
+        }
+    }
+
+    db.replay_time = None;
+    result
+}
+
+/// Apply one item, changes are dated with the time of the log item
+async fn replay_item(db: &mut Database, item: LogItem) -> Result<(), String> {
+    db.replay_time = match item.get_duration() {
+        0 => None,
+        nanos => Some(Duration::from_nanos(nanos as u64)),
+    };
+
+    match item.into_inner() {
+        LogItem::SetKey(_, key, value) => {
+            db.insert(KeyType::Record(key), ValueType::RecordPointer(value.into()))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        LogItem::SetTypedKey(_, key, value) => {
+            db.insert(KeyType::Record(key), ValueType::RecordPointer(value))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        // Older append files contain the failed deletes too, they have not changed anything
+        LogItem::RemKey(_, key) => {
+            let _ = db.delete_key(KeyType::Record(key)).await;
+        }
+        LogItem::RemPath(_, key) => {
+            let _ = db.delete_table(KeyType::Table(key)).await;
+        }
+        LogItem::Expire(_, key, deadline) => {
+            db.expire(KeyType::Record(key), deadline)
+                .map_err(|e| e.to_string())?;
+        }
+        LogItem::RemTypedKey(_, key) => {
+            db.delete_key(key).await.map_err(|e| e.to_string())?;
+        }
+        LogItem::SetHook(_, prefix, link) => {
+            if let Some(hook_sender) = &db.hook_sender {
+                let action = |tx| HookManagerAction::Set(tx, prefix, link);
+                let a = ask_hook_manager(hook_sender, action)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                if a != HookManagerResponse::Ok
+                    && a != HookManagerResponse::Error("Already defined".to_string())
+                {
+                    return Err(format!("failed to set hook: {:?}", a));
+                }
+            }
+        }
+        LogItem::RemHook(_, prefix, link) => {
+            if let Some(hook_sender) = &db.hook_sender {
+                let action = |tx| HookManagerAction::Remove(tx, prefix, link);
+                let a = ask_hook_manager(hook_sender, action)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                if a != HookManagerResponse::Ok {
+                    return Err(format!("failed to remove hook: {:?}", a));
+                }
+            }
+        }
+        LogItem::Push(_, key, value) => {
+            db.push(KeyType::Record(key), value)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        LogItem::PushFront(_, key, value) => {
+            db.push_to(KeyType::Record(key), value, QueueEnd::Front)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        LogItem::Pop(_, key) => {
+            // Older append files may contain failed pops too
+            let _ = db.pop(KeyType::Record(key)).await;
+        }
+        LogItem::PopBack(_, key) => {
+            let _ = db.pop_from(KeyType::Record(key), QueueEnd::Back).await;
+        }
+        LogItem::PopReliable(_, key, id, deadline) => {
+            let _ = db.pop_reliable(KeyType::Record(key), id, deadline).await;
+        }
+        LogItem::Ack(_, id) => {
+            let _ = db.ack(id);
+        }
+        LogItem::Requeue(_, id) => {
+            let _ = db.requeue(id).await;
+        }
+        LogItem::HashSet(_, key, field, value) => {
+            db.hash_set(KeyType::Record(key), field, value)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        LogItem::HashDelete(_, key, field) => {
+            db.hash_delete(KeyType::Record(key), &field)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        LogItem::SetAdd(_, key, member) => {
+            db.set_add(KeyType::Record(key), member)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        LogItem::SetRemove(_, key, member) => {
+            db.set_remove(KeyType::Record(key), &member)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        LogItem::Import(_, snapshot, policy) => {
+            db.import(&snapshot, policy)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        LogItem::Move(_, key, destination) => {
+            db.move_key(key, &destination)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        LogItem::Copy(_, key, destination) => {
+            db.copy_key(key, &destination)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// Return with the time when a queue timeout expires. Timeouts come from the clients,
+/// so the ones above `MAX_QUEUE_TIMEOUT` or that would overflow the time are rejected.
+///
+/// # Arguments
+/// 1. `timeout` - Wait of blocking pop or visibility of reliable pop
+/// 1. `add` - Add the timeout to the start time, e.g. `Instant::checked_add`
+fn queue_deadline<T>(
+    timeout: Duration,
+    add: impl FnOnce(Duration) -> Option<T>,
+) -> Result<T, ErrorKind> {
+    let deadline = match timeout <= MAX_QUEUE_TIMEOUT {
+        true => add(timeout),
+        false => None,
+    };
+
+    deadline.ok_or_else(|| {
+        ErrorKind::InvalidKey(format!(
+            "Timeout cannot be longer than {} seconds",
+            MAX_QUEUE_TIMEOUT.as_secs()
+        ))
+    })
+}
+
+/// Log item of a successful pop from the specified end
+fn pop_log_item(received_at: Duration, key: String, end: QueueEnd) -> LogItem {
+    match end {
+        QueueEnd::Front => LogItem::Pop(received_at, key),
+        QueueEnd::Back => LogItem::PopBack(received_at, key),
+    }
+}
+
+/// Add hooks of an imported snapshot according to the policy, changes are logged like at hook actions
+async fn import_hooks(
+    db: &mut Database,
+    hooks: Vec<SnapshotHook>,
+    policy: ImportPolicy,
+    received_at: Duration,
+) -> Result<ImportSummary, ErrorKind> {
+    let mut summary = ImportSummary::default();
+    let hook_sender = match db.hook_sender.clone() {
+        Some(hook_sender) => hook_sender,
+        None if hooks.is_empty() => return Ok(summary),
+        None => return Err(ErrorKind::InactiveHookManager),
+    };
+
+    for hook in hooks {
+        let existing = match ask_hook_manager(&hook_sender, |tx| {
+            HookManagerAction::Get(tx, hook.prefix.clone())
+        })
+        .await?
+        {
+            HookManagerResponse::Hook(_, links) => links,
+            _ => Vec::new(),
+        };
+
+        if !existing.is_empty() && policy == ImportPolicy::Skip {
+            summary.skipped += 1;
+            continue;
+        }
+
+        let mut log = Vec::new();
+
+        if policy == ImportPolicy::Overwrite {
+            for link in existing.iter().filter(|x| !hook.links.contains(x)) {
+                ask_hook_manager(&hook_sender, |tx| {
+                    HookManagerAction::Remove(tx, hook.prefix.clone(), link.clone())
+                })
+                .await?;
+                db.forget_hook(&hook.prefix, link)?;
+                log.push(LogItem::RemHook(
+                    received_at,
+                    hook.prefix.clone(),
+                    link.clone(),
+                ));
+            }
+        }
+
+        for link in hook.links.iter().filter(|x| !existing.contains(x)) {
+            match ask_hook_manager(&hook_sender, |tx| {
+                HookManagerAction::Set(tx, hook.prefix.clone(), link.clone())
+            })
+            .await?
+            {
+                HookManagerResponse::Ok => (),
+                HookManagerResponse::Error(message) => return Err(ErrorKind::Conflict(message)),
+                _ => return Err(ErrorKind::InternalError("Failed to add hook".to_string())),
+            }
+            db.save_hook(&hook.prefix, link)?;
+            log.push(LogItem::SetHook(
+                received_at,
+                hook.prefix.clone(),
+                link.clone(),
+            ));
+        }
+
+        if !log.is_empty() {
+            write_log!(db, log);
+        }
+
+        summary.imported += 1;
+    }
+
+    Ok(summary)
+}
+
+/// Set the hooks that are saved in persistent storage, append file is not replayed for them
+async fn restore_hooks(db: &Database) -> Result<(), ErrorKind> {
+    let hook_sender = match &db.hook_sender {
+        Some(hook_sender) => hook_sender,
+        None => return Ok(()),
+    };
+
+    for (prefix, link) in db.saved_hooks()? {
+        tracing::debug!("restore hook {}@{}", prefix, link);
+        match ask_hook_manager(hook_sender, |tx| HookManagerAction::Set(tx, prefix, link)).await? {
+            HookManagerResponse::Ok | HookManagerResponse::Error(_) => (),
+            _ => return Err(ErrorKind::InternalError("Failed to add hook".to_string())),
+        }
+    }
+
+    Ok(())
+}
+
+/// Send an action to the hook manager and wait for its answer
+async fn ask_hook_manager<F>(
+    hook_sender: &Sender<HookManagerAction>,
+    action: F,
+) -> Result<HookManagerResponse, ErrorKind>
+where
+    F: FnOnce(Sender<HookManagerResponse>) -> HookManagerAction,
+{
+    let (tx, mut rx) = channel(10);
+    if let Err(e) = hook_sender.send(action(tx)).await {
+        tracing::error!("Failed to send to hook manager: {}", e);
+        return Err(ErrorKind::InternalError(
+            "Failed to send to hook manager".to_string(),
+        ));
+    }
+
+    match rx.recv().await {
+        Some(response) => Ok(response),
+        None => Err(ErrorKind::InternalError(
+            "Failed to receive from hook manager".to_string(),
+        )),
+    }
+}
 
-    (tx, thread)
+/// Filter the record, write it and log it, rejected writes are not logged, so replay does not apply them.
+/// Change of expiration is logged as a separate item, because records are set by other actions too.
+async fn set_record(
+    db: &mut Database,
+    sender: Sender<ResultWithoutResult>,
+    key: String,
+    value: RecordValue,
+    expiration: Expiration,
+    received_at: Duration,
+) {
+    let (key, value) = match db.filter_write(WriteKind::Set, key, value) {
+        Ok(filtered) => filtered,
+        Err(e) => {
+            send_response!(sender, Err(e));
+            return;
+        }
+    };
+
+    let record = KeyType::Record(key.clone());
+    let deadline = match (expiration, db.expiration(&record)) {
+        (Expiration::After(after), _) => match expiration_deadline(received_at, after) {
+            Ok(deadline) => Some(Some(deadline)),
+            Err(e) => {
+                send_response!(sender, Err(e));
+                return;
+            }
+        },
+        (Expiration::Remove, Ok(Some(_))) => Some(None),
+        _ => None,
+    };
+
+    match db
+        .insert(
+            KeyType::Record(key.clone()),
+            ValueType::RecordPointer(value.clone()),
+        )
+        .await
+    {
+        Ok(_) => {
+            if let Some(deadline) = deadline {
+                let _ = db.expire(record, deadline);
+            }
+            send_response!(sender, Ok(()));
+
+            let mut items = vec![match value {
+                RecordValue::String(value) => LogItem::SetKey(received_at, key.clone(), value),
+                value => LogItem::SetTypedKey(received_at, key.clone(), value),
+            }];
+            if let Some(deadline) = deadline {
+                items.push(LogItem::Expire(received_at, key, deadline));
+            }
+            write_log!(db, items);
+        }
+        Err(e) => send_response!(sender, Err(e)),
+    }
+}
+
+/// Return with the time since UNIX epoch when a record expires, it fails if the time would overflow
+fn expiration_deadline(received_at: Duration, after: Duration) -> Result<Duration, ErrorKind> {
+    received_at
+        .checked_add(after)
+        .ok_or_else(|| ErrorKind::InvalidKey("Expiration time is too far".to_string()))
+}
+
+/// Delete the records whose expiration time has passed, they are logged like the other deletes
+async fn delete_expired(db: &mut Database) {
+    let now = match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("Failed to get system timer: {}", e);
+            return;
+        }
+    };
+
+    for key in db.expired_records(now) {
+        tracing::debug!("record '{}' has expired", key);
+        match db.delete_key(KeyType::Record(key.clone())).await {
+            Ok(_) => {
+                write_log!(db, vec![LogItem::RemKey(now, key)]);
+            }
+            Err(e) => {
+                tracing::error!("failed to delete expired record '{}': {}", key, e);
+                let _ = db.expire(KeyType::Record(key.clone()), None);
+
+                write_log!(db, vec![LogItem::Expire(now, key, None)]);
+            }
+        }
+    }
+}
+
+/// Name of queue as waiters are parked by it, so `/root/q` and `root/q/` belong to the same waiters
+fn queue_name(key: &str) -> String {
+    let routes: Vec<&str> = key.split('/').filter(|x| !x.is_empty()).collect();
+    format!("/{}", routes.join("/"))
+}
+
+/// Serve the waiters of queues that are moved or copied to the destination, it can be a table of queues
+async fn serve_relocated_waiters(
+    db: &mut Database,
+    waiters: &mut QueueWaiters,
+    destination: &str,
+    received_at: Duration,
+) {
+    let destination = queue_name(destination);
+    let queues: Vec<String> = waiters
+        .keys()
+        .filter(|x| super::stat::is_under(x, &destination))
+        .cloned()
+        .collect();
+
+    for key in queues {
+        serve_waiters(db, waiters, &key, received_at).await;
+    }
+}
+
+/// Hand over items of the queue to its parked waiters while there is any
+async fn serve_waiters(
+    db: &mut Database,
+    waiters: &mut QueueWaiters,
+    key: &str,
+    received_at: Duration,
+) {
+    let name = queue_name(key);
+    let parked = match waiters.get_mut(&name) {
+        Some(parked) => parked,
+        None => return,
+    };
+
+    while !parked.is_empty() {
+        match db.queue_length(KeyType::Record(key.to_string())) {
+            Ok(length) if length > 0 => (),
+            _ => break,
+        }
+
+        let waiter = match parked.pop_front() {
+            Some(waiter) => waiter,
+            None => break,
+        };
+
+        // Client has gone meanwhile, the item remains for somebody else
+        if waiter.sender.is_closed() {
+            continue;
+        }
+
+        if let Ok(value) = db
+            .pop_from(KeyType::Record(key.to_string()), waiter.end)
+            .await
+        {
+            send_response!(waiter.sender, Ok(ValueType::RecordPointer(value.into())));
+
+            write_log!(
+                db,
+                vec![pop_log_item(received_at, key.to_string(), waiter.end)]
+            );
+        }
+    }
+
+    if parked.is_empty() {
+        waiters.remove(&name);
+    }
+}
+
+/// Return unacknowledged items to their queue and answer waiters whose timeout has expired
+async fn check_queue_timers(db: &mut Database, waiters: &mut QueueWaiters) {
+    let now = match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("Failed to get system timer: {}", e);
+            return;
+        }
+    };
+
+    for id in db.expired_deliveries(now) {
+        match db.requeue(id).await {
+            Ok(key) => {
+                tracing::debug!("delivery {} is not acknowledged, returned to '{}'", id, key);
+
+                write_log!(db, vec![LogItem::Requeue(now, id)]);
+
+                serve_waiters(db, waiters, &key, now).await;
+            }
+            Err(e) => tracing::error!("failed to requeue delivery {}: {}", id, e),
+        }
+    }
+
+    let instant = tokio::time::Instant::now();
+    for parked in waiters.values_mut() {
+        let mut remaining = VecDeque::with_capacity(parked.len());
+        for waiter in parked.drain(..) {
+            if waiter.sender.is_closed() {
+                continue;
+            }
+
+            if waiter.deadline <= instant {
+                send_response!(
+                    waiter.sender,
+                    Err(ErrorKind::NotFound("Queue is empty".to_string()))
+                );
+                continue;
+            }
+
+            remaining.push_back(waiter);
+        }
+        *parked = remaining;
+    }
+    waiters.retain(|_, parked| !parked.is_empty());
 }
 
 macro_rules! hook_inactive {
@@ -495,7 +1713,7 @@ macro_rules! hook_inactive {
             .unwrap_or_else(|e| tracing::error!("Error during send: {}", e))
     };
 }
-pub(self) use hook_inactive;
+use hook_inactive;
 
 macro_rules! hook_send {
     ($sender:expr, $hook_sender:expr, $action:expr) => {
@@ -505,11 +1723,11 @@ macro_rules! hook_send {
                 .send(Err(ErrorKind::InternalError("".to_string())))
                 .await
                 .unwrap_or_else(|e| tracing::error!("Error during send: {}", e));
-            continue;
+            return;
         }
     };
 }
-pub(self) use hook_send;
+use hook_send;
 
 macro_rules! hook_receive_failed {
     ($sender:expr, $error:expr) => {{
@@ -522,7 +1740,7 @@ macro_rules! hook_receive_failed {
             .unwrap_or_else(|e| tracing::error!("Error during send: {}", e));
     }};
 }
-pub(self) use hook_receive_failed;
+use hook_receive_failed;
 
 macro_rules! send_response {
     ($sender:expr, $value:expr) => {{
@@ -532,7 +1750,7 @@ macro_rules! send_response {
             .unwrap_or_else(|e| tracing::error!("Error during send: {}", e));
     }};
 }
-pub(self) use send_response;
+use send_response;
 
 macro_rules! send_response_with_mutex_sender {
     ($sender:expr, $value:expr) => {{
@@ -542,14 +1760,11 @@ macro_rules! send_response_with_mutex_sender {
             .unwrap_or_else(|e| tracing::error!("Error during send: {}", e));
     }};
 }
-pub(self) use send_response_with_mutex_sender;
+use send_response_with_mutex_sender;
 
 macro_rules! write_log {
-    ($logger_sender:expr, $messages:expr) => {
-        $logger_sender
-            .send(LoggerAction::WriteAsync($messages))
-            .await
-            .unwrap_or_else(|e| tracing::error!("{}", e));
+    ($db:expr, $messages:expr) => {
+        $db.write_log($messages).await
     };
 }
-pub(self) use write_log;
+use write_log;
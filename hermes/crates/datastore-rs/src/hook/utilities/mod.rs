@@ -76,4 +76,4 @@ macro_rules! send_response {
             .unwrap_or_else(|e| tracing::error!("Error during send: {}", e))
     };
 }
-pub(self) use send_response;
+use send_response;
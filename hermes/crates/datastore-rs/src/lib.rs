@@ -70,7 +70,7 @@
 //!
 //! sender.send(get_action).await.expect("Failed to send the get request");
 //! let data = rx.recv().await.expect("Failed to receive message").expect("Failed to get data");
-//! assert_eq!(ValueType::RecordPointer("ok".to_string()), data);
+//! assert_eq!(ValueType::RecordPointer("ok".into()), data);
 //! # })
 //! ```
 //!
@@ -87,10 +87,10 @@
 //! let mut db = Database::new("root".to_string()).unwrap();
 //!
 //! let list: Vec<(KeyType, ValueType)> = vec![
-//!     (KeyType::Record("/root/status/sub1".to_string()), ValueType::RecordPointer("OK".to_string())),
-//!     (KeyType::Record("/root/status/sub2".to_string()), ValueType::RecordPointer("NOK".to_string())),
-//!     (KeyType::Record("/root/network/dns".to_string()), ValueType::RecordPointer("OK".to_string())),
-//!     (KeyType::Record("/root/network/www".to_string()), ValueType::RecordPointer("NOK".to_string())),
+//!     (KeyType::Record("/root/status/sub1".to_string()), ValueType::RecordPointer("OK".into())),
+//!     (KeyType::Record("/root/status/sub2".to_string()), ValueType::RecordPointer("NOK".into())),
+//!     (KeyType::Record("/root/network/dns".to_string()), ValueType::RecordPointer("OK".into())),
+//!     (KeyType::Record("/root/network/www".to_string()), ValueType::RecordPointer("NOK".into())),
 //! ];
 //!
 //! for (key, value) in list {
@@ -2,7 +2,11 @@ use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tokio::sync::mpsc::Sender;
 
-use crate::datastore::enums::pair::KeyType;
+use crate::datastore::enums::{
+    context::RequestContext,
+    pair::{KeyType, RecordValue},
+    snapshot::{ImportPolicy, Snapshot},
+};
 
 /// Item for every action in datastore
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -20,10 +24,71 @@ pub enum LogItem {
     HookExecute(Duration, String, Vec<String>),
     Push(Duration, String, String),
     Pop(Duration, String),
+    SetTypedKey(Duration, String, RecordValue),
+    Peek(Duration, String),
+    QueueLength(Duration, String),
+    PopBack(Duration, String),
+    PopReliable(Duration, String, u64, Duration),
+    Ack(Duration, u64),
+    Requeue(Duration, u64),
+    HashSet(Duration, String, String, String),
+    HashGet(Duration, String, String),
+    HashDelete(Duration, String, String),
+    HashGetAll(Duration, String),
+    SetAdd(Duration, String, String),
+    SetRemove(Duration, String, String),
+    SetMembers(Duration, String),
+    SetIsMember(Duration, String, String),
+    Import(Duration, Snapshot, ImportPolicy),
+    Context(RequestContext, Box<LogItem>),
+    Move(Duration, KeyType, String),
+    Copy(Duration, KeyType, String),
+    RemTypedKey(Duration, KeyType),
+    Expire(Duration, String, Option<Duration>),
+    PushFront(Duration, String, String),
 }
 
 impl LogItem {
+    /// Attach the context of request to every item, items are returned as they are without context
+    pub fn with_context(context: &Option<RequestContext>, items: Vec<LogItem>) -> Vec<LogItem> {
+        match context {
+            Some(context) => items
+                .into_iter()
+                .map(|item| Self::Context(context.clone(), Box::new(item)))
+                .collect(),
+            None => items,
+        }
+    }
+
+    /// Item without its context
+    pub fn inner(&self) -> &LogItem {
+        match self {
+            Self::Context(_, item) => item.inner(),
+            item => item,
+        }
+    }
+
+    /// Take the item out of its context
+    pub fn into_inner(self) -> LogItem {
+        match self {
+            Self::Context(_, item) => item.into_inner(),
+            item => item,
+        }
+    }
+
+    /// Context of the request that has made this item
+    pub fn get_context(&self) -> Option<&RequestContext> {
+        match self {
+            Self::Context(context, _) => Some(context),
+            _ => None,
+        }
+    }
+
     pub fn needs_to_log(&self) -> bool {
+        if let Self::Context(_, item) = self {
+            return item.needs_to_log();
+        }
+
         matches!(
             self,
             Self::SetKey(_, _, _)
@@ -33,15 +98,30 @@ impl LogItem {
                 | Self::RemHook(_, _, _)
                 | Self::Push(_, _, _)
                 | Self::Pop(_, _)
+                | Self::SetTypedKey(_, _, _)
+                | Self::PopBack(_, _)
+                | Self::PopReliable(_, _, _, _)
+                | Self::Ack(_, _)
+                | Self::Requeue(_, _)
+                | Self::HashSet(_, _, _, _)
+                | Self::HashDelete(_, _, _)
+                | Self::SetAdd(_, _, _)
+                | Self::SetRemove(_, _, _)
+                | Self::Import(_, _, _)
+                | Self::Move(_, _, _)
+                | Self::Copy(_, _, _)
+                | Self::RemTypedKey(_, _)
+                | Self::Expire(_, _, _)
+                | Self::PushFront(_, _, _)
         )
     }
 
     pub fn is_rem_hook(&self) -> bool {
-        matches!(&self, Self::RemHook(_, _, _))
+        matches!(self.inner(), Self::RemHook(_, _, _))
     }
 
     pub fn get_key(&self) -> Option<KeyType> {
-        match &self {
+        match self.inner() {
             Self::SetKey(_, key, _) => Some(KeyType::Record(key.to_string())),
             Self::RemKey(_, key) => Some(KeyType::Record(key.to_string())),
             Self::RemPath(_, key) => Some(KeyType::Record(key.to_string())),
@@ -49,22 +129,35 @@ impl LogItem {
             Self::RemHook(_, prefix, _) => Some(KeyType::Record(prefix.to_string())),
             Self::Push(_, key, _) => Some(KeyType::Queue(key.to_string())),
             Self::Pop(_, key) => Some(KeyType::Queue(key.to_string())),
+            Self::SetTypedKey(_, key, _) => Some(KeyType::Record(key.to_string())),
+            Self::PopBack(_, key) => Some(KeyType::Queue(key.to_string())),
+            Self::PopReliable(_, key, _, _) => Some(KeyType::Queue(key.to_string())),
+            Self::HashSet(_, key, _, _) => Some(KeyType::Hash(key.to_string())),
+            Self::HashDelete(_, key, _) => Some(KeyType::Hash(key.to_string())),
+            Self::SetAdd(_, key, _) => Some(KeyType::Set(key.to_string())),
+            Self::SetRemove(_, key, _) => Some(KeyType::Set(key.to_string())),
+            Self::Move(_, key, _) => Some(key.clone()),
+            Self::Copy(_, key, _) => Some(key.clone()),
+            Self::RemTypedKey(_, key) => Some(key.clone()),
+            Self::Expire(_, key, _) => Some(KeyType::Record(key.to_string())),
+            Self::PushFront(_, key, _) => Some(KeyType::Queue(key.to_string())),
             _ => None,
         }
     }
 
     pub fn get_value(&self) -> &str {
-        match &self {
+        match self.inner() {
             Self::SetKey(_, _, value) => value,
             Self::SetHook(_, _, value) => value,
             Self::RemHook(_, _, link) => link,
             Self::Push(_, _, value) => value,
+            Self::PushFront(_, _, value) => value,
             _ => "",
         }
     }
 
     pub fn get_duration(&self) -> u128 {
-        match &self {
+        match self.inner() {
             Self::SetKey(dur, _, _) => dur.as_nanos(),
             Self::RemKey(dur, _) => dur.as_nanos(),
             Self::RemPath(dur, _) => dur.as_nanos(),
@@ -72,6 +165,21 @@ impl LogItem {
             Self::RemHook(dur, _, _) => dur.as_nanos(),
             Self::Push(dur, _, _) => dur.as_nanos(),
             Self::Pop(dur, _) => dur.as_nanos(),
+            Self::SetTypedKey(dur, _, _) => dur.as_nanos(),
+            Self::PopBack(dur, _) => dur.as_nanos(),
+            Self::PopReliable(dur, _, _, _) => dur.as_nanos(),
+            Self::Ack(dur, _) => dur.as_nanos(),
+            Self::Requeue(dur, _) => dur.as_nanos(),
+            Self::HashSet(dur, _, _, _) => dur.as_nanos(),
+            Self::HashDelete(dur, _, _) => dur.as_nanos(),
+            Self::SetAdd(dur, _, _) => dur.as_nanos(),
+            Self::SetRemove(dur, _, _) => dur.as_nanos(),
+            Self::Import(dur, _, _) => dur.as_nanos(),
+            Self::Move(dur, _, _) => dur.as_nanos(),
+            Self::Copy(dur, _, _) => dur.as_nanos(),
+            Self::RemTypedKey(dur, _) => dur.as_nanos(),
+            Self::Expire(dur, _, _) => dur.as_nanos(),
+            Self::PushFront(dur, _, _) => dur.as_nanos(),
             _ => 0,
         }
     }
@@ -122,6 +230,111 @@ impl std::fmt::Display for LogItem {
                 format!("{} Push [ '{}', '{}' ]", duration.as_nanos(), key, value)
             }
             Self::Pop(duration, key) => format!("{} Pop [ '{}' ]", duration.as_nanos(), key),
+            Self::SetTypedKey(duration, key, value) => format!(
+                "{} SetTypedKey [ '{}', {}, '{}' ]",
+                duration.as_nanos(),
+                key,
+                value.get_type(),
+                value
+            ),
+            Self::Peek(duration, key) => format!("{} Peek [ '{}' ]", duration.as_nanos(), key),
+            Self::QueueLength(duration, key) => {
+                format!("{} QueueLength [ '{}' ]", duration.as_nanos(), key)
+            }
+            Self::PopBack(duration, key) => {
+                format!("{} PopBack [ '{}' ]", duration.as_nanos(), key)
+            }
+            Self::PopReliable(duration, key, id, deadline) => format!(
+                "{} PopReliable [ '{}', {}, {} ]",
+                duration.as_nanos(),
+                key,
+                id,
+                deadline.as_nanos()
+            ),
+            Self::Ack(duration, id) => format!("{} Ack [ {} ]", duration.as_nanos(), id),
+            Self::Requeue(duration, id) => format!("{} Requeue [ {} ]", duration.as_nanos(), id),
+            Self::HashSet(duration, key, field, value) => format!(
+                "{} HashSet [ '{}', '{}', '{}' ]",
+                duration.as_nanos(),
+                key,
+                field,
+                value
+            ),
+            Self::HashGet(duration, key, field) => {
+                format!("{} HashGet [ '{}', '{}' ]", duration.as_nanos(), key, field)
+            }
+            Self::HashDelete(duration, key, field) => format!(
+                "{} HashDelete [ '{}', '{}' ]",
+                duration.as_nanos(),
+                key,
+                field
+            ),
+            Self::HashGetAll(duration, key) => {
+                format!("{} HashGetAll [ '{}' ]", duration.as_nanos(), key)
+            }
+            Self::SetAdd(duration, key, member) => {
+                format!("{} SetAdd [ '{}', '{}' ]", duration.as_nanos(), key, member)
+            }
+            Self::SetRemove(duration, key, member) => format!(
+                "{} SetRemove [ '{}', '{}' ]",
+                duration.as_nanos(),
+                key,
+                member
+            ),
+            Self::SetMembers(duration, key) => {
+                format!("{} SetMembers [ '{}' ]", duration.as_nanos(), key)
+            }
+            Self::SetIsMember(duration, key, member) => format!(
+                "{} SetIsMember [ '{}', '{}' ]",
+                duration.as_nanos(),
+                key,
+                member
+            ),
+            Self::Import(duration, snapshot, policy) => format!(
+                "{} Import [ {}, {} records, {} queues, {} hashes, {} sets ]",
+                duration.as_nanos(),
+                policy,
+                snapshot.record.len(),
+                snapshot.queue.len(),
+                snapshot.hash.len(),
+                snapshot.set.len()
+            ),
+            Self::Context(context, item) => format!("{} ({})", item, context),
+            Self::Move(duration, key, destination) => {
+                format!(
+                    "{} Move [ {}, '{}' ]",
+                    duration.as_nanos(),
+                    key,
+                    destination
+                )
+            }
+            Self::Copy(duration, key, destination) => {
+                format!(
+                    "{} Copy [ {}, '{}' ]",
+                    duration.as_nanos(),
+                    key,
+                    destination
+                )
+            }
+            Self::RemTypedKey(duration, key) => {
+                format!("{} RemTypedKey [ {} ]", duration.as_nanos(), key)
+            }
+            Self::Expire(duration, key, deadline) => format!(
+                "{} Expire [ '{}', {} ]",
+                duration.as_nanos(),
+                key,
+                deadline
+                    .map(|x| x.as_nanos().to_string())
+                    .unwrap_or_default()
+            ),
+            Self::PushFront(duration, key, value) => {
+                format!(
+                    "{} PushFront [ '{}', '{}' ]",
+                    duration.as_nanos(),
+                    key,
+                    value
+                )
+            }
         };
         write!(f, "{}", text)
     }
@@ -140,6 +353,41 @@ pub enum LogState {
     Suspended,
 }
 
+/// When and how the human log is rotated
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogRotation {
+    /// File is rotated when it reaches this size in bytes
+    pub max_size: Option<u64>,
+
+    /// File is rotated when this much time has passed since the last rotation
+    pub interval: Option<Duration>,
+
+    /// Number of rotated files that are kept, older ones are deleted
+    pub retention: usize,
+
+    /// Rotated files are compressed with gzip
+    pub compress: bool,
+}
+
+/// Change of a key that has been read from the append file
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct HistoryEntry {
+    /// When the change was received
+    pub time: Duration,
+
+    /// What happened, e.g. `set`, `remove` or `push`
+    pub action: String,
+
+    /// Field of hash at `hash_set` and `hash_delete`
+    pub field: Option<String>,
+
+    /// Value that was written, member of set at `set_add` and `set_remove`
+    pub value: Option<String>,
+
+    /// Who has made the change, it is empty if the change was not made by a client
+    pub context: Option<RequestContext>,
+}
+
 /// Types that can be sent back by using the `start_logger` utility
 #[derive(Debug, PartialEq)]
 pub enum LoggerResponse {
@@ -148,6 +396,9 @@ pub enum LoggerResponse {
 
     FromAppendFile(Vec<LogItem>),
 
+    /// Changes of a key in the order of their time
+    History(Vec<HistoryEntry>),
+
     /// Something is wrong, see in message
     Err(String),
 }
@@ -167,6 +418,16 @@ pub enum LoggerAction {
 
     /// Read the append file
     ReadAppendFile(Sender<LoggerResponse>),
+
+    /// Read the append file only until the specified time at the next `ReadAppendFile`,
+    /// later items are cut off from the file, original file is kept with the time and .bak extension
+    RestorePoint(Sender<LoggerResponse>, Duration),
+
+    /// Collect the changes of a key from the append file
+    History(Sender<LoggerResponse>, String),
+
+    /// Rotate the human log automatically by size or time
+    Rotation(Sender<LoggerResponse>, LogRotation),
 }
 
 impl std::fmt::Display for LoggerAction {
@@ -177,6 +438,9 @@ impl std::fmt::Display for LoggerAction {
             Self::Write(_, item) => format!("Write [ '{:?}' ]", item),
             Self::WriteAsync(item) => format!("Write [ '{:?}' ]", item),
             Self::ReadAppendFile(_) => "ReadAppendFile".to_string(),
+            Self::RestorePoint(_, until) => format!("RestorePoint [ {} ]", until.as_nanos()),
+            Self::History(_, key) => format!("History [ '{}' ]", key),
+            Self::Rotation(_, rotation) => format!("Rotation [ {:?} ]", rotation),
         };
         write!(f, "{}", text)
     }
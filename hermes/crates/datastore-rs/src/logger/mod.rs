@@ -4,9 +4,10 @@ use std::{
     fs::File,
     io::{BufWriter, Write},
     path::Path,
+    time::{Duration, SystemTime},
 };
 
-use self::enums::{LogItem, LogState};
+use self::enums::{LogItem, LogRotation, LogState};
 
 pub mod enums;
 pub mod utilities;
@@ -50,6 +51,15 @@ pub struct LoggerManager {
     /// single item, but when a huge amount of log item has been receieved
     /// or some time has pass without update
     pub(crate) write_buffer: VecDeque<LogItem>,
+
+    /// Append file is read only until this time, see `LoggerAction::RestorePoint`
+    pub(crate) restore_point: Option<Duration>,
+
+    /// Rules of human log rotation, without it the file just grows
+    pub(crate) rotation: Option<LogRotation>,
+
+    /// Time of the last rotation or the start of logger
+    rotated_at: SystemTime,
 }
 
 impl LoggerManager {
@@ -83,6 +93,9 @@ impl LoggerManager {
             human_log_file: None,
             buffer: Vec::new(),
             write_buffer: VecDeque::new(),
+            restore_point: None,
+            rotation: None,
+            rotated_at: SystemTime::now(),
         }
     }
 
@@ -103,7 +116,7 @@ impl LoggerManager {
 
         let mut buffer = match File::options()
             .create(true)
-            .write(true)
+            
             .read(true)
             .append(true)
             .open(Path::new(&file_name))
@@ -151,10 +164,14 @@ impl LoggerManager {
             return Ok(());
         }
 
+        if let Err(e) = self.rotate_if_needed() {
+            tracing::error!("failed to rotate log file: {}", e);
+        }
+
         tracing::trace!("opening file for write");
         match File::options()
             .create(true)
-            .write(true)
+            
             .read(true)
             .append(true)
             .open(Path::new(&file_name))
@@ -172,6 +189,86 @@ impl LoggerManager {
         }
     }
 
+    /// Rotate the human log if it has reached the size or time limit of rotation.
+    ///
+    /// # Return
+    ///
+    /// With Ok or with an error text. If rotation is not set, it returns with Ok.
+    fn rotate_if_needed(&mut self) -> Result<(), String> {
+        let rotation = match &self.rotation {
+            Some(rotation) => rotation.clone(),
+            None => return Ok(()),
+        };
+
+        let file_name = format!("{}/human.log", self.path);
+        let size = match std::fs::metadata(&file_name) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return Ok(()),
+        };
+
+        if size == 0 {
+            return Ok(());
+        }
+
+        let too_big = rotation.max_size.map(|x| size >= x).unwrap_or(false);
+        let too_old = match (rotation.interval, self.rotated_at.elapsed()) {
+            (Some(interval), Ok(elapsed)) => elapsed >= interval,
+            _ => false,
+        };
+
+        if too_big || too_old {
+            self.rotate(&rotation)?;
+        }
+
+        Ok(())
+    }
+
+    /// Rename the human log to `human.log.1` and shift the older ones, e.g. `human.log.1` to `human.log.2`.
+    /// Files beyond the retention are deleted. With compression, rotated files have `.gz` extension.
+    ///
+    /// # Return
+    ///
+    /// With Ok or with an error text.
+    fn rotate(&mut self, rotation: &LogRotation) -> Result<(), String> {
+        let file_name = format!("{}/human.log", self.path);
+        tracing::info!("rotate log file '{}'", file_name);
+
+        for index in (1..=rotation.retention).rev() {
+            for extension in ["", ".gz"] {
+                let from = format!("{}.{}{}", file_name, index, extension);
+                if !Path::new(&from).exists() {
+                    continue;
+                }
+
+                let result = match index == rotation.retention {
+                    true => std::fs::remove_file(&from),
+                    false => {
+                        std::fs::rename(&from, format!("{}.{}{}", file_name, index + 1, extension))
+                    }
+                };
+                if let Err(e) = result {
+                    return Err(format!("Failed to rotate '{}': {}", from, e));
+                }
+            }
+        }
+
+        let result = match (rotation.retention, rotation.compress) {
+            (0, _) => std::fs::remove_file(&file_name).map_err(|e| e.to_string()),
+            (_, true) => {
+                utilities::internal::compress_file(&file_name, &format!("{}.1.gz", file_name))
+            }
+            (_, false) => {
+                std::fs::rename(&file_name, format!("{}.1", file_name)).map_err(|e| e.to_string())
+            }
+        };
+        if let Err(e) = result {
+            return Err(format!("Failed to rotate '{}': {}", file_name, e));
+        }
+
+        self.rotated_at = SystemTime::now();
+        Ok(())
+    }
+
     /// Close the buffer for the specified file.
     /// After it, every write request is going to be failed and not buffered.
     ///
@@ -1,6 +1,20 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use crate::logger::LogItem;
+use crate::logger::{enums::HistoryEntry, LogItem};
+
+/// Content of an append file that has been read until its end or its first bad record
+#[derive(Debug, Default)]
+pub struct AppendFileContent {
+    /// Items with the offset where they begin in the file
+    pub items: Vec<(usize, LogItem)>,
+
+    /// Offset and reason of the first record that cannot be read
+    pub error: Option<(usize, String)>,
+
+    /// Size of the file in bytes
+    pub size: usize,
+}
 
 /// Read append file and deserialize it into a vector.
 ///
@@ -12,23 +26,46 @@ use crate::logger::LogItem;
 /// If everything went fine then return with the vector. Else with an error message.
 /// If the logging is not enabled it returns with an empty vector.
 pub fn read_append_file(path: &Path) -> Result<Vec<LogItem>, String> {
-    let mut rows = Vec::new();
+    let content = scan_append_file(path)?;
+
+    match content.error {
+        Some((_, e)) => Err(e),
+        None => Ok(content.items.into_iter().map(|(_, item)| item).collect()),
+    }
+}
+
+/// Read append file until its first bad record and remember where each item begins.
+/// Unlike `read_append_file`, a corrupt record is not an error, so it can be used to inspect and repair the file.
+///
+/// # Arguments
+/// 1. `path`: Append file that has to be read
+///
+/// # Return
+///
+/// With the content of file, or with an error message if the file cannot be read.
+/// If the file does not exist it returns with an empty content.
+pub fn scan_append_file(path: &Path) -> Result<AppendFileContent, String> {
+    let mut result = AppendFileContent::default();
 
     if !path.exists() {
-        return Ok(Vec::new());
+        return Ok(result);
     }
 
     let content = match std::fs::read(path) {
         Ok(content) => content,
         Err(e) => return Err(e.to_string()),
     };
+    result.size = content.len();
 
     let mut vector_index = 0;
 
     while vector_index < content.len() {
         let item: LogItem = match bincode::deserialize(&content[vector_index..]) {
             Ok(item) => item,
-            Err(e) => return Err(e.to_string()),
+            Err(e) => {
+                result.error = Some((vector_index, e.to_string()));
+                break;
+            }
         };
 
         let encoded_item = match bincode::serialize(&item) {
@@ -36,9 +73,317 @@ pub fn read_append_file(path: &Path) -> Result<Vec<LogItem>, String> {
             Err(e) => return Err(e.to_string()),
         };
 
-        rows.push(item);
+        result.items.push((vector_index, item));
         vector_index += encoded_item.len();
     }
 
-    Ok(rows)
+    Ok(result)
+}
+
+/// Copy the append file next to itself with the time in its name, e.g. `hermes.af.1686425200.bak`.
+/// Existing backup is not overwritten, it is an error instead.
+///
+/// # Arguments
+/// 1. `path`: Append file that is saved
+/// 1. `seconds`: Time in the name of backup, seconds since UNIX epoch
+///
+/// # Return
+///
+/// With the name of backup or with an error message.
+pub fn backup_append_file(path: &Path, seconds: u64) -> Result<String, String> {
+    let backup = format!("{}.{}.bak", path.display(), seconds);
+    let result = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&backup)
+        .and_then(|mut target| std::io::copy(&mut std::fs::File::open(path)?, &mut target));
+
+    match result {
+        Ok(_) => Ok(backup),
+        Err(e) => Err(format!("Backup '{}' could not been written: {}", backup, e)),
+    }
+}
+
+/// Read append file until the specified time, like it would be the state of that time.
+/// Later items are cut off from the file, so new items are appended after the restored ones.
+/// Original file is kept next to it with the time in its name, e.g. `hermes.af.1686425200.bak`.
+///
+/// # Arguments
+/// 1. `path`: Append file that has to be restored
+/// 1. `until`: Items that were written at or before this time are kept
+///
+/// # Return
+///
+/// With the kept items or with an error message.
+/// It is an error if a bad record is found before the specified time.
+pub fn restore_append_file(path: &Path, until: Duration) -> Result<Vec<LogItem>, String> {
+    let content = scan_append_file(path)?;
+    let until = until.as_nanos();
+
+    let cut = content
+        .items
+        .iter()
+        .position(|(_, item)| item.get_duration() > until);
+
+    let offset = match (cut, &content.error) {
+        (Some(index), _) => content.items[index].0,
+        (None, Some((offset, _))) => *offset,
+        (None, None) => {
+            tracing::info!("append file does not contain item after the restore point");
+            return Ok(content.items.into_iter().map(|(_, item)| item).collect());
+        }
+    };
+
+    if let Some((error_offset, e)) = &content.error {
+        if *error_offset < offset {
+            return Err(e.clone());
+        }
+    }
+
+    let backup = backup_append_file(path, (until / 1_000_000_000) as u64)?;
+
+    let file = match std::fs::OpenOptions::new().write(true).open(path) {
+        Ok(file) => file,
+        Err(e) => return Err(e.to_string()),
+    };
+    if let Err(e) = file.set_len(offset as u64) {
+        return Err(e.to_string());
+    }
+
+    tracing::warn!(
+        "append file is cut at offset {}, {} bytes are removed, original file is saved as '{}'",
+        offset,
+        content.size - offset,
+        backup
+    );
+
+    Ok(content
+        .items
+        .into_iter()
+        .take_while(|(item_offset, _)| *item_offset < offset)
+        .map(|(_, item)| item)
+        .collect())
+}
+
+/// Find the backups that truncate, restore and compaction have kept next to the append file.
+///
+/// # Arguments
+/// 1. `path`: Append file whose backups are searched
+///
+/// # Return
+///
+/// With the backups and then the append file, in the order of their writing, or with an error message.
+pub fn append_file_segments(path: &Path) -> Result<Vec<PathBuf>, String> {
+    let prefix = match path.file_name() {
+        Some(name) => format!("{}.", name.to_string_lossy()),
+        None => return Err(format!("Invalid append file: {}", path.display())),
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut backups = Vec::new();
+    let entries = std::fs::read_dir(dir).map_err(|e| e.to_string())?;
+    for entry in entries {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with(&prefix) && name.ends_with(".bak") {
+            let modified = entry.metadata().and_then(|x| x.modified());
+            backups.push((modified.map_err(|e| e.to_string())?, entry.path()));
+        }
+    }
+    backups.sort();
+
+    let mut segments: Vec<PathBuf> = backups.into_iter().map(|(_, path)| path).collect();
+    segments.push(path.to_path_buf());
+    Ok(segments)
+}
+
+/// Read the changes of a key from the append file and from its backups, so the history does not
+/// stop at the last truncate, restore or compaction. Items of a segment that are not newer than the
+/// previous segment were already read, e.g. the entries that compaction has written, so they are skipped.
+///
+/// # Arguments
+/// 1. `path`: Append file
+/// 1. `key`: Key whose changes are collected
+///
+/// # Return
+///
+/// With the changes in the order of their writing, or with an error message.
+pub fn read_history(path: &Path, key: &str) -> Result<Vec<HistoryEntry>, String> {
+    let mut history = Vec::new();
+    let mut read_until = 0;
+
+    for segment in append_file_segments(path)? {
+        let content = scan_append_file(&segment)?;
+        if let Some((offset, e)) = &content.error {
+            tracing::warn!(
+                "history of '{}' is read until bad record at offset {}: {}",
+                segment.display(),
+                offset,
+                e
+            );
+        }
+
+        let items: Vec<&LogItem> = content
+            .items
+            .iter()
+            .map(|(_, item)| item)
+            .filter(|item| item.get_duration() > read_until)
+            .collect();
+        history.extend(key_history(items.iter().copied(), key));
+
+        read_until = items
+            .iter()
+            .map(|item| item.get_duration())
+            .fold(read_until, u128::max);
+    }
+
+    Ok(history)
+}
+
+/// Collect the changes of a key from items of append file.
+/// Removal, move and copy of a table is a change of every key under it.
+///
+/// # Arguments
+/// 1. `items`: Items of append file in the order of their writing
+/// 1. `key`: Key whose changes are collected
+///
+/// # Return
+///
+/// With the changes in the order of items.
+pub fn key_history<'a>(items: impl Iterator<Item = &'a LogItem>, key: &str) -> Vec<HistoryEntry> {
+    let mut history = Vec::new();
+
+    for item in items {
+        let entry =
+            |time: &Duration, action: &str, field: Option<&String>, value: Option<String>| {
+                HistoryEntry {
+                    time: *time,
+                    action: action.to_string(),
+                    field: field.cloned(),
+                    value,
+                    context: item.get_context().cloned(),
+                }
+            };
+
+        let change = match item.inner() {
+            LogItem::SetKey(time, k, value) if k == key => {
+                entry(time, "set", None, Some(value.clone()))
+            }
+            LogItem::SetTypedKey(time, k, value) if k == key => {
+                entry(time, "set", None, Some(value.to_string()))
+            }
+            LogItem::RemKey(time, k) if k == key => entry(time, "remove", None, None),
+            LogItem::RemTypedKey(time, k) if k.get_key() == key => {
+                entry(time, "remove", None, None)
+            }
+            LogItem::RemPath(time, path)
+                if key == path || key.starts_with(&format!("{}/", path)) =>
+            {
+                entry(time, "remove_path", None, None)
+            }
+            LogItem::Push(time, k, value) if k == key => {
+                entry(time, "push", None, Some(value.clone()))
+            }
+            LogItem::PushFront(time, k, value) if k == key => {
+                entry(time, "push_front", None, Some(value.clone()))
+            }
+            LogItem::Pop(time, k) if k == key => entry(time, "pop", None, None),
+            LogItem::PopBack(time, k) if k == key => entry(time, "pop_back", None, None),
+            LogItem::PopReliable(time, k, _, _) if k == key => {
+                entry(time, "pop_reliable", None, None)
+            }
+            LogItem::HashSet(time, k, field, value) if k == key => {
+                entry(time, "hash_set", Some(field), Some(value.clone()))
+            }
+            LogItem::HashDelete(time, k, field) if k == key => {
+                entry(time, "hash_delete", Some(field), None)
+            }
+            LogItem::SetAdd(time, k, member) if k == key => {
+                entry(time, "set_add", None, Some(member.clone()))
+            }
+            LogItem::SetRemove(time, k, member) if k == key => {
+                entry(time, "set_remove", None, Some(member.clone()))
+            }
+            LogItem::Move(time, source, destination) => {
+                match relocation(key, source.get_key(), destination) {
+                    Some((true, to)) => entry(time, "move_to", None, Some(to)),
+                    Some((false, from)) => entry(time, "move_from", None, Some(from)),
+                    None => continue,
+                }
+            }
+            LogItem::Copy(time, source, destination) => {
+                match relocation(key, source.get_key(), destination) {
+                    Some((false, from)) => entry(time, "copy_from", None, Some(from)),
+                    _ => continue,
+                }
+            }
+            LogItem::Import(time, snapshot, _) => {
+                if let Some(record) = snapshot.record.iter().find(|x| x.key == key) {
+                    entry(time, "import", None, Some(record.value.clone()))
+                } else if snapshot.queue.iter().any(|x| x.key == key)
+                    || snapshot.hash.iter().any(|x| x.key == key)
+                    || snapshot.set.iter().any(|x| x.key == key)
+                {
+                    entry(time, "import", None, None)
+                } else {
+                    continue;
+                }
+            }
+            _ => continue,
+        };
+
+        history.push(change);
+    }
+
+    history
+}
+
+/// Tells how a move or copy has changed the key. Return with `true` and the new key if key was under
+/// the source, with `false` and the original key if key is under the destination, else with `None`.
+fn relocation(key: &str, source: &str, destination: &str) -> Option<(bool, String)> {
+    let relative = |prefix: &str| match key.strip_prefix(prefix) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => Some(rest.to_string()),
+        _ => None,
+    };
+
+    if let Some(rest) = relative(source) {
+        return Some((true, format!("{}{}", destination, rest)));
+    }
+    relative(destination).map(|rest| (false, format!("{}{}", source, rest)))
+}
+
+/// Compress a file with gzip, the original file is deleted after it.
+///
+/// # Arguments
+/// 1. `from`: File that is compressed
+/// 1. `to`: Compressed file, e.g. `human.log.1.gz`
+///
+/// # Return
+///
+/// With Ok or with an error message.
+pub fn compress_file(from: &str, to: &str) -> Result<(), String> {
+    let mut input = match std::fs::File::open(from) {
+        Ok(file) => file,
+        Err(e) => return Err(e.to_string()),
+    };
+    let output = match std::fs::File::create(to) {
+        Ok(file) => file,
+        Err(e) => return Err(e.to_string()),
+    };
+
+    let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+    if let Err(e) = std::io::copy(&mut input, &mut encoder) {
+        return Err(e.to_string());
+    }
+    if let Err(e) = encoder.finish() {
+        return Err(e.to_string());
+    }
+
+    match std::fs::remove_file(from) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
 }
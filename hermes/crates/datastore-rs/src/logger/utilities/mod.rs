@@ -3,7 +3,7 @@ use tokio::select;
 use tokio::sync::mpsc::{channel, Sender};
 use tokio::task::JoinHandle;
 
-pub(crate) mod internal;
+pub mod internal;
 
 use super::{
     enums::{LoggerAction, LoggerResponse},
@@ -47,13 +47,53 @@ pub async fn start_logger(path: &String) -> (Sender<LoggerAction>, JoinHandle<()
                                         continue;
                                     }
 
-                                    match super::utilities::internal::read_append_file(Path::new(&format!("{}/hermes.af", logger.path))) {
+                                    let file_name = format!("{}/hermes.af", logger.path);
+                                    let result = match logger.restore_point.take() {
+                                        Some(until) => internal::restore_append_file(Path::new(&file_name), until),
+                                        None => internal::read_append_file(Path::new(&file_name)),
+                                    };
+
+                                    match result {
                                         Ok(list) => {
                                             send_response!(sender, LoggerResponse::FromAppendFile(list))
                                         }
                                         Err(e) => send_response!(sender, LoggerResponse::Err(e)),
                                     }
                                 }
+                                LoggerAction::RestorePoint(sender, until) => {
+                                    if logger.path.is_empty() {
+                                        send_response!(sender, LoggerResponse::Err("logging is disabled".to_string()));
+                                        continue;
+                                    }
+
+                                    logger.restore_point = Some(until);
+                                    send_response!(sender, LoggerResponse::Ok);
+                                }
+                                LoggerAction::Rotation(sender, rotation) => {
+                                    logger.rotation = Some(rotation);
+                                    send_response!(sender, LoggerResponse::Ok);
+                                }
+                                LoggerAction::History(sender, key) => {
+                                    if logger.path.is_empty() {
+                                        send_response!(sender, LoggerResponse::History(vec![]));
+                                        continue;
+                                    }
+
+                                    // Buffered items are written first, so the latest changes are in the history too
+                                    if !logger.write_buffer.is_empty() {
+                                        if let Err(e) = logger.write_append_file() {
+                                            tracing::error!("{}", e);
+                                            send_response!(sender, LoggerResponse::Err(e));
+                                            return;
+                                        }
+                                    }
+
+                                    let file_name = format!("{}/hermes.af", logger.path);
+                                    match internal::read_history(Path::new(&file_name), &key) {
+                                        Ok(history) => send_response!(sender, LoggerResponse::History(history)),
+                                        Err(e) => send_response!(sender, LoggerResponse::Err(e)),
+                                    }
+                                }
                                 LoggerAction::Write(sender, lines) => {
                                     for line in lines {
                                         logger.write_buffer.push_back(line);
@@ -82,7 +122,8 @@ pub async fn start_logger(path: &String) -> (Sender<LoggerAction>, JoinHandle<()
                             }
                         }
                         None => {
-                            tracing::error!("failed to receive");
+                            // Every sender is dropped, e.g. database has been dropped
+                            tracing::debug!("logger is stopped, senders are closed");
                             return;
                         },
                     }
@@ -110,4 +151,4 @@ macro_rules! send_response {
             .unwrap_or_else(|e| tracing::error!("Error during send: {}", e))
     };
 }
-pub(self) use send_response;
+use send_response;
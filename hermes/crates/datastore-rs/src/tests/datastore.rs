@@ -1,12 +1,24 @@
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
     use std::io::prelude::*;
+    use std::sync::Arc;
     use tokio::sync::mpsc::channel;
 
     use crate::{
         datastore::{
-            enums::{error::ErrorKind, pair::KeyType, pair::ValueType, DatabaseAction, ListType},
-            utilities::start_datastore,
+            computed::{ComputeFunction, ComputedKey, Function},
+            enums::{
+                error::ErrorCode, error::ErrorKind, event::EventKind, pair::KeyType,
+                pair::RecordValue, pair::ValueType, snapshot::ImportPolicy, snapshot::Snapshot,
+                snapshot::SnapshotFormat, DatabaseAction, Expiration, ListType, QueueEnd,
+            },
+            filter::{FilterBinding, WriteFilter, WriteKind},
+            limits::{EvictionPolicy, Limits, QueueLimit},
+            router::DatastoreRouter,
+            schema::Schema,
+            stat::{StatKind, TableStat},
+            utilities::{start_datastore_handle, start_datastore_with_storage},
             Database,
         },
         hook::HookManager,
@@ -19,26 +31,26 @@ mod tests {
             .build()
             .unwrap();
         rt.block_on(async move {
-            let db = Database::new("root".to_string());
+            let db = Database::with_storage("root".to_string(), super::storage());
             assert_eq!(true, db.is_ok());
             let mut db = db.unwrap();
 
             let list: Vec<(KeyType, ValueType)> = vec![
                 (
                     KeyType::Record("/root/status/sub1".to_string()),
-                    ValueType::RecordPointer("OK".to_string()),
+                    ValueType::RecordPointer("OK".into()),
                 ),
                 (
                     KeyType::Record("/root/status/sub2".to_string()),
-                    ValueType::RecordPointer("NOK".to_string()),
+                    ValueType::RecordPointer("NOK".into()),
                 ),
                 (
                     KeyType::Record("/root/network/dns".to_string()),
-                    ValueType::RecordPointer("OK".to_string()),
+                    ValueType::RecordPointer("OK".into()),
                 ),
                 (
                     KeyType::Record("/root/network/www".to_string()),
-                    ValueType::RecordPointer("NOK".to_string()),
+                    ValueType::RecordPointer("NOK".into()),
                 ),
             ];
 
@@ -61,78 +73,2002 @@ mod tests {
             .unwrap();
         rt.block_on(async move {
             let (hook_sender, _) = crate::hook::utilities::start_hook_manager().await;
+            let (sender, _) = start_datastore_with_storage(
+                "root".to_string(),
+                super::storage(),
+                Some(hook_sender),
+                None,
+            )
+            .await;
+
+            // Add a new pair
+            let (tx, mut rx) = channel(10);
+            let set_action = DatabaseAction::Set(tx, "/root/network".to_string(), "ok".to_string());
+            sender
+                .send(set_action)
+                .await
+                .expect("Failed to send the request");
+            rx.recv()
+                .await
+                .expect("Failed to send action")
+                .expect("Failed to set value");
+
+            let (tx, mut rx) = channel(10);
+            let set_action =
+                DatabaseAction::Set(tx, "/root/network".to_string(), "nok".to_string());
+            sender
+                .send(set_action)
+                .await
+                .expect("Failed to send the request");
+            rx.recv()
+                .await
+                .expect("Failed to send action")
+                .expect("Failed to set value");
+
+            // Get the pair
+            let (tx, mut rx) = channel(10);
+            let get_action = DatabaseAction::Get(tx, "/root/network".to_string());
+
+            sender
+                .send(get_action)
+                .await
+                .expect("Failed to send the get request");
+            let data = rx
+                .recv()
+                .await
+                .expect("Failed to receive message")
+                .expect("Failed to get data");
+            assert_eq!(ValueType::RecordPointer("nok".into()), data);
+
+            let (tx, mut rx) = channel(10);
+            let trigger_action = DatabaseAction::Trigger(
+                tx,
+                "/root/new-test".to_string(),
+                "placeholder".to_string(),
+            );
+            sender
+                .send(trigger_action)
+                .await
+                .expect("Failed to send the request");
+            rx.recv()
+                .await
+                .expect("Failed to send action")
+                .expect("Failed to send trigger value");
+
+            let (tx, mut rx) = channel(10);
+            let get_action = DatabaseAction::Get(tx, "/root/new-test".to_string());
+            sender
+                .send(get_action)
+                .await
+                .expect("Failed to send the request");
+
+            match rx.recv().await.expect("Failed to receive message") {
+                Ok(_) => panic!("This key should not exist"),
+                Err(e) => match e {
+                    ErrorKind::NotFound(msg) => assert_eq!("Specified key does not exist", msg),
+                    e => panic!("This is not a correct panic: {}", e),
+                },
+            }
+        })
+    }
+
+    #[test]
+    fn handle_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let (handle, _) =
+                start_datastore_handle("root".to_string(), super::storage(), None, None).await;
+
+            for i in 0..10 {
+                let (tx, mut rx) = channel(10);
+                let key = format!("/root/status/vps{}", i);
+                let action = DatabaseAction::Set(tx, key, format!("{}", i));
+                handle.send(action).await.unwrap();
+                rx.recv().await.unwrap().expect("Failed to set value");
+            }
+
+            // Reads see the finished writes and they can run concurrently
+            let mut tasks = Vec::new();
+            for i in 0..10 {
+                let handle = handle.clone();
+                tasks.push(tokio::spawn(async move {
+                    let key = format!("/root/status/vps{}", i);
+                    handle.get(key, None).await
+                }));
+            }
+            for (i, task) in tasks.into_iter().enumerate() {
+                let value = task.await.unwrap().expect("Failed to get value");
+                assert_eq!(
+                    ValueType::RecordPointer(RecordValue::from(format!("{}", i))),
+                    value
+                );
+            }
+
+            let list = handle
+                .list_keys("/root/status".to_string(), ListType::All, None)
+                .await
+                .expect("Failed to list keys");
+            assert_eq!(10, list.len());
+
+            // Errors are the same like via the datastore task
+            match handle.get("/root/nothing".to_string(), None).await {
+                Err(ErrorKind::NotFound(_)) => (),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+            match handle.get("/other/status".to_string(), None).await {
+                Err(e) => assert_eq!(ErrorCode::InvalidKey, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+
+            // Sender of the handle reaches the same database
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::DeleteKey(tx, "/root/status/vps0".to_string());
+            handle.sender().send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to delete");
+            assert!(handle
+                .get("/root/status/vps0".to_string(), None)
+                .await
+                .is_err());
+        })
+    }
+
+    #[test]
+    fn router_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let (root, _) =
+                start_datastore_handle("root".to_string(), super::storage(), None, None).await;
+            let (team, _) =
+                start_datastore_handle("team".to_string(), super::storage(), None, None).await;
+
+            let router = DatastoreRouter::new("root".to_string(), root.clone());
+            router
+                .insert("team".to_string(), team.clone())
+                .expect("Failed to add database");
+            assert_eq!(vec!["root".to_string(), "team".to_string()], router.names());
+
+            // Requests are routed by the first segment of key
+            for key in ["/root/status", "/team/status"] {
+                let (tx, mut rx) = channel(10);
+                let action = DatabaseAction::Set(tx, key.to_string(), key.to_string());
+                router.send(action).await.unwrap();
+                rx.recv().await.unwrap().expect("Failed to set value");
+            }
+            assert!(team.get("/team/status".to_string(), None).await.is_ok());
+            assert!(root.get("/team/status".to_string(), None).await.is_err());
+            assert_eq!(
+                ValueType::RecordPointer(RecordValue::from("/team/status")),
+                router.get("/team/status".to_string(), None).await.unwrap()
+            );
+
+            // Unknown root is rejected by the default database
+            match router.get("/other/status".to_string(), None).await {
+                Err(e) => assert_eq!(ErrorCode::InvalidKey, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+
+            // Acknowledge finds the database of delivery
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Push(tx, "/team/jobs".to_string(), "job1".to_string());
+            router.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to push");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::PopReliable(
+                tx,
+                "/team/jobs".to_string(),
+                std::time::Duration::from_secs(10),
+            );
+            router.send(action).await.unwrap();
+            let (id, _) = rx.recv().await.unwrap().expect("Failed to pop");
+
+            let (tx, mut rx) = channel(10);
+            router.send(DatabaseAction::Ack(tx, id)).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to ack");
+
+            let (tx, mut rx) = channel(10);
+            router.send(DatabaseAction::Ack(tx, id)).await.unwrap();
+            match rx.recv().await.unwrap() {
+                Err(ErrorKind::NotFound(_)) => (),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+
+            // Actions of batch are done in order, each of them responds
+            let (push_tx, mut push_rx) = channel(10);
+            let (length_tx, mut length_rx) = channel(10);
+            let (nested_tx, mut nested_rx) = channel(10);
+            let (tx, mut rx) = channel(10);
+            let actions = vec![
+                DatabaseAction::Push(push_tx, "/team/batch".to_string(), "job1".to_string()),
+                DatabaseAction::QueueLength(length_tx, "/team/batch".to_string()),
+                DatabaseAction::Batch(
+                    nested_tx,
+                    vec![DatabaseAction::QueueLength(channel(10).0, "/team/batch".to_string())],
+                ),
+            ];
+            router.send(DatabaseAction::Batch(tx, actions)).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to do batch");
+            push_rx.recv().await.unwrap().expect("Failed to push");
+            assert_eq!(1, length_rx.recv().await.unwrap().unwrap());
+            match nested_rx.recv().await.unwrap() {
+                Err(ErrorKind::InvalidKey(_)) => (),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+
+            // Batch is done by one database
+            let (tx, mut rx) = channel(10);
+            let actions = ["/root/batch", "/team/batch"]
+                .into_iter()
+                .map(|key| DatabaseAction::QueueLength(channel(10).0, key.to_string()))
+                .collect();
+            router.send(DatabaseAction::Batch(tx, actions)).await.unwrap();
+            match rx.recv().await.unwrap() {
+                Err(ErrorKind::InvalidKey(_)) => (),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+
+            // Actions without key are sent to every database
+            let (tx, mut rx) = channel(10);
+            router.send(DatabaseAction::Flush(tx)).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to flush");
+
+            // Databases can be added and removed, except the default one
+            match router.insert("team".to_string(), team) {
+                Err(ErrorKind::Conflict(_)) => (),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+            match router.remove("root") {
+                Err(ErrorKind::Forbidden(_)) => (),
+                other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+            }
+            assert!(router.remove("team").is_ok());
+            assert!(router.get("/team/status".to_string(), None).await.is_err());
+            assert_eq!(vec!["root".to_string()], router.names());
+        })
+    }
+
+    #[test]
+    fn router_watch_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let (root, _) =
+                start_datastore_handle("root".to_string(), super::storage(), None, None).await;
+            let router = DatastoreRouter::new("root".to_string(), root);
+
+            // Watcher is subscribed before the second database exists
+            let (tx, mut rx) = channel(10);
+            router.send(DatabaseAction::Watch(tx)).await.unwrap();
+            let mut events = rx.recv().await.unwrap().expect("Failed to subscribe");
+
+            let (team, _) =
+                start_datastore_handle("team".to_string(), super::storage(), None, None).await;
+            router
+                .insert("team".to_string(), team)
+                .expect("Failed to add database");
+
+            for key in ["/team/status", "/root/status"] {
+                let (tx, mut rx) = channel(10);
+                let action = DatabaseAction::Set(tx, key.to_string(), "ok".to_string());
+                router.send(action).await.unwrap();
+                rx.recv().await.unwrap().expect("Failed to set value");
+
+                let event = tokio::time::timeout(std::time::Duration::from_secs(5), events.recv())
+                    .await
+                    .expect("Event has not arrived")
+                    .expect("Failed to receive event");
+                assert_eq!(key, event.key);
+            }
+        })
+    }
+
+    #[test]
+    fn limits_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let record = |key: &str| KeyType::Record(key.to_string());
+            let value = |text: &str| ValueType::RecordPointer(text.into());
+
+            // Writes are rejected when the keys are not evictable
+            let mut db = Database::with_storage("root".to_string(), super::storage()).unwrap();
+            db.set_limits(Limits {
+                max_keys: Some(2),
+                max_value_size: Some(8),
+                ..Default::default()
+            });
+            db.insert(record("/root/a"), value("1")).await.unwrap();
+            db.insert(record("/root/b"), value("2")).await.unwrap();
+            db.insert(record("/root/a"), value("3")).await.unwrap();
+            match db.insert(record("/root/c"), value("4")).await {
+                Err(e) => assert_eq!(ErrorCode::LimitExceeded, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+            match db
+                .push(record("/root/b/q"), "too long value".to_string())
+                .await
+            {
+                Err(ErrorKind::LimitExceeded(_)) => (),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+            db.delete_key(record("/root/b")).await.unwrap();
+            db.insert(record("/root/c"), value("4")).await.unwrap();
+            assert_eq!(2, db.usage().usage.keys);
+
+            // Policies select different victims
+            for (policy, evicted) in [
+                (EvictionPolicy::Oldest, "/root/cache/a"),
+                (EvictionPolicy::Lru, "/root/cache/b"),
+                (EvictionPolicy::Lfu, "/root/cache/c"),
+            ] {
+                let mut db = Database::with_storage("root".to_string(), super::storage()).unwrap();
+                db.set_limits(Limits {
+                    max_keys: Some(3),
+                    policy,
+                    evict_prefixes: vec!["/root/cache".to_string()],
+                    ..Default::default()
+                });
+                for key in ["/root/cache/a", "/root/cache/b", "/root/cache/c"] {
+                    db.insert(record(key), value("ok")).await.unwrap();
+                }
+                for key in ["b", "b", "a", "a", "c"] {
+                    db.get(record(&format!("/root/cache/{}", key))).unwrap();
+                }
+
+                db.insert(record("/root/cache/d"), value("ok"))
+                    .await
+                    .unwrap();
+                assert!(
+                    db.get(record(evicted)).is_err(),
+                    "{} is not evicted",
+                    policy
+                );
+                assert_eq!(3, db.usage().usage.keys);
+                assert_eq!(1, db.usage().usage.evicted);
+            }
+
+            // Memory limit evicts as many records as needed, other keys are kept
+            let mut db = Database::with_storage("root".to_string(), super::storage()).unwrap();
+            db.set_limits(Limits {
+                max_memory: Some(64),
+                policy: EvictionPolicy::Oldest,
+                evict_prefixes: vec!["/root/cache".to_string()],
+                ..Default::default()
+            });
+            db.insert(record("/root/keep"), value("0123456789"))
+                .await
+                .unwrap();
+            for i in 0..3 {
+                let key = format!("/root/cache/{}", i);
+                db.insert(record(&key), value("0123456789")).await.unwrap();
+            }
+            assert!(db.usage().usage.memory <= 64);
+            assert!(db.get(record("/root/cache/0")).is_err());
+            assert!(db.get(record("/root/keep")).is_ok());
+            match db.insert(record("/root/big"), value(&"x".repeat(64))).await {
+                Err(ErrorKind::LimitExceeded(_)) => (),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+
+            // Full queue drops its oldest item under evictable prefix, else push is rejected
+            let mut db = Database::with_storage("root".to_string(), super::storage()).unwrap();
+            db.set_limits(Limits {
+                queues: vec![QueueLimit {
+                    prefix: "/root".to_string(),
+                    max_length: 2,
+                }],
+                policy: EvictionPolicy::Oldest,
+                evict_prefixes: vec!["/root/events".to_string()],
+                ..Default::default()
+            });
+            for item in ["1", "2", "3"] {
+                db.push(record("/root/events"), item.to_string())
+                    .await
+                    .unwrap();
+            }
+            assert_eq!(2, db.queue_length(record("/root/events")).unwrap());
+            assert_eq!("2", db.pop(record("/root/events")).await.unwrap());
+
+            db.push(record("/root/jobs"), "1".to_string())
+                .await
+                .unwrap();
+            db.push(record("/root/jobs"), "2".to_string())
+                .await
+                .unwrap();
+            match db.push(record("/root/jobs"), "3".to_string()).await {
+                Err(ErrorKind::LimitExceeded(_)) => (),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+
+            // Usage follows the removals
+            assert_eq!(2, db.usage().usage.keys);
+            db.pop(record("/root/events")).await.unwrap();
+            assert_eq!(1, db.usage().usage.keys);
+            db.pop(record("/root/jobs")).await.unwrap();
+            db.pop(record("/root/jobs")).await.unwrap();
+            assert_eq!(0, db.usage().usage.keys);
+            assert_eq!(0, db.usage().usage.memory);
+        })
+    }
+
+    #[test]
+    fn stat_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let record = |key: &str| KeyType::Record(key.to_string());
+            let mut db = Database::with_storage("root".to_string(), super::storage()).unwrap();
+
+            db.insert(
+                record("/root/status/a"),
+                ValueType::RecordPointer("PING".into()),
+            )
+            .await
+            .unwrap();
+            for item in ["j1", "j2"] {
+                db.push(record("/root/status/jobs"), item.to_string())
+                    .await
+                    .unwrap();
+            }
+            db.hash_set(record("/root/h"), "f".to_string(), "v".to_string())
+                .await
+                .unwrap();
+
+            // Keys have their own size and times
+            let stats = db.stat("/root/status/a").unwrap();
+            assert_eq!(1, stats.len());
+            assert_eq!(StatKind::Record, stats[0].kind);
+            assert_eq!(4, stats[0].size);
+            assert_eq!(None, stats[0].length);
+            assert!(stats[0].created.is_some());
+            assert!(stats[0].modified >= stats[0].created);
+
+            let stats = db.stat("/root/status/jobs").unwrap();
+            assert_eq!(StatKind::Queue, stats[0].kind);
+            assert_eq!(Some(2), stats[0].length);
+            assert_eq!(4, stats[0].size);
+
+            // Tables sum up everything under them
+            let stats = db.stat("/root").unwrap();
+            let table = stats[0].table.clone().unwrap();
+            assert_eq!(StatKind::Table, stats[0].kind);
+            assert_eq!(
+                TableStat {
+                    children: 2,
+                    records: 1,
+                    queues: 1,
+                    hashes: 1,
+                    sets: 0,
+                },
+                table
+            );
+            assert_eq!(18 + 21 + 9, stats[0].size);
+            assert_eq!(
+                2,
+                db.stat("/root/status").unwrap()[0]
+                    .table
+                    .clone()
+                    .unwrap()
+                    .children
+            );
+
+            // Record and table can have the same key
+            db.insert(
+                record("/root/status"),
+                ValueType::RecordPointer("OK".into()),
+            )
+            .await
+            .unwrap();
+            let stats = db.stat("/root/status").unwrap();
+            assert_eq!(2, stats.len());
+            assert_eq!(StatKind::Record, stats[0].kind);
+            assert_eq!(StatKind::Table, stats[1].kind);
+
+            // Removals are followed
+            db.delete_key(record("/root/status/a")).await.unwrap();
+            let stats = db.stat("/root/status").unwrap();
+            assert_eq!(1, stats[1].table.clone().unwrap().children);
+            assert_eq!(21, stats[1].size);
+            assert!(db.stat("/root/status/a").is_err());
+
+            db.delete_table(KeyType::Table("/root/status".to_string()))
+                .await
+                .unwrap();
+            let stats = db.stat("/root").unwrap();
+            let table = stats[0].table.clone().unwrap();
+            assert_eq!((2, 1, 0), (table.children, table.records, table.queues));
+            assert_eq!(9 + 14, stats[0].size);
+            let stats = db.stat("/root/status").unwrap();
+            assert_eq!(1, stats.len());
+            assert_eq!(StatKind::Record, stats[0].kind);
+
+            match db.stat("/root/status/jobs") {
+                Err(e) => assert_eq!(ErrorCode::NotFound, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+        })
+    }
+
+    #[test]
+    fn move_copy_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let record = |key: &str| KeyType::Record(key.to_string());
+            let table = |key: &str| KeyType::Table(key.to_string());
+            let mut db = Database::with_storage("root".to_string(), super::storage()).unwrap();
+
+            for (key, value) in [("/root/status/a", "ok"), ("/root/status/b", "failed")] {
+                db.insert(record(key), ValueType::RecordPointer(value.into()))
+                    .await
+                    .unwrap();
+            }
+            db.insert(
+                record("/root/status/empty/x"),
+                ValueType::RecordPointer("1".into()),
+            )
+            .await
+            .unwrap();
+            db.delete_key(record("/root/status/empty/x")).await.unwrap();
+            for item in ["j1", "j2"] {
+                db.push(record("/root/jobs"), item.to_string())
+                    .await
+                    .unwrap();
+            }
+
+            // Single record
+            db.move_key(record("/root/status/b"), "/root/failed")
+                .await
+                .unwrap();
+            assert!(db.get(record("/root/status/b")).is_err());
+            match db.get(record("/root/failed")).unwrap() {
+                ValueType::RecordPointer(value) => assert_eq!("failed", value.to_string()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+
+            // Whole table with its empty subtable
+            db.move_key(table("/root/status"), "/root/infra/status")
+                .await
+                .unwrap();
+            assert!(db.get(record("/root/infra/status/a")).is_ok());
+            assert!(db.stat("/root/infra/status/empty").is_ok());
+            assert!(db.stat("/root/status").is_err());
+
+            // Queue is copied with its items, the original remains
+            db.copy_key(KeyType::Queue("/root/jobs".to_string()), "/root/jobs2")
+                .await
+                .unwrap();
+            assert_eq!(2, db.queue_length(record("/root/jobs2")).unwrap());
+            assert_eq!("j1", db.pop(record("/root/jobs2")).await.unwrap());
+            assert_eq!(2, db.queue_length(record("/root/jobs")).unwrap());
+
+            // Destination must not exist and nothing is changed on error
+            match db
+                .copy_key(record("/root/failed"), "/root/infra/status/a")
+                .await
+            {
+                Err(e) => assert_eq!(ErrorCode::Conflict, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+            match db.move_key(table("/root/infra"), "/root/infra/sub").await {
+                Err(e) => assert_eq!(ErrorCode::InvalidKey, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+            match db.move_key(record("/root/missing"), "/root/other").await {
+                Err(e) => assert_eq!(ErrorCode::NotFound, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+            assert!(db.get(record("/root/failed")).is_ok());
+
+            // Metadata follows the changes
+            let stats = db.stat("/root").unwrap();
+            let counts = stats[0].table.clone().unwrap();
+            assert_eq!((2, 4, 2), (counts.records, counts.children, counts.queues));
+            let counts = db.stat("/root/infra/status").unwrap()[0]
+                .table
+                .clone()
+                .unwrap();
+            assert_eq!((1, 2), (counts.records, counts.children));
+        })
+    }
+
+    #[test]
+    fn schema_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let record = |key: &str| KeyType::Record(key.to_string());
+            let text = |value: &str| ValueType::RecordPointer(value.into());
+            let mut db = Database::with_storage("root".to_string(), super::storage()).unwrap();
+
+            db.set_schemas(vec![
+                Schema {
+                    prefix: "/root/status".to_string(),
+                    r#enum: Some(vec!["OK".to_string(), "NOK".to_string()]),
+                    key_pattern: Some("/root/status/[a-z0-9-]+".to_string()),
+                    queues: Some(false),
+                    ..Default::default()
+                },
+                Schema {
+                    prefix: "/root/servers".to_string(),
+                    json_schema: Some(serde_json::json!({
+                        "type": "object",
+                        "required": ["cpu"],
+                        "properties": {
+                            "cpu": { "type": "integer", "minimum": 0, "maximum": 100 },
+                            "name": { "type": "string", "pattern": "^srv" }
+                        },
+                        "additionalProperties": false
+                    })),
+                    ..Default::default()
+                },
+                Schema {
+                    prefix: "/root/jobs".to_string(),
+                    pattern: Some("job-[0-9]+".to_string()),
+                    max_length: Some(8),
+                    ..Default::default()
+                },
+            ])
+            .unwrap();
+
+            let rejected = |result: Result<(), ErrorKind>| match result {
+                Err(e) => assert_eq!(ErrorCode::ValidationFailed, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            };
+
+            // Allowed values and key naming
+            db.insert(record("/root/status/vps01"), text("OK"))
+                .await
+                .unwrap();
+            rejected(db.insert(record("/root/status/vps01"), text("ok")).await);
+            rejected(db.insert(record("/root/status/VPS02"), text("OK")).await);
+            rejected(
+                db.push(record("/root/status/queue"), "OK".to_string())
+                    .await,
+            );
+
+            // JSON values
+            let doc = |value: serde_json::Value| ValueType::RecordPointer(RecordValue::Json(value));
+            db.insert(
+                record("/root/servers/a"),
+                doc(serde_json::json!({ "cpu": 10, "name": "srv-a" })),
+            )
+            .await
+            .unwrap();
+            rejected(
+                db.insert(
+                    record("/root/servers/a"),
+                    doc(serde_json::json!({ "cpu": 101 })),
+                )
+                .await,
+            );
+            db.insert(
+                record("/root/servers/a"),
+                doc(serde_json::json!({ "cpu": 1.0 })),
+            )
+            .await
+            .unwrap();
+            rejected(
+                db.insert(
+                    record("/root/servers/a"),
+                    doc(serde_json::json!({ "cpu": 1.5 })),
+                )
+                .await,
+            );
+            rejected(
+                db.insert(
+                    record("/root/servers/a"),
+                    doc(serde_json::json!({ "cpu": 1, "name": "web" })),
+                )
+                .await,
+            );
+            rejected(
+                db.insert(
+                    record("/root/servers/a"),
+                    doc(serde_json::json!({ "cpu": 1, "disk": 2 })),
+                )
+                .await,
+            );
+            rejected(db.insert(record("/root/servers/a"), text("busy")).await);
+            match db
+                .patch(record("/root/servers/a"), "$.cpu", serde_json::json!(-1))
+                .await
+            {
+                Err(e) => assert_eq!(ErrorCode::ValidationFailed, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+
+            // Queue items are checked by pattern and length
+            db.push(record("/root/jobs"), "job-1".to_string())
+                .await
+                .unwrap();
+            rejected(db.push(record("/root/jobs"), "backup".to_string()).await);
+            rejected(
+                db.push(record("/root/jobs"), "job-123456".to_string())
+                    .await,
+            );
+            assert_eq!(1, db.queue_length(record("/root/jobs")).unwrap());
+
+            // Rejected write does not change anything, other prefixes are not restricted
+            match db.get(record("/root/status/vps01")).unwrap() {
+                ValueType::RecordPointer(value) => assert_eq!("OK", value.to_string()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+            db.insert(record("/root/other"), text("anything"))
+                .await
+                .unwrap();
+
+            // Hash fields and set members are checked like queue items, their key by the pattern
+            db.hash_set(
+                record("/root/status/vps02"),
+                "state".to_string(),
+                "OK".to_string(),
+            )
+            .await
+            .unwrap();
+            rejected(
+                db.hash_set(
+                    record("/root/status/vps02"),
+                    "state".to_string(),
+                    "bad".to_string(),
+                )
+                .await
+                .map(|_| ()),
+            );
+            rejected(
+                db.hash_set(
+                    record("/root/status/VPS03"),
+                    "state".to_string(),
+                    "OK".to_string(),
+                )
+                .await
+                .map(|_| ()),
+            );
+            db.set_add(record("/root/jobs"), "job-2".to_string())
+                .await
+                .unwrap();
+            rejected(
+                db.set_add(record("/root/jobs"), "backup".to_string())
+                    .await
+                    .map(|_| ()),
+            );
+
+            // Moved and copied values are checked at their new key before anything is changed
+            rejected(
+                db.copy_key(record("/root/other"), "/root/status/other")
+                    .await,
+            );
+            rejected(
+                db.move_key(KeyType::Queue("/root/jobs".to_string()), "/root/status/jobs")
+                    .await,
+            );
+            assert_eq!(1, db.queue_length(record("/root/jobs")).unwrap());
+            db.insert(record("/root/tmp/a"), text("OK")).await.unwrap();
+            rejected(
+                db.move_key(KeyType::Table("/root/tmp".to_string()), "/root/status/tmp")
+                    .await,
+            );
+            assert!(db.get(record("/root/tmp/a")).is_ok());
+            db.copy_key(record("/root/tmp/a"), "/root/status/vps04")
+                .await
+                .unwrap();
+
+            // Invalid schema is refused and the current ones remain
+            let invalid = db.set_schemas(vec![Schema {
+                prefix: "/root".to_string(),
+                pattern: Some("(".to_string()),
+                ..Default::default()
+            }]);
+            assert!(invalid.is_err());
+            assert_eq!(3, db.schemas().len());
+        })
+    }
+
+    #[test]
+    fn filter_test() {
+        struct Uppercase;
+
+        impl WriteFilter for Uppercase {
+            fn filter(
+                &self,
+                _: WriteKind,
+                key: &str,
+                value: &str,
+                _: Option<&str>,
+            ) -> Result<(String, String), ErrorKind> {
+                Ok((key.to_string(), value.to_uppercase()))
+            }
+        }
+
+        // Rejects the unknown values and moves the old ones to an archive key
+        struct Guard;
+
+        impl WriteFilter for Guard {
+            fn filter(
+                &self,
+                kind: WriteKind,
+                key: &str,
+                value: &str,
+                old: Option<&str>,
+            ) -> Result<(String, String), ErrorKind> {
+                match (kind, value, old) {
+                    (WriteKind::Set, "OK" | "NOK", Some("ARCHIVE")) => {
+                        Ok((key.replace("/status/", "/archive/"), value.to_string()))
+                    }
+                    (_, "OK" | "NOK", _) => Ok((key.to_string(), value.to_string())),
+                    _ => Err(ErrorKind::ValidationError(format!("{} is unknown", value))),
+                }
+            }
+        }
+
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let (handle, _) =
+                start_datastore_handle("root".to_string(), super::storage(), None, None).await;
+
+            let set = |key: &str, value: &str| {
+                let (handle, key, value) = (handle.clone(), key.to_string(), value.to_string());
+                async move {
+                    let (tx, mut rx) = channel(10);
+                    handle
+                        .send(DatabaseAction::Set(tx, key, value))
+                        .await
+                        .unwrap();
+                    rx.recv().await.unwrap()
+                }
+            };
+
+            // Key is written as it is before filters are set
+            set("/root/status/vps01", "ARCHIVE").await.unwrap();
+
+            let (tx, mut rx) = channel(10);
+            let filters = vec![
+                FilterBinding {
+                    prefix: "/root/status".to_string(),
+                    filter: Arc::new(Uppercase),
+                },
+                FilterBinding {
+                    prefix: "/root/status".to_string(),
+                    filter: Arc::new(Guard),
+                },
+            ];
+            let action = DatabaseAction::SetFilters(tx, "/root".to_string(), filters);
+            handle.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to set filters");
+
+            // Value is normalized, then filtered by the next filter
+            set("/root/status/vps02", "ok").await.unwrap();
+            assert_eq!(
+                ValueType::RecordPointer(RecordValue::from("OK")),
+                handle
+                    .get("/root/status/vps02".to_string(), None)
+                    .await
+                    .unwrap()
+            );
+
+            // Rejected write is not done
+            match set("/root/status/vps02", "maybe").await {
+                Err(e) => assert_eq!(ErrorCode::ValidationFailed, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+            assert_eq!(
+                ValueType::RecordPointer(RecordValue::from("OK")),
+                handle
+                    .get("/root/status/vps02".to_string(), None)
+                    .await
+                    .unwrap()
+            );
+
+            // Write is redirected, the original record remains
+            set("/root/status/vps01", "nok").await.unwrap();
+            assert_eq!(
+                ValueType::RecordPointer(RecordValue::from("NOK")),
+                handle
+                    .get("/root/archive/vps01".to_string(), None)
+                    .await
+                    .unwrap()
+            );
+            assert_eq!(
+                ValueType::RecordPointer(RecordValue::from("ARCHIVE")),
+                handle
+                    .get("/root/status/vps01".to_string(), None)
+                    .await
+                    .unwrap()
+            );
+
+            // Pushed items are filtered too
+            let (tx, mut rx) = channel(10);
+            let action =
+                DatabaseAction::Push(tx, "/root/status/queue".to_string(), "ok".to_string());
+            handle.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to push");
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Pop(tx, "/root/status/queue".to_string());
+            handle.send(action).await.unwrap();
+            assert_eq!(
+                ValueType::RecordPointer(RecordValue::from("OK")),
+                rx.recv().await.unwrap().unwrap()
+            );
+
+            // Other prefixes are not filtered
+            set("/root/other", "maybe").await.unwrap();
+
+            // New value of increment is filtered like a set
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Increment(tx, "/root/status/count".to_string(), 1);
+            handle.send(action).await.unwrap();
+            match rx.recv().await.unwrap() {
+                Err(e) => assert_eq!(ErrorCode::ValidationFailed, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+            assert!(handle
+                .get("/root/status/count".to_string(), None)
+                .await
+                .is_err());
+
+            // Fields of hashes and members of sets are filtered
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::HashSet(
+                tx,
+                "/root/status/vps03".to_string(),
+                "cpu".to_string(),
+                "nok".to_string(),
+            );
+            handle.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to set field");
+            let (tx, mut rx) = channel(10);
+            let action =
+                DatabaseAction::HashGet(tx, "/root/status/vps03".to_string(), "cpu".to_string());
+            handle.send(action).await.unwrap();
+            assert_eq!(
+                ValueType::RecordPointer(RecordValue::from("NOK")),
+                rx.recv().await.unwrap().unwrap()
+            );
+
+            let (tx, mut rx) = channel(10);
+            let action =
+                DatabaseAction::SetAdd(tx, "/root/status/tags".to_string(), "maybe".to_string());
+            handle.send(action).await.unwrap();
+            match rx.recv().await.unwrap() {
+                Err(e) => assert_eq!(ErrorCode::ValidationFailed, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+
+            // Content of imported snapshot is filtered, it is rejected as a whole
+            let import = |text: &str| {
+                let handle = handle.clone();
+                let snapshot = Snapshot::decode(text.as_bytes(), SnapshotFormat::Json).unwrap();
+                async move {
+                    let (tx, mut rx) = channel(10);
+                    let action = DatabaseAction::Import(tx, snapshot, ImportPolicy::Overwrite);
+                    handle.send(action).await.unwrap();
+                    rx.recv().await.unwrap()
+                }
+            };
+            import(r#"{"record": [{"key": "/root/status/vps04", "value": "ok"}]}"#)
+                .await
+                .expect("Failed to import");
+            assert_eq!(
+                ValueType::RecordPointer(RecordValue::from("OK")),
+                handle
+                    .get("/root/status/vps04".to_string(), None)
+                    .await
+                    .unwrap()
+            );
+            let result = import(
+                r#"{"record": [{"key": "/root/status/vps05", "value": "ok"}],
+                    "queue": [{"key": "/root/status/queue", "values": ["maybe"]}]}"#,
+            );
+            match result.await {
+                Err(e) => assert_eq!(ErrorCode::ValidationFailed, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+            assert!(handle
+                .get("/root/status/vps05".to_string(), None)
+                .await
+                .is_err());
+
+            // Moved values are not changed by filters, only accepted or rejected
+            let relocate = |key: &str, destination: &str| {
+                let (handle, key, destination) =
+                    (handle.clone(), key.to_string(), destination.to_string());
+                async move {
+                    let (tx, mut rx) = channel(10);
+                    let action = DatabaseAction::Move(tx, KeyType::Record(key), destination);
+                    handle.send(action).await.unwrap();
+                    rx.recv().await.unwrap()
+                }
+            };
+            set("/root/valid", "OK").await.unwrap();
+            relocate("/root/valid", "/root/status/vps06")
+                .await
+                .expect("Failed to move");
+            match relocate("/root/other", "/root/status/vps07").await {
+                Err(e) => assert_eq!(ErrorCode::ValidationFailed, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+            set("/root/lower", "ok").await.unwrap();
+            match relocate("/root/lower", "/root/status/vps07").await {
+                Err(e) => assert_eq!(ErrorCode::ValidationFailed, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+            assert!(handle.get("/root/lower".to_string(), None).await.is_ok());
+
+            // Typed values keep their type
+            let mut db = Database::new("root".to_string()).unwrap();
+            db.set_filters(vec![FilterBinding {
+                prefix: "/root".to_string(),
+                filter: Arc::new(Uppercase),
+            }]);
+            match db.filter_write(
+                WriteKind::Set,
+                "/root/count".to_string(),
+                RecordValue::Integer(1),
+            ) {
+                Ok((key, value)) => {
+                    assert_eq!("/root/count", key);
+                    assert_eq!(RecordValue::Integer(1), value);
+                }
+                other => panic!("Unexpected result: {:?}", other),
+            }
+        });
+    }
+
+    #[test]
+    fn computed_test() {
+        // Joins the keys of records whose value is NOK
+        struct Failing;
+
+        impl ComputeFunction for Failing {
+            fn compute(
+                &self,
+                _: &str,
+                records: &BTreeMap<String, RecordValue>,
+            ) -> Result<Option<String>, ErrorKind> {
+                let keys: Vec<&str> = records
+                    .iter()
+                    .filter(|(_, value)| value.to_string() == "NOK")
+                    .map(|(key, _)| key.as_str())
+                    .collect();
+                Ok((!keys.is_empty()).then(|| keys.join(",")))
+            }
+        }
+
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let record = |key: &str| KeyType::Record(key.to_string());
+            let text = |value: &str| ValueType::RecordPointer(value.into());
+            let integer = |value: i64| ValueType::RecordPointer(RecordValue::Integer(value));
+            let computed = |key: &str, source: &str, function: Function| ComputedKey {
+                key: key.to_string(),
+                source: source.to_string(),
+                function,
+            };
+            let mut db = Database::with_storage("root".to_string(), super::storage()).unwrap();
+
+            db.insert(record("/root/status/vps01"), text("OK"))
+                .await
+                .unwrap();
+            db.set_computed(vec![
+                computed(
+                    "/root/summary/nok_count",
+                    "/root/status",
+                    Function::Count(Some("NOK".to_string())),
+                ),
+                computed("/root/summary/total", "/root/status", Function::Count(None)),
+                computed(
+                    "/root/summary/failing",
+                    "/root/status",
+                    Function::Custom(Arc::new(Failing)),
+                ),
+                computed(
+                    "/root/summary/updated",
+                    "/root/status",
+                    Function::LastUpdated,
+                ),
+                computed("/root/load/sum", "/root/load", Function::Sum),
+                computed("/root/load/min", "/root/load", Function::Min),
+                computed("/root/load/max", "/root/load", Function::Max),
+            ])
+            .unwrap();
+
+            // Records that exist before are counted
+            db.refresh_computed().await;
+            assert_eq!(
+                integer(0),
+                db.get(record("/root/summary/nok_count")).unwrap()
+            );
+            assert_eq!(integer(1), db.get(record("/root/summary/total")).unwrap());
+            assert!(db.get(record("/root/summary/failing")).is_err());
+            assert!(db.get(record("/root/load/min")).is_err());
+
+            // Computed keys follow the changes of source
+            db.insert(record("/root/status/vps02"), text("NOK"))
+                .await
+                .unwrap();
+            db.insert(record("/root/status/vps03"), text("NOK"))
+                .await
+                .unwrap();
+            db.refresh_computed().await;
+            assert_eq!(
+                integer(2),
+                db.get(record("/root/summary/nok_count")).unwrap()
+            );
+            assert_eq!(integer(3), db.get(record("/root/summary/total")).unwrap());
+            assert_eq!(
+                text("/root/status/vps02,/root/status/vps03"),
+                db.get(record("/root/summary/failing")).unwrap()
+            );
+
+            // Time of the newest change under the source, not the time of refresh
+            let modified = db.stat("/root/status/vps03").unwrap()[0].modified.unwrap();
+            let updated = integer(modified.as_nanos() as i64);
+            assert_eq!(updated, db.get(record("/root/summary/updated")).unwrap());
+
+            db.delete_key(record("/root/status/vps02")).await.unwrap();
+            db.refresh_computed().await;
+            assert_eq!(updated, db.get(record("/root/summary/updated")).unwrap());
+            assert_eq!(
+                integer(1),
+                db.get(record("/root/summary/nok_count")).unwrap()
+            );
+
+            // Numbers under the source, computed keys under it are not counted
+            db.insert(record("/root/load/a"), text("5")).await.unwrap();
+            db.insert(record("/root/load/b"), integer(-2))
+                .await
+                .unwrap();
+            db.insert(record("/root/load/c"), text("high"))
+                .await
+                .unwrap();
+            db.refresh_computed().await;
+            assert_eq!(integer(3), db.get(record("/root/load/sum")).unwrap());
+            assert_eq!(integer(-2), db.get(record("/root/load/min")).unwrap());
+            assert_eq!(integer(5), db.get(record("/root/load/max")).unwrap());
+
+            db.insert(
+                record("/root/load/d"),
+                ValueType::RecordPointer(RecordValue::Float(0.5)),
+            )
+            .await
+            .unwrap();
+            db.refresh_computed().await;
+            assert_eq!(
+                ValueType::RecordPointer(RecordValue::Float(3.5)),
+                db.get(record("/root/load/sum")).unwrap()
+            );
+
+            // Computed keys are read-only
+            let forbidden = |result: Result<(), ErrorKind>| match result {
+                Err(e) => assert_eq!(ErrorCode::Forbidden, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            };
+            forbidden(db.insert(record("/root/summary/total"), integer(10)).await);
+            forbidden(db.delete_key(record("/root/summary/total")).await);
+            forbidden(
+                db.move_key(record("/root/summary/total"), "/root/other")
+                    .await,
+            );
+            forbidden(
+                db.copy_key(record("/root/status/vps01"), "/root/summary/total")
+                    .await,
+            );
+            assert!(db
+                .increment(record("/root/summary/total"), 1)
+                .await
+                .is_err());
+
+            // Removed table is computed again
+            db.delete_table(KeyType::Table("/root/summary".to_string()))
+                .await
+                .unwrap();
+            db.refresh_computed().await;
+            assert_eq!(integer(2), db.get(record("/root/summary/total")).unwrap());
+
+            // Changes are written by the datastore after every request
+            let (handle, _) =
+                start_datastore_handle("root".to_string(), super::storage(), None, None).await;
+            let (tx, mut rx) = channel(10);
+            let keys = vec![computed(
+                "/root/count",
+                "/root/status",
+                Function::Count(None),
+            )];
+            let action = DatabaseAction::SetComputed(tx, "/root".to_string(), keys);
+            handle.send(action).await.unwrap();
+            rx.recv()
+                .await
+                .unwrap()
+                .expect("Failed to set computed keys");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Set(tx, "/root/status/a".to_string(), "OK".to_string());
+            handle.send(action).await.unwrap();
+            rx.recv().await.unwrap().unwrap();
+            assert_eq!(
+                integer(1),
+                handle.get("/root/count".to_string(), None).await.unwrap()
+            );
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Set(tx, "/root/count".to_string(), "5".to_string());
+            handle.send(action).await.unwrap();
+            match rx.recv().await.unwrap() {
+                Err(e) => assert_eq!(ErrorCode::Forbidden, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+        });
+    }
+
+    #[test]
+    fn index_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let record = |key: &str| KeyType::Record(key.to_string());
+            let text = |value: &str| ValueType::RecordPointer(value.into());
+            let keys = |list: &[&str]| list.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+            let mut db = Database::with_storage("root".to_string(), super::storage()).unwrap();
+
+            // Existing records are indexed
+            db.insert(record("/root/status/vps01"), text("NOK"))
+                .await
+                .unwrap();
+            db.set_indexes(vec!["/root/status".to_string()]).unwrap();
+            assert_eq!(vec!["/root/status".to_string()], db.indexes());
+
+            db.insert(record("/root/status/vps02"), text("OK"))
+                .await
+                .unwrap();
+            db.insert(record("/root/status/dc1/vps03"), text("NOK"))
+                .await
+                .unwrap();
+            db.insert(record("/root/other/vps04"), text("NOK"))
+                .await
+                .unwrap();
+            assert_eq!(
+                keys(&["/root/status/dc1/vps03", "/root/status/vps01"]),
+                db.find("/root/status", "NOK").unwrap()
+            );
+            assert_eq!(
+                keys(&["/root/status/dc1/vps03"]),
+                db.find("/root/status/dc1", "NOK").unwrap()
+            );
+            assert!(db.find("/root/status", "maybe").unwrap().is_empty());
+
+            // Prefix without index is rejected
+            match db.find("/root/other", "NOK") {
+                Err(e) => assert_eq!(ErrorCode::NotFound, e.code()),
+                other => panic!("Unexpected result: {:?}", other),
+            }
+
+            // Updated and removed records
+            db.insert(record("/root/status/vps01"), text("OK"))
+                .await
+                .unwrap();
+            db.delete_key(record("/root/status/vps02")).await.unwrap();
+            assert_eq!(
+                keys(&["/root/status/vps01"]),
+                db.find("/root/status", "OK").unwrap()
+            );
+
+            // Moved and copied records
+            db.move_key(record("/root/status/vps01"), "/root/status/vps05")
+                .await
+                .unwrap();
+            db.copy_key(
+                KeyType::Table("/root/status/dc1".to_string()),
+                "/root/status/dc2",
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                keys(&["/root/status/vps05"]),
+                db.find("/root/status", "OK").unwrap()
+            );
+            assert_eq!(
+                keys(&["/root/status/dc1/vps03", "/root/status/dc2/vps03"]),
+                db.find("/root/status", "NOK").unwrap()
+            );
+
+            // Removed table
+            db.delete_table(KeyType::Table("/root/status/dc1".to_string()))
+                .await
+                .unwrap();
+            assert_eq!(
+                keys(&["/root/status/dc2/vps03"]),
+                db.find("/root/status", "NOK").unwrap()
+            );
+
+            // Action of datastore
+            let (handle, _) =
+                start_datastore_handle("root".to_string(), super::storage(), None, None).await;
+            let (tx, mut rx) = channel(10);
+            let action =
+                DatabaseAction::SetIndexes(tx, "/root".to_string(), vec!["/root".to_string()]);
+            handle.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to set indexes");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Set(tx, "/root/a".to_string(), "x".to_string());
+            handle.send(action).await.unwrap();
+            rx.recv().await.unwrap().unwrap();
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Find(tx, "/root".to_string(), "x".to_string());
+            handle.send(action).await.unwrap();
+            assert_eq!(
+                vec![record("/root/a")],
+                rx.recv().await.unwrap().expect("Failed to find")
+            );
+        });
+    }
+
+    #[test]
+    fn typed_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let (sender, _) =
+                start_datastore_with_storage("root".to_string(), super::storage(), None, None)
+                    .await;
+
+            // Typed values keep their type
+            let (tx, mut rx) = channel(10);
+            let value = RecordValue::parse("integer", "42").expect("Failed to parse integer");
+            let action = DatabaseAction::SetTyped(tx, "/root/count".to_string(), value);
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to set value");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Get(tx, "/root/count".to_string());
+            sender.send(action).await.unwrap();
+            let value = rx.recv().await.unwrap().expect("Failed to get value");
+            assert_eq!(ValueType::RecordPointer(RecordValue::Integer(42)), value);
+
+            // Parse errors are reported as invalid input
+            let e = RecordValue::parse("integer", "forty-two").unwrap_err();
+            assert_eq!(ErrorCode::InvalidKey, e.code());
+            let e = RecordValue::parse("decimal", "42").unwrap_err();
+            assert_eq!(ErrorCode::InvalidKey, e.code());
+
+            // Read and patch sub-path of JSON document
+            let (tx, mut rx) = channel(10);
+            let value =
+                RecordValue::parse("json", r#"{"status":{"cpu":10},"disks":[{"name":"sda"}]}"#)
+                    .expect("Failed to parse JSON");
+            let action = DatabaseAction::SetTyped(tx, "/root/server1".to_string(), value);
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to set value");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Patch(
+                tx,
+                "/root/server1".to_string(),
+                "$.status.cpu".to_string(),
+                serde_json::json!(25),
+            );
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to patch value");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Get(tx, "/root/server1".to_string());
+            sender.send(action).await.unwrap();
+            let value = match rx.recv().await.unwrap().expect("Failed to get value") {
+                ValueType::RecordPointer(value) => value,
+                _ => panic!("It should be record pointer"),
+            };
+            assert_eq!("json", value.get_type());
+            assert_eq!(
+                serde_json::json!(25),
+                value.get_path("$.status.cpu").unwrap()
+            );
+            assert_eq!(
+                serde_json::json!("sda"),
+                value.get_path("$.disks[0].name").unwrap()
+            );
+            assert_eq!(
+                ErrorCode::NotFound,
+                value.get_path("$.memory").unwrap_err().code()
+            );
+
+            // Patch is allowed only on JSON records
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Patch(
+                tx,
+                "/root/count".to_string(),
+                "$.value".to_string(),
+                serde_json::json!(1),
+            );
+            sender.send(action).await.unwrap();
+            let e = rx.recv().await.unwrap().unwrap_err();
+            assert_eq!(ErrorCode::TypeMismatch, e.code());
+        });
+    }
+
+    #[test]
+    fn counter_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let (sender, _) =
+                start_datastore_with_storage("root".to_string(), super::storage(), None, None)
+                    .await;
+
+            // Missing counter is created, then changed
+            for (by, expected) in [(1, 1), (5, 6), (-10, -4)] {
+                let (tx, mut rx) = channel(10);
+                let action = DatabaseAction::Increment(tx, "/root/builds".to_string(), by);
+                sender.send(action).await.unwrap();
+                let value = rx.recv().await.unwrap().expect("Failed to increment");
+                assert_eq!(
+                    ValueType::RecordPointer(RecordValue::Integer(expected)),
+                    value
+                );
+            }
+
+            // Numeric text, e.g. set by a plain SET, can be incremented too
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Set(tx, "/root/errors".to_string(), "41".to_string());
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to set value");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Increment(tx, "/root/errors".to_string(), 1);
+            sender.send(action).await.unwrap();
+            let value = rx.recv().await.unwrap().expect("Failed to increment");
+            assert_eq!(ValueType::RecordPointer(RecordValue::Integer(42)), value);
+
+            // Non-numeric value and overflow are rejected, value is not changed
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Set(tx, "/root/name".to_string(), "vps01".to_string());
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to set value");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Increment(tx, "/root/name".to_string(), 1);
+            sender.send(action).await.unwrap();
+            let e = rx.recv().await.unwrap().unwrap_err();
+            assert_eq!(ErrorCode::TypeMismatch, e.code());
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Increment(tx, "/root/errors".to_string(), i64::MAX);
+            sender.send(action).await.unwrap();
+            let e = rx.recv().await.unwrap().unwrap_err();
+            assert_eq!(ErrorCode::Conflict, e.code());
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Get(tx, "/root/errors".to_string());
+            sender.send(action).await.unwrap();
+            let value = rx.recv().await.unwrap().expect("Failed to get value");
+            assert_eq!(ValueType::RecordPointer(RecordValue::Integer(42)), value);
+        });
+    }
+
+    #[test]
+    fn expire_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
             let (sender, _) =
-                start_datastore("root".to_string(), Some(hook_sender), None).await;
+                start_datastore_with_storage("root".to_string(), super::storage(), None, None)
+                    .await;
+            let after = std::time::Duration::from_millis(200);
 
-            // Add a new pair
+            // Missing record has no expiration
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Expire(tx, "/root/session".to_string(), Some(after));
+            sender.send(action).await.unwrap();
+            assert_eq!(false, rx.recv().await.unwrap().expect("Failed to expire"));
+
+            for (key, expiration) in [
+                ("/root/session", Expiration::After(after)),
+                ("/root/token", Expiration::After(after)),
+                ("/root/lock", Expiration::Remove),
+            ] {
+                let (tx, mut rx) = channel(10);
+                let action =
+                    DatabaseAction::SetExpiring(tx, key.to_string(), "1".to_string(), expiration);
+                sender.send(action).await.unwrap();
+                rx.recv().await.unwrap().expect("Failed to set value");
+            }
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Expire(tx, "/root/lock".to_string(), Some(after));
+            sender.send(action).await.unwrap();
+            assert_eq!(true, rx.recv().await.unwrap().expect("Failed to expire"));
+
+            // Set cancels the expiration, set that keeps it does not
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Set(tx, "/root/token".to_string(), "2".to_string());
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to set value");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::SetExpiring(
+                tx,
+                "/root/lock".to_string(),
+                "2".to_string(),
+                Expiration::Keep,
+            );
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to set value");
+
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+            for (key, exists) in [("/root/session", false), ("/root/token", true), ("/root/lock", false)] {
+                let (tx, mut rx) = channel(10);
+                let action = DatabaseAction::Get(tx, key.to_string());
+                sender.send(action).await.unwrap();
+                assert_eq!(exists, rx.recv().await.unwrap().is_ok(), "{}", key);
+            }
+        });
+    }
+
+    #[test]
+    fn queue_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let (sender, _) =
+                start_datastore_with_storage("root".to_string(), super::storage(), None, None)
+                    .await;
+
+            for job in ["job1", "job2", "job3"] {
+                let (tx, mut rx) = channel(10);
+                let action = DatabaseAction::Push(tx, "/root/jobs".to_string(), job.to_string());
+                sender.send(action).await.unwrap();
+                rx.recv().await.unwrap().expect("Failed to push");
+            }
+
+            // Peek and length do not change the queue
+            for (end, expected) in [(QueueEnd::Front, "job1"), (QueueEnd::Back, "job3")] {
+                let (tx, mut rx) = channel(10);
+                let action = DatabaseAction::Peek(tx, "/root/jobs".to_string(), end);
+                sender.send(action).await.unwrap();
+                let value = rx.recv().await.unwrap().expect("Failed to peek");
+                assert_eq!(ValueType::RecordPointer(expected.into()), value);
+            }
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::QueueLength(tx, "/root/jobs".to_string());
+            sender.send(action).await.unwrap();
+            assert_eq!(3, rx.recv().await.unwrap().expect("Failed to get length"));
+
+            // Pop from the back
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::PopFrom(tx, "/root/jobs".to_string(), QueueEnd::Back);
+            sender.send(action).await.unwrap();
+            let value = rx.recv().await.unwrap().expect("Failed to pop");
+            assert_eq!(ValueType::RecordPointer("job3".into()), value);
+
+            // Reliable pop, acknowledged item is gone, the other one is returned after timeout
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::PopReliable(
+                tx,
+                "/root/jobs".to_string(),
+                std::time::Duration::from_secs(60),
+            );
+            sender.send(action).await.unwrap();
+            let (id, value) = rx.recv().await.unwrap().expect("Failed to pop");
+            assert_eq!("job1", value);
+
+            let (tx, mut rx) = channel(10);
+            sender.send(DatabaseAction::Ack(tx, id)).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to ack");
+
+            let (tx, mut rx) = channel(10);
+            sender.send(DatabaseAction::Ack(tx, id)).await.unwrap();
+            let e = rx.recv().await.unwrap().unwrap_err();
+            assert_eq!(ErrorCode::NotFound, e.code());
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::PopReliable(
+                tx,
+                "/root/jobs".to_string(),
+                std::time::Duration::from_millis(50),
+            );
+            sender.send(action).await.unwrap();
+            let (_, value) = rx.recv().await.unwrap().expect("Failed to pop");
+            assert_eq!("job2", value);
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::QueueLength(tx, "/root/jobs".to_string());
+            sender.send(action).await.unwrap();
+            assert_eq!(0, rx.recv().await.unwrap().expect("Failed to get length"));
+
+            tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Pop(tx, "/root/jobs".to_string());
+            sender.send(action).await.unwrap();
+            let value = rx.recv().await.unwrap().expect("Failed to pop");
+            assert_eq!(ValueType::RecordPointer("job2".into()), value);
+
+            // Blocking pop times out on empty queue
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::PopWait(
+                tx,
+                "/root/jobs".to_string(),
+                QueueEnd::Front,
+                std::time::Duration::from_millis(100),
+            );
+            sender.send(action).await.unwrap();
+            let e = rx.recv().await.unwrap().unwrap_err();
+            assert_eq!(ErrorCode::NotFound, e.code());
+
+            // Blocking pop is served by the next push
+            let (wait_tx, mut wait_rx) = channel(10);
+            let action = DatabaseAction::PopWait(
+                wait_tx,
+                "/root/jobs".to_string(),
+                QueueEnd::Front,
+                std::time::Duration::from_secs(10),
+            );
+            sender.send(action).await.unwrap();
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Push(tx, "/root/jobs".to_string(), "job4".to_string());
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to push");
+
+            let value = wait_rx.recv().await.unwrap().expect("Failed to pop");
+            assert_eq!(ValueType::RecordPointer("job4".into()), value);
+
+            // Waiter is served whatever way the name of queue is written
+            let (wait_tx, mut wait_rx) = channel(10);
+            let action = DatabaseAction::PopWait(
+                wait_tx,
+                "/root//jobs/".to_string(),
+                QueueEnd::Front,
+                std::time::Duration::from_secs(10),
+            );
+            sender.send(action).await.unwrap();
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Push(tx, "/root/jobs".to_string(), "job6".to_string());
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to push");
+
+            let value = wait_rx.recv().await.unwrap().expect("Failed to pop");
+            assert_eq!(ValueType::RecordPointer("job6".into()), value);
+
+            // Waiter is served by a queue that is moved onto its place
+            let (wait_tx, mut wait_rx) = channel(10);
+            let action = DatabaseAction::PopWait(
+                wait_tx,
+                "/root/moved".to_string(),
+                QueueEnd::Front,
+                std::time::Duration::from_secs(10),
+            );
+            sender.send(action).await.unwrap();
+
+            let (tx, mut rx) = channel(10);
+            let action =
+                DatabaseAction::Push(tx, "/root/staging".to_string(), "job7".to_string());
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to push");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Move(
+                tx,
+                KeyType::Queue("/root/staging".to_string()),
+                "/root/moved".to_string(),
+            );
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to move");
+
+            let value = wait_rx.recv().await.unwrap().expect("Failed to pop");
+            assert_eq!(ValueType::RecordPointer("job7".into()), value);
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Peek(tx, "/root/jobs".to_string(), QueueEnd::Front);
+            sender.send(action).await.unwrap();
+            let e = rx.recv().await.unwrap().unwrap_err();
+            assert_eq!(ErrorCode::NotFound, e.code());
+
+            // Too long wait and visibility are rejected, database keeps running
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::PopWait(
+                tx,
+                "/root/jobs".to_string(),
+                QueueEnd::Front,
+                std::time::Duration::from_secs(u64::MAX),
+            );
+            sender.send(action).await.unwrap();
+            let e = rx.recv().await.unwrap().unwrap_err();
+            assert_eq!(ErrorCode::InvalidKey, e.code());
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Push(tx, "/root/jobs".to_string(), "job5".to_string());
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to push");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::PopReliable(
+                tx,
+                "/root/jobs".to_string(),
+                std::time::Duration::from_secs(u64::MAX),
+            );
+            sender.send(action).await.unwrap();
+            let e = rx.recv().await.unwrap().unwrap_err();
+            assert_eq!(ErrorCode::InvalidKey, e.code());
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::QueueLength(tx, "/root/jobs".to_string());
+            sender.send(action).await.unwrap();
+            assert_eq!(1, rx.recv().await.unwrap().expect("Failed to get length"));
+
+            // Push to either end returns with the length of queue
+            for (job, end, expected) in [("job0", QueueEnd::Front, 2), ("job9", QueueEnd::Back, 3)] {
+                let (tx, mut rx) = channel(10);
+                let action =
+                    DatabaseAction::PushTo(tx, "/root/jobs".to_string(), job.to_string(), end);
+                sender.send(action).await.unwrap();
+                assert_eq!(expected, rx.recv().await.unwrap().expect("Failed to push"));
+            }
+
+            // Multiple pop stops at the end of queue, then it fails on the empty queue
+            let (tx, mut rx) = channel(10);
+            let action =
+                DatabaseAction::PopMany(tx, "/root/jobs".to_string(), QueueEnd::Back, 1_000_000);
+            sender.send(action).await.unwrap();
+            let items = rx.recv().await.unwrap().expect("Failed to pop");
+            assert_eq!(vec!["job9", "job5", "job0"], items);
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::PopMany(tx, "/root/jobs".to_string(), QueueEnd::Back, 2);
+            sender.send(action).await.unwrap();
+            let e = rx.recv().await.unwrap().unwrap_err();
+            assert_eq!(ErrorCode::NotFound, e.code());
+        });
+    }
+
+    #[test]
+    fn hash_set_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let (sender, _) =
+                start_datastore_with_storage("root".to_string(), super::storage(), None, None)
+                    .await;
+
+            // Fields of hash
+            for (field, value, new) in [
+                ("os", "debian", true),
+                ("cpu", "4", true),
+                ("cpu", "8", false),
+            ] {
+                let (tx, mut rx) = channel(10);
+                let action = DatabaseAction::HashSet(
+                    tx,
+                    "/root/servers/vps01".to_string(),
+                    field.to_string(),
+                    value.to_string(),
+                );
+                sender.send(action).await.unwrap();
+                assert_eq!(new, rx.recv().await.unwrap().expect("Failed to set field"));
+            }
+
+            let (tx, mut rx) = channel(10);
+            let action =
+                DatabaseAction::HashGet(tx, "/root/servers/vps01".to_string(), "cpu".to_string());
+            sender.send(action).await.unwrap();
+            let value = rx.recv().await.unwrap().expect("Failed to get field");
+            assert_eq!(ValueType::RecordPointer("8".into()), value);
+
+            let (tx, mut rx) = channel(10);
+            let action =
+                DatabaseAction::HashDelete(tx, "/root/servers/vps01".to_string(), "os".to_string());
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to delete field");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::HashGetAll(tx, "/root/servers/vps01".to_string());
+            sender.send(action).await.unwrap();
+            let hash = rx.recv().await.unwrap().expect("Failed to get hash");
+            assert_eq!(1, hash.len());
+            assert_eq!(Some(&"8".to_string()), hash.get("cpu"));
+
+            // Hash does not appear as a table in list
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::ListKeys(tx, "/root/servers".to_string(), ListType::All);
+            sender.send(action).await.unwrap();
+            let list = rx.recv().await.unwrap().expect("Failed to list");
+            assert_eq!(vec![KeyType::Hash("/root/servers/vps01".to_string())], list);
+
+            // Members of set
+            for (member, new) in [("vps01", true), ("vps02", true), ("vps01", false)] {
+                let (tx, mut rx) = channel(10);
+                let action =
+                    DatabaseAction::SetAdd(tx, "/root/online".to_string(), member.to_string());
+                sender.send(action).await.unwrap();
+                assert_eq!(new, rx.recv().await.unwrap().expect("Failed to add member"));
+            }
+
+            let (tx, mut rx) = channel(10);
+            let action =
+                DatabaseAction::SetRemove(tx, "/root/online".to_string(), "vps02".to_string());
+            sender.send(action).await.unwrap();
+            assert!(rx.recv().await.unwrap().expect("Failed to remove member"));
+
+            let (tx, mut rx) = channel(10);
+            let action =
+                DatabaseAction::SetIsMember(tx, "/root/online".to_string(), "vps02".to_string());
+            sender.send(action).await.unwrap();
+            assert!(!rx.recv().await.unwrap().expect("Failed to check member"));
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::SetMembers(tx, "/root/online".to_string());
+            sender.send(action).await.unwrap();
+            let members = rx.recv().await.unwrap().expect("Failed to get members");
+            assert_eq!(vec!["vps01"], members.iter().collect::<Vec<_>>());
+
+            // Empty set is removed
+            let (tx, mut rx) = channel(10);
+            let action =
+                DatabaseAction::SetRemove(tx, "/root/online".to_string(), "vps01".to_string());
+            sender.send(action).await.unwrap();
+            assert!(rx.recv().await.unwrap().expect("Failed to remove member"));
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::SetMembers(tx, "/root/online".to_string());
+            sender.send(action).await.unwrap();
+            let e = rx.recv().await.unwrap().unwrap_err();
+            assert_eq!(ErrorCode::NotFound, e.code());
+
+            // Only the specified kind is deleted, record with the same name remains
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Set(
+                tx,
+                "/root/servers/vps01".to_string(),
+                "online".to_string(),
+            );
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to set");
+
+            let hash = KeyType::Hash("/root/servers/vps01".to_string());
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::DeleteTyped(tx, hash.clone());
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to delete hash");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::DeleteTyped(tx, hash);
+            sender.send(action).await.unwrap();
+            let e = rx.recv().await.unwrap().unwrap_err();
+            assert_eq!(ErrorCode::NotFound, e.code());
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::HashGetAll(tx, "/root/servers/vps01".to_string());
+            sender.send(action).await.unwrap();
+            let e = rx.recv().await.unwrap().unwrap_err();
+            assert_eq!(ErrorCode::NotFound, e.code());
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Get(tx, "/root/servers/vps01".to_string());
+            sender.send(action).await.unwrap();
+            let value = rx.recv().await.unwrap().expect("Failed to get");
+            assert_eq!(ValueType::RecordPointer("online".into()), value);
+        });
+    }
+
+    #[test]
+    fn watch_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let (sender, _) =
+                start_datastore_with_storage("root".to_string(), super::storage(), None, None)
+                    .await;
+
+            // Subscribe to events
             let (tx, mut rx) = channel(10);
-            let set_action = DatabaseAction::Set(tx, "/root/network".to_string(), "ok".to_string());
             sender
-                .send(set_action)
+                .send(DatabaseAction::Watch(tx))
                 .await
                 .expect("Failed to send the request");
-            rx.recv()
+            let mut events = rx
+                .recv()
                 .await
-                .expect("Failed to send action")
-                .expect("Failed to set value");
+                .expect("Failed to receive")
+                .expect("Failed to subscribe");
 
+            // Make some changes
             let (tx, mut rx) = channel(10);
-            let set_action =
-                DatabaseAction::Set(tx, "/root/network".to_string(), "nok".to_string());
+            let action = DatabaseAction::Set(tx, "/root/status/dns".to_string(), "ok".to_string());
             sender
-                .send(set_action)
+                .send(action)
                 .await
                 .expect("Failed to send the request");
-            rx.recv()
-                .await
-                .expect("Failed to send action")
-                .expect("Failed to set value");
+            rx.recv().await.unwrap().expect("Failed to set value");
 
-            // Get the pair
             let (tx, mut rx) = channel(10);
-            let get_action = DatabaseAction::Get(tx, "/root/network".to_string());
-
+            let action = DatabaseAction::Push(tx, "/root/queue".to_string(), "job1".to_string());
             sender
-                .send(get_action)
-                .await
-                .expect("Failed to send the get request");
-            let data = rx
-                .recv()
+                .send(action)
                 .await
-                .expect("Failed to receive message")
-                .expect("Failed to get data");
-            assert_eq!(ValueType::RecordPointer("nok".to_string()), data);
+                .expect("Failed to send the request");
+            rx.recv().await.unwrap().expect("Failed to push value");
 
             let (tx, mut rx) = channel(10);
-            let trigger_action = DatabaseAction::Trigger(
-                tx,
-                "/root/new-test".to_string(),
-                "placeholder".to_string(),
-            );
+            let action = DatabaseAction::Pop(tx, "/root/queue".to_string());
             sender
-                .send(trigger_action)
+                .send(action)
                 .await
                 .expect("Failed to send the request");
-            rx.recv()
+            rx.recv().await.unwrap().expect("Failed to pop value");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::DeleteKey(tx, "/root/status/dns".to_string());
+            sender
+                .send(action)
                 .await
-                .expect("Failed to send action")
-                .expect("Failed to send trigger value");
+                .expect("Failed to send the request");
+            rx.recv().await.unwrap().expect("Failed to delete key");
 
+            // Failed actions do not generate event
             let (tx, mut rx) = channel(10);
-            let get_action = DatabaseAction::Get(tx, "/root/new-test".to_string());
+            let action = DatabaseAction::DeleteKey(tx, "/root/status/dns".to_string());
             sender
-                .send(get_action)
+                .send(action)
                 .await
                 .expect("Failed to send the request");
+            assert!(rx.recv().await.unwrap().is_err());
 
-            match rx.recv().await.expect("Failed to receive message") {
-                Ok(_) => panic!("This key should not exist"),
-                Err(e) => match e {
-                    ErrorKind::InvalidKey(msg) => assert_eq!("Specified key does not exist", msg),
-                    e => panic!("This is not a correct panic: {}", e),
-                },
+            let expected = vec![
+                (1, EventKind::Set, "/root/status/dns", Some("ok")),
+                (2, EventKind::Push, "/root/queue", Some("job1")),
+                (3, EventKind::Pop, "/root/queue", Some("job1")),
+                (4, EventKind::Delete, "/root/status/dns", None),
+            ];
+
+            for (id, kind, key, value) in expected {
+                let event = events.recv().await.expect("Failed to receive event");
+                assert_eq!(id, event.id);
+                assert_eq!(kind, event.kind);
+                assert_eq!(key, event.key);
+                assert_eq!(value.map(|x| x.to_string()), event.value);
             }
-        })
+
+            assert!(events.try_recv().is_err());
+        });
     }
 
     #[test]
@@ -142,13 +2078,13 @@ mod tests {
             .build()
             .unwrap();
         rt.block_on(async move {
-            let mut db = Database::new("root".to_string())?;
+            let mut db = Database::with_storage("root".to_string(), super::storage())?;
 
             // Error #1
             match db
                 .insert(
                     KeyType::Record("/other/status".to_string()),
-                    ValueType::RecordPointer("ok".to_string()),
+                    ValueType::RecordPointer("ok".into()),
                 )
                 .await
             {
@@ -164,10 +2100,10 @@ mod tests {
             // Error #2
             match db.get(KeyType::Record("/root/status".to_string())) {
                 Err(e) => match e {
-                    ErrorKind::InvalidKey(msg) => {
+                    ErrorKind::NotFound(msg) => {
                         assert_eq!("Specified key does not exist", msg)
                     }
-                    _ => panic!("Should have returned InvalidKey instead {:?}", e),
+                    _ => panic!("Should have returned NotFound instead {:?}", e),
                 },
                 Ok(_) => panic!("Returned with Ok but it should have with Err"),
             }
@@ -186,10 +2122,10 @@ mod tests {
             // Error #4
             match db.delete_key(KeyType::Table("/root/asd".to_string())).await {
                 Err(e) => match e {
-                    ErrorKind::InvalidKey(msg) => {
+                    ErrorKind::TypeMismatch(msg) => {
                         assert_eq!("Parameter must be a Record type", msg)
                     }
-                    _ => panic!("Should have returned InvalidKey instead {:?}", e),
+                    _ => panic!("Should have returned TypeMismatch instead {:?}", e),
                 },
                 Ok(_) => panic!("Returned with Ok but it should have with Err"),
             }
@@ -200,10 +2136,10 @@ mod tests {
                 .await
             {
                 Err(e) => match e {
-                    ErrorKind::InvalidKey(msg) => {
+                    ErrorKind::TypeMismatch(msg) => {
                         assert_eq!("Parameter must be a Table type", msg)
                     }
-                    _ => panic!("Should have returned InvalidKey instead {:?}", e),
+                    _ => panic!("Should have returned TypeMismatch instead {:?}", e),
                 },
                 Ok(_) => panic!("Returned with Ok but it should have with Err"),
             }
@@ -214,10 +2150,10 @@ mod tests {
                 .await
             {
                 Err(e) => match e {
-                    ErrorKind::InvalidKey(msg) => {
+                    ErrorKind::NotFound(msg) => {
                         assert_eq!("Specified key does not exist", msg)
                     }
-                    _ => panic!("Should have returned InvalidKey instead {:?}", e),
+                    _ => panic!("Should have returned NotFound instead {:?}", e),
                 },
                 Ok(_) => panic!("Returned with Ok but it should have with Err"),
             }
@@ -228,10 +2164,10 @@ mod tests {
                 .await
             {
                 Err(e) => match e {
-                    ErrorKind::InvalidKey(msg) => {
+                    ErrorKind::NotFound(msg) => {
                         assert_eq!("Specified key does not exist", msg)
                     }
-                    _ => panic!("Should have returned InvalidKey instead {:?}", e),
+                    _ => panic!("Should have returned NotFound instead {:?}", e),
                 },
                 Ok(_) => panic!("Returned with Ok but it should have with Err"),
             }
@@ -239,14 +2175,32 @@ mod tests {
             // Error #8
             match db.pop(KeyType::Record("/root/asd".to_string())).await {
                 Err(e) => match e {
-                    ErrorKind::InvalidKey(msg) => {
+                    ErrorKind::NotFound(msg) => {
                         assert_eq!("Specified key does not exist", msg)
                     }
-                    _ => panic!("Should have returned InvalidKey instead {:?}", e),
+                    _ => panic!("Should have returned NotFound instead {:?}", e),
                 },
                 Ok(_) => panic!("Returned with Ok but it should have with Err"),
             }
 
+            // Error #9
+            match db.get(KeyType::Record("".to_string())) {
+                Err(e) => {
+                    assert_eq!(ErrorCode::InvalidKey, e.code());
+                    assert_eq!("INVALID_KEY", e.code().to_string());
+                }
+                Ok(_) => panic!("Returned with Ok but it should have with Err"),
+            }
+
+            // Error #10
+            match db.get(KeyType::Record("/root/asd".to_string())) {
+                Err(e) => {
+                    assert_eq!(ErrorCode::NotFound, e.code());
+                    assert_eq!("NOT_FOUND", e.code().to_string());
+                }
+                Ok(_) => panic!("Returned with Ok but it should have with Err"),
+            }
+
             return Ok(());
         })
     }
@@ -258,7 +2212,7 @@ mod tests {
             .build()
             .unwrap();
         rt.block_on(async move {
-            let db = Database::new("root".to_string());
+            let db = Database::with_storage("root".to_string(), super::storage());
             assert_eq!(true, db.is_ok());
 
             let mut db = db.unwrap();
@@ -267,7 +2221,7 @@ mod tests {
             let response = db
                 .insert(
                     KeyType::Record("/root/status".to_string()),
-                    ValueType::RecordPointer("okay".to_string()),
+                    ValueType::RecordPointer("okay".into()),
                 )
                 .await;
             assert_eq!(true, response.is_ok());
@@ -275,7 +2229,7 @@ mod tests {
             let response = db
                 .insert(
                     KeyType::Record("/root/status/sub1".to_string()),
-                    ValueType::RecordPointer("okay".to_string()),
+                    ValueType::RecordPointer("okay".into()),
                 )
                 .await;
             assert_eq!(true, response.is_ok());
@@ -283,7 +2237,7 @@ mod tests {
             let response = db
                 .insert(
                     KeyType::Record("/root/status/sub2".to_string()),
-                    ValueType::RecordPointer("okay".to_string()),
+                    ValueType::RecordPointer("okay".into()),
                 )
                 .await;
             assert_eq!(true, response.is_ok());
@@ -291,7 +2245,7 @@ mod tests {
             let response = db
                 .insert(
                     KeyType::Record("/root/node_name".to_string()),
-                    ValueType::RecordPointer("teszt1".to_string()),
+                    ValueType::RecordPointer("teszt1".into()),
                 )
                 .await;
             assert_eq!(true, response.is_ok());
@@ -299,7 +2253,7 @@ mod tests {
             let response = db
                 .insert(
                     KeyType::Record("/root/network/gitea".to_string()),
-                    ValueType::RecordPointer("okay".to_string()),
+                    ValueType::RecordPointer("okay".into()),
                 )
                 .await;
             assert_eq!(true, response.is_ok());
@@ -312,7 +2266,7 @@ mod tests {
                 ValueType::RecordPointer(value) => value,
                 _ => panic!(),
             };
-            assert_eq!("okay".to_string(), *value);
+            assert_eq!("okay".to_string(), value.to_string());
 
             // Get non exist key
             let response = db.get(KeyType::Record("/root/asd/eqq".to_string()));
@@ -322,14 +2276,16 @@ mod tests {
             let response = db
                 .insert(
                     KeyType::Record("/root/status".to_string()),
-                    ValueType::RecordPointer("great".to_string()),
+                    ValueType::RecordPointer("great".into()),
                 )
                 .await;
             assert_eq!(true, response.is_ok());
 
             match db.get(KeyType::Record("/root/status".to_string())) {
                 Ok(value) => match value {
-                    ValueType::RecordPointer(text) => assert_eq!("great".to_string(), *text),
+                    ValueType::RecordPointer(text) => {
+                        assert_eq!("great".to_string(), text.to_string())
+                    }
                     _ => panic!("It should be record pointer"),
                 },
                 Err(e) => panic!("{}", e),
@@ -339,7 +2295,7 @@ mod tests {
             let response = db
                 .insert(
                     KeyType::Record("/status".to_string()),
-                    ValueType::RecordPointer("okay".to_string()),
+                    ValueType::RecordPointer("okay".into()),
                 )
                 .await;
             assert_eq!(true, response.is_err());
@@ -347,7 +2303,7 @@ mod tests {
             let response = db
                 .insert(
                     KeyType::Record("root/batch/error/plan1".to_string()),
-                    ValueType::RecordPointer("failed".to_string()),
+                    ValueType::RecordPointer("failed".into()),
                 )
                 .await;
             assert_eq!(true, response.is_err());
@@ -370,6 +2326,9 @@ mod tests {
             match db.list_keys(KeyType::Record("/root".to_string()), ListType::OneLevel) {
                 Ok(table) => {
                     assert_eq!(true, table.len() >= 1);
+                    assert!(table
+                        .iter()
+                        .any(|x| x.is_table() && x.get_key() == "/root/network"));
                 }
                 Err(e) => panic!("{}", e),
             }
@@ -405,7 +2364,7 @@ mod tests {
             let response = db
                 .insert(
                     KeyType::Record("/root/tickets".to_string()),
-                    ValueType::RecordPointer("okay".to_string()),
+                    ValueType::RecordPointer("okay".into()),
                 )
                 .await;
             assert_eq!(true, response.is_ok());
@@ -413,7 +2372,7 @@ mod tests {
             let response = db
                 .insert(
                     KeyType::Record("/root/tickets/forward_to".to_string()),
-                    ValueType::RecordPointer("127.0.0.1".to_string()),
+                    ValueType::RecordPointer("127.0.0.1".into()),
                 )
                 .await;
             assert_eq!(true, response.is_ok());
@@ -456,12 +2415,150 @@ mod tests {
             let value = db
                 .get(KeyType::Record("/root/tickets".to_string()))
                 .expect("Failed to fetch key after queue actions");
-            assert_eq!(ValueType::RecordPointer("okay".to_string()), value);
+            assert_eq!(ValueType::RecordPointer("okay".into()), value);
 
             let value = db
                 .get(KeyType::Record("/root/tickets/forward_to".to_string()))
                 .expect("Failed to fetch key after queue actions");
-            assert_eq!(ValueType::RecordPointer("127.0.0.1".to_string()), value);
+            assert_eq!(ValueType::RecordPointer("127.0.0.1".into()), value);
+        })
+    }
+
+    #[test]
+    fn export_import_test() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let mut db = Database::with_storage("root".to_string(), super::storage()).unwrap();
+
+            db.insert(
+                KeyType::Record("/root/status/vps01".to_string()),
+                ValueType::RecordPointer("okay".into()),
+            )
+            .await
+            .unwrap();
+            db.insert(
+                KeyType::Record("/root/status/cpu".to_string()),
+                ValueType::RecordPointer(RecordValue::Integer(42)),
+            )
+            .await
+            .unwrap();
+            db.insert(
+                KeyType::Record("/root/other".to_string()),
+                ValueType::RecordPointer("not exported".into()),
+            )
+            .await
+            .unwrap();
+            db.push(
+                KeyType::Record("/root/status/jobs".to_string()),
+                "job1".to_string(),
+            )
+            .await
+            .unwrap();
+            db.hash_set(
+                KeyType::Record("/root/status/vps02".to_string()),
+                "cpu".to_string(),
+                "10".to_string(),
+            )
+            .await
+            .unwrap();
+            db.set_add(
+                KeyType::Record("/root/status/tags".to_string()),
+                "prod".to_string(),
+            )
+            .await
+            .unwrap();
+
+            // Export only a subtree
+            let snapshot = db
+                .export(KeyType::Record("/root/status".to_string()))
+                .expect("Failed to export");
+            assert_eq!(2, snapshot.record.len());
+            assert_eq!(1, snapshot.queue.len());
+            assert_eq!(1, snapshot.hash.len());
+            assert_eq!(1, snapshot.set.len());
+
+            let response = db.export(KeyType::Record("/root/no_exist".to_string()));
+            assert_eq!(ErrorCode::NotFound, response.unwrap_err().code());
+
+            // Every format gives back the same snapshot
+            for format in [
+                SnapshotFormat::Json,
+                SnapshotFormat::Toml,
+                SnapshotFormat::Binary,
+            ] {
+                let data = snapshot.encode(format).expect("Failed to encode");
+                let decoded = Snapshot::decode(&data, format).expect("Failed to decode");
+                assert_eq!(snapshot, decoded);
+            }
+
+            // Import into an empty database
+            let mut other = Database::with_storage("root".to_string(), super::storage()).unwrap();
+            let summary = other.import(&snapshot, ImportPolicy::Merge).await.unwrap();
+            assert_eq!(5, summary.imported);
+            assert_eq!(
+                ValueType::RecordPointer(RecordValue::Integer(42)),
+                other
+                    .get(KeyType::Record("/root/status/cpu".to_string()))
+                    .unwrap()
+            );
+            assert!(other
+                .get(KeyType::Record("/root/other".to_string()))
+                .is_err());
+
+            // Skip leaves existing keys as they are
+            let summary = other.import(&snapshot, ImportPolicy::Skip).await.unwrap();
+            assert_eq!(0, summary.imported);
+            assert_eq!(5, summary.skipped);
+            assert_eq!(
+                1,
+                other
+                    .queue_length(KeyType::Record("/root/status/jobs".to_string()))
+                    .unwrap()
+            );
+
+            // Merge appends to queues, overwrite replaces them
+            other.import(&snapshot, ImportPolicy::Merge).await.unwrap();
+            assert_eq!(
+                2,
+                other
+                    .queue_length(KeyType::Record("/root/status/jobs".to_string()))
+                    .unwrap()
+            );
+
+            other
+                .import(&snapshot, ImportPolicy::Overwrite)
+                .await
+                .unwrap();
+            assert_eq!(
+                1,
+                other
+                    .queue_length(KeyType::Record("/root/status/jobs".to_string()))
+                    .unwrap()
+            );
+
+            // Invalid snapshot does not change anything
+            other
+                .push(
+                    KeyType::Record("/root/status/jobs".to_string()),
+                    "job2".to_string(),
+                )
+                .await
+                .unwrap();
+
+            let mut invalid = snapshot.clone();
+            invalid.record[0].r#type = "integer".to_string();
+            invalid.record[0].value = "not a number".to_string();
+            let response = other.import(&invalid, ImportPolicy::Overwrite).await;
+            assert!(response.is_err());
+            assert_eq!(
+                2,
+                other
+                    .queue_length(KeyType::Record("/root/status/jobs".to_string()))
+                    .unwrap()
+            );
         })
     }
 
@@ -3,9 +3,15 @@ mod test {
     use tokio::sync::mpsc::channel;
 
     use crate::{
-        datastore::{enums::DatabaseAction, utilities::start_datastore},
+        datastore::{
+            enums::context::RequestContext,
+            enums::pair::{RecordValue, ValueType},
+            enums::{DatabaseAction, QueueEnd},
+            limits::{EvictionPolicy, Limits, QueueLimit},
+            utilities::start_datastore,
+        },
         logger::{
-            enums::{LogItem, LoggerAction, LoggerResponse},
+            enums::{LogItem, LogRotation, LoggerAction, LoggerResponse},
             utilities::start_logger,
             LoggerManager,
         },
@@ -276,8 +282,16 @@ mod test {
             let placeholder_date = std::time::Duration::from_secs(5);
 
             let etalon = vec![
-                LogItem::SetKey(placeholder_date, "/root/test1".to_string(), "placeholder value".to_string()),
-                LogItem::SetKey(placeholder_date, "/root/test2".to_string(), "placeholder value".to_string())
+                LogItem::SetKey(
+                    placeholder_date,
+                    "/root/test1".to_string(),
+                    "placeholder value".to_string(),
+                ),
+                LogItem::SetKey(
+                    placeholder_date,
+                    "/root/test2".to_string(),
+                    "placeholder value".to_string(),
+                ),
             ];
 
             let (logger_sender, _) = start_logger(&path).await;
@@ -296,4 +310,349 @@ mod test {
             assert_eq!(LoggerResponse::FromAppendFile(etalon), response);
         });
     }
+
+    #[test]
+    fn test_log6() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let path = "/tmp/datastore-log6".to_string();
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).expect("failed to delete directory");
+
+            let placeholder_date = std::time::Duration::from_secs(5);
+            let document = RecordValue::parse("json", r#"{"status":{"cpu":10}}"#).unwrap();
+
+            let etalon = vec![
+                LogItem::SetTypedKey(placeholder_date, "/root/doc".to_string(), document.clone()),
+                LogItem::SetTypedKey(
+                    placeholder_date,
+                    "/root/count".to_string(),
+                    RecordValue::Integer(-42),
+                ),
+                LogItem::SetTypedKey(
+                    placeholder_date,
+                    "/root/blob".to_string(),
+                    RecordValue::Bytes(vec![0, 159, 255]),
+                ),
+            ];
+
+            let (logger_sender, _) = start_logger(&path).await;
+
+            let (tx, mut rx) = channel(10);
+            let action = LoggerAction::Write(tx, etalon.clone());
+
+            logger_sender.send(action).await.unwrap();
+            rx.recv().await.unwrap();
+
+            // Types must be kept in the append file
+            let (tx, mut rx) = channel(10);
+            let action = LoggerAction::ReadAppendFile(tx);
+
+            logger_sender.send(action).await.unwrap();
+            let response = rx.recv().await.unwrap();
+            assert_eq!(LoggerResponse::FromAppendFile(etalon), response);
+
+            // Types must be kept after replay too
+            let (sender, _) = start_datastore("root".to_string(), None, Some(logger_sender)).await;
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Get(tx, "/root/doc".to_string());
+            sender.send(action).await.unwrap();
+            let value = rx.recv().await.unwrap().expect("Failed to get value");
+            assert_eq!(ValueType::RecordPointer(document), value);
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Get(tx, "/root/blob".to_string());
+            sender.send(action).await.unwrap();
+            let value = rx.recv().await.unwrap().expect("Failed to get value");
+            assert_eq!(
+                ValueType::RecordPointer(RecordValue::Bytes(vec![0, 159, 255])),
+                value
+            );
+        });
+    }
+
+    #[test]
+    fn test_log7() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let path = "/tmp/datastore-log7".to_string();
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).expect("failed to delete directory");
+
+            let second = |x| std::time::Duration::from_secs(x);
+
+            let etalon = vec![
+                LogItem::SetKey(second(1), "/root/vps01".to_string(), "ok".to_string()),
+                LogItem::SetKey(second(2), "/root/vps02".to_string(), "ok".to_string()),
+                LogItem::SetKey(second(3), "/root/vps01".to_string(), "failed".to_string()),
+                LogItem::RemPath(second(4), "/root".to_string()),
+            ];
+
+            let (logger_sender, _) = start_logger(&path).await;
+
+            let (tx, mut rx) = channel(10);
+            logger_sender
+                .send(LoggerAction::Write(tx, etalon.clone()))
+                .await
+                .unwrap();
+            rx.recv().await.unwrap();
+
+            // Changes of other keys are not in the history, removal of table is
+            let (tx, mut rx) = channel(10);
+            logger_sender
+                .send(LoggerAction::History(tx, "/root/vps01".to_string()))
+                .await
+                .unwrap();
+            let history = match rx.recv().await.unwrap() {
+                LoggerResponse::History(history) => history,
+                other => panic!("unexpected response: {:?}", other),
+            };
+            let changes: Vec<(u64, &str, Option<&str>)> = history
+                .iter()
+                .map(|x| (x.time.as_secs(), x.action.as_str(), x.value.as_deref()))
+                .collect();
+            assert_eq!(
+                vec![
+                    (1, "set", Some("ok")),
+                    (3, "set", Some("failed")),
+                    (4, "remove_path", None)
+                ],
+                changes
+            );
+
+            // Items after the restore point are cut off
+            let (tx, mut rx) = channel(10);
+            logger_sender
+                .send(LoggerAction::RestorePoint(tx, second(2)))
+                .await
+                .unwrap();
+            assert_eq!(LoggerResponse::Ok, rx.recv().await.unwrap());
+
+            let (tx, mut rx) = channel(10);
+            logger_sender
+                .send(LoggerAction::ReadAppendFile(tx))
+                .await
+                .unwrap();
+            let response = rx.recv().await.unwrap();
+            assert_eq!(
+                LoggerResponse::FromAppendFile(etalon[..2].to_vec()),
+                response
+            );
+
+            // Restore point is used only once, original file is kept
+            let (tx, mut rx) = channel(10);
+            logger_sender
+                .send(LoggerAction::ReadAppendFile(tx))
+                .await
+                .unwrap();
+            let response = rx.recv().await.unwrap();
+            assert_eq!(
+                LoggerResponse::FromAppendFile(etalon[..2].to_vec()),
+                response
+            );
+
+            let backup = crate::logger::utilities::internal::read_append_file(
+                std::path::Path::new(&format!("{}/hermes.af.2.bak", path)),
+            )
+            .expect("Failed to read backup");
+            assert_eq!(etalon, backup);
+
+            // History is read from the backup too, items of the append file that are in the backup
+            // are not repeated
+            let (tx, mut rx) = channel(10);
+            let item = LogItem::SetKey(second(5), "/root/vps01".to_string(), "ok".to_string());
+            logger_sender
+                .send(LoggerAction::Write(tx, vec![item]))
+                .await
+                .unwrap();
+            rx.recv().await.unwrap();
+
+            let (tx, mut rx) = channel(10);
+            logger_sender
+                .send(LoggerAction::History(tx, "/root/vps01".to_string()))
+                .await
+                .unwrap();
+            let history = match rx.recv().await.unwrap() {
+                LoggerResponse::History(history) => history,
+                other => panic!("unexpected response: {:?}", other),
+            };
+            let changes: Vec<(u64, &str)> = history
+                .iter()
+                .map(|x| (x.time.as_secs(), x.action.as_str()))
+                .collect();
+            assert_eq!(
+                vec![(1, "set"), (3, "set"), (4, "remove_path"), (5, "set")],
+                changes
+            );
+        });
+    }
+
+    #[test]
+    fn test_log8() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let path = "/tmp/datastore-log8".to_string();
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).expect("failed to delete directory");
+
+            let second = |x| std::time::Duration::from_secs(x);
+            let context = RequestContext {
+                interface: "rest".to_string(),
+                peer: Some("127.0.0.1:50000".to_string()),
+                claimed_user: Some("ati".to_string()),
+                request_id: Some("abc".to_string()),
+            };
+
+            let (logger_sender, _) = start_logger(&path).await;
+
+            // Every write rotates, because the log is always bigger than 1 byte
+            let (tx, mut rx) = channel(10);
+            let rotation = LogRotation {
+                max_size: Some(1),
+                interval: None,
+                retention: 2,
+                compress: true,
+            };
+            logger_sender
+                .send(LoggerAction::Rotation(tx, rotation))
+                .await
+                .unwrap();
+            assert_eq!(LoggerResponse::Ok, rx.recv().await.unwrap());
+
+            for i in 1..=4 {
+                let items = LogItem::with_context(
+                    &Some(context.clone()),
+                    vec![LogItem::SetKey(
+                        second(i),
+                        "/root/vps01".to_string(),
+                        format!("value{}", i),
+                    )],
+                );
+                let (tx, mut rx) = channel(10);
+                logger_sender
+                    .send(LoggerAction::Write(tx, items))
+                    .await
+                    .unwrap();
+                assert_eq!(LoggerResponse::Ok, rx.recv().await.unwrap());
+            }
+
+            // Only the retained files are kept and they are compressed
+            let human_log = std::fs::read_to_string(format!("{}/human.log", path)).unwrap();
+            assert!(human_log.contains("value4"));
+            assert!(human_log
+                .contains("(interface=rest peer=127.0.0.1:50000 claimed_user=ati request=abc)"));
+
+            let mut decoder = flate2::read::GzDecoder::new(
+                std::fs::File::open(format!("{}/human.log.1.gz", path)).unwrap(),
+            );
+            let mut rotated = String::new();
+            std::io::Read::read_to_string(&mut decoder, &mut rotated).unwrap();
+            assert!(rotated.contains("value3"));
+
+            assert!(std::path::Path::new(&format!("{}/human.log.2.gz", path)).exists());
+            assert!(!std::path::Path::new(&format!("{}/human.log.3.gz", path)).exists());
+            assert!(!std::path::Path::new(&format!("{}/human.log.1", path)).exists());
+
+            // Append file is not rotated and the context is kept in the history
+            let (tx, mut rx) = channel(10);
+            logger_sender
+                .send(LoggerAction::History(tx, "/root/vps01".to_string()))
+                .await
+                .unwrap();
+            let history = match rx.recv().await.unwrap() {
+                LoggerResponse::History(history) => history,
+                other => panic!("unexpected response: {:?}", other),
+            };
+            assert_eq!(4, history.len());
+            assert!(history.iter().all(|x| x.context == Some(context.clone())));
+        });
+    }
+
+    #[test]
+    fn test_log9() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let path = "/tmp/datastore-log9".to_string();
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).expect("failed to delete directory");
+
+            let (logger_sender, _) = start_logger(&path).await;
+            let (sender, _) =
+                start_datastore("root".to_string(), None, Some(logger_sender.clone())).await;
+
+            let limits = Limits {
+                queues: vec![QueueLimit {
+                    prefix: "/root".to_string(),
+                    max_length: 2,
+                }],
+                policy: EvictionPolicy::Oldest,
+                evict_prefixes: vec!["/root/events".to_string()],
+                ..Default::default()
+            };
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::SetLimits(tx, "/root".to_string(), limits);
+            sender.send(action).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to set limits");
+
+            // Third push evicts the oldest item
+            for item in ["1", "2", "3"] {
+                let (tx, mut rx) = channel(10);
+                let action = DatabaseAction::Push(tx, "/root/events".to_string(), item.to_string());
+                sender.send(action).await.unwrap();
+                rx.recv().await.unwrap().expect("Failed to push");
+            }
+
+            // Push to the front evicts the item at the back
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::PushTo(
+                tx,
+                "/root/events".to_string(),
+                "0".to_string(),
+                QueueEnd::Front,
+            );
+            sender.send(action).await.unwrap();
+            assert_eq!(2, rx.recv().await.unwrap().expect("Failed to push"));
+
+            let (tx, mut rx) = channel(10);
+            sender.send(DatabaseAction::Flush(tx)).await.unwrap();
+            rx.recv().await.unwrap().expect("Failed to flush");
+
+            // Replay of the append file gives the same queue, without limits
+            let (sender, _) = start_datastore("root".to_string(), None, Some(logger_sender)).await;
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::QueueLength(tx, "/root/events".to_string());
+            sender.send(action).await.unwrap();
+            assert_eq!(2, rx.recv().await.unwrap().expect("Failed to get length"));
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Pop(tx, "/root/events".to_string());
+            sender.send(action).await.unwrap();
+            assert_eq!(
+                ValueType::RecordPointer(RecordValue::from("0")),
+                rx.recv().await.unwrap().expect("Failed to pop")
+            );
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Peek(tx, "/root/events".to_string(), QueueEnd::Back);
+            sender.send(action).await.unwrap();
+            assert_eq!(
+                ValueType::RecordPointer(RecordValue::from("2")),
+                rx.recv().await.unwrap().expect("Failed to peek")
+            );
+        });
+    }
 }
@@ -1,3 +1,248 @@
+#![allow(
+    clippy::bool_assert_comparison,
+    clippy::len_zero,
+    clippy::let_unit_value,
+    clippy::needless_return,
+    clippy::unnecessary_to_owned
+)]
+
 mod hook_manager;
-mod datastore;
-mod logger;
\ No newline at end of file
+mod logger;
+
+/// Datastore tests on the default, in-memory storage
+mod datastore {
+    use crate::datastore::storage::{MemoryStorage, Storage};
+
+    fn storage() -> Box<dyn Storage> {
+        Box::new(MemoryStorage::new())
+    }
+
+    include!("datastore.rs");
+}
+
+/// Same datastore tests on sled storage
+mod datastore_sled {
+    use crate::datastore::storage::{SledStorage, Storage};
+
+    fn storage() -> Box<dyn Storage> {
+        Box::new(SledStorage::temporary().expect("Failed to open sled storage"))
+    }
+
+    include!("datastore.rs");
+
+    #[test]
+    fn test_persistence() {
+        use crate::datastore::{
+            enums::pair::{KeyType, RecordValue, ValueType},
+            Database,
+        };
+
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let path = std::env::temp_dir().join(format!("hermes-sled-{}", std::process::id()));
+            let path = path.to_str().unwrap().to_string();
+
+            let stats = {
+                let storage = SledStorage::open(&path).unwrap();
+                let mut db = Database::with_storage("root".to_string(), Box::new(storage)).unwrap();
+                assert!(db.is_persistent());
+                db.insert(
+                    KeyType::Record("/root/status/vps01".to_string()),
+                    ValueType::RecordPointer(RecordValue::from("ok")),
+                )
+                .await
+                .unwrap();
+
+                // Expiration is kept after reopen
+                db.expire(
+                    KeyType::Record("/root/status/vps01".to_string()),
+                    Some(std::time::Duration::from_secs(60)),
+                )
+                .unwrap();
+
+                // Delivery stays in flight after reopen
+                let queue = KeyType::Record("/root/jobs".to_string());
+                db.push(queue.clone(), "job1".to_string()).await.unwrap();
+                let id = db.next_delivery_id();
+                db.pop_reliable(queue, id, std::time::Duration::from_secs(10))
+                    .await
+                    .unwrap();
+
+                (
+                    db.stat("/root/status/vps01").unwrap(),
+                    db.stat("/root/status").unwrap(),
+                )
+            };
+
+            // Value is there after reopen without any append file.
+            // Flusher thread of sled releases the directory lock a bit later than the drop.
+            let mut storage = SledStorage::open(&path);
+            for _ in 0..20 {
+                if storage.is_ok() {
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                storage = SledStorage::open(&path);
+            }
+            let storage = storage.unwrap();
+            let mut db = Database::with_storage("root".to_string(), Box::new(storage)).unwrap();
+            let value = db.get(KeyType::Record("/root/status/vps01".to_string()));
+            let usage = db.usage().usage;
+            let reopened = (
+                db.stat("/root/status/vps01").unwrap(),
+                db.stat("/root/status").unwrap(),
+            );
+            let expiration = db.expiration(&KeyType::Record("/root/status/vps01".to_string()));
+            let expired = db.expired_deliveries(std::time::Duration::MAX);
+            let requeued = match expired.first() {
+                Some(id) => db.requeue(*id).await.ok(),
+                None => None,
+            };
+            let job = db.pop(KeyType::Record("/root/jobs".to_string())).await.ok();
+            drop(db);
+            let _ = std::fs::remove_dir_all(&path);
+
+            assert_eq!(
+                ValueType::RecordPointer(RecordValue::from("ok")),
+                value.unwrap()
+            );
+
+            // Existing content is counted at open, queue is removed while its only item is in flight
+            assert_eq!(1, usage.keys);
+            assert!(usage.memory > 0);
+
+            // Times of keys and tables are kept with them
+            assert!(stats.0[0].created.is_some());
+            assert!(stats.1[0].modified.is_some());
+            assert_eq!(stats, reopened);
+
+            assert_eq!(
+                Some(std::time::Duration::from_secs(60)),
+                expiration.unwrap()
+            );
+
+            // Unacknowledged item returns to its queue
+            assert_eq!(1, expired.len());
+            assert_eq!(Some("/root/jobs".to_string()), requeued);
+            assert_eq!(Some("job1".to_string()), job);
+        });
+    }
+
+    #[test]
+    fn test_persistence_of_hooks_and_queues() {
+        use crate::datastore::{
+            enums::{DatabaseAction, QueueEnd},
+            utilities::start_datastore_handle,
+        };
+        use crate::hook::utilities::start_hook_manager;
+        use tokio::sync::mpsc::channel;
+
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let path =
+                std::env::temp_dir().join(format!("hermes-sled-hooks-{}", std::process::id()));
+            let path = path.to_str().unwrap().to_string();
+
+            // Open the database again, flusher thread of sled releases the directory lock a bit later
+            async fn open(path: &str) -> SledStorage {
+                let mut storage = SledStorage::open(path);
+                for _ in 0..20 {
+                    if storage.is_ok() {
+                        break;
+                    }
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    storage = SledStorage::open(path);
+                }
+                storage.unwrap()
+            }
+
+            {
+                let (hook_sender, _) = start_hook_manager().await;
+                let (handle, thread) = start_datastore_handle(
+                    "root".to_string(),
+                    Box::new(open(&path).await),
+                    Some(hook_sender),
+                    None,
+                )
+                .await;
+
+                let (tx, mut rx) = channel(10);
+                let action = DatabaseAction::HookSet(
+                    tx,
+                    "/root/status".to_string(),
+                    "http://127.0.0.1:3031".to_string(),
+                );
+                handle.send(action).await.unwrap();
+                rx.recv().await.unwrap().unwrap();
+
+                for job in ["job1", "job2", "job3"] {
+                    let (tx, mut rx) = channel(10);
+                    let action =
+                        DatabaseAction::Push(tx, "/root/jobs".to_string(), job.to_string());
+                    handle.send(action).await.unwrap();
+                    rx.recv().await.unwrap().unwrap();
+                }
+
+                let (tx, mut rx) = channel(10);
+                let action = DatabaseAction::PopFrom(tx, "/root/jobs".to_string(), QueueEnd::Back);
+                handle.send(action).await.unwrap();
+                rx.recv().await.unwrap().unwrap();
+
+                drop(handle);
+                thread.await.unwrap();
+            }
+
+            // Hooks are set again and queue items are read one by one after reopen
+            let (hook_sender, _) = start_hook_manager().await;
+            let (handle, thread) = start_datastore_handle(
+                "root".to_string(),
+                Box::new(open(&path).await),
+                Some(hook_sender),
+                None,
+            )
+            .await;
+
+            let (tx, mut rx) = channel(10);
+            handle
+                .send(DatabaseAction::HookGet(tx, "/root/status".to_string()))
+                .await
+                .unwrap();
+            let hook = rx.recv().await.unwrap();
+
+            let (tx, mut rx) = channel(10);
+            handle
+                .send(DatabaseAction::QueueLength(tx, "/root/jobs".to_string()))
+                .await
+                .unwrap();
+            let length = rx.recv().await.unwrap();
+
+            let (tx, mut rx) = channel(10);
+            handle
+                .send(DatabaseAction::Peek(
+                    tx,
+                    "/root/jobs".to_string(),
+                    QueueEnd::Back,
+                ))
+                .await
+                .unwrap();
+            let last = rx.recv().await.unwrap();
+
+            drop(handle);
+            thread.await.unwrap();
+            let _ = std::fs::remove_dir_all(&path);
+
+            assert_eq!(vec!["http://127.0.0.1:3031".to_string()], hook.unwrap().1);
+            assert_eq!(2, length.unwrap());
+            assert_eq!(
+                crate::datastore::enums::pair::ValueType::RecordPointer("job2".into()),
+                last.unwrap()
+            );
+        });
+    }
+}
@@ -0,0 +1,9 @@
+-- Status is stored in upper case, only OK and NOK are accepted
+status = string.upper(_G.new["value"])
+
+if status ~= "OK" and status ~= "NOK" then
+    _G.new["reject"] = "status must be OK or NOK"
+    return
+end
+
+_G.new["value"] = status
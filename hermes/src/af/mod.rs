@@ -0,0 +1,727 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc::{channel, Sender};
+
+use onlyati_datastore::{
+    datastore::{
+        enums::{
+            pair::{KeyType, RecordValue},
+            snapshot::{Snapshot, SnapshotFormat, SnapshotHook},
+        },
+        stat::StatKind,
+        utilities::replay_log,
+        Database,
+    },
+    hook::{
+        enums::{HookManagerAction, HookManagerResponse},
+        utilities::start_hook_manager,
+    },
+    logger::{
+        enums::LogItem,
+        utilities::internal::{backup_append_file, scan_append_file, AppendFileContent},
+    },
+};
+
+use crate::arg::{AfAction, AfArgs};
+use crate::common::{format_time, parse_time};
+
+/// Entrypoint of append file tool
+///
+/// # Parameters
+/// - `args`: Arguments that has been parsed
+///
+/// # Return
+///
+/// In case of internal error with an error.
+/// Else return codes mean the following:
+/// - 0: Everything was fine
+/// - 1: Action has failed, e.g. file could not been read or written
+/// - 2: File contains a bad record
+pub async fn main_async(args: AfArgs) -> Result<i32, Box<dyn std::error::Error>> {
+    // Read environment variable and set trace accordingly, default is Level::ERROR
+    let subscriber = tracing_subscriber::FmtSubscriber::builder()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_env("HERMES_CLI_LOG"))
+        .finish();
+    tracing::subscriber::set_global_default(subscriber).expect("Failed to set loger");
+
+    let path = Path::new(&args.file);
+    if !path.is_file() {
+        eprintln!("File '{}' does not exist", args.file);
+        return Ok(1);
+    }
+
+    let content = match scan_append_file(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("File '{}' could not been read: {}", args.file, e);
+            return Ok(1);
+        }
+    };
+
+    let result = match args.action {
+        AfAction::Print {
+            format,
+            key,
+            since,
+            until,
+        } => print(&content, &format, key, since, until),
+        AfAction::Validate => validate(&content),
+        AfAction::Truncate => truncate(&args.file, &content),
+        AfAction::Compact { output, name } => compact(&args.file, content, output, name).await,
+        AfAction::Dump {
+            format,
+            key,
+            name,
+            until,
+        } => dump(content, &format, key, name, until).await,
+        AfAction::Restore { until, output } => restore(&args.file, &content, &until, output),
+    };
+
+    match result {
+        Ok(rc) => Ok(rc),
+        Err(e) => {
+            eprintln!("{}", e);
+            Ok(1)
+        }
+    }
+}
+
+/// Print entries that match with the filters
+fn print(
+    content: &AppendFileContent,
+    format: &str,
+    key: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+) -> Result<i32, String> {
+    let json = match format {
+        "text" => false,
+        "json" => true,
+        _ => return Err("Format must be 'text' or 'json'".to_string()),
+    };
+    let since = since
+        .map(|x| parse_time(&x))
+        .transpose()?
+        .map(|x| x.as_nanos());
+    let until = until
+        .map(|x| parse_time(&x))
+        .transpose()?
+        .map(|x| x.as_nanos());
+
+    let mut stdout = std::io::stdout().lock();
+
+    for (offset, item) in &content.items {
+        let time = item.get_duration();
+
+        if since.map(|x| time < x).unwrap_or(false) || until.map(|x| time >= x).unwrap_or(false) {
+            continue;
+        }
+
+        if let Some(prefix) = &key {
+            match item.get_key() {
+                Some(key) if key.get_key().starts_with(prefix.as_str()) => (),
+                _ => continue,
+            }
+        }
+
+        let line = match json {
+            true => serde_json::json!({
+                "offset": offset,
+                "time": format_time(time),
+                "item": item,
+            })
+            .to_string(),
+            false => format!("{:>10} {} {}", offset, format_time(time), item),
+        };
+
+        if writeln!(stdout, "{}", line).is_err() {
+            return Ok(0);
+        }
+    }
+
+    if let Some((offset, e)) = &content.error {
+        eprintln!("Bad record at offset {}: {}", offset, e);
+        return Ok(2);
+    }
+
+    Ok(0)
+}
+
+/// Report the first bad record of file
+fn validate(content: &AppendFileContent) -> Result<i32, String> {
+    match &content.error {
+        Some((offset, e)) => {
+            println!(
+                "{} good records, first bad record is at offset {}, {} bytes cannot be read: {}",
+                content.items.len(),
+                offset,
+                content.size - offset,
+                e
+            );
+            Ok(2)
+        }
+        None => {
+            println!(
+                "{} records in {} bytes, file is valid",
+                content.items.len(),
+                content.size
+            );
+            Ok(0)
+        }
+    }
+}
+
+/// Cut the file at its first bad record
+fn truncate(file: &str, content: &AppendFileContent) -> Result<i32, String> {
+    let offset = match &content.error {
+        Some((offset, _)) => *offset,
+        None => {
+            println!("File is valid, nothing to truncate");
+            return Ok(0);
+        }
+    };
+
+    let backup = backup_file(file)?;
+
+    let handle = std::fs::OpenOptions::new()
+        .write(true)
+        .open(file)
+        .map_err(|e| format!("File '{}' could not been opened: {}", file, e))?;
+    handle
+        .set_len(offset as u64)
+        .map_err(|e| format!("File '{}' could not been truncated: {}", file, e))?;
+
+    println!(
+        "File is truncated at offset {}, {} bytes are removed, original file is saved as '{}'",
+        offset,
+        content.size - offset,
+        backup
+    );
+    Ok(0)
+}
+
+/// Replace the file with entries that rebuild the current state
+async fn compact(
+    file: &str,
+    content: AppendFileContent,
+    output: Option<String>,
+    name: Option<String>,
+) -> Result<i32, String> {
+    if let Some((offset, e)) = &content.error {
+        eprintln!(
+            "Bad record at offset {}: {}, truncate the file before compaction",
+            offset, e
+        );
+        return Ok(2);
+    }
+
+    eprintln!(
+        "Warning: compaction drops the intermediate changes, point-in-time recovery and history \
+         of the compacted file can go back only to the last change of each key"
+    );
+
+    let before = content.items.len();
+    let name = name.unwrap_or_else(|| database_name(&content.items));
+    let root = format!("/{}", name);
+    let mut times = ChangeTimes::from_log(&content.items);
+    let (mut db, hook_sender) = replay(content, name).await?;
+
+    // Times are read before requeue, else requeued queues would look changed now
+    let snapshot = state(&db, &hook_sender, root.clone()).await?;
+    times.read_keys(&db, &snapshot);
+
+    // Unacknowledged items cannot be written without their delivery, so they are put back to their queue
+    for id in db.expired_deliveries(Duration::MAX) {
+        db.requeue(id).await.map_err(|e| e.to_string())?;
+    }
+
+    let snapshot = state(&db, &hook_sender, root).await?;
+    let items = compacted_items(snapshot, &times)?;
+
+    let mut data = Vec::new();
+    for item in &items {
+        let encoded = bincode::serialize(item).map_err(|e| e.to_string())?;
+        data.extend(encoded);
+    }
+
+    let target = match output {
+        Some(output) => output,
+        None => {
+            let backup = backup_file(file)?;
+            println!("Original file is saved as '{}'", backup);
+            file.to_string()
+        }
+    };
+
+    std::fs::write(&target, data)
+        .map_err(|e| format!("File '{}' could not been written: {}", target, e))?;
+
+    println!(
+        "{} records are compacted into {} records in '{}'",
+        before,
+        items.len(),
+        target
+    );
+    Ok(0)
+}
+
+/// Print the state of database after replay
+async fn dump(
+    mut content: AppendFileContent,
+    format: &str,
+    key: Option<String>,
+    name: Option<String>,
+    until: Option<String>,
+) -> Result<i32, String> {
+    let format = match format.parse::<SnapshotFormat>() {
+        Ok(SnapshotFormat::Binary) | Err(_) => {
+            return Err("Format must be 'json' or 'toml'".to_string())
+        }
+        Ok(format) => format,
+    };
+
+    if let Some(until) = until {
+        let until = parse_time(&until)?.as_nanos();
+        if let Some(index) = restore_point(&content, until) {
+            content.items.truncate(index);
+            content.error = None;
+        }
+    }
+
+    let error = content.error.clone();
+    let name = name.unwrap_or_else(|| database_name(&content.items));
+    let key = key.unwrap_or_else(|| format!("/{}", name));
+    let (db, hook_sender) = replay(content, name).await?;
+    let snapshot = state(&db, &hook_sender, key).await?;
+    let data = snapshot.encode(format).map_err(|e| e.to_string())?;
+    // Output may be closed meanwhile, e.g. by head, it is not an error
+    let _ = writeln!(std::io::stdout(), "{}", String::from_utf8_lossy(&data));
+
+    if let Some((offset, e)) = error {
+        eprintln!(
+            "Bad record at offset {}: {}, state is dumped until it",
+            offset, e
+        );
+        return Ok(2);
+    }
+
+    Ok(0)
+}
+
+/// Cut the file after the last entry that was written at or before the specified time
+fn restore(
+    file: &str,
+    content: &AppendFileContent,
+    until: &str,
+    output: Option<String>,
+) -> Result<i32, String> {
+    let until = parse_time(until)?.as_nanos();
+
+    let (index, offset) = match restore_point(content, until) {
+        Some(index) => (index, content.items[index].0),
+        None => match &content.error {
+            Some((offset, e)) => {
+                eprintln!(
+                    "Bad record at offset {}: {}, truncate the file before restore",
+                    offset, e
+                );
+                return Ok(2);
+            }
+            None => {
+                println!(
+                    "File does not contain entry after the specified time, nothing to restore"
+                );
+                return Ok(0);
+            }
+        },
+    };
+
+    let target = match output {
+        Some(output) => {
+            std::fs::copy(file, &output)
+                .map_err(|e| format!("File '{}' could not been written: {}", output, e))?;
+            output
+        }
+        None => {
+            let backup = backup_file(file)?;
+            println!("Original file is saved as '{}'", backup);
+            file.to_string()
+        }
+    };
+
+    let handle = std::fs::OpenOptions::new()
+        .write(true)
+        .open(&target)
+        .map_err(|e| format!("File '{}' could not been opened: {}", target, e))?;
+    handle
+        .set_len(offset as u64)
+        .map_err(|e| format!("File '{}' could not been truncated: {}", target, e))?;
+
+    println!(
+        "{} records are kept and {} records are removed in '{}'",
+        index,
+        content.items.len() - index,
+        target
+    );
+    Ok(0)
+}
+
+/// Index of the first entry that was written after the specified time
+fn restore_point(content: &AppendFileContent, until: u128) -> Option<usize> {
+    content
+        .items
+        .iter()
+        .position(|(_, item)| item.get_duration() > until)
+}
+
+/// Replay the good records into an empty database with a hook manager that does not execute hooks
+async fn replay(
+    content: AppendFileContent,
+    name: String,
+) -> Result<(Database, Sender<HookManagerAction>), String> {
+    let mut db = Database::new(name).map_err(|e| e.to_string())?;
+
+    let (hook_sender, _) = start_hook_manager().await;
+    match ask_hook_manager(&hook_sender, |tx| HookManagerAction::Enable(tx, false)).await? {
+        HookManagerResponse::Ok => (),
+        other => return Err(format!("Failed to turn off hook execution: {:?}", other)),
+    }
+    db.subscribe_to_hook_manager(hook_sender.clone());
+
+    let (offsets, items): (Vec<usize>, Vec<LogItem>) = content.items.into_iter().unzip();
+    if let Err((index, e)) = replay_log(&mut db, items).await {
+        return Err(format!("Record at offset {}: {}", offsets[index], e));
+    }
+
+    Ok((db, hook_sender))
+}
+
+/// Export a table of database with the hooks
+async fn state(
+    db: &Database,
+    hook_sender: &Sender<HookManagerAction>,
+    key: String,
+) -> Result<Snapshot, String> {
+    let mut snapshot = db
+        .export(KeyType::Record(key.clone()))
+        .map_err(|e| e.to_string())?;
+
+    if let HookManagerResponse::HookList(list) =
+        ask_hook_manager(hook_sender, |tx| HookManagerAction::List(tx, key)).await?
+    {
+        snapshot.hook = list
+            .into_iter()
+            .map(|(prefix, links)| SnapshotHook { prefix, links })
+            .collect();
+    }
+
+    Ok(snapshot)
+}
+
+/// Time of the last change of keys and hooks, compacted entries are written with them, so they stay
+/// in time order with the later entries. Earlier changes are dropped, so point-in-time recovery and
+/// history of the compacted file cannot go back before the last change of a key, only the backup can
+#[derive(Default)]
+struct ChangeTimes {
+    /// Time of the last log item, it is used when nothing is known about an entry
+    last: Duration,
+
+    /// Time when the links of hooks were set, by prefix and link
+    hooks: BTreeMap<(String, String), Duration>,
+
+    /// Time of the last change of keys, from the replay
+    keys: BTreeMap<String, Duration>,
+
+    /// Time when the records expire, from the replay
+    expirations: BTreeMap<String, Duration>,
+}
+
+impl ChangeTimes {
+    /// Collect the time of the last log item and the times of hook changes
+    fn from_log(items: &[(usize, LogItem)]) -> Self {
+        let mut times = Self::default();
+
+        for (_, item) in items {
+            let time = Duration::from_nanos(item.get_duration() as u64);
+            times.last = times.last.max(time);
+            if let LogItem::SetHook(_, prefix, link) = item.inner() {
+                times.hooks.insert((prefix.clone(), link.clone()), time);
+            }
+        }
+
+        times
+    }
+
+    /// Read the modification time of the keys of snapshot, replay stamped them with the time of log items
+    fn read_keys(&mut self, db: &Database, snapshot: &Snapshot) {
+        let keys = snapshot
+            .record
+            .iter()
+            .map(|x| &x.key)
+            .chain(snapshot.queue.iter().map(|x| &x.key))
+            .chain(snapshot.hash.iter().map(|x| &x.key))
+            .chain(snapshot.set.iter().map(|x| &x.key));
+
+        for key in keys {
+            let modified = db
+                .stat(key)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|x| x.kind != StatKind::Table)
+                .filter_map(|x| x.modified)
+                .max();
+            if let Some(modified) = modified {
+                self.keys.insert(key.clone(), modified);
+            }
+        }
+
+        for record in &snapshot.record {
+            let key = KeyType::Record(record.key.clone());
+            if let Ok(Some(deadline)) = db.expiration(&key) {
+                self.expirations.insert(record.key.clone(), deadline);
+            }
+        }
+    }
+
+    fn key(&self, key: &str) -> Duration {
+        self.keys.get(key).copied().unwrap_or(self.last)
+    }
+
+    fn hook(&self, prefix: &str, link: &str) -> Duration {
+        self.hooks
+            .get(&(prefix.to_string(), link.to_string()))
+            .copied()
+            .unwrap_or(self.last)
+    }
+}
+
+/// Make the fewest append file entries that give back the snapshot, they are ordered by their time
+fn compacted_items(snapshot: Snapshot, times: &ChangeTimes) -> Result<Vec<LogItem>, String> {
+    let mut items = Vec::new();
+
+    for hook in snapshot.hook {
+        for link in hook.links {
+            let time = times.hook(&hook.prefix, &link);
+            items.push(LogItem::SetHook(time, hook.prefix.clone(), link));
+        }
+    }
+
+    for record in snapshot.record {
+        let time = times.key(&record.key);
+        let expiration = times.expirations.get(&record.key).copied();
+        match RecordValue::parse(&record.r#type, &record.value).map_err(|e| e.to_string())? {
+            RecordValue::String(value) => {
+                items.push(LogItem::SetKey(time, record.key.clone(), value))
+            }
+            value => items.push(LogItem::SetTypedKey(time, record.key.clone(), value)),
+        }
+        if let Some(deadline) = expiration {
+            items.push(LogItem::Expire(time, record.key, Some(deadline)));
+        }
+    }
+
+    for queue in snapshot.queue {
+        let time = times.key(&queue.key);
+        for value in queue.values {
+            items.push(LogItem::Push(time, queue.key.clone(), value));
+        }
+    }
+
+    for hash in snapshot.hash {
+        let time = times.key(&hash.key);
+        for (field, value) in hash.fields {
+            items.push(LogItem::HashSet(time, hash.key.clone(), field, value));
+        }
+    }
+
+    for set in snapshot.set {
+        let time = times.key(&set.key);
+        for member in set.members {
+            items.push(LogItem::SetAdd(time, set.key.clone(), member));
+        }
+    }
+
+    // Stable sort keeps the order of queue items
+    items.sort_by_key(|x| x.get_duration());
+    Ok(items)
+}
+
+/// Name of database is the first part of the first key, e.g. `root` of `/root/status`
+fn database_name(items: &[(usize, LogItem)]) -> String {
+    items
+        .iter()
+        .filter_map(|(_, item)| item.get_key())
+        .filter_map(|key| key.get_key().split('/').nth(1).map(|x| x.to_string()))
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| "root".to_string())
+}
+
+/// Copy the file next to itself with the current time in its name, existing backup is not overwritten
+fn backup_file(file: &str) -> Result<String, String> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| e.to_string())?;
+    backup_append_file(Path::new(file), now.as_secs())
+}
+
+/// Send an action to the hook manager and wait for its answer
+async fn ask_hook_manager<F>(
+    hook_sender: &Sender<HookManagerAction>,
+    action: F,
+) -> Result<HookManagerResponse, String>
+where
+    F: FnOnce(Sender<HookManagerResponse>) -> HookManagerAction,
+{
+    let (tx, mut rx) = channel(10);
+    if let Err(e) = hook_sender.send(action(tx)).await {
+        return Err(format!("Failed to send to hook manager: {}", e));
+    }
+
+    match rx.recv().await {
+        Some(response) => Ok(response),
+        None => Err("Failed to receive from hook manager".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Time of the nth entry of the generated file
+    fn time(n: u64) -> Duration {
+        Duration::from_secs(1_686_000_000 + n)
+    }
+
+    /// Write an append file with every kind of change, the deletes of missing keys are like in older files
+    fn generate(path: &str) -> usize {
+        let items = vec![
+            LogItem::SetKey(time(1), "/root/status/a".to_string(), "1".to_string()),
+            LogItem::SetKey(time(2), "/root/status/a".to_string(), "2".to_string()),
+            LogItem::Push(time(3), "/root/jobs".to_string(), "job1".to_string()),
+            LogItem::Push(time(4), "/root/jobs".to_string(), "job2".to_string()),
+            LogItem::Pop(time(5), "/root/jobs".to_string()),
+            LogItem::HashSet(
+                time(6),
+                "/root/hash".to_string(),
+                "field".to_string(),
+                "value".to_string(),
+            ),
+            LogItem::SetAdd(time(7), "/root/set".to_string(), "member".to_string()),
+            LogItem::SetKey(time(8), "/root/old/a".to_string(), "x".to_string()),
+            LogItem::RemPath(time(9), "/root/old".to_string()),
+            LogItem::RemKey(time(10), "/root/missing".to_string()),
+            LogItem::Expire(
+                time(11),
+                "/root/status/a".to_string(),
+                Some(time(1_000_000)),
+            ),
+        ];
+
+        let mut data = Vec::new();
+        for item in &items {
+            data.extend(bincode::serialize(item).unwrap());
+        }
+        std::fs::write(path, data).unwrap();
+        items.len()
+    }
+
+    /// Snapshot of the root table after the replay of file
+    async fn snapshot_of(path: &str) -> Snapshot {
+        let content = scan_append_file(Path::new(path)).unwrap();
+        let (db, hook_sender) = replay(content, "root".to_string()).await.unwrap();
+        state(&db, &hook_sender, "/root".to_string()).await.unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            let dir = "/tmp/hermes-af-test";
+            let _ = std::fs::remove_dir_all(dir);
+            std::fs::create_dir_all(dir).unwrap();
+            let file = format!("{}/hermes.af", dir);
+            let count = generate(&file);
+            let size = std::fs::metadata(&file).unwrap().len();
+
+            // Every entry can be read, printed and replayed
+            let content = scan_append_file(Path::new(&file)).unwrap();
+            assert_eq!(count, content.items.len());
+            assert_eq!(Ok(0), validate(&content));
+            assert_eq!(Ok(0), print(&content, "text", None, None, None));
+            assert!(print(&content, "xml", None, None, None).is_err());
+            assert_eq!(Ok(0), dump(content, "json", None, None, None).await);
+
+            let snapshot = snapshot_of(&file).await;
+            assert_eq!(1, snapshot.record.len());
+            assert_eq!("2", snapshot.record[0].value);
+            assert_eq!(vec!["job2".to_string()], snapshot.queue[0].values);
+
+            // Half written record is reported and cut off, the original file is kept
+            let mut handle = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&file)
+                .unwrap();
+            handle.write_all(&[1, 2, 3]).unwrap();
+            drop(handle);
+
+            let content = scan_append_file(Path::new(&file)).unwrap();
+            assert_eq!(Some(size as usize), content.error.as_ref().map(|x| x.0));
+            assert_eq!(Ok(2), validate(&content));
+            assert_eq!(Ok(0), truncate(&file, &content));
+            assert_eq!(size, std::fs::metadata(&file).unwrap().len());
+
+            let backups = std::fs::read_dir(dir)
+                .unwrap()
+                .filter_map(|x| x.ok())
+                .filter(|x| x.file_name().to_string_lossy().ends_with(".bak"))
+                .count();
+            assert_eq!(1, backups);
+
+            // Backup is not overwritten
+            assert!(backup_append_file(Path::new(&file), 1).is_ok());
+            assert!(backup_append_file(Path::new(&file), 1).is_err());
+
+            // Compacted file gives the same state with fewer entries
+            let compacted = format!("{}/compacted.af", dir);
+            let content = scan_append_file(Path::new(&file)).unwrap();
+            assert_eq!(
+                Ok(0),
+                compact(&file, content, Some(compacted.clone()), None).await
+            );
+            let content = scan_append_file(Path::new(&compacted)).unwrap();
+            assert!(content.items.len() < count);
+            assert!(content
+                .items
+                .iter()
+                .any(|(_, item)| matches!(item, LogItem::Expire(_, _, Some(_)))));
+            assert_eq!(snapshot, snapshot_of(&compacted).await);
+
+            // Restored file keeps the entries until the time
+            let restored = format!("{}/restored.af", dir);
+            let content = scan_append_file(Path::new(&file)).unwrap();
+            let until = format_time(time(5).as_nanos());
+            assert_eq!(
+                Ok(0),
+                restore(&file, &content, &until, Some(restored.clone()))
+            );
+            let content = scan_append_file(Path::new(&restored)).unwrap();
+            assert_eq!(5, content.items.len());
+
+            let snapshot = snapshot_of(&restored).await;
+            assert_eq!("2", snapshot.record[0].value);
+            assert_eq!(vec!["job2".to_string()], snapshot.queue[0].values);
+            assert!(snapshot.hash.is_empty());
+
+            let content = scan_append_file(Path::new(&file)).unwrap();
+            let until = Some(format_time(time(5).as_nanos()));
+            assert_eq!(Ok(0), dump(content, "toml", None, None, until).await);
+
+            std::fs::remove_dir_all(dir).unwrap();
+        });
+    }
+}
@@ -26,6 +26,11 @@ pub enum Mode {
         /// Parameters for server
         #[arg(long, short)]
         config: String,
+
+        /// Restore the state of this time from the append file, later entries are cut off from it,
+        /// RFC 3339 format, e.g. 2023-06-10T03:00:00Z
+        #[arg(long)]
+        restore_until: Option<String>,
     },
 
     /// Start a shell to issue CLI commands
@@ -41,6 +46,9 @@ pub enum Mode {
         #[arg(short, long, default_value_t = String::from("/etc/olympus/hermes/client.toml"))]
         config: String,
     },
+
+    /// Inspect and repair an append file without starting a server
+    Af(AfArgs),
 }
 
 #[derive(Args, Debug, Clone)]
@@ -61,6 +69,86 @@ pub struct CliArgs {
     pub config: String,
 }
 
+#[derive(Args, Debug, Clone)]
+pub struct AfArgs {
+    /// Specify the action what to do
+    #[command(subcommand)]
+    pub action: AfAction,
+
+    /// Append file, e.g. /var/lib/hermes/hermes.af
+    #[arg(short, long)]
+    pub file: String,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum AfAction {
+    /// Print entries of append file
+    Print {
+        /// Output format: text or json
+        #[arg(long, short = 'o', default_value_t = String::from("text"))]
+        format: String,
+
+        /// Print only entries whose key begins with this prefix
+        #[arg(long, short)]
+        key: Option<String>,
+
+        /// Print only entries written at or after this time, RFC 3339 format, e.g. 2023-06-10T12:00:00Z
+        #[arg(long, short)]
+        since: Option<String>,
+
+        /// Print only entries written before this time, RFC 3339 format
+        #[arg(long, short)]
+        until: Option<String>,
+    },
+
+    /// Check that every record can be read, report the offset of the first bad one
+    Validate,
+
+    /// Cut the file after its last good record, original file is kept as <file>.<time>.bak
+    Truncate,
+
+    /// Rewrite the file with the fewest entries that give the same state, original file is kept as <file>.<time>.bak
+    Compact {
+        /// Write compacted file here instead of replacing the original
+        #[arg(long, short)]
+        output: Option<String>,
+
+        /// Name of database, by default it is taken from the first key of the file
+        #[arg(long, short)]
+        name: Option<String>,
+    },
+
+    /// Replay the file into an empty database and print its content
+    Dump {
+        /// Output format: json or toml
+        #[arg(long, short = 'o', default_value_t = String::from("json"))]
+        format: String,
+
+        /// Print only this table, by default the whole database is printed
+        #[arg(long, short)]
+        key: Option<String>,
+
+        /// Name of database, by default it is taken from the first key of the file
+        #[arg(long, short)]
+        name: Option<String>,
+
+        /// Replay only the entries written at or before this time, RFC 3339 format, e.g. 2023-06-10T03:00:00Z
+        #[arg(long, short)]
+        until: Option<String>,
+    },
+
+    /// Cut the file after the last entry that was written at or before the time, original file is kept as <file>.<time>.bak
+    Restore {
+        /// Entries written at or before this time are kept, RFC 3339 format, e.g. 2023-06-10T03:00:00Z
+        #[arg(long, short)]
+        until: String,
+
+        /// Write restored file here instead of replacing the original
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+}
+
 #[derive(Subcommand, Debug, Clone)]
 pub enum Action {
     /// Get a value of a key
@@ -68,6 +156,10 @@ pub enum Action {
         /// Specify the name of the key
         #[arg(long, short)]
         key: String,
+
+        /// Return only a part of JSON record, e.g. $.status.cpu
+        #[arg(long, short)]
+        path: Option<String>,
     },
 
     /// Set value to a key
@@ -79,6 +171,47 @@ pub enum Action {
         /// Specify the value for the key
         #[arg(long, short)]
         value: String,
+
+        /// Type of the value: string, integer, float, bool, json or bytes (base64)
+        #[arg(long, short)]
+        r#type: Option<String>,
+    },
+
+    /// Replace a part of JSON record
+    Patch {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+
+        /// Path within the JSON record, e.g. $.status.cpu
+        #[arg(long, short)]
+        path: String,
+
+        /// New value of the path in JSON format
+        #[arg(long, short)]
+        value: String,
+    },
+
+    /// Increment a counter, it is created if does not exist
+    Incr {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+
+        /// Amount of change, default is 1
+        #[arg(long, short, allow_negative_numbers = true)]
+        by: Option<i64>,
+    },
+
+    /// Decrement a counter, it is created if does not exist
+    Decr {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+
+        /// Amount of change, default is 1
+        #[arg(long, short, allow_negative_numbers = true)]
+        by: Option<i64>,
     },
 
     /// Remove specified key
@@ -100,6 +233,10 @@ pub enum Action {
         /// Specify the name of the key
         #[arg(long, short)]
         key: String,
+
+        /// List only the current level instead of everything under the key
+        #[arg(long, short)]
+        one_level: bool,
     },
 
     /// Send trigger for hooks
@@ -178,11 +315,220 @@ pub enum Action {
         save: bool,
     },
 
+    /// List scripts that can be executed on server
+    ListScripts,
+
     /// Push value to a queue
     Pop {
         /// Specify the name of the key
         #[arg(long, short)]
         key: String,
+
+        /// End of the queue: front or back, default is front
+        #[arg(long, short)]
+        end: Option<String>,
+
+        /// Seconds to wait for an item if the queue is empty
+        #[arg(long, short)]
+        wait: Option<u64>,
+
+        /// Seconds while the item must be acknowledged, else it is returned to the queue
+        #[arg(long)]
+        visibility: Option<u64>,
+    },
+
+    /// Read value from a queue without removing it
+    Peek {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+
+        /// End of the queue: front or back, default is front
+        #[arg(long, short)]
+        end: Option<String>,
+    },
+
+    /// Get the number of values in a queue
+    QueueLen {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+    },
+
+    /// Acknowledge a value that was popped with visibility timeout
+    Ack {
+        /// Identifier of delivery
+        #[arg(long, short)]
+        id: u64,
+    },
+
+    /// Set a field of hash, hash is created if it does not exist
+    HashSet {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+
+        /// Specify the name of the field
+        #[arg(long, short)]
+        field: String,
+
+        /// Specify the value for the field
+        #[arg(long, short)]
+        value: String,
+    },
+
+    /// Get a field of hash
+    HashGet {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+
+        /// Specify the name of the field
+        #[arg(long, short)]
+        field: String,
+    },
+
+    /// Remove a field of hash
+    HashDel {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+
+        /// Specify the name of the field
+        #[arg(long, short)]
+        field: String,
+    },
+
+    /// Get every field of hash
+    HashGetAll {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+    },
+
+    /// Add member to a set, set is created if it does not exist
+    SetAdd {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+
+        /// Specify the member of the set
+        #[arg(long, short)]
+        member: String,
+    },
+
+    /// Remove member from a set
+    SetRem {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+
+        /// Specify the member of the set
+        #[arg(long, short)]
+        member: String,
+    },
+
+    /// Get every member of a set
+    SetMembers {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+    },
+
+    /// Check that value is member of a set
+    SetIsMember {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+
+        /// Specify the member of the set
+        #[arg(long, short)]
+        member: String,
+    },
+
+    /// Export a table with everything under it and its hooks
+    Export {
+        /// Specify the table, e.g. /root for the whole database
+        #[arg(long, short)]
+        key: String,
+
+        /// Format of snapshot: json, toml or binary, default is json
+        #[arg(long, short)]
+        format: Option<String>,
+
+        /// Write snapshot into this file instead of the output
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+
+    /// Import a snapshot from file
+    Import {
+        /// Specify the file that contains the snapshot
+        #[arg(long, short = 'i')]
+        file: String,
+
+        /// Format of snapshot: json, toml or binary, default is json
+        #[arg(long, short)]
+        format: Option<String>,
+
+        /// What happens with existing keys: merge, overwrite or skip, default is merge
+        #[arg(long, short)]
+        policy: Option<String>,
+    },
+
+    /// List the past changes of a key from the append file of server
+    History {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+    },
+
+    /// Show the type, size, times and counts of a key or a table
+    Stat {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+    },
+
+    /// Move a record, queue, hash, set or complete table to another key
+    Move {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+
+        /// Specify the new name of the key
+        #[arg(long, short)]
+        destination: String,
+
+        /// Type of the key: record, queue, hash, set or table, default is record
+        #[arg(long, short)]
+        r#type: Option<String>,
+    },
+
+    /// Copy a record, queue, hash, set or complete table to another key
+    Copy {
+        /// Specify the name of the key
+        #[arg(long, short)]
+        key: String,
+
+        /// Specify the name of the copy
+        #[arg(long, short)]
+        destination: String,
+
+        /// Type of the key: record, queue, hash, set or table, default is record
+        #[arg(long, short)]
+        r#type: Option<String>,
+    },
+
+    /// Find the records under a prefix with a value, prefix must have value index on server
+    Find {
+        /// Specify the prefix where records are searched
+        #[arg(long, short)]
+        key: String,
+
+        /// Specify the value of records
+        #[arg(long, short)]
+        value: String,
     },
 
     /// Pop value from a queue
@@ -1 +1,26 @@
-pub mod websocket;
\ No newline at end of file
+pub mod websocket;
+
+use std::time::Duration;
+
+/// Parse RFC 3339 time into duration since epoch, like the time of append file entries
+pub fn parse_time(text: &str) -> Result<Duration, String> {
+    match chrono::DateTime::parse_from_rfc3339(text) {
+        Ok(time) if time.timestamp_nanos() >= 0 => {
+            Ok(Duration::from_nanos(time.timestamp_nanos() as u64))
+        }
+        Ok(_) => Err(format!("Invalid time '{}': it is before 1970", text)),
+        Err(e) => Err(format!("Invalid time '{}': {}", text, e)),
+    }
+}
+
+/// Format duration since epoch in RFC 3339
+pub fn format_time(nanos: u128) -> String {
+    let time = chrono::NaiveDateTime::from_timestamp_opt(
+        (nanos / 1_000_000_000) as i64,
+        (nanos % 1_000_000_000) as u32,
+    );
+    match time {
+        Some(time) => chrono::DateTime::<chrono::Utc>::from_utc(time, chrono::Utc).to_rfc3339(),
+        None => nanos.to_string(),
+    }
+}
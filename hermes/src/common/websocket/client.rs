@@ -1,3 +1,4 @@
+use base64::Engine;
 use futures_util::SinkExt;
 use futures_util::StreamExt;
 use tokio::net::TcpStream;
@@ -33,15 +34,36 @@ pub async fn perform_action(
 
     match &action {
         // GET action
-        Action::Get { key } => {
+        Action::Get { key, path } => {
             request.command = CommandMethod::GetKey;
             request.key = Some(key.clone());
+            request.path = path.clone();
         }
         // SET action
-        Action::Set { key, value } => {
+        Action::Set { key, value, r#type } => {
             request.command = CommandMethod::SetKey;
             request.key = Some(key.clone());
             request.value = Some(value.clone());
+            request.r#type = r#type.clone();
+        }
+        // PATCH action
+        Action::Patch { key, path, value } => {
+            request.command = CommandMethod::PatchKey;
+            request.key = Some(key.clone());
+            request.path = Some(path.clone());
+            request.value = Some(value.clone());
+        }
+        // INCR action
+        Action::Incr { key, by } => {
+            request.command = CommandMethod::Incr;
+            request.key = Some(key.clone());
+            request.value = by.map(|x| x.to_string());
+        }
+        // DECR action
+        Action::Decr { key, by } => {
+            request.command = CommandMethod::Decr;
+            request.key = Some(key.clone());
+            request.value = by.map(|x| x.to_string());
         }
         // REMKEY action
         Action::RemKey { key } => {
@@ -54,9 +76,10 @@ pub async fn perform_action(
             request.key = Some(key.clone());
         }
         // LIST action
-        Action::ListKeys { key } => {
+        Action::ListKeys { key, one_level } => {
             request.command = CommandMethod::ListKeys;
             request.key = Some(key.clone());
+            request.one_level = Some(*one_level);
         }
         // TRIGGER action
         Action::Trigger { key, value } => {
@@ -109,10 +132,147 @@ pub async fn perform_action(
             request.parm = parms.clone();
             request.save = Some(*save);
         }
+        // LISTSCRIPTS action
+        Action::ListScripts => {
+            request.command = CommandMethod::ListScripts;
+        }
         // POP action
-        Action::Pop { key } => {
+        Action::Pop {
+            key,
+            end,
+            wait,
+            visibility,
+        } => {
             request.command = CommandMethod::Pop;
             request.key = Some(key.clone());
+            request.end = end.clone();
+            request.wait = *wait;
+            request.visibility = *visibility;
+        }
+        // PEEK action
+        Action::Peek { key, end } => {
+            request.command = CommandMethod::Peek;
+            request.key = Some(key.clone());
+            request.end = end.clone();
+        }
+        // QUEUELEN action
+        Action::QueueLen { key } => {
+            request.command = CommandMethod::QueueLength;
+            request.key = Some(key.clone());
+        }
+        // ACK action
+        Action::Ack { id } => {
+            request.command = CommandMethod::Ack;
+            request.id = Some(*id);
+        }
+        // HASHSET action
+        Action::HashSet { key, field, value } => {
+            request.command = CommandMethod::HashSet;
+            request.key = Some(key.clone());
+            request.field = Some(field.clone());
+            request.value = Some(value.clone());
+        }
+        // HASHGET action
+        Action::HashGet { key, field } => {
+            request.command = CommandMethod::HashGet;
+            request.key = Some(key.clone());
+            request.field = Some(field.clone());
+        }
+        // HASHDEL action
+        Action::HashDel { key, field } => {
+            request.command = CommandMethod::HashDelete;
+            request.key = Some(key.clone());
+            request.field = Some(field.clone());
+        }
+        // HASHGETALL action
+        Action::HashGetAll { key } => {
+            request.command = CommandMethod::HashGetAll;
+            request.key = Some(key.clone());
+        }
+        // SETADD action
+        Action::SetAdd { key, member } => {
+            request.command = CommandMethod::SetAdd;
+            request.key = Some(key.clone());
+            request.value = Some(member.clone());
+        }
+        // SETREM action
+        Action::SetRem { key, member } => {
+            request.command = CommandMethod::SetRemove;
+            request.key = Some(key.clone());
+            request.value = Some(member.clone());
+        }
+        // SETMEMBERS action
+        Action::SetMembers { key } => {
+            request.command = CommandMethod::SetMembers;
+            request.key = Some(key.clone());
+        }
+        // SETISMEMBER action
+        Action::SetIsMember { key, member } => {
+            request.command = CommandMethod::SetIsMember;
+            request.key = Some(key.clone());
+            request.value = Some(member.clone());
+        }
+        // EXPORT action
+        Action::Export { key, format, .. } => {
+            request.command = CommandMethod::Export;
+            request.key = Some(key.clone());
+            request.format = format.clone();
+        }
+        // IMPORT action
+        Action::Import {
+            file,
+            format,
+            policy,
+        } => {
+            let data = match std::fs::read(file) {
+                Ok(data) => data,
+                Err(e) => return Err(format!("File '{}' could not been read: {}", file, e)),
+            };
+
+            request.command = CommandMethod::Import;
+            request.value = Some(match is_binary(format) {
+                true => base64::engine::general_purpose::STANDARD.encode(data),
+                false => String::from_utf8_lossy(&data).to_string(),
+            });
+            request.format = format.clone();
+            request.policy = policy.clone();
+        }
+        // HISTORY action
+        Action::History { key } => {
+            request.command = CommandMethod::History;
+            request.key = Some(key.clone());
+        }
+        // STAT action
+        Action::Stat { key } => {
+            request.command = CommandMethod::Stat;
+            request.key = Some(key.clone());
+        }
+        // MOVE and COPY actions
+        Action::Move {
+            key,
+            destination,
+            r#type,
+        } => {
+            request.command = CommandMethod::Move;
+            request.key = Some(key.clone());
+            request.value = Some(destination.clone());
+            request.r#type = r#type.clone();
+        }
+        Action::Copy {
+            key,
+            destination,
+            r#type,
+        } => {
+            request.command = CommandMethod::Copy;
+            request.key = Some(key.clone());
+            request.value = Some(destination.clone());
+            request.r#type = r#type.clone();
+        }
+        // FIND action
+        Action::Find { key, value } => {
+            request.command = CommandMethod::Find;
+            request.key = Some(key.clone());
+            request.value = Some(value.clone());
         }
         // PUSH action
         Action::Push { key, value } => {
@@ -153,15 +313,54 @@ pub async fn perform_action(
                 };
 
             if response.status == crate::common::websocket::WsResponseStatus::Ok {
-                Ok(response.message)
+                if let Action::Export {
+                    format,
+                    output: Some(output),
+                    ..
+                } = &action
+                {
+                    return write_snapshot(output, format, response.message);
+                }
+
+                match response.delivery {
+                    Some(id) => Ok(format!("{}\n{}", id, response.message)),
+                    None => Ok(response.message),
+                }
             } else {
-                Err(response.message)
+                match response.code {
+                    Some(code) => Err(format!("{}\n{}", code, response.message)),
+                    None => Err(response.message),
+                }
             }
         }
         resp => Err(format!("server responded with {}", resp)),
     }
 }
 
+/// Tells that snapshot format is binary, it is transferred as base64 text
+fn is_binary(format: &Option<String>) -> bool {
+    format
+        .as_deref()
+        .map(|x| x.eq_ignore_ascii_case("binary"))
+        .unwrap_or(false)
+}
+
+/// Write exported snapshot into file, binary snapshot is decoded from base64
+fn write_snapshot(path: &String, format: &Option<String>, data: String) -> Result<String, String> {
+    let data = match is_binary(format) {
+        true => match base64::engine::general_purpose::STANDARD.decode(data) {
+            Ok(data) => data,
+            Err(e) => return Err(format!("Failed to decode snapshot: {}", e)),
+        },
+        false => data.into_bytes(),
+    };
+
+    match std::fs::write(path, data) {
+        Ok(_) => Ok(format!("Snapshot is written into '{}'", path)),
+        Err(e) => Err(format!("File '{}' could not been written: {}", path, e)),
+    }
+}
+
 use serde::Deserialize;
 
 /// Represent one node in client config toml file
@@ -174,11 +373,40 @@ pub struct Node {
     pub address: String,
 }
 
+/// Edit mode of shell, it is set in `[shell]` section of client config
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EditMode {
+    /// Emacs key bindings, e.g. Ctrl+A and Ctrl+E
+    #[default]
+    Emacs,
+
+    /// Vi key bindings, line is edited in insert mode and Esc switches to command mode
+    Vi,
+}
+
+/// Represent the shell settings in client config toml file
+///
+/// ```toml
+/// [shell]
+/// edit_mode = "vi"    # Optional, emacs or vi, default is emacs
+/// ```
+#[derive(Deserialize, Debug, Default)]
+pub struct Shell {
+    /// Key bindings of shell
+    #[serde(default)]
+    pub edit_mode: EditMode,
+}
+
 /// Represent the whole client config toml file
 #[derive(Deserialize)]
 pub struct Config {
     /// Vector about defined nodes
     pub node: Vec<Node>,
+
+    /// Settings of shell
+    #[serde(default)]
+    pub shell: Shell,
 }
 
 /// Read config from client's config file
@@ -1,3 +1,4 @@
+use onlyati_datastore::datastore::enums::error::{ErrorCode, ErrorKind};
 use serde::{Deserialize, Serialize};
 
 pub mod client;
@@ -8,12 +9,29 @@ pub struct WsRequest {
     /// Command that tells what has to be done
     pub command: CommandMethod,
 
-    /// Key for GET, SET, REM-KEY, REM-PATH, LIST-KEYS commands
+    /// Key for GET, SET, REM-KEY, REM-PATH, LIST-KEYS, EXPORT, HISTORY commands, prefix for FIND command
     pub key: Option<String>,
+    /// List only the current level at LIST-KEYS command
+    #[serde(default)]
+    pub one_level: Option<bool>,
 
-    /// Value belongs to key
+    /// Value belongs to key, member for SET-ADD, SET-REM and SET-IS-MEMBER commands, snapshot for IMPORT command,
+    /// destination for MOVE and COPY commands, searched value for FIND command
     pub value: Option<String>,
 
+    /// Field of hash for HASH-SET, HASH-GET and HASH-DEL commands
+    #[serde(default)]
+    pub field: Option<String>,
+
+    /// Type of value for SET command: `string`, `integer`, `float`, `bool`, `json` or `bytes`,
+    /// kind of source for MOVE and COPY commands: `record`, `queue`, `hash`, `set` or `table`
+    #[serde(default, rename = "type")]
+    pub r#type: Option<String>,
+
+    /// Path within JSON record for GET and PATCH commands, e.g. `$.status.cpu`
+    #[serde(default)]
+    pub path: Option<String>,
+
     /// Prefix for GET-HOOK, SET-HOOK, REM-HOOK, LIST-HOOKS commands
     pub prefix: Option<String>,
     /// Link belongs to prefix
@@ -25,6 +43,26 @@ pub struct WsRequest {
     pub parm: Option<String>,
     /// Save the result of procedure or just a trigger
     pub save: Option<bool>,
+
+    /// End of queue for POP and PEEK commands: `front` or `back`
+    #[serde(default)]
+    pub end: Option<String>,
+    /// Seconds to wait for an item if queue is empty at POP command
+    #[serde(default)]
+    pub wait: Option<u64>,
+    /// Seconds while the popped item must be acknowledged at POP command
+    #[serde(default)]
+    pub visibility: Option<u64>,
+    /// Identifier of delivery for ACK command
+    #[serde(default)]
+    pub id: Option<u64>,
+
+    /// Format of snapshot for EXPORT and IMPORT commands: `json`, `toml` or `binary`, binary is base64 encoded
+    #[serde(default)]
+    pub format: Option<String>,
+    /// What happens with existing keys at IMPORT command: `merge`, `overwrite` or `skip`
+    #[serde(default)]
+    pub policy: Option<String>,
 }
 
 impl Default for WsRequest {
@@ -32,12 +70,22 @@ impl Default for WsRequest {
         Self {
             command: CommandMethod::GetKey,
             key: None,
+            one_level: None,
             value: None,
+            field: None,
+            r#type: None,
+            path: None,
             prefix: None,
             link: None,
             exec: None,
             parm: None,
             save: None,
+            end: None,
+            wait: None,
+            visibility: None,
+            id: None,
+            format: None,
+            policy: None,
         }
     }
 }
@@ -73,6 +121,28 @@ pub enum CommandMethod {
     Exec,
     Push,
     Pop,
+    PatchKey,
+    Incr,
+    Decr,
+    Peek,
+    QueueLength,
+    Ack,
+    HashSet,
+    HashGet,
+    HashDelete,
+    HashGetAll,
+    SetAdd,
+    SetRemove,
+    SetMembers,
+    SetIsMember,
+    Export,
+    Import,
+    History,
+    Stat,
+    Move,
+    Copy,
+    Find,
+    ListScripts,
 }
 
 /// Struct to send response back for websocket calls
@@ -84,6 +154,18 @@ pub struct WsResponse {
     /// If it is successful then return with the output.
     /// If it is failed then error message
     pub message: String,
+
+    /// Machine-readable code of error, it is specified only if it is failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+
+    /// Type of the returned value, it is specified only for GET command
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<String>,
+
+    /// Identifier of delivery, it is specified only if item is popped with visibility timeout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delivery: Option<u64>,
 }
 
 impl WsResponse {
@@ -92,16 +174,49 @@ impl WsResponse {
         WsResponse {
             status: WsResponseStatus::Ok,
             message: message.to_string(),
+            code: None,
+            r#type: None,
+            delivery: None,
+        }
+    }
+
+    /// Create a new successful response of a typed value
+    pub fn new_typed<T: std::fmt::Display>(message: T, r#type: &str) -> Self {
+        WsResponse {
+            status: WsResponseStatus::Ok,
+            message: message.to_string(),
+            code: None,
+            r#type: Some(r#type.to_string()),
+            delivery: None,
+        }
+    }
+
+    /// Create a new successful response of an item that must be acknowledged
+    pub fn new_delivery<T: std::fmt::Display>(message: T, id: u64) -> Self {
+        WsResponse {
+            status: WsResponseStatus::Ok,
+            message: message.to_string(),
+            code: None,
+            r#type: None,
+            delivery: Some(id),
         }
     }
 
     /// Create a new failed response
-    pub fn new_err<T: std::fmt::Display>(message: T) -> Self {
+    pub fn new_err<T: std::fmt::Display>(code: ErrorCode, message: T) -> Self {
         WsResponse {
             status: WsResponseStatus::Err,
             message: message.to_string(),
+            code: Some(code),
+            r#type: None,
+            delivery: None,
         }
     }
+
+    /// Create a new failed response from an error of database
+    pub fn from_error(error: ErrorKind) -> Self {
+        Self::new_err(error.code(), error)
+    }
 }
 
 /// Enum to indicate the status of websocket request
@@ -3,6 +3,7 @@ use std::process::exit;
 use arg::Mode;
 use clap::Parser;
 
+mod af;
 mod arg;
 mod cli;
 mod server;
@@ -23,7 +24,10 @@ fn main() {
                 Ok(rc) => exit(rc),
                 Err(_) => exit(-999),
             },
-            Mode::Server { config } => match server::main_async(config).await {
+            Mode::Server {
+                config,
+                restore_until,
+            } => match server::main_async(config, restore_until).await {
                 Ok(rc) => exit(rc),
                 Err(_) => exit(-999),
             },
@@ -31,6 +35,10 @@ fn main() {
                 Ok(rc) => exit(rc),
                 Err(_) => exit(-999),
             },
+            Mode::Af(args) => match af::main_async(args).await {
+                Ok(rc) => exit(rc),
+                Err(_) => exit(-999),
+            },
         }
     });
 }
@@ -1,11 +1,15 @@
 macro_rules! send_data_request {
-    ($payload:expr, $data_mutex:expr) => {{
-        let sender = $data_mutex.lock().await;
-        if let Err(e) = sender.send($payload).await {
+    ($payload:expr, $data_sender:expr) => {{
+        if let Err(e) = $data_sender
+            .send(crate::server::interfaces::context::with_context($payload))
+            .await
+        {
             for line in e.to_string().lines() {
                 tracing::error!("{}", line);
             }
-            return ">Err\n".as_bytes().to_vec();
+            return format!(">Err\n{}\n", ErrorCode::Internal)
+                .as_bytes()
+                .to_vec();
         }
     }};
 }
@@ -13,21 +17,32 @@ pub(in crate::server::interfaces::classic) use send_data_request;
 
 macro_rules! return_server_error {
     ($error:expr) => {{
+        return_server_error!(ErrorCode::Internal, $error)
+    }};
+    ($code:expr, $error:expr) => {{
         for line in $error.to_string().lines() {
             tracing::error!("{}", line);
         }
-        return ">Err".as_bytes().to_vec();
+        return format!(">Err\n{}\n", $code).as_bytes().to_vec();
     }};
 }
 pub(in crate::server::interfaces::classic) use return_server_error;
 
 macro_rules! return_client_error {
-    ($error:expr) => {{
-        return format!(">Err\n{}\n", $error).as_bytes().to_vec();
+    ($code:expr, $error:expr) => {{
+        return format!(">Err\n{}\n{}\n", $code, $error).as_bytes().to_vec();
     }};
 }
 pub(in crate::server::interfaces::classic) use return_client_error;
 
+macro_rules! return_database_error {
+    ($error:expr) => {{
+        let error: ErrorKind = $error;
+        return_client_error!(error.code(), error)
+    }};
+}
+pub(in crate::server::interfaces::classic) use return_database_error;
+
 macro_rules! return_ok {
     () => {{
         return ">Ok\n".as_bytes().to_vec();
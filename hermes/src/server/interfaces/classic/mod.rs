@@ -1,13 +1,13 @@
 // External dependencies
 use std::sync::Arc;
-use tokio::sync::{mpsc::Sender, Mutex, RwLock};
+use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 
 use crate::server::utilities::config_parse::Config;
 
 // Internal dependecies
 use super::ApplicationInterface;
-use onlyati_datastore::datastore::enums::DatabaseAction;
+use onlyati_datastore::datastore::router::DatastoreRouter;
 
 mod macros;
 mod utilities;
@@ -30,8 +30,8 @@ mod utilities;
 /// - PUSH `key` `value`
 /// - POP `key`
 pub struct Classic {
-    /// Sender to send data to database thread
-    data_sender: Arc<Mutex<Sender<DatabaseAction>>>,
+    /// Router to send data to the database threads
+    data_sender: DatastoreRouter,
 
     /// Host address where the interface bind and listen
     address: String,
@@ -47,14 +47,10 @@ impl Classic {
     /// Create new interface
     ///
     /// # Parmeters
-    /// - `data_sender`: Sender to send data to database thread
+    /// - `data_sender`: Router to send data to the database threads
     /// - `address`: Host address where the interface bind and listen
     /// - `config`: Application's config file
-    pub fn new(
-        data_sender: Arc<Mutex<Sender<DatabaseAction>>>,
-        address: String,
-        config: Arc<RwLock<Config>>,
-    ) -> Self {
+    pub fn new(data_sender: DatastoreRouter, address: String, config: Arc<RwLock<Config>>) -> Self {
         Self {
             data_sender,
             address,
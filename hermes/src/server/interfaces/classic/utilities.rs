@@ -3,16 +3,23 @@ use bytes::BytesMut;
 use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpListener;
-use tokio::sync::{mpsc::channel, mpsc::Sender, Mutex, RwLock};
+use tokio::sync::{mpsc::channel, RwLock};
 
 // Internal dependencies
-use onlyati_datastore::datastore::enums::{pair::ValueType, DatabaseAction};
+use onlyati_datastore::datastore::enums::{
+    context::RequestContext, error::ErrorCode, error::ErrorKind, pair::KeyType, pair::RecordValue,
+    pair::ValueType, queue_timeout, DatabaseAction, QueueEnd,
+};
+use onlyati_datastore::datastore::router::DatastoreRouter;
 
+use crate::common::format_time;
+use crate::server::interfaces::context;
 use crate::server::utilities::config_parse::Config;
 
 // Import macros
 use super::macros::{
-    return_client_error, return_ok, return_ok_with_value, return_server_error, send_data_request,
+    return_client_error, return_database_error, return_ok, return_ok_with_value,
+    return_server_error, send_data_request,
 };
 
 /// Read parameters from request then execute them
@@ -23,7 +30,7 @@ use super::macros::{
 /// - `config`: Application's configuration
 pub async fn parse_request(
     request: Vec<u8>,
-    data_sender: Arc<Mutex<Sender<DatabaseAction>>>,
+    data_sender: DatastoreRouter,
     config: Arc<RwLock<Config>>,
 ) -> Result<Vec<u8>, String> {
     // List all valid actions it will be matched later
@@ -43,6 +50,32 @@ pub async fn parse_request(
         "EXEC",
         "PUSH",
         "POP",
+        "SETTYPED",
+        "GETTYPED",
+        "GETPATH",
+        "PATCH",
+        "INCR",
+        "DECR",
+        "POPBACK",
+        "BPOP",
+        "BPOPBACK",
+        "POPRELIABLE",
+        "ACK",
+        "PEEK",
+        "PEEKBACK",
+        "QLEN",
+        "HSET",
+        "HGET",
+        "HDEL",
+        "HGETALL",
+        "SADD",
+        "SREM",
+        "SMEMBERS",
+        "SISMEMBER",
+        "STAT",
+        "MOVE",
+        "COPY",
+        "FIND",
     ];
     let request = match String::from_utf8(request) {
         Ok(req) => req,
@@ -61,7 +94,10 @@ pub async fn parse_request(
                 if !valid_commands.contains(&command.as_str()) {
                     // If not valid command then don't check further
                     tracing::debug!("invalid command specified: {}", command);
-                    return Err(">Err\nInvalid command\n".to_string());
+                    return Err(format!(
+                        ">Err\n{}\nInvalid command\n",
+                        ErrorCode::InvalidKey
+                    ));
                 }
                 copy += 1;
                 continue;
@@ -102,13 +138,13 @@ async fn handle_command(
     command: String,
     key: String,
     value: String,
-    data_sender: Arc<Mutex<Sender<DatabaseAction>>>,
+    data_sender: DatastoreRouter,
     config: Arc<RwLock<Config>>,
 ) -> Vec<u8> {
     // Key is required for all request
     if key.is_empty() {
         tracing::trace!("key is missing");
-        return_client_error!("Key is missing");
+        return_client_error!(ErrorCode::InvalidKey, "Key is missing");
     }
 
     match command.as_str() {
@@ -119,7 +155,7 @@ async fn handle_command(
             // SET without value is an error
             if value.is_empty() {
                 tracing::debug!("no value specified for SET action");
-                return_client_error!("Value is missing")
+                return_client_error!(ErrorCode::InvalidKey, "Value is missing")
             }
 
             // Handle SET request
@@ -130,7 +166,7 @@ async fn handle_command(
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => return_ok!(),
-                    Err(e) => return_client_error!(e),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to receive message from database"),
             }
@@ -139,7 +175,52 @@ async fn handle_command(
         // Get value of specific key
         //
         "GET" => {
-            // Handle GET request
+            // Handle GET request, reads are served by the handle without the datastore task
+            match data_sender.get(key, context::current()).await {
+                Ok(value) => match value {
+                    ValueType::RecordPointer(data) => return_ok_with_value!(data),
+                    _ => return_client_error!(
+                        ErrorCode::TypeMismatch,
+                        "Pointer must be Record but it was Table"
+                    ),
+                },
+                Err(e) => return_database_error!(e),
+            }
+        }
+        //
+        // Create or update record with typed value
+        //
+        "SETTYPED" => {
+            // First word of value is the type, the rest is the value itself
+            let (r#type, value) = match value.split_once(' ') {
+                Some(pair) => pair,
+                None => {
+                    tracing::debug!("no type or value specified for SETTYPED action");
+                    return_client_error!(ErrorCode::InvalidKey, "Type or value is missing")
+                }
+            };
+
+            let value = match RecordValue::parse(r#type, value) {
+                Ok(value) => value,
+                Err(e) => return_database_error!(e),
+            };
+
+            let (tx, mut rx) = channel(10);
+            let set_action = DatabaseAction::SetTyped(tx, key, value);
+            send_data_request!(set_action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(_) => return_ok!(),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Get type and value of specific key
+        //
+        "GETTYPED" => {
             let (tx, mut rx) = channel(10);
             let get_action = DatabaseAction::Get(tx, key);
             send_data_request!(get_action, data_sender);
@@ -147,10 +228,110 @@ async fn handle_command(
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(value) => match value {
-                        ValueType::RecordPointer(data) => return_ok_with_value!(data),
-                        _ => return_server_error!("Pointer must be Record but it was Table"),
+                        ValueType::RecordPointer(data) => {
+                            return_ok_with_value!(format!("{}\n{}", data.get_type(), data))
+                        }
+                        _ => return_client_error!(
+                            ErrorCode::TypeMismatch,
+                            "Pointer must be Record but it was Table"
+                        ),
                     },
-                    Err(e) => return_client_error!(e),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Get a part of JSON record
+        //
+        "GETPATH" => {
+            if value.is_empty() {
+                tracing::debug!("no path specified for GETPATH action");
+                return_client_error!(ErrorCode::InvalidKey, "Path is missing")
+            }
+
+            let (tx, mut rx) = channel(10);
+            let get_action = DatabaseAction::Get(tx, key);
+            send_data_request!(get_action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(ValueType::RecordPointer(data)) => match data.get_path(&value) {
+                        Ok(data) => return_ok_with_value!(data),
+                        Err(e) => return_database_error!(e),
+                    },
+                    Ok(_) => return_client_error!(
+                        ErrorCode::TypeMismatch,
+                        "Pointer must be Record but it was Table"
+                    ),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Replace a part of JSON record
+        //
+        "PATCH" => {
+            // First word of value is the path, the rest is the new JSON value
+            let (path, value) = match value.split_once(' ') {
+                Some(pair) => pair,
+                None => {
+                    tracing::debug!("no path or value specified for PATCH action");
+                    return_client_error!(ErrorCode::InvalidKey, "Path or value is missing")
+                }
+            };
+
+            let value = match serde_json::from_str(value) {
+                Ok(value) => value,
+                Err(e) => {
+                    return_client_error!(
+                        ErrorCode::InvalidKey,
+                        format!("Value must be JSON: {}", e)
+                    )
+                }
+            };
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Patch(tx, key, path.to_string(), value);
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(_) => return_ok!(),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Increment or decrement a counter, the amount is optional
+        //
+        "INCR" | "DECR" => {
+            let by = match value.trim() {
+                "" => 1,
+                amount => match amount.parse::<i64>() {
+                    Ok(by) => by,
+                    Err(_) => return_client_error!(ErrorCode::InvalidKey, "Amount must be integer"),
+                },
+            };
+            let by = match command.as_str() {
+                "DECR" => match by.checked_neg() {
+                    Some(by) => by,
+                    None => return_client_error!(ErrorCode::InvalidKey, "Amount is out of range"),
+                },
+                _ => by,
+            };
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Increment(tx, key, by);
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(ValueType::RecordPointer(data)) => return_ok_with_value!(data),
+                    Ok(_) => return_server_error!("counter is not a record"),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to receive message from database"),
             }
@@ -160,27 +341,52 @@ async fn handle_command(
         //
         "LIST" => {
             // Handle LIST request
+            let response = data_sender
+                .list_keys(
+                    key,
+                    onlyati_datastore::datastore::enums::ListType::All,
+                    context::current(),
+                )
+                .await;
+
+            match response {
+                Ok(list) => {
+                    let mut data = String::new();
+                    for key in list {
+                        data += key.get_type();
+                        data += " ";
+                        data += key.get_key();
+                        data += "\n";
+                    }
+                    return_ok_with_value!(data);
+                }
+                Err(e) => return_database_error!(e),
+            }
+        }
+        //
+        // Find records under a prefix by value index, one key per line
+        //
+        "FIND" => {
+            if value.is_empty() {
+                tracing::debug!("no value specified for FIND action");
+                return_client_error!(ErrorCode::InvalidKey, "Value is missing")
+            }
+
             let (tx, mut rx) = channel(10);
-            let list_action = DatabaseAction::ListKeys(
-                tx,
-                key,
-                onlyati_datastore::datastore::enums::ListType::All,
-            );
-            send_data_request!(list_action, data_sender);
+            let action = DatabaseAction::Find(tx, key, value);
+            send_data_request!(action, data_sender);
 
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(list) => {
                         let mut data = String::new();
                         for key in list {
-                            data += key.get_type();
-                            data += " ";
                             data += key.get_key();
                             data += "\n";
                         }
                         return_ok_with_value!(data);
                     }
-                    Err(e) => return_client_error!(e),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to receive message from database"),
             }
@@ -192,7 +398,7 @@ async fn handle_command(
             // TRIGGER without value is an error
             if value.is_empty() {
                 tracing::debug!("no value specified for SET action");
-                return_client_error!("Value is missing")
+                return_client_error!(ErrorCode::InvalidKey, "Value is missing")
             }
 
             // Handle TRIGGER request
@@ -203,7 +409,7 @@ async fn handle_command(
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => return_ok!(),
-                    Err(e) => return_client_error!(e),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to receive message from database"),
             }
@@ -220,7 +426,7 @@ async fn handle_command(
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => return_ok!(),
-                    Err(e) => return_client_error!(e),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to receive message from database"),
             }
@@ -237,7 +443,7 @@ async fn handle_command(
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => return_ok!(),
-                    Err(e) => return_client_error!(e),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to receive message from database"),
             }
@@ -249,7 +455,7 @@ async fn handle_command(
             // Add new hook
             if value.is_empty() {
                 tracing::debug!("no link specified for SETHOOK action");
-                return_client_error!("Link is missing")
+                return_client_error!(ErrorCode::InvalidKey, "Link is missing")
             }
             let prefix = key;
             let link = value;
@@ -261,7 +467,7 @@ async fn handle_command(
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => return_ok!(),
-                    Err(e) => return_client_error!(e),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to receive message from database"),
             }
@@ -286,7 +492,7 @@ async fn handle_command(
                         }
                         return_ok_with_value!(response);
                     }
-                    Err(e) => return_client_error!(e),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to receive message from database"),
             }
@@ -298,7 +504,7 @@ async fn handle_command(
             // Delete an existing hook
             if value.is_empty() {
                 tracing::debug!("no link specified for SETHOOK action");
-                return_client_error!("Link is missing")
+                return_client_error!(ErrorCode::InvalidKey, "Link is missing")
             }
             let prefix = key;
             let link = value;
@@ -310,7 +516,7 @@ async fn handle_command(
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => return_ok!(),
-                    Err(e) => return_client_error!(e),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to receive message from database"),
             }
@@ -334,7 +540,7 @@ async fn handle_command(
                         }
                         return_ok_with_value!(response);
                     }
-                    Err(e) => return_client_error!(e),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to receive message from database"),
             }
@@ -345,7 +551,10 @@ async fn handle_command(
         "SUSPEND" => {
             // Resume log action
             if key != "LOG" {
-                return_client_error!("Invalid command, you may wanted to write: SUSPEND LOG");
+                return_client_error!(
+                    ErrorCode::InvalidKey,
+                    "Invalid command, you may wanted to write: SUSPEND LOG"
+                );
             }
 
             let (tx, mut rx) = channel(10);
@@ -355,7 +564,7 @@ async fn handle_command(
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => return_ok!(),
-                    Err(e) => return_client_error!(e),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to receive message from database"),
             }
@@ -366,7 +575,10 @@ async fn handle_command(
         "RESUME" => {
             // Suspend log action
             if key != "LOG" {
-                return_client_error!("Invalid command, you may wanted to write: SUSPEND LOG");
+                return_client_error!(
+                    ErrorCode::InvalidKey,
+                    "Invalid command, you may wanted to write: SUSPEND LOG"
+                );
             }
 
             let (tx, mut rx) = channel(10);
@@ -376,7 +588,7 @@ async fn handle_command(
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => return_ok!(),
-                    Err(e) => return_client_error!(e),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to receive message from database"),
             }
@@ -389,7 +601,10 @@ async fn handle_command(
             // EXEC_SET <key> <script> <set-or-trigger> <value>
             if value.is_empty() {
                 tracing::debug!("only action is specified but rest should be needed");
-                return_client_error!("Script name, type and value are missing")
+                return_client_error!(
+                    ErrorCode::InvalidKey,
+                    "Script name, type and value are missing"
+                )
             }
 
             let mut c1st_space: usize = 0;
@@ -410,11 +625,11 @@ async fn handle_command(
             }
 
             if c2nd_space == 0 {
-                return_client_error!("Invalid command, type is missing");
+                return_client_error!(ErrorCode::InvalidKey, "Invalid command, type is missing");
             }
 
             if c1st_space == 0 {
-                return_client_error!("Invalid command, script is missing");
+                return_client_error!(ErrorCode::InvalidKey, "Invalid command, script is missing");
             }
 
             tracing::debug!("breakpoints for split value: {} {}", c1st_space, c2nd_space);
@@ -440,8 +655,11 @@ async fn handle_command(
             let old_pair = match rx.recv().await {
                 Some(response) => match response {
                     Ok(value) => match value {
-                        ValueType::RecordPointer(data) => Some((key.clone(), data)),
-                        _ => return_server_error!("Pointer must be Record but it was Table"),
+                        ValueType::RecordPointer(data) => Some((key.clone(), data.to_string())),
+                        _ => return_client_error!(
+                            ErrorCode::TypeMismatch,
+                            "Pointer must be Record but it was Table"
+                        ),
                     },
                     Err(_) => None,
                 },
@@ -454,9 +672,15 @@ async fn handle_command(
                 match &config.scripts {
                     Some(scr) => match scr.execs.contains(script) {
                         true => scr.clone(),
-                        false => return_client_error!("requested script is not defined"),
+                        false => return_client_error!(
+                            ErrorCode::InvalidKey,
+                            "requested script is not defined"
+                        ),
                     },
-                    None => return_client_error!("requested script is not defined"),
+                    None => return_client_error!(
+                        ErrorCode::InvalidKey,
+                        "requested script is not defined"
+                    ),
                 }
             };
 
@@ -473,7 +697,10 @@ async fn handle_command(
             .await
             {
                 Ok(modified_pair) => modified_pair,
-                Err(e) => return_server_error!(format!("error during script exection: {}", e)),
+                Err(e) => return_server_error!(
+                    ErrorCode::ScriptError,
+                    format!("error during script exection: {}", e)
+                ),
             };
 
             // Make a SET action for the modified pair
@@ -487,7 +714,7 @@ async fn handle_command(
                     match rx.recv().await {
                         Some(response) => match response {
                             Ok(_) => return_ok!(),
-                            Err(e) => return_client_error!(e.to_string()),
+                            Err(e) => return_database_error!(e),
                         },
                         None => return_server_error!("failed to receive message from database"),
                     }
@@ -499,7 +726,7 @@ async fn handle_command(
                     match rx.recv().await {
                         Some(response) => match response {
                             Ok(_) => return_ok!(),
-                            Err(e) => return_client_error!(e.to_string()),
+                            Err(e) => return_database_error!(e),
                         },
                         None => return_server_error!("failed to receive message from database"),
                     }
@@ -515,15 +742,18 @@ async fn handle_command(
                     match rx.recv().await {
                         Some(response) => match response {
                             Ok(_) => return_ok!(),
-                            Err(e) => return_client_error!(e.to_string()),
+                            Err(e) => return_database_error!(e),
                         },
                         None => return_server_error!("failed to receive message from database"),
                     }
                 } else {
-                    return_client_error!("After script was run, the new value is empty");
+                    return_client_error!(
+                        ErrorCode::ScriptError,
+                        "After script was run, the new value is empty"
+                    );
                 }
             } else {
-                return_client_error!("Type can be either SET or TRIGGER");
+                return_client_error!(ErrorCode::InvalidKey, "Type can be either SET or TRIGGER");
             }
         }
         //
@@ -533,7 +763,7 @@ async fn handle_command(
             // SET without value is an error
             if value.is_empty() {
                 tracing::debug!("no value specified for SET action");
-                return_client_error!("Value is missing")
+                return_client_error!(ErrorCode::InvalidKey, "Value is missing")
             }
 
             // Handle SET request
@@ -544,7 +774,7 @@ async fn handle_command(
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => return_ok!(),
-                    Err(e) => return_client_error!(e),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to receive message from database"),
             }
@@ -552,19 +782,369 @@ async fn handle_command(
         //
         // Get an item from a queue
         //
-        "POP" => {
-            // Handle GET request
+        "POP" | "POPBACK" | "BPOP" | "BPOPBACK" => {
+            let end = match command.as_str() {
+                "POPBACK" | "BPOPBACK" => QueueEnd::Back,
+                _ => QueueEnd::Front,
+            };
+
+            // Blocking variants wait for the specified seconds
             let (tx, mut rx) = channel(10);
-            let get_action = DatabaseAction::Pop(tx, key);
+            let get_action = match command.as_str() {
+                "BPOP" | "BPOPBACK" => match value.trim().parse::<u64>().map(queue_timeout) {
+                    Ok(Ok(wait)) => DatabaseAction::PopWait(tx, key, end, wait),
+                    Ok(Err(e)) => return_database_error!(e),
+                    Err(_) => {
+                        return_client_error!(ErrorCode::InvalidKey, "Timeout must be integer")
+                    }
+                },
+                _ => DatabaseAction::PopFrom(tx, key, end),
+            };
             send_data_request!(get_action, data_sender);
 
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(value) => match value {
                         ValueType::RecordPointer(data) => return_ok_with_value!(data),
-                        _ => return_server_error!("Pointer must be Record but it was Table"),
+                        _ => return_client_error!(
+                            ErrorCode::TypeMismatch,
+                            "Pointer must be Record but it was Table"
+                        ),
                     },
-                    Err(e) => return_client_error!(e),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Get an item from a queue that must be acknowledged, first line is the delivery id
+        //
+        "POPRELIABLE" => {
+            let visibility = match value.trim().parse::<u64>().map(queue_timeout) {
+                Ok(Ok(visibility)) => visibility,
+                Ok(Err(e)) => return_database_error!(e),
+                Err(_) => return_client_error!(ErrorCode::InvalidKey, "Timeout must be integer"),
+            };
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::PopReliable(tx, key, visibility);
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok((id, data)) => return_ok_with_value!(format!("{}\n{}", id, data)),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Acknowledge an item that was popped by POPRELIABLE
+        //
+        "ACK" => {
+            let id = match key.trim().parse::<u64>() {
+                Ok(id) => id,
+                Err(_) => return_client_error!(ErrorCode::InvalidKey, "Delivery must be integer"),
+            };
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Ack(tx, id);
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(_) => return_ok!(),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Read an item of queue without removing it
+        //
+        "PEEK" | "PEEKBACK" => {
+            let end = match command.as_str() {
+                "PEEKBACK" => QueueEnd::Back,
+                _ => QueueEnd::Front,
+            };
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Peek(tx, key, end);
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(value) => return_ok_with_value!(value.get_value()),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Number of items in a queue
+        //
+        "QLEN" => {
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::QueueLength(tx, key);
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(length) => return_ok_with_value!(length),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Set field of a hash
+        //
+        "HSET" => {
+            // First word of value is the field, the rest is its value
+            let (field, value) = match value.split_once(' ') {
+                Some(pair) => pair,
+                None => {
+                    tracing::debug!("no field or value specified for HSET action");
+                    return_client_error!(ErrorCode::InvalidKey, "Field or value is missing")
+                }
+            };
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::HashSet(tx, key, field.to_string(), value.to_string());
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(created) => return_ok_with_value!(created),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Get field of a hash
+        //
+        "HGET" => {
+            if value.is_empty() {
+                tracing::debug!("no field specified for HGET action");
+                return_client_error!(ErrorCode::InvalidKey, "Field is missing")
+            }
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::HashGet(tx, key, value);
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(value) => return_ok_with_value!(value.get_value()),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Remove field of a hash
+        //
+        "HDEL" => {
+            if value.is_empty() {
+                tracing::debug!("no field specified for HDEL action");
+                return_client_error!(ErrorCode::InvalidKey, "Field is missing")
+            }
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::HashDelete(tx, key, value);
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(_) => return_ok!(),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Get every field of a hash, one field and its value per line
+        //
+        "HGETALL" => {
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::HashGetAll(tx, key);
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(fields) => {
+                        let lines: Vec<String> = fields
+                            .iter()
+                            .map(|(field, value)| format!("{} {}", field, value))
+                            .collect();
+                        return_ok_with_value!(lines.join("\n"))
+                    }
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Add member to a set
+        //
+        "SADD" => {
+            if value.is_empty() {
+                tracing::debug!("no member specified for SADD action");
+                return_client_error!(ErrorCode::InvalidKey, "Member is missing")
+            }
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::SetAdd(tx, key, value);
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(added) => return_ok_with_value!(added),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Remove member from a set
+        //
+        "SREM" => {
+            if value.is_empty() {
+                tracing::debug!("no member specified for SREM action");
+                return_client_error!(ErrorCode::InvalidKey, "Member is missing")
+            }
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::SetRemove(tx, key, value);
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(removed) => return_ok_with_value!(removed),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Get every member of a set, one member per line
+        //
+        "SMEMBERS" => {
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::SetMembers(tx, key);
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(members) => {
+                        let lines: Vec<String> = members.into_iter().collect();
+                        return_ok_with_value!(lines.join("\n"))
+                    }
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Check that value is member of a set
+        //
+        "SISMEMBER" => {
+            if value.is_empty() {
+                tracing::debug!("no member specified for SISMEMBER action");
+                return_client_error!(ErrorCode::InvalidKey, "Member is missing")
+            }
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::SetIsMember(tx, key, value);
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(found) => return_ok_with_value!(found),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Metadata of key or table, one line per record, queue, hash, set and table
+        //
+        "STAT" => {
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Stat(tx, key);
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(stats) => {
+                        let lines: Vec<String> = stats
+                            .into_iter()
+                            .map(|stat| {
+                                let mut line = format!("{} size={}", stat.kind, stat.size);
+                                if let Some(length) = stat.length {
+                                    line.push_str(&format!(" length={}", length));
+                                }
+                                if let Some(table) = stat.table {
+                                    line.push_str(&format!(
+                                        " children={} records={} queues={} hashes={} sets={}",
+                                        table.children,
+                                        table.records,
+                                        table.queues,
+                                        table.hashes,
+                                        table.sets
+                                    ));
+                                }
+                                if let Some(created) = stat.created {
+                                    line.push_str(&format!(
+                                        " created={}",
+                                        format_time(created.as_nanos())
+                                    ));
+                                }
+                                if let Some(modified) = stat.modified {
+                                    line.push_str(&format!(
+                                        " modified={}",
+                                        format_time(modified.as_nanos())
+                                    ));
+                                }
+                                line
+                            })
+                            .collect();
+                        return_ok_with_value!(lines.join("\n"))
+                    }
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_server_error!("failed to receive message from database"),
+            }
+        }
+        //
+        // Move or copy a key or a table, optional second word is the type of key
+        //
+        "MOVE" | "COPY" => {
+            let (destination, kind) = match value.split_once(' ') {
+                Some((destination, kind)) => (destination.to_string(), kind.trim()),
+                None => (value.clone(), "record"),
+            };
+            if destination.is_empty() {
+                tracing::debug!("no destination specified for {} action", command);
+                return_client_error!(ErrorCode::InvalidKey, "Destination is missing")
+            }
+            let key = match KeyType::from_kind(kind, key) {
+                Ok(key) => key,
+                Err(e) => return_database_error!(e),
+            };
+
+            let (tx, mut rx) = channel(10);
+            let action = match command.as_str() {
+                "MOVE" => DatabaseAction::Move(tx, key, destination),
+                _ => DatabaseAction::Copy(tx, key, destination),
+            };
+            send_data_request!(action, data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(_) => return_ok!(),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to receive message from database"),
             }
@@ -579,11 +1159,7 @@ async fn handle_command(
 /// - `request`: Request that has been read from socket
 /// - `data_sender`: Sender that send data to database thread
 /// - `config`: Application's configuration
-pub async fn run_async(
-    data_sender: Arc<Mutex<Sender<DatabaseAction>>>,
-    address: String,
-    config: Arc<RwLock<Config>>,
-) {
+pub async fn run_async(data_sender: DatastoreRouter, address: String, config: Arc<RwLock<Config>>) {
     tracing::info!("classic interface on {} is starting...", address);
 
     // Try to bind for address
@@ -611,7 +1187,7 @@ pub async fn run_async(
                 let mut buffer = BytesMut::with_capacity(4096);
                 match socket.0.read_buf(&mut buffer).await {
                     // socket closed
-                    Ok(n) if n == 0 => {
+                    Ok(0) => {
                         tracing::trace!("has read EOF");
                         break;
                     }
@@ -630,10 +1206,17 @@ pub async fn run_async(
             tracing::trace!("has been read {} bytes", request.len());
 
             // Handle it
-            let response = match parse_request(request, data_sender, config).await {
-                Ok(vector) => String::from_utf8(vector).unwrap(),
-                Err(e) => e,
+            let context = RequestContext {
+                interface: String::from("classic"),
+                peer: Some(socket.1.to_string()),
+                claimed_user: None,
+                request_id: Some(context::next_request_id()),
             };
+            let response =
+                match context::scope(context, parse_request(request, data_sender, config)).await {
+                    Ok(vector) => String::from_utf8(vector).unwrap(),
+                    Err(e) => e,
+                };
 
             tracing::trace!("write length: {}", response.len());
 
@@ -0,0 +1,138 @@
+use axum::http::{header, HeaderMap, Request};
+use base64::Engine;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use tokio::task::futures::TaskLocalFuture;
+use tower::{Layer, Service};
+
+use onlyati_datastore::datastore::enums::{context::RequestContext, DatabaseAction};
+
+tokio::task_local! {
+    /// Identity of the client whose request is currently handled by the interface
+    pub static CONTEXT: RequestContext;
+}
+
+/// Counter for requests that did not bring their own identifier
+static REQUEST_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// Generate a new request identifier
+pub fn next_request_id() -> String {
+    format!("{:x}", REQUEST_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Context of the current request, or `None` if it is not called within a request scope
+pub fn current() -> Option<RequestContext> {
+    CONTEXT.try_with(|context| context.clone()).ok()
+}
+
+/// Wrap the action into the context of the current request, so the database logs who made the change.
+/// If it is not called within a request scope, the action is returned untouched.
+pub fn with_context(action: DatabaseAction) -> DatabaseAction {
+    match current() {
+        Some(context) => DatabaseAction::WithContext(context, Box::new(action)),
+        None => action,
+    }
+}
+
+/// Run the future in the scope of a request context
+pub fn scope<F: Future>(context: RequestContext, future: F) -> TaskLocalFuture<RequestContext, F> {
+    CONTEXT.scope(context, future)
+}
+
+/// Build context from HTTP headers. Request identifier is taken from `X-Request-Id` header
+/// and the claimed user from the username of a basic `Authorization` header, its password is not checked.
+///
+/// # Parameters
+/// - `interface`: Name of the interface
+/// - `peer`: Address of the client
+/// - `headers`: Headers of the request
+pub fn from_headers(
+    interface: &str,
+    peer: Option<SocketAddr>,
+    headers: &HeaderMap,
+) -> RequestContext {
+    let request_id = headers
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+        .unwrap_or_else(next_request_id);
+
+    let claimed_user = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|value| base64::engine::general_purpose::STANDARD.decode(value).ok())
+        .and_then(|value| String::from_utf8(value).ok())
+        .and_then(|value| value.split(':').next().map(|user| user.to_string()));
+
+    RequestContext {
+        interface: interface.to_string(),
+        peer: peer.map(|addr| addr.to_string()),
+        claimed_user,
+        request_id: Some(request_id),
+    }
+}
+
+/// Layer that runs every HTTP request in the scope of its request context.
+/// It works with axum (`ConnectInfo`) and tonic (`TcpConnectInfo`) servers.
+#[derive(Clone)]
+pub struct ContextLayer {
+    /// Name of the interface
+    interface: &'static str,
+}
+
+impl ContextLayer {
+    /// Create new layer for the interface
+    pub fn new(interface: &'static str) -> Self {
+        Self { interface }
+    }
+}
+
+impl<S> Layer<S> for ContextLayer {
+    type Service = ContextService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ContextService {
+            inner,
+            interface: self.interface,
+        }
+    }
+}
+
+/// Service that is created by `ContextLayer`
+#[derive(Clone)]
+pub struct ContextService<S> {
+    /// Wrapped service
+    inner: S,
+
+    /// Name of the interface
+    interface: &'static str,
+}
+
+impl<S, B> Service<Request<B>> for ContextService<S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = TaskLocalFuture<RequestContext, S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let extensions = request.extensions();
+        let peer = match extensions.get::<axum::extract::ConnectInfo<SocketAddr>>() {
+            Some(info) => Some(info.0),
+            None => extensions
+                .get::<tonic::transport::server::TcpConnectInfo>()
+                .and_then(|info| info.remote_addr()),
+        };
+
+        let context = from_headers(self.interface, peer, request.headers());
+        scope(context, self.inner.call(request))
+    }
+}
@@ -1,8 +1,11 @@
 // External dependencies
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tokio::task::JoinHandle;
 
 // Internal depenencies
 use super::ApplicationInterface;
+use crate::server::utilities::database::DatabaseTasks;
 
 /// This sturct is for those thread that are started by something else but monitored by InterfaceHandler
 pub struct Dummy {
@@ -23,3 +26,40 @@ impl ApplicationInterface for Dummy {
         self.thread.as_ref().map(|thread| !thread.is_finished())
     }
 }
+
+/// Tasks of the databases that are added besides the default one. Databases can be created and dropped
+/// while the application runs, so their tasks are registered here instead of one by one.
+#[derive(Clone, Default)]
+pub struct Databases {
+    tasks: Arc<Mutex<HashMap<String, DatabaseTasks>>>,
+}
+
+impl Databases {
+    /// Start monitoring the tasks of a database
+    pub fn insert(&self, name: String, tasks: DatabaseTasks) {
+        self.tasks.lock().unwrap().insert(name, tasks);
+    }
+
+    /// Stop monitoring the tasks of a database, it must be called before the database is dropped
+    pub fn remove(&self, name: &str) -> Option<DatabaseTasks> {
+        self.tasks.lock().unwrap().remove(name)
+    }
+}
+
+impl ApplicationInterface for Databases {
+    fn run(&mut self) {}
+
+    fn is_it_run(&self) -> Option<bool> {
+        for (name, tasks) in self.tasks.lock().unwrap().iter() {
+            if tasks.hook_manager.is_finished()
+                || tasks.datastore.is_finished()
+                || tasks.logger.as_ref().is_some_and(|x| x.is_finished())
+            {
+                tracing::error!("task of database '{}' has stopped", name);
+                return Some(false);
+            }
+        }
+
+        Some(true)
+    }
+}
@@ -0,0 +1,60 @@
+macro_rules! send_data_request {
+    ($payload:expr, $data_sender:expr) => {{
+        if let Err(e) = $data_sender
+            .send(crate::server::interfaces::context::with_context($payload))
+            .await
+        {
+            for line in e.to_string().lines() {
+                tracing::error!("{}", line);
+            }
+            return Err(Status::internal("failed to send request to database"));
+        }
+    }};
+}
+pub(in crate::server::interfaces::grpc) use send_data_request;
+
+macro_rules! receive_data_response {
+    ($receiver:expr) => {{
+        match $receiver.recv().await {
+            Some(response) => response,
+            None => {
+                tracing::error!("failed to get response from database");
+                return Err(Status::internal("failed to get response from database"));
+            }
+        }
+    }};
+}
+pub(in crate::server::interfaces::grpc) use receive_data_response;
+
+macro_rules! return_ok {
+    () => {{
+        return Ok(Response::new(Empty {}));
+    }};
+}
+pub(in crate::server::interfaces::grpc) use return_ok;
+
+macro_rules! return_status {
+    ($error:expr) => {{
+        return Err(error_to_status($error));
+    }};
+}
+pub(in crate::server::interfaces::grpc) use return_status;
+
+macro_rules! return_pair {
+    ($key:expr, $value:expr) => {{
+        match $value {
+            ValueType::RecordPointer(value) => {
+                return Ok(Response::new(Pair {
+                    key: $key,
+                    value: value.to_string(),
+                }))
+            }
+            _ => {
+                return Err(Status::failed_precondition(
+                    "Pointer must be Record but it was Table",
+                ))
+            }
+        }
+    }};
+}
+pub(in crate::server::interfaces::grpc) use return_pair;
@@ -0,0 +1,947 @@
+// External dependencies
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc::channel, RwLock};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Server, Request, Response, Status};
+
+// Internal dependencies
+use onlyati_datastore::datastore::enums::{
+    error::ErrorCode, error::ErrorKind, pair::KeyType, pair::RecordValue, pair::ValueType,
+    queue_timeout, snapshot, snapshot::ImportPolicy, snapshot::SnapshotFormat, DatabaseAction,
+    ListType, QueueEnd,
+};
+use onlyati_datastore::datastore::router::DatastoreRouter;
+
+use crate::common::format_time;
+use crate::server::interfaces::context::{self, ContextLayer};
+use crate::server::utilities::config_parse::Config;
+
+// Import macros
+use super::macros::{
+    receive_data_response, return_ok, return_pair, return_status, send_data_request,
+};
+
+// Generated code from proto/hermes.proto
+mod hermes_grpc {
+    tonic::include_proto!("hermes");
+}
+
+use hermes_grpc::hermes_server::{Hermes, HermesServer};
+use hermes_grpc::{
+    AckRequest, Change, CounterRequest, Delivery, Empty, Event, ExecRequest, ExportRequest,
+    FindRequest, Flag, HashEntry, HashFieldRequest, HashFields, HistoryList, Hook, HookList,
+    HookRequest, ImportRequest, ImportSummary, KeyEntry, KeyList, KeyNames, KeyRequest, KeyStat,
+    Length, Limits, ListRequest, MemberRequest, Members, Pair, PatchRequest, PrefixRequest,
+    QueueLimit, QueueRequest, ReliablePopRequest, RelocateRequest, Snapshot, StatList, TableCounts,
+    TypedPair, Usage, UsageReport, WatchRequest,
+};
+
+/// Convert database error to gRPC status
+fn error_to_status(error: ErrorKind) -> Status {
+    if let ErrorKind::InactiveHookManager = error {
+        return Status::failed_precondition(error.to_string());
+    }
+
+    match error.code() {
+        ErrorCode::NotFound => Status::not_found(error.to_string()),
+        ErrorCode::TypeMismatch => Status::failed_precondition(error.to_string()),
+        ErrorCode::InvalidKey | ErrorCode::ValidationFailed => {
+            Status::invalid_argument(error.to_string())
+        }
+        ErrorCode::Conflict => Status::already_exists(error.to_string()),
+        ErrorCode::Forbidden => Status::permission_denied(error.to_string()),
+        ErrorCode::LimitExceeded => Status::resource_exhausted(error.to_string()),
+        ErrorCode::ScriptError | ErrorCode::Internal => Status::internal(error.to_string()),
+    }
+}
+
+/// Parse the optional end of queue, default is the front
+fn queue_end(end: Option<String>) -> Result<QueueEnd, ErrorKind> {
+    Ok(end
+        .as_deref()
+        .map(str::parse)
+        .transpose()?
+        .unwrap_or(QueueEnd::Front))
+}
+
+/// Struct that implements the generated service
+pub struct HermesGrpc {
+    /// Router to send data to the database threads
+    data_sender: DatastoreRouter,
+
+    /// Configuration of application
+    config: Arc<RwLock<Config>>,
+}
+
+#[tonic::async_trait]
+impl Hermes for HermesGrpc {
+    /// Get value of a key
+    async fn get_key(&self, request: Request<KeyRequest>) -> Result<Response<Pair>, Status> {
+        let request = request.into_inner();
+
+        // Reads are served by the handle without the datastore task
+        let response = self
+            .data_sender
+            .get(request.key.clone(), context::current())
+            .await;
+
+        match response {
+            Ok(ValueType::RecordPointer(value)) => Ok(Response::new(Pair {
+                key: request.key,
+                value: value.to_string(),
+            })),
+            Ok(_) => Err(Status::failed_precondition(
+                "Pointer must be Record but it was Table",
+            )),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Create or update a key
+    async fn set_key(&self, request: Request<Pair>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::Set(tx, request.key, request.value);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Remove a key
+    async fn delete_key(&self, request: Request<KeyRequest>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::DeleteKey(tx, request.key);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Remove everything under a path
+    async fn delete_path(&self, request: Request<KeyRequest>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::DeleteTable(tx, request.key);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// List keys under a path
+    async fn list_keys(&self, request: Request<ListRequest>) -> Result<Response<KeyList>, Status> {
+        let request = request.into_inner();
+        let level = match request.one_level {
+            true => ListType::OneLevel,
+            false => ListType::All,
+        };
+
+        let response = self
+            .data_sender
+            .list_keys(request.key, level, context::current())
+            .await;
+
+        match response {
+            Ok(list) => Ok(Response::new(KeyList {
+                keys: list
+                    .iter()
+                    .map(|x| KeyEntry {
+                        key: x.get_key().to_string(),
+                        r#type: x.get_type().to_string(),
+                    })
+                    .collect(),
+            })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Send pair to hook manager without saving it
+    async fn trigger(&self, request: Request<Pair>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::Trigger(tx, request.key, request.value);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Create a new hook
+    async fn set_hook(&self, request: Request<HookRequest>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::HookSet(tx, request.prefix, request.link);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Get links of a hook
+    async fn get_hook(&self, request: Request<PrefixRequest>) -> Result<Response<Hook>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::HookGet(tx, request.prefix);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok((prefix, links)) => Ok(Response::new(Hook { prefix, links })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Remove a link from a hook
+    async fn remove_hook(&self, request: Request<HookRequest>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::HookRemove(tx, request.prefix, request.link);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// List hooks under a prefix
+    async fn list_hooks(
+        &self,
+        request: Request<PrefixRequest>,
+    ) -> Result<Response<HookList>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::HookList(tx, request.prefix);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(hooks) => Ok(Response::new(HookList {
+                hooks: hooks
+                    .into_iter()
+                    .map(|(prefix, links)| Hook { prefix, links })
+                    .collect(),
+            })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Execute a Lua script, then save or trigger its result
+    async fn exec(&self, request: Request<ExecRequest>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+
+        // Get the old value of exists
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::Get(tx, request.key.clone());
+        send_data_request!(action, self.data_sender);
+
+        let old_pair = match receive_data_response!(rx) {
+            Ok(ValueType::RecordPointer(data)) => Some((request.key.clone(), data.to_string())),
+            Ok(_) => {
+                return Err(Status::failed_precondition(
+                    "Pointer must be Record but it was Table",
+                ))
+            }
+            Err(_) => None,
+        };
+
+        // Get config
+        let config = {
+            let config = self.config.read().await;
+            match &config.scripts {
+                Some(scr) if scr.execs.contains(&request.script) => scr.clone(),
+                _ => return Err(Status::invalid_argument("requested script is not defined")),
+            }
+        };
+
+        let new_pair = (request.key.clone(), request.value.clone());
+
+        // Call lua utility
+        let modified_pair = match crate::server::utilities::lua::run(
+            config,
+            old_pair,
+            new_pair,
+            request.script,
+            request.parms,
+        )
+        .await
+        {
+            Ok(modified_pair) => modified_pair,
+            Err(e) => {
+                return Err(Status::internal(format!(
+                    "error during script exection: {}",
+                    e
+                )))
+            }
+        };
+
+        // Make a SET or DELETE action for the modified pair, or a TRIGGER if this was requested
+        let (tx, mut rx) = channel(10);
+        let action = match (request.save, modified_pair.1.is_empty()) {
+            (true, true) => DatabaseAction::DeleteKey(tx, modified_pair.0),
+            (true, false) => DatabaseAction::Set(tx, modified_pair.0, modified_pair.1),
+            (false, false) => DatabaseAction::Trigger(tx, modified_pair.0, modified_pair.1),
+            (false, true) => {
+                return Err(Status::invalid_argument(
+                    "After script was run, the new value is empty",
+                ))
+            }
+        };
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Push value into a queue
+    async fn push(&self, request: Request<Pair>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::Push(tx, request.key, request.value);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Pop value from a queue
+    async fn pop(&self, request: Request<KeyRequest>) -> Result<Response<Pair>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::Pop(tx, request.key.clone());
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(ValueType::RecordPointer(value)) => Ok(Response::new(Pair {
+                key: request.key,
+                value: value.to_string(),
+            })),
+            Ok(_) => Err(Status::failed_precondition(
+                "Pointer must be Record but it was Table",
+            )),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Suspend writing of log files
+    async fn suspend_log(&self, _request: Request<Empty>) -> Result<Response<Empty>, Status> {
+        let (tx, mut rx) = channel(10);
+        send_data_request!(DatabaseAction::SuspendLog(tx), self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Resume writing of log files
+    async fn resume_log(&self, _request: Request<Empty>) -> Result<Response<Empty>, Status> {
+        let (tx, mut rx) = channel(10);
+        send_data_request!(DatabaseAction::ResumeLog(tx), self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    type WatchStream = ReceiverStream<Result<Event, Status>>;
+
+    /// Stream every change under a prefix
+    async fn watch(
+        &self,
+        request: Request<WatchRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let prefix = request.into_inner().prefix;
+
+        let (tx, mut rx) = channel(10);
+        send_data_request!(DatabaseAction::Watch(tx), self.data_sender);
+
+        let mut events = match receive_data_response!(rx) {
+            Ok(events) => events,
+            Err(e) => return_status!(e),
+        };
+
+        // Forward the matching events until the client is connected
+        let (tx, rx) = channel(100);
+        tokio::spawn(async move {
+            tracing::debug!("start watching '{}' prefix", prefix);
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(count)) => {
+                        tracing::warn!("watcher of '{}' has lost {} events", prefix, count);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                if !event.match_prefix(&prefix) {
+                    continue;
+                }
+
+                let event = Event {
+                    id: event.id,
+                    kind: event.kind.to_string(),
+                    key: event.key,
+                    value: event.value,
+                };
+
+                if tx.send(Ok(event)).await.is_err() {
+                    break;
+                }
+            }
+            tracing::debug!("stop watching '{}' prefix", prefix);
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    /// Get metadata of a key or a table
+    async fn stat(&self, request: Request<KeyRequest>) -> Result<Response<StatList>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        send_data_request!(DatabaseAction::Stat(tx, request.key), self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(stats) => Ok(Response::new(StatList {
+                stats: stats
+                    .into_iter()
+                    .map(|x| KeyStat {
+                        key: x.key,
+                        kind: x.kind.to_string(),
+                        size: x.size as u64,
+                        length: x.length.map(|x| x as u64),
+                        created: x.created.map(|x| format_time(x.as_nanos())),
+                        modified: x.modified.map(|x| format_time(x.as_nanos())),
+                        table: x.table.map(|x| TableCounts {
+                            children: x.children as u64,
+                            records: x.records as u64,
+                            queues: x.queues as u64,
+                            hashes: x.hashes as u64,
+                            sets: x.sets as u64,
+                        }),
+                    })
+                    .collect(),
+            })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Move a key or a table to another key
+    async fn r#move(&self, request: Request<RelocateRequest>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+        let kind = request.kind.unwrap_or_else(|| "record".to_string());
+        let key = match KeyType::from_kind(&kind, request.key) {
+            Ok(key) => key,
+            Err(e) => return_status!(e),
+        };
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::Move(tx, key, request.destination);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Copy a key or a table to another key
+    async fn copy(&self, request: Request<RelocateRequest>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+        let kind = request.kind.unwrap_or_else(|| "record".to_string());
+        let key = match KeyType::from_kind(&kind, request.key) {
+            Ok(key) => key,
+            Err(e) => return_status!(e),
+        };
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::Copy(tx, key, request.destination);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Create or update a key with a typed value
+    async fn set_typed(&self, request: Request<TypedPair>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+        let value = match RecordValue::parse(&request.r#type, &request.value) {
+            Ok(value) => value,
+            Err(e) => return_status!(e),
+        };
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::SetTyped(tx, request.key, value);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Change a part of a JSON record
+    async fn patch(&self, request: Request<PatchRequest>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+        let value = match serde_json::from_str(&request.value) {
+            Ok(value) => value,
+            Err(e) => {
+                return Err(Status::invalid_argument(format!(
+                    "Invalid JSON value: {}",
+                    e
+                )))
+            }
+        };
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::Patch(tx, request.key, request.path, value);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Add a number to a counter, then return with the new value
+    async fn increment(&self, request: Request<CounterRequest>) -> Result<Response<Pair>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::Increment(tx, request.key.clone(), request.by.unwrap_or(1));
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(value) => return_pair!(request.key, value),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Read an item of a queue without removing it
+    async fn peek(&self, request: Request<QueueRequest>) -> Result<Response<Pair>, Status> {
+        let request = request.into_inner();
+        let end = match queue_end(request.end) {
+            Ok(end) => end,
+            Err(e) => return_status!(e),
+        };
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::Peek(tx, request.key.clone(), end);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(value) => return_pair!(request.key, value),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Get the number of items in a queue
+    async fn queue_length(&self, request: Request<KeyRequest>) -> Result<Response<Length>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        send_data_request!(
+            DatabaseAction::QueueLength(tx, request.key),
+            self.data_sender
+        );
+
+        match receive_data_response!(rx) {
+            Ok(length) => Ok(Response::new(Length {
+                length: length as u64,
+            })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Pop value from the front or back of a queue, optionally wait for it
+    async fn pop_from(&self, request: Request<QueueRequest>) -> Result<Response<Pair>, Status> {
+        let request = request.into_inner();
+        let end = match queue_end(request.end) {
+            Ok(end) => end,
+            Err(e) => return_status!(e),
+        };
+        let wait = match request.wait.map(queue_timeout).transpose() {
+            Ok(wait) => wait,
+            Err(e) => return_status!(e),
+        };
+
+        let (tx, mut rx) = channel(10);
+        let action = match wait {
+            Some(wait) => DatabaseAction::PopWait(tx, request.key.clone(), end, wait),
+            None => DatabaseAction::PopFrom(tx, request.key.clone(), end),
+        };
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(value) => return_pair!(request.key, value),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Pop value from a queue that must be acknowledged within the visibility timeout
+    async fn pop_reliable(
+        &self,
+        request: Request<ReliablePopRequest>,
+    ) -> Result<Response<Delivery>, Status> {
+        let request = request.into_inner();
+        let visibility = match queue_timeout(request.visibility) {
+            Ok(visibility) => visibility,
+            Err(e) => return_status!(e),
+        };
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::PopReliable(tx, request.key, visibility);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok((id, value)) => Ok(Response::new(Delivery { id, value })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Acknowledge value that was popped by PopReliable
+    async fn ack(&self, request: Request<AckRequest>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        send_data_request!(DatabaseAction::Ack(tx, request.id), self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Set a field of a hash
+    async fn hash_set(&self, request: Request<HashEntry>) -> Result<Response<Flag>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::HashSet(tx, request.key, request.field, request.value);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(value) => Ok(Response::new(Flag { value })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Get a field of a hash
+    async fn hash_get(&self, request: Request<HashFieldRequest>) -> Result<Response<Pair>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::HashGet(tx, request.key, request.field.clone());
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(value) => return_pair!(request.field, value),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Remove a field of a hash
+    async fn hash_delete(
+        &self,
+        request: Request<HashFieldRequest>,
+    ) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::HashDelete(tx, request.key, request.field);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(_) => return_ok!(),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Get every field of a hash
+    async fn hash_get_all(
+        &self,
+        request: Request<KeyRequest>,
+    ) -> Result<Response<HashFields>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        send_data_request!(
+            DatabaseAction::HashGetAll(tx, request.key),
+            self.data_sender
+        );
+
+        match receive_data_response!(rx) {
+            Ok(fields) => Ok(Response::new(HashFields {
+                fields: fields.into_iter().collect(),
+            })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Add a member to a set
+    async fn set_add(&self, request: Request<MemberRequest>) -> Result<Response<Flag>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::SetAdd(tx, request.key, request.member);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(value) => Ok(Response::new(Flag { value })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Remove a member from a set
+    async fn set_remove(&self, request: Request<MemberRequest>) -> Result<Response<Flag>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::SetRemove(tx, request.key, request.member);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(value) => Ok(Response::new(Flag { value })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Get every member of a set
+    async fn set_members(&self, request: Request<KeyRequest>) -> Result<Response<Members>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        send_data_request!(
+            DatabaseAction::SetMembers(tx, request.key),
+            self.data_sender
+        );
+
+        match receive_data_response!(rx) {
+            Ok(members) => Ok(Response::new(Members {
+                members: members.into_iter().collect(),
+            })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Tell that the value is member of a set
+    async fn set_is_member(
+        &self,
+        request: Request<MemberRequest>,
+    ) -> Result<Response<Flag>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::SetIsMember(tx, request.key, request.member);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(value) => Ok(Response::new(Flag { value })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Find records under a prefix by value index
+    async fn find(&self, request: Request<FindRequest>) -> Result<Response<KeyNames>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::Find(tx, request.prefix, request.value);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(keys) => Ok(Response::new(KeyNames {
+                keys: keys.iter().map(|x| x.get_key().to_string()).collect(),
+            })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Export everything under a key
+    async fn export(&self, request: Request<ExportRequest>) -> Result<Response<Snapshot>, Status> {
+        let request = request.into_inner();
+        let format = match request
+            .format
+            .as_deref()
+            .unwrap_or("json")
+            .parse::<SnapshotFormat>()
+        {
+            Ok(format) => format,
+            Err(e) => return_status!(e),
+        };
+
+        let (tx, mut rx) = channel(10);
+        send_data_request!(DatabaseAction::Export(tx, request.key), self.data_sender);
+
+        match receive_data_response!(rx).and_then(|x| x.encode(format)) {
+            Ok(data) => Ok(Response::new(Snapshot { data })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Import a snapshot that was made by export
+    async fn import(
+        &self,
+        request: Request<ImportRequest>,
+    ) -> Result<Response<ImportSummary>, Status> {
+        let request = request.into_inner();
+        let format = match request
+            .format
+            .as_deref()
+            .unwrap_or("json")
+            .parse::<SnapshotFormat>()
+        {
+            Ok(format) => format,
+            Err(e) => return_status!(e),
+        };
+        let policy = match request
+            .policy
+            .as_deref()
+            .unwrap_or("merge")
+            .parse::<ImportPolicy>()
+        {
+            Ok(policy) => policy,
+            Err(e) => return_status!(e),
+        };
+        let snapshot = match snapshot::Snapshot::decode(&request.data, format) {
+            Ok(snapshot) => snapshot,
+            Err(e) => return_status!(e),
+        };
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::Import(tx, snapshot, policy);
+        send_data_request!(action, self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(summary) => Ok(Response::new(ImportSummary {
+                imported: summary.imported as u64,
+                skipped: summary.skipped as u64,
+            })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Get the past changes of a key from the append file
+    async fn history(&self, request: Request<KeyRequest>) -> Result<Response<HistoryList>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        send_data_request!(DatabaseAction::History(tx, request.key), self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(history) => Ok(Response::new(HistoryList {
+                changes: history
+                    .into_iter()
+                    .map(|x| Change {
+                        time: format_time(x.time.as_nanos()),
+                        action: x.action,
+                        field: x.field,
+                        value: x.value,
+                        context: x.context.map(|x| x.to_string()),
+                    })
+                    .collect(),
+            })),
+            Err(e) => return_status!(e),
+        }
+    }
+
+    /// Get limits and current usage of the database of a key
+    async fn usage(&self, request: Request<KeyRequest>) -> Result<Response<UsageReport>, Status> {
+        let request = request.into_inner();
+
+        let (tx, mut rx) = channel(10);
+        send_data_request!(DatabaseAction::Usage(tx, request.key), self.data_sender);
+
+        match receive_data_response!(rx) {
+            Ok(report) => Ok(Response::new(UsageReport {
+                limits: Some(Limits {
+                    max_memory: report.limits.max_memory.map(|x| x as u64),
+                    max_keys: report.limits.max_keys.map(|x| x as u64),
+                    max_value_size: report.limits.max_value_size.map(|x| x as u64),
+                    queues: report
+                        .limits
+                        .queues
+                        .into_iter()
+                        .map(|x| QueueLimit {
+                            prefix: x.prefix,
+                            max_length: x.max_length as u64,
+                        })
+                        .collect(),
+                    policy: report.limits.policy.to_string(),
+                    evict_prefixes: report.limits.evict_prefixes,
+                }),
+                usage: Some(Usage {
+                    memory: report.usage.memory as u64,
+                    keys: report.usage.keys as u64,
+                    evicted: report.usage.evicted,
+                }),
+            })),
+            Err(e) => return_status!(e),
+        }
+    }
+}
+
+/// Run gRPC interface
+///
+/// # Parameters
+/// - `data_sender`: Sender that send data to database thread
+/// - `address`: Host address where the interface bind and listen
+/// - `config`: Application's configuration
+pub async fn run_async(data_sender: DatastoreRouter, address: String, config: Arc<RwLock<Config>>) {
+    tracing::info!("gRPC interface on {} is starting...", address);
+
+    let address: SocketAddr = match address.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            for line in e.to_string().lines() {
+                tracing::error!("{}", line);
+            }
+            return;
+        }
+    };
+
+    let service = HermesGrpc {
+        data_sender,
+        config,
+    };
+
+    if let Err(e) = Server::builder()
+        .layer(ContextLayer::new("grpc"))
+        .add_service(HermesServer::new(service))
+        .serve(address)
+        .await
+    {
+        panic!("gRPC interface has failed: {}", e);
+    }
+}
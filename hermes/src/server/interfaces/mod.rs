@@ -2,6 +2,10 @@ pub mod classic;
 pub mod rest;
 pub mod dummy;
 pub mod websocket;
+pub mod resp;
+pub mod grpc;
+pub mod mqtt;
+pub mod context;
 
 /// Interface handler
 /// 
@@ -90,10 +94,10 @@ pub trait ApplicationInterface {
 /// so it is impossible to store them directly in a vector.
 impl ApplicationInterface for Box<dyn ApplicationInterface> {
     fn run(&mut self) {
-        return self.as_mut().run();
+        self.as_mut().run()
     }
 
     fn is_it_run(&self) -> Option<bool> {
-        return self.as_ref().is_it_run();
+        self.as_ref().is_it_run()
     }
 }
@@ -0,0 +1,63 @@
+// External dependencies
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+use crate::server::utilities::config_parse::Config;
+
+// Internal dependecies
+use super::ApplicationInterface;
+use onlyati_datastore::datastore::router::DatastoreRouter;
+
+mod utilities;
+
+pub use utilities::check_loops;
+
+/// MQTT bridge that connects to a broker.
+/// Functions:
+/// - Publish changes under the configured prefixes onto the mapped topics
+/// - Subscribe to the configured topics and SET or PUSH their payloads into the mapped keys
+pub struct Mqtt {
+    /// Router to send data to the database threads
+    data_sender: DatastoreRouter,
+
+    /// Task of the interface, it is used for health check
+    thread: Option<JoinHandle<()>>,
+
+    /// Application's config file
+    config: Arc<RwLock<Config>>,
+}
+
+impl Mqtt {
+    /// Create new interface
+    ///
+    /// # Parmeters
+    /// - `data_sender`: Router to send data to the database threads
+    /// - `config`: Application's config file
+    pub fn new(data_sender: DatastoreRouter, config: Arc<RwLock<Config>>) -> Self {
+        Self {
+            data_sender,
+            thread: None,
+            config,
+        }
+    }
+}
+
+impl ApplicationInterface for Mqtt {
+    /// Function to start the interface
+    fn run(&mut self) {
+        let data_sender = self.data_sender.clone();
+        let config = self.config.clone();
+        let thread = tokio::spawn(async move {
+            tracing::trace!("Start MQTT bridge");
+            utilities::run_async(data_sender, config).await;
+        });
+
+        self.thread = Some(thread);
+    }
+
+    /// Check function that interface is running
+    fn is_it_run(&self) -> Option<bool> {
+        self.thread.as_ref().map(|thread| !thread.is_finished())
+    }
+}
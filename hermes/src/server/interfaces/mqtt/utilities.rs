@@ -0,0 +1,489 @@
+// External dependencies
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc::channel, mpsc::Receiver, RwLock};
+
+// Internal dependencies
+use onlyati_datastore::datastore::enums::{
+    context::RequestContext, event::EventKind, pair::ValueType, DatabaseAction,
+};
+use onlyati_datastore::datastore::router::DatastoreRouter;
+
+use crate::server::utilities::config_parse::{
+    Config, Mqtt, MqttAction, MqttPublish, MqttSubscribe, Scripts,
+};
+
+/// Payload that has been received from a subscribed topic and waits to be saved
+struct IngestItem {
+    /// Key where the payload is saved
+    key: String,
+
+    /// Payload of the message
+    value: String,
+
+    /// SET or PUSH the payload
+    action: MqttAction,
+
+    /// Lua script that is run before save
+    script: Option<String>,
+}
+
+/// Convert number from config to QoS
+fn to_qos(qos: Option<u8>) -> QoS {
+    match qos {
+        Some(1) => QoS::AtLeastOnce,
+        Some(2) => QoS::ExactlyOnce,
+        _ => QoS::AtMostOnce,
+    }
+}
+
+/// Map a key onto topic if it is under the prefix, e.g.:
+/// `/root/status/x` with `/root/status` prefix and `hermes/status` topic is `hermes/status/x`
+fn key_to_topic(key: &str, publish: &MqttPublish) -> Option<String> {
+    let prefix = publish.prefix.trim_end_matches('/');
+    let topic = publish.topic.trim_end_matches('/');
+
+    if key == prefix {
+        return Some(topic.to_string());
+    }
+
+    key.strip_prefix(prefix)
+        .filter(|rest| rest.starts_with('/'))
+        .map(|rest| format!("{}{}", topic, rest))
+}
+
+/// Map a topic onto key if it matches with the filter of subscription. Levels that
+/// are matched by wildcards are appended to the key, e.g.:
+/// `devices/a/temp` with `devices/#` filter and `/root/devices` key is `/root/devices/a/temp`
+fn topic_to_key(topic: &str, subscribe: &MqttSubscribe) -> Option<String> {
+    let filter: Vec<&str> = subscribe.topic.split('/').collect();
+    let levels: Vec<&str> = topic.split('/').collect();
+    let key = subscribe.key.trim_end_matches('/');
+
+    let mut wildcard_levels = Vec::new();
+    for (index, part) in filter.iter().enumerate() {
+        match *part {
+            "#" => {
+                wildcard_levels.extend_from_slice(&levels[index.min(levels.len())..]);
+                break;
+            }
+            "+" => match levels.get(index) {
+                Some(level) => wildcard_levels.push(*level),
+                None => return None,
+            },
+            part => {
+                if levels.get(index) != Some(&part) {
+                    return None;
+                }
+            }
+        }
+
+        if index == filter.len() - 1 && levels.len() != filter.len() {
+            return None;
+        }
+    }
+
+    if wildcard_levels.is_empty() {
+        return Some(key.to_string());
+    }
+
+    Some(format!("{}/{}", key, wildcard_levels.join("/")))
+}
+
+/// Tells that topic filter matches with the topic or with any topic under it
+fn filter_overlaps(filter: &str, topic: &str) -> bool {
+    let filter: Vec<&str> = filter.split('/').collect();
+    for (index, level) in topic.trim_end_matches('/').split('/').enumerate() {
+        match filter.get(index) {
+            Some(&"#") => return true,
+            Some(&"+") => continue,
+            Some(part) if *part == level => continue,
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+/// Tells that one key is the other or it is under the other
+fn keys_overlap(first: &str, second: &str) -> bool {
+    let first = first.trim_end_matches('/');
+    let second = second.trim_end_matches('/');
+    let under = |key: &str, prefix: &str| key == prefix || key.starts_with(&format!("{}/", prefix));
+
+    under(first, second) || under(second, first)
+}
+
+/// Check that payloads are not published back onto a subscribed topic, it would be an endless loop:
+/// the ingested payload is saved, the change is published and the published message is received again
+///
+/// # Parameters
+/// - `mqtt`: MQTT settings of config
+///
+/// # Return
+///
+/// With error text if a subscription overlaps with a publish
+pub fn check_loops(mqtt: &Mqtt) -> Result<(), String> {
+    for subscribe in &mqtt.subscribe {
+        for publish in &mqtt.publish {
+            if keys_overlap(&subscribe.key, &publish.prefix)
+                && filter_overlaps(&subscribe.topic, &publish.topic)
+            {
+                return Err(format!(
+                    "mqtt.subscribe of '{}' onto '{}' overlaps with mqtt.publish of '{}' onto '{}', it would be a loop",
+                    subscribe.topic, subscribe.key, publish.prefix, publish.topic
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Save the received payloads one after the other, so the order of pushes are kept
+async fn ingest(
+    mut receiver: Receiver<IngestItem>,
+    data_sender: DatastoreRouter,
+    scripts: Option<Scripts>,
+) {
+    while let Some(item) = receiver.recv().await {
+        let (key, value) = match (&item.script, &scripts) {
+            (Some(script), Some(scripts)) => {
+                // Get the old value of exists
+                let (tx, mut rx) = channel(10);
+                let action = DatabaseAction::Get(tx, item.key.clone());
+                if let Err(e) = data_sender.send(action).await {
+                    tracing::error!("failed to send request to database: {}", e);
+                    continue;
+                }
+
+                let old_pair = match rx.recv().await {
+                    Some(Ok(ValueType::RecordPointer(data))) => {
+                        Some((item.key.clone(), data.to_string()))
+                    }
+                    _ => None,
+                };
+
+                match crate::server::utilities::lua::run(
+                    scripts.clone(),
+                    old_pair,
+                    (item.key, item.value),
+                    script.clone(),
+                    None,
+                )
+                .await
+                {
+                    Ok(pair) => pair,
+                    Err(e) => {
+                        tracing::error!("error during script exection: {}", e);
+                        continue;
+                    }
+                }
+            }
+            _ => (item.key, item.value),
+        };
+
+        if value.is_empty() {
+            tracing::debug!("value of '{}' is empty, it is not saved", key);
+            continue;
+        }
+
+        let (tx, mut rx) = channel(10);
+        let action = match item.action {
+            MqttAction::Set => DatabaseAction::Set(tx, key.clone(), value),
+            MqttAction::Push => DatabaseAction::Push(tx, key.clone(), value),
+        };
+        let context = RequestContext {
+            interface: String::from("mqtt"),
+            ..Default::default()
+        };
+        let action = DatabaseAction::WithContext(context, Box::new(action));
+
+        if let Err(e) = data_sender.send(action).await {
+            tracing::error!("failed to send request to database: {}", e);
+            continue;
+        }
+
+        match rx.recv().await {
+            Some(Ok(_)) => tracing::trace!("payload is saved onto '{}'", key),
+            Some(Err(e)) => tracing::warn!("failed to save payload onto '{}': {}", key, e),
+            None => tracing::error!("failed to get response from database"),
+        }
+    }
+}
+
+/// Publish the changes of database onto the mapped topics
+async fn publish(client: AsyncClient, data_sender: DatastoreRouter, publishes: Vec<MqttPublish>) {
+    let (tx, mut rx) = channel(10);
+    if let Err(e) = data_sender.send(DatabaseAction::Watch(tx)).await {
+        tracing::error!("failed to send request to database: {}", e);
+        return;
+    }
+
+    let mut events = match rx.recv().await {
+        Some(Ok(events)) => events,
+        _ => {
+            tracing::error!("failed to subscribe to database events");
+            return;
+        }
+    };
+
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(count)) => {
+                tracing::warn!("MQTT bridge has lost {} events", count);
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+
+        // Delete is published as empty payload, so retained messages are cleared too.
+        // Changes of hashes and sets are partial, they would be misleading as retained message.
+        // Move and copy events carry the source key instead of the value, so they are skipped.
+        let payload = match event.kind {
+            EventKind::Set | EventKind::Push | EventKind::Trigger => {
+                event.value.clone().unwrap_or_default()
+            }
+            EventKind::Delete => String::new(),
+            EventKind::Pop
+            | EventKind::HashSet
+            | EventKind::HashDelete
+            | EventKind::SetAdd
+            | EventKind::SetRemove
+            | EventKind::Move
+            | EventKind::Copy => continue,
+        };
+
+        for publish in &publishes {
+            if let Some(topic) = key_to_topic(&event.key, publish) {
+                tracing::trace!("publish change of '{}' onto '{}'", event.key, topic);
+                if let Err(e) = client
+                    .publish(
+                        topic,
+                        to_qos(publish.qos),
+                        publish.retain.unwrap_or(false),
+                        payload.clone(),
+                    )
+                    .await
+                {
+                    tracing::error!("failed to publish: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Run MQTT bridge
+///
+/// # Parameters
+/// - `data_sender`: Sender that send data to database thread
+/// - `config`: Application's configuration
+pub async fn run_async(data_sender: DatastoreRouter, config: Arc<RwLock<Config>>) {
+    let (mqtt, scripts) = {
+        let config = config.read().await;
+        match &config.mqtt {
+            Some(mqtt) => (mqtt.clone(), config.scripts.clone()),
+            None => return,
+        }
+    };
+
+    tracing::info!("MQTT bridge to {}:{} is starting...", mqtt.host, mqtt.port);
+
+    let mut options = MqttOptions::new(
+        mqtt.client_id.clone().unwrap_or("hermes".to_string()),
+        mqtt.host.clone(),
+        mqtt.port,
+    );
+    options.set_keep_alive(std::time::Duration::from_secs(
+        mqtt.keep_alive.unwrap_or(30),
+    ));
+    if let Some(username) = &mqtt.username {
+        options.set_credentials(username, mqtt.password.clone().unwrap_or_default());
+    }
+
+    let (client, mut eventloop) = AsyncClient::new(options, 100);
+
+    // Start publisher for changes
+    if !mqtt.publish.is_empty() {
+        tokio::spawn(publish(
+            client.clone(),
+            data_sender.clone(),
+            mqtt.publish.clone(),
+        ));
+    }
+
+    // Start the worker that saves the incoming payloads
+    let (ingest_sender, ingest_receiver) = channel(100);
+    tokio::spawn(ingest(ingest_receiver, data_sender, scripts));
+
+    loop {
+        let event = match eventloop.poll().await {
+            Ok(event) => event,
+            Err(e) => {
+                tracing::warn!("MQTT connection error: {}, reconnect after 5 seconds", e);
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                continue;
+            }
+        };
+
+        match event {
+            // Subscriptions are made after every (re)connection
+            Event::Incoming(Packet::ConnAck(_)) => {
+                tracing::debug!("connected to MQTT broker");
+                for subscribe in &mqtt.subscribe {
+                    if let Err(e) = client.try_subscribe(&subscribe.topic, to_qos(subscribe.qos)) {
+                        tracing::error!("failed to subscribe to '{}': {}", subscribe.topic, e);
+                    }
+                }
+            }
+            Event::Incoming(Packet::Publish(message)) => {
+                let value = match String::from_utf8(message.payload.to_vec()) {
+                    Ok(value) => value,
+                    Err(_) => {
+                        tracing::warn!("payload of '{}' is not UTF-8, skipped", message.topic);
+                        continue;
+                    }
+                };
+
+                for subscribe in &mqtt.subscribe {
+                    if let Some(key) = topic_to_key(&message.topic, subscribe) {
+                        tracing::trace!("message of '{}' is saved onto '{}'", message.topic, key);
+                        let item = IngestItem {
+                            key,
+                            value: value.clone(),
+                            action: subscribe.action.clone(),
+                            script: subscribe.script.clone(),
+                        };
+
+                        if ingest_sender.send(item).await.is_err() {
+                            tracing::error!("ingest worker of MQTT bridge has stopped");
+                            return;
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn publish(prefix: &str, topic: &str) -> MqttPublish {
+        MqttPublish {
+            prefix: prefix.to_string(),
+            topic: topic.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn subscribe(topic: &str, key: &str) -> MqttSubscribe {
+        MqttSubscribe {
+            topic: topic.to_string(),
+            key: key.to_string(),
+            action: MqttAction::Set,
+            script: None,
+            qos: None,
+        }
+    }
+
+    #[test]
+    fn test_key_to_topic() {
+        let entry = publish("/root/status", "hermes/status");
+        assert_eq!(
+            Some("hermes/status".to_string()),
+            key_to_topic("/root/status", &entry)
+        );
+        assert_eq!(
+            Some("hermes/status/dc1/vps01".to_string()),
+            key_to_topic("/root/status/dc1/vps01", &entry)
+        );
+        assert_eq!(None, key_to_topic("/root/statuses/vps01", &entry));
+        assert_eq!(None, key_to_topic("/root/other", &entry));
+
+        // Closing slashes of config are ignored
+        let entry = publish("/root/status/", "hermes/status/");
+        assert_eq!(
+            Some("hermes/status/vps01".to_string()),
+            key_to_topic("/root/status/vps01", &entry)
+        );
+    }
+
+    #[test]
+    fn test_topic_to_key() {
+        // Exact topic
+        let entry = subscribe("devices/a/temp", "/root/temp");
+        assert_eq!(
+            Some("/root/temp".to_string()),
+            topic_to_key("devices/a/temp", &entry)
+        );
+        assert_eq!(None, topic_to_key("devices/a/temp/x", &entry));
+        assert_eq!(None, topic_to_key("devices/a", &entry));
+        assert_eq!(None, topic_to_key("devices/b/temp", &entry));
+
+        // Single level wildcard
+        let entry = subscribe("devices/+/temp", "/root/temp/");
+        assert_eq!(
+            Some("/root/temp/a".to_string()),
+            topic_to_key("devices/a/temp", &entry)
+        );
+        assert_eq!(None, topic_to_key("devices/a/b/temp", &entry));
+        assert_eq!(None, topic_to_key("devices/a", &entry));
+
+        // Multi level wildcard
+        let entry = subscribe("devices/#", "/root/devices");
+        assert_eq!(
+            Some("/root/devices/a/temp".to_string()),
+            topic_to_key("devices/a/temp", &entry)
+        );
+        assert_eq!(
+            Some("/root/devices/a".to_string()),
+            topic_to_key("devices/a", &entry)
+        );
+        assert_eq!(
+            Some("/root/devices".to_string()),
+            topic_to_key("devices", &entry)
+        );
+        assert_eq!(None, topic_to_key("other/a", &entry));
+
+        // Both wildcards
+        let entry = subscribe("+/sensors/#", "/root/sensors");
+        assert_eq!(
+            Some("/root/sensors/site1/a/temp".to_string()),
+            topic_to_key("site1/sensors/a/temp", &entry)
+        );
+        assert_eq!(None, topic_to_key("site1/actors/a", &entry));
+    }
+
+    #[test]
+    fn test_check_loops() {
+        let mut mqtt = Mqtt {
+            publish: vec![publish("/root/devices", "hermes/devices")],
+            subscribe: vec![subscribe("devices/#", "/root/devices")],
+            ..Default::default()
+        };
+        assert!(check_loops(&mqtt).is_ok());
+
+        // Ingested payloads would be published onto the subscribed topics
+        mqtt.subscribe = vec![subscribe("hermes/#", "/root")];
+        assert!(check_loops(&mqtt).is_err());
+
+        mqtt.subscribe = vec![subscribe("hermes/devices/+", "/root/devices")];
+        assert!(check_loops(&mqtt).is_err());
+
+        mqtt.subscribe = vec![subscribe("hermes/+/temp", "/root/devices/a")];
+        assert!(check_loops(&mqtt).is_err());
+
+        // Same topics, but saved outside of the published prefix
+        mqtt.subscribe = vec![subscribe("hermes/#", "/root/ingest")];
+        assert!(check_loops(&mqtt).is_ok());
+
+        // Same keys, but other topics
+        mqtt.subscribe = vec![subscribe("hermes/other/#", "/root/devices")];
+        assert!(check_loops(&mqtt).is_ok());
+    }
+}
@@ -0,0 +1,37 @@
+macro_rules! send_data_request {
+    ($payload:expr, $data_sender:expr) => {{
+        if let Err(e) = $data_sender
+            .send(crate::server::interfaces::context::with_context($payload))
+            .await
+        {
+            for line in e.to_string().lines() {
+                tracing::error!("{}", line);
+            }
+            return RespValue::error("internal server error");
+        }
+    }};
+}
+pub(in crate::server::interfaces::resp) use send_data_request;
+
+macro_rules! receive_data_response {
+    ($receiver:expr) => {{
+        match $receiver.recv().await {
+            Some(response) => response,
+            None => {
+                tracing::error!("failed to receive message from database");
+                return RespValue::error("internal server error");
+            }
+        }
+    }};
+}
+pub(in crate::server::interfaces::resp) use receive_data_response;
+
+macro_rules! verify_arguments {
+    ($words:expr, $min:expr, $name:expr) => {
+        if $words.len() < $min {
+            return RespValue::error(format!("wrong number of arguments for '{}' command", $name))
+                .into();
+        }
+    };
+}
+pub(in crate::server::interfaces::resp) use verify_arguments;
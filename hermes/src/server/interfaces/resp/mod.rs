@@ -0,0 +1,78 @@
+// External dependencies
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+use crate::server::utilities::config_parse::Config;
+
+// Internal dependecies
+use super::ApplicationInterface;
+use onlyati_datastore::datastore::router::DatastoreRouter;
+
+mod macros;
+mod protocol;
+mod utilities;
+
+/// Redis compatible interface that speaks RESP2 and RESP3
+/// Functions:
+/// - GET `key`
+/// - SET `key` `value` [EX `seconds` | PX `milliseconds`]
+/// - DEL `key` [`key` ...]
+/// - EXISTS `key` [`key` ...]
+/// - KEYS `pattern`
+/// - LPUSH/RPUSH `key` `value` [`value` ...]
+/// - LPOP `key` [`count`]
+/// - PUBLISH `channel` `message`
+/// - EXPIRE `key` `seconds`
+/// - MULTI, EXEC, DISCARD
+/// - PING, ECHO, HELLO, SELECT, COMMAND, CLIENT, QUIT
+pub struct Resp {
+    /// Router to send data to the database threads
+    data_sender: DatastoreRouter,
+
+    /// Host address where the interface bind and listen
+    address: String,
+
+    /// Task of the interface, it is used for health check
+    thread: Option<JoinHandle<()>>,
+
+    /// Application's config file
+    config: Arc<RwLock<Config>>,
+}
+
+impl Resp {
+    /// Create new interface
+    ///
+    /// # Parmeters
+    /// - `data_sender`: Router to send data to the database threads
+    /// - `address`: Host address where the interface bind and listen
+    /// - `config`: Application's config file
+    pub fn new(data_sender: DatastoreRouter, address: String, config: Arc<RwLock<Config>>) -> Self {
+        Self {
+            data_sender,
+            address,
+            thread: None,
+            config,
+        }
+    }
+}
+
+impl ApplicationInterface for Resp {
+    /// Function to start the interface
+    fn run(&mut self) {
+        let data_sender = self.data_sender.clone();
+        let addres = self.address.clone();
+        let config = self.config.clone();
+        let thread = tokio::spawn(async move {
+            tracing::trace!("Start RESP interface");
+            utilities::run_async(data_sender, addres, config).await;
+        });
+
+        self.thread = Some(thread);
+    }
+
+    /// Check function that interface is running
+    fn is_it_run(&self) -> Option<bool> {
+        self.thread.as_ref().map(|thread| !thread.is_finished())
+    }
+}
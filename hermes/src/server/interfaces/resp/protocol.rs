@@ -0,0 +1,356 @@
+use onlyati_datastore::datastore::enums::error::{ErrorCode, ErrorKind};
+
+/// Value that can be sent or received via RESP protocol
+#[derive(Debug, Clone, PartialEq)]
+pub enum RespValue {
+    /// `+OK\r\n`
+    SimpleString(String),
+
+    /// `-ERR message\r\n`
+    Error(String),
+
+    /// `:1000\r\n`
+    Integer(i64),
+
+    /// `$5\r\nhello\r\n`
+    BulkString(Vec<u8>),
+
+    /// `*2\r\n...`
+    Array(Vec<RespValue>),
+
+    /// Null bulk string in RESP2 (`$-1\r\n`), null in RESP3 (`_\r\n`)
+    Null,
+
+    /// Map in RESP3 (`%2\r\n...`), flat array in RESP2
+    Map(Vec<(RespValue, RespValue)>),
+}
+
+impl RespValue {
+    /// Create bulk string from anything that can be displayed
+    pub fn bulk<T: std::fmt::Display>(value: T) -> Self {
+        Self::BulkString(value.to_string().into_bytes())
+    }
+
+    /// Create a simple `+OK` response
+    pub fn ok() -> Self {
+        Self::SimpleString("OK".to_string())
+    }
+
+    /// Create an error response, `ERR` prefix is added if the message does not have an error code
+    pub fn error<T: std::fmt::Display>(message: T) -> Self {
+        let message = message.to_string().replace(['\r', '\n'], " ");
+        match message.split_whitespace().next() {
+            Some(code) if code.chars().all(|c| c.is_ascii_uppercase()) => Self::Error(message),
+            _ => Self::Error(format!("ERR {}", message)),
+        }
+    }
+
+    /// Create an error response from an error of database, its code is used as error prefix,
+    /// except type mismatch that is sent as the usual `WRONGTYPE`
+    pub fn from_error(error: ErrorKind) -> Self {
+        let message = error.to_string().replace(['\r', '\n'], " ");
+        match error.code() {
+            ErrorCode::TypeMismatch => Self::Error(format!("WRONGTYPE {}", message)),
+            code => Self::Error(format!("{} {}", code, message)),
+        }
+    }
+
+    /// Serialize value according to the negotiated protocol version
+    ///
+    /// # Parameters
+    /// - `version`: Protocol version, 2 or 3
+    pub fn encode(&self, version: u8) -> Vec<u8> {
+        let mut output = Vec::new();
+        self.encode_into(&mut output, version);
+        output
+    }
+
+    fn encode_into(&self, output: &mut Vec<u8>, version: u8) {
+        match self {
+            Self::SimpleString(text) => {
+                output.extend_from_slice(format!("+{}\r\n", text).as_bytes())
+            }
+            Self::Error(text) => output.extend_from_slice(format!("-{}\r\n", text).as_bytes()),
+            Self::Integer(number) => {
+                output.extend_from_slice(format!(":{}\r\n", number).as_bytes())
+            }
+            Self::BulkString(data) => {
+                output.extend_from_slice(format!("${}\r\n", data.len()).as_bytes());
+                output.extend_from_slice(data);
+                output.extend_from_slice(b"\r\n");
+            }
+            Self::Array(items) => {
+                output.extend_from_slice(format!("*{}\r\n", items.len()).as_bytes());
+                for item in items {
+                    item.encode_into(output, version);
+                }
+            }
+            Self::Null => match version {
+                3 => output.extend_from_slice(b"_\r\n"),
+                _ => output.extend_from_slice(b"$-1\r\n"),
+            },
+            Self::Map(items) => {
+                match version {
+                    3 => output.extend_from_slice(format!("%{}\r\n", items.len()).as_bytes()),
+                    _ => output.extend_from_slice(format!("*{}\r\n", items.len() * 2).as_bytes()),
+                }
+                for (key, value) in items {
+                    key.encode_into(output, version);
+                    value.encode_into(output, version);
+                }
+            }
+        }
+    }
+}
+
+/// Maximum number of words in a command, like `proto-max-multibulk-len` of Redis
+pub const MAX_MULTIBULK_LENGTH: i64 = 1024 * 1024;
+
+/// Maximum length of a bulk string, like `proto-max-bulk-len` of Redis but smaller
+pub const MAX_BULK_LENGTH: i64 = 16 * 1024 * 1024;
+
+/// Maximum length of an inline command without line feed
+pub const MAX_INLINE_LENGTH: usize = 64 * 1024;
+
+/// Maximum size of unprocessed bytes of a connection, like `client-query-buffer-limit` of Redis but smaller,
+/// it is enough for a command with the longest bulk string
+pub const MAX_BUFFER_SIZE: usize = 32 * 1024 * 1024;
+
+/// Result of parsing: words of the command and number of consumed bytes
+type ParsedCommand = Result<Option<(Vec<Vec<u8>>, usize)>, String>;
+
+/// Try to parse one command from the buffer.
+///
+/// # Parameters
+/// - `buffer`: Bytes that has been read from socket so far
+///
+/// # Return
+///
+/// - `Ok(Some((words, consumed)))` if a complete command is in the buffer
+/// - `Ok(None)` if more bytes are needed
+/// - `Err(message)` if the buffer contains protocol error
+pub fn parse_command(buffer: &[u8]) -> ParsedCommand {
+    if buffer.is_empty() {
+        return Ok(None);
+    }
+
+    // Regular clients send array of bulk strings, everything else is an inline command
+    if buffer[0] != b'*' {
+        return parse_inline(buffer);
+    }
+
+    let (count, mut position) = match read_line(buffer, 1)? {
+        Some((line, next)) => (parse_number(line)?, next),
+        None => return Ok(None),
+    };
+
+    if count <= 0 {
+        return Ok(Some((Vec::new(), position)));
+    }
+
+    if count > MAX_MULTIBULK_LENGTH {
+        return Err("Protocol error: invalid multibulk length".to_string());
+    }
+
+    // Count comes from the client, so memory is not reserved for all words in advance
+    let mut words = Vec::with_capacity(count.min(64) as usize);
+    for _ in 0..count {
+        if position >= buffer.len() {
+            return Ok(None);
+        }
+
+        if buffer[position] != b'$' {
+            return Err(format!(
+                "Protocol error: expected '$', got '{}'",
+                buffer[position] as char
+            ));
+        }
+
+        let (length, next) = match read_line(buffer, position + 1)? {
+            Some((line, next)) => (parse_number(line)?, next),
+            None => return Ok(None),
+        };
+
+        if !(0..=MAX_BULK_LENGTH).contains(&length) {
+            return Err("Protocol error: invalid bulk length".to_string());
+        }
+
+        let end = match next.checked_add(length as usize) {
+            Some(end) if end.checked_add(2).is_some() => end,
+            _ => return Err("Protocol error: invalid bulk length".to_string()),
+        };
+        if buffer.len() < end + 2 {
+            return Ok(None);
+        }
+
+        if &buffer[end..end + 2] != b"\r\n" {
+            return Err("Protocol error: bulk string is not terminated".to_string());
+        }
+
+        words.push(buffer[next..end].to_vec());
+        position = end + 2;
+    }
+
+    Ok(Some((words, position)))
+}
+
+/// Parse inline command, for example what `telnet` or `socat` sends: `GET /root/status\r\n`
+fn parse_inline(buffer: &[u8]) -> ParsedCommand {
+    let end = match buffer.iter().position(|x| *x == b'\n') {
+        Some(index) => index,
+        None if buffer.len() > MAX_INLINE_LENGTH => {
+            return Err("Protocol error: too big inline request".to_string())
+        }
+        None => return Ok(None),
+    };
+
+    let line = match String::from_utf8(buffer[..end].to_vec()) {
+        Ok(line) => line,
+        Err(_) => return Err("Protocol error: inline command is not UTF-8".to_string()),
+    };
+
+    let words = line
+        .split_whitespace()
+        .map(|x| x.as_bytes().to_vec())
+        .collect();
+
+    Ok(Some((words, end + 1)))
+}
+
+/// Read a line that is terminated by CRLF beginning from `start`
+fn read_line(buffer: &[u8], start: usize) -> Result<Option<(&[u8], usize)>, String> {
+    let mut index = start;
+    while index + 1 < buffer.len() {
+        if buffer[index] == b'\r' && buffer[index + 1] == b'\n' {
+            return Ok(Some((&buffer[start..index], index + 2)));
+        }
+        index += 1;
+    }
+
+    Ok(None)
+}
+
+/// Convert bytes to a number
+fn parse_number(line: &[u8]) -> Result<i64, String> {
+    match std::str::from_utf8(line) {
+        Ok(text) => match text.parse::<i64>() {
+            Ok(number) => Ok(number),
+            Err(_) => Err(format!("Protocol error: invalid number '{}'", text)),
+        },
+        Err(_) => Err("Protocol error: invalid number".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(list: &[&str]) -> Vec<Vec<u8>> {
+        list.iter().map(|x| x.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_parse_command() {
+        let frame = b"*2\r\n$3\r\nGET\r\n$12\r\n/root/status\r\n";
+        assert_eq!(
+            Ok(Some((words(&["GET", "/root/status"]), frame.len()))),
+            parse_command(frame)
+        );
+
+        // Only the first command is consumed, the rest remains for the next parse
+        let mut pipeline = frame.to_vec();
+        pipeline.extend_from_slice(b"*1\r\n$4\r\nPING\r\n");
+        assert_eq!(
+            Ok(Some((words(&["GET", "/root/status"]), frame.len()))),
+            parse_command(&pipeline)
+        );
+        assert_eq!(
+            Ok(Some((words(&["PING"]), 14))),
+            parse_command(&pipeline[frame.len()..])
+        );
+
+        // Empty bulk string and empty array
+        assert_eq!(
+            Ok(Some((words(&["ECHO", ""]), 20))),
+            parse_command(b"*2\r\n$4\r\nECHO\r\n$0\r\n\r\n")
+        );
+        assert_eq!(Ok(Some((Vec::new(), 4))), parse_command(b"*0\r\n"));
+    }
+
+    #[test]
+    fn test_partial_frame() {
+        let frame = b"*2\r\n$3\r\nGET\r\n$12\r\n/root/status\r\n";
+
+        // Every prefix of the frame needs more bytes
+        for end in 0..frame.len() {
+            assert_eq!(
+                Ok(None),
+                parse_command(&frame[..end]),
+                "prefix of {} bytes",
+                end
+            );
+        }
+    }
+
+    #[test]
+    fn test_bad_frame() {
+        // Clients send array of bulk strings only, nested arrays are refused
+        assert!(parse_command(b"*1\r\n*1\r\n$4\r\nPING\r\n").is_err());
+        assert!(parse_command(b"*1\r\n:1\r\n").is_err());
+
+        // Invalid or too big lengths
+        assert!(parse_command(b"*x\r\n").is_err());
+        assert!(parse_command(b"*1\r\n$-5\r\n").is_err());
+        assert!(parse_command(b"*1\r\n$abc\r\n").is_err());
+        assert!(parse_command(format!("*{}\r\n", MAX_MULTIBULK_LENGTH + 1).as_bytes()).is_err());
+        assert!(parse_command(format!("*1\r\n${}\r\n", MAX_BULK_LENGTH + 1).as_bytes()).is_err());
+        assert!(parse_command(b"*1\r\n$9223372036854775807\r\n").is_err());
+
+        // Length does not match the content
+        assert!(parse_command(b"*1\r\n$2\r\nPING\r\n").is_err());
+    }
+
+    #[test]
+    fn test_inline_command() {
+        assert_eq!(
+            Ok(Some((words(&["SET", "status", "ok"]), 16))),
+            parse_command(b"SET  status ok\r\n\r\n")
+        );
+        assert_eq!(Ok(Some((words(&["PING"]), 5))), parse_command(b"PING\nGET"));
+        assert_eq!(Ok(Some((Vec::new(), 2))), parse_command(b"\r\n"));
+
+        assert_eq!(Ok(None), parse_command(b"GET /root"));
+        assert!(parse_command(&vec![b'a'; MAX_INLINE_LENGTH + 1]).is_err());
+        assert!(parse_command(b"GET \xff\n").is_err());
+    }
+
+    #[test]
+    fn test_encode() {
+        let value = RespValue::Array(vec![
+            RespValue::ok(),
+            RespValue::Integer(-1),
+            RespValue::bulk("hi"),
+            RespValue::Null,
+            RespValue::Map(vec![(RespValue::bulk("a"), RespValue::Integer(1))]),
+        ]);
+
+        assert_eq!(
+            b"*5\r\n+OK\r\n:-1\r\n$2\r\nhi\r\n$-1\r\n*2\r\n$1\r\na\r\n:1\r\n".to_vec(),
+            value.encode(2)
+        );
+        assert_eq!(
+            b"*5\r\n+OK\r\n:-1\r\n$2\r\nhi\r\n_\r\n%1\r\n$1\r\na\r\n:1\r\n".to_vec(),
+            value.encode(3)
+        );
+
+        // Line breaks cannot be in an error
+        assert_eq!(
+            RespValue::Error("ERR bad value  ".to_string()),
+            RespValue::error("bad value\r\n")
+        );
+        assert_eq!(
+            RespValue::Error("WRONGTYPE no".to_string()),
+            RespValue::error("WRONGTYPE no")
+        );
+    }
+}
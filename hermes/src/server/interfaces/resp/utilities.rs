@@ -0,0 +1,1162 @@
+// External dependencies
+use bytes::{Buf, BytesMut};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::RwLock;
+
+// Internal dependencies
+use onlyati_datastore::datastore::enums::{
+    context::RequestContext, error::ErrorKind, pair::KeyType, pair::RecordValue, pair::ValueType,
+    DatabaseAction, Expiration, ListType, QueueEnd, MAX_QUEUE_TIMEOUT,
+};
+use onlyati_datastore::datastore::router::DatastoreRouter;
+use onlyati_datastore::datastore::types::ResultWithResult;
+
+use crate::common::format_time;
+use crate::server::interfaces::context;
+use crate::server::utilities::config_parse::Config;
+
+// Import macros
+use super::macros::{receive_data_response, send_data_request, verify_arguments};
+use super::protocol::{parse_command, RespValue, MAX_BUFFER_SIZE};
+
+/// Commands that can be queued after a MULTI
+const TRANSACTION_COMMANDS: [&str; 26] = [
+    "GET",
+    "SET",
+    "DEL",
+    "EXISTS",
+    "KEYS",
+    "LPUSH",
+    "RPUSH",
+    "LPOP",
+    "RPOP",
+    "LLEN",
+    "LINDEX",
+    "HSET",
+    "HGET",
+    "HDEL",
+    "HGETALL",
+    "HEXISTS",
+    "SADD",
+    "SREM",
+    "SMEMBERS",
+    "SISMEMBER",
+    "PUBLISH",
+    "EXPIRE",
+    "PING",
+    "STAT",
+    "RENAME",
+    "COPY",
+];
+
+/// Kinds of Hermes keys that a Redis key can be, Redis keys have no kind in their name
+const KEY_KINDS: [fn(String) -> KeyType; 4] =
+    [KeyType::Record, KeyType::Queue, KeyType::Hash, KeyType::Set];
+
+/// Struct that is shared among connections
+#[derive(Clone)]
+struct InjectedData {
+    /// Router to send data to the database threads
+    data_sender: DatastoreRouter,
+
+    /// Name of the root table, it is used to translate Redis keys to Hermes keys
+    root: String,
+
+    /// Counter for connection identifiers
+    counter: Arc<AtomicU64>,
+}
+
+/// State of a single client connection
+struct Session {
+    /// Identifier of the connection
+    id: u64,
+
+    /// Negotiated protocol version (2 or 3)
+    version: u8,
+
+    /// Queued commands between MULTI and EXEC
+    transaction: Option<Vec<Vec<Vec<u8>>>>,
+
+    /// Transaction has an invalid command, EXEC will be refused
+    aborted: bool,
+
+    /// Address of the client
+    peer: SocketAddr,
+
+    /// User name that was given in AUTH command, password is not checked
+    claimed_user: Option<String>,
+}
+
+/// Translate a Redis key to a Hermes key.
+///
+/// Keys that begin with '/' are used as they are, e.g. `/root/status/vps01`.
+/// Other keys are put under the root table and ':' is used as separator, e.g. `status:vps01` -> `/root/status/vps01`.
+fn to_hermes_key(key: &str, root: &str) -> String {
+    if key.starts_with('/') {
+        return key.to_string();
+    }
+
+    format!("/{}/{}", root, key.replace(':', "/"))
+}
+
+/// Translate a Hermes key back to the format that client has used in its request
+fn to_redis_key(key: &str, root: &str, as_path: bool) -> String {
+    if as_path {
+        return key.to_string();
+    }
+
+    let prefix = format!("/{}/", root);
+    match key.strip_prefix(&prefix) {
+        Some(key) => key.replace('/', ":"),
+        None => key.to_string(),
+    }
+}
+
+/// Convert a Redis glob pattern to regular expression
+fn glob_to_regex(pattern: &str) -> Option<regex::Regex> {
+    let mut expression = String::from("^");
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => expression.push_str(".*"),
+            '?' => expression.push('.'),
+            '[' => {
+                expression.push('[');
+                if chars.peek() == Some(&'^') {
+                    chars.next();
+                    expression.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    expression.push_str(&regex::escape(&c.to_string()));
+                }
+                expression.push(']');
+            }
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    expression.push_str(&regex::escape(&c.to_string()));
+                }
+            }
+            c => expression.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    expression.push('$');
+
+    regex::Regex::new(&expression).ok()
+}
+
+/// Convert bytes of a word to string
+fn word_to_string(word: &[u8]) -> Result<String, RespValue> {
+    match String::from_utf8(word.to_vec()) {
+        Ok(text) => Ok(text),
+        Err(_) => Err(RespValue::error("only UTF-8 values are supported")),
+    }
+}
+
+/// Actions of a command and its reply, the reply is made from the responses of the actions
+struct Prepared {
+    /// Actions that are sent to the database together
+    actions: Vec<DatabaseAction>,
+
+    /// Reply to client, it waits for the responses of the actions
+    reply: Pin<Box<dyn Future<Output = RespValue> + Send>>,
+}
+
+impl Prepared {
+    fn new(
+        actions: Vec<DatabaseAction>,
+        reply: impl Future<Output = RespValue> + Send + 'static,
+    ) -> Self {
+        Self {
+            actions,
+            reply: Box::pin(reply),
+        }
+    }
+}
+
+impl From<RespValue> for Prepared {
+    /// Command is answered without database, e.g. due to wrong arguments
+    fn from(value: RespValue) -> Self {
+        Self::new(Vec::new(), async move { value })
+    }
+}
+
+/// Make an action for every item and collect the receivers of their responses
+fn prepare_each<I, T, R>(
+    items: I,
+    action: impl Fn(Sender<R>, T) -> DatabaseAction,
+) -> (Vec<DatabaseAction>, Vec<Receiver<R>>)
+where
+    I: IntoIterator<Item = T>,
+{
+    let mut actions = Vec::new();
+    let mut receivers = Vec::new();
+    for item in items {
+        let (tx, rx) = channel(10);
+        actions.push(action(tx, item));
+        receivers.push(rx);
+    }
+    (actions, receivers)
+}
+
+/// Reply of a record read
+fn record_reply(response: ResultWithResult) -> RespValue {
+    match response {
+        Ok(ValueType::RecordPointer(data)) => RespValue::bulk(data),
+        Ok(_) => {
+            RespValue::error("WRONGTYPE Operation against a key holding the wrong kind of value")
+        }
+        Err(ErrorKind::NotFound(_)) => RespValue::Null,
+        Err(e) => RespValue::from_error(e),
+    }
+}
+
+/// Send the actions to the database, more actions are sent as one batch,
+/// so other requests are not done between them
+///
+/// # Parameters
+/// - `actions`: Actions of one or more commands
+/// - `injected`: Shared data among connections
+async fn send_actions(mut actions: Vec<DatabaseAction>, injected: &InjectedData) -> RespValue {
+    if actions.len() == 1 {
+        send_data_request!(actions.remove(0), injected.data_sender);
+        return RespValue::ok();
+    }
+
+    if !actions.is_empty() {
+        let (tx, mut rx) = channel(10);
+        send_data_request!(DatabaseAction::Batch(tx, actions), injected.data_sender);
+
+        if let Err(e) = receive_data_response!(rx) {
+            return RespValue::from_error(e);
+        }
+    }
+
+    RespValue::ok()
+}
+
+/// Execute a command that is not related to connection or transaction handling
+///
+/// # Parameters
+/// - `words`: Command and its arguments
+/// - `injected`: Shared data among connections
+async fn execute_command(words: &[Vec<u8>], injected: &InjectedData) -> RespValue {
+    // Single reads are served by the handle without the datastore task
+    if words.len() == 2 && String::from_utf8_lossy(&words[0]).eq_ignore_ascii_case("GET") {
+        let key = match word_to_string(&words[1]) {
+            Ok(key) => to_hermes_key(&key, &injected.root),
+            Err(e) => return e,
+        };
+        return record_reply(injected.data_sender.get(key, context::current()).await);
+    }
+
+    let prepared = prepare_command(words, &injected.root);
+    match send_actions(prepared.actions, injected).await {
+        RespValue::SimpleString(_) => prepared.reply.await,
+        error => error,
+    }
+}
+
+/// Make the database actions of a command, actions of the queued commands of a transaction
+/// are sent together, so the actions must not depend on the response of each other.
+///
+/// # Parameters
+/// - `words`: Command and its arguments
+/// - `root`: Name of the root table
+fn prepare_command(words: &[Vec<u8>], root: &str) -> Prepared {
+    let command = String::from_utf8_lossy(&words[0]).to_uppercase();
+    let mut args = Vec::with_capacity(words.len() - 1);
+    for word in &words[1..] {
+        match word_to_string(word) {
+            Ok(arg) => args.push(arg),
+            Err(e) => return e.into(),
+        }
+    }
+
+    match command.as_str() {
+        //
+        // Get value of a key
+        //
+        "GET" => {
+            verify_arguments!(args, 1, "get");
+            let key = to_hermes_key(&args[0], root);
+
+            let (tx, mut rx) = channel(10);
+            Prepared::new(vec![DatabaseAction::Get(tx, key)], async move {
+                record_reply(receive_data_response!(rx))
+            })
+        }
+        //
+        // Create or update a key, optionally with expiration
+        //
+        "SET" => {
+            verify_arguments!(args, 2, "set");
+            let key = to_hermes_key(&args[0], root);
+
+            let mut expiration = Expiration::Remove;
+            let mut index = 2;
+            while index < args.len() {
+                match args[index].to_uppercase().as_str() {
+                    "EX" | "PX" if index + 1 < args.len() => {
+                        let amount = match args[index + 1].parse::<u64>() {
+                            Ok(amount) if amount > 0 => amount,
+                            _ => {
+                                return RespValue::error("invalid expire time in 'set' command")
+                                    .into()
+                            }
+                        };
+                        expiration = match args[index].to_uppercase().as_str() {
+                            "EX" => Expiration::After(std::time::Duration::from_secs(amount)),
+                            _ => Expiration::After(std::time::Duration::from_millis(amount)),
+                        };
+                        index += 2;
+                    }
+                    "KEEPTTL" => {
+                        expiration = Expiration::Keep;
+                        index += 1;
+                    }
+                    _ => return RespValue::error("syntax error").into(),
+                }
+            }
+
+            // Expiration is kept by the datastore, so a set from any interface cancels it
+            let (tx, mut rx) = channel(10);
+            let action = match expiration {
+                Expiration::Remove => DatabaseAction::Set(tx, key, args[1].clone()),
+                expiration => DatabaseAction::SetExpiring(tx, key, args[1].clone(), expiration),
+            };
+
+            Prepared::new(vec![action], async move {
+                match receive_data_response!(rx) {
+                    Ok(_) => RespValue::ok(),
+                    Err(e) => RespValue::from_error(e),
+                }
+            })
+        }
+        //
+        // Delete keys
+        //
+        "DEL" => {
+            verify_arguments!(args, 1, "del");
+
+            // Type of key is unknown, so every kind is deleted, a key is counted once
+            let keys = args.iter().flat_map(|key| {
+                let key = to_hermes_key(key, root);
+                KEY_KINDS.into_iter().map(move |kind| kind(key.clone()))
+            });
+            let (actions, receivers) = prepare_each(keys, DatabaseAction::DeleteTyped);
+
+            Prepared::new(actions, async move {
+                let mut counter = 0;
+                let mut receivers = receivers.into_iter();
+                while receivers.len() > 0 {
+                    let mut deleted = false;
+                    for mut rx in receivers.by_ref().take(KEY_KINDS.len()) {
+                        match receive_data_response!(rx) {
+                            Ok(_) => deleted = true,
+                            Err(ErrorKind::NotFound(_)) => (),
+                            Err(e) => return RespValue::from_error(e),
+                        }
+                    }
+
+                    if deleted {
+                        counter += 1;
+                    }
+                }
+
+                RespValue::Integer(counter)
+            })
+        }
+        //
+        // Count the existing keys
+        //
+        "EXISTS" => {
+            verify_arguments!(args, 1, "exists");
+            let keys = args.iter().map(|key| to_hermes_key(key, root));
+            let (actions, receivers) = prepare_each(keys, DatabaseAction::Get);
+
+            Prepared::new(actions, async move {
+                let mut counter = 0;
+                for mut rx in receivers {
+                    if receive_data_response!(rx).is_ok() {
+                        counter += 1;
+                    }
+                }
+
+                RespValue::Integer(counter)
+            })
+        }
+        //
+        // List keys that match with a glob pattern
+        //
+        "KEYS" => {
+            verify_arguments!(args, 1, "keys");
+            let as_path = args[0].starts_with('/');
+            let pattern = to_hermes_key(&args[0], root);
+
+            let regex = match glob_to_regex(&pattern) {
+                Some(regex) => regex,
+                None => return RespValue::error("invalid pattern").into(),
+            };
+
+            // Start the listing from the deepest table that does not contain pattern character
+            let literal_end = pattern.find(['*', '?', '[', '\\']).unwrap_or(pattern.len());
+            let base = match pattern[..literal_end].rfind('/') {
+                Some(index) if index > 0 => pattern[..index].to_string(),
+                _ => format!("/{}", root),
+            };
+
+            let (tx, mut rx) = channel(10);
+            let root = root.to_string();
+            Prepared::new(
+                vec![DatabaseAction::ListKeys(tx, base, ListType::All)],
+                async move {
+                    match receive_data_response!(rx) {
+                        Ok(list) => RespValue::Array(
+                            list.iter()
+                                .filter(|x| regex.is_match(x.get_key()))
+                                .map(|x| RespValue::bulk(to_redis_key(x.get_key(), &root, as_path)))
+                                .collect(),
+                        ),
+                        Err(_) => RespValue::Array(Vec::new()),
+                    }
+                },
+            )
+        }
+        //
+        // Change a counter atomically
+        //
+        "INCR" | "DECR" | "INCRBY" | "DECRBY" => {
+            let by = match command.as_str() {
+                "INCR" | "DECR" => {
+                    verify_arguments!(args, 1, command.to_lowercase());
+                    1
+                }
+                _ => {
+                    verify_arguments!(args, 2, command.to_lowercase());
+                    match args[1].parse::<i64>() {
+                        Ok(by) => by,
+                        Err(_) => {
+                            return RespValue::error("value is not an integer or out of range")
+                                .into()
+                        }
+                    }
+                }
+            };
+            let by = match command.starts_with("DECR") {
+                true => match by.checked_neg() {
+                    Some(by) => by,
+                    None => {
+                        return RespValue::error("value is not an integer or out of range").into()
+                    }
+                },
+                false => by,
+            };
+            let key = to_hermes_key(&args[0], root);
+
+            let (tx, mut rx) = channel(10);
+            Prepared::new(vec![DatabaseAction::Increment(tx, key, by)], async move {
+                match receive_data_response!(rx) {
+                    Ok(ValueType::RecordPointer(RecordValue::Integer(number))) => {
+                        RespValue::Integer(number)
+                    }
+                    Ok(ValueType::RecordPointer(data)) => RespValue::bulk(data),
+                    Ok(_) => RespValue::error(
+                        "WRONGTYPE Operation against a key holding the wrong kind of value",
+                    ),
+                    Err(e) => RespValue::from_error(e),
+                }
+            })
+        }
+        //
+        // Push values into the front or back of a queue, it returns with the length of queue
+        //
+        "LPUSH" | "RPUSH" => {
+            verify_arguments!(args, 2, command.to_lowercase());
+            let key = to_hermes_key(&args[0], root);
+            let end = match command.as_str() {
+                "LPUSH" => QueueEnd::Front,
+                _ => QueueEnd::Back,
+            };
+            let (actions, receivers) = prepare_each(args[1..].to_vec(), |tx, value| {
+                DatabaseAction::PushTo(tx, key.clone(), value, end)
+            });
+
+            Prepared::new(actions, async move {
+                let mut length = 0;
+                for mut rx in receivers {
+                    match receive_data_response!(rx) {
+                        Ok(current) => length = current,
+                        Err(e) => return RespValue::from_error(e),
+                    }
+                }
+
+                RespValue::Integer(length as i64)
+            })
+        }
+        //
+        // Pop values from a queue, with count an array of at most that many items is returned
+        //
+        "LPOP" | "RPOP" => {
+            verify_arguments!(args, 1, command.to_lowercase());
+            let key = to_hermes_key(&args[0], root);
+            let end = match command.as_str() {
+                "RPOP" => QueueEnd::Back,
+                _ => QueueEnd::Front,
+            };
+
+            let count = match args.get(1) {
+                Some(count) => match count.parse::<usize>() {
+                    Ok(count) => Some(count),
+                    Err(_) => {
+                        return RespValue::error("value is out of range, must be positive").into()
+                    }
+                },
+                None => None,
+            };
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::PopMany(tx, key, end, count.unwrap_or(1));
+            Prepared::new(vec![action], async move {
+                let mut values = match receive_data_response!(rx) {
+                    Ok(values) => values.into_iter().map(RespValue::bulk).collect(),
+                    Err(_) => Vec::new(),
+                };
+
+                match count {
+                    Some(_) if values.is_empty() => RespValue::Null,
+                    Some(_) => RespValue::Array(values),
+                    None => values.pop().unwrap_or(RespValue::Null),
+                }
+            })
+        }
+        //
+        // Blocking pop, only one queue can be waited for
+        //
+        "BLPOP" | "BRPOP" => {
+            verify_arguments!(args, 2, command.to_lowercase());
+            if args.len() > 2 {
+                return RespValue::error("only one key is supported by blocking pop").into();
+            }
+
+            let key = to_hermes_key(&args[0], root);
+            let end = match command.as_str() {
+                "BRPOP" => QueueEnd::Back,
+                _ => QueueEnd::Front,
+            };
+            // Zero waits "forever", that is the longest timeout of the database
+            let timeout = match args[1].parse::<f64>() {
+                Ok(0.0) => MAX_QUEUE_TIMEOUT,
+                Ok(seconds) => match std::time::Duration::try_from_secs_f64(seconds) {
+                    Ok(timeout) if timeout <= MAX_QUEUE_TIMEOUT => timeout,
+                    _ => return RespValue::error("timeout is out of range").into(),
+                },
+                _ => return RespValue::error("timeout is not a float or out of range").into(),
+            };
+
+            let (tx, mut rx) = channel(10);
+            let name = args[0].clone();
+            Prepared::new(
+                vec![DatabaseAction::PopWait(tx, key, end, timeout)],
+                async move {
+                    match receive_data_response!(rx) {
+                        Ok(ValueType::RecordPointer(data)) => {
+                            RespValue::Array(vec![RespValue::bulk(name), RespValue::bulk(data)])
+                        }
+                        _ => RespValue::Null,
+                    }
+                },
+            )
+        }
+        //
+        // Length of a queue
+        //
+        "LLEN" => {
+            verify_arguments!(args, 1, "llen");
+            let key = to_hermes_key(&args[0], root);
+
+            let (tx, mut rx) = channel(10);
+            Prepared::new(vec![DatabaseAction::QueueLength(tx, key)], async move {
+                match receive_data_response!(rx) {
+                    Ok(length) => RespValue::Integer(length as i64),
+                    Err(e) => RespValue::from_error(e),
+                }
+            })
+        }
+        //
+        // Read an item of queue, only the first (0) and the last (-1) can be read
+        //
+        "LINDEX" => {
+            verify_arguments!(args, 2, "lindex");
+            let key = to_hermes_key(&args[0], root);
+            let end = match args[1].as_str() {
+                "0" => QueueEnd::Front,
+                "-1" => QueueEnd::Back,
+                _ => return RespValue::error("only index 0 and -1 are supported").into(),
+            };
+
+            let (tx, mut rx) = channel(10);
+            Prepared::new(vec![DatabaseAction::Peek(tx, key, end)], async move {
+                match receive_data_response!(rx) {
+                    Ok(ValueType::RecordPointer(data)) => RespValue::bulk(data),
+                    _ => RespValue::Null,
+                }
+            })
+        }
+        //
+        // Set fields of a hash, it returns with the number of new fields
+        //
+        "HSET" => {
+            verify_arguments!(args, 3, "hset");
+            if args.len() % 2 == 0 {
+                return RespValue::error("wrong number of arguments for 'hset' command").into();
+            }
+            let key = to_hermes_key(&args[0], root);
+            let (actions, receivers) = prepare_each(args[1..].chunks(2), |tx, pair| {
+                DatabaseAction::HashSet(tx, key.clone(), pair[0].clone(), pair[1].clone())
+            });
+
+            Prepared::new(actions, async move {
+                let mut counter = 0;
+                for mut rx in receivers {
+                    match receive_data_response!(rx) {
+                        Ok(true) => counter += 1,
+                        Ok(false) => (),
+                        Err(e) => return RespValue::from_error(e),
+                    }
+                }
+
+                RespValue::Integer(counter)
+            })
+        }
+        //
+        // Get a field of hash
+        //
+        "HGET" => {
+            verify_arguments!(args, 2, "hget");
+            let key = to_hermes_key(&args[0], root);
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::HashGet(tx, key, args[1].clone());
+            Prepared::new(vec![action], async move {
+                match receive_data_response!(rx) {
+                    Ok(value) => RespValue::bulk(value.get_value()),
+                    Err(ErrorKind::NotFound(_)) => RespValue::Null,
+                    Err(e) => RespValue::from_error(e),
+                }
+            })
+        }
+        //
+        // Check that field of hash exists
+        //
+        "HEXISTS" => {
+            verify_arguments!(args, 2, "hexists");
+            let key = to_hermes_key(&args[0], root);
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::HashGet(tx, key, args[1].clone());
+            Prepared::new(vec![action], async move {
+                match receive_data_response!(rx) {
+                    Ok(_) => RespValue::Integer(1),
+                    Err(ErrorKind::NotFound(_)) => RespValue::Integer(0),
+                    Err(e) => RespValue::from_error(e),
+                }
+            })
+        }
+        //
+        // Remove fields of a hash, it returns with the number of removed fields
+        //
+        "HDEL" => {
+            verify_arguments!(args, 2, "hdel");
+            let key = to_hermes_key(&args[0], root);
+            let (actions, receivers) = prepare_each(args[1..].to_vec(), |tx, field| {
+                DatabaseAction::HashDelete(tx, key.clone(), field)
+            });
+
+            Prepared::new(actions, async move {
+                let mut counter = 0;
+                for mut rx in receivers {
+                    match receive_data_response!(rx) {
+                        Ok(_) => counter += 1,
+                        Err(ErrorKind::NotFound(_)) => (),
+                        Err(e) => return RespValue::from_error(e),
+                    }
+                }
+
+                RespValue::Integer(counter)
+            })
+        }
+        //
+        // Get every field of a hash
+        //
+        "HGETALL" => {
+            verify_arguments!(args, 1, "hgetall");
+            let key = to_hermes_key(&args[0], root);
+
+            let (tx, mut rx) = channel(10);
+            Prepared::new(vec![DatabaseAction::HashGetAll(tx, key)], async move {
+                match receive_data_response!(rx) {
+                    Ok(fields) => RespValue::Map(
+                        fields
+                            .into_iter()
+                            .map(|(field, value)| (RespValue::bulk(field), RespValue::bulk(value)))
+                            .collect(),
+                    ),
+                    Err(ErrorKind::NotFound(_)) => RespValue::Map(Vec::new()),
+                    Err(e) => RespValue::from_error(e),
+                }
+            })
+        }
+        //
+        // Add or remove members of a set, it returns with the number of changed members
+        //
+        "SADD" | "SREM" => {
+            verify_arguments!(args, 2, command.to_lowercase());
+            let key = to_hermes_key(&args[0], root);
+            let (actions, receivers) =
+                prepare_each(args[1..].to_vec(), |tx, member| match command.as_str() {
+                    "SREM" => DatabaseAction::SetRemove(tx, key.clone(), member),
+                    _ => DatabaseAction::SetAdd(tx, key.clone(), member),
+                });
+
+            Prepared::new(actions, async move {
+                let mut counter = 0;
+                for mut rx in receivers {
+                    match receive_data_response!(rx) {
+                        Ok(true) => counter += 1,
+                        Ok(false) | Err(ErrorKind::NotFound(_)) => (),
+                        Err(e) => return RespValue::from_error(e),
+                    }
+                }
+
+                RespValue::Integer(counter)
+            })
+        }
+        //
+        // Get every member of a set
+        //
+        "SMEMBERS" => {
+            verify_arguments!(args, 1, "smembers");
+            let key = to_hermes_key(&args[0], root);
+
+            let (tx, mut rx) = channel(10);
+            Prepared::new(vec![DatabaseAction::SetMembers(tx, key)], async move {
+                match receive_data_response!(rx) {
+                    Ok(members) => {
+                        RespValue::Array(members.into_iter().map(RespValue::bulk).collect())
+                    }
+                    Err(ErrorKind::NotFound(_)) => RespValue::Array(Vec::new()),
+                    Err(e) => RespValue::from_error(e),
+                }
+            })
+        }
+        //
+        // Check that value is member of a set
+        //
+        "SISMEMBER" => {
+            verify_arguments!(args, 2, "sismember");
+            let key = to_hermes_key(&args[0], root);
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::SetIsMember(tx, key, args[1].clone());
+            Prepared::new(vec![action], async move {
+                match receive_data_response!(rx) {
+                    Ok(found) => RespValue::Integer(found as i64),
+                    Err(e) => RespValue::from_error(e),
+                }
+            })
+        }
+        //
+        // Publish is mapped to trigger, channel is the key
+        //
+        "PUBLISH" => {
+            verify_arguments!(args, 2, "publish");
+            let key = to_hermes_key(&args[0], root);
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Trigger(tx, key, args[1].clone());
+            Prepared::new(vec![action], async move {
+                match receive_data_response!(rx) {
+                    Ok(_) => RespValue::Integer(0),
+                    Err(e) => RespValue::from_error(e),
+                }
+            })
+        }
+        //
+        // Set timeout on a key
+        //
+        "EXPIRE" => {
+            verify_arguments!(args, 2, "expire");
+            let key = to_hermes_key(&args[0], root);
+
+            let seconds = match args[1].parse::<i64>() {
+                Ok(seconds) => seconds,
+                Err(_) => {
+                    return RespValue::error("value is not an integer or out of range").into()
+                }
+            };
+
+            let after = std::time::Duration::from_secs(seconds.max(0) as u64);
+            let (tx, mut rx) = channel(10);
+            Prepared::new(
+                vec![DatabaseAction::Expire(tx, key, Some(after))],
+                async move {
+                    match receive_data_response!(rx) {
+                        Ok(true) => RespValue::Integer(1),
+                        Ok(false) => RespValue::Integer(0),
+                        Err(e) => RespValue::from_error(e),
+                    }
+                },
+            )
+        }
+        "PING" => match args.first() {
+            Some(message) => RespValue::bulk(message).into(),
+            None => RespValue::SimpleString("PONG".to_string()).into(),
+        },
+        //
+        // Metadata of key or table, one map per record, queue, hash, set and table
+        //
+        "STAT" => {
+            verify_arguments!(args, 1, "stat");
+            let key = to_hermes_key(&args[0], root);
+
+            let optional = |value: Option<usize>| match value {
+                Some(value) => RespValue::Integer(value as i64),
+                None => RespValue::Null,
+            };
+            let time = |value: Option<std::time::Duration>| match value {
+                Some(value) => RespValue::bulk(format_time(value.as_nanos())),
+                None => RespValue::Null,
+            };
+
+            let (tx, mut rx) = channel(10);
+            Prepared::new(vec![DatabaseAction::Stat(tx, key)], async move {
+                match receive_data_response!(rx) {
+                    Ok(stats) => RespValue::Array(
+                        stats
+                            .into_iter()
+                            .map(|stat| {
+                                let mut fields = vec![
+                                    (RespValue::bulk("key"), RespValue::bulk(stat.key)),
+                                    (RespValue::bulk("kind"), RespValue::bulk(stat.kind)),
+                                    (RespValue::bulk("size"), optional(Some(stat.size))),
+                                    (RespValue::bulk("length"), optional(stat.length)),
+                                    (RespValue::bulk("created"), time(stat.created)),
+                                    (RespValue::bulk("modified"), time(stat.modified)),
+                                ];
+                                if let Some(table) = stat.table {
+                                    for (name, count) in [
+                                        ("children", table.children),
+                                        ("records", table.records),
+                                        ("queues", table.queues),
+                                        ("hashes", table.hashes),
+                                        ("sets", table.sets),
+                                    ] {
+                                        fields.push((RespValue::bulk(name), optional(Some(count))));
+                                    }
+                                }
+                                RespValue::Map(fields)
+                            })
+                            .collect(),
+                    ),
+                    Err(e) => RespValue::from_error(e),
+                }
+            })
+        }
+        //
+        // Move or copy a string, list, hash or set, destination must not exist
+        //
+        "RENAME" | "COPY" => {
+            verify_arguments!(args, 2, command.to_lowercase());
+            let key = to_hermes_key(&args[0], root);
+            let destination = to_hermes_key(&args[1], root);
+
+            // Type of key is unknown, so every kind is relocated, like DEL deletes every kind
+            let (actions, receivers) = prepare_each(KEY_KINDS, |tx, kind| match command.as_str() {
+                "RENAME" => DatabaseAction::Move(tx, kind(key.clone()), destination.clone()),
+                _ => DatabaseAction::Copy(tx, kind(key.clone()), destination.clone()),
+            });
+
+            Prepared::new(actions, async move {
+                // Result is the success if any, otherwise the first error that is not a missing kind
+                let mut result = Err(ErrorKind::NotFound("no such key".to_string()));
+                for mut rx in receivers {
+                    let response = receive_data_response!(rx);
+                    if result.is_ok() || matches!(response, Err(ErrorKind::NotFound(_))) {
+                        continue;
+                    }
+                    if response.is_ok() || matches!(result, Err(ErrorKind::NotFound(_))) {
+                        result = response;
+                    }
+                }
+
+                match (command.as_str(), result) {
+                    ("RENAME", Ok(_)) => RespValue::ok(),
+                    ("RENAME", Err(ErrorKind::NotFound(_))) => RespValue::error("no such key"),
+                    (_, Ok(_)) => RespValue::Integer(1),
+                    (_, Err(ErrorKind::NotFound(_) | ErrorKind::Conflict(_))) => {
+                        RespValue::Integer(0)
+                    }
+                    (_, Err(e)) => RespValue::from_error(e),
+                }
+            })
+        }
+        _ => RespValue::error(format!("unknown command '{}'", command.to_lowercase())).into(),
+    }
+}
+
+/// Handle one command from a connection, this function takes care about the
+/// connection level commands and transactions, then pass other to `execute_command`.
+///
+/// # Parameters
+/// - `words`: Command and its arguments
+/// - `session`: State of the connection
+/// - `injected`: Shared data among connections
+async fn handle_command(
+    words: Vec<Vec<u8>>,
+    session: &mut Session,
+    injected: &InjectedData,
+) -> RespValue {
+    let command = String::from_utf8_lossy(&words[0]).to_uppercase();
+    tracing::debug!("received command: {}", command);
+
+    // Commands between MULTI and EXEC are queued
+    if session.transaction.is_some() && !["EXEC", "DISCARD", "MULTI"].contains(&command.as_str()) {
+        if !TRANSACTION_COMMANDS.contains(&command.as_str()) {
+            session.aborted = true;
+            return RespValue::error(format!("unknown command '{}'", command.to_lowercase()));
+        }
+
+        if let Some(queue) = &mut session.transaction {
+            queue.push(words);
+        }
+        return RespValue::SimpleString("QUEUED".to_string());
+    }
+
+    match command.as_str() {
+        "MULTI" => {
+            if session.transaction.is_some() {
+                return RespValue::error("MULTI calls can not be nested");
+            }
+            session.transaction = Some(Vec::new());
+            session.aborted = false;
+            RespValue::ok()
+        }
+        "DISCARD" => match session.transaction.take() {
+            Some(_) => RespValue::ok(),
+            None => RespValue::error("DISCARD without MULTI"),
+        },
+        "EXEC" => {
+            let queue = match session.transaction.take() {
+                Some(queue) => queue,
+                None => return RespValue::error("EXEC without MULTI"),
+            };
+
+            if session.aborted {
+                session.aborted = false;
+                return RespValue::error(
+                    "EXECABORT Transaction discarded because of previous errors.",
+                );
+            }
+
+            // Actions of every command are sent as one batch, so the transaction is isolated
+            let mut actions = Vec::new();
+            let mut replies = Vec::with_capacity(queue.len());
+            for words in queue {
+                let prepared = prepare_command(&words, &injected.root);
+                actions.extend(prepared.actions);
+                replies.push(prepared.reply);
+            }
+
+            match send_actions(actions, injected).await {
+                RespValue::SimpleString(_) => (),
+                error => return error,
+            }
+
+            let mut responses = Vec::with_capacity(replies.len());
+            for reply in replies {
+                responses.push(reply.await);
+            }
+            RespValue::Array(responses)
+        }
+        "HELLO" => {
+            if let Some(version) = words.get(1) {
+                match String::from_utf8_lossy(version).parse::<u8>() {
+                    Ok(version) if version == 2 || version == 3 => session.version = version,
+                    _ => return RespValue::error("NOPROTO unsupported protocol version"),
+                }
+            }
+
+            // HELLO version AUTH username password
+            if let (Some(option), Some(user)) = (words.get(2), words.get(3)) {
+                if String::from_utf8_lossy(option).eq_ignore_ascii_case("AUTH") {
+                    session.claimed_user = Some(String::from_utf8_lossy(user).to_string());
+                }
+            }
+
+            RespValue::Map(vec![
+                (RespValue::bulk("server"), RespValue::bulk("hermes")),
+                (
+                    RespValue::bulk("version"),
+                    RespValue::bulk(env!("CARGO_PKG_VERSION")),
+                ),
+                (
+                    RespValue::bulk("proto"),
+                    RespValue::Integer(session.version as i64),
+                ),
+                (RespValue::bulk("id"), RespValue::Integer(session.id as i64)),
+                (RespValue::bulk("mode"), RespValue::bulk("standalone")),
+                (RespValue::bulk("role"), RespValue::bulk("master")),
+                (RespValue::bulk("modules"), RespValue::Array(Vec::new())),
+            ])
+        }
+        "ECHO" => match words.get(1) {
+            Some(message) => RespValue::BulkString(message.clone()),
+            None => RespValue::error("wrong number of arguments for 'echo' command"),
+        },
+        "SELECT" => match words.get(1).map(|x| x.as_slice()) {
+            Some(b"0") => RespValue::ok(),
+            Some(_) => RespValue::error("DB index is out of range"),
+            None => RespValue::error("wrong number of arguments for 'select' command"),
+        },
+        // Clients are asking these during connection, they do not have meaning in Hermes
+        "COMMAND" => RespValue::Array(Vec::new()),
+        "CLIENT" => match words
+            .get(1)
+            .map(|x| String::from_utf8_lossy(x).to_uppercase())
+        {
+            Some(sub) if sub == "ID" => RespValue::Integer(session.id as i64),
+            _ => RespValue::ok(),
+        },
+        "AUTH" => {
+            // AUTH [username] password, there is no real authentication, the user is just logged
+            session.claimed_user = match words.len() {
+                3 => Some(String::from_utf8_lossy(&words[1]).to_string()),
+                _ => Some(String::from("default")),
+            };
+            RespValue::ok()
+        }
+        _ => execute_command(&words, injected).await,
+    }
+}
+
+/// Run RESP interface
+///
+/// # Parameters
+/// - `data_sender`: Sender that send data to database thread
+/// - `address`: Host address where the interface bind and listen
+/// - `config`: Application's configuration
+pub async fn run_async(data_sender: DatastoreRouter, address: String, config: Arc<RwLock<Config>>) {
+    tracing::info!("RESP interface on {} is starting...", address);
+
+    let injected = InjectedData {
+        data_sender,
+        root: config.read().await.general.database_name.clone(),
+        counter: Arc::new(AtomicU64::new(1)),
+    };
+
+    // Try to bind for address
+    let listener = match TcpListener::bind(address.clone()).await {
+        Ok(listener) => listener,
+        Err(e) => panic!("RESP interface failed to bind: {e}"),
+    };
+
+    loop {
+        // Catch every connection
+        let (mut socket, who) = match listener.accept().await {
+            Ok(socket) => socket,
+            Err(e) => panic!("failed to accept connection: {}", e),
+        };
+        tracing::debug!("{} connected to RESP interface", who);
+
+        // Spawn thread for them
+        let injected = injected.clone();
+        tokio::spawn(async move {
+            let mut session = Session {
+                id: injected.counter.fetch_add(1, Ordering::Relaxed),
+                version: 2,
+                transaction: None,
+                aborted: false,
+                peer: who,
+                claimed_user: None,
+            };
+            let mut buffer = BytesMut::with_capacity(4096);
+
+            loop {
+                // Process every complete command that is in the buffer
+                loop {
+                    let (words, consumed) = match parse_command(&buffer) {
+                        Ok(Some(parsed)) => parsed,
+                        Ok(None) => break,
+                        Err(e) => {
+                            let response = RespValue::error(e).encode(session.version);
+                            let _ = socket.write_all(&response).await;
+                            return;
+                        }
+                    };
+                    buffer.advance(consumed);
+
+                    if words.is_empty() {
+                        continue;
+                    }
+
+                    let quit = String::from_utf8_lossy(&words[0]).eq_ignore_ascii_case("QUIT");
+                    let response = match quit {
+                        true => RespValue::ok(),
+                        false => {
+                            let context = RequestContext {
+                                interface: String::from("resp"),
+                                peer: Some(session.peer.to_string()),
+                                claimed_user: session.claimed_user.clone(),
+                                request_id: Some(context::next_request_id()),
+                            };
+                            context::scope(context, handle_command(words, &mut session, &injected))
+                                .await
+                        }
+                    };
+
+                    if let Err(e) = socket.write_all(&response.encode(session.version)).await {
+                        tracing::warn!("failed to write to socket; err = {:?}", e);
+                        return;
+                    }
+
+                    if quit {
+                        tracing::trace!("close connection");
+                        let _ = socket.flush().await;
+                        return;
+                    }
+                }
+
+                // Incomplete command cannot grow without limit while more bytes are waited
+                if buffer.len() > MAX_BUFFER_SIZE {
+                    let response = RespValue::error("Protocol error: query buffer is too big");
+                    let _ = socket.write_all(&response.encode(session.version)).await;
+                    return;
+                }
+
+                // Read further bytes
+                match socket.read_buf(&mut buffer).await {
+                    Ok(0) => {
+                        tracing::trace!("has read EOF");
+                        return;
+                    }
+                    Ok(n) => tracing::trace!("has read {} bytes", n),
+                    Err(e) => {
+                        tracing::warn!("failed to read from socket; err = {:?}", e);
+                        return;
+                    }
+                }
+            }
+        });
+    }
+}
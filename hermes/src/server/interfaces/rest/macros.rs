@@ -1,11 +1,13 @@
 macro_rules! send_data_request {
-    ($payload:expr, $data_mutex:expr) => {{
-        let sender = $data_mutex.lock().await;
-        if let Err(e) = sender.send($payload).await {
+    ($payload:expr, $data_sender:expr) => {{
+        if let Err(e) = $data_sender
+            .send(crate::server::interfaces::context::with_context($payload))
+            .await
+        {
             for line in e.to_string().lines() {
                 tracing::error!("{}", line);
             }
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            return_server_error!("failed to send request to database");
         }
     }};
 }
@@ -16,18 +18,27 @@ macro_rules! return_server_error {
         for line in $error.to_string().lines() {
             tracing::error!("{}", line);
         }
-        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        return_client_error!(ErrorCode::Internal, "internal server error");
     }};
 }
 pub(in crate::server::interfaces::rest) use return_server_error;
 
 macro_rules! return_client_error {
-    ($error:expr) => {{
-        return (StatusCode::BAD_REQUEST, Json($error)).into_response();
+    ($code:expr, $error:expr) => {{
+        let code: ErrorCode = $code;
+        return (status_of_error(code), Json(ErrorBody::new(code, $error))).into_response();
     }};
 }
 pub(in crate::server::interfaces::rest) use return_client_error;
 
+macro_rules! return_database_error {
+    ($error:expr) => {{
+        let error: ErrorKind = $error;
+        return_client_error!(error.code(), error)
+    }};
+}
+pub(in crate::server::interfaces::rest) use return_database_error;
+
 macro_rules! return_ok_with_value {
     ($value:expr) => {{
         return (StatusCode::OK, Json($value)).into_response();
@@ -39,5 +50,8 @@ macro_rules! return_ok {
     () => {{
         return StatusCode::OK.into_response();
     }};
+    ($status:expr) => {{
+        return $status.into_response();
+    }};
 }
 pub(in crate::server::interfaces::rest) use return_ok;
@@ -1,21 +1,25 @@
 // External depencies
 use std::sync::Arc;
-use tokio::sync::{mpsc::Sender, Mutex, RwLock};
+use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 
 use crate::server::utilities::config_parse::Config;
 
 // Internal depencies
-use super::ApplicationInterface;
-use onlyati_datastore::datastore::enums::DatabaseAction;
+use super::{dummy::Databases, ApplicationInterface};
+use onlyati_datastore::datastore::router::DatastoreRouter;
 
 mod macros;
 mod utilities;
+mod v2;
 
 /// Struct that handles the REST interface
 pub struct Rest {
-    /// Sender to send data to database thread
-    data_sender: Arc<Mutex<Sender<DatabaseAction>>>,
+    /// Router to send data to the database threads
+    data_sender: DatastoreRouter,
+
+    /// Tasks of databases that are added besides the default one
+    databases: Databases,
 
     /// Host address where the interface bind and listen
     address: String,
@@ -31,16 +35,19 @@ impl Rest {
     /// Create new interface
     ///
     /// # Parmeters
-    /// - `data_sender`: Sender to send data to database thread
+    /// - `data_sender`: Router to send data to the database threads
+    /// - `databases`: Tasks of databases that are added besides the default one
     /// - `address`: Host address where the interface bind and listen
     /// - `config`: Application's config file
     pub fn new(
-        data_sender: Arc<Mutex<Sender<DatabaseAction>>>,
+        data_sender: DatastoreRouter,
+        databases: Databases,
         address: String,
         config: Arc<RwLock<Config>>,
     ) -> Self {
         Self {
             data_sender,
+            databases,
             address,
             thread: None,
             config,
@@ -52,10 +59,11 @@ impl ApplicationInterface for Rest {
     /// Function to start the interface
     fn run(&mut self) {
         let data_sender = self.data_sender.clone();
+        let databases = self.databases.clone();
         let addres = self.address.clone();
         let cfg = self.config.clone();
         let thread = tokio::spawn(async move {
-            utilities::run_async(data_sender, addres, cfg).await;
+            utilities::run_async(data_sender, databases, addres, cfg).await;
         });
 
         self.thread = Some(thread);
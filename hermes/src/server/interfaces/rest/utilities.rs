@@ -1,36 +1,76 @@
 // External depencies
 use axum::{
-    extract::{BodyStream, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
-    routing::{delete, get, post},
+    extract::{BodyStream, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        IntoResponse,
+    },
+    routing::{delete, get, patch, post},
     Json, Router,
 };
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
 use tokio::sync::mpsc::channel;
-use tokio::sync::{mpsc::Sender, Mutex, RwLock};
+use tokio::sync::RwLock;
+use tokio_stream::wrappers::ReceiverStream;
+use utoipa::ToSchema;
 
 // Internal depencies
-use onlyati_datastore::datastore::{enums::pair::ValueType, enums::DatabaseAction};
+use onlyati_datastore::datastore::{
+    enums::error::ErrorCode, enums::error::ErrorKind, enums::event::Event, enums::pair::KeyType,
+    enums::pair::RecordValue, enums::pair::ValueType, enums::queue_timeout,
+    enums::snapshot::ImportPolicy, enums::snapshot::Snapshot, enums::snapshot::SnapshotFormat,
+    enums::DatabaseAction, enums::QueueEnd, router::DatastoreRouter, stat::Stat,
+};
+
+use onlyati_datastore::logger::enums::HistoryEntry;
 
+use crate::common::format_time;
+use crate::server::interfaces::context::{self, ContextLayer};
+use crate::server::interfaces::dummy::Databases;
 use crate::server::utilities::config_parse::Config;
 
 // Import macroes
 use super::macros::{
-    return_client_error, return_ok, return_ok_with_value, return_server_error, send_data_request,
+    return_client_error, return_database_error, return_ok, return_ok_with_value,
+    return_server_error, send_data_request,
 };
 
+/// How many events are kept for Last-Event-ID resumption
+const EVENT_HISTORY_SIZE: usize = 1000;
+
+/// Interval of heartbeat on event streams
+const EVENT_HEARTBEAT_SECONDS: u64 = 15;
+
 /// Struct that is injected into every endpoint
 #[derive(Clone)]
 pub struct InjectedData {
-    /// Sender to send data to database thread
-    data_sender: Arc<Mutex<Sender<DatabaseAction>>>,
+    /// Router to send data to the database threads
+    pub(super) data_sender: DatastoreRouter,
+
+    /// Tasks of databases that are added besides the default one
+    pub(super) databases: Databases,
 
     /// Configuration of application
-    config: Arc<RwLock<Config>>,
+    pub(super) config: Arc<RwLock<Config>>,
+
+    /// Last events of database
+    pub(super) history: Arc<RwLock<EventHistory>>,
+}
+
+/// Last events of database and the stream of new ones. New event is added and sent under the same lock,
+/// so a client that reads the history and subscribes together gets every event exactly once.
+pub struct EventHistory {
+    /// Last events, the oldest one is the first
+    events: VecDeque<Event>,
+
+    /// Events after they are added to the history
+    live: broadcast::Sender<Event>,
 }
 
 /// Struct is used to query the SET endpoint
@@ -43,6 +83,128 @@ pub struct Pair {
     value: String,
 }
 
+/// Struct is used to query the SET endpoint with typed value
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TypedPair {
+    /// Key for record
+    key: String,
+
+    /// Value of key, native JSON value
+    value: serde_json::Value,
+
+    /// Type of value: `string`, `integer`, `float`, `bool`, `json` or `bytes`
+    r#type: Option<String>,
+}
+
+/// Struct is used to query the PATCH endpoint
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PatchArg {
+    /// Key of JSON record
+    key: String,
+
+    /// Path within the JSON document, e.g. `$.status.cpu`
+    path: String,
+
+    /// New value of the path
+    value: serde_json::Value,
+}
+
+/// Struct is used to query the INCR and DECR endpoints
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CounterArg {
+    /// Key of counter
+    key: String,
+
+    /// Amount of change, default is 1
+    by: Option<i64>,
+}
+
+/// Struct is used to query the queue endpoints
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct QueueParm {
+    /// Key of queue
+    key: String,
+
+    /// End of queue: `front` or `back`, default is `front`
+    end: Option<String>,
+
+    /// Seconds to wait for an item if queue is empty
+    wait: Option<u64>,
+
+    /// Seconds while the popped item must be acknowledged, else it is returned to the queue
+    visibility: Option<u64>,
+}
+
+/// Struct is used to query the ACK endpoint
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AckArg {
+    /// Identifier of delivery
+    id: u64,
+}
+
+/// Item popped with visibility timeout
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Delivery {
+    /// Identifier of delivery, it is needed for the acknowledge
+    id: u64,
+
+    /// Popped value
+    value: String,
+}
+
+/// Struct is used to query the hash endpoints
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct HashParm {
+    /// Key of hash
+    key: String,
+
+    /// Name of field, without it every field is returned by GET
+    field: Option<String>,
+}
+
+/// Struct is used to set a field of hash
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct HashArg {
+    /// Key of hash
+    key: String,
+
+    /// Name of field
+    field: String,
+
+    /// Value of field
+    value: String,
+}
+
+/// Struct is used to query the set endpoints
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SetParm {
+    /// Key of set
+    key: String,
+
+    /// Member of set, without it every member is returned by GET
+    member: Option<String>,
+}
+
+/// Struct is used to add member to a set
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SetArg {
+    /// Key of set
+    key: String,
+
+    /// Member to be added
+    member: String,
+}
+
+/// Struct is used to query the GET endpoint
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GetParm {
+    /// Key for record
+    key: String,
+
+    /// Path within JSON document, e.g. `$.status.cpu`
+    path: Option<String>,
+}
+
 /// Struct is used to query the GET and LIST endpoint
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct KeyParm {
@@ -50,6 +212,16 @@ pub struct KeyParm {
     key: String,
 }
 
+/// Struct is used to query the FIND endpoint
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FindParm {
+    /// Records are searched under this prefix
+    prefix: String,
+
+    /// Value of records
+    value: String,
+}
+
 /// Struct is used to query the REMKEY and REMPATH endpoints
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DeleteParm {
@@ -60,6 +232,49 @@ pub struct DeleteParm {
     kind: Option<String>,
 }
 
+/// Struct is used to query the MOVE and COPY endpoints
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RelocateArg {
+    /// Key of source
+    key: String,
+
+    /// Key of destination
+    destination: String,
+
+    /// Kind of source: `record`, `queue`, `hash`, `set` or `table`, default is `record`
+    kind: Option<String>,
+}
+
+/// Struct is used to create database via the admin endpoint
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DatabaseArg {
+    /// Name of database, this is the root for each key
+    name: String,
+
+    /// Write the append file of database, default is `general.logging`
+    logging: Option<bool>,
+}
+
+/// Struct is used to query the EXPORT endpoint
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ExportParm {
+    /// Table that is exported with everything under it
+    key: String,
+
+    /// Format of snapshot: `json`, `toml` or `binary`, default is `json`
+    format: Option<String>,
+}
+
+/// Struct is used to query the IMPORT endpoint
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ImportParm {
+    /// Format of snapshot: `json`, `toml` or `binary`, default is `json`
+    format: Option<String>,
+
+    /// What happens with existing keys: `merge`, `overwrite` or `skip`, default is `merge`
+    policy: Option<String>,
+}
+
 /// Struct that is used to return with hook value
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Hook {
@@ -93,6 +308,185 @@ pub struct ExecParm {
     save: bool,
 }
 
+/// Struct is used to query the EVENTS endpoint
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EventParm {
+    /// Only changes under this prefix are sent
+    prefix: Option<String>,
+}
+
+/// Struct that is sent as data of an event
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EventData {
+    /// Key that has been changed
+    key: String,
+
+    /// New value, popped value or null in case of delete
+    value: Option<String>,
+}
+
+/// Struct that is sent back in the body if request has failed
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct ErrorBody {
+    /// Machine-readable code of the error
+    #[schema(value_type = String, example = "NOT_FOUND")]
+    code: ErrorCode,
+
+    /// Explanation of the error
+    message: String,
+}
+
+impl ErrorBody {
+    /// Create new error body
+    pub(super) fn new<T: std::fmt::Display>(code: ErrorCode, message: T) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Map the error code to HTTP status code
+pub(super) fn status_of_error(code: ErrorCode) -> StatusCode {
+    match code {
+        ErrorCode::NotFound => StatusCode::NOT_FOUND,
+        ErrorCode::TypeMismatch | ErrorCode::Conflict => StatusCode::CONFLICT,
+        ErrorCode::InvalidKey => StatusCode::BAD_REQUEST,
+        ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+        ErrorCode::LimitExceeded => StatusCode::INSUFFICIENT_STORAGE,
+        ErrorCode::ValidationFailed => StatusCode::UNPROCESSABLE_ENTITY,
+        ErrorCode::ScriptError | ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Change of a key that has been read from the append file
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct KeyChange {
+    /// When the change was received, in RFC 3339 format
+    time: String,
+
+    /// What happened: `set`, `remove`, `remove_path`, `push`, `push_front`, `pop`, `pop_back`,
+    /// `pop_reliable`, `hash_set`, `hash_delete`, `set_add`, `set_remove` or `import`
+    action: String,
+
+    /// Field of hash at `hash_set` and `hash_delete`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<String>,
+
+    /// Value that was written, member of set at `set_add` and `set_remove`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+
+    /// Interface where the change was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interface: Option<String>,
+
+    /// Address of the client
+    #[serde(skip_serializing_if = "Option::is_none")]
+    peer: Option<String>,
+
+    /// User that the client claimed, it is not verified
+    #[serde(skip_serializing_if = "Option::is_none")]
+    claimed_user: Option<String>,
+
+    /// Identifier of the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
+impl From<HistoryEntry> for KeyChange {
+    fn from(entry: HistoryEntry) -> Self {
+        let context = entry.context.unwrap_or_default();
+        Self {
+            time: format_time(entry.time.as_nanos()),
+            action: entry.action,
+            field: entry.field,
+            value: entry.value,
+            interface: Some(context.interface).filter(|x| !x.is_empty()),
+            peer: context.peer,
+            claimed_user: context.claimed_user,
+            request_id: context.request_id,
+        }
+    }
+}
+
+/// Metadata of a key or a table
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct KeyStat {
+    /// Full key, e.g. `/root/status`
+    key: String,
+
+    /// What the key is: `record`, `queue`, `hash`, `set` or `table`
+    kind: String,
+
+    /// Size of value in bytes, for tables the size of keys and values under it
+    size: usize,
+
+    /// Number of items in queue, fields in hash or members in set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    length: Option<usize>,
+
+    /// When the key was created, in RFC 3339 format, unknown for keys that were stored before start
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created: Option<String>,
+
+    /// When the key or anything under the table was changed, in RFC 3339 format
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modified: Option<String>,
+
+    /// Number of keys and tables directly in the table
+    #[serde(skip_serializing_if = "Option::is_none")]
+    children: Option<usize>,
+
+    /// Number of records under the table, recursively
+    #[serde(skip_serializing_if = "Option::is_none")]
+    records: Option<usize>,
+
+    /// Number of queues under the table, recursively
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queues: Option<usize>,
+
+    /// Number of hashes under the table, recursively
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hashes: Option<usize>,
+
+    /// Number of sets under the table, recursively
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sets: Option<usize>,
+}
+
+impl From<Stat> for KeyStat {
+    fn from(stat: Stat) -> Self {
+        let table = stat.table;
+        Self {
+            key: stat.key,
+            kind: stat.kind.to_string(),
+            size: stat.size,
+            length: stat.length,
+            created: stat.created.map(|x| format_time(x.as_nanos())),
+            modified: stat.modified.map(|x| format_time(x.as_nanos())),
+            children: table.as_ref().map(|x| x.children),
+            records: table.as_ref().map(|x| x.records),
+            queues: table.as_ref().map(|x| x.queues),
+            hashes: table.as_ref().map(|x| x.hashes),
+            sets: table.as_ref().map(|x| x.sets),
+        }
+    }
+}
+
+/// Convert the value of request to record. Without type the JSON value decides it,
+/// with type the value is parsed as text.
+pub(super) fn to_record(
+    value: serde_json::Value,
+    r#type: Option<String>,
+) -> Result<RecordValue, ErrorKind> {
+    match (r#type, value) {
+        (None, value) => Ok(RecordValue::from_json(value)),
+        (Some(r#type), serde_json::Value::String(text)) => RecordValue::parse(&r#type, &text),
+        (Some(r#type), value) => RecordValue::parse(&r#type, &value.to_string()),
+    }
+}
+
 /// Struct is used to query the SET endpoint
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ListKey {
@@ -108,41 +502,87 @@ pub struct ListKey {
 /// # Http parameters:
 /// - Endpoint: `GET /db`
 /// - Body: `none`
-/// - Query: `?key=_string_`
+/// - Query: `?key=_string_&path=_string_`
+///   - Path is optional, it selects a part of JSON document, e.g. `$.status.cpu`
 ///
 /// # Other parameters
 /// - `injected`: Axum state that share information among endpoints
 ///
 /// # Return codes
-/// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `OK`: Successfully done, value is in native JSON format
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 async fn get_key(
     State(injected): State<InjectedData>,
-    Query(parms): Query<KeyParm>,
+    Query(parms): Query<GetParm>,
+) -> impl IntoResponse {
+    // Reads are served by the handle without the datastore task
+    let response = injected
+        .data_sender
+        .get(parms.key, context::current())
+        .await;
+
+    match response {
+        Ok(value) => match value {
+            ValueType::RecordPointer(data) => match parms.path {
+                Some(path) => match data.get_path(&path) {
+                    Ok(value) => return_ok_with_value!(value),
+                    Err(e) => return_database_error!(e),
+                },
+                None => return_ok_with_value!(data.to_json()),
+            },
+            _ => return_client_error!(
+                ErrorCode::TypeMismatch,
+                "Pointer must be Record but it was Table"
+            ),
+        },
+        Err(e) => return_database_error!(e),
+    }
+}
+
+/// Endpoint to set value for a key, override value if already exist
+///
+/// # Http parameters:
+/// - Endpoint: `POST /db`
+/// - Body: `JSON { "key" : _string_, "value" : _any_, "type": _string_ }`
+///   - Type is optional, without it the JSON type of value is kept
+/// - Query: none
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn set_key(
+    State(injected): State<InjectedData>,
+    Json(pair): Json<TypedPair>,
 ) -> impl IntoResponse {
+    let value = match to_record(pair.value, pair.r#type) {
+        Ok(value) => value,
+        Err(e) => return_database_error!(e),
+    };
+
     let (tx, mut rx) = channel(10);
-    let get_action = DatabaseAction::Get(tx, parms.key);
+    let set_action = DatabaseAction::SetTyped(tx, pair.key, value);
 
-    send_data_request!(get_action, injected.data_sender);
+    send_data_request!(set_action, injected.data_sender);
 
     match rx.recv().await {
         Some(response) => match response {
-            Ok(value) => match value {
-                ValueType::RecordPointer(data) => return_ok_with_value!(data),
-                _ => return_server_error!("Pointer must be Record but it was Table"),
-            },
-            Err(e) => return_client_error!(e.to_string()),
+            Ok(_) => return_ok!(),
+            Err(e) => return_database_error!(e),
         },
         None => return_server_error!("failed to get response from server"),
     }
 }
 
-/// Endpoint to set value for a key, override value if already exist
+/// Endpoint to replace a part of JSON record
 ///
 /// # Http parameters:
-/// - Endpoint: `POST /db`
-/// - Body: `JSON { "key" : _string_, "value" : _string_ }`
+/// - Endpoint: `PATCH /db`
+/// - Body: `JSON { "key" : _string_, "path" : _string_, "value" : _any_ }`
 /// - Query: none
 ///
 /// # Other parameters
@@ -150,21 +590,21 @@ async fn get_key(
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
-async fn set_key(
+async fn patch_key(
     State(injected): State<InjectedData>,
-    Json(pair): Json<Pair>,
+    Json(arg): Json<PatchArg>,
 ) -> impl IntoResponse {
     let (tx, mut rx) = channel(10);
-    let set_action = DatabaseAction::Set(tx, pair.key.clone(), pair.value);
+    let action = DatabaseAction::Patch(tx, arg.key, arg.path, arg.value);
 
-    send_data_request!(set_action, injected.data_sender);
+    send_data_request!(action, injected.data_sender);
 
     match rx.recv().await {
         Some(response) => match response {
             Ok(_) => return_ok!(),
-            Err(e) => return_client_error!(e.to_string()),
+            Err(e) => return_database_error!(e),
         },
         None => return_server_error!("failed to get response from server"),
     }
@@ -183,7 +623,7 @@ async fn set_key(
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 async fn delete_key(
     State(injected): State<InjectedData>,
@@ -195,10 +635,10 @@ async fn delete_key(
         Some(kind) => match kind.as_str() {
             "record" => DatabaseAction::DeleteKey(tx, parms.key),
             "path" => DatabaseAction::DeleteTable(tx, parms.key),
-            _ => return_client_error!(format!(
-                "Only record or path can be delete but not {}",
-                kind
-            )),
+            _ => return_client_error!(
+                ErrorCode::InvalidKey,
+                format!("Only record or path can be delete but not {}", kind)
+            ),
         },
         None => DatabaseAction::DeleteKey(tx, parms.key),
     };
@@ -208,7 +648,7 @@ async fn delete_key(
     match rx.recv().await {
         Some(response) => match response {
             Ok(_) => return_ok!(),
-            Err(e) => return_client_error!(e.to_string()),
+            Err(e) => return_database_error!(e),
         },
         None => return_server_error!("failed to get response from server"),
     }
@@ -226,31 +666,62 @@ async fn delete_key(
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 async fn list_keys(
     State(injected): State<InjectedData>,
     Query(parms): Query<KeyParm>,
 ) -> impl IntoResponse {
-    let (tx, mut rx) = channel(10);
-    let list_action = DatabaseAction::ListKeys(
-        tx,
-        parms.key,
-        onlyati_datastore::datastore::enums::ListType::All,
-    );
+    let response = injected
+        .data_sender
+        .list_keys(
+            parms.key,
+            onlyati_datastore::datastore::enums::ListType::All,
+            context::current(),
+        )
+        .await;
+
+    match response {
+        Ok(list) => return_ok_with_value!(list
+            .iter()
+            .map(|x| ListKey {
+                r#type: x.get_type().to_string(),
+                key: x.get_key().to_string()
+            })
+            .collect::<Vec<ListKey>>()),
+        Err(e) => return_database_error!(e),
+    }
+}
 
-    send_data_request!(list_action, injected.data_sender);
+/// Endpoint to find the records under a prefix with a value, prefix must have value index
+///
+/// # Http parameters:
+/// - Endpoint: `GET /db_find`
+/// - Body: `none`
+/// - Query: `?prefix=_string_&value=_string_`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done, keys are in the body as Json string array
+/// - `BAD_REQUEST`, `NOT_FOUND`: Request has failed, e.g. no index for prefix, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn find_keys(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<FindParm>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Find(tx, parms.prefix, parms.value);
+    send_data_request!(action, injected.data_sender);
 
     match rx.recv().await {
         Some(response) => match response {
             Ok(list) => return_ok_with_value!(list
                 .iter()
-                .map(|x| ListKey {
-                    r#type: x.get_type().to_string(),
-                    key: x.get_key().to_string()
-                })
-                .collect::<Vec<ListKey>>()),
-            Err(e) => return_client_error!(e.to_string()),
+                .map(|x| x.get_key().to_string())
+                .collect::<Vec<String>>()),
+            Err(e) => return_database_error!(e),
         },
         None => return_server_error!("failed to get response from server"),
     }
@@ -268,7 +739,7 @@ async fn list_keys(
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 async fn trigger(
     State(injected): State<InjectedData>,
@@ -282,7 +753,7 @@ async fn trigger(
     match rx.recv().await {
         Some(response) => match response {
             Ok(_) => return_ok!(),
-            Err(e) => return_client_error!(e.to_string()),
+            Err(e) => return_database_error!(e),
         },
         None => return_server_error!("failed to get response from server"),
     }
@@ -300,7 +771,7 @@ async fn trigger(
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 async fn set_hook(
     State(injected): State<InjectedData>,
@@ -313,7 +784,7 @@ async fn set_hook(
     match rx.recv().await {
         Some(response) => match response {
             Ok(_) => return_ok!(),
-            Err(e) => return_client_error!(e.to_string()),
+            Err(e) => return_database_error!(e),
         },
         None => return_server_error!("failed to get response from server"),
     }
@@ -331,7 +802,7 @@ async fn set_hook(
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 async fn get_hook(
     State(injected): State<InjectedData>,
@@ -346,7 +817,7 @@ async fn get_hook(
             Ok((prefix, links)) => {
                 return_ok_with_value!(Hook { prefix, links });
             }
-            Err(e) => return_client_error!(e.to_string()),
+            Err(e) => return_database_error!(e),
         },
         None => return_server_error!("failed to get response from server"),
     }
@@ -364,7 +835,7 @@ async fn get_hook(
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 async fn delete_hook(
     State(injected): State<InjectedData>,
@@ -377,7 +848,7 @@ async fn delete_hook(
     match rx.recv().await {
         Some(response) => match response {
             Ok(_) => return_ok!(),
-            Err(e) => return_client_error!(e.to_string()),
+            Err(e) => return_database_error!(e),
         },
         None => return_server_error!("failed to get response from server"),
     }
@@ -395,7 +866,7 @@ async fn delete_hook(
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 async fn list_hooks(
     State(injected): State<InjectedData>,
@@ -416,7 +887,7 @@ async fn list_hooks(
 
                 return_ok_with_value!(collection);
             }
-            Err(e) => return_client_error!(e.to_string()),
+            Err(e) => return_database_error!(e),
         },
         None => return_server_error!("failed to get response from server"),
     }
@@ -434,7 +905,7 @@ async fn list_hooks(
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 async fn suspend_log(State(injected): State<InjectedData>) -> impl IntoResponse {
     let (tx, mut rx) = channel(10);
@@ -445,7 +916,7 @@ async fn suspend_log(State(injected): State<InjectedData>) -> impl IntoResponse
     match rx.recv().await {
         Some(response) => match response {
             Ok(_) => return_ok!(),
-            Err(e) => return_client_error!(e.to_string()),
+            Err(e) => return_database_error!(e),
         },
         None => return_server_error!("failed to get response from server"),
     }
@@ -463,7 +934,7 @@ async fn suspend_log(State(injected): State<InjectedData>) -> impl IntoResponse
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 async fn resume_log(State(injected): State<InjectedData>) -> impl IntoResponse {
     let (tx, mut rx) = channel(10);
@@ -474,7 +945,7 @@ async fn resume_log(State(injected): State<InjectedData>) -> impl IntoResponse {
     match rx.recv().await {
         Some(response) => match response {
             Ok(_) => return_ok!(),
-            Err(e) => return_client_error!(e.to_string()),
+            Err(e) => return_database_error!(e),
         },
         None => return_server_error!("failed to get response from server"),
     }
@@ -493,7 +964,7 @@ async fn resume_log(State(injected): State<InjectedData>) -> impl IntoResponse {
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 async fn exec_script(
     State(injected): State<InjectedData>,
@@ -509,8 +980,11 @@ async fn exec_script(
     let old_pair = match rx.recv().await {
         Some(response) => match response {
             Ok(value) => match value {
-                ValueType::RecordPointer(data) => Some((arg.key.clone(), data)),
-                _ => return_server_error!("Pointer must be Record but it was Table"),
+                ValueType::RecordPointer(data) => Some((arg.key.clone(), data.to_string())),
+                _ => return_client_error!(
+                    ErrorCode::TypeMismatch,
+                    "Pointer must be Record but it was Table"
+                ),
             },
             Err(_) => None,
         },
@@ -523,9 +997,11 @@ async fn exec_script(
         match &config.scripts {
             Some(scr) => match scr.execs.contains(&exec.exec) {
                 true => scr.clone(),
-                false => return_client_error!("requested script is not defined"),
+                false => {
+                    return_client_error!(ErrorCode::InvalidKey, "requested script is not defined")
+                }
             },
-            None => return_client_error!("requested script is not defined"),
+            None => return_client_error!(ErrorCode::InvalidKey, "requested script is not defined"),
         }
     };
 
@@ -537,7 +1013,10 @@ async fn exec_script(
             .await
         {
             Ok(modified_pair) => modified_pair,
-            Err(e) => return_server_error!(format!("error during script exection: {}", e)),
+            Err(e) => {
+                tracing::error!("error during script exection: {}", e);
+                return_client_error!(ErrorCode::ScriptError, "failed to execute script")
+            }
         };
 
     // Make a SET action for the modified pair
@@ -551,7 +1030,7 @@ async fn exec_script(
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => return_ok!(),
-                    Err(e) => return_client_error!(e.to_string()),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to get response from server"),
             }
@@ -563,7 +1042,7 @@ async fn exec_script(
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => return_ok!(),
-                    Err(e) => return_client_error!(e.to_string()),
+                    Err(e) => return_database_error!(e),
                 },
                 None => return_server_error!("failed to get response from server"),
             }
@@ -578,12 +1057,15 @@ async fn exec_script(
         match rx.recv().await {
             Some(response) => match response {
                 Ok(_) => return_ok!(),
-                Err(e) => return_client_error!(e.to_string()),
+                Err(e) => return_database_error!(e),
             },
             None => return_server_error!("failed to get response from server"),
         }
     } else {
-        return_client_error!("After script was run, the new value is empty");
+        return_client_error!(
+            ErrorCode::ScriptError,
+            "After script was run, the new value is empty"
+        );
     }
 }
 
@@ -596,12 +1078,77 @@ async fn exec_script(
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 pub async fn health_check() -> impl IntoResponse {
     return_ok!();
 }
 
+/// Endpoint to increment a counter, counter is created if it does not exist
+///
+/// # Http parameters:
+/// - Endpoint: `POST /db_incr`
+/// - Body: `JSON { "key" : _string_, "by" : _integer_ }`
+///   - By is optional, default is 1
+/// - Query: `none`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done, new value is in the body
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn increment(
+    State(injected): State<InjectedData>,
+    Json(arg): Json<CounterArg>,
+) -> impl IntoResponse {
+    change_counter(injected, arg.key, arg.by.unwrap_or(1)).await
+}
+
+/// Endpoint to decrement a counter, counter is created if it does not exist
+///
+/// # Http parameters:
+/// - Endpoint: `POST /db_decr`
+/// - Body: `JSON { "key" : _string_, "by" : _integer_ }`
+///   - By is optional, default is 1
+/// - Query: `none`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done, new value is in the body
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn decrement(
+    State(injected): State<InjectedData>,
+    Json(arg): Json<CounterArg>,
+) -> impl IntoResponse {
+    let by = match arg.by.unwrap_or(1).checked_neg() {
+        Some(by) => by,
+        None => return_client_error!(ErrorCode::InvalidKey, "Amount is out of range"),
+    };
+    change_counter(injected, arg.key, by).await
+}
+
+/// Add a number to a counter then return with the new value
+async fn change_counter(injected: InjectedData, key: String, by: i64) -> axum::response::Response {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Increment(tx, key, by);
+
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(ValueType::RecordPointer(value)) => return_ok_with_value!(value.to_json()),
+            Ok(_) => return_server_error!("counter is not a record"),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
 /// Endpoint to push item into a queue
 ///
 /// # Http parameters:
@@ -614,7 +1161,7 @@ pub async fn health_check() -> impl IntoResponse {
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 async fn push(State(injected): State<InjectedData>, Json(pair): Json<Pair>) -> impl IntoResponse {
     let (tx, mut rx) = channel(10);
@@ -625,7 +1172,7 @@ async fn push(State(injected): State<InjectedData>, Json(pair): Json<Pair>) -> i
     match rx.recv().await {
         Some(response) => match response {
             Ok(_) => return_ok!(),
-            Err(e) => return_client_error!(e.to_string()),
+            Err(e) => return_database_error!(e),
         },
         None => return_server_error!("failed to get response from server"),
     }
@@ -636,35 +1183,687 @@ async fn push(State(injected): State<InjectedData>, Json(pair): Json<Pair>) -> i
 /// # Http parameters:
 /// - Endpoint: `GET /queue`
 /// - Body: `none`
-/// - Query: `?key=_string_`
+/// - Query: `?key=_string_&end=_string_&wait=_integer_&visibility=_integer_`
+///   - End is `front` or `back`, default is `front`
+///   - Wait is optional, seconds to wait for an item if queue is empty
+///   - Visibility is optional, item must be acknowledged via `POST /queue_ack` within that seconds
+///     else it is returned to the queue, then response is `JSON { "id" : _integer_, "value" : _string_ }`
 ///
 /// # Other parameters
 /// - `injected`: Axum state that share information among endpoints
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 async fn pop(
     State(injected): State<InjectedData>,
-    Query(parms): Query<KeyParm>,
+    Query(parms): Query<QueueParm>,
 ) -> impl IntoResponse {
-    let (tx, mut rx) = channel(10);
-    let get_action = DatabaseAction::Pop(tx, parms.key);
+    let end = match parms.end.as_deref().map(str::parse).transpose() {
+        Ok(end) => end.unwrap_or(QueueEnd::Front),
+        Err(e) => return_database_error!(e),
+    };
 
-    send_data_request!(get_action, injected.data_sender);
+    if let Some(visibility) = parms.visibility {
+        if end != QueueEnd::Front {
+            return_client_error!(
+                ErrorCode::InvalidKey,
+                "Visibility timeout is supported only at the front"
+            );
+        }
 
-    match rx.recv().await {
-        Some(response) => match response {
-            Ok(value) => match value {
-                ValueType::RecordPointer(data) => return_ok_with_value!(data),
-                _ => return_server_error!("Pointer must be Record but it was Table"),
-            },
-            Err(e) => return_client_error!(e.to_string()),
-        },
-        None => return_server_error!("failed to get response from server"),
-    }
-}
+        let visibility = match queue_timeout(visibility) {
+            Ok(visibility) => visibility,
+            Err(e) => return_database_error!(e),
+        };
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::PopReliable(tx, parms.key, visibility);
+
+        send_data_request!(action, injected.data_sender);
+
+        match rx.recv().await {
+            Some(response) => match response {
+                Ok((id, value)) => return_ok_with_value!(Delivery { id, value }),
+                Err(e) => return_database_error!(e),
+            },
+            None => return_server_error!("failed to get response from server"),
+        }
+    }
+
+    let wait = match parms.wait.map(queue_timeout).transpose() {
+        Ok(wait) => wait,
+        Err(e) => return_database_error!(e),
+    };
+
+    let (tx, mut rx) = channel(10);
+    let get_action = match wait {
+        Some(wait) => DatabaseAction::PopWait(tx, parms.key, end, wait),
+        None => DatabaseAction::PopFrom(tx, parms.key, end),
+    };
+
+    send_data_request!(get_action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(value) => match value {
+                ValueType::RecordPointer(data) => return_ok_with_value!(data.to_json()),
+                _ => return_client_error!(
+                    ErrorCode::TypeMismatch,
+                    "Pointer must be Record but it was Table"
+                ),
+            },
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to read item of a queue without removing it
+///
+/// # Http parameters:
+/// - Endpoint: `GET /queue_peek`
+/// - Body: `none`
+/// - Query: `?key=_string_&end=_string_`
+///   - End is `front` or `back`, default is `front`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn peek(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<QueueParm>,
+) -> impl IntoResponse {
+    let end = match parms.end.as_deref().map(str::parse).transpose() {
+        Ok(end) => end.unwrap_or(QueueEnd::Front),
+        Err(e) => return_database_error!(e),
+    };
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Peek(tx, parms.key, end);
+
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(ValueType::RecordPointer(data)) => return_ok_with_value!(data.to_json()),
+            Ok(_) => return_server_error!("queue item is not a record"),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to get the number of items in a queue
+///
+/// # Http parameters:
+/// - Endpoint: `GET /queue_len`
+/// - Body: `none`
+/// - Query: `?key=_string_`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done, length is in the body
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn queue_length(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<KeyParm>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::QueueLength(tx, parms.key);
+
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(length) => return_ok_with_value!(length),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to acknowledge an item that was popped with visibility timeout
+///
+/// # Http parameters:
+/// - Endpoint: `POST /queue_ack`
+/// - Body: `JSON { "id" : _integer_ }`
+/// - Query: `none`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn ack(State(injected): State<InjectedData>, Json(arg): Json<AckArg>) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Ack(tx, arg.id);
+
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(_) => return_ok!(),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to get field of a hash or the whole hash
+///
+/// # Http parameters:
+/// - Endpoint: `GET /hash`
+/// - Body: `none`
+/// - Query: `?key=_string_&field=_string_`
+///   - Without field, every field is returned as Json object
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn hash_get(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<HashParm>,
+) -> impl IntoResponse {
+    if let Some(field) = parms.field {
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::HashGet(tx, parms.key, field);
+        send_data_request!(action, injected.data_sender);
+
+        match rx.recv().await {
+            Some(response) => match response {
+                Ok(value) => return_ok_with_value!(value.get_value()),
+                Err(e) => return_database_error!(e),
+            },
+            None => return_server_error!("failed to get response from server"),
+        }
+    }
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::HashGetAll(tx, parms.key);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(hash) => return_ok_with_value!(hash),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to set field of a hash, hash is created if it does not exist
+///
+/// # Http parameters:
+/// - Endpoint: `POST /hash`
+/// - Body: `JSON { "key" : _string_, "field" : _string_, "value" : _string_ }`
+/// - Query: `none`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn hash_set(
+    State(injected): State<InjectedData>,
+    Json(arg): Json<HashArg>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::HashSet(tx, arg.key, arg.field, arg.value);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(_) => return_ok!(),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to remove field of a hash
+///
+/// # Http parameters:
+/// - Endpoint: `DELETE /hash`
+/// - Body: `none`
+/// - Query: `?key=_string_&field=_string_`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn hash_delete(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<HashParm>,
+) -> impl IntoResponse {
+    let field = match parms.field {
+        Some(field) => field,
+        None => return_client_error!(ErrorCode::InvalidKey, "Field is missing"),
+    };
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::HashDelete(tx, parms.key, field);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(_) => return_ok!(),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to check membership or get every member of a set
+///
+/// # Http parameters:
+/// - Endpoint: `GET /set`
+/// - Body: `none`
+/// - Query: `?key=_string_&member=_string_`
+///   - With member, Json boolean is returned, else Json array of members
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn set_get(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<SetParm>,
+) -> impl IntoResponse {
+    if let Some(member) = parms.member {
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::SetIsMember(tx, parms.key, member);
+        send_data_request!(action, injected.data_sender);
+
+        match rx.recv().await {
+            Some(response) => match response {
+                Ok(flag) => return_ok_with_value!(flag),
+                Err(e) => return_database_error!(e),
+            },
+            None => return_server_error!("failed to get response from server"),
+        }
+    }
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::SetMembers(tx, parms.key);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(members) => return_ok_with_value!(members),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to add member to a set, set is created if it does not exist
+///
+/// # Http parameters:
+/// - Endpoint: `POST /set`
+/// - Body: `JSON { "key" : _string_, "member" : _string_ }`
+/// - Query: `none`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done, body tells that member was new
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn set_add(
+    State(injected): State<InjectedData>,
+    Json(arg): Json<SetArg>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::SetAdd(tx, arg.key, arg.member);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(added) => return_ok_with_value!(added),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to remove member from a set
+///
+/// # Http parameters:
+/// - Endpoint: `DELETE /set`
+/// - Body: `none`
+/// - Query: `?key=_string_&member=_string_`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done, body tells that it was a member
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn set_remove(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<SetParm>,
+) -> impl IntoResponse {
+    let member = match parms.member {
+        Some(member) => member,
+        None => return_client_error!(ErrorCode::InvalidKey, "Member is missing"),
+    };
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::SetRemove(tx, parms.key, member);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(removed) => return_ok_with_value!(removed),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to export a table with everything under it and the hooks of its prefix
+///
+/// # Http parameters:
+/// - Endpoint: `GET /export`
+/// - Body: `none`
+/// - Query: `?key=_string_&format=_json|toml|binary_`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done, snapshot is in the body
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn export(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<ExportParm>,
+) -> impl IntoResponse {
+    let format = match parms
+        .format
+        .as_deref()
+        .unwrap_or("json")
+        .parse::<SnapshotFormat>()
+    {
+        Ok(format) => format,
+        Err(e) => return_database_error!(e),
+    };
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Export(tx, parms.key);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(snapshot) => match snapshot.encode(format) {
+                Ok(data) => (
+                    StatusCode::OK,
+                    [(header::CONTENT_TYPE, format.content_type())],
+                    data,
+                )
+                    .into_response(),
+                Err(e) => return_server_error!(e),
+            },
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to read the past changes of a key from the append file
+///
+/// # Http parameters:
+/// - Endpoint: `GET /history`
+/// - Body: `none`
+/// - Query: `?key=_string_`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done, changes are in the body in the order of their time
+/// - `BAD_REQUEST`: Invalid key, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Logging is disabled or something issue happened on server
+async fn key_history(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<KeyParm>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::History(tx, parms.key);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(history) => return_ok_with_value!(history
+                .into_iter()
+                .map(KeyChange::from)
+                .collect::<Vec<KeyChange>>()),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to get the metadata of a key or a table
+///
+/// # Http parameters:
+/// - Endpoint: `GET /stat`
+/// - Body: `none`
+/// - Query: `?key=_string_`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done, every record, queue, hash, set and table with the key is in the body
+/// - `BAD_REQUEST`, `NOT_FOUND`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn stat(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<KeyParm>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Stat(tx, parms.key);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(stats) => return_ok_with_value!(stats
+                .into_iter()
+                .map(KeyStat::from)
+                .collect::<Vec<KeyStat>>()),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to move a record, queue, hash, set or whole table to another key
+///
+/// # Http parameters:
+/// - Endpoint: `POST /move`
+/// - Body: `JSON { "key" : _string_, "destination" : _string_, "kind" : _string_ }`
+///   - Kind is optional and it can be `record`, `queue`, `hash`, `set` or `table`, default is `record`
+/// - Query: `none`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INSUFFICIENT_STORAGE`: Destination would exceed a limit of database
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn move_key(
+    State(injected): State<InjectedData>,
+    Json(arg): Json<RelocateArg>,
+) -> impl IntoResponse {
+    relocate(injected, arg, true).await
+}
+
+/// Endpoint to copy a record, queue, hash, set or whole table to another key
+///
+/// # Http parameters:
+/// - Endpoint: `POST /copy`
+/// - Body: `JSON { "key" : _string_, "destination" : _string_, "kind" : _string_ }`
+///   - Kind is optional and it can be `record`, `queue`, `hash`, `set` or `table`, default is `record`
+/// - Query: `none`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INSUFFICIENT_STORAGE`: Destination would exceed a limit of database
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn copy_key(
+    State(injected): State<InjectedData>,
+    Json(arg): Json<RelocateArg>,
+) -> impl IntoResponse {
+    relocate(injected, arg, false).await
+}
+
+/// Move or copy a key then return with empty response
+async fn relocate(
+    injected: InjectedData,
+    arg: RelocateArg,
+    remove: bool,
+) -> axum::response::Response {
+    let kind = arg.kind.unwrap_or_else(|| "record".to_string());
+    let key = match KeyType::from_kind(&kind, arg.key) {
+        Ok(key) => key,
+        Err(e) => return_database_error!(e),
+    };
+
+    let (tx, mut rx) = channel(10);
+    let action = match remove {
+        true => DatabaseAction::Move(tx, key, arg.destination),
+        false => DatabaseAction::Copy(tx, key, arg.destination),
+    };
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(_) => return_ok!(),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to get the limits and current usage of a database
+///
+/// # Http parameters:
+/// - Endpoint: `GET /usage`
+/// - Body: `none`
+/// - Query: `?key=_string_`, key is the root of database, e.g. `/root`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done, limits and usage are in the body
+/// - `BAD_REQUEST`: Invalid key, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn usage(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<KeyParm>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Usage(tx, parms.key);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(report) => return_ok_with_value!(report),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Endpoint to import a snapshot that was made by export or written like the initial file
+///
+/// # Http parameters:
+/// - Endpoint: `POST /import`
+/// - Body: `snapshot in the specified format`
+/// - Query: `?format=_json|toml|binary_&policy=_merge|overwrite|skip_`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done, number of imported and skipped items are returned
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn import(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<ImportParm>,
+    body: bytes::Bytes,
+) -> impl IntoResponse {
+    let format = match parms
+        .format
+        .as_deref()
+        .unwrap_or("json")
+        .parse::<SnapshotFormat>()
+    {
+        Ok(format) => format,
+        Err(e) => return_database_error!(e),
+    };
+
+    let policy = match parms
+        .policy
+        .as_deref()
+        .unwrap_or("merge")
+        .parse::<ImportPolicy>()
+    {
+        Ok(policy) => policy,
+        Err(e) => return_database_error!(e),
+    };
+
+    let snapshot = match Snapshot::decode(&body, format) {
+        Ok(snapshot) => snapshot,
+        Err(e) => return_database_error!(e),
+    };
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Import(tx, snapshot, policy);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(summary) => return_ok_with_value!(summary),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
 
 /// Endpoint to receive gitea hooks
 ///
@@ -678,7 +1877,7 @@ async fn pop(
 ///
 /// # Return codes
 /// - `OK`: Successfully done
-/// - `BAD_REQUEST`: Something was specified badly in the request
+/// - `BAD_REQUEST`, `FORBIDDEN`, `NOT_FOUND`, `CONFLICT`: Request has failed, reason is in `ErrorBody`
 /// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
 pub async fn gitea(
     State(injected): State<InjectedData>,
@@ -691,17 +1890,17 @@ pub async fn gitea(
         match &config.gitea {
             Some(gitea) => {
                 if !gitea.enable {
-                    return_client_error!("gitea plugin is not enabled")
+                    return_client_error!(ErrorCode::Forbidden, "gitea plugin is not enabled")
                 }
                 match &config.scripts {
                     Some(scr) => (
                         format!("{}/{}", scr.exec_path, gitea.script),
                         gitea.key_base.clone(),
                     ),
-                    None => return_client_error!("no script path is specified"),
+                    None => return_server_error!("no script path is specified"),
                 }
             }
-            None => return_client_error!("gitea plugin is not enabled"),
+            None => return_client_error!(ErrorCode::Forbidden, "gitea plugin is not enabled"),
         }
     };
 
@@ -726,7 +1925,10 @@ pub async fn gitea(
     let (key, value) =
         match crate::server::utilities::lua::run_lua_for_gitea(script, message, prefix).await {
             Ok((key, value)) => (key, value),
-            Err(e) => return_server_error!(e),
+            Err(e) => {
+                tracing::error!("error during script exection: {}", e);
+                return_client_error!(ErrorCode::ScriptError, "failed to execute script")
+            }
         };
 
     tracing::debug!("save gitea data onto {} key", key);
@@ -746,30 +1948,289 @@ pub async fn gitea(
     match rx.recv().await {
         Some(response) => match response {
             Ok(_) => return_ok!(),
-            Err(e) => return_client_error!(e.to_string()),
+            Err(e) => return_database_error!(e),
         },
         None => return_server_error!("failed to get response from server"),
     }
 }
 
+/// Convert database event to Server-Sent Event
+fn to_sse_event(event: Event) -> SseEvent {
+    let data = EventData {
+        key: event.key,
+        value: event.value,
+    };
+
+    SseEvent::default()
+        .id(event.id.to_string())
+        .event(event.kind.to_string())
+        .json_data(data)
+        .unwrap_or_default()
+}
+
+/// Collect the last events of database, so clients can resume from them
+///
+/// # Parameters
+/// - `data_sender`: Sender that send data to database thread
+/// - `history`: Collection of last events
+async fn record_history(data_sender: DatastoreRouter, history: Arc<RwLock<EventHistory>>) {
+    let (tx, mut rx) = channel(10);
+    if let Err(e) = data_sender.send(DatabaseAction::Watch(tx)).await {
+        tracing::error!("failed to send request to database: {}", e);
+        return;
+    }
+
+    let mut events = match rx.recv().await {
+        Some(Ok(events)) => events,
+        _ => {
+            tracing::error!("failed to subscribe to database events");
+            return;
+        }
+    };
+
+    loop {
+        match events.recv().await {
+            Ok(event) => {
+                let mut history = history.write().await;
+                if history.events.len() >= EVENT_HISTORY_SIZE {
+                    history.events.pop_front();
+                }
+                history.events.push_back(event.clone());
+                // Nobody may listen, then the event is dropped
+                let _ = history.live.send(event);
+            }
+            Err(RecvError::Lagged(count)) => {
+                tracing::warn!("event history has lost {} events", count)
+            }
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+/// Endpoint to stream changes as Server-Sent Events
+///
+/// # Http parameters:
+/// - Endpoint: `GET /events`
+/// - Body: `none`
+/// - Query: `?prefix=_string_`
+/// - Header: `Last-Event-ID` is optional, events after it are sent from the history
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done, event stream is started
+async fn events(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<EventParm>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let prefix = parms.prefix.unwrap_or("/".to_string());
+    let last_id = headers
+        .get("last-event-id")
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.parse::<u64>().ok());
+
+    // Subscribe and read the history under the same lock, so no event is lost or sent twice between them.
+    // Collect the missed events. If the ID is not known, e.g. server has been restarted since, then it is ignored.
+    let (mut receiver, last_id, missed) = {
+        let history = injected.history.read().await;
+        let receiver = history.live.subscribe();
+        match last_id {
+            Some(id) if history.events.back().map(|x| x.id >= id).unwrap_or(false) => (
+                receiver,
+                id,
+                history
+                    .events
+                    .iter()
+                    .filter(|x| x.id > id && x.match_prefix(&prefix))
+                    .cloned()
+                    .collect::<Vec<Event>>(),
+            ),
+            _ => (receiver, 0, Vec::new()),
+        }
+    };
+
+    let (tx, rx) = channel::<Result<SseEvent, std::convert::Infallible>>(100);
+    tokio::spawn(async move {
+        let mut last_sent = last_id;
+
+        for event in missed {
+            last_sent = event.id;
+            if tx.send(Ok(to_sse_event(event))).await.is_err() {
+                return;
+            }
+        }
+
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(count)) => {
+                    tracing::warn!("event stream of '{}' has lost {} events", prefix, count);
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            };
+
+            if event.id <= last_sent || !event.match_prefix(&prefix) {
+                continue;
+            }
+
+            last_sent = event.id;
+            if tx.send(Ok(to_sse_event(event))).await.is_err() {
+                tracing::trace!("event stream of '{}' is closed", prefix);
+                return;
+            }
+        }
+    });
+
+    Sse::new(ReceiverStream::new(rx))
+        .keep_alive(
+            KeepAlive::new()
+                .interval(std::time::Duration::from_secs(EVENT_HEARTBEAT_SECONDS))
+                .text("heartbeat"),
+        )
+        .into_response()
+}
+
+/// Endpoint to list the databases that run in the server
+///
+/// # Http parameters:
+/// - Endpoint: `GET /admin/databases`
+/// - Body: `none`
+/// - Query: `none`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done, names of databases are in the body
+async fn list_databases(State(injected): State<InjectedData>) -> impl IntoResponse {
+    return_ok_with_value!(injected.data_sender.names());
+}
+
+/// Endpoint to create a database at runtime. If it has files from earlier, they are read during start.
+/// Database exists until restart unless it is declared in the config file too.
+///
+/// # Http parameters:
+/// - Endpoint: `POST /admin/databases`
+/// - Body: `JSON { "name" : _string_, "logging" : _bool_ }`
+///   - Logging is optional, default is `general.logging`
+/// - Query: `none`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `CREATED`: Successfully done
+/// - `BAD_REQUEST`: Invalid name
+/// - `CONFLICT`: Database already exists
+/// - `INTERNAL_SERVER_ERROR`: Logging is requested without logger config, or the database could not be started
+async fn create_database(
+    State(injected): State<InjectedData>,
+    Json(arg): Json<DatabaseArg>,
+) -> impl IntoResponse {
+    if let Err(e) = crate::server::utilities::config_parse::validate_database_name(&arg.name) {
+        return_client_error!(ErrorCode::InvalidKey, e);
+    }
+
+    if injected.data_sender.handle(&arg.name).is_some() {
+        return_database_error!(ErrorKind::Conflict(format!(
+            "database '{}' already exists",
+            arg.name
+        )));
+    }
+
+    let config = injected.config.read().await.clone();
+    let logging = arg.logging.unwrap_or(config.general.logging);
+    if logging && config.logger.is_none() {
+        return_client_error!(ErrorCode::Internal, "no logger location defined in config");
+    }
+
+    match crate::server::utilities::database::start_database(&config, &arg.name, logging, None)
+        .await
+    {
+        Ok((handle, tasks)) => match injected.data_sender.insert(arg.name.clone(), handle) {
+            Ok(_) => {
+                injected.databases.insert(arg.name.clone(), tasks);
+                tracing::info!("database '{}' is created", arg.name);
+                return_ok!(StatusCode::CREATED)
+            }
+            Err(e) => return_database_error!(e),
+        },
+        Err(e) => return_server_error!(e),
+    }
+}
+
+/// Endpoint to drop a database at runtime, its files are kept
+///
+/// # Http parameters:
+/// - Endpoint: `DELETE /admin/databases/{name}`
+/// - Body: `none`
+/// - Query: `none`
+///
+/// # Other parameters
+/// - `injected`: Axum state that share information among endpoints
+///
+/// # Return codes
+/// - `OK`: Successfully done
+/// - `FORBIDDEN`: Default database cannot be dropped
+/// - `NOT_FOUND`: Database does not exist
+/// - `INTERNAL_SERVER_ERROR`: Something issue happened on server
+async fn drop_database(
+    State(injected): State<InjectedData>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    let handle = match injected.data_sender.remove(&name) {
+        Ok(handle) => handle,
+        Err(e) => return_database_error!(e),
+    };
+
+    // Database stops when the handle is dropped, it is not a failure
+    injected.databases.remove(&name);
+
+    // Pending changes are written before the last handle is dropped and the database stops
+    let (tx, mut rx) = channel(10);
+    if handle.send(DatabaseAction::Flush(tx)).await.is_ok() {
+        if let Some(Err(e)) = rx.recv().await {
+            return_database_error!(e);
+        }
+    }
+
+    tracing::info!("database '{}' is dropped", name);
+    return_ok!();
+}
+
 /// Start the REST server
 ///
 /// # Parameters
 /// - `data_sender`: Sender that send data to database thread
+/// - `databases`: Tasks of databases that are added besides the default one
 /// - `address`: Host address where interface bind and listen
 /// - `config`: Configuration of the application
 pub async fn run_async(
-    data_sender: Arc<Mutex<Sender<DatabaseAction>>>,
+    data_sender: DatastoreRouter,
+    databases: Databases,
     address: String,
     config: Arc<RwLock<Config>>,
 ) {
     tracing::info!("REST interface on {} is starting...", address);
 
+    let history = Arc::new(RwLock::new(EventHistory {
+        events: VecDeque::with_capacity(EVENT_HISTORY_SIZE),
+        live: broadcast::channel(EVENT_HISTORY_SIZE).0,
+    }));
+    tokio::spawn(record_history(data_sender.clone(), history.clone()));
+
     let app = Router::new()
         .route("/db", post(set_key))
         .route("/db", get(get_key))
         .route("/db", delete(delete_key))
+        .route("/db", patch(patch_key))
         .route("/db_list", get(list_keys))
+        .route("/db_find", get(find_keys))
+        .route("/db_incr", post(increment))
+        .route("/db_decr", post(decrement))
         .route("/trigger", post(trigger))
         .route("/hook", post(set_hook))
         .route("/hook", get(get_hook))
@@ -780,15 +2241,39 @@ pub async fn run_async(
         .route("/exec", post(exec_script))
         .route("/hc", get(health_check))
         .route("/queue", post(push))
-        .route("/queue", get(pop))
+        .route("/queue_peek", get(peek))
+        .route("/queue_len", get(queue_length))
+        .route("/queue_ack", post(ack))
+        .route("/hash", get(hash_get).post(hash_set).delete(hash_delete))
+        .route("/set", get(set_get).post(set_add).delete(set_remove))
+        .route("/export", get(export))
+        .route("/import", post(import))
+        .route("/history", get(key_history))
+        .route("/usage", get(usage))
+        .route("/stat", get(stat))
+        .route("/move", post(move_key))
+        .route("/copy", post(copy_key))
         .route("/gitea", post(gitea))
+        .route("/events", get(events))
+        .route(
+            "/admin/databases",
+            get(list_databases).post(create_database),
+        )
+        .route("/admin/databases/:name", delete(drop_database))
+        .nest("/v2", super::v2::router())
         .layer(tower_http::timeout::TimeoutLayer::new(
             std::time::Duration::from_secs(10),
         ))
+        // Pop can wait for an item longer than the timeout, so it is routed without it
+        .route("/queue", get(pop))
+        .nest("/v2", super::v2::blocking_router())
         .layer(tower_http::trace::TraceLayer::new_for_http())
+        .layer(ContextLayer::new("rest"))
         .with_state(InjectedData {
             data_sender,
+            databases,
             config,
+            history,
         });
 
     let address: SocketAddr = match address.parse() {
@@ -802,7 +2287,7 @@ pub async fn run_async(
     };
 
     if let Err(e) = axum::Server::bind(&address)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
     {
         tracing::error!("failed to start REST server");
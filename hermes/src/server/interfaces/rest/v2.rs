@@ -0,0 +1,1493 @@
+// External depencies
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::channel;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+// Internal depencies
+use onlyati_datastore::datastore::enums::{
+    error::ErrorCode, error::ErrorKind, pair::KeyType, pair::RecordValue, pair::ValueType,
+    queue_timeout, snapshot::ImportPolicy, snapshot::Snapshot, snapshot::SnapshotFormat,
+    DatabaseAction, ListType, QueueEnd,
+};
+use onlyati_datastore::datastore::limits::{Limits, Usage};
+
+// Import macros
+use super::macros::{
+    return_client_error, return_database_error, return_ok, return_ok_with_value,
+    return_server_error, send_data_request,
+};
+use super::utilities::{status_of_error, to_record, ErrorBody, InjectedData, KeyChange, KeyStat};
+use crate::server::interfaces::context;
+
+/// OpenAPI document of the `/v2` endpoints
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Hermes REST API", version = "2"),
+    paths(
+        list_keys,
+        get_key,
+        set_key,
+        patch_key,
+        delete_key,
+        trigger,
+        list_hooks,
+        get_hook,
+        add_hook_link,
+        remove_hook_link,
+        push,
+        pop,
+        peek,
+        queue_length,
+        ack,
+        add_counter,
+        get_hash,
+        set_hash_field,
+        delete_hash_field,
+        get_set,
+        add_set_member,
+        remove_set_member,
+        export,
+        import,
+        key_history,
+        usage,
+        stat,
+        move_key,
+        copy_key,
+    ),
+    components(schemas(
+        KeyValue,
+        QueueLength,
+        Hash,
+        Set,
+        MemberBody,
+        ValueBody,
+        TypedValueBody,
+        PatchBody,
+        KeyEntry,
+        Hook,
+        LinkBody,
+        ImportResult,
+        KeyChange,
+        DatabaseUsage,
+        KeyStat,
+        RelocateBody,
+        ErrorBody
+    )),
+    tags(
+        (name = "keys", description = "Records and tables"),
+        (name = "hooks", description = "Hook definitions"),
+        (name = "queues", description = "Queues with blocking and acknowledged pop"),
+        (name = "counters", description = "Numeric records that are changed atomically"),
+        (name = "hashes", description = "Records that store fields and their values"),
+        (name = "sets", description = "Records that store unique members"),
+        (name = "snapshots", description = "Export and import of the database or its subtree"),
+        (name = "history", description = "Past changes that are read from the append file"),
+        (name = "usage", description = "Limits and current usage of databases"),
+        (name = "stat", description = "Metadata of keys and tables"),
+        (name = "relocation", description = "Move and copy of keys and tables"),
+    )
+)]
+struct ApiDoc;
+
+/// Key and its value
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct KeyValue {
+    /// Full key, e.g. `/root/status/server1`
+    key: String,
+
+    /// Value of the key in native JSON format
+    #[schema(value_type = Object)]
+    value: serde_json::Value,
+
+    /// Type of the value: `string`, `integer`, `float`, `bool`, `json` or `bytes`
+    r#type: String,
+
+    /// Identifier of delivery if queue item was popped with visibility timeout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    delivery: Option<u64>,
+}
+
+/// Length of a queue
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct QueueLength {
+    /// Full key of queue
+    key: String,
+
+    /// Number of items, it is 0 if queue does not exist
+    length: usize,
+}
+
+/// Limits and current usage of a database
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct DatabaseUsage {
+    /// Name of database
+    database: String,
+
+    /// Configured limits, unset ones are `null`
+    #[schema(value_type = Object)]
+    limits: Limits,
+
+    /// Estimated memory in bytes, number of keys and evictions since start
+    #[schema(value_type = Object)]
+    usage: Usage,
+}
+
+/// Fields of a hash
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct Hash {
+    /// Full key of hash
+    key: String,
+
+    /// Fields and their values
+    fields: std::collections::BTreeMap<String, String>,
+}
+
+/// Members of a set
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct Set {
+    /// Full key of set
+    key: String,
+
+    /// Members in sorted order
+    members: Vec<String>,
+}
+
+/// Body of request that adds member to a set
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct MemberBody {
+    /// Member to be added
+    member: String,
+}
+
+/// Body of requests that store a value
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct ValueBody {
+    /// Value to be stored
+    value: String,
+}
+
+/// Body of requests that store a typed value
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct TypedValueBody {
+    /// Value to be stored, without type its JSON type is kept
+    #[schema(value_type = Object)]
+    value: serde_json::Value,
+
+    /// Type of the value: `string`, `integer`, `float`, `bool`, `json` or `bytes`, bytes are base64 encoded
+    r#type: Option<String>,
+}
+
+/// Body of request that replaces a part of JSON record
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct PatchBody {
+    /// Path within the JSON document, e.g. `$.status.cpu`
+    path: String,
+
+    /// New value of the path
+    #[schema(value_type = Object)]
+    value: serde_json::Value,
+}
+
+/// Entry of key list
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct KeyEntry {
+    /// Full key
+    key: String,
+
+    /// Type of the entry: `r` for record, `t` for table, `q` for queue, `h` for hash, `s` for set
+    r#type: String,
+}
+
+/// Hook definition
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct Hook {
+    /// Prefix that belongs to a hook definition
+    prefix: String,
+
+    /// Links that belong to the prefix
+    links: Vec<String>,
+}
+
+/// Body of request that adds link to a hook
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct LinkBody {
+    /// Address where the changes are sent
+    link: String,
+}
+
+/// Body of request that moves or copies a key
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct RelocateBody {
+    /// Full key of destination, e.g. `/root/archive/server1`
+    destination: String,
+
+    /// Kind of source: `record`, `queue`, `hash`, `set` or `table`, default is `record`
+    kind: Option<String>,
+}
+
+/// Result of an import
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct ImportResult {
+    /// Number of records, queues, hashes, sets and hooks that have been written
+    imported: usize,
+
+    /// Number of records, queues, hashes, sets and hooks that have been left as they were
+    skipped: usize,
+}
+
+/// Query parameters of listing keys
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ListParm {
+    /// Keys are listed under this prefix
+    prefix: String,
+
+    /// List only the direct children of prefix, default is false
+    one_level: Option<bool>,
+}
+
+/// Query parameters of key reading
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct GetParm {
+    /// Return only this path of JSON record, e.g. `$.status.cpu`
+    path: Option<String>,
+}
+
+/// Query parameters of counter change
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CounterParm {
+    /// Number that is added to the counter, negative number decrements it, default is 1
+    by: Option<i64>,
+}
+
+/// Query parameters of queue read
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PeekParm {
+    /// End of queue: `front` or `back`, default is `front`
+    end: Option<String>,
+}
+
+/// Query parameters of queue pop
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PopParm {
+    /// End of queue: `front` or `back`, default is `front`
+    end: Option<String>,
+
+    /// Seconds to wait for an item if queue is empty
+    wait: Option<u64>,
+
+    /// Seconds while the item must be acknowledged, else it is returned to the front of queue
+    visibility: Option<u64>,
+}
+
+/// Query parameters of hash read
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct HashGetParm {
+    /// Return only this field of hash
+    field: Option<String>,
+}
+
+/// Query parameters of hash change
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct FieldParm {
+    /// Name of the field
+    field: String,
+}
+
+/// Query parameters of set read
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SetGetParm {
+    /// Return only this member, if it is in the set
+    member: Option<String>,
+}
+
+/// Query parameters of set member removal
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct MemberParm {
+    /// Member to be removed
+    member: String,
+}
+
+/// Query parameters of export
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ExportParm {
+    /// Format of snapshot: `json`, `toml` or `binary`, default is `json`
+    format: Option<String>,
+}
+
+/// Query parameters of import
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ImportParm {
+    /// Format of snapshot: `json`, `toml` or `binary`, default is `json`
+    format: Option<String>,
+
+    /// What happens with existing keys and hooks: `merge`, `overwrite` or `skip`, default is `merge`
+    policy: Option<String>,
+}
+
+/// Query parameters of key deletion
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DeleteParm {
+    /// Remove the complete table under the path instead of a record, default is false
+    recursive: Option<bool>,
+}
+
+/// Query parameters of listing hooks
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct HookListParm {
+    /// Hooks are listed under this prefix
+    prefix: String,
+}
+
+/// Query parameters of link removal
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct LinkParm {
+    /// Address that is removed from the hook
+    link: String,
+}
+
+/// Make key from the captured path, e.g. `root/status` -> `/root/status`
+fn to_key(path: String) -> String {
+    format!("/{}", path.trim_start_matches('/'))
+}
+
+/// List keys under a prefix
+#[utoipa::path(
+    get,
+    path = "/v2/keys",
+    tag = "keys",
+    params(ListParm),
+    responses(
+        (status = 200, description = "List of keys", body = [KeyEntry]),
+        (status = 400, description = "Invalid prefix", body = ErrorBody),
+        (status = 404, description = "Prefix does not exist", body = ErrorBody),
+    )
+)]
+async fn list_keys(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<ListParm>,
+) -> impl IntoResponse {
+    let level = match parms.one_level {
+        Some(true) => ListType::OneLevel,
+        _ => ListType::All,
+    };
+
+    let response = injected
+        .data_sender
+        .list_keys(parms.prefix, level, context::current())
+        .await;
+
+    match response {
+        Ok(list) => return_ok_with_value!(list
+            .iter()
+            .map(|x| KeyEntry {
+                key: x.get_key().to_string(),
+                r#type: x.get_type().to_string(),
+            })
+            .collect::<Vec<KeyEntry>>()),
+        Err(e) => return_database_error!(e),
+    }
+}
+
+/// Get value of a key
+#[utoipa::path(
+    get,
+    path = "/v2/keys/{path}",
+    tag = "keys",
+    params(("path" = String, Path, description = "Key without the leading slash, e.g. `root/status/server1`"), GetParm),
+    responses(
+        (status = 200, description = "Value of the key", body = KeyValue),
+        (status = 400, description = "Invalid key", body = ErrorBody),
+        (status = 404, description = "Key or JSON path does not exist", body = ErrorBody),
+        (status = 409, description = "Path is requested but record is not JSON", body = ErrorBody),
+    )
+)]
+async fn get_key(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Query(parms): Query<GetParm>,
+) -> impl IntoResponse {
+    let key = to_key(path);
+
+    let response = injected
+        .data_sender
+        .get(key.clone(), context::current())
+        .await;
+
+    match response {
+        Ok(ValueType::RecordPointer(record)) => {
+            let record = match parms.path {
+                Some(path) => match record.get_path(&path) {
+                    Ok(value) => RecordValue::from_json(value),
+                    Err(e) => return_database_error!(e),
+                },
+                None => record,
+            };
+            return_ok_with_value!(KeyValue {
+                key,
+                value: record.to_json(),
+                r#type: record.get_type().to_string(),
+                delivery: None,
+            })
+        }
+        Ok(_) => return_client_error!(
+            ErrorCode::TypeMismatch,
+            "Pointer must be Record but it was Table"
+        ),
+        Err(e) => return_database_error!(e),
+    }
+}
+
+/// Create or update a key
+#[utoipa::path(
+    put,
+    path = "/v2/keys/{path}",
+    tag = "keys",
+    params(("path" = String, Path, description = "Key without the leading slash")),
+    request_body = TypedValueBody,
+    responses(
+        (status = 204, description = "Key is saved"),
+        (status = 400, description = "Invalid key or value", body = ErrorBody),
+    )
+)]
+async fn set_key(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Json(body): Json<TypedValueBody>,
+) -> impl IntoResponse {
+    let value = match to_record(body.value, body.r#type) {
+        Ok(value) => value,
+        Err(e) => return_database_error!(e),
+    };
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::SetTyped(tx, to_key(path), value);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(_) => return_ok!(StatusCode::NO_CONTENT),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Replace a part of JSON record, missing fields are created
+#[utoipa::path(
+    patch,
+    path = "/v2/keys/{path}",
+    tag = "keys",
+    params(("path" = String, Path, description = "Key without the leading slash")),
+    request_body = PatchBody,
+    responses(
+        (status = 204, description = "Record is updated"),
+        (status = 400, description = "Invalid key or JSON path", body = ErrorBody),
+        (status = 404, description = "Key or array item does not exist", body = ErrorBody),
+        (status = 409, description = "Record is not JSON", body = ErrorBody),
+    )
+)]
+async fn patch_key(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Json(body): Json<PatchBody>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Patch(tx, to_key(path), body.path, body.value);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(_) => return_ok!(StatusCode::NO_CONTENT),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Remove a record, or a complete table if `recursive` is set
+#[utoipa::path(
+    delete,
+    path = "/v2/keys/{path}",
+    tag = "keys",
+    params(("path" = String, Path, description = "Key without the leading slash"), DeleteParm),
+    responses(
+        (status = 204, description = "Key is removed"),
+        (status = 400, description = "Invalid key", body = ErrorBody),
+        (status = 404, description = "Key does not exist", body = ErrorBody),
+    )
+)]
+async fn delete_key(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Query(parms): Query<DeleteParm>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = match parms.recursive {
+        Some(true) => DatabaseAction::DeleteTable(tx, to_key(path)),
+        _ => DatabaseAction::DeleteKey(tx, to_key(path)),
+    };
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(_) => return_ok!(StatusCode::NO_CONTENT),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Send the value to the hooks of the key without saving it
+#[utoipa::path(
+    post,
+    path = "/v2/triggers/{path}",
+    tag = "hooks",
+    params(("path" = String, Path, description = "Key without the leading slash")),
+    request_body = ValueBody,
+    responses(
+        (status = 204, description = "Trigger is sent"),
+        (status = 400, description = "Invalid key", body = ErrorBody),
+    )
+)]
+async fn trigger(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Json(body): Json<ValueBody>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Trigger(tx, to_key(path), body.value);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(_) => return_ok!(StatusCode::NO_CONTENT),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// List hooks under a prefix
+#[utoipa::path(
+    get,
+    path = "/v2/hooks",
+    tag = "hooks",
+    params(HookListParm),
+    responses(
+        (status = 200, description = "List of hooks", body = [Hook]),
+    )
+)]
+async fn list_hooks(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<HookListParm>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::HookList(tx, parms.prefix);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(hooks) => return_ok_with_value!(hooks
+                .into_iter()
+                .map(|(prefix, links)| Hook { prefix, links })
+                .collect::<Vec<Hook>>()),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Get links of a hook
+#[utoipa::path(
+    get,
+    path = "/v2/hooks/{prefix}",
+    tag = "hooks",
+    params(("prefix" = String, Path, description = "Prefix without the leading slash")),
+    responses(
+        (status = 200, description = "Hook definition", body = Hook),
+        (status = 404, description = "Hook does not exist", body = ErrorBody),
+    )
+)]
+async fn get_hook(
+    State(injected): State<InjectedData>,
+    Path(prefix): Path<String>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::HookGet(tx, to_key(prefix));
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok((prefix, links)) => return_ok_with_value!(Hook { prefix, links }),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Add a link to the hook of prefix
+#[utoipa::path(
+    post,
+    path = "/v2/hooks/{prefix}",
+    tag = "hooks",
+    params(("prefix" = String, Path, description = "Prefix without the leading slash")),
+    request_body = LinkBody,
+    responses(
+        (status = 201, description = "Link is added"),
+        (status = 409, description = "Link is already defined", body = ErrorBody),
+    )
+)]
+async fn add_hook_link(
+    State(injected): State<InjectedData>,
+    Path(prefix): Path<String>,
+    Json(body): Json<LinkBody>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::HookSet(tx, to_key(prefix), body.link);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(_) => return_ok!(StatusCode::CREATED),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Remove a link from the hook of prefix
+#[utoipa::path(
+    delete,
+    path = "/v2/hooks/{prefix}",
+    tag = "hooks",
+    params(("prefix" = String, Path, description = "Prefix without the leading slash"), LinkParm),
+    responses(
+        (status = 204, description = "Link is removed"),
+        (status = 404, description = "Hook or link does not exist", body = ErrorBody),
+    )
+)]
+async fn remove_hook_link(
+    State(injected): State<InjectedData>,
+    Path(prefix): Path<String>,
+    Query(parms): Query<LinkParm>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::HookRemove(tx, to_key(prefix), parms.link);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(_) => return_ok!(StatusCode::NO_CONTENT),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Push a value at the end of a queue, queue is created if it does not exist
+#[utoipa::path(
+    post,
+    path = "/v2/queues/{path}",
+    tag = "queues",
+    params(("path" = String, Path, description = "Key of queue without the leading slash")),
+    request_body = ValueBody,
+    responses(
+        (status = 201, description = "Value is pushed"),
+        (status = 400, description = "Invalid key", body = ErrorBody),
+    )
+)]
+async fn push(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Json(body): Json<ValueBody>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Push(tx, to_key(path), body.value);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(_) => return_ok!(StatusCode::CREATED),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Remove a value from a queue and return with it
+#[utoipa::path(
+    delete,
+    path = "/v2/queues/{path}",
+    tag = "queues",
+    params(("path" = String, Path, description = "Key of queue without the leading slash"), PopParm),
+    responses(
+        (status = 200, description = "Removed value of the queue, `delivery` is set if visibility is specified", body = KeyValue),
+        (status = 404, description = "Queue does not exist or empty", body = ErrorBody),
+    )
+)]
+async fn pop(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Query(parms): Query<PopParm>,
+) -> impl IntoResponse {
+    let key = to_key(path);
+    let end = match parms.end.as_deref().map(str::parse).transpose() {
+        Ok(end) => end.unwrap_or(QueueEnd::Front),
+        Err(e) => return_database_error!(e),
+    };
+
+    if let Some(visibility) = parms.visibility {
+        if end != QueueEnd::Front {
+            return_client_error!(
+                ErrorCode::InvalidKey,
+                "Visibility timeout is supported only at the front"
+            );
+        }
+
+        let visibility = match queue_timeout(visibility) {
+            Ok(visibility) => visibility,
+            Err(e) => return_database_error!(e),
+        };
+
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::PopReliable(tx, key.clone(), visibility);
+        send_data_request!(action, injected.data_sender);
+
+        match rx.recv().await {
+            Some(response) => match response {
+                Ok((id, value)) => return_ok_with_value!(KeyValue {
+                    key,
+                    value: serde_json::Value::String(value),
+                    r#type: "string".to_string(),
+                    delivery: Some(id),
+                }),
+                Err(e) => return_database_error!(e),
+            },
+            None => return_server_error!("failed to get response from server"),
+        }
+    }
+
+    let wait = match parms.wait.map(queue_timeout).transpose() {
+        Ok(wait) => wait,
+        Err(e) => return_database_error!(e),
+    };
+
+    let (tx, mut rx) = channel(10);
+    let action = match wait {
+        Some(wait) => DatabaseAction::PopWait(tx, key.clone(), end, wait),
+        None => DatabaseAction::PopFrom(tx, key.clone(), end),
+    };
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(ValueType::RecordPointer(record)) => return_ok_with_value!(KeyValue {
+                key,
+                value: record.to_json(),
+                r#type: record.get_type().to_string(),
+                delivery: None,
+            }),
+            Ok(_) => return_client_error!(
+                ErrorCode::TypeMismatch,
+                "Pointer must be Record but it was Table"
+            ),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Read a value of queue without removing it
+#[utoipa::path(
+    get,
+    path = "/v2/queues/{path}",
+    tag = "queues",
+    params(("path" = String, Path, description = "Key of queue without the leading slash"), PeekParm),
+    responses(
+        (status = 200, description = "Value at the specified end of queue", body = KeyValue),
+        (status = 404, description = "Queue does not exist or empty", body = ErrorBody),
+    )
+)]
+async fn peek(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Query(parms): Query<PeekParm>,
+) -> impl IntoResponse {
+    let key = to_key(path);
+    let end = match parms.end.as_deref().map(str::parse).transpose() {
+        Ok(end) => end.unwrap_or(QueueEnd::Front),
+        Err(e) => return_database_error!(e),
+    };
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Peek(tx, key.clone(), end);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(ValueType::RecordPointer(record)) => return_ok_with_value!(KeyValue {
+                key,
+                value: record.to_json(),
+                r#type: record.get_type().to_string(),
+                delivery: None,
+            }),
+            Ok(_) => return_server_error!("queue item is not a record"),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Get the number of values in a queue
+#[utoipa::path(
+    get,
+    path = "/v2/queue-lengths/{path}",
+    tag = "queues",
+    params(("path" = String, Path, description = "Key of queue without the leading slash")),
+    responses(
+        (status = 200, description = "Length of the queue", body = QueueLength),
+        (status = 400, description = "Invalid key", body = ErrorBody),
+    )
+)]
+async fn queue_length(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+) -> impl IntoResponse {
+    let key = to_key(path);
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::QueueLength(tx, key.clone());
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(length) => return_ok_with_value!(QueueLength { key, length }),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Acknowledge a value that was popped with visibility timeout
+#[utoipa::path(
+    delete,
+    path = "/v2/deliveries/{id}",
+    tag = "queues",
+    params(("id" = u64, Path, description = "Identifier of delivery")),
+    responses(
+        (status = 204, description = "Delivery is acknowledged"),
+        (status = 404, description = "Delivery does not exist or its visibility timeout has expired", body = ErrorBody),
+    )
+)]
+async fn ack(State(injected): State<InjectedData>, Path(id): Path<u64>) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Ack(tx, id);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(_) => return_ok!(StatusCode::NO_CONTENT),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Add a number to a counter atomically, counter is created if it does not exist
+#[utoipa::path(
+    post,
+    path = "/v2/counters/{path}",
+    tag = "counters",
+    params(("path" = String, Path, description = "Key of counter without the leading slash"), CounterParm),
+    responses(
+        (status = 200, description = "New value of the counter", body = KeyValue),
+        (status = 400, description = "Invalid key", body = ErrorBody),
+        (status = 409, description = "Value is not numeric or it would overflow", body = ErrorBody),
+    )
+)]
+async fn add_counter(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Query(parms): Query<CounterParm>,
+) -> impl IntoResponse {
+    let key = to_key(path);
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Increment(tx, key.clone(), parms.by.unwrap_or(1));
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(ValueType::RecordPointer(record)) => return_ok_with_value!(KeyValue {
+                key,
+                value: record.to_json(),
+                r#type: record.get_type().to_string(),
+                delivery: None,
+            }),
+            Ok(_) => return_server_error!("counter is not a record"),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Get fields of a hash
+#[utoipa::path(
+    get,
+    path = "/v2/hashes/{path}",
+    tag = "hashes",
+    params(("path" = String, Path, description = "Key of hash without the leading slash"), HashGetParm),
+    responses(
+        (status = 200, description = "Every field of hash or only the requested one", body = Hash),
+        (status = 404, description = "Hash or field does not exist", body = ErrorBody),
+        (status = 409, description = "Key is not a hash", body = ErrorBody),
+    )
+)]
+async fn get_hash(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Query(parms): Query<HashGetParm>,
+) -> impl IntoResponse {
+    let key = to_key(path);
+
+    if let Some(field) = parms.field {
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::HashGet(tx, key.clone(), field.clone());
+        send_data_request!(action, injected.data_sender);
+
+        match rx.recv().await {
+            Some(response) => match response {
+                Ok(value) => return_ok_with_value!(Hash {
+                    key,
+                    fields: [(field, value.get_value())].into(),
+                }),
+                Err(e) => return_database_error!(e),
+            },
+            None => return_server_error!("failed to get response from server"),
+        }
+    }
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::HashGetAll(tx, key.clone());
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(fields) => return_ok_with_value!(Hash { key, fields }),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Set a field of hash, hash is created if it does not exist
+#[utoipa::path(
+    put,
+    path = "/v2/hashes/{path}",
+    tag = "hashes",
+    params(("path" = String, Path, description = "Key of hash without the leading slash"), FieldParm),
+    request_body = ValueBody,
+    responses(
+        (status = 201, description = "Field is created"),
+        (status = 204, description = "Field is updated"),
+        (status = 400, description = "Invalid key", body = ErrorBody),
+        (status = 409, description = "Key is not a hash", body = ErrorBody),
+    )
+)]
+async fn set_hash_field(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Query(parms): Query<FieldParm>,
+    Json(body): Json<ValueBody>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::HashSet(tx, to_key(path), parms.field, body.value);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(true) => return_ok!(StatusCode::CREATED),
+            Ok(false) => return_ok!(StatusCode::NO_CONTENT),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Remove a field of hash, hash is removed with its last field
+#[utoipa::path(
+    delete,
+    path = "/v2/hashes/{path}",
+    tag = "hashes",
+    params(("path" = String, Path, description = "Key of hash without the leading slash"), FieldParm),
+    responses(
+        (status = 204, description = "Field is removed"),
+        (status = 404, description = "Hash or field does not exist", body = ErrorBody),
+        (status = 409, description = "Key is not a hash", body = ErrorBody),
+    )
+)]
+async fn delete_hash_field(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Query(parms): Query<FieldParm>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::HashDelete(tx, to_key(path), parms.field);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(_) => return_ok!(StatusCode::NO_CONTENT),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Get members of a set
+#[utoipa::path(
+    get,
+    path = "/v2/sets/{path}",
+    tag = "sets",
+    params(("path" = String, Path, description = "Key of set without the leading slash"), SetGetParm),
+    responses(
+        (status = 200, description = "Every member of set or only the requested one", body = Set),
+        (status = 404, description = "Set does not exist or value is not its member", body = ErrorBody),
+        (status = 409, description = "Key is not a set", body = ErrorBody),
+    )
+)]
+async fn get_set(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Query(parms): Query<SetGetParm>,
+) -> impl IntoResponse {
+    let key = to_key(path);
+
+    if let Some(member) = parms.member {
+        let (tx, mut rx) = channel(10);
+        let action = DatabaseAction::SetIsMember(tx, key.clone(), member.clone());
+        send_data_request!(action, injected.data_sender);
+
+        match rx.recv().await {
+            Some(response) => match response {
+                Ok(true) => return_ok_with_value!(Set {
+                    key,
+                    members: vec![member],
+                }),
+                Ok(false) => return_client_error!(ErrorCode::NotFound, "Member does not exist"),
+                Err(e) => return_database_error!(e),
+            },
+            None => return_server_error!("failed to get response from server"),
+        }
+    }
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::SetMembers(tx, key.clone());
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(members) => return_ok_with_value!(Set {
+                key,
+                members: members.into_iter().collect(),
+            }),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Add a member to a set, set is created if it does not exist
+#[utoipa::path(
+    post,
+    path = "/v2/sets/{path}",
+    tag = "sets",
+    params(("path" = String, Path, description = "Key of set without the leading slash")),
+    request_body = MemberBody,
+    responses(
+        (status = 201, description = "Member is added"),
+        (status = 200, description = "Value was already a member"),
+        (status = 400, description = "Invalid key", body = ErrorBody),
+        (status = 409, description = "Key is not a set", body = ErrorBody),
+    )
+)]
+async fn add_set_member(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Json(body): Json<MemberBody>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::SetAdd(tx, to_key(path), body.member);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(true) => return_ok!(StatusCode::CREATED),
+            Ok(false) => return_ok!(),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Remove a member from a set, set is removed with its last member
+#[utoipa::path(
+    delete,
+    path = "/v2/sets/{path}",
+    tag = "sets",
+    params(("path" = String, Path, description = "Key of set without the leading slash"), MemberParm),
+    responses(
+        (status = 204, description = "Member is removed"),
+        (status = 404, description = "Set does not exist or value is not its member", body = ErrorBody),
+        (status = 409, description = "Key is not a set", body = ErrorBody),
+    )
+)]
+async fn remove_set_member(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Query(parms): Query<MemberParm>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::SetRemove(tx, to_key(path), parms.member);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(true) => return_ok!(StatusCode::NO_CONTENT),
+            Ok(false) => return_client_error!(ErrorCode::NotFound, "Member does not exist"),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Export a table with everything under it and the hooks of its prefix
+#[utoipa::path(
+    get,
+    path = "/v2/export/{path}",
+    tag = "snapshots",
+    params(("path" = String, Path, description = "Table without the leading slash, e.g. `root` for the whole database"), ExportParm),
+    responses(
+        (status = 200, description = "Snapshot in the requested format"),
+        (status = 400, description = "Invalid key or format", body = ErrorBody),
+        (status = 404, description = "Table does not exist", body = ErrorBody),
+    )
+)]
+async fn export(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Query(parms): Query<ExportParm>,
+) -> impl IntoResponse {
+    let format = match parms
+        .format
+        .as_deref()
+        .unwrap_or("json")
+        .parse::<SnapshotFormat>()
+    {
+        Ok(format) => format,
+        Err(e) => return_database_error!(e),
+    };
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Export(tx, to_key(path));
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(snapshot) => match snapshot.encode(format) {
+                Ok(data) => (
+                    StatusCode::OK,
+                    [(header::CONTENT_TYPE, format.content_type())],
+                    data,
+                )
+                    .into_response(),
+                Err(e) => return_server_error!(e),
+            },
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Import a snapshot that was made by export or written like the initial file
+#[utoipa::path(
+    post,
+    path = "/v2/import",
+    tag = "snapshots",
+    params(ImportParm),
+    request_body(content = String, description = "Snapshot in the specified format"),
+    responses(
+        (status = 200, description = "Snapshot is imported", body = ImportResult),
+        (status = 400, description = "Invalid snapshot, format or policy", body = ErrorBody),
+        (status = 409, description = "Hook link cannot be added", body = ErrorBody),
+    )
+)]
+async fn import(
+    State(injected): State<InjectedData>,
+    Query(parms): Query<ImportParm>,
+    body: bytes::Bytes,
+) -> impl IntoResponse {
+    let format = match parms
+        .format
+        .as_deref()
+        .unwrap_or("json")
+        .parse::<SnapshotFormat>()
+    {
+        Ok(format) => format,
+        Err(e) => return_database_error!(e),
+    };
+
+    let policy = match parms
+        .policy
+        .as_deref()
+        .unwrap_or("merge")
+        .parse::<ImportPolicy>()
+    {
+        Ok(policy) => policy,
+        Err(e) => return_database_error!(e),
+    };
+
+    let snapshot = match Snapshot::decode(&body, format) {
+        Ok(snapshot) => snapshot,
+        Err(e) => return_database_error!(e),
+    };
+
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Import(tx, snapshot, policy);
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(summary) => return_ok_with_value!(ImportResult {
+                imported: summary.imported,
+                skipped: summary.skipped,
+            }),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Read the past changes of a key from the append file
+#[utoipa::path(
+    get,
+    path = "/v2/history/{path}",
+    tag = "history",
+    params(("path" = String, Path, description = "Key without the leading slash, e.g. `root/status/server1`")),
+    responses(
+        (status = 200, description = "Changes in the order of their time", body = [KeyChange]),
+        (status = 400, description = "Invalid key", body = ErrorBody),
+        (status = 500, description = "Logging is disabled or append file cannot be read", body = ErrorBody),
+    )
+)]
+async fn key_history(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::History(tx, to_key(path));
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(history) => return_ok_with_value!(history
+                .into_iter()
+                .map(KeyChange::from)
+                .collect::<Vec<KeyChange>>()),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Get the limits and current usage of a database
+#[utoipa::path(
+    get,
+    path = "/v2/usage/{database}",
+    tag = "usage",
+    params(("database" = String, Path, description = "Name of database, e.g. `root`")),
+    responses(
+        (status = 200, description = "Limits and usage of the database", body = DatabaseUsage),
+        (status = 400, description = "Invalid database name", body = ErrorBody),
+    )
+)]
+async fn usage(
+    State(injected): State<InjectedData>,
+    Path(database): Path<String>,
+) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Usage(tx, format!("/{}", database));
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(report) => return_ok_with_value!(DatabaseUsage {
+                database,
+                limits: report.limits,
+                usage: report.usage,
+            }),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Get the metadata of a key or a table, every record, queue, hash, set and table with the key is returned
+#[utoipa::path(
+    get,
+    path = "/v2/stat/{path}",
+    tag = "stat",
+    params(("path" = String, Path, description = "Key without the leading slash, e.g. `root/status`")),
+    responses(
+        (status = 200, description = "Metadata of key and table", body = [KeyStat]),
+        (status = 400, description = "Invalid key", body = ErrorBody),
+        (status = 404, description = "Key does not exist", body = ErrorBody),
+    )
+)]
+async fn stat(State(injected): State<InjectedData>, Path(path): Path<String>) -> impl IntoResponse {
+    let (tx, mut rx) = channel(10);
+    let action = DatabaseAction::Stat(tx, to_key(path));
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(stats) => return_ok_with_value!(stats
+                .into_iter()
+                .map(KeyStat::from)
+                .collect::<Vec<KeyStat>>()),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Move a record, queue, hash, set or complete table to another key of the same database
+#[utoipa::path(
+    post,
+    path = "/v2/move/{path}",
+    tag = "relocation",
+    params(("path" = String, Path, description = "Key of source without the leading slash")),
+    request_body = RelocateBody,
+    responses(
+        (status = 204, description = "Key is moved"),
+        (status = 400, description = "Invalid key or kind", body = ErrorBody),
+        (status = 404, description = "Source does not exist", body = ErrorBody),
+        (status = 409, description = "Destination already exists", body = ErrorBody),
+        (status = 507, description = "Destination would exceed a limit", body = ErrorBody),
+    )
+)]
+async fn move_key(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Json(body): Json<RelocateBody>,
+) -> impl IntoResponse {
+    relocate(injected, path, body, true).await
+}
+
+/// Copy a record, queue, hash, set or complete table to another key of the same database
+#[utoipa::path(
+    post,
+    path = "/v2/copy/{path}",
+    tag = "relocation",
+    params(("path" = String, Path, description = "Key of source without the leading slash")),
+    request_body = RelocateBody,
+    responses(
+        (status = 204, description = "Key is copied"),
+        (status = 400, description = "Invalid key or kind", body = ErrorBody),
+        (status = 404, description = "Source does not exist", body = ErrorBody),
+        (status = 409, description = "Destination already exists", body = ErrorBody),
+        (status = 507, description = "Destination would exceed a limit", body = ErrorBody),
+    )
+)]
+async fn copy_key(
+    State(injected): State<InjectedData>,
+    Path(path): Path<String>,
+    Json(body): Json<RelocateBody>,
+) -> impl IntoResponse {
+    relocate(injected, path, body, false).await
+}
+
+/// Move or copy a key then return with empty response
+async fn relocate(
+    injected: InjectedData,
+    path: String,
+    body: RelocateBody,
+    remove: bool,
+) -> axum::response::Response {
+    let kind = body.kind.unwrap_or_else(|| "record".to_string());
+    let key = match KeyType::from_kind(&kind, to_key(path)) {
+        Ok(key) => key,
+        Err(e) => return_database_error!(e),
+    };
+
+    let (tx, mut rx) = channel(10);
+    let action = match remove {
+        true => DatabaseAction::Move(tx, key, body.destination),
+        false => DatabaseAction::Copy(tx, key, body.destination),
+    };
+    send_data_request!(action, injected.data_sender);
+
+    match rx.recv().await {
+        Some(response) => match response {
+            Ok(_) => return_ok!(StatusCode::NO_CONTENT),
+            Err(e) => return_database_error!(e),
+        },
+        None => return_server_error!("failed to get response from server"),
+    }
+}
+
+/// Serve the OpenAPI document
+async fn openapi() -> impl IntoResponse {
+    Json(ApiDoc::openapi())
+}
+
+/// Router of the `/v2` endpoints
+pub fn router() -> Router<InjectedData> {
+    Router::new()
+        .route("/openapi.json", get(openapi))
+        .route("/keys", get(list_keys))
+        .route(
+            "/keys/*path",
+            get(get_key)
+                .put(set_key)
+                .patch(patch_key)
+                .delete(delete_key),
+        )
+        .route("/triggers/*path", post(trigger))
+        .route("/hooks", get(list_hooks))
+        .route(
+            "/hooks/*prefix",
+            get(get_hook).post(add_hook_link).delete(remove_hook_link),
+        )
+        .route("/queues/*path", get(peek).post(push))
+        .route("/queue-lengths/*path", get(queue_length))
+        .route("/deliveries/:id", delete(ack))
+        .route("/counters/*path", post(add_counter))
+        .route(
+            "/hashes/*path",
+            get(get_hash).put(set_hash_field).delete(delete_hash_field),
+        )
+        .route(
+            "/sets/*path",
+            get(get_set).post(add_set_member).delete(remove_set_member),
+        )
+        .route("/export/*path", get(export))
+        .route("/import", post(import))
+        .route("/history/*path", get(key_history))
+        .route("/usage/:database", get(usage))
+        .route("/stat/*path", get(stat))
+        .route("/move/*path", post(move_key))
+        .route("/copy/*path", post(copy_key))
+}
+
+/// Router of the `/v2` endpoints that can wait longer than the request timeout
+pub fn blocking_router() -> Router<InjectedData> {
+    Router::new().route("/queues/*path", delete(pop))
+}
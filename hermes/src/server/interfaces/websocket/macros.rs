@@ -1,11 +1,13 @@
 macro_rules! send_data_request {
-    ($payload:expr, $data_mutex:expr) => {{
-        let sender = $data_mutex.lock().await;
-        if let Err(e) = sender.send($payload).await {
+    ($payload:expr, $data_sender:expr) => {{
+        if let Err(e) = $data_sender
+            .send(crate::server::interfaces::context::with_context($payload))
+            .await
+        {
             for line in e.to_string().lines() {
                 tracing::error!("{}", line);
             }
-            return WsResponse::new_err("internal server error");
+            return WsResponse::new_err(ErrorCode::Internal, "internal server error");
         }
     }};
 }
@@ -27,7 +29,7 @@ macro_rules! verify_one_item {
     ($req:expr, $error_msg:expr) => {
         match $req {
             Some(value1) => value1,
-            _ => return WsResponse::new_err($error_msg),
+            _ => return WsResponse::new_err(ErrorCode::InvalidKey, $error_msg),
         }
     };
 }
@@ -37,7 +39,7 @@ macro_rules! verify_two_items {
     ($req1:expr, $req2:expr, $error_msg:expr) => {
         match ($req1, $req2) {
             (Some(value1), Some(value2)) => (value1, value2),
-            _ => return WsResponse::new_err($error_msg),
+            _ => return WsResponse::new_err(ErrorCode::InvalidKey, $error_msg),
         }
     };
 }
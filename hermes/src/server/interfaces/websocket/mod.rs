@@ -1,20 +1,20 @@
 // External dependencies
 use std::sync::Arc;
-use tokio::sync::{mpsc::Sender, RwLock, Mutex};
+use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 
 // Internal dependencies
 use super::ApplicationInterface;
 use crate::server::utilities::config_parse::Config;
-use onlyati_datastore::datastore::enums::DatabaseAction;
+use onlyati_datastore::datastore::router::DatastoreRouter;
 
 mod macros;
 mod utilities;
 
 /// Websocket interface that run the function
 pub struct Websocket {
-    /// Sender to send data to database thread
-    data_sender: Arc<Mutex<Sender<DatabaseAction>>>,
+    /// Router to send data to the database threads
+    data_sender: DatastoreRouter,
 
     /// Host address where the interface bind and listen
     address: String,
@@ -30,14 +30,10 @@ impl Websocket {
     /// Create new interface
     ///
     /// # Parmeters
-    /// - `data_sender`: Sender to send data to database thread
+    /// - `data_sender`: Router to send data to the database threads
     /// - `address`: Host address where the interface bind and listen
     /// - `config`: Application's config file
-    pub fn new(
-        data_sender: Arc<Mutex<Sender<DatabaseAction>>>,
-        address: String,
-        config: Arc<RwLock<Config>>,
-    ) -> Self {
+    pub fn new(data_sender: DatastoreRouter, address: String, config: Arc<RwLock<Config>>) -> Self {
         Self {
             data_sender,
             address,
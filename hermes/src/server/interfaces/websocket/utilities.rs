@@ -11,25 +11,31 @@ use axum::{
     routing::get,
     Router,
 };
+use base64::Engine;
 use std::borrow::Cow;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::{mpsc::channel, mpsc::Sender, Mutex, RwLock};
+use tokio::sync::{mpsc::channel, RwLock};
 use tower_http::trace::DefaultMakeSpan;
 use tower_http::trace::TraceLayer;
 
 // Internal depencies
 use super::macros::{send_data_back, send_data_request, verify_one_item, verify_two_items};
+use crate::common::format_time;
 use crate::common::websocket::{CommandMethod, WsRequest, WsResponse};
+use crate::server::interfaces::context;
 use crate::server::utilities::config_parse::Config;
 use onlyati_datastore::datastore::{
-    enums::pair::ValueType, enums::DatabaseAction, enums::ListType,
+    enums::context::RequestContext, enums::error::ErrorCode, enums::pair::KeyType,
+    enums::pair::RecordValue, enums::pair::ValueType, enums::queue_timeout,
+    enums::snapshot::ImportPolicy, enums::snapshot::Snapshot, enums::snapshot::SnapshotFormat,
+    enums::DatabaseAction, enums::ListType, enums::QueueEnd, router::DatastoreRouter,
 };
 
 /// Struct that is injected into every endpoint
 #[derive(Clone)]
 pub struct InjectedData {
-    data_sender: Arc<Mutex<Sender<DatabaseAction>>>,
+    data_sender: DatastoreRouter,
     config: Arc<RwLock<Config>>,
 }
 
@@ -54,22 +60,25 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
         CommandMethod::GetKey => {
             let key = verify_one_item!(req.key, "'key' must be specified");
 
-            let (tx, mut rx) = channel(10);
-            let action = DatabaseAction::Get(tx, key);
-            send_data_request!(action, injected.data_sender);
-
-            match rx.recv().await {
-                Some(response) => match response {
-                    Ok(value) => match value {
-                        ValueType::RecordPointer(data) => WsResponse::new_ok(data),
-                        _ => WsResponse::new_err("Pointer must be Record but it was Table"),
+            // Reads are served by the handle without the datastore task
+            match injected.data_sender.get(key, context::current()).await {
+                Ok(value) => match value {
+                    ValueType::RecordPointer(data) => match req.path {
+                        Some(path) => match data.get_path(&path) {
+                            Ok(value) => {
+                                let value = RecordValue::from_json(value);
+                                WsResponse::new_typed(&value, value.get_type())
+                            }
+                            Err(e) => WsResponse::from_error(e),
+                        },
+                        None => WsResponse::new_typed(&data, data.get_type()),
                     },
-                    Err(e) => WsResponse::new_err(e.to_string()),
+                    _ => WsResponse::new_err(
+                        ErrorCode::TypeMismatch,
+                        "Pointer must be Record but it was Table",
+                    ),
                 },
-                None => {
-                    tracing::error!("failed to receive from database");
-                    WsResponse::new_err("internal server error")
-                }
+                Err(e) => WsResponse::from_error(e),
             }
         }
         //
@@ -80,17 +89,23 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
                 verify_two_items!(req.key, req.value, "'key' and 'value' must be specified");
 
             let (tx, mut rx) = channel(10);
-            let action = DatabaseAction::Set(tx, key, value);
+            let action = match req.r#type {
+                Some(r#type) => match RecordValue::parse(&r#type, &value) {
+                    Ok(value) => DatabaseAction::SetTyped(tx, key, value),
+                    Err(e) => return WsResponse::from_error(e),
+                },
+                None => DatabaseAction::Set(tx, key, value),
+            };
             send_data_request!(action, injected.data_sender);
 
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => WsResponse::new_ok(""),
-                    Err(e) => WsResponse::new_err(e),
+                    Err(e) => WsResponse::from_error(e),
                 },
                 None => {
                     tracing::error!("failed to receive from database");
-                    WsResponse::new_err("internal server error")
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
                 }
             }
         }
@@ -107,11 +122,11 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => WsResponse::new_ok(""),
-                    Err(e) => WsResponse::new_err(e),
+                    Err(e) => WsResponse::from_error(e),
                 },
                 None => {
                     tracing::error!("failed to receive from database");
-                    WsResponse::new_err("internal server error")
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
                 }
             }
         }
@@ -128,11 +143,11 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => WsResponse::new_ok(""),
-                    Err(e) => WsResponse::new_err(e),
+                    Err(e) => WsResponse::from_error(e),
                 },
                 None => {
                     tracing::error!("failed to receive from database");
-                    WsResponse::new_err("internal server error")
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
                 }
             }
         }
@@ -141,9 +156,39 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
         //
         CommandMethod::ListKeys => {
             let key = verify_one_item!(req.key, "'key' must be specified");
+            let level = match req.one_level {
+                Some(true) => ListType::OneLevel,
+                _ => ListType::All,
+            };
+
+            let response = injected
+                .data_sender
+                .list_keys(key, level, context::current())
+                .await;
+
+            match response {
+                Ok(list) => {
+                    let mut data = String::new();
+                    for key in list {
+                        data += key.get_type();
+                        data += " ";
+                        data += key.get_key();
+                        data += "\n";
+                    }
+                    WsResponse::new_ok(data)
+                }
+                Err(e) => WsResponse::from_error(e),
+            }
+        }
+        //
+        // Find records by value index, one key per line
+        //
+        CommandMethod::Find => {
+            let (key, value) =
+                verify_two_items!(req.key, req.value, "'key' and 'value' must be specified");
 
             let (tx, mut rx) = channel(10);
-            let action = DatabaseAction::ListKeys(tx, key, ListType::All);
+            let action = DatabaseAction::Find(tx, key, value);
             send_data_request!(action, injected.data_sender);
 
             match rx.recv().await {
@@ -151,18 +196,16 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
                     Ok(list) => {
                         let mut data = String::new();
                         for key in list {
-                            data += key.get_type();
-                            data += " ";
                             data += key.get_key();
                             data += "\n";
                         }
                         WsResponse::new_ok(data)
                     }
-                    Err(e) => WsResponse::new_err(e),
+                    Err(e) => WsResponse::from_error(e),
                 },
                 None => {
                     tracing::error!("failed to receive from database");
-                    WsResponse::new_err("internal server error")
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
                 }
             }
         }
@@ -180,11 +223,11 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => WsResponse::new_ok(""),
-                    Err(e) => WsResponse::new_err(e),
+                    Err(e) => WsResponse::from_error(e),
                 },
                 None => {
                     tracing::error!("failed to receive from database");
-                    WsResponse::new_err("internal server error")
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
                 }
             }
         }
@@ -208,11 +251,11 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
                         }
                         WsResponse::new_ok(response)
                     }
-                    Err(e) => WsResponse::new_err(e),
+                    Err(e) => WsResponse::from_error(e),
                 },
                 None => {
                     tracing::error!("failed to receive from database");
-                    WsResponse::new_err("internal server error")
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
                 }
             }
         }
@@ -233,11 +276,11 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => WsResponse::new_ok(""),
-                    Err(e) => WsResponse::new_err(e),
+                    Err(e) => WsResponse::from_error(e),
                 },
                 None => {
                     tracing::error!("failed to receive from database");
-                    WsResponse::new_err("internal server error")
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
                 }
             }
         }
@@ -258,11 +301,11 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => WsResponse::new_ok(""),
-                    Err(e) => WsResponse::new_err(e),
+                    Err(e) => WsResponse::from_error(e),
                 },
                 None => {
                     tracing::error!("failed to receive from database");
-                    WsResponse::new_err("internal server error")
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
                 }
             }
         }
@@ -285,11 +328,11 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
                         }
                         WsResponse::new_ok(response)
                     }
-                    Err(e) => WsResponse::new_err(e),
+                    Err(e) => WsResponse::from_error(e),
                 },
                 None => {
                     tracing::error!("failed to receive from database");
-                    WsResponse::new_err("internal server error")
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
                 }
             }
         }
@@ -304,11 +347,11 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => WsResponse::new_ok(""),
-                    Err(e) => WsResponse::new_err(e),
+                    Err(e) => WsResponse::from_error(e),
                 },
                 None => {
                     tracing::error!("failed to receive from database");
-                    WsResponse::new_err("internal server error")
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
                 }
             }
         }
@@ -323,15 +366,29 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => WsResponse::new_ok(""),
-                    Err(e) => WsResponse::new_err(e),
+                    Err(e) => WsResponse::from_error(e),
                 },
                 None => {
                     tracing::error!("failed to receive from database");
-                    WsResponse::new_err("internal server error")
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
                 }
             }
         }
         //
+        // List scripts that can be executed, one name per line
+        //
+        CommandMethod::ListScripts => {
+            let config = injected.config.read().await;
+            let mut response = String::new();
+            if let Some(scripts) = &config.scripts {
+                for script in &scripts.execs {
+                    response += script;
+                    response += "\n";
+                }
+            }
+            WsResponse::new_ok(response)
+        }
+        //
         // Execute lua script
         //
         CommandMethod::Exec => {
@@ -349,17 +406,20 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
             let old_pair = match rx.recv().await {
                 Some(response) => match response {
                     Ok(value) => match value {
-                        ValueType::RecordPointer(data) => Some((key.clone(), data)),
+                        ValueType::RecordPointer(data) => Some((key.clone(), data.to_string())),
                         _ => {
                             tracing::error!("Pointer must be Record but it was Table");
-                            return WsResponse::new_err("internal server error");
+                            return WsResponse::new_err(
+                                ErrorCode::Internal,
+                                "internal server error",
+                            );
                         }
                     },
                     Err(_) => None,
                 },
                 None => {
                     tracing::error!("failed to receive from database");
-                    return WsResponse::new_err("internal server error");
+                    return WsResponse::new_err(ErrorCode::Internal, "internal server error");
                 }
             };
 
@@ -369,9 +429,19 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
                 match &config.scripts {
                     Some(scr) => match scr.execs.contains(&script) {
                         true => scr.clone(),
-                        false => return WsResponse::new_err("requested script is not defined"),
+                        false => {
+                            return WsResponse::new_err(
+                                ErrorCode::InvalidKey,
+                                "requested script is not defined",
+                            )
+                        }
                     },
-                    None => return WsResponse::new_err("requested script is not defined"),
+                    None => {
+                        return WsResponse::new_err(
+                            ErrorCode::InvalidKey,
+                            "requested script is not defined",
+                        )
+                    }
                 }
             };
 
@@ -388,7 +458,7 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
                     for line in e.lines() {
                         tracing::error!("{}", line);
                     }
-                    return WsResponse::new_err("failed to execute script".to_string());
+                    return WsResponse::new_err(ErrorCode::ScriptError, "failed to execute script");
                 }
             };
 
@@ -409,12 +479,12 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
                             }
                             Err(e) => {
                                 tracing::debug!("failed to delete the key: {}", e);
-                                WsResponse::new_err(e)
+                                WsResponse::from_error(e)
                             }
                         },
                         None => {
                             tracing::error!("failed to receive from database");
-                            WsResponse::new_err("internal server error")
+                            WsResponse::new_err(ErrorCode::Internal, "internal server error")
                         }
                     }
                 } else {
@@ -431,12 +501,12 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
                             }
                             Err(e) => {
                                 tracing::debug!("failed to save key: {}", e);
-                                WsResponse::new_err(e)
+                                WsResponse::from_error(e)
                             }
                         },
                         None => {
                             tracing::error!("failed to receive from database");
-                            WsResponse::new_err("internal server error")
+                            WsResponse::new_err(ErrorCode::Internal, "internal server error")
                         }
                     }
                 }
@@ -456,16 +526,19 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
                         }
                         Err(e) => {
                             tracing::debug!("failed to set trigger: {}", e);
-                            WsResponse::new_err(e)
+                            WsResponse::from_error(e)
                         }
                     },
                     None => {
                         tracing::error!("failed to receive from database");
-                        WsResponse::new_err("internal server error")
+                        WsResponse::new_err(ErrorCode::Internal, "internal server error")
                     }
                 }
             } else {
-                WsResponse::new_err("After script was run, the new value is empty")
+                WsResponse::new_err(
+                    ErrorCode::ScriptError,
+                    "After script was run, the new value is empty",
+                )
             }
         }
         //
@@ -482,11 +555,11 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(_) => WsResponse::new_ok(""),
-                    Err(e) => WsResponse::new_err(e),
+                    Err(e) => WsResponse::from_error(e),
                 },
                 None => {
                     tracing::error!("failed to receive from database");
-                    WsResponse::new_err("internal server error")
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
                 }
             }
         }
@@ -495,22 +568,583 @@ async fn handle_request(req: WsRequest, injected: &InjectedData) -> WsResponse {
         //
         CommandMethod::Pop => {
             let key = verify_one_item!(req.key, "'key' must be specified");
+            let end = match req.end.as_deref().map(str::parse).transpose() {
+                Ok(end) => end.unwrap_or(QueueEnd::Front),
+                Err(e) => return WsResponse::from_error(e),
+            };
+
+            if let Some(visibility) = req.visibility {
+                if end != QueueEnd::Front {
+                    return WsResponse::new_err(
+                        ErrorCode::InvalidKey,
+                        "Visibility timeout is supported only at the front",
+                    );
+                }
+
+                let visibility = match queue_timeout(visibility) {
+                    Ok(visibility) => visibility,
+                    Err(e) => return WsResponse::from_error(e),
+                };
+
+                let (tx, mut rx) = channel(10);
+                let action = DatabaseAction::PopReliable(tx, key, visibility);
+                send_data_request!(action, injected.data_sender);
+
+                return match rx.recv().await {
+                    Some(response) => match response {
+                        Ok((id, value)) => WsResponse::new_delivery(value, id),
+                        Err(e) => WsResponse::from_error(e),
+                    },
+                    None => {
+                        tracing::error!("failed to receive from database");
+                        WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                    }
+                };
+            }
+
+            let wait = match req.wait.map(queue_timeout).transpose() {
+                Ok(wait) => wait,
+                Err(e) => return WsResponse::from_error(e),
+            };
 
             let (tx, mut rx) = channel(10);
-            let action = DatabaseAction::Pop(tx, key);
+            let action = match wait {
+                Some(wait) => DatabaseAction::PopWait(tx, key, end, wait),
+                None => DatabaseAction::PopFrom(tx, key, end),
+            };
             send_data_request!(action, injected.data_sender);
 
             match rx.recv().await {
                 Some(response) => match response {
                     Ok(value) => match value {
                         ValueType::RecordPointer(data) => WsResponse::new_ok(data),
-                        _ => WsResponse::new_err("Pointer must be Record but it was Table"),
+                        _ => WsResponse::new_err(
+                            ErrorCode::TypeMismatch,
+                            "Pointer must be Record but it was Table",
+                        ),
                     },
-                    Err(e) => WsResponse::new_err(e.to_string()),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Read item of queue without removing it
+        //
+        CommandMethod::Peek => {
+            let key = verify_one_item!(req.key, "'key' must be specified");
+            let end = match req.end.as_deref().map(str::parse).transpose() {
+                Ok(end) => end.unwrap_or(QueueEnd::Front),
+                Err(e) => return WsResponse::from_error(e),
+            };
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Peek(tx, key, end);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(value) => WsResponse::new_ok(value.get_value()),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Number of items in queue
+        //
+        CommandMethod::QueueLength => {
+            let key = verify_one_item!(req.key, "'key' must be specified");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::QueueLength(tx, key);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(length) => WsResponse::new_ok(length),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Acknowledge item that was popped with visibility timeout
+        //
+        CommandMethod::Ack => {
+            let id = verify_one_item!(req.id, "'id' must be specified");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Ack(tx, id);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(_) => WsResponse::new_ok(""),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Increment or decrement a counter
+        //
+        CommandMethod::Incr | CommandMethod::Decr => {
+            let key = verify_one_item!(req.key, "'key' must be specified");
+
+            let by = match req.value {
+                Some(value) => match value.trim().parse::<i64>() {
+                    Ok(by) => by,
+                    Err(_) => {
+                        return WsResponse::new_err(
+                            ErrorCode::InvalidKey,
+                            "'value' must be integer",
+                        )
+                    }
+                },
+                None => 1,
+            };
+            let by = match req.command {
+                CommandMethod::Decr => match by.checked_neg() {
+                    Some(by) => by,
+                    None => {
+                        return WsResponse::new_err(
+                            ErrorCode::InvalidKey,
+                            "'value' is out of range",
+                        )
+                    }
+                },
+                _ => by,
+            };
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Increment(tx, key, by);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(ValueType::RecordPointer(data)) => {
+                        WsResponse::new_typed(&data, data.get_type())
+                    }
+                    Ok(_) => WsResponse::new_err(ErrorCode::Internal, "internal server error"),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Patch a JSON record
+        //
+        CommandMethod::PatchKey => {
+            let (key, value) =
+                verify_two_items!(req.key, req.value, "'key' and 'value' must be specified");
+            let path = verify_one_item!(req.path, "'path' must be specified");
+
+            let value = match serde_json::from_str(&value) {
+                Ok(value) => value,
+                Err(e) => {
+                    return WsResponse::new_err(
+                        ErrorCode::InvalidKey,
+                        format!("Value must be JSON: {}", e),
+                    )
+                }
+            };
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Patch(tx, key, path, value);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(_) => WsResponse::new_ok(""),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Set field of a hash
+        //
+        CommandMethod::HashSet => {
+            let (key, value) =
+                verify_two_items!(req.key, req.value, "'key' and 'value' must be specified");
+            let field = verify_one_item!(req.field, "'field' must be specified");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::HashSet(tx, key, field, value);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(created) => WsResponse::new_ok(created),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Read field of a hash
+        //
+        CommandMethod::HashGet => {
+            let (key, field) =
+                verify_two_items!(req.key, req.field, "'key' and 'field' must be specified");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::HashGet(tx, key, field);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(value) => WsResponse::new_ok(value.get_value()),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Remove field of a hash
+        //
+        CommandMethod::HashDelete => {
+            let (key, field) =
+                verify_two_items!(req.key, req.field, "'key' and 'field' must be specified");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::HashDelete(tx, key, field);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(_) => WsResponse::new_ok(""),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Read every field of a hash as JSON object
+        //
+        CommandMethod::HashGetAll => {
+            let key = verify_one_item!(req.key, "'key' must be specified");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::HashGetAll(tx, key);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(fields) => WsResponse::new_ok(serde_json::json!(fields)),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Add member to a set
+        //
+        CommandMethod::SetAdd => {
+            let (key, member) =
+                verify_two_items!(req.key, req.value, "'key' and 'value' must be specified");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::SetAdd(tx, key, member);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(added) => WsResponse::new_ok(added),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Remove member from a set
+        //
+        CommandMethod::SetRemove => {
+            let (key, member) =
+                verify_two_items!(req.key, req.value, "'key' and 'value' must be specified");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::SetRemove(tx, key, member);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(removed) => WsResponse::new_ok(removed),
+                    Err(e) => WsResponse::from_error(e),
                 },
                 None => {
                     tracing::error!("failed to receive from database");
-                    WsResponse::new_err("internal server error")
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Read every member of a set as JSON array
+        //
+        CommandMethod::SetMembers => {
+            let key = verify_one_item!(req.key, "'key' must be specified");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::SetMembers(tx, key);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(members) => WsResponse::new_ok(serde_json::json!(members)),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Check that value is member of a set
+        //
+        CommandMethod::SetIsMember => {
+            let (key, member) =
+                verify_two_items!(req.key, req.value, "'key' and 'value' must be specified");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::SetIsMember(tx, key, member);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(found) => WsResponse::new_ok(found),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Export a table with everything under it
+        //
+        CommandMethod::Export => {
+            let key = verify_one_item!(req.key, "'key' must be specified");
+            let format = match req
+                .format
+                .as_deref()
+                .unwrap_or("json")
+                .parse::<SnapshotFormat>()
+            {
+                Ok(format) => format,
+                Err(e) => return WsResponse::from_error(e),
+            };
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Export(tx, key);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response.and_then(|x| x.encode(format)) {
+                    Ok(data) if format == SnapshotFormat::Binary => {
+                        WsResponse::new_ok(base64::engine::general_purpose::STANDARD.encode(data))
+                    }
+                    Ok(data) => WsResponse::new_ok(String::from_utf8_lossy(&data)),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Import a snapshot
+        //
+        CommandMethod::Import => {
+            let value = verify_one_item!(req.value, "'value' must be specified");
+            let format = match req
+                .format
+                .as_deref()
+                .unwrap_or("json")
+                .parse::<SnapshotFormat>()
+            {
+                Ok(format) => format,
+                Err(e) => return WsResponse::from_error(e),
+            };
+            let policy = match req
+                .policy
+                .as_deref()
+                .unwrap_or("merge")
+                .parse::<ImportPolicy>()
+            {
+                Ok(policy) => policy,
+                Err(e) => return WsResponse::from_error(e),
+            };
+
+            let data = match format {
+                SnapshotFormat::Binary => {
+                    match base64::engine::general_purpose::STANDARD.decode(value) {
+                        Ok(data) => data,
+                        Err(e) => {
+                            return WsResponse::new_err(
+                                ErrorCode::InvalidKey,
+                                format!("Binary snapshot must be base64 encoded: {}", e),
+                            )
+                        }
+                    }
+                }
+                _ => value.into_bytes(),
+            };
+
+            let snapshot = match Snapshot::decode(&data, format) {
+                Ok(snapshot) => snapshot,
+                Err(e) => return WsResponse::from_error(e),
+            };
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Import(tx, snapshot, policy);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(summary) => WsResponse::new_ok(serde_json::json!(summary)),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Read the past changes of a key as JSON array
+        //
+        CommandMethod::History => {
+            let key = verify_one_item!(req.key, "'key' must be specified");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::History(tx, key);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(history) => {
+                        let changes: Vec<serde_json::Value> = history
+                            .into_iter()
+                            .map(|x| {
+                                serde_json::json!({
+                                    "time": format_time(x.time.as_nanos()),
+                                    "action": x.action,
+                                    "field": x.field,
+                                    "value": x.value,
+                                    "context": x.context,
+                                })
+                            })
+                            .collect();
+                        WsResponse::new_ok(serde_json::json!(changes))
+                    }
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Read the metadata of a key or a table as JSON array
+        //
+        CommandMethod::Stat => {
+            let key = verify_one_item!(req.key, "'key' must be specified");
+
+            let (tx, mut rx) = channel(10);
+            let action = DatabaseAction::Stat(tx, key);
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(stats) => {
+                        let stats: Vec<serde_json::Value> = stats
+                            .into_iter()
+                            .map(|x| {
+                                serde_json::json!({
+                                    "key": x.key,
+                                    "kind": x.kind,
+                                    "size": x.size,
+                                    "length": x.length,
+                                    "created": x.created.map(|x| format_time(x.as_nanos())),
+                                    "modified": x.modified.map(|x| format_time(x.as_nanos())),
+                                    "table": x.table,
+                                })
+                            })
+                            .collect();
+                        WsResponse::new_ok(serde_json::json!(stats))
+                    }
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
+                }
+            }
+        }
+        //
+        // Move or copy a key or a table, value is the destination
+        //
+        CommandMethod::Move | CommandMethod::Copy => {
+            let (key, destination) =
+                verify_two_items!(req.key, req.value, "'key' and 'value' must be specified");
+            let key = match KeyType::from_kind(req.r#type.as_deref().unwrap_or("record"), key) {
+                Ok(key) => key,
+                Err(e) => return WsResponse::from_error(e),
+            };
+
+            let (tx, mut rx) = channel(10);
+            let action = match req.command {
+                CommandMethod::Move => DatabaseAction::Move(tx, key, destination),
+                _ => DatabaseAction::Copy(tx, key, destination),
+            };
+            send_data_request!(action, injected.data_sender);
+
+            match rx.recv().await {
+                Some(response) => match response {
+                    Ok(_) => WsResponse::new_ok(""),
+                    Err(e) => WsResponse::from_error(e),
+                },
+                None => {
+                    tracing::error!("failed to receive from database");
+                    WsResponse::new_err(ErrorCode::Internal, "internal server error")
                 }
             }
         }
@@ -547,7 +1181,14 @@ async fn handle_socket(mut socket: WebSocket, who: SocketAddr, injected: Injecte
                         }
                     };
 
-                    let response = handle_request(request, &injected).await;
+                    let context = RequestContext {
+                        interface: String::from("websocket"),
+                        peer: Some(who.to_string()),
+                        claimed_user: None,
+                        request_id: Some(context::next_request_id()),
+                    };
+                    let response =
+                        context::scope(context, handle_request(request, &injected)).await;
                     match serde_json::to_string(&response) {
                         Ok(str) => send_data_back!(socket, Message::Text(str)),
                         Err(e) => {
@@ -633,18 +1274,14 @@ async fn ws_handler(
 /// Start the websocket server
 ///
 /// # Parameters
-/// - `data_sender`: Sender to send data to database thread
+/// - `data_sender`: Router to send data to the database threads
 /// - `address`: where it should listen
 /// - `config`: application configuration
 ///
 /// # Details
 ///
 /// This is called to run this interface. `data_sender` and `config` will be shared in endpoints.
-pub async fn run_async(
-    data_sender: Arc<Mutex<Sender<DatabaseAction>>>,
-    address: String,
-    config: Arc<RwLock<Config>>,
-) {
+pub async fn run_async(data_sender: DatastoreRouter, address: String, config: Arc<RwLock<Config>>) {
     tracing::info!("Websocket interface on {} is starting...", address);
 
     let app = Router::new()
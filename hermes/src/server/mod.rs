@@ -1,27 +1,34 @@
+use onlyati_datastore::datastore::enums::DatabaseAction;
+use onlyati_datastore::datastore::router::DatastoreRouter;
 use std::sync::Arc;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::RwLock;
 
 mod interfaces;
 mod utilities;
 
 use interfaces::classic::Classic;
-use interfaces::dummy::Dummy;
+use interfaces::dummy::{Databases, Dummy};
+use interfaces::grpc::Grpc;
+use interfaces::mqtt::Mqtt;
+use interfaces::resp::Resp;
 use interfaces::rest::Rest;
 use interfaces::websocket::Websocket;
 use interfaces::ApplicationInterface;
 use interfaces::InterfaceHandler;
+use utilities::config_parse::StorageKind;
 
 /// Main entrypoint when Hermes run as a server
 ///
 /// # Parameters
 /// - `args`: Command arguments that has been parse bly `clap`.
+/// - `restore_until`: If specified, state of this time is restored from the append file and later entries are cut off from it
 ///
 /// # Details
 ///
 /// This function start the server by the following stpes:
 /// 1. Initialize tracer
 /// 1. Read configuration that path has been passed as argument
-/// 1. Initialize datastore, logger and hook manager
+/// 1. Initialize databases with their logger and hook manager
 /// 1. Register interfaces that has been enabled in the configueration file
 /// 1. Register handler for interrupt and terminate signals (for graceful shutdown)
 /// 1. Start registered interfaces and if any of them fails, then stop the application
@@ -29,7 +36,10 @@ use interfaces::InterfaceHandler;
 /// # Return
 ///
 /// This function return with a code normally. If something error would occure then with the error itself.
-pub async fn main_async(args: String) -> Result<i32, Box<dyn std::error::Error>> {
+pub async fn main_async(
+    args: String,
+    restore_until: Option<String>,
+) -> Result<i32, Box<dyn std::error::Error>> {
     // Read environment variable and set trace accordingly, default is Level::ERROR
     let subscriber = tracing_subscriber::FmtSubscriber::builder()
         .with_env_filter(tracing_subscriber::EnvFilter::from_env("HERMES_LOG"))
@@ -41,8 +51,8 @@ pub async fn main_async(args: String) -> Result<i32, Box<dyn std::error::Error>>
     std::panic::set_hook(Box::new(move |info| {
         for line in info.to_string().lines() {
             tracing::error!("{}", line);
-            std::process::exit(-1);
         }
+        std::process::exit(-1);
     }));
 
     // Read configuration
@@ -55,52 +65,95 @@ pub async fn main_async(args: String) -> Result<i32, Box<dyn std::error::Error>>
     };
     let config_arc = Arc::new(RwLock::new(config.clone()));
 
-    // Initialize HookManager and Logger for Datastore)
-    let (hook_sender, hook_thread) = onlyati_datastore::hook::utilities::start_hook_manager().await;
-    let (logger_sender, logger_thread) = if config.general.logging {
-        let path = config.logger.unwrap().location;
-        let (a, b) = onlyati_datastore::logger::utilities::start_logger(&path).await;
-        (Some(a), b)
-    } else {
-        (None, tokio::spawn(async move {}))
+    let restore_until = match restore_until.map(|x| crate::common::parse_time(&x)) {
+        Some(Ok(_)) if !config.general.logging => {
+            tracing::error!("Point-in-time restore needs logging to be enabled");
+            return Ok(1);
+        }
+        Some(Ok(_)) if config.general.storage != StorageKind::Memory => {
+            tracing::error!("Point-in-time restore works only with memory storage");
+            return Ok(1);
+        }
+        Some(Err(e)) => {
+            tracing::error!("{}", e);
+            return Ok(1);
+        }
+        Some(Ok(until)) => Some(until),
+        None => None,
     };
 
-    // Initialize Datastore
-    let (sender, db_thread) = onlyati_datastore::datastore::utilities::start_datastore(
-        config.general.database_name,
-        Some(hook_sender),
-        logger_sender,
+    // Initialize default database with its HookManager and Logger
+    let (handle, tasks) = match utilities::database::start_database(
+        &config,
+        &config.general.database_name,
+        config.general.logging,
+        restore_until,
     )
-    .await;
+    .await
+    {
+        Ok(database) => database,
+        Err(e) => {
+            tracing::error!("Failed to start database: {}", e);
+            return Ok(1);
+        }
+    };
+    let sender = DatastoreRouter::new(config.general.database_name.clone(), handle);
+
+    // Initialize additional databases, their tasks stop when they are dropped
+    let databases = Databases::default();
+    for database in &config.databases {
+        let logging = database.logging.unwrap_or(config.general.logging);
+        let restore_until = restore_until.filter(|_| logging);
+        let handle = match utilities::database::start_database(
+            &config,
+            &database.name,
+            logging,
+            restore_until,
+        )
+        .await
+        {
+            Ok((handle, tasks)) => {
+                databases.insert(database.name.clone(), tasks);
+                handle
+            }
+            Err(e) => {
+                tracing::error!("Failed to start database '{}': {}", database.name, e);
+                return Ok(1);
+            }
+        };
+        sender
+            .insert(database.name.clone(), handle)
+            .map_err(|e| e.to_string())?;
+    }
 
     // Parse the input data for database and hooks too
     utilities::initial_parse::parse_initial_file(&config.initials.path, &sender)
         .await
         .unwrap_or_else(|x| panic!("{}", x));
 
-    let sender = Arc::new(Mutex::new(sender));
-
     // Create interface handler
     let mut handler: InterfaceHandler<Box<dyn ApplicationInterface>> = InterfaceHandler::new();
 
     // Register the monitor only interfaces
     handler.register_interface(
-        Box::new(Dummy::new(Some(hook_thread))),
+        Box::new(Dummy::new(Some(tasks.hook_manager))),
         "HookManager".to_string(),
     );
 
     handler.register_interface(
-        Box::new(Dummy::new(Some(db_thread))),
+        Box::new(Dummy::new(Some(tasks.datastore))),
         "Datastore".to_string(),
     );
 
-    if config.general.logging {
+    if let Some(logger_thread) = tasks.logger {
         handler.register_interface(
             Box::new(Dummy::new(Some(logger_thread))),
             "Logger".to_string(),
         );
     }
 
+    handler.register_interface(Box::new(databases.clone()), "Databases".to_string());
+
     // Register classic interface
     if let Some(addr) = &config.network.classic {
         let config = config_arc.clone();
@@ -114,7 +167,12 @@ pub async fn main_async(args: String) -> Result<i32, Box<dyn std::error::Error>>
     if let Some(addr) = &config.network.rest {
         let config = config_arc.clone();
         handler.register_interface(
-            Box::new(Rest::new(sender.clone(), addr.clone(), config)),
+            Box::new(Rest::new(
+                sender.clone(),
+                databases.clone(),
+                addr.clone(),
+                config,
+            )),
             "REST".to_string(),
         )
     }
@@ -128,6 +186,33 @@ pub async fn main_async(args: String) -> Result<i32, Box<dyn std::error::Error>>
         )
     }
 
+    // Register RESP interface
+    if let Some(addr) = &config.network.resp {
+        let config = config_arc.clone();
+        handler.register_interface(
+            Box::new(Resp::new(sender.clone(), addr.clone(), config)),
+            "RESP".to_string(),
+        )
+    }
+
+    // Register gRPC interface
+    if let Some(addr) = &config.network.grpc {
+        let config = config_arc.clone();
+        handler.register_interface(
+            Box::new(Grpc::new(sender.clone(), addr.clone(), config)),
+            "gRPC".to_string(),
+        )
+    }
+
+    // Register MQTT bridge
+    if config.mqtt.is_some() {
+        let config = config_arc.clone();
+        handler.register_interface(
+            Box::new(Mqtt::new(sender.clone(), config)),
+            "MQTT".to_string(),
+        )
+    }
+
     // Start interfaces and watch them
     handler.start();
 
@@ -154,18 +239,28 @@ pub async fn main_async(args: String) -> Result<i32, Box<dyn std::error::Error>>
 
     // Start application
     tracing::debug!("service is starting");
-    tokio::select! {
+    let rc = tokio::select! {
         _ = handler.watch() => {
             tracing::error!("application has been stopped");
-            Ok(-16)
+            -16
         }
         _ = terminate.recv() => {
             tracing::info!("stop signal has recieved");
-            Ok(-8)
+            -8
         }
         _ = interrupt.recv() => {
             tracing::info!("interrupt signal has recieved");
-            Ok(-8)
+            -8
+        }
+    };
+
+    // Process exits without drop, so storages must write their pending changes before
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+    if sender.send(DatabaseAction::Flush(tx)).await.is_ok() {
+        if let Some(Err(e)) = rx.recv().await {
+            tracing::error!("Failed to flush storage: {}", e);
         }
     }
+
+    Ok(rc)
 }
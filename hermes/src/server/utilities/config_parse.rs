@@ -1,3 +1,5 @@
+use onlyati_datastore::datastore::limits::{self, EvictionPolicy, QueueLimit};
+use onlyati_datastore::datastore::schema::Schema;
 use serde::Deserialize;
 
 /// Represent a general table in config toml file
@@ -6,11 +8,90 @@ use serde::Deserialize;
 /// ```
 /// database_name = "hermes1"     # Name of database, this is the root for each key
 /// logging = true                # Logging into a file to keep persistency or just use in-memory
+/// storage = "sled"              # Optional, where tables are kept: "memory" or "sled", default is "memory"
+/// storage_path = "/var/lib/hermes/db"   # Directory of sled database, mandatory if storage is "sled"
 /// ```
 #[derive(Deserialize, Clone, Debug, Default)]
 pub struct General {
     pub database_name: String,
     pub logging: bool,
+    #[serde(default)]
+    pub storage: StorageKind,
+    pub storage_path: Option<String>,
+}
+
+/// Storage backend of database
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageKind {
+    /// Tables are kept in memory, append file is replayed at start
+    #[default]
+    Memory,
+
+    /// Tables are kept in a sled database on the disk
+    Sled,
+}
+
+/// Represent a databases entry in config toml file, that is an additional database next to the
+/// one of `general.database_name`. Its append file is written into `{logger.location}/{name}`
+/// and its sled database into `{general.storage_path}/{name}`.
+///
+/// # Example
+/// ```toml
+/// [[databases]]
+/// name = "team1"              # Name of database, this is the root for each key, e.g. /team1/status
+/// logging = false             # Optional, default is general.logging
+///
+/// [databases.limits]          # Optional, default is the limits table
+/// max_keys = 1000
+/// ```
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct Database {
+    pub name: String,
+    pub logging: Option<bool>,
+    pub limits: Option<Limits>,
+}
+
+/// Represent a limits table in config toml file, nothing is limited without it
+///
+/// # Example
+/// ```toml
+/// [limits]
+/// max_memory = 512                    # Optional, estimated size of keys and values in MB
+/// max_keys = 100000                   # Optional, number of records, queues, hashes and sets
+/// max_value_size = 65536              # Optional, size of a single value in bytes
+/// policy = "lru"                      # What happens at limit: "reject", "lru", "lfu" or "oldest"
+/// evict_prefixes = ["/root/cache"]    # Records under them can be evicted, default is none
+///
+/// [[limits.queues]]
+/// prefix = "/root/jobs"               # Queues under this prefix are limited
+/// max_length = 1000                   # Full queue drops its oldest item if it is under evict_prefixes
+/// ```
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct Limits {
+    pub max_memory: Option<usize>,
+    pub max_keys: Option<usize>,
+    pub max_value_size: Option<usize>,
+    #[serde(default)]
+    pub policy: EvictionPolicy,
+    #[serde(default)]
+    pub evict_prefixes: Vec<String>,
+    #[serde(default)]
+    pub queues: Vec<QueueLimit>,
+}
+
+impl Limits {
+    /// Convert to the limits of database, memory is changed to bytes
+    pub fn to_limits(&self) -> limits::Limits {
+        limits::Limits {
+            max_memory: self.max_memory.map(|x| x * 1024 * 1024),
+            max_keys: self.max_keys,
+            max_value_size: self.max_value_size,
+            queues: self.queues.clone(),
+            policy: self.policy,
+            evict_prefixes: self.evict_prefixes.clone(),
+        }
+    }
 }
 
 /// Represent a network table in config toml file
@@ -21,12 +102,16 @@ pub struct General {
 /// classic = "127.0.0.1:3031"     # Classic TCP interface bind to this address
 /// rest = "0.0.0.0:3032"          # REST interface bind to this address
 /// websocket = "127.0.0.1:3033"   # Websocket interface bind to this address
+/// resp = "127.0.0.1:3034"        # Redis compatible (RESP) interface bind to this address
+/// grpc = "127.0.0.1:3035"        # gRPC interface bind to this address
 /// ```
 #[derive(Deserialize, Clone, Debug, Default)]
 pub struct Network {
     pub classic: Option<String>,
     pub rest: Option<String>,
     pub websocket: Option<String>,
+    pub resp: Option<String>,
+    pub grpc: Option<String>,
 }
 
 /// Represent a initials table in config toml file
@@ -48,10 +133,18 @@ pub struct Initials {
 /// ```toml
 /// [logger]
 /// location = "/tmp/hermes-datastore-test.txt" # Which file should the database log written
+/// rotate_size = 64                              # Rotate human.log at 64 MB (optional)
+/// rotate_interval = 86400                       # Rotate human.log daily (optional)
+/// retention = 5                                 # Number of rotated logs kept (default 5)
+/// compress = true                               # Compress rotated logs with gzip (default false)
 /// ```
 #[derive(Deserialize, Clone, Debug, Default)]
 pub struct Logger {
     pub location: String,
+    pub rotate_size: Option<u64>,
+    pub rotate_interval: Option<u64>,
+    pub retention: Option<usize>,
+    pub compress: Option<bool>,
 }
 
 /// Represent a scripts table in config toml file
@@ -91,6 +184,155 @@ pub struct Gitea {
     pub key_base: String,
 }
 
+/// Action that is done with the payload of a subscribed MQTT topic
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MqttAction {
+    /// Payload is saved as value of the key
+    Set,
+
+    /// Payload is pushed into the queue
+    Push,
+}
+
+/// Represent a publish entry in mqtt table of config toml file.
+/// Changes under `prefix` are published onto `topic`, the rest of the key is appended to the topic.
+///
+/// # Example
+/// ```toml
+/// [[mqtt.publish]]
+/// prefix = "/root/status"     # Changes under this prefix are published
+/// topic = "hermes/status"     # For example /root/status/x is published onto hermes/status/x
+/// qos = 1                     # Optional, default is 0
+/// retain = true               # Optional, default is false
+/// ```
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct MqttPublish {
+    pub prefix: String,
+    pub topic: String,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+}
+
+/// Represent a subscribe entry in mqtt table of config toml file.
+/// Levels of the incoming topic, that are matched by wildcards, are appended to `key`.
+///
+/// # Example
+/// ```toml
+/// [[mqtt.subscribe]]
+/// topic = "devices/#"         # Topic filter to subscribe
+/// key = "/root/devices"       # For example devices/a/temp is saved onto /root/devices/a/temp
+/// action = "set"              # What to do with payload: "set" or "push"
+/// script = "transform.lua"    # Optional Lua script from scripts.execs that is run before save
+/// qos = 1                     # Optional, default is 0
+/// ```
+#[derive(Deserialize, Clone, Debug)]
+pub struct MqttSubscribe {
+    pub topic: String,
+    pub key: String,
+    pub action: MqttAction,
+    pub script: Option<String>,
+    pub qos: Option<u8>,
+}
+
+/// Represent a filter entry in config toml file.
+/// Script is called before every set and push under `prefix` and it can change, redirect or reject the write.
+///
+/// # Example
+/// ```toml
+/// [[filters]]
+/// prefix = "/root/status"     # Writes under this prefix are filtered
+/// script = "normalize.lua"    # Lua script from scripts.execs
+/// ```
+#[derive(Deserialize, Clone, Debug)]
+pub struct Filter {
+    pub prefix: String,
+    pub script: String,
+}
+
+/// Represent an index entry in config toml file.
+/// Records under `prefix` are indexed by their value, so they can be found by value without listing.
+///
+/// # Example
+/// ```toml
+/// [[indexes]]
+/// prefix = "/root/status"     # Records under this prefix are indexed
+/// ```
+#[derive(Deserialize, Clone, Debug)]
+pub struct Index {
+    pub prefix: String,
+}
+
+/// Function of a computed key
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ComputedFunction {
+    /// Number of records, or records with `value`
+    Count,
+
+    /// Sum of numeric records
+    Sum,
+
+    /// Smallest numeric record
+    Min,
+
+    /// Largest numeric record
+    Max,
+
+    /// Time of last change in nanoseconds since epoch
+    LastUpdated,
+
+    /// Result of Lua `expression`
+    Lua,
+}
+
+/// Represent a computed entry in config toml file.
+/// Key is maintained by the server from the records under `source`, clients can only read it.
+///
+/// # Example
+/// ```toml
+/// [[computed]]
+/// key = "/root/summary/nok_count"   # Computed record, it is read-only for clients
+/// source = "/root/status"           # Records under this prefix are the input
+/// function = "count"                # count, sum, min, max, last_updated or lua
+/// value = "NOK"                     # Optional for count, only records with this value are counted
+/// expression = "#records"           # Mandatory for lua, records is a table of key-value pairs
+/// ```
+#[derive(Deserialize, Clone, Debug)]
+pub struct Computed {
+    pub key: String,
+    pub source: String,
+    pub function: ComputedFunction,
+    pub value: Option<String>,
+    pub expression: Option<String>,
+}
+
+/// Represent a mqtt table in config toml file
+///
+/// # Example
+/// ```toml
+/// [mqtt]
+/// host = "127.0.0.1"          # Address of MQTT broker
+/// port = 1883                 # Port of MQTT broker
+/// client_id = "hermes"        # Optional, default is "hermes"
+/// username = "hermes"         # Optional
+/// password = "secret"         # Optional
+/// keep_alive = 30             # Optional, in seconds, default is 30
+/// ```
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct Mqtt {
+    pub host: String,
+    pub port: u16,
+    pub client_id: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub keep_alive: Option<u64>,
+    #[serde(default)]
+    pub publish: Vec<MqttPublish>,
+    #[serde(default)]
+    pub subscribe: Vec<MqttSubscribe>,
+}
+
 /// Represent the whole config.toml file
 #[derive(Deserialize, Clone, Debug, Default)]
 pub struct Config {
@@ -100,6 +342,127 @@ pub struct Config {
     pub logger: Option<Logger>,
     pub scripts: Option<Scripts>,
     pub gitea: Option<Gitea>,
+    pub mqtt: Option<Mqtt>,
+    #[serde(default)]
+    pub databases: Vec<Database>,
+    pub limits: Option<Limits>,
+    /// Schemas restrict the keys and values under their prefix, they belong to the database of their prefix root
+    ///
+    /// # Example
+    /// ```toml
+    /// [[schemas]]
+    /// prefix = "/root/status"                 # Keys under this prefix are checked
+    /// enum = ["OK", "NOK"]                    # Optional, allowed values
+    /// pattern = "OK|NOK"                      # Optional, regular expression that the whole value must match
+    /// max_length = 16                         # Optional, maximum number of characters of value
+    /// key_pattern = "/root/status/[a-z0-9-]+" # Optional, regular expression that the whole key must match
+    /// queues = false                          # Optional, queues can be created under the prefix, default is true
+    ///
+    /// [schemas.json_schema]                   # Optional, JSON Schema of the value
+    /// type = "object"
+    /// required = ["cpu"]
+    /// ```
+    #[serde(default)]
+    pub schemas: Vec<Schema>,
+    #[serde(default)]
+    pub filters: Vec<Filter>,
+    #[serde(default)]
+    pub computed: Vec<Computed>,
+    #[serde(default)]
+    pub indexes: Vec<Index>,
+}
+
+impl Config {
+    /// Return with the limits of a database: its own limits if it is defined in databases,
+    /// else the limits table
+    ///
+    /// # Parameters
+    /// - `name`: Name of database
+    pub fn limits_of(&self, name: &str) -> Option<&Limits> {
+        self.databases
+            .iter()
+            .find(|x| x.name == name)
+            .and_then(|x| x.limits.as_ref())
+            .or(self.limits.as_ref())
+    }
+
+    /// Return with the schemas whose prefix is under the root of database
+    ///
+    /// # Parameters
+    /// - `name`: Name of database
+    pub fn schemas_of(&self, name: &str) -> Vec<Schema> {
+        let root = format!("/{}", name);
+        self.schemas
+            .iter()
+            .filter(|x| x.prefix == root || x.prefix.starts_with(&format!("{}/", root)))
+            .cloned()
+            .collect()
+    }
+
+    /// Return with the filters whose prefix is under the root of database, in order of config
+    ///
+    /// # Parameters
+    /// - `name`: Name of database
+    pub fn filters_of(&self, name: &str) -> Vec<Filter> {
+        let root = format!("/{}", name);
+        self.filters
+            .iter()
+            .filter(|x| x.prefix == root || x.prefix.starts_with(&format!("{}/", root)))
+            .cloned()
+            .collect()
+    }
+
+    /// Return with the indexed prefixes that are under the root of database
+    ///
+    /// # Parameters
+    /// - `name`: Name of database
+    pub fn indexes_of(&self, name: &str) -> Vec<String> {
+        let root = format!("/{}", name);
+        self.indexes
+            .iter()
+            .filter(|x| x.prefix == root || x.prefix.starts_with(&format!("{}/", root)))
+            .map(|x| x.prefix.clone())
+            .collect()
+    }
+
+    /// Return with the computed keys whose key is under the root of database
+    ///
+    /// # Parameters
+    /// - `name`: Name of database
+    pub fn computed_of(&self, name: &str) -> Vec<Computed> {
+        let root = format!("/{}", name);
+        self.computed
+            .iter()
+            .filter(|x| x.key.starts_with(&format!("{}/", root)))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Check that name can be used as database name, it is the root of keys and a directory name too
+///
+/// # Parameters
+/// - `name`: Name of database
+///
+/// # Return
+///
+/// With Ok(()) if name is valid, else with Err(String) that describes the problem.
+pub fn validate_database_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("database name cannot be empty".to_string());
+    }
+
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "database name '{}' may contain only letters, digits, '-' and '_'",
+            name
+        ));
+    }
+
+    Ok(())
 }
 
 /// This function parse the passed toml config file and create a struct based on it.
@@ -123,6 +486,8 @@ pub fn parse_config(config_path: &String) -> Result<Config, String> {
     if config.network.classic.is_none()
         && config.network.rest.is_none()
         && config.network.websocket.is_none()
+        && config.network.resp.is_none()
+        && config.network.grpc.is_none()
     {
         return Err(String::from("At least one interface must be enabled"));
     }
@@ -131,13 +496,70 @@ pub fn parse_config(config_path: &String) -> Result<Config, String> {
     tracing::info!("Config settings:");
     tracing::info!("- general.database_name: {}", config.general.database_name);
     tracing::info!("- general.logging: {}", config.general.logging);
+    tracing::info!("- general.storage: {:?}", config.general.storage);
+    match (&config.general.storage, &config.general.storage_path) {
+        (StorageKind::Sled, Some(path)) => tracing::info!("- general.storage_path: {}", path),
+        (StorageKind::Sled, None) => {
+            return Err("storage is sled but no storage_path defined".to_string())
+        }
+        _ => (),
+    }
+    for (i, database) in config.databases.iter().enumerate() {
+        validate_database_name(&database.name)?;
+        if database.name == config.general.database_name
+            || config.databases[..i]
+                .iter()
+                .any(|x| x.name == database.name)
+        {
+            return Err(format!(
+                "database '{}' is defined more times",
+                database.name
+            ));
+        }
+        if database.logging.unwrap_or(config.general.logging) && config.logger.is_none() {
+            return Err(format!(
+                "database '{}' has logging but no logger location defined",
+                database.name
+            ));
+        }
+        tracing::info!(
+            "- databases: {} (logging: {})",
+            database.name,
+            database.logging.unwrap_or(config.general.logging)
+        );
+    }
+    if let Some(limits) = &config.limits {
+        tracing::info!("- limits.max_memory: {:?} MB", limits.max_memory);
+        tracing::info!("- limits.max_keys: {:?}", limits.max_keys);
+        tracing::info!("- limits.max_value_size: {:?}", limits.max_value_size);
+        tracing::info!("- limits.policy: {}", limits.policy);
+        tracing::info!("- limits.evict_prefixes: {:?}", limits.evict_prefixes);
+        for queue in &limits.queues {
+            tracing::info!("- limits.queues: {} ({})", queue.prefix, queue.max_length);
+        }
+    }
+    for schema in &config.schemas {
+        tracing::info!("- schemas: {}", schema.prefix);
+    }
     tracing::info!("- network.classic: {:?}", config.network.classic);
     tracing::info!("- network.rest: {:?}", config.network.rest);
     tracing::info!("- network.websocket: {:?}", config.network.websocket);
+    tracing::info!("- network.resp: {:?}", config.network.resp);
+    tracing::info!("- network.grpc: {:?}", config.network.grpc);
     tracing::info!("- initials.path: {}", config.initials.path);
 
     if let Some(logger) = &config.logger {
         tracing::info!("- logger.location: {}", logger.location);
+        if let Some(size) = logger.rotate_size {
+            tracing::info!("- logger.rotate_size: {} MB", size);
+        }
+        if let Some(interval) = logger.rotate_interval {
+            tracing::info!("- logger.rotate_interval: {} s", interval);
+        }
+        if logger.rotate_size.is_some() || logger.rotate_interval.is_some() {
+            tracing::info!("- logger.retention: {}", logger.retention.unwrap_or(5));
+            tracing::info!("- logger.compress: {}", logger.compress.unwrap_or(false));
+        }
     } else if config.general.logging {
         return Err("parameter mem_only is true but no logger location defined".to_string());
     }
@@ -172,6 +594,52 @@ pub fn parse_config(config_path: &String) -> Result<Config, String> {
         tracing::debug!("- scripts.execs: {:?}", scripts.execs);
     }
 
+    // Filters are called on every write, so their scripts must exist
+    for filter in &config.filters {
+        tracing::info!("- filters: {} -> {}", filter.prefix, filter.script);
+        let defined = match &config.scripts {
+            Some(scripts) => scripts.execs.contains(&filter.script),
+            None => false,
+        };
+        if !defined {
+            return Err(format!(
+                "script '{}' of filters is not defined in scripts.execs",
+                filter.script
+            ));
+        }
+    }
+
+    for index in &config.indexes {
+        tracing::info!("- indexes: {}", index.prefix);
+    }
+
+    // Computed keys are maintained in the database of their key, source must be there too
+    for computed in &config.computed {
+        tracing::info!(
+            "- computed: {} <- {} ({:?})",
+            computed.key,
+            computed.source,
+            computed.function
+        );
+        let root = |key: &str| {
+            key.split('/')
+                .find(|x| !x.is_empty())
+                .map(|x| x.to_string())
+        };
+        if root(&computed.key) != root(&computed.source) {
+            return Err(format!(
+                "computed key '{}' and its source must be in the same database",
+                computed.key
+            ));
+        }
+        if computed.function == ComputedFunction::Lua && computed.expression.is_none() {
+            return Err(format!(
+                "computed key '{}' has lua function without expression",
+                computed.key
+            ));
+        }
+    }
+
     // If Gitea plugin is active display its settings
     if let Some(gitea) = &config.gitea {
         tracing::info!("- gitea.enable: {}", gitea.enable);
@@ -179,5 +647,38 @@ pub fn parse_config(config_path: &String) -> Result<Config, String> {
         tracing::info!("- gitea.key_base: {}", gitea.key_base);
     }
 
+    // If MQTT bridge is active display its settings
+    if let Some(mqtt) = &config.mqtt {
+        tracing::info!("- mqtt.host: {}:{}", mqtt.host, mqtt.port);
+        tracing::info!("- mqtt.client_id: {:?}", mqtt.client_id);
+        for publish in &mqtt.publish {
+            tracing::info!("- mqtt.publish: {} -> {}", publish.prefix, publish.topic);
+        }
+        for subscribe in &mqtt.subscribe {
+            tracing::info!(
+                "- mqtt.subscribe: {} -> {} ({:?}, script: {:?})",
+                subscribe.topic,
+                subscribe.key,
+                subscribe.action,
+                subscribe.script
+            );
+
+            if let Some(script) = &subscribe.script {
+                let defined = match &config.scripts {
+                    Some(scripts) => scripts.execs.contains(script),
+                    None => false,
+                };
+                if !defined {
+                    return Err(format!(
+                        "script '{}' of mqtt.subscribe is not defined in scripts.execs",
+                        script
+                    ));
+                }
+            }
+        }
+
+        crate::server::interfaces::mqtt::check_loops(mqtt)?;
+    }
+
     Ok(config)
 }
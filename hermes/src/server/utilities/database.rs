@@ -0,0 +1,216 @@
+use onlyati_datastore::datastore::computed::{ComputedKey, Function};
+use onlyati_datastore::datastore::enums::DatabaseAction;
+use onlyati_datastore::datastore::filter::FilterBinding;
+use onlyati_datastore::datastore::handle::DatastoreHandle;
+use onlyati_datastore::datastore::storage::{MemoryStorage, SledStorage, Storage};
+use onlyati_datastore::logger::enums::{LogRotation, LoggerAction, LoggerResponse};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use super::config_parse::{ComputedFunction, Config, StorageKind};
+use super::lua::{LuaComputation, LuaFilter};
+
+/// Tasks of a started database
+pub struct DatabaseTasks {
+    /// Task of the hook manager
+    pub hook_manager: JoinHandle<()>,
+
+    /// Task of the logger, if logging is enabled
+    pub logger: Option<JoinHandle<()>>,
+
+    /// Task of the datastore
+    pub datastore: JoinHandle<()>,
+}
+
+/// Start a database with its own hook manager, logger and storage.
+///
+/// # Parameters
+/// - `config`: Application's config file
+/// - `name`: Name of database, this is the root for each key
+/// - `logging`: Write the append file of database or not
+/// - `restore_until`: If specified, state of this time is restored from the append file
+///
+/// # Details
+///
+/// The database of `general.database_name` uses `logger.location` and `general.storage_path`.
+/// Other databases use a directory with their name under `logger.location`, next to the files of the
+/// default database, and under `general.storage_path` with `.d` extension, because the sled directory
+/// of the default database cannot contain other directories.
+///
+/// # Return
+///
+/// With the handle and tasks of database, or with the error text.
+pub async fn start_database(
+    config: &Config,
+    name: &str,
+    logging: bool,
+    restore_until: Option<Duration>,
+) -> Result<(DatastoreHandle, DatabaseTasks), String> {
+    let default = name == config.general.database_name;
+    let location = |base: &str| match default {
+        true => base.to_string(),
+        false => format!("{}/{}", base, name),
+    };
+
+    // Initialize HookManager and Logger for Datastore
+    let (hook_sender, hook_thread) = onlyati_datastore::hook::utilities::start_hook_manager().await;
+    let (logger_sender, logger_thread) = match (&config.logger, logging) {
+        (Some(logger), true) => {
+            let path = location(&logger.location);
+            std::fs::create_dir_all(&path)
+                .map_err(|e| format!("Failed to create log directory '{}': {}", path, e))?;
+            let (a, b) = onlyati_datastore::logger::utilities::start_logger(&path).await;
+
+            // Human log is rotated only if any limit is set
+            if logger.rotate_size.is_some() || logger.rotate_interval.is_some() {
+                let rotation = LogRotation {
+                    max_size: logger.rotate_size.map(|x| x * 1024 * 1024),
+                    interval: logger.rotate_interval.map(Duration::from_secs),
+                    retention: logger.retention.unwrap_or(5),
+                    compress: logger.compress.unwrap_or(false),
+                };
+                let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+                a.send(LoggerAction::Rotation(tx, rotation))
+                    .await
+                    .map_err(|e| e.to_string())?;
+                if let Some(LoggerResponse::Err(e)) = rx.recv().await {
+                    return Err(format!("Failed to set log rotation: {}", e));
+                }
+            }
+
+            // Logger cuts the append file at the restore point when datastore reads it
+            if let Some(until) = restore_until {
+                tracing::warn!(
+                    "state of '{}' is restored until {}",
+                    name,
+                    crate::common::format_time(until.as_nanos())
+                );
+                let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+                a.send(LoggerAction::RestorePoint(tx, until))
+                    .await
+                    .map_err(|e| e.to_string())?;
+                if let Some(LoggerResponse::Err(e)) = rx.recv().await {
+                    return Err(format!("Failed to set restore point: {}", e));
+                }
+            }
+
+            (Some(a), Some(b))
+        }
+        _ => (None, None),
+    };
+
+    // Initialize Datastore
+    let storage: Box<dyn Storage> = match &config.general.storage {
+        StorageKind::Memory => Box::new(MemoryStorage::new()),
+        StorageKind::Sled => {
+            let base = config.general.storage_path.as_deref().unwrap_or_default();
+            let path = match default {
+                true => base.to_string(),
+                false => format!("{}.d/{}", base.trim_end_matches('/'), name),
+            };
+            match SledStorage::open(&path) {
+                Ok(storage) => Box::new(storage),
+                Err(e) => return Err(format!("Failed to open storage: {}", e)),
+            }
+        }
+    };
+
+    let (handle, db_thread) = onlyati_datastore::datastore::utilities::start_datastore_handle(
+        name.to_string(),
+        storage,
+        Some(hook_sender),
+        logger_sender,
+    )
+    .await;
+
+    // Limits are set after the append file is replayed, so the replay is not limited
+    if let Some(limits) = config.limits_of(name) {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let action = DatabaseAction::SetLimits(tx, format!("/{}", name), limits.to_limits());
+        handle.send(action).await.map_err(|e| e.to_string())?;
+        if let Some(Err(e)) = rx.recv().await {
+            return Err(format!("Failed to set limits: {}", e));
+        }
+    }
+
+    // Schemas are set after the replay too, values that were written before remain valid
+    let schemas = config.schemas_of(name);
+    if !schemas.is_empty() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let action = DatabaseAction::SetSchemas(tx, format!("/{}", name), schemas);
+        handle.send(action).await.map_err(|e| e.to_string())?;
+        if let Some(Err(e)) = rx.recv().await {
+            return Err(format!("Failed to set schemas: {}", e));
+        }
+    }
+
+    // Filters are set after the replay too, the append file has the values that were filtered
+    let filters = config.filters_of(name);
+    if let (false, Some(scripts)) = (filters.is_empty(), &config.scripts) {
+        let filters = filters
+            .iter()
+            .map(|x| {
+                Ok(FilterBinding {
+                    prefix: x.prefix.clone(),
+                    filter: Arc::new(LuaFilter::new(scripts, &x.script)?),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let action = DatabaseAction::SetFilters(tx, format!("/{}", name), filters);
+        handle.send(action).await.map_err(|e| e.to_string())?;
+        if let Some(Err(e)) = rx.recv().await {
+            return Err(format!("Failed to set filters: {}", e));
+        }
+    }
+
+    // Indexes are built from the replayed records
+    let indexes = config.indexes_of(name);
+    if !indexes.is_empty() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let action = DatabaseAction::SetIndexes(tx, format!("/{}", name), indexes);
+        handle.send(action).await.map_err(|e| e.to_string())?;
+        if let Some(Err(e)) = rx.recv().await {
+            return Err(format!("Failed to set indexes: {}", e));
+        }
+    }
+
+    // Computed keys are set at the end, so they are computed from the replayed records
+    let computed = config.computed_of(name);
+    if !computed.is_empty() {
+        let computed = computed
+            .into_iter()
+            .map(|x| {
+                Ok(ComputedKey {
+                    key: x.key,
+                    source: x.source,
+                    function: match x.function {
+                        ComputedFunction::Count => Function::Count(x.value),
+                        ComputedFunction::Sum => Function::Sum,
+                        ComputedFunction::Min => Function::Min,
+                        ComputedFunction::Max => Function::Max,
+                        ComputedFunction::LastUpdated => Function::LastUpdated,
+                        ComputedFunction::Lua => Function::Custom(Arc::new(LuaComputation::new(
+                            x.expression.as_deref().unwrap_or_default(),
+                        )?)),
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let action = DatabaseAction::SetComputed(tx, format!("/{}", name), computed);
+        handle.send(action).await.map_err(|e| e.to_string())?;
+        if let Some(Err(e)) = rx.recv().await {
+            return Err(format!("Failed to set computed keys: {}", e));
+        }
+    }
+
+    let tasks = DatabaseTasks {
+        hook_manager: hook_thread,
+        logger: logger_thread,
+        datastore: db_thread,
+    };
+
+    Ok((handle, tasks))
+}
@@ -1,10 +1,10 @@
 // External depencies
 use serde::Deserialize;
 use tokio::sync::mpsc::channel;
-use tokio::sync::mpsc::Sender;
 
 // Internal depencies
-use onlyati_datastore::datastore::enums::DatabaseAction;
+use onlyati_datastore::datastore::enums::{pair::RecordValue, DatabaseAction};
+use onlyati_datastore::datastore::router::DatastoreRouter;
 
 /// Represent a record in initial toml file
 #[derive(Deserialize)]
@@ -12,6 +12,8 @@ pub struct Record {
     key: String,
     value: String,
     r#override: Option<bool>,
+    /// Type of value like in exported snapshots, default is `string`
+    r#type: Option<String>,
 }
 
 /// Represent a hook in initial toml file
@@ -34,14 +36,14 @@ pub struct InitialData {
 ///
 /// # Parameters
 /// - `path`: Path to initial file
-/// - `data_sender`: Sender that sends data to core database.
+/// - `data_sender`: Router that sends data to the database of each key.
 ///
 /// # Return
 ///
 /// With Ok if no issue, else with error text.
 pub async fn parse_initial_file(
     path: &String,
-    data_sender: &Sender<DatabaseAction>,
+    data_sender: &DatastoreRouter,
 ) -> Result<(), String> {
     let file_content = super::get_file_content(path)?;
 
@@ -147,7 +149,13 @@ pub async fn parse_initial_file(
             // Write record into Hermes
             tracing::debug!("write pair with '{}' to the database", pair.key);
             let (tx, mut rx) = channel(10);
-            let action = DatabaseAction::Set(tx, pair.key.clone(), pair.value.clone());
+            let action = match &pair.r#type {
+                Some(r#type) => match RecordValue::parse(r#type, &pair.value) {
+                    Ok(value) => DatabaseAction::SetTyped(tx, pair.key.clone(), value),
+                    Err(e) => return Err(format!("Invalid value of '{}': {}", pair.key, e)),
+                },
+                None => DatabaseAction::Set(tx, pair.key.clone(), pair.value.clone()),
+            };
 
             if let Err(e) = data_sender.send(action).await {
                 return Err(format!("Error: {}", e));
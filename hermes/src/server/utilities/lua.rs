@@ -1,4 +1,8 @@
-use mlua::{Lua, Table};
+use mlua::{ChunkMode, HookTriggers, Lua, Table, Value};
+use std::collections::BTreeMap;
+use onlyati_datastore::datastore::computed::ComputeFunction;
+use onlyati_datastore::datastore::enums::{error::ErrorKind, pair::RecordValue};
+use onlyati_datastore::datastore::filter::{WriteFilter, WriteKind};
 
 use super::config_parse::{Scripts};
 
@@ -148,4 +152,233 @@ pub async fn run_lua_for_gitea(
     let final_key = final_key.get("key")?;
 
     Ok((final_key, final_value))
-}
\ No newline at end of file
+}
+
+/// Lua script that is bound to a prefix and called before every write under it.
+/// Defined global variables that can be used in Lua script:
+/// - `_G.new["key"]` and `_G.new["value"]`: The key-value pair that is written, script can change both
+/// - `_G.new["kind"]`: It is `set`, `push`, `hash_set` or `set_add`
+/// - `_G.old["key"]` and `_G.old["value"]`: Current value of record. It is Nil for new records and for the other kinds
+///
+/// If script sets `_G.new["reject"]` to a text, then the write is rejected with this message.
+///
+/// Script is compiled once when the filter is created, then every write runs it in a new Lua environment
+/// that stops it after `FILTER_INSTRUCTION_LIMIT` instructions, because it is called under the write lock.
+pub struct LuaFilter {
+    /// Full path of the script
+    script: String,
+
+    /// Compiled chunk of the script
+    chunk: Vec<u8>,
+}
+
+/// Filter script is stopped after this number of Lua instructions
+const FILTER_INSTRUCTION_LIMIT: u32 = 1_000_000;
+
+impl LuaFilter {
+    /// Create a filter from a script, it fails if the script cannot be read or compiled
+    ///
+    /// # Parameters
+    /// - `config`: Script part from configuration that contains the location of scripts
+    /// - `script`: Script name from scripts.execs
+    pub fn new(config: &Scripts, script: &str) -> Result<Self, String> {
+        let script = format!("{}/{}", config.exec_path, script);
+        let source = std::fs::read(&script)
+            .map_err(|e| format!("Failed to read '{}' filter: {}", script, e))?;
+
+        let lua = Lua::new();
+        let chunk = lua
+            .load(&source)
+            .set_name(&script)
+            .and_then(|chunk| chunk.into_function())
+            .map_err(|e| format!("Failed to compile '{}' filter: {}", script, e))?
+            .dump(false);
+
+        Ok(Self { script, chunk })
+    }
+
+    /// Run the script in a new Lua environment, then return with the modified pair and the reject message
+    fn run(
+        &self,
+        kind: WriteKind,
+        key: &str,
+        value: &str,
+        old: Option<&str>,
+    ) -> Result<(String, String, Option<String>), mlua::Error> {
+        tracing::trace!("initializing lua environment");
+        let lua = Lua::new();
+        lua.set_hook(
+            HookTriggers::every_nth_instruction(FILTER_INSTRUCTION_LIMIT),
+            |_, _| {
+                Err(mlua::Error::RuntimeError(format!(
+                    "filter is stopped after {} instructions",
+                    FILTER_INSTRUCTION_LIMIT
+                )))
+            },
+        )?;
+        let globals = lua.globals();
+
+        if let Some(old) = old {
+            let old_table = lua.create_table()?;
+            old_table.set("key", key)?;
+            old_table.set("value", old)?;
+            globals.set("old", old_table)?;
+        }
+
+        let new_table = lua.create_table()?;
+        new_table.set("key", key)?;
+        new_table.set("value", value)?;
+        new_table.set("kind", kind.to_string())?;
+        globals.set("new", new_table)?;
+
+        tracing::debug!("execute '{}' lua filter for '{}' key", self.script, key);
+        lua.load(&self.chunk)
+            .set_name(&self.script)?
+            .set_mode(ChunkMode::Binary)
+            .exec()?;
+
+        let new_table: Table = globals.get("new")?;
+        Ok((
+            new_table.get("key")?,
+            new_table.get("value")?,
+            new_table.get("reject")?,
+        ))
+    }
+}
+
+impl WriteFilter for LuaFilter {
+    fn filter(
+        &self,
+        kind: WriteKind,
+        key: &str,
+        value: &str,
+        old: Option<&str>,
+    ) -> Result<(String, String), ErrorKind> {
+        match self.run(kind, key, value, old) {
+            Ok((_, _, Some(message))) => {
+                tracing::debug!("{} of '{}' is rejected by {}: {}", kind, key, self.script, message);
+                Err(ErrorKind::ValidationError(message))
+            }
+            Ok((key, value, None)) => Ok((key, value)),
+            Err(e) => {
+                tracing::error!("failed to execute {} filter", self.script);
+                Err(ErrorKind::ScriptError(e.to_string()))
+            }
+        }
+    }
+}
+
+/// Lua expression of a computed key, it is evaluated when a record changes under the source.
+/// Defined global variables that can be used in the expression:
+/// - `_G.records`: Table of records under the source, full key is the index and value is the text of record
+/// - `_G.key`: The computed key
+///
+/// Expression can be a chunk with `return` statement too. If it returns with Nil, then the computed key is removed.
+///
+/// Expression is compiled once when the computation is created, then every evaluation runs it in a new Lua environment
+/// that stops it after `COMPUTATION_INSTRUCTION_LIMIT` instructions, because it is called under the write lock.
+pub struct LuaComputation {
+    /// Compiled chunk of the expression
+    chunk: Vec<u8>,
+}
+
+/// Expression of computed key is stopped after this number of Lua instructions
+const COMPUTATION_INSTRUCTION_LIMIT: u32 = 1_000_000;
+
+impl LuaComputation {
+    /// Create a computation from an expression, it fails if the expression cannot be compiled
+    ///
+    /// # Parameters
+    /// - `expression`: Lua expression from the config, e.g. `#records`
+    pub fn new(expression: &str) -> Result<Self, String> {
+        // Same as `eval`: try it as an expression first, then as a chunk
+        let lua = Lua::new();
+        let chunk = lua
+            .load(&format!("return {}", expression))
+            .into_function()
+            .or_else(|_| lua.load(expression).into_function())
+            .map_err(|e| format!("Failed to compile '{}' expression: {}", expression, e))?
+            .dump(false);
+
+        Ok(Self { chunk })
+    }
+
+    /// Evaluate the expression in a new Lua environment
+    fn run(&self, key: &str, records: &BTreeMap<String, RecordValue>) -> Result<Option<String>, mlua::Error> {
+        tracing::trace!("initializing lua environment");
+        let lua = Lua::new();
+        lua.set_hook(
+            HookTriggers::every_nth_instruction(COMPUTATION_INSTRUCTION_LIMIT),
+            |_, _| {
+                Err(mlua::Error::RuntimeError(format!(
+                    "expression is stopped after {} instructions",
+                    COMPUTATION_INSTRUCTION_LIMIT
+                )))
+            },
+        )?;
+        let globals = lua.globals();
+
+        let records_table = lua.create_table()?;
+        for (key, value) in records {
+            records_table.set(key.as_str(), value.to_string())?;
+        }
+        globals.set("records", records_table)?;
+        globals.set("key", key)?;
+
+        tracing::debug!("evaluate lua expression of '{}' computed key", key);
+        let result = match lua.load(&self.chunk).set_mode(ChunkMode::Binary).call::<_, Value>(())? {
+            Value::Nil => None,
+            Value::Boolean(value) => Some(value.to_string()),
+            Value::Integer(value) => Some(value.to_string()),
+            Value::Number(value) => Some(value.to_string()),
+            Value::String(value) => Some(value.to_str()?.to_string()),
+            other => {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "expression has returned with {} instead of a value",
+                    other.type_name()
+                )))
+            }
+        };
+
+        Ok(result)
+    }
+}
+
+impl ComputeFunction for LuaComputation {
+    fn compute(
+        &self,
+        key: &str,
+        records: &BTreeMap<String, RecordValue>,
+    ) -> Result<Option<String>, ErrorKind> {
+        self.run(key, records).map_err(|e| {
+            tracing::error!("failed to evaluate lua expression of '{}'", key);
+            ErrorKind::ScriptError(e.to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computation_test() {
+        let mut records = BTreeMap::new();
+        records.insert("/root/status/a".to_string(), RecordValue::String("NOK".to_string()));
+        records.insert("/root/status/b".to_string(), RecordValue::Integer(5));
+
+        // Expression and chunk with return are both accepted
+        let computation = LuaComputation::new("records['/root/status/a'] .. key").unwrap();
+        assert_eq!(
+            Some("NOK/root/summary".to_string()),
+            computation.compute("/root/summary", &records).unwrap()
+        );
+        let computation = LuaComputation::new("local n = 0 for _ in pairs(records) do n = n + 1 end return n").unwrap();
+        assert_eq!(Some("2".to_string()), computation.compute("/root/summary", &records).unwrap());
+
+        // Syntax errors fail at creation, endless loops are stopped
+        assert!(LuaComputation::new("return (").is_err());
+        let computation = LuaComputation::new("while true do end").unwrap();
+        assert!(computation.compute("/root/summary", &records).is_err());
+    }
+}
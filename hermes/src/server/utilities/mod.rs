@@ -1,4 +1,5 @@
 pub mod config_parse;
+pub mod database;
 pub mod initial_parse;
 pub mod lua;
 
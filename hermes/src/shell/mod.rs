@@ -1,13 +1,15 @@
 use clap::Parser;
 use futures_util::SinkExt;
-use rustyline::{config::Configurer, error::ReadlineError};
-use rustyline::{CompletionType, DefaultEditor};
+use rustyline::{error::ReadlineError, history::DefaultHistory};
+use rustyline::{CompletionType, EditMode, Editor};
 use termion::color::{self, Fg};
 use tokio::net::TcpStream;
 use tokio_tungstenite::{tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream};
 
 use crate::arg::ShellParms;
 use crate::common::websocket::client::{connecto_to_server, perform_action, client_config_parse, get_address_for_client};
+use crate::common::websocket::client;
+use utilities::helper::ShellHelper;
 
 mod utilities;
 
@@ -81,10 +83,25 @@ pub async fn main_async(mut hostname: Option<String>, client_config: String) ->
         }
     }
 
-    // Initialize terminal
-    let mut rl = DefaultEditor::new()?;
-    rl.set_completion_type(CompletionType::List);
-    rl.set_auto_add_history(true);
+    // Initialize terminal, edit mode can be set in client config
+    let edit_mode = match config.as_ref().map(|cfg| cfg.shell.edit_mode) {
+        Some(client::EditMode::Vi) => EditMode::Vi,
+        _ => EditMode::Emacs,
+    };
+    let editor_config = rustyline::Config::builder()
+        .completion_type(CompletionType::List)
+        .edit_mode(edit_mode)
+        .auto_add_history(true)
+        .build();
+    let mut rl: Editor<ShellHelper, DefaultHistory> = Editor::with_config(editor_config)?;
+
+    // Helper completes commands, keys, hook prefixes and scripts, and highlights the line
+    let nodes = match &config {
+        Some(cfg) => cfg.node.iter().map(|node| node.name.clone()).collect(),
+        None => Vec::new(),
+    };
+    rl.set_helper(Some(ShellHelper::new(nodes)));
+
     let home_dir = match std::env::var("HOME") {
         Ok(var) => var,
         Err(e) => {
@@ -106,6 +123,11 @@ pub async fn main_async(mut hostname: Option<String>, client_config: String) ->
             format!("{}hermes@disconnected=> {}", LIGHT_GRAY, WHITE)
         };
 
+        // Completion asks the same server where the shell is connected
+        if let Some(helper) = rl.helper_mut() {
+            helper.set_address(stream.as_ref().and(hostname.clone())).await;
+        }
+
         let readline = rl.readline(&prefix);
 
         // Check what the user typoed then decide what to do
@@ -269,6 +291,11 @@ pub async fn main_async(mut hostname: Option<String>, client_config: String) ->
         }
     }
 
+    // Close connection of completion
+    if let Some(helper) = rl.helper_mut() {
+        helper.set_address(None).await;
+    }
+
     // Save command history
     rl.save_history(&history_file)
         .expect("failed to save history");
@@ -0,0 +1,490 @@
+//! Helper of the shell editor: it completes and highlights the line that is typed
+//!
+//! Shell commands, subcommands and their flags are completed from `ShellParms`. Keys, hook prefixes and
+//! script names are asked from the connected server on a separate connection, so the connection of
+//! the shell is not touched while the line is edited.
+use std::borrow::Cow;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use clap::{Command, CommandFactory};
+use futures_util::SinkExt;
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream};
+
+use crate::arg::{Action, ShellParms};
+use crate::common::websocket::client::{connecto_to_server, perform_action};
+use crate::shell::{LIGHT_BLUE, LIGHT_GREEN, LIGHT_YELLOW, RED, WHITE};
+
+/// Commands of the shell itself
+const BUILTINS: [&str; 6] = ["\\c", "\\d", "\\l", "\\clear", "\\q", "\\?"];
+
+/// Completion gives up after this time, so a slow server does not freeze the shell
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Helper that is set for the shell editor
+pub struct ShellHelper {
+    /// Address of the server where the shell is connected
+    address: Option<String>,
+
+    /// Connection for completion, it is opened at the first completion that needs the server
+    stream: Mutex<Option<WebSocketStream<MaybeTlsStream<TcpStream>>>>,
+
+    /// Names of nodes from client config, they are completed after `\c`
+    nodes: Vec<String>,
+
+    /// Commands of the shell, it is built once instead of at every keystroke
+    command: Command,
+
+    /// Line that is highlighted last time, it is highlighted again only if it changes
+    highlighted: Mutex<String>,
+}
+
+impl ShellHelper {
+    /// Create a new helper
+    ///
+    /// # Parameters
+    /// - `nodes`: Names of nodes from client config
+    pub fn new(nodes: Vec<String>) -> Self {
+        Self {
+            address: None,
+            stream: Mutex::new(None),
+            nodes,
+            command: ShellParms::command(),
+            highlighted: Mutex::new(String::new()),
+        }
+    }
+
+    /// Set the server where the shell is connected, connection of completion to the old server is closed
+    ///
+    /// # Parameters
+    /// - `address`: Address of server, `None` if shell is disconnected
+    pub async fn set_address(&mut self, address: Option<String>) {
+        if self.address == address {
+            return;
+        }
+
+        let stream = match self.stream.get_mut() {
+            Ok(stream) => stream.take(),
+            Err(e) => e.into_inner().take(),
+        };
+        if let Some(mut stream) = stream {
+            if let Err(e) = stream.send(Message::Close(None)).await {
+                tracing::debug!("failed to close completion connection: {}", e);
+            }
+        }
+
+        self.address = address;
+    }
+
+    /// Send an action to the server and return with its response, `None` if it failed
+    fn request(&self, action: Action) -> Option<String> {
+        let address = self.address.clone()?;
+        let mut stream = self.stream.lock().ok()?;
+
+        block_on(async move {
+            if stream.is_none() {
+                match tokio::time::timeout(TIMEOUT, connecto_to_server(address)).await {
+                    Ok(Ok(socket)) => *stream = Some(socket),
+                    Ok(Err(e)) => {
+                        tracing::debug!("completion failed to connect: {}", e);
+                        return None;
+                    }
+                    Err(_) => {
+                        tracing::debug!("completion failed to connect in {:?}", TIMEOUT);
+                        return None;
+                    }
+                }
+            }
+
+            let socket = stream.as_mut()?;
+            match tokio::time::timeout(TIMEOUT, perform_action(socket, action)).await {
+                Ok(Ok(response)) => Some(response),
+                Ok(Err(e)) => {
+                    tracing::debug!("completion request failed: {}", e);
+                    None
+                }
+                Err(_) => {
+                    // Response may arrive later, so connection cannot be used any more
+                    tracing::debug!("completion request did not finish in {:?}", TIMEOUT);
+                    *stream = None;
+                    None
+                }
+            }
+        })
+    }
+
+    /// Complete a shell command or a subcommand
+    fn commands(&self, word: &str) -> Vec<Pair> {
+        let subcommands = self.command.get_subcommands().map(|x| x.get_name());
+
+        BUILTINS
+            .into_iter()
+            .chain(subcommands)
+            .filter(|x| x.starts_with(word))
+            .map(|x| pair(x.to_string(), x.to_string()))
+            .collect()
+    }
+
+    /// Complete a flag of the subcommand
+    fn flags(&self, subcommand: &str, word: &str) -> Vec<Pair> {
+        let subcommand = match self.command.find_subcommand(subcommand) {
+            Some(subcommand) => subcommand,
+            None => return Vec::new(),
+        };
+
+        subcommand
+            .get_arguments()
+            .filter_map(|x| x.get_long())
+            .map(|x| format!("--{}", x))
+            .filter(|x| x.starts_with(word))
+            .map(|x| pair(x.clone(), x))
+            .collect()
+    }
+
+    /// Complete a node from client config as `cfg://<name>`
+    fn nodes(&self, word: &str) -> Vec<Pair> {
+        self.nodes
+            .iter()
+            .map(|x| format!("cfg://{}", x))
+            .filter(|x| x.starts_with(word))
+            .map(|x| pair(x.clone(), x))
+            .collect()
+    }
+
+    /// Complete the value of a flag
+    fn values(&self, subcommand: &str, flag: &str, word: &str) -> Vec<Pair> {
+        let argument = self.command.find_subcommand(subcommand).and_then(|x| {
+            x.get_arguments().find(|arg| {
+                arg.get_long().map(|x| format!("--{}", x)).as_deref() == Some(flag)
+                    || arg.get_short().map(|x| format!("-{}", x)).as_deref() == Some(flag)
+            })
+        });
+
+        match argument.map(|x| x.get_id().as_str()) {
+            Some("key") | Some("destination") => self.keys(word),
+            Some("prefix") if subcommand == "set-hook" => self.keys(word),
+            Some("prefix") => self.hooks(word),
+            Some("script") => self.scripts(word),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Complete a key by listing one level of its parent on the server, tables get a closing '/'
+    fn keys(&self, word: &str) -> Vec<Pair> {
+        let parent = match word.rfind('/') {
+            Some(index) if index > 0 => &word[..index],
+            _ => return Vec::new(),
+        };
+
+        let action = Action::ListKeys {
+            key: parent.to_string(),
+            one_level: true,
+        };
+        let response = match self.request(action) {
+            Some(response) => response,
+            None => return Vec::new(),
+        };
+
+        response
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .filter(|(_, key)| key.starts_with(word) && key.len() > parent.len())
+            .map(|(kind, key)| {
+                let key = match kind {
+                    "t" => format!("{}/", key),
+                    _ => key.to_string(),
+                };
+                pair(key[parent.len() + 1..].to_string(), key)
+            })
+            .collect()
+    }
+
+    /// Complete a prefix of existing hook
+    fn hooks(&self, word: &str) -> Vec<Pair> {
+        let prefix = match word.is_empty() {
+            true => "/".to_string(),
+            false => word.to_string(),
+        };
+
+        let response = match self.request(Action::ListHooks { prefix }) {
+            Some(response) => response,
+            None => return Vec::new(),
+        };
+
+        let mut prefixes: Vec<&str> = response
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .collect();
+        prefixes.dedup();
+
+        prefixes
+            .into_iter()
+            .map(|x| pair(x.to_string(), x.to_string()))
+            .collect()
+    }
+
+    /// Complete a script that can be executed on the server
+    fn scripts(&self, word: &str) -> Vec<Pair> {
+        let response = match self.request(Action::ListScripts) {
+            Some(response) => response,
+            None => return Vec::new(),
+        };
+
+        response
+            .lines()
+            .filter(|x| x.starts_with(word))
+            .map(|x| pair(x.to_string(), x.to_string()))
+            .collect()
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .rfind(char::is_whitespace)
+            .map(|x| x + 1)
+            .unwrap_or(0);
+        let word = &line[start..pos];
+        let words: Vec<&str> = line[..start].split_whitespace().collect();
+
+        let candidates = match words.as_slice() {
+            [] => self.commands(word),
+            ["\\c"] => self.nodes(word),
+            [subcommand, ..] if word.starts_with('-') => self.flags(subcommand, word),
+            [subcommand, .., flag] if flag.starts_with('-') => self.values(subcommand, flag, word),
+            _ => Vec::new(),
+        };
+
+        Ok((start, candidates))
+    }
+}
+
+impl Highlighter for ShellHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        if line.is_empty() {
+            return Cow::Borrowed(line);
+        }
+
+        let mut highlighted = String::with_capacity(line.len() * 2);
+        let mut first = true;
+
+        for token in split_tokens(line) {
+            if token.starts_with(char::is_whitespace) {
+                highlighted.push_str(token);
+                continue;
+            }
+
+            let color = if first {
+                first = false;
+                let known = BUILTINS.contains(&token)
+                    || self.command.find_subcommand(token).is_some()
+                    || token == "help";
+                match known {
+                    true => LIGHT_BLUE.to_string(),
+                    false => RED.to_string(),
+                }
+            } else if token.starts_with('-') {
+                LIGHT_YELLOW.to_string()
+            } else if token.starts_with('"') || token.starts_with('\'') {
+                LIGHT_GREEN.to_string()
+            } else {
+                WHITE.to_string()
+            };
+
+            highlighted.push_str(&color);
+            highlighted.push_str(token);
+        }
+        highlighted.push_str(&WHITE.to_string());
+
+        Cow::Owned(highlighted)
+    }
+
+    fn highlight_char(&self, line: &str, _pos: usize) -> bool {
+        let mut highlighted = match self.highlighted.lock() {
+            Ok(highlighted) => highlighted,
+            Err(e) => e.into_inner(),
+        };
+
+        // Only the cursor has moved, colors remain the same
+        if *highlighted == line {
+            return false;
+        }
+
+        line.clone_into(&mut highlighted);
+        true
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+
+    /// Hint the rest of command or flag if only one can be completed, server is not asked for hints
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+
+        let start = line.rfind(char::is_whitespace).map(|x| x + 1).unwrap_or(0);
+        let word = &line[start..];
+        let words: Vec<&str> = line[..start].split_whitespace().collect();
+        if word.is_empty() {
+            return None;
+        }
+
+        let candidates = match words.as_slice() {
+            [] => self.commands(word),
+            [subcommand, ..] if word.starts_with('-') => self.flags(subcommand, word),
+            _ => return None,
+        };
+
+        match candidates.as_slice() {
+            [candidate] => candidate
+                .replacement
+                .strip_prefix(word)
+                .filter(|x| !x.is_empty())
+                .map(|x| x.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+fn pair(display: String, replacement: String) -> Pair {
+    Pair {
+        display,
+        replacement,
+    }
+}
+
+/// Split the line into words and whitespaces, quoted text is one word like at `split_arguments`
+fn split_tokens(line: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut quote: Option<char> = None;
+    let mut space: Option<bool> = None;
+
+    for (index, c) in line.char_indices() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+            continue;
+        }
+
+        let is_space = c.is_whitespace();
+        if space.is_some() && space != Some(is_space) {
+            tokens.push(&line[start..index]);
+            start = index;
+        }
+        space = Some(is_space);
+
+        if c == '"' || c == '\'' {
+            quote = Some(c);
+        }
+    }
+    if start < line.len() {
+        tokens.push(&line[start..]);
+    }
+
+    tokens
+}
+
+/// Run an async function from the editor, which is called synchronously inside the runtime
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustyline::history::DefaultHistory;
+
+    fn complete(helper: &ShellHelper, line: &str) -> (usize, Vec<String>) {
+        let history = DefaultHistory::new();
+        let (start, candidates) = helper
+            .complete(line, line.len(), &Context::new(&history))
+            .unwrap();
+        (
+            start,
+            candidates.into_iter().map(|x| x.replacement).collect(),
+        )
+    }
+
+    fn hint(helper: &ShellHelper, line: &str, pos: usize) -> Option<String> {
+        let history = DefaultHistory::new();
+        helper.hint(line, pos, &Context::new(&history))
+    }
+
+    #[test]
+    fn test_complete() {
+        let helper = ShellHelper::new(vec!["dev".to_string(), "prod".to_string()]);
+
+        let (start, candidates) = complete(&helper, "set-h");
+        assert_eq!(0, start);
+        assert_eq!(vec!["set-hook".to_string()], candidates);
+
+        let (_, candidates) = complete(&helper, "\\c");
+        assert!(candidates.contains(&"\\c".to_string()));
+        assert!(candidates.contains(&"\\clear".to_string()));
+
+        let (start, candidates) = complete(&helper, "\\c cfg://p");
+        assert_eq!(3, start);
+        assert_eq!(vec!["cfg://prod".to_string()], candidates);
+
+        let (start, candidates) = complete(&helper, "get --key /root/a --p");
+        assert_eq!(18, start);
+        assert_eq!(vec!["--path".to_string()], candidates);
+
+        // Keys are asked from the server, without server there is nothing to complete
+        let (_, candidates) = complete(&helper, "get --key /root/");
+        assert!(candidates.is_empty());
+
+        let (_, candidates) = complete(&helper, "unknown --");
+        assert!(candidates.is_empty());
+    }
+
+    #[test]
+    fn test_hint() {
+        let helper = ShellHelper::new(Vec::new());
+
+        assert_eq!(Some("ook".to_string()), hint(&helper, "set-h", 5));
+        assert_eq!(
+            Some("ath".to_string()),
+            hint(&helper, "get --key /root/a --p", 21)
+        );
+
+        // No hint if more candidates exist, the word is complete or cursor is not at the end
+        assert_eq!(None, hint(&helper, "set", 3));
+        assert_eq!(None, hint(&helper, "set-hook", 8));
+        assert_eq!(None, hint(&helper, "set-h", 3));
+        assert_eq!(None, hint(&helper, "get ", 4));
+        assert_eq!(None, hint(&helper, "get --key /ro", 13));
+    }
+
+    #[test]
+    fn test_highlight_char() {
+        let helper = ShellHelper::new(Vec::new());
+
+        assert!(helper.highlight_char("get", 3));
+        assert!(!helper.highlight_char("get", 1));
+        assert!(helper.highlight_char("get ", 4));
+        assert!(!helper.highlight_char("get ", 0));
+    }
+}